@@ -6,8 +6,10 @@ use std::time::Instant;
 
 use log::error;
 use rustdds::{
+  no_key,
   policy::History,
   policy::Reliability,
+  with_key,
   with_key::Sample,
   //DataWriterStatus,
   DataReaderStatus,
@@ -21,7 +23,7 @@ use rustdds::{
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use smol::Timer;
-use futures::{/* FutureExt, */ StreamExt, TryFutureExt};
+use futures::{/* FutureExt, */ StreamExt};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct KeyedSeq {
@@ -37,6 +39,23 @@ impl Keyed for KeyedSeq {
   }
 }
 
+// The same KeyedSeq is also sent on a NO_KEY topic, so that the keyless
+// throughput can be compared with the keyed one.
+enum PerfWriter {
+  Keyed(with_key::DataWriter<KeyedSeq>),
+  NoKey(no_key::DataWriter<KeyedSeq>),
+}
+
+impl PerfWriter {
+  async fn async_write(&self, data: KeyedSeq, source_timestamp: Option<Timestamp>) {
+    let result = match self {
+      PerfWriter::Keyed(w) => w.async_write(data, source_timestamp).await,
+      PerfWriter::NoKey(w) => w.async_write(data, source_timestamp).await,
+    };
+    result.unwrap_or_else(|e| error!("DataWriter async_write failed: {e:?}"));
+  }
+}
+
 // --------------------------------------------------
 // --------------------------------------------------
 
@@ -46,6 +65,9 @@ struct CommandLineArgs {
   #[arg(short = 'u', long)]
   best_effort: bool,
   // This flag is called 'u' because it is so in CycloneDDS version also.
+  /// Use a NO_KEY topic for data. Only Pub and Sub modes support this.
+  #[arg(short = 'k', long)]
+  keyless: bool,
   #[command(subcommand)]
   main_mode: MainMode,
 }
@@ -103,14 +125,27 @@ fn main() {
     'R'
   };
 
-  let topic_suffix = "KS"; // TODO: Support others also
+  let keyless = command_line_args.keyless;
+  if keyless
+    && matches!(
+      command_line_args.main_mode,
+      MainMode::Ping { .. } | MainMode::Pong
+    )
+  {
+    panic!("Keyless topics are supported only in Pub and Sub modes.");
+  }
+  let (topic_suffix, data_topic_kind) = if keyless {
+    ("NKS", TopicKind::NoKey)
+  } else {
+    ("KS", TopicKind::WithKey) // TODO: Support others also
+  };
 
   let perf_data_topic = domain_participant
     .create_topic(
       format!("DDSPerf{reliability_marker}Data{topic_suffix}"), // topic name
       "KeyedSeq".to_string(),                                   // type name
       &qos,
-      TopicKind::WithKey,
+      data_topic_kind,
     )
     .unwrap_or_else(|e| panic!("create_topic failed: {e:?}"));
 
@@ -134,13 +169,36 @@ fn main() {
   match command_line_args.main_mode {
     MainMode::Sub => {
       let subscriber = domain_participant.create_subscriber(&qos).unwrap();
-      let data_reader = subscriber
-        .create_datareader_cdr::<KeyedSeq>(&perf_data_topic, None) // None = get qos policy from publisher
-        .unwrap();
+      // Both streams yield the baggage size of received samples.
+      let (mut sample_stream, mut event_stream) = if keyless {
+        let data_reader = subscriber
+          .create_datareader_no_key_cdr::<KeyedSeq>(&perf_data_topic, None) // None = get qos policy from publisher
+          .unwrap();
+        let sample_stream = data_reader.async_sample_stream();
+        let event_stream = sample_stream.async_event_stream();
+        (
+          sample_stream
+            .map(|r| r.map(|s| Sample::Value(s.into_value().baggage.len())))
+            .boxed_local()
+            .fuse(),
+          event_stream.boxed_local().fuse(),
+        )
+      } else {
+        let data_reader = subscriber
+          .create_datareader_cdr::<KeyedSeq>(&perf_data_topic, None) // None = get qos policy from publisher
+          .unwrap();
+        let sample_stream = data_reader.async_sample_stream();
+        let event_stream = sample_stream.async_event_stream();
+        (
+          sample_stream
+            .map(|r| r.map(|s| s.into_value().map_value(|v| v.baggage.len())))
+            .boxed_local()
+            .fuse(),
+          event_stream.boxed_local().fuse(),
+        )
+      };
 
       smol::block_on(async {
-        let mut sample_stream = data_reader.async_sample_stream();
-        let mut event_stream = sample_stream.async_event_stream();
         let mut ticker = StreamExt::fuse(async_io::Timer::interval(Duration::from_secs(1)));
 
         let mut sample_count = 0_u64;
@@ -162,13 +220,13 @@ fn main() {
 
             result = sample_stream.select_next_some() => {
               match result {
-                Ok(s) => match s.into_value() {
-                  Sample::Value(keyed_seq_msg) => {
+                Ok(s) => match s {
+                  Sample::Value(baggage_len) => {
                     sample_count += 1;
                     // estimate size of message on the wire:
                     // 8 bytes for u32 + u32
                     // 4 bytes for baggage sequence size
-                    byte_count += (8 + 4 + keyed_seq_msg.baggage.len()) as u64;
+                    byte_count += (8 + 4 + baggage_len) as u64;
                   }
                   Sample::Dispose(key) =>
                     println!("Disposed with key={key}"),
@@ -201,9 +259,20 @@ fn main() {
       pub_mode_args,
     } => {
       let publisher = domain_participant.create_publisher(&qos).unwrap();
-      let writer = publisher
-        .create_datawriter_cdr::<KeyedSeq>(&perf_data_topic, None) // None = get qos policy from publisher
-        .unwrap();
+      // None = get qos policy from publisher
+      let writer = if keyless {
+        PerfWriter::NoKey(
+          publisher
+            .create_datawriter_no_key_cdr::<KeyedSeq>(&perf_data_topic, None)
+            .unwrap(),
+        )
+      } else {
+        PerfWriter::Keyed(
+          publisher
+            .create_datawriter_cdr::<KeyedSeq>(&perf_data_topic, None)
+            .unwrap(),
+        )
+      };
 
       let baggage_size: usize = match pub_mode_args {
         None => 0,
@@ -225,10 +294,7 @@ fn main() {
           let mut new_message = keyed_seq_msg.clone();
           new_message.seq = seq;
           seq += 1;
-          writer
-            .async_write(new_message, None)
-            .unwrap_or_else(|e| error!("DataWriter async_write failed: {e:?}"))
-            .await;
+          writer.async_write(new_message, None).await;
           // wait for 1 sec for transfer to complete before exiting.
           let interval = 1_000_000_000 / rate;
           Timer::after(Duration::from_nanos(interval.into())).await;
@@ -0,0 +1,288 @@
+//! Implementation of the `ddsutil` subcommands.
+//!
+//! This is a module of its own, so that `tests/ddsutil.rs` can run the
+//! commands in-process against a publisher it has started itself.
+
+use std::{
+  collections::BTreeMap,
+  convert::Infallible,
+  fmt::Write,
+  thread,
+  time::{Duration, Instant},
+};
+
+use rustdds::{
+  dds::CreateResult,
+  no_key::{DataReader, DataSample, Decode, DefaultDecoder, DeserializerAdapter},
+  policy::{Durability, History, Reliability},
+  DomainParticipant, EndpointSnapshot, QosPolicyBuilder, RepresentationIdentifier, SampleInfo,
+  Timestamp, TopicKind,
+};
+
+// ------------------------------------------------------------------------
+// Raw samples
+
+/// A sample as it was received: the serialized payload and its encoding.
+/// `ddsutil` does not know the data types, so it cannot deserialize them.
+pub struct RawSample {
+  pub encoding: RepresentationIdentifier,
+  pub bytes: Vec<u8>,
+}
+
+/// DeserializerAdapter that accepts any encoding and keeps the bytes as is.
+pub struct RawAdapter;
+
+const ALL_ENCODINGS: &[RepresentationIdentifier] = &[
+  RepresentationIdentifier::CDR_BE,
+  RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::PL_CDR_BE,
+  RepresentationIdentifier::PL_CDR_LE,
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::XML,
+  RepresentationIdentifier::XCDR2_BE,
+  RepresentationIdentifier::XCDR2_LE,
+  RepresentationIdentifier::D_CDR2_BE,
+  RepresentationIdentifier::D_CDR2_LE,
+  RepresentationIdentifier::PL_XCDR2_BE,
+  RepresentationIdentifier::PL_XCDR2_LE,
+];
+
+impl DeserializerAdapter<RawSample> for RawAdapter {
+  type Error = Infallible;
+  type Decoded = RawSample;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    ALL_ENCODINGS
+  }
+
+  fn transform_decoded(decoded: RawSample) -> RawSample {
+    decoded
+  }
+}
+
+impl DefaultDecoder<RawSample> for RawAdapter {
+  type Decoder = RawDecoder;
+  const DECODER: Self::Decoder = RawDecoder;
+}
+
+#[derive(Clone)]
+pub struct RawDecoder;
+
+impl Decode<RawSample> for RawDecoder {
+  type Error = Infallible;
+
+  fn decode_bytes(
+    self,
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<RawSample, Infallible> {
+    Ok(RawSample {
+      encoding,
+      bytes: input_bytes.to_vec(),
+    })
+  }
+}
+
+pub type RawReader = DataReader<RawSample, RawAdapter>;
+
+// ------------------------------------------------------------------------
+// list and info
+
+/// A Topic seen in Discovery, with the number of remote endpoints on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicSummary {
+  pub name: String,
+  pub type_name: String,
+  pub writers: usize,
+  pub readers: usize,
+}
+
+/// Topics of remote participants, sorted by name. Local endpoints, i.e. those
+/// of `ddsutil` itself, are not counted.
+pub fn list(dp: &DomainParticipant) -> Vec<TopicSummary> {
+  let snapshot = dp.discovery_snapshot();
+  let mut topics = BTreeMap::new();
+  // Topics are announced separately, but not by all implementations. Then
+  // the endpoints still tell the topic and type.
+  for topic in &snapshot.topics {
+    summary_entry(&mut topics, &topic.name, &topic.type_name);
+  }
+  for participant in &snapshot.participants {
+    for w in &participant.writers {
+      summary_entry(&mut topics, &w.topic_name, &w.type_name).writers += 1;
+    }
+    for r in &participant.readers {
+      summary_entry(&mut topics, &r.topic_name, &r.type_name).readers += 1;
+    }
+  }
+  topics.into_values().collect()
+}
+
+fn summary_entry<'a>(
+  topics: &'a mut BTreeMap<String, TopicSummary>,
+  name: &str,
+  type_name: &str,
+) -> &'a mut TopicSummary {
+  topics
+    .entry(name.to_string())
+    .or_insert_with(|| TopicSummary {
+      name: name.to_string(),
+      type_name: type_name.to_string(),
+      writers: 0,
+      readers: 0,
+    })
+}
+
+/// Waits until Discovery has found `topic_name`, at most for `timeout`.
+pub fn find_topic(
+  dp: &DomainParticipant,
+  topic_name: &str,
+  timeout: Duration,
+) -> Option<TopicSummary> {
+  let deadline = Instant::now() + timeout;
+  loop {
+    let found = list(dp).into_iter().find(|t| t.name == topic_name);
+    if found.is_some() || Instant::now() >= deadline {
+      return found;
+    }
+    thread::sleep(Duration::from_millis(100));
+  }
+}
+
+/// Remote DataWriters and DataReaders on `topic_name`, writers first.
+pub fn info(dp: &DomainParticipant, topic_name: &str) -> Vec<(&'static str, EndpointSnapshot)> {
+  let snapshot = dp.discovery_snapshot();
+  let mut writers = Vec::new();
+  let mut readers = Vec::new();
+  for participant in snapshot.participants {
+    writers.extend(
+      participant
+        .writers
+        .into_iter()
+        .filter(|w| w.topic_name == topic_name)
+        .map(|w| ("writer", w)),
+    );
+    readers.extend(
+      participant
+        .readers
+        .into_iter()
+        .filter(|r| r.topic_name == topic_name)
+        .map(|r| ("reader", r)),
+    );
+  }
+  writers.extend(readers);
+  writers
+}
+
+// ------------------------------------------------------------------------
+// echo, hz and bw
+
+/// Subscribes to a discovered topic without knowing its data type.
+///
+/// The QoS is BestEffort and Volatile, so that the reader matches any
+/// DataWriter.
+pub fn subscribe(dp: &DomainParticipant, topic: &TopicSummary) -> CreateResult<RawReader> {
+  let qos = QosPolicyBuilder::new()
+    .reliability(Reliability::BestEffort)
+    .durability(Durability::Volatile)
+    .history(History::KeepLast { depth: 1000 })
+    .build();
+  let dds_topic = dp.create_topic(
+    topic.name.clone(),
+    topic.type_name.clone(),
+    &qos,
+    TopicKind::NoKey,
+  )?;
+  let subscriber = dp.create_subscriber(&qos)?;
+  subscriber.create_datareader_no_key::<RawSample, RawAdapter>(&dds_topic, Some(qos))
+}
+
+/// Formats a sample as a line of JSON, or as a hex dump.
+pub fn format_sample(sample: &DataSample<RawSample>, hex: bool) -> String {
+  let raw = sample.value();
+  if hex {
+    return hex_dump(&raw.bytes);
+  }
+  let info = sample.sample_info();
+  serde_json::json!({
+    "writer": info.writer_guid(),
+    "source_timestamp": info.source_timestamp().map(|t| format!("{t:?}")),
+    "reception_timestamp": format!("{:?}", info.reception_timestamp()),
+    "encoding": format!("{:?}", raw.encoding),
+    "size": raw.bytes.len(),
+    "data": raw.bytes.iter().fold(String::new(), |mut s, b| {
+      let _ = write!(s, "{b:02x}");
+      s
+    }),
+  })
+  .to_string()
+}
+
+fn hex_dump(bytes: &[u8]) -> String {
+  let mut out = String::new();
+  for (offset, line) in bytes.chunks(16).enumerate() {
+    let _ = write!(out, "{:08x} ", offset * 16);
+    for b in line {
+      let _ = write!(out, " {b:02x}");
+    }
+    out.push('\n');
+  }
+  out
+}
+
+/// Sample rate and bandwidth, from the reception timestamps of samples.
+#[derive(Debug, Default, Clone)]
+pub struct RateMeter {
+  pub samples: u64,
+  pub bytes: u64,
+  first: Option<Timestamp>,
+  last: Option<Timestamp>,
+}
+
+impl RateMeter {
+  pub fn add(&mut self, info: &SampleInfo, size: usize) {
+    let received = info.reception_timestamp();
+    self.samples += 1;
+    self.bytes += size as u64;
+    self.first.get_or_insert(received);
+    self.last = Some(received);
+  }
+
+  // Time between the first and the last sample
+  fn span(&self) -> Option<f64> {
+    let secs = Duration::from(self.last?.duration_since(self.first?)).as_secs_f64();
+    (secs > 0.0).then_some(secs)
+  }
+
+  /// Samples per second
+  pub fn rate(&self) -> Option<f64> {
+    let span = self.span()?;
+    Some((self.samples - 1) as f64 / span)
+  }
+
+  /// Payload bytes per second
+  pub fn bandwidth(&self) -> Option<f64> {
+    let span = self.span()?;
+    // The first sample only starts the measurement.
+    let per_sample = self.bytes as f64 / self.samples as f64;
+    Some((self.samples - 1) as f64 * per_sample / span)
+  }
+}
+
+/// Takes samples from `reader` for `duration`, and measures them.
+pub fn measure(reader: &mut RawReader, duration: Duration) -> RateMeter {
+  let mut meter = RateMeter::default();
+  let deadline = Instant::now() + duration;
+  while Instant::now() < deadline {
+    while let Ok(Some(sample)) = reader.take_next_sample() {
+      meter.add(sample.sample_info(), sample.value().bytes.len());
+    }
+    thread::sleep(Duration::from_millis(5));
+  }
+  meter
+}
@@ -0,0 +1,147 @@
+//! Command line tool for looking into a DDS domain, in the spirit of
+//! `ros2 topic` and the CycloneDDS tools.
+//!
+//! ```text
+//! cargo run --example ddsutil -- list
+//! cargo run --example ddsutil -- echo Square
+//! cargo run --example ddsutil -- hz Square
+//! cargo run --example ddsutil -- bw Square
+//! cargo run --example ddsutil -- info Square
+//! ```
+//!
+//! `ddsutil` does not know the data types of the topics, so `echo` prints
+//! the serialized payload, as JSON or as a hex dump.
+
+use std::{process::exit, time::Duration};
+
+use clap::{Parser, Subcommand};
+use rustdds::DomainParticipant;
+
+mod commands;
+use commands::TopicSummary;
+
+#[derive(Parser)]
+struct CommandLineArgs {
+  /// DDS Domain id
+  #[arg(short, long, default_value_t = 0)]
+  domain_id: u16,
+  /// Seconds to wait for Discovery before giving up on a topic, or before
+  /// listing topics
+  #[arg(short, long, default_value_t = 3)]
+  wait: u64,
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Print discovered topics with their types and endpoint counts
+  List,
+  /// Print samples of a topic as they arrive
+  Echo {
+    topic: String,
+    /// Print a hex dump instead of JSON
+    #[arg(long)]
+    hex: bool,
+  },
+  /// Print the sample rate of a topic
+  Hz {
+    topic: String,
+    /// Seconds per measurement
+    #[arg(short = 'n', long, default_value_t = 1)]
+    window: u64,
+  },
+  /// Print the bandwidth of a topic
+  Bw {
+    topic: String,
+    /// Seconds per measurement
+    #[arg(short = 'n', long, default_value_t = 1)]
+    window: u64,
+  },
+  /// Print the QoS of all discovered endpoints of a topic
+  Info { topic: String },
+}
+
+fn main() {
+  let args = CommandLineArgs::parse();
+  let wait = Duration::from_secs(args.wait);
+  let dp = DomainParticipant::new(args.domain_id)
+    .unwrap_or_else(|e| fail(&format!("Cannot create DomainParticipant: {e}")));
+
+  match args.command {
+    Command::List => {
+      // Let Discovery find the others first
+      std::thread::sleep(wait);
+      println!(
+        "{:<40} {:<40} {:>7} {:>7}",
+        "TOPIC", "TYPE", "WRITERS", "READERS"
+      );
+      for t in commands::list(&dp) {
+        println!(
+          "{:<40} {:<40} {:>7} {:>7}",
+          t.name, t.type_name, t.writers, t.readers
+        );
+      }
+    }
+    Command::Echo { topic, hex } => {
+      let mut reader = subscribe(&dp, &topic, wait);
+      loop {
+        while let Ok(Some(sample)) = reader.take_next_sample() {
+          println!("{}", commands::format_sample(&sample, hex));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+      }
+    }
+    Command::Hz { topic, window } => {
+      let mut reader = subscribe(&dp, &topic, wait);
+      loop {
+        let meter = commands::measure(&mut reader, Duration::from_secs(window));
+        match meter.rate() {
+          Some(rate) => println!("{rate:.2} Hz ({} samples)", meter.samples),
+          None => println!("no samples"),
+        }
+      }
+    }
+    Command::Bw { topic, window } => {
+      let mut reader = subscribe(&dp, &topic, wait);
+      loop {
+        let meter = commands::measure(&mut reader, Duration::from_secs(window));
+        match meter.bandwidth() {
+          Some(bw) => println!(
+            "{:.1} kB/s ({} samples, {} bytes)",
+            bw / 1000.0,
+            meter.samples,
+            meter.bytes
+          ),
+          None => println!("no samples"),
+        }
+      }
+    }
+    Command::Info { topic } => {
+      find(&dp, &topic, wait);
+      for (kind, endpoint) in commands::info(&dp, &topic) {
+        println!("{kind} {:?} type {}", endpoint.guid, endpoint.type_name);
+        match serde_json::to_string_pretty(&endpoint.qos) {
+          Ok(qos) => println!("{qos}"),
+          Err(e) => println!("  QoS not printable: {e}"),
+        }
+      }
+    }
+  }
+}
+
+fn find(dp: &DomainParticipant, topic: &str, wait: Duration) -> TopicSummary {
+  commands::find_topic(dp, topic, wait)
+    .unwrap_or_else(|| fail(&format!("Topic {topic} not found in {wait:?}")))
+}
+
+fn subscribe(dp: &DomainParticipant, topic: &str, wait: Duration) -> commands::RawReader {
+  let summary = find(dp, topic, wait);
+  commands::subscribe(dp, &summary)
+    .unwrap_or_else(|e| fail(&format!("Cannot subscribe to {topic}: {e}")))
+}
+
+fn fail(message: &str) -> ! {
+  eprintln!("{message}");
+  exit(1)
+}
@@ -360,6 +360,7 @@ fn main() {
                   statusevents::DataWriterStatus::PublicationMatched { .. } => {
                     println!("on_publication_matched()");
                   }
+                  _ => {}
                 }
               } else {
                 println!("DataWriter status: {status:?}");
@@ -8,10 +8,16 @@ pub(crate) mod participant;
 pub use participant::DomainParticipant;
 
 pub(crate) mod ddsdata;
+pub(crate) mod discovery_snapshot;
+pub(crate) mod endpoint_options;
+pub(crate) mod health;
 pub(crate) mod pubsub;
 pub(crate) mod readcondition;
+pub(crate) mod statistics;
+pub(crate) mod thread_options;
 pub(crate) mod topic;
 pub(crate) mod typedesc;
+pub(crate) mod wildcard;
 
 pub mod result;
 pub use result::{
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+#[cfg(feature = "security")]
+use crate::discovery::secure_discovery::AuthenticationStatus;
+use crate::{
+  dds::qos::{QosPolicies, QosPolicyId},
+  structure::guid::GUID,
+};
+
+/// Everything Discovery currently knows about the domain, as seen from a
+/// [`DomainParticipant`](crate::DomainParticipant).
+///
+/// See
+/// [`DomainParticipant::discovery_snapshot`](crate::DomainParticipant::discovery_snapshot).
+///
+/// This implements `serde::Serialize`, e.g. for attaching it as JSON to a bug
+/// report. Locators are formatted as text.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoverySnapshot {
+  /// GUID of the local DomainParticipant
+  pub local_participant: GUID,
+  /// DataWriters of the local DomainParticipant, with their matches to remote
+  /// DataReaders.
+  pub local_writers: Vec<EndpointSnapshot>,
+  /// DataReaders of the local DomainParticipant, with their matches to remote
+  /// DataWriters.
+  pub local_readers: Vec<EndpointSnapshot>,
+  /// Remote DomainParticipants, and their DataWriters and DataReaders
+  pub participants: Vec<ParticipantSnapshot>,
+  /// User-defined Topics, one entry per DomainParticipant announcing it
+  pub topics: Vec<TopicSnapshot>,
+}
+
+/// A remote DomainParticipant in a [`DiscoverySnapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ParticipantSnapshot {
+  pub guid: GUID,
+  pub entity_name: Option<String>,
+  pub vendor_id: String,
+  /// `None` if the participant did not announce a lease duration
+  pub lease_duration: Option<Duration>,
+  /// Time since anything was heard from the participant
+  pub since_last_life_sign: Option<Duration>,
+  pub metatraffic_unicast_locators: Vec<String>,
+  pub metatraffic_multicast_locators: Vec<String>,
+  pub default_unicast_locators: Vec<String>,
+  pub default_multicast_locators: Vec<String>,
+  /// `None` if authentication with the participant has not started
+  #[cfg(feature = "security")]
+  pub authentication_status: Option<AuthenticationStatus>,
+  pub writers: Vec<EndpointSnapshot>,
+  pub readers: Vec<EndpointSnapshot>,
+}
+
+/// A DataWriter or DataReader in a [`DiscoverySnapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointSnapshot {
+  pub guid: GUID,
+  pub topic_name: String,
+  pub type_name: String,
+  pub qos: QosPolicies,
+  pub unicast_locators: Vec<String>,
+  pub multicast_locators: Vec<String>,
+  /// Only for local endpoints: the remote endpoints of the opposite kind on
+  /// the same Topic.
+  pub matches: Vec<MatchSnapshot>,
+}
+
+/// Match state between a local and a remote endpoint in a
+/// [`DiscoverySnapshot`].
+///
+/// Only QoS compatibility is checked here. A Writer may still refuse to match
+/// a Reader for other reasons, such as payload compression support.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchSnapshot {
+  pub remote: GUID,
+  /// The first incompatible QoS policy, or `None` if the endpoints match.
+  pub incompatible_policy: Option<QosPolicyId>,
+}
+
+/// A Topic in a [`DiscoverySnapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct TopicSnapshot {
+  pub name: String,
+  pub type_name: String,
+  pub qos: QosPolicies,
+}
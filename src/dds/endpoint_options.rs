@@ -0,0 +1,162 @@
+use std::net::SocketAddr;
+
+use crate::{
+  create_error_bad_parameter,
+  dds::result::{CreateError, CreateResult},
+  structure::locator::Locator,
+};
+
+/// Per-endpoint settings given when creating a DataWriter or DataReader.
+///
+/// See e.g.
+/// [`Publisher::create_datawriter_with_options`](crate::Publisher::create_datawriter_with_options).
+///
+/// The locators set here are advertised in Discovery for this endpoint only,
+/// instead of the participant default locators. A remote participant then
+/// sends to these addresses when communicating with this endpoint. An empty
+/// list (the default) means that the participant defaults are used.
+///
+/// RustDDS does not open any sockets for these addresses. Traffic to them must
+/// reach the participant's own sockets by other means, e.g. NAT port
+/// forwarding.
+///
+/// # Examples
+///
+/// ```
+/// # use std::net::SocketAddr;
+/// # use rustdds::*;
+/// let public: SocketAddr = "203.0.113.7:7411".parse().unwrap();
+/// let options = EndpointOptions::new().unicast_locators(vec![Locator::from(public)]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointOptions {
+  unicast_locators: Vec<Locator>,
+  multicast_locators: Vec<Locator>,
+}
+
+impl EndpointOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Unicast locators to advertise for this endpoint.
+  #[must_use]
+  pub fn unicast_locators(mut self, locators: Vec<Locator>) -> Self {
+    self.unicast_locators = locators;
+    self
+  }
+
+  /// Multicast locators to advertise for this endpoint. These must be
+  /// multicast addresses.
+  #[must_use]
+  pub fn multicast_locators(mut self, locators: Vec<Locator>) -> Self {
+    self.multicast_locators = locators;
+    self
+  }
+
+  pub(crate) fn has_locators(&self) -> bool {
+    !self.unicast_locators.is_empty() || !self.multicast_locators.is_empty()
+  }
+
+  pub(crate) fn validate(&self) -> CreateResult<()> {
+    let check = |locator: &Locator, multicast: bool| -> CreateResult<()> {
+      match locator {
+        Locator::Invalid | Locator::Reserved => {
+          create_error_bad_parameter!("Locator {:?} cannot be advertised", locator)
+        }
+        Locator::UdpV4(_) | Locator::UdpV6(_) => {
+          let address = SocketAddr::from(*locator);
+          if address.ip().is_unspecified() || address.port() == 0 {
+            create_error_bad_parameter!(
+              "Advertised locator {} needs an address and a port",
+              address
+            )
+          } else if multicast && !address.ip().is_multicast() {
+            create_error_bad_parameter!("{} is not a multicast address", address)
+          } else {
+            Ok(())
+          }
+        }
+        // Other transports are passed on as they are.
+        Locator::Other { .. } => Ok(()),
+      }
+    };
+
+    for locator in &self.unicast_locators {
+      check(locator, false)?;
+    }
+    for locator in &self.multicast_locators {
+      check(locator, true)?;
+    }
+    Ok(())
+  }
+
+  // Replaces the given lists with the ones set here, if any.
+  pub(crate) fn apply(&self, unicast: &mut Vec<Locator>, multicast: &mut Vec<Locator>) {
+    if !self.unicast_locators.is_empty() {
+      unicast.clone_from(&self.unicast_locators);
+    }
+    if !self.multicast_locators.is_empty() {
+      multicast.clone_from(&self.multicast_locators);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn locator(s: &str) -> Locator {
+    Locator::from(s.parse::<SocketAddr>().unwrap())
+  }
+
+  #[test]
+  fn endpoint_options_validate() {
+    assert!(EndpointOptions::new().validate().is_ok());
+    assert!(EndpointOptions::new()
+      .unicast_locators(vec![locator("203.0.113.7:7411")])
+      .multicast_locators(vec![locator("239.255.0.1:7401")])
+      .validate()
+      .is_ok());
+
+    let bad_unicast = [
+      Locator::Invalid,
+      locator("0.0.0.0:7411"), // becomes Locator::Invalid
+      locator("203.0.113.7:0"),
+      Locator::UdpV6("[::]:7411".parse().unwrap()),
+    ];
+    for bad in bad_unicast {
+      assert!(
+        EndpointOptions::new()
+          .unicast_locators(vec![bad])
+          .validate()
+          .is_err(),
+        "{bad:?}"
+      );
+    }
+    assert!(EndpointOptions::new()
+      .multicast_locators(vec![locator("203.0.113.7:7401")])
+      .validate()
+      .is_err());
+  }
+
+  #[test]
+  fn endpoint_options_apply() {
+    let default_unicast = vec![locator("192.168.1.2:7411")];
+    let default_multicast = vec![locator("239.255.0.1:7401")];
+    let public = vec![locator("203.0.113.7:7411")];
+
+    let mut unicast = default_unicast.clone();
+    let mut multicast = default_multicast.clone();
+    EndpointOptions::new().apply(&mut unicast, &mut multicast);
+    assert_eq!(
+      (&unicast, &multicast),
+      (&default_unicast, &default_multicast)
+    );
+
+    EndpointOptions::new()
+      .unicast_locators(public.clone())
+      .apply(&mut unicast, &mut multicast);
+    assert_eq!((&unicast, &multicast), (&public, &default_multicast));
+  }
+}
@@ -0,0 +1,420 @@
+use std::{
+  any::Any,
+  panic::{self, AssertUnwindSafe},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  dds::{
+    key::Keyed,
+    qos::{
+      policy::{Durability, History, Reliability},
+      QosPolicies, QosPolicyBuilder,
+    },
+    statistics::StatisticsCounters,
+    statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
+  },
+  structure::{duration::Duration as DdsDuration, guid::GUID},
+};
+
+// A background thread is considered stuck, if it has not completed a loop
+// round for this long. The event loop wakes up at least every 2 seconds, and
+// Discovery at least every 5 seconds, even if there is nothing to do.
+const EVENT_LOOP_STALE_AFTER: Duration = Duration::from_secs(10);
+const DISCOVERY_STALE_AFTER: Duration = Duration::from_secs(15);
+
+/// Self-diagnostics snapshot of a
+/// [`DomainParticipant`](crate::DomainParticipant).
+///
+/// See [`DomainParticipant::health`](crate::DomainParticipant::health).
+#[derive(Debug, Clone)]
+pub struct ParticipantHealth {
+  /// The event loop thread, which runs RTPS Readers and Writers and all
+  /// network I/O.
+  pub event_loop: ThreadHealth,
+  /// The Discovery thread.
+  pub discovery: ThreadHealth,
+  /// Commands queued from application threads to the event loop, but not yet
+  /// processed by it.
+  pub channel_depths: ChannelDepths,
+  /// Time since a packet was last received from any socket, or `None` if
+  /// nothing has been received yet.
+  pub since_last_socket_read: Option<Duration>,
+  /// Time since a packet was last successfully sent, or `None` if nothing has
+  /// been sent yet.
+  pub since_last_socket_write: Option<Duration>,
+  /// If a background thread has panicked, the participant is no longer usable,
+  /// and this tells what happened.
+  pub failure: Option<String>,
+}
+
+impl ParticipantHealth {
+  /// No failure, and both background threads are alive.
+  pub fn is_healthy(&self) -> bool {
+    self.failure.is_none() && self.event_loop.alive && self.discovery.alive
+  }
+}
+
+/// Sample published on the health topic of a
+/// [`DomainParticipant`](crate::DomainParticipant).
+///
+/// See
+/// [`DomainParticipantBuilder::enable_health_topic`](crate::DomainParticipantBuilder::enable_health_topic).
+/// The topic is keyed by participant, so a monitoring application subscribing
+/// to [`TOPIC_NAME`](Self::TOPIC_NAME) sees one instance per participant. When
+/// a participant shuts down, it disposes its instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipantHealthSample {
+  /// GUID of the publishing DomainParticipant.
+  pub participant: GUID,
+  /// Time since the DomainParticipant was created.
+  pub uptime: DdsDuration,
+  /// [`ParticipantHealth::is_healthy`] at the time of publishing.
+  pub healthy: bool,
+  /// Number of remote DomainParticipants currently known by Discovery.
+  pub discovered_participants: u32,
+  /// Number of local user DataWriters, including the health topic writer.
+  pub local_writers: u32,
+  /// Number of local user DataReaders.
+  pub local_readers: u32,
+  /// Number of remote DataReaders on topics where we have a DataWriter. QoS
+  /// compatibility is not checked.
+  pub matched_remote_readers: u32,
+  /// Number of remote DataWriters on topics where we have a DataReader. QoS
+  /// compatibility is not checked.
+  pub matched_remote_writers: u32,
+}
+
+impl ParticipantHealthSample {
+  /// Name of the health topic.
+  pub const TOPIC_NAME: &'static str = "rustdds_participant_health";
+  /// Type name of the health topic.
+  pub const TYPE_NAME: &'static str = "rustdds::ParticipantHealthSample";
+
+  /// QoS of the health topic writer. A DataReader with this QoS receives the
+  /// latest sample of each participant also when it joins late.
+  pub fn qos() -> QosPolicies {
+    QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: DdsDuration::ZERO,
+      })
+      .durability(Durability::TransientLocal)
+      .history(History::KeepLast { depth: 1 })
+      .build()
+  }
+}
+
+impl Keyed for ParticipantHealthSample {
+  type K = GUID;
+
+  fn key(&self) -> GUID {
+    self.participant
+  }
+}
+
+/// Liveness of a background thread, as observed from its heartbeat counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadHealth {
+  /// The thread is running and has recently completed a round of its loop.
+  pub alive: bool,
+  /// Number of loop rounds completed so far.
+  pub heartbeats: u64,
+  /// Time since the thread last completed a loop round, or `None` if it never
+  /// has.
+  pub since_last_heartbeat: Option<Duration>,
+}
+
+/// Number of commands waiting in each internal command channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelDepths {
+  pub add_reader: usize,
+  pub remove_reader: usize,
+  pub add_writer: usize,
+  pub remove_writer: usize,
+  pub event_loop_commands: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MonitoredThread {
+  EventLoop,
+  Discovery,
+}
+
+// Counts messages in and out of a channel. The channels themselves cannot
+// tell how many items they contain.
+#[derive(Debug, Default)]
+pub(crate) struct ChannelCounter {
+  sent: AtomicUsize,
+  received: AtomicUsize,
+}
+
+impl ChannelCounter {
+  pub fn sent(&self) {
+    self.sent.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn received(&self) {
+    self.received.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn depth(&self) -> usize {
+    // Receiver may be counted before the sender, so saturate.
+    let received = self.received.load(Ordering::Relaxed);
+    self.sent.load(Ordering::Relaxed).saturating_sub(received)
+  }
+}
+
+#[derive(Debug, Default)]
+struct ThreadMonitor {
+  running: AtomicBool,
+  heartbeats: AtomicU64,
+  last_heartbeat: AtomicU64, // see HealthMonitor::stamp()
+}
+
+// Participant-wide health bookkeeping, shared between the DomainParticipant,
+// its Publishers and Subscribers, the event loop and Discovery.
+//
+// Timestamps are stored as nanoseconds since the creation of the monitor, plus
+// one, so that zero means "never". This keeps the hot paths lock-free.
+#[derive(Debug)]
+pub(crate) struct HealthMonitor {
+  epoch: Instant,
+  event_loop: ThreadMonitor,
+  discovery: ThreadMonitor,
+  pub add_reader: ChannelCounter,
+  pub remove_reader: ChannelCounter,
+  pub add_writer: ChannelCounter,
+  pub remove_writer: ChannelCounter,
+  pub event_loop_commands: ChannelCounter,
+  pub statistics: StatisticsCounters,
+  last_socket_read: AtomicU64,
+  last_socket_write: AtomicU64,
+  failed: AtomicBool,
+  failure: Mutex<Option<String>>,
+}
+
+impl Default for HealthMonitor {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl HealthMonitor {
+  pub fn new() -> Self {
+    Self {
+      epoch: Instant::now(),
+      event_loop: ThreadMonitor::default(),
+      discovery: ThreadMonitor::default(),
+      add_reader: ChannelCounter::default(),
+      remove_reader: ChannelCounter::default(),
+      add_writer: ChannelCounter::default(),
+      remove_writer: ChannelCounter::default(),
+      event_loop_commands: ChannelCounter::default(),
+      statistics: StatisticsCounters::default(),
+      last_socket_read: AtomicU64::new(0),
+      last_socket_write: AtomicU64::new(0),
+      failed: AtomicBool::new(false),
+      failure: Mutex::new(None),
+    }
+  }
+
+  fn stamp(&self) -> u64 {
+    u64::try_from(self.epoch.elapsed().as_nanos())
+      .unwrap_or(u64::MAX - 1)
+      .saturating_add(1)
+  }
+
+  fn since(&self, stamp: &AtomicU64, now: u64) -> Option<Duration> {
+    match stamp.load(Ordering::Relaxed) {
+      0 => None,
+      s => Some(Duration::from_nanos(now.saturating_sub(s))),
+    }
+  }
+
+  fn thread(&self, thread: MonitoredThread) -> &ThreadMonitor {
+    match thread {
+      MonitoredThread::EventLoop => &self.event_loop,
+      MonitoredThread::Discovery => &self.discovery,
+    }
+  }
+
+  // Called by a background thread on every round of its loop.
+  pub fn heartbeat(&self, thread: MonitoredThread) {
+    let t = self.thread(thread);
+    t.heartbeats.fetch_add(1, Ordering::Relaxed);
+    t.last_heartbeat.store(self.stamp(), Ordering::Relaxed);
+  }
+
+  // For loops run by the application instead of a background thread.
+  pub fn set_running(&self, thread: MonitoredThread, running: bool) {
+    self
+      .thread(thread)
+      .running
+      .store(running, Ordering::Relaxed);
+  }
+
+  pub fn socket_read(&self) {
+    self.last_socket_read.store(self.stamp(), Ordering::Relaxed);
+  }
+
+  pub fn socket_written(&self) {
+    self
+      .last_socket_write
+      .store(self.stamp(), Ordering::Relaxed);
+  }
+
+  // Returns the failure reason, if the participant has failed.
+  pub fn failure(&self) -> Option<String> {
+    if self.failed.load(Ordering::Acquire) {
+      self
+        .failure
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    } else {
+      None
+    }
+  }
+
+  pub fn set_failed(&self, reason: String) {
+    let mut failure = self.failure.lock().unwrap_or_else(|e| e.into_inner());
+    // The first failure is the interesting one
+    if failure.is_none() {
+      *failure = Some(reason);
+    }
+    self.failed.store(true, Ordering::Release);
+  }
+
+  // Runs the main function of a background thread, keeping track of whether
+  // it is running. A panic is caught and turned into a participant failure,
+  // which is reported as the last DomainParticipantStatusEvent.
+  pub fn run_monitored<F: FnOnce()>(
+    &self,
+    thread: MonitoredThread,
+    status_sender: &StatusChannelSender<DomainParticipantStatusEvent>,
+    main: F,
+  ) {
+    let t = self.thread(thread);
+    t.running.store(true, Ordering::Relaxed);
+    let result = panic::catch_unwind(AssertUnwindSafe(main));
+    t.running.store(false, Ordering::Relaxed);
+
+    if let Err(payload) = result {
+      let reason = format!("{thread:?} thread panicked: {}", panic_message(&*payload));
+      error!("RustDDS participant failed: {reason}");
+      self.set_failed(reason.clone());
+      status_sender
+        .try_send(DomainParticipantStatusEvent::ParticipantFailed { reason })
+        .unwrap_or_else(|e| error!("Cannot report participant failure: {e:?}"));
+    }
+  }
+
+  fn thread_health(&self, thread: MonitoredThread, now: u64) -> ThreadHealth {
+    let t = self.thread(thread);
+    let stale_after = match thread {
+      MonitoredThread::EventLoop => EVENT_LOOP_STALE_AFTER,
+      MonitoredThread::Discovery => DISCOVERY_STALE_AFTER,
+    };
+    let since_last_heartbeat = self.since(&t.last_heartbeat, now);
+    ThreadHealth {
+      alive: t.running.load(Ordering::Relaxed)
+        && since_last_heartbeat.is_some_and(|d| d <= stale_after),
+      heartbeats: t.heartbeats.load(Ordering::Relaxed),
+      since_last_heartbeat,
+    }
+  }
+
+  pub fn snapshot(&self) -> ParticipantHealth {
+    let now = self.stamp();
+    ParticipantHealth {
+      event_loop: self.thread_health(MonitoredThread::EventLoop, now),
+      discovery: self.thread_health(MonitoredThread::Discovery, now),
+      channel_depths: ChannelDepths {
+        add_reader: self.add_reader.depth(),
+        remove_reader: self.remove_reader.depth(),
+        add_writer: self.add_writer.depth(),
+        remove_writer: self.remove_writer.depth(),
+        event_loop_commands: self.event_loop_commands.depth(),
+      },
+      since_last_socket_read: self.since(&self.last_socket_read, now),
+      since_last_socket_write: self.since(&self.last_socket_write, now),
+      failure: self.failure(),
+    }
+  }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+  if let Some(s) = payload.downcast_ref::<&str>() {
+    s
+  } else if let Some(s) = payload.downcast_ref::<String>() {
+    s
+  } else {
+    "(non-string panic payload)"
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dds::statusevents::{sync_status_channel, StatusEvented};
+
+  #[test]
+  fn health_monitor_tracks_threads_and_channels() {
+    let monitor = HealthMonitor::new();
+    let h = monitor.snapshot();
+    assert!(!h.event_loop.alive);
+    assert_eq!(h.event_loop.since_last_heartbeat, None);
+    assert_eq!(h.since_last_socket_read, None);
+    assert_eq!(h.channel_depths, ChannelDepths::default());
+
+    let (status_sender, _status_receiver) = sync_status_channel(4).unwrap();
+    monitor.run_monitored(MonitoredThread::Discovery, &status_sender, || {
+      monitor.heartbeat(MonitoredThread::Discovery);
+      monitor.heartbeat(MonitoredThread::Discovery);
+      let h = monitor.snapshot();
+      assert!(h.discovery.alive);
+      assert_eq!(h.discovery.heartbeats, 2);
+      assert!(!h.event_loop.alive);
+    });
+    // not running anymore
+    assert!(!monitor.snapshot().discovery.alive);
+
+    monitor.add_writer.sent();
+    monitor.add_writer.sent();
+    monitor.add_writer.received();
+    monitor.socket_written();
+    let h = monitor.snapshot();
+    assert_eq!(h.channel_depths.add_writer, 1);
+    assert!(h.since_last_socket_write.is_some());
+    assert_eq!(h.failure, None);
+  }
+
+  #[test]
+  fn health_monitor_captures_panic() {
+    let monitor = HealthMonitor::new();
+    let (status_sender, status_receiver) = sync_status_channel(4).unwrap();
+    monitor.run_monitored(MonitoredThread::EventLoop, &status_sender, || {
+      monitor.heartbeat(MonitoredThread::EventLoop);
+      panic!("test panic");
+    });
+
+    let h = monitor.snapshot();
+    assert!(!h.event_loop.alive);
+    assert!(!h.is_healthy());
+    let reason = h.failure.expect("failure should be recorded");
+    assert!(reason.contains("test panic"));
+
+    match status_receiver.try_recv_status() {
+      Some(DomainParticipantStatusEvent::ParticipantFailed { reason: r }) => assert_eq!(r, reason),
+      other => panic!("Expected ParticipantFailed, got {other:?}"),
+    }
+  }
+}
@@ -1,6 +1,17 @@
-use std::thread;
+use std::{
+  collections::BTreeMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Condvar, Mutex, OnceLock,
+  },
+  task::{Wake, Waker},
+  thread::{self, Thread},
+  time::Instant,
+};
 
 use mio_extras::channel::{SyncSender, TrySendError};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
 
 use crate::structure::duration::Duration;
 
@@ -39,3 +50,98 @@ pub fn try_send_timeout<T>(
     Err(other) => Err(other),
   }
 }
+
+// Waker that unparks a blocked thread. Used by the blocking calls that wait
+// for something that is signalled to async tasks via a Waker.
+pub(crate) struct ThreadWaker(pub Thread);
+
+impl Wake for ThreadWaker {
+  fn wake(self: Arc<Self>) {
+    self.0.unpark();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.0.unpark();
+  }
+}
+
+// Wakes a task at a deadline, unless dropped before that. There is no timer
+// available to futures in a runtime-independent way, so all alarms are served
+// by a single background thread.
+pub(crate) struct Alarm {
+  key: (Instant, u64),
+}
+
+impl Alarm {
+  pub fn new(deadline: Instant, waker: &Waker) -> Self {
+    let clock = AlarmClock::get();
+    let key = (deadline, clock.next_id.fetch_add(1, Ordering::Relaxed));
+    clock.alarms.lock().unwrap().insert(key, waker.clone());
+    clock.changed.notify_one();
+    Self { key }
+  }
+
+  // The task may be polled with a different Waker than before.
+  pub fn set_waker(&self, waker: &Waker) {
+    if let Some(w) = AlarmClock::get().alarms.lock().unwrap().get_mut(&self.key) {
+      w.clone_from(waker);
+    }
+  }
+}
+
+impl Drop for Alarm {
+  fn drop(&mut self) {
+    // Do not wake a task that is no longer interested.
+    AlarmClock::get().alarms.lock().unwrap().remove(&self.key);
+  }
+}
+
+struct AlarmClock {
+  // Ordered by deadline. The id tells apart alarms with the same deadline.
+  alarms: Mutex<BTreeMap<(Instant, u64), Waker>>,
+  changed: Condvar,
+  next_id: AtomicU64,
+}
+
+impl AlarmClock {
+  fn get() -> &'static Self {
+    static CLOCK: OnceLock<AlarmClock> = OnceLock::new();
+    CLOCK.get_or_init(|| {
+      let started = thread::Builder::new()
+        .name("RustDDS alarm clock".to_string())
+        .spawn(|| AlarmClock::get().run());
+      if let Err(e) = started {
+        error!("Cannot start alarm clock thread. Timeouts are not reported. {e}");
+      }
+      AlarmClock {
+        alarms: Mutex::new(BTreeMap::new()),
+        changed: Condvar::new(),
+        next_id: AtomicU64::new(0),
+      }
+    })
+  }
+
+  fn run(&self) {
+    let mut alarms = self.alarms.lock().unwrap();
+    loop {
+      let now = Instant::now();
+      // Alarms are removed as they go off, so that they go off only once.
+      let remaining = alarms.split_off(&(now, u64::MAX));
+      let due = std::mem::replace(&mut *alarms, remaining);
+      if !due.is_empty() {
+        // Wake without holding the lock, as waking may poll the task.
+        drop(alarms);
+        due.into_values().for_each(Waker::wake);
+        alarms = self.alarms.lock().unwrap();
+        continue;
+      }
+      alarms = match alarms.keys().next() {
+        Some((deadline, _)) => {
+          let timeout = deadline.saturating_duration_since(now);
+          self.changed.wait_timeout(alarms, timeout).unwrap().0
+        }
+        None => self.changed.wait(alarms).unwrap(),
+      };
+    }
+  }
+}
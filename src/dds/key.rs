@@ -53,10 +53,18 @@ impl KeyHash {
     Self([0; 16])
   }
 
+  pub fn from_bytes(bytes: [u8; 16]) -> Self {
+    Self(bytes)
+  }
+
   pub fn to_vec(self) -> Vec<u8> {
     Vec::from(self.0)
   }
 
+  pub fn to_bytes(self) -> [u8; 16] {
+    self.0
+  }
+
   pub fn into_pl_cdr_bytes(self) -> Result<Vec<u8>, PlCdrSerializeError> {
     Ok(self.to_vec())
   }
@@ -68,6 +76,22 @@ impl KeyHash {
   }
 }
 
+/// Identifies an instance that a
+/// [`DataWriter`](crate::with_key::DataWriter) has written, disposed, or
+/// registered.
+///
+/// See DDS Spec v1.4 Section 2.2.2.4.2.5 register_instance.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy)]
+pub struct InstanceHandle(pub(crate) KeyHash);
+
+impl InstanceHandle {
+  /// The RTPS key hash of the instance. This is the non-MD5 form, if the
+  /// key is short enough.
+  pub fn key_hash(&self) -> KeyHash {
+    self.0
+  }
+}
+
 /// Trait for instance lookup key in a WITH_KEY topic.
 ///
 /// The corresponding data sample type must implement [`Keyed`].
@@ -1,29 +1,42 @@
 use std::{
   io,
+  ops::Range,
   pin::Pin,
   task::{Context, Poll},
 };
 
-use futures::stream::{FusedStream, Stream};
+use futures::{
+  future::FusedFuture,
+  stream::{FusedStream, Stream, StreamExt, TakeUntil},
+  Future,
+};
 
 use crate::{
   dds::{
     adapters::no_key::{DefaultDecoder, DeserializerAdapter},
     no_key::datasample::DataSample,
-    qos::{HasQoSPolicy, QosPolicies},
+    pubsub::Subscriber,
+    qos::{HasQoSPolicy, QosPolicies, QosPolicyId},
     readcondition::ReadCondition,
-    result::ReadResult,
-    statusevents::DataReaderStatus,
+    result::{ReadError, ReadResult},
+    sampleinfo::SampleInfo,
+    statusevents::{
+      CountWithChange, DataReaderStatus, EndpointDescription, LivelinessChangedStatus,
+      RemoteLocators, SubscriptionMatchedStatus,
+    },
+    topic::Topic,
     with_key::{
       datareader as datareader_with_key,
       datasample::{DataSample as WithKeyDataSample, Sample},
       BareDataReaderStream as WithKeyBareDataReaderStream, DataReader as WithKeyDataReader,
       DataReaderEventStream as WithKeyDataReaderEventStream,
-      DataReaderStream as WithKeyDataReaderStream,
+      DataReaderSampleAndEventStream as WithKeyDataReaderSampleAndEventStream,
+      DataReaderStream as WithKeyDataReaderStream, SampleOrEvent,
     },
   },
+  discovery::sedp_messages::PublicationBuiltinTopicData,
   serialization::CDRDeserializerAdapter,
-  structure::entity::RTPSEntity,
+  structure::{duration::Duration, entity::RTPSEntity, sequence_number::SequenceNumber},
   StatusEvented, GUID,
 };
 use super::wrappers::{DAWrapper, NoKeyWrapper};
@@ -166,6 +179,50 @@ where
     Ok(result)
   }
 
+  /// Reads samples like [`read`](Self::read), but only those whose value
+  /// satisfies `predicate`.
+  ///
+  /// See [`with_key::DataReader::read_matching`](crate::with_key::DataReader::read_matching).
+  pub fn read_matching(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    predicate: impl Fn(&D, &SampleInfo) -> bool,
+  ) -> ReadResult<Vec<DataSample<&D>>> {
+    let values: Vec<WithKeyDataSample<&NoKeyWrapper<D>>> =
+      self
+        .keyed_datareader
+        .read_matching(max_samples, read_condition, |d, info| predicate(d, info))?;
+    Ok(
+      values
+        .into_iter()
+        .filter_map(DataSample::<D>::from_with_key_ref)
+        .collect(),
+    )
+  }
+
+  /// Takes samples like [`take`](Self::take), but only those whose value
+  /// satisfies `predicate`. Other samples are left in the DataReader.
+  ///
+  /// See [`with_key::DataReader::take_matching`](crate::with_key::DataReader::take_matching).
+  pub fn take_matching(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    predicate: impl Fn(&D, &SampleInfo) -> bool,
+  ) -> ReadResult<Vec<DataSample<D>>> {
+    let values: Vec<WithKeyDataSample<NoKeyWrapper<D>>> =
+      self
+        .keyed_datareader
+        .take_matching(max_samples, read_condition, |d, info| predicate(d, info))?;
+    Ok(
+      values
+        .into_iter()
+        .filter_map(DataSample::<D>::from_with_key)
+        .collect(),
+    )
+  }
+
   /// Reads next unread sample
   ///
   /// # Examples
@@ -196,6 +253,14 @@ where
     Ok(ds.pop())
   }
 
+  /// Returns samples borrowed by [`read`](Self::read), ending the loan.
+  ///
+  /// See
+  /// [`with_key::DataReader::return_loan`](crate::with_key::DataReader::return_loan).
+  pub fn return_loan(samples: Vec<DataSample<&D>>) {
+    drop(samples);
+  }
+
   /// Takes next unread sample
   ///
   /// # Examples
@@ -226,6 +291,26 @@ where
     Ok(ds.pop())
   }
 
+  /// Reads the oldest NOT_READ sample without blocking, returning a copy of
+  /// it. Returns `Ok(None)` immediately, if there is nothing new.
+  ///
+  /// See [`with_key::DataReader::try_read_one`](crate::with_key::DataReader::try_read_one).
+  pub fn try_read_one(&mut self) -> ReadResult<Option<DataSample<D>>>
+  where
+    D: Clone,
+  {
+    // Disposals have no data in no_key topics, so skip over them.
+    while let Some(ks) = self.keyed_datareader.read_first_not_read()? {
+      if let Some(ds) = DataSample::<D>::from_with_key_ref(ks) {
+        return Ok(Some(DataSample {
+          sample_info: ds.sample_info,
+          value: ds.value.clone(),
+        }));
+      }
+    }
+    Ok(None)
+  }
+
   // Iterator interface
 
   /// Produces an iterator over the currently available NOT_READ samples.
@@ -422,6 +507,152 @@ where
   }
   */
 
+  /// Blocks the calling thread until there is at least one NOT_READ sample
+  /// available, or `timeout` expires.
+  ///
+  /// See [`with_key::DataReader::wait_for_data`](crate::with_key::DataReader::wait_for_data).
+  pub fn wait_for_data(&mut self, timeout: Duration) -> ReadResult<bool> {
+    self.keyed_datareader.wait_for_data(timeout)
+  }
+
+  /// Ranges of sequence numbers that have not been received, per matched
+  /// Writer.
+  ///
+  /// See [`with_key::DataReader::observed_gaps`](crate::with_key::DataReader::observed_gaps).
+  pub fn observed_gaps(&self) -> Vec<(GUID, Vec<Range<SequenceNumber>>)> {
+    self.keyed_datareader.observed_gaps()
+  }
+
+  /// Locators of the matched DataWriters.
+  ///
+  /// See [`with_key::DataReader::remote_writer_locators`](crate::with_key::DataReader::remote_writer_locators).
+  pub fn remote_writer_locators(&self) -> ReadResult<Vec<RemoteLocators>> {
+    self.keyed_datareader.remote_writer_locators()
+  }
+
+  /// Gets the Discovery data of one matched DataWriter.
+  ///
+  /// See [`with_key::DataReader::get_matched_publication_data`](crate::with_key::DataReader::get_matched_publication_data).
+  pub fn get_matched_publication_data(
+    &self,
+    writer_guid: GUID,
+  ) -> Option<PublicationBuiltinTopicData> {
+    self
+      .keyed_datareader
+      .get_matched_publication_data(writer_guid)
+  }
+
+  /// Describes the DataWriter that wrote a sample.
+  ///
+  /// See [`with_key::DataReader::publication_description`](crate::with_key::DataReader::publication_description).
+  pub fn publication_description(&self, publication_handle: GUID) -> Option<EndpointDescription> {
+    self
+      .keyed_datareader
+      .publication_description(publication_handle)
+  }
+
+  /// Pauses or resumes acknowledging received samples to reliable Writers.
+  ///
+  /// See [`with_key::DataReader::set_flow_control_pause`](crate::with_key::DataReader::set_flow_control_pause).
+  pub fn set_flow_control_pause(&self, paused: bool) -> ReadResult<()> {
+    self.keyed_datareader.set_flow_control_pause(paused)
+  }
+
+  /// Pauses receiving samples from the network.
+  ///
+  /// See [`with_key::DataReader::pause`](crate::with_key::DataReader::pause).
+  pub fn pause(&self) -> ReadResult<()> {
+    self.keyed_datareader.pause()
+  }
+
+  /// Resumes receiving samples after [`pause`](Self::pause).
+  ///
+  /// See [`with_key::DataReader::resume`](crate::with_key::DataReader::resume).
+  pub fn resume(&self) -> ReadResult<()> {
+    self.keyed_datareader.resume()
+  }
+
+  /// Writes a human-readable summary of the state of this DataReader.
+  ///
+  /// See [`with_key::DataReader::print_diagnostic`](crate::with_key::DataReader::print_diagnostic).
+  pub fn print_diagnostic(&mut self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+    self.keyed_datareader.print_diagnostic(out)
+  }
+
+  /// Returns the counts of matched Writers, and resets the `count_change`
+  /// fields.
+  ///
+  /// See [`with_key::DataReader::get_subscription_matched_status`](crate::with_key::DataReader::get_subscription_matched_status).
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self.keyed_datareader.get_subscription_matched_status()
+  }
+
+  /// Returns the counts of alive and not alive matched Writers, and resets
+  /// the `count_change` fields.
+  ///
+  /// See [`with_key::DataReader::get_liveliness_changed_status`](crate::with_key::DataReader::get_liveliness_changed_status).
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.keyed_datareader.get_liveliness_changed_status()
+  }
+
+  /// Topic this DataReader reads from.
+  ///
+  /// Topic name, type name and [`TopicKind`](crate::TopicKind) are available
+  /// through the [`TopicDescription`](crate::TopicDescription) trait.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::no_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType {}
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// let data_reader = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// assert_eq!(data_reader.topic(), &topic);
+  /// assert_eq!(data_reader.topic().kind(), TopicKind::NoKey);
+  /// ```
+  pub fn topic(&self) -> &Topic {
+    self.keyed_datareader.topic()
+  }
+
+  /// Subscriber this DataReader belongs to.
+  ///
+  /// See [`with_key::DataReader::subscriber`](crate::with_key::DataReader::subscriber).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::no_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType {}
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// let data_reader = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// assert_eq!(data_reader.subscriber(), &subscriber);
+  /// ```
+  pub fn subscriber(&self) -> &Subscriber {
+    self.keyed_datareader.subscriber()
+  }
+
   /// An async stream for reading the (bare) data samples
   pub fn async_bare_sample_stream(self) -> BareDataReaderStream<D, DA> {
     BareDataReaderStream {
@@ -435,6 +666,16 @@ where
       keyed_stream: self.keyed_datareader.async_sample_stream(),
     }
   }
+
+  /// An async stream of both data samples and status events, in the order
+  /// they occurred.
+  ///
+  /// See [`with_key::DataReader::async_sample_and_event_stream`](crate::with_key::DataReader::async_sample_and_event_stream).
+  pub fn async_sample_and_event_stream(self) -> DataReaderSampleAndEventStream<D, DA> {
+    DataReaderSampleAndEventStream {
+      keyed_stream: self.keyed_datareader.async_sample_and_event_stream(),
+    }
+  }
 }
 
 /// WARNING! UNTESTED
@@ -659,6 +900,84 @@ where
   }
 }
 
+impl<D, DA> DataReaderStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  /// Pauses or resumes acknowledging received samples.
+  ///
+  /// See [`with_key::DataReader::set_flow_control_pause`](crate::with_key::DataReader::set_flow_control_pause).
+  pub fn set_flow_control_pause(&self, paused: bool) -> ReadResult<()> {
+    self.keyed_stream.set_flow_control_pause(paused)
+  }
+
+  /// Forwards samples from this stream until `signal` resolves, after which
+  /// the returned stream terminates.
+  ///
+  /// See [`with_key::DataReaderStream::take_until_signal`](crate::with_key::DataReaderStream::take_until_signal).
+  pub fn take_until_signal<F>(self, signal: F) -> TakeUntil<Self, F>
+  where
+    F: Future<Output = ()>,
+  {
+    self.take_until(signal)
+  }
+
+  /// Converts the value of each sample with `f`, e.g. from the wire type to
+  /// an application domain type.
+  ///
+  /// If `f` fails, the stream produces [`ReadError::Conversion`] carrying the
+  /// error in place of that sample, and continues with the next one, just
+  /// like after a read error.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #[derive(Serialize, Deserialize)]
+  /// struct Celsius {
+  ///   value: f64,
+  /// }
+  /// struct Kelvin(f64);
+  ///
+  /// let topic = domain_participant.create_topic("temperature".to_string(), "Celsius".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// let data_reader = subscriber.create_datareader_no_key::<Celsius, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let kelvin_stream = data_reader
+  ///   .async_sample_stream()
+  ///   .try_map_value(|c: Celsius| {
+  ///     if c.value < -273.15 {
+  ///       Err(format!("{} is below absolute zero", c.value))
+  ///     } else {
+  ///       Ok(Kelvin(c.value + 273.15))
+  ///     }
+  ///   });
+  /// ```
+  pub fn try_map_value<T, E, F>(self, f: F) -> TryMapValue<D, DA, F>
+  where
+    F: FnMut(D) -> Result<T, E>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+  {
+    TryMapValue { stream: self, f }
+  }
+
+  /// Calls `f` with each sample as it passes through the stream, without
+  /// consuming it.
+  ///
+  /// See [`with_key::DataReaderStream::inspect`](crate::with_key::DataReaderStream::inspect).
+  pub fn inspect<F>(self, f: F) -> InspectDataReaderStream<D, DA, F>
+  where
+    F: FnMut(&DataSample<D>),
+  {
+    InspectDataReaderStream { stream: self, f }
+  }
+}
+
 // https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
 impl<D, DA> Unpin for DataReaderStream<D, DA>
 where
@@ -707,6 +1026,161 @@ where
   }
 }
 
+/// Stream returned by [`DataReaderStream::inspect`].
+pub struct InspectDataReaderStream<D: 'static, DA: DeserializerAdapter<D> + 'static, F> {
+  stream: DataReaderStream<D, DA>,
+  f: F,
+}
+
+impl<D, DA, F> InspectDataReaderStream<D, DA, F>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    self.stream.async_event_stream()
+  }
+}
+
+impl<D, DA, F> Stream for InspectDataReaderStream<D, DA, F>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+  F: FnMut(&DataSample<D>) + Unpin,
+{
+  type Item = ReadResult<DataSample<D>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    let poll = Pin::new(&mut this.stream).poll_next(cx);
+    if let Poll::Ready(Some(Ok(sample))) = &poll {
+      (this.f)(sample);
+    }
+    poll
+  }
+}
+
+impl<D, DA, F> FusedStream for InspectDataReaderStream<D, DA, F>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+  F: FnMut(&DataSample<D>) + Unpin,
+{
+  fn is_terminated(&self) -> bool {
+    self.stream.is_terminated()
+  }
+}
+
+/// Stream returned by [`DataReaderStream::try_map_value`].
+pub struct TryMapValue<D: 'static, DA: DeserializerAdapter<D> + 'static, F> {
+  stream: DataReaderStream<D, DA>,
+  f: F,
+}
+
+impl<D, DA, F> TryMapValue<D, DA, F>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    self.stream.async_event_stream()
+  }
+}
+
+impl<D, DA, F, T, E> Stream for TryMapValue<D, DA, F>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+  F: FnMut(D) -> Result<T, E> + Unpin,
+  E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+  type Item = ReadResult<DataSample<T>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    Pin::new(&mut this.stream).poll_next(cx).map(|item| {
+      item.map(|result| {
+        let DataSample { sample_info, value } = result?;
+        match (this.f)(value) {
+          Ok(value) => Ok(DataSample { sample_info, value }),
+          Err(e) => Err(ReadError::Conversion { source: e.into() }),
+        }
+      })
+    })
+  }
+}
+
+impl<D, DA, F, T, E> FusedStream for TryMapValue<D, DA, F>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+  F: FnMut(D) -> Result<T, E> + Unpin,
+  E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+  fn is_terminated(&self) -> bool {
+    self.stream.is_terminated()
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+
+/// Wraps [`with_key::DataReaderSampleAndEventStream`](crate::with_key::DataReaderSampleAndEventStream)
+/// and unwraps [`Sample`](crate::with_key::Sample) and `NoKeyWrapper` on
+/// `poll_next`.
+pub struct DataReaderSampleAndEventStream<
+  D: 'static,
+  DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
+> {
+  keyed_stream: WithKeyDataReaderSampleAndEventStream<NoKeyWrapper<D>, DAWrapper<DA>>,
+}
+
+impl<D, DA> Unpin for DataReaderSampleAndEventStream<D, DA>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+}
+
+impl<D, DA> Stream for DataReaderSampleAndEventStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  type Item = ReadResult<SampleOrEvent<DataSample<D>>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let mut keyed_stream = Pin::new(&mut Pin::into_inner(self).keyed_stream);
+    loop {
+      match keyed_stream.as_mut().poll_next(cx) {
+        Poll::Ready(Some(Err(e))) => break Poll::Ready(Some(Err(e))),
+        Poll::Ready(Some(Ok(SampleOrEvent::Event(e)))) => {
+          break Poll::Ready(Some(Ok(SampleOrEvent::Event(e))))
+        }
+        Poll::Ready(Some(Ok(SampleOrEvent::Sample(d)))) => match d.value() {
+          Sample::Value(_) => match DataSample::<D>::from_with_key(d) {
+            Some(d) => break Poll::Ready(Some(Ok(SampleOrEvent::Sample(d)))),
+            None => break Poll::Ready(None), // This should never happen
+          },
+          // Disposed data is ignored. Loop, because Ready left no waker behind.
+          Sample::Dispose(_) => (),
+        },
+        Poll::Ready(None) => break Poll::Ready(None), // This should never happen
+        Poll::Pending => break Poll::Pending,
+      }
+    } // loop
+  }
+}
+
+impl<D, DA> FusedStream for DataReaderSampleAndEventStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  fn is_terminated(&self) -> bool {
+    false // Never terminate. This means it is always valid to call poll_next().
+  }
+}
+
 // ----------------------------------------------------------------------------------------------------
 // ----------------------------------------------------------------------------------------------------
 
@@ -718,6 +1192,36 @@ pub struct DataReaderEventStream<
   keyed_stream: WithKeyDataReaderEventStream<NoKeyWrapper<D>, DAWrapper<DA>>,
 }
 
+impl<D, DA> DataReaderEventStream<D, DA>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// See [`with_key::DataReaderEventStream::on_subscription_matched`](crate::with_key::DataReaderEventStream::on_subscription_matched).
+  pub fn on_subscription_matched<F>(self, f: F) -> impl FusedFuture<Output = ()>
+  where
+    F: FnMut(GUID, CountWithChange),
+  {
+    self.keyed_stream.on_subscription_matched(f)
+  }
+
+  /// See [`with_key::DataReaderEventStream::on_liveliness_changed`](crate::with_key::DataReaderEventStream::on_liveliness_changed).
+  pub fn on_liveliness_changed<F>(self, f: F) -> impl FusedFuture<Output = ()>
+  where
+    F: FnMut(CountWithChange, CountWithChange),
+  {
+    self.keyed_stream.on_liveliness_changed(f)
+  }
+
+  /// See [`with_key::DataReaderEventStream::on_requested_incompatible_qos`](crate::with_key::DataReaderEventStream::on_requested_incompatible_qos).
+  pub fn on_requested_incompatible_qos<F>(self, f: F) -> impl FusedFuture<Output = ()>
+  where
+    F: FnMut(GUID, QosPolicyId),
+  {
+    self.keyed_stream.on_requested_incompatible_qos(f)
+  }
+}
+
 impl<D, DA> Stream for DataReaderEventStream<D, DA>
 where
   D: 'static,
@@ -739,3 +1243,138 @@ where
     false // Never terminate. This means it is always valid to call poll_next().
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use futures::FutureExt;
+
+  use super::*;
+  use crate::{
+    dds::{participant::DomainParticipant, qos::policy},
+    serialization::CDRSerializerAdapter,
+    test::random_data::RandomData,
+    TopicKind,
+  };
+
+  #[test]
+  fn try_map_value_reports_conversion_errors() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    // Samples written before matching are delivered once the reader matches.
+    let qos = QosPolicies::builder()
+      .durability(policy::Durability::TransientLocal)
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let topic = dp
+      .create_topic(
+        "dr try_map_value".to_string(),
+        "try_map_value test".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap()
+      .async_sample_stream()
+      .try_map_value(|d: RandomData| u8::try_from(d.a));
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    for a in [1, 1000, 2] {
+      writer
+        .write(
+          RandomData {
+            a,
+            b: String::new(),
+          },
+          None,
+        )
+        .unwrap();
+    }
+
+    let mut items = Vec::new();
+    let start = std::time::Instant::now();
+    while items.len() < 3 && start.elapsed() < std::time::Duration::from_secs(10) {
+      while let Some(Some(item)) = stream.next().now_or_never() {
+        items.push(item);
+      }
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    assert_eq!(items.len(), 3, "{items:?}");
+    assert_eq!(*items[0].as_ref().unwrap().value(), 1);
+    match &items[1] {
+      Err(ReadError::Conversion { source }) => {
+        assert!(source.is::<std::num::TryFromIntError>(), "{source:?}");
+      }
+      other => panic!("Expected a conversion error, got {other:?}"),
+    }
+    assert_eq!(*items[2].as_ref().unwrap().value(), 2);
+  }
+
+  #[test]
+  fn inspect_sees_samples_without_consuming() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::builder()
+      .durability(policy::Durability::TransientLocal)
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let topic = dp
+      .create_topic(
+        "dr inspect".to_string(),
+        "inspect test".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let inspected = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let inspected_in_stream = inspected.clone();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_no_key::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap()
+      .async_sample_stream()
+      .inspect(move |s| inspected_in_stream.lock().unwrap().push(s.value().a));
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    for a in [1, 2, 3] {
+      writer
+        .write(
+          RandomData {
+            a,
+            b: String::new(),
+          },
+          None,
+        )
+        .unwrap();
+    }
+
+    let mut items = Vec::new();
+    let start = std::time::Instant::now();
+    while items.len() < 3 && start.elapsed() < std::time::Duration::from_secs(10) {
+      while let Some(Some(item)) = stream.next().now_or_never() {
+        items.push(item.unwrap().into_value().a);
+      }
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    assert_eq!(items, vec![1, 2, 3]);
+    assert_eq!(*inspected.lock().unwrap(), items);
+  }
+}
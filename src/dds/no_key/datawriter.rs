@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use futures::Future;
 use mio_06::Evented;
 
 use crate::{
@@ -8,13 +9,18 @@ use crate::{
     pubsub::Publisher,
     qos::{HasQoSPolicy, QosPolicies},
     result::{unwrap_no_key_write_error, WriteResult},
-    statusevents::{DataWriterStatus, StatusReceiverStream},
+    statusevents::{
+      DataWriterStatus, HistoryCacheStatus, OfferedIncompatibleQosStatus, PublicationMatchedStatus,
+      RemoteLocators, StatusReceiverStream,
+    },
     topic::Topic,
     with_key::datawriter as datawriter_with_key,
   },
   discovery::sedp_messages::SubscriptionBuiltinTopicData,
   serialization::CDRSerializerAdapter,
-  structure::{entity::RTPSEntity, rpc::SampleIdentity, time::Timestamp},
+  structure::{
+    entity::RTPSEntity, rpc::SampleIdentity, sequence_number::SequenceNumber, time::Timestamp,
+  },
   StatusEvented, GUID,
 };
 use super::wrappers::{NoKeyWrapper, SAWrapper};
@@ -128,6 +134,24 @@ where
   pub fn wait_for_acknowledgments(&self, max_wait: Duration) -> WriteResult<bool, ()> {
     self.keyed_datawriter.wait_for_acknowledgments(max_wait)
   }
+
+  /// Locators of the matched DataReaders. See
+  /// [`with_key::DataWriter::remote_reader_locators`](crate::with_key::DataWriter::remote_reader_locators).
+  pub fn remote_reader_locators(&self) -> WriteResult<Vec<RemoteLocators>, ()> {
+    self.keyed_datawriter.remote_reader_locators()
+  }
+
+  /// Size of the history cache. See
+  /// [`with_key::DataWriter::history_cache_status`](crate::with_key::DataWriter::history_cache_status).
+  pub fn history_cache_status(&self) -> WriteResult<HistoryCacheStatus, ()> {
+    self.keyed_datawriter.history_cache_status()
+  }
+
+  /// Writes a human-readable summary of the state of this DataWriter. See
+  /// [`with_key::DataWriter::print_diagnostic`](crate::with_key::DataWriter::print_diagnostic).
+  pub fn print_diagnostic(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+    self.keyed_datawriter.print_diagnostic(out)
+  }
   /*
   // status queries
   /// Unimplemented. <b>Do not use</b>.
@@ -189,67 +213,51 @@ where
   pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
     self.keyed_datawriter.get_offered_deadline_missed_status()
   }
+  */
 
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
-  ///
-  // TODO: enable run when implemented
-  /// ```no_run
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::no_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// # #[derive(Serialize, Deserialize)]
-  /// # struct SomeType {}
-  /// #
-  /// // NoKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// Returns the QoS incompatibility counts, and resets the `count_change`
+  /// field.
   ///
-  /// if let Ok(status) = data_writer.get_offered_incompatible_qos_status() {
-  ///   // Do something
-  /// }
-  /// ```
-  pub fn get_offered_incompatible_qos_status(&self) -> Result<OfferedIncompatibleQosStatus> {
+  /// See [`with_key::DataWriter::get_offered_incompatible_qos_status`](crate::with_key::DataWriter::get_offered_incompatible_qos_status).
+  pub fn get_offered_incompatible_qos_status(&self) -> OfferedIncompatibleQosStatus {
     self.keyed_datawriter.get_offered_incompatible_qos_status()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
+  /// Reports the acknowledgements of each sample written after this call.
   ///
-  // TODO: enable run when implemented
-  /// ```no_run
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::no_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// # #[derive(Serialize, Deserialize)]
-  /// # struct SomeType {}
-  /// #
-  /// // NoKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
+  /// See [`with_key::DataWriter::ack_listener`](crate::with_key::DataWriter::ack_listener).
+  pub fn ack_listener(
+    &self,
+    quorum: datawriter_with_key::AckQuorum,
+    timeout: Duration,
+    callback: impl Fn(SequenceNumber, datawriter_with_key::AckStatus) + Send + 'static,
+  ) -> WriteResult<(), ()> {
+    self
+      .keyed_datawriter
+      .ack_listener(quorum, timeout, callback)
+  }
+
+  /// Returns the counts of matched Readers, and resets the `count_change`
+  /// fields.
   ///
-  /// if let Ok(status) = data_writer.get_publication_matched_status() {
-  ///   // Do something
-  /// }
-  /// ```
-  pub fn get_publication_matched_status(&self) -> Result<PublicationMatchedStatus> {
+  /// See [`with_key::DataWriter::get_publication_matched_status`](crate::with_key::DataWriter::get_publication_matched_status).
+  pub fn get_publication_matched_status(&self) -> PublicationMatchedStatus {
     self.keyed_datawriter.get_publication_matched_status()
   }
-  */
+
+  /// Makes write operations fail with `WriteError::NoMatchedReaders` when no
+  /// DataReader is matched.
+  ///
+  /// See [`with_key::DataWriter::set_require_matched_readers`](crate::with_key::DataWriter::set_require_matched_readers).
+  pub fn set_require_matched_readers(&self, require: bool) {
+    self.keyed_datawriter.set_require_matched_readers(require);
+  }
+
+  /// See [`set_require_matched_readers`](Self::set_require_matched_readers).
+  pub fn require_matched_readers(&self) -> bool {
+    self.keyed_datawriter.require_matched_readers()
+  }
+
   /// Topic this DataWriter is connected to.
   ///
   /// # Examples
@@ -331,6 +339,15 @@ where
     self.keyed_datawriter.assert_liveliness()
   }
 
+  /// Asserts liveliness periodically in a background thread. See
+  /// [`with_key::DataWriter::assert_liveliness_periodic`](crate::with_key::DataWriter::assert_liveliness_periodic).
+  pub fn assert_liveliness_periodic(
+    &self,
+    interval: Duration,
+  ) -> datawriter_with_key::LivelinessAssertionHandle {
+    self.keyed_datawriter.assert_liveliness_periodic(interval)
+  }
+
   /// Unimplemented. <b>Do not use</b>.
   ///
   /// # Examples
@@ -359,6 +376,18 @@ where
   pub fn get_matched_subscriptions(&self) -> Vec<SubscriptionBuiltinTopicData> {
     self.keyed_datawriter.get_matched_subscriptions()
   }
+
+  /// Gets the Discovery data of one matched DataReader.
+  ///
+  /// See [`with_key::DataWriter::get_matched_subscription_data`](crate::with_key::DataWriter::get_matched_subscription_data).
+  pub fn get_matched_subscription_data(
+    &self,
+    reader_guid: GUID,
+  ) -> Option<SubscriptionBuiltinTopicData> {
+    self
+      .keyed_datawriter
+      .get_matched_subscription_data(reader_guid)
+  }
   /*
   /// Gets mio receiver for all implemented Status changes
   ///
@@ -463,6 +492,14 @@ where
   pub async fn async_wait_for_acknowledgments(&self) -> WriteResult<bool, ()> {
     self.keyed_datawriter.async_wait_for_acknowledgments().await
   } // fn
+
+  /// Returns a future that completes when all samples written so far have been
+  /// handed over to the network.
+  ///
+  /// See [`with_key::DataWriter::drain`](crate::with_key::DataWriter::drain).
+  pub fn drain(&self) -> impl Future<Output = ()> {
+    self.keyed_datawriter.drain()
+  }
 } // impl
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use std::{
   io,
+  ops::Range,
   pin::Pin,
   task::{Context, Poll, Waker},
 };
@@ -12,13 +13,16 @@ use crate::{
   dds::{
     adapters::no_key::*,
     no_key::{datasample::DeserializedCacheChange, wrappers::DecodeWrapper},
+    pubsub::Subscriber,
     qos::*,
     result::ReadResult,
     statusevents::*,
+    topic::Topic,
     with_key,
   },
+  discovery::sedp_messages::PublicationBuiltinTopicData,
   serialization::CDRDeserializerAdapter,
-  structure::entity::RTPSEntity,
+  structure::{duration::Duration, entity::RTPSEntity, sequence_number::SequenceNumber},
   GUID,
 };
 use super::wrappers::{DAWrapper, NoKeyWrapper};
@@ -79,6 +83,89 @@ where
     }
   }
 
+  /// Blocks the calling thread until there is unread data available, or
+  /// `timeout` expires.
+  ///
+  /// See [`with_key::SimpleDataReader::wait_for_data`](crate::with_key::SimpleDataReader::wait_for_data).
+  pub fn wait_for_data(&self, timeout: Duration) -> ReadResult<bool> {
+    self.keyed_simpledatareader.wait_for_data(timeout)
+  }
+
+  /// Ranges of sequence numbers that have not been received, per matched
+  /// Writer.
+  ///
+  /// See [`with_key::SimpleDataReader::observed_gaps`](crate::with_key::SimpleDataReader::observed_gaps).
+  pub fn observed_gaps(&self) -> Vec<(GUID, Vec<Range<SequenceNumber>>)> {
+    self.keyed_simpledatareader.observed_gaps()
+  }
+
+  /// Locators of the matched DataWriters.
+  ///
+  /// See [`with_key::SimpleDataReader::remote_writer_locators`](crate::with_key::SimpleDataReader::remote_writer_locators).
+  pub fn remote_writer_locators(&self) -> ReadResult<Vec<RemoteLocators>> {
+    self.keyed_simpledatareader.remote_writer_locators()
+  }
+
+  /// Gets the Discovery data of one matched DataWriter.
+  ///
+  /// See [`with_key::DataReader::get_matched_publication_data`](crate::with_key::DataReader::get_matched_publication_data).
+  pub fn get_matched_publication_data(
+    &self,
+    writer_guid: GUID,
+  ) -> Option<PublicationBuiltinTopicData> {
+    self
+      .keyed_simpledatareader
+      .get_matched_publication_data(writer_guid)
+  }
+
+  /// Describes the DataWriter identified by a publication handle.
+  ///
+  /// See [`with_key::DataReader::publication_description`](crate::with_key::DataReader::publication_description).
+  pub fn publication_description(&self, publication_handle: GUID) -> Option<EndpointDescription> {
+    self
+      .keyed_simpledatareader
+      .publication_description(publication_handle)
+  }
+
+  /// Pauses or resumes acknowledging received samples to reliable Writers.
+  ///
+  /// See [`with_key::DataReader::set_flow_control_pause`](crate::with_key::DataReader::set_flow_control_pause).
+  pub fn set_flow_control_pause(&self, paused: bool) -> ReadResult<()> {
+    self.keyed_simpledatareader.set_flow_control_pause(paused)
+  }
+
+  /// Pauses or resumes receiving samples from the network.
+  ///
+  /// See [`with_key::DataReader::pause`](crate::with_key::DataReader::pause).
+  pub fn set_delivery_pause(&self, paused: bool) -> ReadResult<()> {
+    self.keyed_simpledatareader.set_delivery_pause(paused)
+  }
+
+  /// Writes a human-readable summary of the RTPS state of this Reader.
+  ///
+  /// See [`with_key::SimpleDataReader::print_diagnostic`](crate::with_key::SimpleDataReader::print_diagnostic).
+  pub fn print_diagnostic(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+    self.keyed_simpledatareader.print_diagnostic(out)
+  }
+
+  /// Returns the counts of matched Writers, and resets the `count_change`
+  /// fields.
+  ///
+  /// See [`with_key::SimpleDataReader::get_subscription_matched_status`](crate::with_key::SimpleDataReader::get_subscription_matched_status).
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self
+      .keyed_simpledatareader
+      .get_subscription_matched_status()
+  }
+
+  /// Returns the counts of alive and not alive matched Writers, and resets
+  /// the `count_change` fields.
+  ///
+  /// See [`with_key::SimpleDataReader::get_liveliness_changed_status`](crate::with_key::SimpleDataReader::get_liveliness_changed_status).
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.keyed_simpledatareader.get_liveliness_changed_status()
+  }
+
   pub fn qos(&self) -> &QosPolicies {
     self.keyed_simpledatareader.qos()
   }
@@ -87,6 +174,16 @@ where
     self.keyed_simpledatareader.guid()
   }
 
+  /// See [`with_key::SimpleDataReader::topic`](crate::with_key::SimpleDataReader::topic).
+  pub fn topic(&self) -> &Topic {
+    self.keyed_simpledatareader.topic()
+  }
+
+  /// See [`with_key::SimpleDataReader::subscriber`](crate::with_key::SimpleDataReader::subscriber).
+  pub fn subscriber(&self) -> &Subscriber {
+    self.keyed_simpledatareader.subscriber()
+  }
+
   pub fn as_async_stream(
     &self,
   ) -> impl FusedStream<Item = ReadResult<DeserializedCacheChange<D>>> + '_
@@ -124,11 +221,10 @@ where
       })
   }
 
-  // pub fn as_simple_data_reader_event_stream(
-  //   &self,
-  // ) -> impl Stream<Item = ReadResult<DataReaderStatus>> + '_ { self
-  //   .keyed_simpledatareader .as_simple_data_reader_event_stream()
-  // }
+  /// See [`with_key::SimpleDataReader::as_async_event_stream`](crate::with_key::SimpleDataReader::as_async_event_stream).
+  pub fn as_async_event_stream(&self) -> SimpleDataReaderEventStream<'_, D, DA> {
+    SimpleDataReaderEventStream::from_keyed(self.keyed_simpledatareader.as_async_event_stream())
+  }
 }
 
 // This is  not part of DDS spec. We implement mio Eventd so that the
@@ -215,9 +311,7 @@ where
   }
 
   fn as_async_status_stream(&'a self) -> SimpleDataReaderEventStream<'a, D, DA> {
-    SimpleDataReaderEventStream {
-      keyed_event_stream: self.keyed_simpledatareader.as_async_status_stream(),
-    }
+    self.as_async_event_stream()
   }
 
   fn try_recv_status(&self) -> Option<DataReaderStatus> {
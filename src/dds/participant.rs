@@ -7,7 +7,6 @@ use std::{
   pin::Pin,
   sync::{atomic, Arc, Mutex, RwLock, Weak},
   task::{Context, Poll},
-  thread,
   thread::JoinHandle,
   time::{Duration, Instant},
 };
@@ -20,30 +19,39 @@ use futures::stream::{FusedStream, Stream};
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_out_of_resources, create_error_poisoned,
+  create_error_bad_parameter, create_error_out_of_resources, create_error_poisoned,
   dds::{
+    discovery_snapshot::DiscoverySnapshot,
+    health::{HealthMonitor, MonitoredThread, ParticipantHealth},
     pubsub::*,
     qos::*,
     result::*,
+    statistics::ParticipantStatistics,
     statusevents::{
       sync_status_channel, DomainParticipantStatusEvent, StatusChannelReceiver, StatusChannelSender,
     },
+    thread_options::{ThreadOptions, ThreadPriority, ThreadSpawnOptions},
     topic::*,
     typedesc::TypeDesc,
   },
   discovery::{
-    discovery::{Discovery, DiscoveryCommand},
-    discovery_db::DiscoveryDB,
-    sedp_messages::DiscoveredTopicData,
+    builtin_endpoint::BuiltinEndpointSet,
+    config::DiscoveryConfig,
+    discovery::{Discovery, DiscoveryCommand, ParticipantMessageObservers},
+    discovery_db::{discovery_db_read, DiscoveryDB},
+    sedp_messages::{DiscoveredTopicData, ParticipantMessageData},
   },
-  network::{constant::*, udp_listener::UDPListener},
+  messages::vendor_id::VendorId,
+  network::{constant::*, udp_listener::UDPListener, util::get_local_unicast_locators},
   rtps::{
     constant::*,
     dp_event_loop::{DPEventLoop, DomainInfo, EventLoopCommand},
     reader::*,
     writer::WriterIngredients,
   },
-  structure::{dds_cache::DDSCache, entity::RTPSEntity, guid::*, locator::Locator},
+  structure::{
+    dds_cache::DDSCache, entity::RTPSEntity, guid::*, locator::Locator, memory_budget::MemoryUsage,
+  },
   StatusEvented,
 };
 #[cfg(feature = "security")]
@@ -66,6 +74,30 @@ pub struct DomainParticipantBuilder {
   which interfaces the DomainParticipant will talk to. */
   only_networks: Option<Vec<String>>, // if specified, run RTPS only over these interfaces
 
+  guid_prefix: Option<GuidPrefix>, // if not specified, a random one is generated
+
+  participant_id: Option<u16>, // if not specified, the first free one is used
+
+  builtin_endpoint_set: u32, // as announced in SPDP
+
+  memory_limit: Option<usize>, // bytes. If not specified, there is no limit.
+
+  always_include_source_timestamp: bool,
+
+  max_message_size: Option<usize>, // bytes. If not specified, no limit is applied.
+
+  initial_acknack_delay: Option<Duration>, // None: no ACKNACK on match
+
+  event_loop_thread: ThreadOptions,
+
+  spawn_options: ThreadSpawnOptions,
+
+  manual_event_loop: bool,
+
+  discovery_config: DiscoveryConfig,
+
+  health_topic_interval: Option<Duration>,
+
   #[cfg(feature = "security")]
   security_plugins: Option<SecurityPlugins>,
   #[cfg(feature = "security")]
@@ -77,6 +109,18 @@ impl DomainParticipantBuilder {
     DomainParticipantBuilder {
       domain_id,
       only_networks: None,
+      guid_prefix: None,
+      participant_id: None,
+      builtin_endpoint_set: BuiltinEndpointSet::DEFAULT_SET,
+      memory_limit: None,
+      always_include_source_timestamp: false,
+      max_message_size: None,
+      initial_acknack_delay: Some(INITIAL_ACKNACK_DELAY),
+      event_loop_thread: ThreadOptions::default(),
+      spawn_options: ThreadSpawnOptions::default(),
+      manual_event_loop: false,
+      discovery_config: DiscoveryConfig::default(),
+      health_topic_interval: None,
       #[cfg(feature = "security")]
       security_plugins: None,
       #[cfg(feature = "security")]
@@ -84,6 +128,342 @@ impl DomainParticipantBuilder {
     }
   }
 
+  /// Use a GuidPrefix derived from `seed` instead of a random one.
+  ///
+  /// This gives the DomainParticipant a stable identity across restarts, e.g.
+  /// when the seed is a configured node id. Remote participants then recognize
+  /// a restarted participant as the one they already knew. See
+  /// [`GuidPrefix::from_seed`].
+  ///
+  /// Two DomainParticipants with the same seed must not be running on the same
+  /// Domain at the same time.
+  ///
+  /// If security is enabled, the GUID is further adjusted by the
+  /// Authentication plugin (`validate_local_identity`). The builtin plugin
+  /// derives the adjusted GUID by hashing the certificate subject name and the
+  /// candidate GUID, so the result is also stable, but does not start with our
+  /// vendor id.
+  #[must_use]
+  pub fn guid_prefix_seed(mut self, seed: &[u8]) -> Self {
+    self.guid_prefix = Some(GuidPrefix::from_seed(seed));
+    self
+  }
+
+  /// Use an explicitly given GuidPrefix instead of a random one.
+  ///
+  /// The prefix must start with our vendor id, as required by the RTPS
+  /// specification. Otherwise [`build`](Self::build) fails with
+  /// [`CreateError::BadParameter`]. The same caveats apply as in
+  /// [`guid_prefix_seed`](Self::guid_prefix_seed).
+  #[must_use]
+  pub fn guid_prefix(mut self, guid_prefix: GuidPrefix) -> Self {
+    self.guid_prefix = Some(guid_prefix);
+    self
+  }
+
+  /// Use the given RTPS participant id, instead of the first free one.
+  ///
+  /// The participant id selects the unicast ports of the DomainParticipant,
+  /// see RTPS spec v2.5 Section 9.6.2.3 Default Port Numbers. It
+  /// differentiates DomainParticipants of the same Domain on the same host,
+  /// so a fixed id makes the ports predictable, e.g. for firewall rules or
+  /// static peer configuration.
+  ///
+  /// [`build`](Self::build) fails with [`CreateError::BadParameter`] if the
+  /// id is 120 or more, and with [`CreateError::OutOfResources`] if the
+  /// discovery port of the id is already in use.
+  #[must_use]
+  pub fn participant_id(mut self, participant_id: u16) -> Self {
+    self.participant_id = Some(participant_id);
+    self
+  }
+
+  /// Set the built-in endpoints announced to remote participants in SPDP
+  /// (PID_BUILTIN_ENDPOINT_SET), as a combination of the
+  /// [`BuiltinEndpointSet`] flags. Remote participants match their built-in
+  /// endpoints only with those announced here.
+  ///
+  /// The default announces all the non-secure built-in endpoints,
+  /// [`BuiltinEndpointSet::DEFAULT_SET`]. Leaving out e.g.
+  /// [`TOPICS_ANNOUNCER`](BuiltinEndpointSet::TOPICS_ANNOUNCER) can help with
+  /// implementations that mishandle the optional endpoints. If security is
+  /// enabled, the secure built-in endpoints are added to the set.
+  ///
+  /// The set must contain
+  /// [`PARTICIPANT_ANNOUNCER`](BuiltinEndpointSet::PARTICIPANT_ANNOUNCER) and
+  /// [`PARTICIPANT_DETECTOR`](BuiltinEndpointSet::PARTICIPANT_DETECTOR), or
+  /// [`build`](Self::build) fails with [`CreateError::BadParameter`].
+  #[must_use]
+  pub fn builtin_endpoint_set(mut self, flags: u32) -> Self {
+    self.builtin_endpoint_set = flags;
+    self
+  }
+
+  /// How often the DomainParticipant announces itself in SPDP. Default 10 s.
+  ///
+  /// This is a shortcut for
+  /// [`DiscoveryConfig::spdp_announcement_period`], and the announced lease
+  /// duration is five times the period. Remote participants consider us
+  /// lost, if they hear nothing during the lease duration. Note that a later
+  /// [`discovery_config`](Self::discovery_config) call replaces the period.
+  #[must_use]
+  pub fn spdp_heartbeat_period(mut self, period: Duration) -> Self {
+    self.discovery_config.spdp_announcement_period = period;
+    self
+  }
+
+  /// Limit the memory used for sample data to approximately `bytes`.
+  ///
+  /// The limit covers samples held by all Readers and Writers of the
+  /// DomainParticipant, including the built-in Discovery ones, and partially
+  /// received fragmented samples. Accounting is approximate: payload sizes
+  /// and per-sample bookkeeping are counted, but e.g. socket buffers are not.
+  ///
+  /// When the limit is reached:
+  /// 1. The oldest samples in the reader caches of Topics whose local
+  ///    DataReaders all have BestEffort
+  ///    [`Reliability`](crate::policy::Reliability) are evicted, even if the
+  ///    DataReaders have not taken them yet. The DataReaders get a
+  ///    [`DataReaderStatus::SampleLost`](crate::DataReaderStatus::SampleLost)
+  ///    event for them. Built-in Discovery topics are not evicted.
+  /// 2. If that does not make room, `DataWriter` write and dispose operations
+  ///    fail with [`WriteError::WouldBlock`]. The sample is returned to the
+  ///    caller.
+  /// 3. Likewise, received samples are dropped, and the DataReader gets a
+  ///    [`DataReaderStatus::SampleRejected`](crate::DataReaderStatus::SampleRejected)
+  ///    event with reason
+  ///    [`ByMemoryLimit`](crate::dds::statusevents::SampleRejectedStatusKind::ByMemoryLimit).
+  ///    A Reliable DataReader does not get another chance to receive a
+  ///    rejected sample.
+  ///
+  /// Reaching the limit is reported as
+  /// [`DomainParticipantStatusEvent::MemoryLimitReached`].
+  ///
+  /// The limit is a safety net. The History and ResourceLimits QoS policies
+  /// remain the primary means to bound memory use. Size these so that the
+  /// normal working set fits within the limit.
+  #[must_use]
+  pub fn memory_limit(mut self, bytes: usize) -> Self {
+    self.memory_limit = Some(bytes);
+    self
+  }
+
+  /// Give every sample a source timestamp, even if the application does not
+  /// provide one. The default is `false`.
+  ///
+  /// If enabled, DataWriters stamp samples written without an explicit
+  /// timestamp with the current time, so that every DATA submessage is
+  /// preceded by an `INFO_TS` submessage. Some implementations expect this,
+  /// e.g. when using [`DestinationOrder::BySourceTimeStamp`](policy::DestinationOrder).
+  /// The timestamp is also visible to local DataReaders as
+  /// [`SampleInfo::source_timestamp`](crate::SampleInfo::source_timestamp).
+  #[must_use]
+  pub fn always_include_source_timestamp(mut self, always: bool) -> Self {
+    self.always_include_source_timestamp = always;
+    self
+  }
+
+  /// Keep RTPS messages sent by DataWriters within `bytes`, e.g. the path MTU
+  /// minus IP and UDP headers. By default no limit is applied.
+  ///
+  /// Large samples are sent as DATA_FRAG submessages small enough to fit, and
+  /// messages carrying several submessages are split. If RTPS message
+  /// protection is enabled, the limit applies to the protected message, i.e.
+  /// the overhead reported by the Cryptographic plugin is accounted for. This
+  /// avoids IP fragmentation, which multiplies packet loss on lossy links.
+  ///
+  /// The limit must be at least 512 bytes, or [`build`](Self::build) fails
+  /// with [`CreateError::BadParameter`].
+  #[must_use]
+  pub fn max_message_size(mut self, bytes: usize) -> Self {
+    self.max_message_size = Some(bytes);
+    self
+  }
+
+  /// How long a reliable DataReader waits after matching a remote DataWriter
+  /// before sending it a pre-emptive ACKNACK. The ACKNACK asks the DataWriter
+  /// for its available history right away, so that a late joining
+  /// DataReader gets e.g. TransientLocal samples without waiting for the next
+  /// HEARTBEAT. The delay gives the DataWriter time to match the DataReader
+  /// first. Default 10 ms.
+  ///
+  /// With `None`, pre-emptive ACKNACKs are only sent periodically, to Writers
+  /// that have not sent anything yet.
+  #[must_use]
+  pub fn initial_acknack_delay(mut self, delay: Option<Duration>) -> Self {
+    self.initial_acknack_delay = delay;
+    self
+  }
+
+  /// Name of the event loop thread, which runs the RTPS Readers and Writers
+  /// and all network I/O. The default is
+  /// "RustDDS Participant {participant_id} event loop".
+  #[must_use]
+  pub fn thread_name(mut self, name: String) -> Self {
+    self.event_loop_thread.name = Some(name);
+    self
+  }
+
+  /// Run the event loop thread with a real-time scheduling priority.
+  ///
+  /// This is supported on Unix-like platforms. If the priority cannot be set,
+  /// e.g. due to missing privileges, a warning is logged and the thread runs
+  /// with the default priority.
+  #[must_use]
+  pub fn thread_priority(mut self, priority: ThreadPriority) -> Self {
+    self.event_loop_thread.priority = Some(priority);
+    self
+  }
+
+  /// Restrict the event loop thread to run only on the given CPUs, numbered
+  /// from 0.
+  ///
+  /// This is supported on Linux. If the affinity cannot be set, a warning is
+  /// logged and the thread may run on any CPU.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::{DomainParticipantBuilder, ThreadPriority};
+  /// let domain_participant = DomainParticipantBuilder::new(0)
+  ///   .thread_name("dds".to_string())
+  ///   .thread_priority(ThreadPriority::Fifo(50))
+  ///   .thread_affinity(vec![0])
+  ///   .build()
+  ///   .unwrap();
+  /// ```
+  #[must_use]
+  pub fn thread_affinity(mut self, cpus: Vec<usize>) -> Self {
+    self.event_loop_thread.affinity = Some(cpus);
+    self
+  }
+
+  /// Name the background threads "{prefix} event loop" and
+  /// "{prefix} discovery". An explicit [`thread_name`](Self::thread_name)
+  /// still applies to the event loop thread.
+  ///
+  /// Note that Linux truncates thread names to 15 bytes.
+  #[must_use]
+  pub fn thread_name_prefix(mut self, prefix: String) -> Self {
+    self.spawn_options.name_prefix = Some(prefix);
+    self
+  }
+
+  /// Stack size, in bytes, of the background threads. The default is that
+  /// of [`std::thread::Builder`].
+  #[must_use]
+  pub fn thread_stack_size(mut self, bytes: usize) -> Self {
+    self.spawn_options.stack_size = Some(bytes);
+    self
+  }
+
+  /// Call `on_start` in each background thread when it starts, with the name
+  /// of the thread.
+  ///
+  /// This lets the application set scheduling policy, affinity, or anything
+  /// else that must be done from within the thread, with the crate of its
+  /// choice. It is called after [`thread_priority`](Self::thread_priority)
+  /// and [`thread_affinity`](Self::thread_affinity) have been applied, and
+  /// before the thread starts its work.
+  ///
+  /// The threads are the event loop and Discovery, unless
+  /// [`manual_event_loop`](Self::manual_event_loop) is set, in which case
+  /// there are none. See [`DomainParticipant::thread_names`]. Helper threads
+  /// for timers, and those started by individual DataReaders and DataWriters,
+  /// are not included.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipantBuilder;
+  /// let domain_participant = DomainParticipantBuilder::new(0)
+  ///   .thread_name_prefix("dds".to_string())
+  ///   .on_thread_start(|name| {
+  ///     println!("Started {name}");
+  ///     // e.g. set SCHED_FIFO and pin to an isolated core here
+  ///   })
+  ///   .build()
+  ///   .unwrap();
+  /// assert_eq!(
+  ///   domain_participant.thread_names(),
+  ///   vec!["dds event loop".to_string(), "dds discovery".to_string()]
+  /// );
+  /// ```
+  #[must_use]
+  pub fn on_thread_start(mut self, on_start: impl Fn(&str) + Send + Sync + 'static) -> Self {
+    self.spawn_options.on_start = Some(Arc::new(on_start));
+    self
+  }
+
+  /// Run the event loop and Discovery in the application thread, instead of
+  /// starting background threads for them. The default is `false`.
+  ///
+  /// Then the application must call [`DomainParticipant::poll_timeout`] or
+  /// [`DomainParticipant::run_once`] regularly, e.g. once per frame or from
+  /// its own event loop. Nothing is sent or received, timers do not fire, and
+  /// remote participants are not discovered in between. Discovery expects to
+  /// run at least every few hundred milliseconds.
+  ///
+  /// Operations that wait for the event loop, such as
+  /// [`wait_for_acknowledgments`](crate::no_key::DataWriter::wait_for_acknowledgments)
+  /// or a Reliable write into a full history, cannot make progress while they
+  /// block the application thread. They return when their timeout expires.
+  ///
+  /// The event loop thread options, such as
+  /// [`thread_name`](Self::thread_name), have no effect in this mode. Note
+  /// that timers still use helper threads internally.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use std::time::Duration;
+  /// # use rustdds::DomainParticipantBuilder;
+  /// let domain_participant = DomainParticipantBuilder::new(0)
+  ///   .manual_event_loop(true)
+  ///   .build()
+  ///   .unwrap();
+  /// for _frame in 0..10 {
+  ///   // ... application work ...
+  ///   domain_participant.poll_timeout(Duration::from_millis(10));
+  /// }
+  /// ```
+  #[must_use]
+  pub fn manual_event_loop(mut self, manual: bool) -> Self {
+    self.manual_event_loop = manual;
+    self
+  }
+
+  /// Tune the built-in Discovery endpoints, e.g. for a very large domain or a
+  /// small embedded system. See [`DiscoveryConfig`] for the trade-offs.
+  ///
+  /// The values are checked in [`build`](Self::build), which fails with
+  /// [`CreateError::BadParameter`] if they are invalid.
+  #[must_use]
+  pub fn discovery_config(mut self, config: DiscoveryConfig) -> Self {
+    self.discovery_config = config;
+    self
+  }
+
+  /// Publish a [`ParticipantHealthSample`](crate::ParticipantHealthSample)
+  /// every `interval` on the topic
+  /// [`ParticipantHealthSample::TOPIC_NAME`](crate::ParticipantHealthSample::TOPIC_NAME).
+  ///
+  /// This lets a monitoring application observe the liveness of
+  /// participants over DDS, without access to their processes. The topic is
+  /// published with the normal DataWriter machinery, so it is visible in
+  /// Discovery like any user topic. Subscribe with
+  /// [`ParticipantHealthSample::qos`](crate::ParticipantHealthSample::qos) to
+  /// get the latest sample of each
+  /// participant immediately.
+  ///
+  /// [`build`](Self::build) fails with [`CreateError::BadParameter`] if
+  /// `interval` is zero.
+  #[must_use]
+  pub fn enable_health_topic(mut self, interval: Duration) -> Self {
+    self.health_topic_interval = Some(interval);
+    self
+  }
+
   #[cfg(feature = "security")]
   /// Low-level security configuration, which allows supplying custom plugins.
   pub fn security(
@@ -100,8 +480,11 @@ impl DomainParticipantBuilder {
 
   #[cfg(feature = "security")]
   /// Easier way to configure security.
-  pub fn builtin_security(mut self, configs: DomainParticipantSecurityConfigFiles) -> Self {
-    let auth = Box::new(security::AuthenticationBuiltin::new());
+  pub fn builtin_security(mut self, mut configs: DomainParticipantSecurityConfigFiles) -> Self {
+    let auth = Box::new(match configs.secure_random_source.take() {
+      Some(random_source) => security::AuthenticationBuiltin::with_random_source(random_source),
+      None => security::AuthenticationBuiltin::new(),
+    });
     let access = Box::new(security::AccessControlBuiltin::new());
     let crypto = Box::new(security::CryptographicBuiltin::new());
     self.security(auth, access, crypto, configs.into_property_policy());
@@ -109,6 +492,33 @@ impl DomainParticipantBuilder {
   }
 
   pub fn build(#[allow(unused_mut)] mut self) -> CreateResult<DomainParticipant> {
+    self.discovery_config.validate()?;
+    if self.health_topic_interval == Some(Duration::ZERO) {
+      return create_error_bad_parameter!("Health topic interval must be positive.");
+    }
+    if let Some(participant_id) = self.participant_id {
+      if participant_id >= PARTICIPANT_ID_LIMIT {
+        return create_error_bad_parameter!(
+          "Participant id {participant_id} is not less than {PARTICIPANT_ID_LIMIT}."
+        );
+      }
+    }
+    let required_endpoints =
+      BuiltinEndpointSet::PARTICIPANT_ANNOUNCER | BuiltinEndpointSet::PARTICIPANT_DETECTOR;
+    if !BuiltinEndpointSet::from_u32(self.builtin_endpoint_set).contains(required_endpoints) {
+      return create_error_bad_parameter!(
+        "Built-in endpoint set {:#x} lacks the participant announcer or detector.",
+        self.builtin_endpoint_set
+      );
+    }
+    if let Some(max_message_size) = self.max_message_size {
+      if max_message_size < MIN_MAX_MESSAGE_SIZE {
+        return create_error_bad_parameter!(
+          "Maximum message size {max_message_size} is less than {MIN_MAX_MESSAGE_SIZE} bytes."
+        );
+      }
+    }
+
     // QosPolicies with possible security properties, otherwise default
     let participant_qos = QosPolicies {
       #[cfg(feature = "security")]
@@ -116,7 +526,18 @@ impl DomainParticipantBuilder {
       ..Default::default()
     };
 
-    let candidate_participant_guid = GUID::new_participant_guid();
+    let candidate_participant_guid = match self.guid_prefix {
+      None => GUID::new_participant_guid(),
+      Some(prefix) if prefix.is_valid_for_this_participant() => {
+        GUID::participant_guid_with_prefix(prefix)
+      }
+      Some(prefix) => {
+        return create_error_bad_parameter!(
+          "GuidPrefix {prefix:?} does not start with our vendor id {:?}",
+          VendorId::THIS_IMPLEMENTATION
+        );
+      }
+    };
     #[cfg(not(feature = "security"))]
     let participant_guid = candidate_participant_guid;
     // If security plugins are present, security is enabled
@@ -209,7 +630,10 @@ impl DomainParticipantBuilder {
     // It updates data to DiscoveryDB, and sends notifications to dp_event_loop,
     // which owns the Readers and Writers and notifies them also.
     let (discovery_updated_sender, discovery_update_notification_receiver) =
-      mio_channel::sync_channel::<DiscoveryNotificationType>(32);
+      mio_channel::sync_channel::<DiscoveryNotificationType>(channel_capacity(
+        self.manual_event_loop,
+        32,
+      ));
 
     // This channel is used to:
     // * local DataReader and DataWriter notify Discovery on drop() so that
@@ -218,7 +642,7 @@ impl DomainParticipantBuilder {
     // message to remote participants.
     // * Discovery commands Discovery (thread) to terminate on exit.
     let (discovery_command_sender, discovery_command_receiver) =
-      mio_channel::sync_channel::<DiscoveryCommand>(64);
+      mio_channel::sync_channel::<DiscoveryCommand>(channel_capacity(self.manual_event_loop, 64));
 
     // Channel used to report noteworthy events to DomainParticipant
     let (status_sender, status_receiver) = sync_status_channel(16)?;
@@ -229,10 +653,19 @@ impl DomainParticipantBuilder {
     let security_plugins_handle = self.security_plugins.map(SecurityPluginsHandle::new);
 
     // intermediate DP wrapper
-    let dp = DomainParticipantDisc::new(
+    let (dp, manual_event_loop) = DomainParticipantDisc::new(
       self.domain_id,
       participant_guid,
       participant_qos,
+      self.participant_id,
+      self.builtin_endpoint_set,
+      self.memory_limit,
+      self.always_include_source_timestamp,
+      self.max_message_size,
+      self.initial_acknack_delay,
+      (!self.manual_event_loop).then_some(self.event_loop_thread),
+      &self.spawn_options,
+      self.discovery_config,
       djh_receiver,
       discovery_update_notification_receiver,
       discovery_command_sender,
@@ -245,31 +678,69 @@ impl DomainParticipantBuilder {
     // outer DP wrapper
     let dp = DomainParticipant {
       dpi: Arc::new(Mutex::new(dp)),
+      manual_event_loop: manual_event_loop.map(|m| Arc::new(Mutex::new(m))),
     };
 
     let (discovery_started_sender, discovery_started_receiver) = std::sync::mpsc::channel();
 
-    // Construct and start background thread
     let dp_clone = dp.weak_clone();
     let disc_db_clone = dp.discovery_db();
-    let discovery_handle = thread::Builder::new()
-      .name("RustDDS discovery thread".to_string())
-      .spawn(move || {
-        if let Ok(mut discovery) = Discovery::new(
-          dp_clone,
-          disc_db_clone,
-          discovery_started_sender,
-          discovery_updated_sender,
-          discovery_command_receiver,
-          spdp_liveness_receiver,
-          status_sender,
-          security_plugins_handle,
-        ) {
-          discovery.discovery_event_loop(); // run the event loop
-        }
+    let health = dp.health_monitor();
+    let participant_message_observers = dp.participant_message_observers();
+    if let Some(manual_event_loop) = &dp.manual_event_loop {
+      // On failure, Discovery::new reports to discovery_started_sender.
+      if let Ok(discovery) = Discovery::new(
+        dp_clone,
+        disc_db_clone,
+        discovery_started_sender,
+        discovery_updated_sender,
+        discovery_command_receiver,
+        spdp_liveness_receiver,
+        status_sender,
+        security_plugins_handle,
+        health,
+        participant_message_observers,
+        self.discovery_config,
+        self.health_topic_interval,
+      ) {
+        manual_event_loop.lock()?.start_discovery(discovery);
+      }
+    } else {
+      // Construct and start background thread
+      let discovery_config = self.discovery_config;
+      let health_topic_interval = self.health_topic_interval;
+      let spawn_options = self.spawn_options;
+      let thread_name = spawn_options.discovery_name();
+      let discovery_handle = spawn_options.builder(thread_name.clone()).spawn(move || {
+        spawn_options.thread_started(&thread_name);
+        let failure_status_sender = status_sender.clone();
+        let health_clone = Arc::clone(&health);
+        health.run_monitored(
+          MonitoredThread::Discovery,
+          &failure_status_sender,
+          move || {
+            if let Ok(mut discovery) = Discovery::new(
+              dp_clone,
+              disc_db_clone,
+              discovery_started_sender,
+              discovery_updated_sender,
+              discovery_command_receiver,
+              spdp_liveness_receiver,
+              status_sender,
+              security_plugins_handle,
+              health_clone,
+              participant_message_observers,
+              discovery_config,
+              health_topic_interval,
+            ) {
+              discovery.discovery_event_loop(); // run the event loop
+            }
+          },
+        );
       })?;
 
-    djh_sender.send(discovery_handle).unwrap_or(()); // send join handle to inner participant
+      djh_sender.send(discovery_handle).unwrap_or(()); // send join handle to inner participant
+    }
 
     debug!("Waiting for discovery to start"); // blocking until discovery answers
     match discovery_started_receiver.recv_timeout(Duration::from_secs(10)) {
@@ -304,6 +775,9 @@ impl DomainParticipantBuilder {
 // This is a smart pointer for DomainParticipant for easier manipulation.
 pub struct DomainParticipant {
   dpi: Arc<Mutex<DomainParticipantDisc>>,
+  // Present only if built with DomainParticipantBuilder::manual_event_loop.
+  // This is outside dpi, because Discovery needs to lock dpi.
+  manual_event_loop: Option<Arc<Mutex<ManualEventLoop>>>,
 }
 
 impl DomainParticipant {
@@ -323,7 +797,8 @@ impl DomainParticipant {
   /// # Arguments
   ///
   /// * `qos` - Takes [qos policies](qos/struct.QosPolicies.html) for publisher
-  ///   and given to DataWriter as default.
+  ///   and given to DataWriter as default. These take precedence over Topic
+  ///   QoS, see [`Publisher`](crate::Publisher#qos-of-created-datawriters-and-datareaders).
   ///
   /// # Examples
   ///
@@ -344,7 +819,8 @@ impl DomainParticipant {
   /// # Arguments
   ///
   /// * `qos` - Takes [qos policies](qos/struct.QosPolicies.html) for subscriber
-  ///   and given to DataReader as default.
+  ///   and given to DataReader as default. These take precedence over Topic
+  ///   QoS, see [`Publisher`](crate::Publisher#qos-of-created-datawriters-and-datareaders).
   ///
   /// # Examples
   ///
@@ -368,7 +844,8 @@ impl DomainParticipant {
   /// * `name` - Name of the topic.
   /// * `type_desc` - Name of the type this topic is supposed to deliver.
   /// * `qos` - Takes [qos policies](qos/struct.QosPolicies.html) that are
-  ///   distributed to DataReaders and DataWriters.
+  ///   distributed to DataReaders and DataWriters. Policies not set here are
+  ///   taken from the [default Topic QoS](Self::get_default_topic_qos).
   ///
   /// # Examples
   ///
@@ -394,6 +871,52 @@ impl DomainParticipant {
       .create_topic(&w, name, type_desc, qos, topic_kind)
   }
 
+  /// Returns the default Topic QoS. Policies not specified in `create_topic`
+  /// are taken from this. Initially, no policies are set.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// assert_eq!(QosPolicies::qos_none(), domain_participant.get_default_topic_qos());
+  /// ```
+  pub fn get_default_topic_qos(&self) -> QosPolicies {
+    self.dpi.lock().unwrap().get_default_topic_qos().clone()
+  }
+
+  /// Sets the default Topic QoS. This affects only Topics created after this
+  /// call.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::{*, qos::HasQoSPolicy};
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let default_qos = QosPolicyBuilder::new()
+  ///   .reliability(policy::Reliability::BestEffort)
+  ///   .durability(policy::Durability::Volatile)
+  ///   .build();
+  /// domain_participant.set_default_topic_qos(&default_qos);
+  ///
+  /// // Only reliability is given here, durability comes from the default.
+  /// let qos = QosPolicyBuilder::new()
+  ///   .reliability(policy::Reliability::Reliable {
+  ///     max_blocking_time: Duration::ZERO,
+  ///   })
+  ///   .build();
+  /// let topic = domain_participant
+  ///   .create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey)
+  ///   .unwrap();
+  /// assert_eq!(topic.qos().durability(), Some(policy::Durability::Volatile));
+  /// assert_eq!(topic.qos().reliability(), qos.reliability());
+  /// ```
+  pub fn set_default_topic_qos(&self, q: &QosPolicies) {
+    self.dpi.lock().unwrap().set_default_topic_qos(q);
+  }
+
   pub fn find_topic(&self, name: &str, timeout: Duration) -> CreateResult<Option<Topic>> {
     let w = self.weak_clone();
     self.dpi.lock()?.find_topic(&w, name, timeout)
@@ -440,6 +963,65 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().discovered_topics()
   }
 
+  /// Returns everything Discovery currently knows: remote DomainParticipants
+  /// and their DataWriters and DataReaders, Topics, QoS, and how the local
+  /// endpoints match the remote ones.
+  ///
+  /// This is meant for debugging, e.g. attaching to a bug report as JSON, or
+  /// checking the discovered graph in tests. With the "security" feature, the
+  /// authentication status of each remote DomainParticipant is included.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let snapshot = domain_participant.discovery_snapshot();
+  /// for participant in &snapshot.participants {
+  ///   println!("{:?}: {} writers", participant.guid, participant.writers.len());
+  /// }
+  /// ```
+  pub fn discovery_snapshot(&self) -> DiscoverySnapshot {
+    self.dpi.lock().unwrap().discovery_snapshot()
+  }
+
+  /// Lists the data types in use in the domain, by type name, each with the
+  /// names of the Topics it is used on. Both lists are sorted by name.
+  ///
+  /// This is collected from Discovery: announced Topics and the DataWriters
+  /// and DataReaders of both remote and local DomainParticipants. Built-in
+  /// Discovery topics are not included.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for (type_name, topics) in domain_participant.discovered_types() {
+  ///   println!("{type_name} is used on {}", topics.join(", "));
+  /// }
+  /// ```
+  pub fn discovered_types(&self) -> Vec<(String, Vec<String>)> {
+    self.dpi.lock().unwrap().discovered_types()
+  }
+
+  /// Approximate memory, in bytes, currently charged against the limit set
+  /// by [`DomainParticipantBuilder::memory_limit`].
+  pub fn memory_in_use(&self) -> usize {
+    self.dds_cache().read().unwrap().memory_budget().used()
+  }
+
+  /// Approximate memory use, in bytes, by where the memory is held.
+  ///
+  /// Everything except [`MemoryUsage::discovery_db`] is charged against the
+  /// limit set by [`DomainParticipantBuilder::memory_limit`]. Memory is
+  /// counted also if there is no limit.
+  pub fn memory_usage(&self) -> MemoryUsage {
+    self.dpi.lock().unwrap().memory_usage()
+  }
+
   /// Manually asserts liveliness, affecting all writers with
   /// LIVELINESS QoS of MANUAL_BY_PARTICIPANT created by
   /// this particular participant.
@@ -456,48 +1038,301 @@ impl DomainParticipant {
     self.dpi.lock()?.assert_liveliness()
   }
 
-  /// Get a `DomainDomainParticipantStatusListener` that can be used
-  /// to get `DomainParticipantStatusEvent`s for this DomainParticipant.
-  pub fn status_listener(&self) -> DomainParticipantStatusListener {
-    DomainParticipantStatusListener {
-      dp_disc: Arc::clone(&self.dpi),
-    }
+  /// Re-enumerates local network interfaces and updates the locators this
+  /// DomainParticipant announces, e.g. after a DHCP lease change or an
+  /// interface coming up or going down.
+  ///
+  /// Multicast groups are joined on new interfaces, and multicast senders are
+  /// created for them. Unicast sockets listen on all interfaces, so they do
+  /// not need to be rebound. If the locators changed, the new ones are
+  /// immediately announced to remote participants via Discovery.
+  ///
+  /// Discovery also checks for interface changes periodically (every few
+  /// seconds) and does the same automatically, reporting them as
+  /// [`DomainParticipantStatusEvent::NetworkInterfacesChanged`]. Calling this
+  /// is only needed to react faster than that.
+  ///
+  /// Returns `Ok(true)` if the announced locators changed.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// // Nothing has changed since creation
+  /// assert!(!domain_participant.refresh_locators().unwrap());
+  /// ```
+  pub fn refresh_locators(&self) -> WriteResult<bool, ()> {
+    self.dpi.lock()?.refresh_locators()
   }
 
-  pub(crate) fn weak_clone(&self) -> DomainParticipantWeak {
-    DomainParticipantWeak::new(self)
+  pub(crate) fn update_self_locators(&self) -> WriteResult<bool, ()> {
+    self.dpi.lock()?.update_self_locators()
   }
 
-  pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
-    self.dpi.lock().unwrap().dds_cache()
+  /// Forgets the remote DomainParticipant `remote_prefix` right away, as if
+  /// its lease had expired, and then announces this participant to prompt
+  /// rediscovery. Use this e.g. when the remote is known to have restarted,
+  /// instead of waiting for its lease to expire.
+  ///
+  /// This reports
+  /// [`ParticipantLost`](DomainParticipantStatusEvent::ParticipantLost) with
+  /// [`LostReason::Preempted`](crate::dds::statusevents::LostReason::Preempted)
+  /// and unmatches all endpoints of the remote participant. If the remote is
+  /// still there, it is discovered again from its next announcement. Unknown
+  /// prefixes are ignored, apart from the announcement.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::{DomainParticipant, GUID};
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// // E.g. from a ParticipantDiscovered status event
+  /// let remote: GUID = GUID::GUID_UNKNOWN;
+  /// domain_participant.preempt_discovery(remote.prefix).unwrap();
+  /// ```
+  pub fn preempt_discovery(&self, remote_prefix: GuidPrefix) -> WriteResult<(), ()> {
+    self.dpi.lock()?.preempt_discovery(remote_prefix)
   }
 
-  #[cfg(feature = "security")] // just to avoid warning
-  pub(crate) fn qos(&self) -> QosPolicies {
-    self.dpi.lock().unwrap().qos()
+  /// Unicast and multicast locators that this DomainParticipant listens on,
+  /// for both discovery and user traffic. The port numbers are the actually
+  /// bound ones. Unicast locators list each local network address
+  /// separately.
+  ///
+  /// These are the same locators that are announced to other participants
+  /// in Discovery.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for locator in domain_participant.listening_locators() {
+  ///   println!("Listening on {locator}");
+  /// }
+  /// ```
+  pub fn listening_locators(&self) -> Vec<Locator> {
+    let mut locators: Vec<Locator> = self.self_locators().into_values().flatten().collect();
+    locators.sort();
+    locators.dedup();
+    locators
   }
 
-  pub(crate) fn discovery_db(&self) -> Arc<RwLock<DiscoveryDB>> {
-    self.dpi.lock().unwrap().dpi.discovery_db.clone()
+  /// Self-diagnostics: are the background threads alive, how many commands
+  /// are queued for the event loop, and how long ago network traffic was
+  /// last seen.
+  ///
+  /// If a background thread panics, the panic is captured and reported as
+  /// [`DomainParticipantStatusEvent::ParticipantFailed`], and
+  /// [`ParticipantHealth::failure`] tells what happened. After that, creating
+  /// new entities and writing data fail with a `Poisoned` error.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let health = domain_participant.health();
+  /// assert!(health.failure.is_none());
+  /// ```
+  pub fn health(&self) -> ParticipantHealth {
+    self.health_monitor().snapshot()
   }
 
-  pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
-    self.dpi.lock().unwrap().new_entity_id(entity_kind)
+  /// Returns the current values of operational counters, such as messages
+  /// and bytes sent and received, and the number of matched endpoints.
+  ///
+  /// The counters are maintained all the time, so taking a snapshot is cheap.
+  /// [`ParticipantStatistics`] implements `serde::Serialize` for exporting
+  /// e.g. as JSON.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let statistics = domain_participant.get_statistics_snapshot();
+  /// println!("{} bytes sent", statistics.bytes_sent);
+  /// ```
+  pub fn get_statistics_snapshot(&self) -> ParticipantStatistics {
+    self.health_monitor().statistics.snapshot()
   }
 
-  pub(crate) fn self_locators(&self) -> HashMap<mio_06::Token, Vec<Locator>> {
-    self.dpi.lock().unwrap().self_locators()
+  /// Names of the background threads that this DomainParticipant has started:
+  /// the event loop and Discovery. Empty if it was built with
+  /// [`manual_event_loop`](DomainParticipantBuilder::manual_event_loop).
+  ///
+  /// The names are set with
+  /// [`thread_name`](DomainParticipantBuilder::thread_name) and
+  /// [`thread_name_prefix`](DomainParticipantBuilder::thread_name_prefix).
+  pub fn thread_names(&self) -> Vec<String> {
+    self.dpi.lock().unwrap().thread_names()
   }
-} // end impl DomainParticipant
-
-// --------------------------------------------------------------------------
-// --------------------------------------------------------------------------
-
-pub struct DomainParticipantStatusListener {
-  dp_disc: Arc<Mutex<DomainParticipantDisc>>,
-}
-
-impl DomainParticipantStatusListener {}
+
+  /// Runs the event loop and Discovery in the calling thread, if this
+  /// DomainParticipant was built with
+  /// [`manual_event_loop`](DomainParticipantBuilder::manual_event_loop).
+  ///
+  /// This receives and sends network traffic, fires timers, and processes
+  /// Discovery. It waits for at most `timeout` for something to happen, and
+  /// returns sooner if there was. Discovery does not wake it up, but is
+  /// serviced before and after waiting.
+  ///
+  /// Returns `false` if this DomainParticipant runs background threads
+  /// instead, so that there is nothing to do here.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use std::time::Duration;
+  /// # use rustdds::{DomainParticipant, DomainParticipantBuilder};
+  /// let threaded = DomainParticipant::new(0).unwrap();
+  /// assert!(!threaded.poll_timeout(Duration::from_millis(10)));
+  ///
+  /// let manual = DomainParticipantBuilder::new(0)
+  ///   .manual_event_loop(true)
+  ///   .build()
+  ///   .unwrap();
+  /// assert!(manual.poll_timeout(Duration::from_millis(10)));
+  /// ```
+  pub fn poll_timeout(&self, timeout: Duration) -> bool {
+    match &self.manual_event_loop {
+      Some(manual_event_loop) => manual_event_loop.lock().unwrap().poll(timeout),
+      None => false,
+    }
+  }
+
+  /// Same as [`poll_timeout`](Self::poll_timeout) with zero timeout: handles
+  /// whatever is pending, but does not wait.
+  pub fn run_once(&self) -> bool {
+    self.poll_timeout(Duration::ZERO)
+  }
+
+  pub(crate) fn health_monitor(&self) -> Arc<HealthMonitor> {
+    self.dpi.lock().unwrap().health_monitor()
+  }
+
+  /// Receiver of the ParticipantMessages that Discovery receives. See RTPS
+  /// spec v2.5 Section "8.4.13 Writer Liveliness Protocol".
+  ///
+  /// ParticipantMessages carry the liveliness assertions of a remote
+  /// participant's writers, and may also carry vendor-specific data. They are
+  /// reported here as received, in addition to being processed for
+  /// liveliness as usual. This is meant for debugging and interoperability
+  /// testing of liveliness.
+  ///
+  /// Each call creates a new, independent receiver. If a receiver is not read
+  /// fast enough, messages to it are dropped.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let messages = domain_participant.participant_message_receiver().unwrap();
+  /// while let Ok(message) = messages.try_recv() {
+  ///   println!("{:?} sent {:?}", message.guid, message.kind);
+  /// }
+  /// ```
+  pub fn participant_message_receiver(
+    &self,
+  ) -> CreateResult<StatusChannelReceiver<ParticipantMessageData>> {
+    let (sender, receiver) = sync_status_channel(64)?;
+    self
+      .participant_message_observers()
+      .lock()
+      .unwrap()
+      .push(sender);
+    Ok(receiver)
+  }
+
+  pub(crate) fn participant_message_observers(&self) -> ParticipantMessageObservers {
+    self.dpi.lock().unwrap().participant_message_observers()
+  }
+
+  pub(crate) fn always_include_source_timestamp(&self) -> bool {
+    self.dpi.lock().unwrap().always_include_source_timestamp()
+  }
+
+  pub(crate) fn max_message_size(&self) -> Option<usize> {
+    self.dpi.lock().unwrap().max_message_size()
+  }
+
+  pub(crate) fn initial_acknack_delay(&self) -> Option<Duration> {
+    self.dpi.lock().unwrap().initial_acknack_delay()
+  }
+
+  pub(crate) fn builtin_endpoint_set(&self) -> u32 {
+    self.dpi.lock().unwrap().builtin_endpoint_set()
+  }
+
+  /// Get a `DomainDomainParticipantStatusListener` that can be used
+  /// to get `DomainParticipantStatusEvent`s for this DomainParticipant.
+  pub fn status_listener(&self) -> DomainParticipantStatusListener {
+    DomainParticipantStatusListener {
+      dp_disc: Arc::clone(&self.dpi),
+    }
+  }
+
+  pub(crate) fn weak_clone(&self) -> DomainParticipantWeak {
+    DomainParticipantWeak::new(self)
+  }
+
+  pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
+    self.dpi.lock().unwrap().dds_cache()
+  }
+
+  #[cfg(feature = "security")] // just to avoid warning
+  pub(crate) fn qos(&self) -> QosPolicies {
+    self.dpi.lock().unwrap().qos()
+  }
+
+  pub(crate) fn discovery_db(&self) -> Arc<RwLock<DiscoveryDB>> {
+    self.dpi.lock().unwrap().dpi.discovery_db.clone()
+  }
+
+  pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
+    self.dpi.lock().unwrap().new_entity_id(entity_kind)
+  }
+
+  pub(crate) fn self_locators(&self) -> HashMap<mio_06::Token, Vec<Locator>> {
+    self.dpi.lock().unwrap().self_locators()
+  }
+} // end impl DomainParticipant
+
+// --------------------------------------------------------------------------
+// --------------------------------------------------------------------------
+
+pub struct DomainParticipantStatusListener {
+  dp_disc: Arc<Mutex<DomainParticipantDisc>>,
+}
+
+impl DomainParticipantStatusListener {
+  /// Async stream of security-related status events only, i.e. those for
+  /// which
+  /// [`is_security_event`](DomainParticipantStatusEvent::is_security_event)
+  /// is true. Use this to follow the authentication of remote Participants
+  /// step by step.
+  ///
+  /// All status events of a DomainParticipant come through the same channel,
+  /// so other events are consumed and discarded by this stream. Use
+  /// [`as_async_status_stream`](StatusEvented::as_async_status_stream) if
+  /// you need them, too.
+  #[cfg(feature = "security")]
+  pub fn as_async_security_status_stream(
+    &self,
+  ) -> impl FusedStream<Item = DomainParticipantStatusEvent> + '_ {
+    futures::StreamExt::filter(self.as_async_status_stream(), |event| {
+      futures::future::ready(event.is_security_event())
+    })
+  }
+}
 
 impl<'a> StatusEvented<'a, DomainParticipantStatusEvent, DomainParticipantStatusStream<'a>>
   for DomainParticipantStatusListener
@@ -657,6 +1492,7 @@ impl PartialEq for DomainParticipant {
 #[derive(Clone)]
 pub struct DomainParticipantWeak {
   dpi: Weak<Mutex<DomainParticipantDisc>>,
+  manual_event_loop: Option<Weak<Mutex<ManualEventLoop>>>,
   // This struct caches some items to avoid construction deadlocks
   #[cfg(feature = "security")] // just to avoid warning
   domain_id: u16,
@@ -669,6 +1505,7 @@ impl DomainParticipantWeak {
   pub fn new(dp: &DomainParticipant) -> Self {
     Self {
       dpi: Arc::downgrade(&dp.dpi),
+      manual_event_loop: dp.manual_event_loop.as_ref().map(Arc::downgrade),
       #[cfg(feature="security")] // just to avoid warning
       domain_id: dp.domain_id(),
       guid: dp.guid(),
@@ -728,7 +1565,10 @@ impl DomainParticipantWeak {
   }
 
   pub fn upgrade(self) -> Option<DomainParticipant> {
-    self.dpi.upgrade().map(|d| DomainParticipant { dpi: d })
+    self.dpi.upgrade().map(|d| DomainParticipant {
+      dpi: d,
+      manual_event_loop: self.manual_event_loop.and_then(|m| m.upgrade()),
+    })
   }
 } // end impl
 
@@ -755,18 +1595,36 @@ impl DomainParticipantDisc {
     domain_id: u16,
     participant_guid: GUID,
     qos_policies: QosPolicies,
+    participant_id: Option<u16>,
+    builtin_endpoint_set: u32,
+    memory_limit: Option<usize>,
+    always_include_source_timestamp: bool,
+    max_message_size: Option<usize>,
+    initial_acknack_delay: Option<Duration>,
+    event_loop_thread: Option<ThreadOptions>,
+    spawn_options: &ThreadSpawnOptions,
+    discovery_config: DiscoveryConfig,
     discovery_join_handle: mio_channel::Receiver<JoinHandle<()>>,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
     discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
-    spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_liveness_sender: mio_channel::SyncSender<(GuidPrefix, Option<VendorId>)>,
     status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
-  ) -> CreateResult<Self> {
-    let dpi = DomainParticipantInner::new(
+  ) -> CreateResult<(Self, Option<ManualEventLoop>)> {
+    let (dpi, manual_event_loop) = DomainParticipantInner::new(
       domain_id,
       participant_guid,
       qos_policies,
+      participant_id,
+      builtin_endpoint_set,
+      memory_limit,
+      always_include_source_timestamp,
+      max_message_size,
+      initial_acknack_delay,
+      event_loop_thread,
+      spawn_options,
+      discovery_config,
       discovery_update_notification_receiver,
       discovery_command_sender.clone(),
       spdp_liveness_sender,
@@ -775,12 +1633,15 @@ impl DomainParticipantDisc {
       security_plugins_handle,
     )?;
 
-    Ok(Self {
-      dpi,
-      discovery_command_sender,
-      discovery_join_handle,
-      entity_id_generator: atomic::AtomicU32::new(0),
-    })
+    Ok((
+      Self {
+        dpi,
+        discovery_command_sender,
+        discovery_join_handle,
+        entity_id_generator: atomic::AtomicU32::new(0),
+      },
+      manual_event_loop,
+    ))
   }
 
   // This generates identifiers that consist of given EntityKind and arbitrary,
@@ -825,6 +1686,14 @@ impl DomainParticipantDisc {
     self.dpi.create_topic(dp, name, type_desc, qos, topic_kind)
   }
 
+  pub fn get_default_topic_qos(&self) -> &QosPolicies {
+    self.dpi.get_default_topic_qos()
+  }
+
+  pub fn set_default_topic_qos(&mut self, q: &QosPolicies) {
+    self.dpi.set_default_topic_qos(q);
+  }
+
   pub fn find_topic(
     &self,
     dp: &DomainParticipantWeak,
@@ -846,10 +1715,50 @@ impl DomainParticipantDisc {
     self.dpi.discovered_topics()
   }
 
+  pub fn discovery_snapshot(&self) -> DiscoverySnapshot {
+    self.dpi.discovery_snapshot()
+  }
+
+  pub fn discovered_types(&self) -> Vec<(String, Vec<String>)> {
+    self.dpi.discovered_types()
+  }
+
+  pub fn memory_usage(&self) -> MemoryUsage {
+    self.dpi.memory_usage()
+  }
+
   pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
     self.dpi.dds_cache()
   }
 
+  pub(crate) fn health_monitor(&self) -> Arc<HealthMonitor> {
+    self.dpi.health_monitor()
+  }
+
+  pub fn thread_names(&self) -> Vec<String> {
+    self.dpi.thread_names()
+  }
+
+  pub(crate) fn participant_message_observers(&self) -> ParticipantMessageObservers {
+    self.dpi.participant_message_observers()
+  }
+
+  pub(crate) fn always_include_source_timestamp(&self) -> bool {
+    self.dpi.always_include_source_timestamp
+  }
+
+  pub(crate) fn max_message_size(&self) -> Option<usize> {
+    self.dpi.max_message_size
+  }
+
+  pub(crate) fn initial_acknack_delay(&self) -> Option<Duration> {
+    self.dpi.initial_acknack_delay
+  }
+
+  pub(crate) fn builtin_endpoint_set(&self) -> u32 {
+    self.dpi.builtin_endpoint_set
+  }
+
   #[cfg(feature = "security")] // just to avoid warning
   pub(crate) fn qos(&self) -> QosPolicies {
     self.dpi.qos()
@@ -874,6 +1783,44 @@ impl DomainParticipantDisc {
     self.dpi.self_locators.clone()
   }
 
+  pub(crate) fn refresh_locators(&mut self) -> WriteResult<bool, ()> {
+    let changed = self.dpi.refresh_locators()?;
+    if changed.is_empty() {
+      return Ok(false);
+    }
+    // The new locators are stored only after Discovery has accepted the
+    // command, so that a failed announcement is retried on the next call.
+    // Discovery reads them only after this participant is unlocked.
+    self
+      .discovery_command_sender
+      .send(DiscoveryCommand::RefreshLocators)
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("Cannot send to Discovery: {e}"),
+        data: (),
+      })?;
+    self.dpi.set_self_locators(changed);
+    Ok(true)
+  }
+
+  // Same as refresh_locators, but does not notify Discovery. This is for
+  // Discovery itself, which re-announces on its own.
+  pub(crate) fn update_self_locators(&mut self) -> WriteResult<bool, ()> {
+    let changed = self.dpi.refresh_locators()?;
+    let any_changed = !changed.is_empty();
+    self.dpi.set_self_locators(changed);
+    Ok(any_changed)
+  }
+
+  pub(crate) fn preempt_discovery(&self, guid_prefix: GuidPrefix) -> WriteResult<(), ()> {
+    self
+      .discovery_command_sender
+      .send(DiscoveryCommand::PreemptDiscovery { guid_prefix })
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("Cannot send to Discovery: {e}"),
+        data: (),
+      })
+  }
+
   pub(crate) fn status_channel_receiver(
     &self,
   ) -> &StatusChannelReceiver<DomainParticipantStatusEvent> {
@@ -898,6 +1845,8 @@ impl Drop for DomainParticipantDisc {
       .is_err()
     {
       error!("dp_event_loop not responding to prepare stop discovery_command");
+    } else {
+      self.dpi.health.event_loop_commands.sent();
     }
 
     debug!("Sending Discovery Stop signal.");
@@ -918,6 +1867,117 @@ impl Drop for DomainParticipantDisc {
   }
 }
 
+// In manual event loop mode, the channels between the application, Discovery
+// and the event loop are drained only when the application polls. Then they
+// must be able to hold a burst of new entities or discovery updates.
+const MANUAL_EVENT_LOOP_CHANNEL_CAPACITY: usize = 256;
+
+fn channel_capacity(manual_event_loop: bool, threaded_capacity: usize) -> usize {
+  if manual_event_loop {
+    threaded_capacity.max(MANUAL_EVENT_LOOP_CHANNEL_CAPACITY)
+  } else {
+    threaded_capacity
+  }
+}
+
+// How long dropping a manually run DomainParticipant may take to let Discovery
+// and the event loop shut down.
+const MANUAL_EVENT_LOOP_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The event loop and Discovery, when they are run by the application instead
+// of background threads. See DomainParticipantBuilder::manual_event_loop.
+pub(crate) struct ManualEventLoop {
+  event_loop: Option<DPEventLoop>, // None after the event loop has stopped
+  discovery: Option<Discovery>,    // None before start and after stop
+  discovery_events: mio_06::Events,
+  stop_poll_sender: mio_channel::Sender<EventLoopCommand>,
+  discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
+  health: Arc<HealthMonitor>,
+}
+
+impl ManualEventLoop {
+  fn new(
+    event_loop: DPEventLoop,
+    stop_poll_sender: mio_channel::Sender<EventLoopCommand>,
+    discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
+    health: Arc<HealthMonitor>,
+  ) -> Self {
+    health.set_running(MonitoredThread::EventLoop, true);
+    Self {
+      event_loop: Some(event_loop),
+      discovery: None,
+      discovery_events: mio_06::Events::with_capacity(32),
+      stop_poll_sender,
+      discovery_command_sender,
+      health,
+    }
+  }
+
+  fn start_discovery(&mut self, mut discovery: Discovery) {
+    self.health.set_running(MonitoredThread::Discovery, true);
+    discovery.start();
+    self.discovery = Some(discovery);
+  }
+
+  // Returns false if the event loop has stopped.
+  fn poll(&mut self, timeout: Duration) -> bool {
+    self.poll_discovery(Duration::ZERO);
+    if let Some(event_loop) = self.event_loop.as_mut() {
+      if !event_loop.poll_once(timeout) {
+        self.event_loop = None;
+        self.health.set_running(MonitoredThread::EventLoop, false);
+      }
+    }
+    // Discovery data received by the event loop is waiting for Discovery now.
+    self.poll_discovery(Duration::ZERO);
+    self.event_loop.is_some()
+  }
+
+  fn poll_discovery(&mut self, timeout: Duration) {
+    if let Some(discovery) = self.discovery.as_mut() {
+      if !discovery.poll_once(&mut self.discovery_events, timeout) {
+        self.discovery = None;
+        self.health.set_running(MonitoredThread::Discovery, false);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+impl DomainParticipant {
+  // Runs `f` on the Discovery of a participant built with a manual event loop,
+  // so that tests can drive Discovery directly.
+  pub(crate) fn with_discovery<R>(&self, f: impl FnOnce(&mut Discovery) -> R) -> Option<R> {
+    let mut manual_event_loop = self.manual_event_loop.as_ref()?.lock().unwrap();
+    manual_event_loop.discovery.as_mut().map(f)
+  }
+}
+
+impl Drop for ManualEventLoop {
+  fn drop(&mut self) {
+    // Stop Discovery first, like DomainParticipantDisc does with the background
+    // threads, so that the event loop still sends out its farewell messages.
+    // DomainParticipantDisc may have already requested this, but it is not
+    // necessarily dropped yet.
+    let deadline = Instant::now() + MANUAL_EVENT_LOOP_STOP_TIMEOUT;
+    self
+      .discovery_command_sender
+      .try_send(DiscoveryCommand::StopDiscovery)
+      .unwrap_or(()); // Discovery may be stopped already
+    while self.discovery.is_some() && Instant::now() < deadline {
+      self.poll_discovery(Duration::from_millis(10));
+    }
+
+    if self.stop_poll_sender.send(EventLoopCommand::Stop).is_ok() {
+      self.health.event_loop_commands.sent();
+    }
+    while self.event_loop.is_some() && Instant::now() < deadline {
+      self.poll(Duration::from_millis(10));
+    }
+    debug!("Manual event loop stopped");
+  }
+}
+
 // This is the actual working DomainParticipant.
 pub(crate) struct DomainParticipantInner {
   domain_info: DomainInfo,
@@ -933,6 +1993,9 @@ pub(crate) struct DomainParticipantInner {
   stop_poll_sender: mio_channel::Sender<EventLoopCommand>,
   ev_loop_handle: Option<JoinHandle<()>>, // this is Option, because it needs to be extracted
   // out of the struct (take) in order to .join() on the handle.
+  // It is None from the start, if the event loop is run manually.
+  // Event loop and Discovery, if they run in background threads
+  thread_names: Vec<String>,
 
   // Writers
   add_writer_sender: mio_channel::SyncSender<WriterIngredients>,
@@ -942,35 +2005,57 @@ pub(crate) struct DomainParticipantInner {
   discovery_db: Arc<RwLock<DiscoveryDB>>,
   discovery_db_event_receiver: mio_channel::Receiver<()>,
 
+  // used when creating a new Topic
+  default_topic_qos: QosPolicies,
+
   // status event receiver
   status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
 
   // RTPS locators describing how to reach this DP
   self_locators: HashMap<mio_06::Token, Vec<Locator>>,
+  // Port numbers of unicast listeners, for recomputing self_locators
+  unicast_listener_ports: HashMap<mio_06::Token, u16>,
 
   security_plugins_handle: Option<SecurityPluginsHandle>,
+
+  // Self-diagnostics, updated by the background threads
+  health: Arc<HealthMonitor>,
+
+  // Receivers of DomainParticipant::participant_message_receiver
+  participant_message_observers: ParticipantMessageObservers,
+
+  // See DomainParticipantBuilder::always_include_source_timestamp
+  always_include_source_timestamp: bool,
+
+  // See DomainParticipantBuilder::max_message_size
+  max_message_size: Option<usize>,
+
+  // See DomainParticipantBuilder::initial_acknack_delay
+  initial_acknack_delay: Option<Duration>,
+
+  // See DomainParticipantBuilder::builtin_endpoint_set
+  builtin_endpoint_set: u32,
 }
 
 impl Drop for DomainParticipantInner {
   fn drop(&mut self) {
+    let Some(join_handle) = self.ev_loop_handle.take() else {
+      // Manual event loop. ManualEventLoop stops it after Discovery.
+      return;
+    };
+
     // if send has an error simply leave as we have lost control of the
     // ev_loop_thread anyways
     if self.stop_poll_sender.send(EventLoopCommand::Stop).is_err() {
       error!("dp_event_loop not responding to stop discovery_command");
       return;
     }
+    self.health.event_loop_commands.sent();
 
     debug!("Waiting for dp_event_loop join");
-    match self.ev_loop_handle.take() {
-      Some(join_handle) => {
-        join_handle
-          .join()
-          .unwrap_or_else(|e| warn!("Failed to join dp_event_loop: {e:?}"));
-      }
-      None => {
-        error!("Someone managed to steal dp_event_loop join handle from DomainParticipantInner.");
-      }
-    }
+    join_handle
+      .join()
+      .unwrap_or_else(|e| warn!("Failed to join dp_event_loop: {e:?}"));
     debug!("Joined dp_event_loop");
   }
 }
@@ -981,13 +2066,22 @@ impl DomainParticipantInner {
     domain_id: u16,
     participant_guid: GUID,
     _qos_policies: QosPolicies,
+    requested_participant_id: Option<u16>,
+    builtin_endpoint_set: u32,
+    memory_limit: Option<usize>,
+    always_include_source_timestamp: bool,
+    max_message_size: Option<usize>,
+    initial_acknack_delay: Option<Duration>,
+    event_loop_thread: Option<ThreadOptions>, // None means manual event loop
+    spawn_options: &ThreadSpawnOptions,
+    discovery_config: DiscoveryConfig,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
     discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
-    spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_liveness_sender: mio_channel::SyncSender<(GuidPrefix, Option<VendorId>)>,
     status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
-  ) -> CreateResult<Self> {
+  ) -> CreateResult<(Self, Option<ManualEventLoop>)> {
     #[cfg(not(feature = "security"))]
     let _dummy = _qos_policies; // to make clippy happy
 
@@ -1004,30 +2098,36 @@ impl DomainParticipantInner {
       Err(e) => warn!("Cannot get multicast discovery listener: {e:?}"),
     }
 
-    let mut participant_id = 0;
+    let mut participant_id = requested_participant_id.unwrap_or(0);
 
     let mut discovery_listener = None;
 
-    // Magic value 120 below is from RTPS spec 2.5 Section "9.6.2.3 Default Port
-    // Numbers"
-    while discovery_listener.is_none() && participant_id < 120 {
+    // The limit is from RTPS spec 2.5 Section "9.6.2.3 Default Port Numbers"
+    while discovery_listener.is_none() && participant_id < PARTICIPANT_ID_LIMIT {
       discovery_listener = UDPListener::new_unicast(
         "0.0.0.0",
         spdp_well_known_unicast_port(domain_id, participant_id),
       )
       .ok();
       if discovery_listener.is_none() {
+        if requested_participant_id.is_some() {
+          // Do not silently pick another one
+          break;
+        }
         participant_id += 1;
       }
     }
 
-    info!("ParticipantId {participant_id} selected.");
-
     // here discovery_listener is redefined (shadowed)
     let discovery_listener = match discovery_listener {
       Some(dl) => dl,
+      None if requested_participant_id.is_some() => {
+        return create_error_out_of_resources!("ParticipantId {participant_id} is already in use")
+      }
       None => return create_error_out_of_resources!("Could not find free ParticipantId"),
     };
+
+    info!("ParticipantId {participant_id} selected.");
     listeners.insert(DISCOVERY_LISTENER_TOKEN, discovery_listener);
 
     // Now the user traffic listeners
@@ -1076,15 +2176,25 @@ impl DomainParticipantInner {
       })
       .collect();
 
+    let unicast_listener_ports = listeners
+      .iter()
+      .filter(|(_, l)| !l.is_multicast())
+      .map(|(t, l)| (*t, l.port()))
+      .collect();
+
+    let capacity = |threaded| channel_capacity(event_loop_thread.is_none(), threaded);
+
     // Adding readers
     let (sender_add_reader, receiver_add_reader) =
-      mio_channel::sync_channel::<ReaderIngredients>(100);
-    let (sender_remove_reader, receiver_remove_reader) = mio_channel::sync_channel::<GUID>(4);
+      mio_channel::sync_channel::<ReaderIngredients>(capacity(100));
+    let (sender_remove_reader, receiver_remove_reader) =
+      mio_channel::sync_channel::<GUID>(capacity(4));
 
     // Writers
     let (add_writer_sender, add_writer_receiver) =
-      mio_channel::sync_channel::<WriterIngredients>(10);
-    let (remove_writer_sender, remove_writer_receiver) = mio_channel::sync_channel::<GUID>(4);
+      mio_channel::sync_channel::<WriterIngredients>(capacity(10));
+    let (remove_writer_sender, remove_writer_receiver) =
+      mio_channel::sync_channel::<GUID>(capacity(4));
 
     let domain_info = DomainInfo {
       domain_participant_guid: participant_guid,
@@ -1093,7 +2203,10 @@ impl DomainParticipantInner {
     };
     let domain_info_clone = domain_info.clone();
 
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let dds_cache = Arc::new(RwLock::new(DDSCache::with_memory_limit(
+      memory_limit,
+      status_sender.clone(),
+    )));
     let dds_cache_clone = Arc::clone(&dds_cache);
 
     let (discovery_db_event_sender, discovery_db_event_receiver) =
@@ -1108,43 +2221,82 @@ impl DomainParticipantInner {
 
     let (stop_poll_sender, stop_poll_receiver) = mio_channel::channel();
 
-    // Launch the background thread for DomainParticipant
+    let health = Arc::new(HealthMonitor::new());
+
     let disc_db_clone = discovery_db.clone();
     let security_plugins_clone = security_plugins_handle.clone();
-    let ev_loop_handle = thread::Builder::new()
-      .name(format!("RustDDS Participant {participant_id} event loop"))
-      .spawn(move || {
-        let dp_event_loop = DPEventLoop::new(
-          domain_info_clone,
-          dds_cache_clone,
-          listeners,
-          disc_db_clone,
-          participant_guid.prefix,
-          TokenReceiverPair {
-            token: ADD_READER_TOKEN,
-            receiver: receiver_add_reader,
-          },
-          TokenReceiverPair {
-            token: REMOVE_READER_TOKEN,
-            receiver: receiver_remove_reader,
-          },
-          TokenReceiverPair {
-            token: ADD_WRITER_TOKEN,
-            receiver: add_writer_receiver,
-          },
-          TokenReceiverPair {
-            token: REMOVE_WRITER_TOKEN,
-            receiver: remove_writer_receiver,
-          },
-          stop_poll_receiver,
-          discovery_update_notification_receiver,
-          discovery_command_sender,
-          spdp_liveness_sender,
-          status_sender,
-          security_plugins_clone,
-        );
-        dp_event_loop.event_loop();
-      })?;
+    let health_clone = Arc::clone(&health);
+    let failure_status_sender = status_sender.clone();
+    let discovery_command_sender_clone = discovery_command_sender.clone();
+    let stop_poll_sender_clone = stop_poll_sender.clone();
+    let new_event_loop = move || {
+      DPEventLoop::new(
+        domain_info_clone,
+        dds_cache_clone,
+        listeners,
+        disc_db_clone,
+        participant_guid.prefix,
+        TokenReceiverPair {
+          token: ADD_READER_TOKEN,
+          receiver: receiver_add_reader,
+        },
+        TokenReceiverPair {
+          token: REMOVE_READER_TOKEN,
+          receiver: receiver_remove_reader,
+        },
+        TokenReceiverPair {
+          token: ADD_WRITER_TOKEN,
+          receiver: add_writer_receiver,
+        },
+        TokenReceiverPair {
+          token: REMOVE_WRITER_TOKEN,
+          receiver: remove_writer_receiver,
+        },
+        stop_poll_receiver,
+        discovery_update_notification_receiver,
+        discovery_command_sender,
+        spdp_liveness_sender,
+        status_sender,
+        security_plugins_clone,
+        health_clone,
+        discovery_config,
+      )
+    };
+
+    let thread_names = match &event_loop_thread {
+      Some(event_loop_thread) => vec![
+        spawn_options.event_loop_name(event_loop_thread, participant_id),
+        spawn_options.discovery_name(),
+      ],
+      None => Vec::new(),
+    };
+    let (ev_loop_handle, manual_event_loop) = match event_loop_thread {
+      None => (
+        None,
+        Some(ManualEventLoop::new(
+          new_event_loop(),
+          stop_poll_sender_clone,
+          discovery_command_sender_clone,
+          Arc::clone(&health),
+        )),
+      ),
+      Some(event_loop_thread) => {
+        // Launch the background thread for DomainParticipant
+        let health = Arc::clone(&health);
+        let spawn_options = spawn_options.clone();
+        let thread_name = spawn_options.event_loop_name(&event_loop_thread, participant_id);
+        let handle = spawn_options.builder(thread_name.clone()).spawn(move || {
+          event_loop_thread.apply_to_current_thread();
+          spawn_options.thread_started(&thread_name);
+          health.run_monitored(
+            MonitoredThread::EventLoop,
+            &failure_status_sender,
+            move || new_event_loop().event_loop(),
+          );
+        })?;
+        (Some(handle), None)
+      }
+    };
 
     #[cfg(feature = "security")]
     let have_security = true;
@@ -1156,29 +2308,88 @@ impl DomainParticipantInner {
        GUID={participant_guid:?} security_feature_enabled={have_security}",
     );
 
-    Ok(Self {
+    let dpi = Self {
       domain_info,
       #[cfg(feature = "security")]
       my_qos_policies: _qos_policies,
       sender_add_reader,
       sender_remove_reader,
       stop_poll_sender,
-      ev_loop_handle: Some(ev_loop_handle),
+      ev_loop_handle,
+      thread_names,
       add_writer_sender,
       remove_writer_sender,
       dds_cache,
       discovery_db,
       discovery_db_event_receiver,
+      default_topic_qos: QosPolicies::qos_none(),
       status_receiver,
       self_locators,
+      unicast_listener_ports,
       security_plugins_handle,
-    })
+      health,
+      participant_message_observers: Arc::new(Mutex::new(Vec::new())),
+      always_include_source_timestamp,
+      max_message_size,
+      initial_acknack_delay,
+      builtin_endpoint_set,
+    };
+    Ok((dpi, manual_event_loop))
+  }
+
+  // Returns the locators that differ from self_locators. They are not stored
+  // here, see set_self_locators.
+  fn refresh_locators(&mut self) -> WriteResult<HashMap<mio_06::Token, Vec<Locator>>, ()> {
+    self
+      .stop_poll_sender
+      .send(EventLoopCommand::RefreshNetworkInterfaces)
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("Cannot send to event loop: {e}"),
+        data: (),
+      })?;
+    self.health.event_loop_commands.sent();
+
+    let mut changed = HashMap::new();
+    for (token, port) in &self.unicast_listener_ports {
+      let locators = get_local_unicast_locators(*port);
+      if self.self_locators.get(token) != Some(&locators) {
+        changed.insert(*token, locators);
+      }
+    }
+    Ok(changed)
+  }
+
+  fn set_self_locators(&mut self, changed: HashMap<mio_06::Token, Vec<Locator>>) {
+    for (token, locators) in changed {
+      info!("Locators for {token:?} changed to {locators:?}");
+      self.self_locators.insert(token, locators);
+    }
   }
 
   pub fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
     self.dds_cache.clone()
   }
 
+  pub(crate) fn health_monitor(&self) -> Arc<HealthMonitor> {
+    Arc::clone(&self.health)
+  }
+
+  pub fn thread_names(&self) -> Vec<String> {
+    self.thread_names.clone()
+  }
+
+  pub(crate) fn participant_message_observers(&self) -> ParticipantMessageObservers {
+    Arc::clone(&self.participant_message_observers)
+  }
+
+  // After a background thread has failed, new entities would never be serviced.
+  fn check_not_failed(&self) -> CreateResult<()> {
+    match self.health.failure() {
+      None => Ok(()),
+      Some(reason) => create_error_poisoned!("Participant failed: {reason}"),
+    }
+  }
+
   #[cfg(feature = "security")] // just to avoid warning
   pub(crate) fn qos(&self) -> QosPolicies {
     self.my_qos_policies.clone()
@@ -1195,6 +2406,8 @@ impl DomainParticipantInner {
     qos: &QosPolicies,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   ) -> CreateResult<Publisher> {
+    self.check_not_failed()?;
+    qos.validate()?;
     Ok(Publisher::new(
       domain_participant.clone(),
       self.discovery_db.clone(),
@@ -1204,6 +2417,7 @@ impl DomainParticipantInner {
       self.remove_writer_sender.clone(),
       discovery_command,
       self.security_plugins_handle.clone(),
+      self.health_monitor(),
     ))
   }
 
@@ -1213,6 +2427,8 @@ impl DomainParticipantInner {
     qos: &QosPolicies,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   ) -> CreateResult<Subscriber> {
+    self.check_not_failed()?;
+    qos.validate()?;
     Ok(Subscriber::new(
       domain_participant.clone(),
       self.discovery_db.clone(),
@@ -1221,6 +2437,7 @@ impl DomainParticipantInner {
       self.sender_remove_reader.clone(),
       discovery_command,
       self.security_plugins_handle.clone(),
+      self.health_monitor(),
     ))
   }
 
@@ -1238,6 +2455,10 @@ impl DomainParticipantInner {
     qos: &QosPolicies,
     topic_kind: TopicKind,
   ) -> CreateResult<Topic> {
+    self.check_not_failed()?;
+    let qos = &self.default_topic_qos.modify_by(qos);
+    qos.validate()?;
+
     #[cfg(feature = "security")]
     if let Some(sec_handle) = self.security_plugins_handle.as_ref() {
       // Security is enabled.
@@ -1283,6 +2504,14 @@ impl DomainParticipantInner {
     Ok(topic)
   }
 
+  pub fn get_default_topic_qos(&self) -> &QosPolicies {
+    &self.default_topic_qos
+  }
+
+  pub fn set_default_topic_qos(&mut self, q: &QosPolicies) {
+    self.default_topic_qos = q.clone();
+  }
+
   // Do not implement content filtered topics or multi-topics (yet)
 
   pub fn find_topic(
@@ -1398,6 +2627,21 @@ impl DomainParticipantInner {
 
     db.all_user_topics().cloned().collect()
   }
+
+  pub fn discovery_snapshot(&self) -> DiscoverySnapshot {
+    discovery_db_read(&self.discovery_db).snapshot()
+  }
+
+  pub fn discovered_types(&self) -> Vec<(String, Vec<String>)> {
+    discovery_db_read(&self.discovery_db).types_in_use()
+  }
+
+  pub fn memory_usage(&self) -> MemoryUsage {
+    let mut usage = self.dds_cache.read().unwrap().memory_budget().usage();
+    usage.discovery_db = discovery_db_read(&self.discovery_db).approximate_size();
+    usage
+  }
+
   pub(crate) fn status_channel_receiver(
     &self,
   ) -> &StatusChannelReceiver<DomainParticipantStatusEvent> {
@@ -1440,36 +2684,136 @@ impl std::fmt::Debug for DomainParticipant {
 mod tests {
   use std::{
     collections::BTreeSet,
+    io,
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::{
+      atomic::{AtomicUsize, Ordering},
+      Arc, Mutex,
+    },
+    time::{Duration as StdDuration, Instant},
   };
 
+  use bytes::Bytes;
   use enumflags2::BitFlags;
   use log::info;
   use speedy::{Endianness, Writable};
   use byteorder::LittleEndian;
 
   use crate::{
-    dds::{qos::QosPolicies, topic::TopicKind},
+    dds::{
+      qos::{
+        policy::{History, Reliability},
+        QosPolicies, QosPolicyBuilder,
+      },
+      result::{CreateError, WriteError},
+      statusevents::{
+        DataReaderStatus, DataWriterStatus, DomainParticipantStatusEvent, LostReason, StatusEvented,
+      },
+      topic::TopicKind,
+    },
+    discovery::config::DiscoveryConfig,
     messages::{
       header::Header, protocol_id::ProtocolId, protocol_version::ProtocolVersion,
       submessages::submessages::*, vendor_id::VendorId,
     },
     network::{constant::user_traffic_unicast_port, udp_sender::UDPSender},
     rtps::{submessage::*, Message},
-    serialization::CDRSerializerAdapter,
+    serialization::{
+      compression::{CompressionFallback, PayloadCompression, PayloadCompressor},
+      CDRDeserializerAdapter, CDRSerializerAdapter,
+    },
     structure::{
-      guid::{EntityId, GUID},
+      duration::Duration,
+      entity::RTPSEntity,
+      guid::{EntityId, GuidPrefix, GUID},
       locator::Locator,
       sequence_number::{SequenceNumber, SequenceNumberSet},
     },
-    test::random_data::RandomData,
+    test::{random_data::RandomData, zero_rle::ZeroRle},
   };
-  use super::DomainParticipant;
+  use super::{DomainParticipant, DomainParticipantBuilder};
 
-  // TODO: improve basic test when more or the structure is known
   #[test]
-  fn dp_basic_domain_participant() {
-    // let _dp = DomainParticipant::new();
+  fn dp_seeded_guid_prefix() {
+    let dp = DomainParticipantBuilder::new(0)
+      .guid_prefix_seed(b"dp_seeded_guid_prefix test")
+      .build()
+      .unwrap();
+    assert_eq!(
+      dp.guid().prefix,
+      GuidPrefix::from_seed(b"dp_seeded_guid_prefix test")
+    );
+    assert_eq!(dp.guid().entity_id, EntityId::PARTICIPANT);
+  }
+
+  #[test]
+  fn dp_rejects_foreign_guid_prefix() {
+    let result = DomainParticipantBuilder::new(0)
+      .guid_prefix(GuidPrefix::new(b"FakeTestGUID"))
+      .build();
+    assert!(matches!(result, Err(CreateError::BadParameter { .. })));
+  }
+
+  // TODO: improve basic test when more or the structure is known
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn dp_event_loop_thread_name() {
+    let _dp = DomainParticipantBuilder::new(0)
+      .thread_name("dp_thread_test".to_string())
+      .build()
+      .unwrap();
+    let thread_names: Vec<String> = std::fs::read_dir("/proc/self/task")
+      .unwrap()
+      .filter_map(|task| std::fs::read_to_string(task.ok()?.path().join("comm")).ok())
+      .map(|name| name.trim_end().to_string())
+      .collect();
+    assert!(
+      thread_names.iter().any(|name| name == "dp_thread_test"),
+      "{thread_names:?}"
+    );
+  }
+
+  #[test]
+  fn dp_on_thread_start() {
+    let started = Arc::new(Mutex::new(Vec::new()));
+    let started_clone = Arc::clone(&started);
+    let dp = DomainParticipantBuilder::new(0)
+      .thread_name_prefix("dp_start".to_string())
+      .thread_stack_size(4 * 1024 * 1024)
+      .on_thread_start(move |name| {
+        assert_eq!(std::thread::current().name(), Some(name));
+        started_clone.lock().unwrap().push(name.to_string());
+      })
+      .build()
+      .unwrap();
+    let expected = vec![
+      "dp_start event loop".to_string(),
+      "dp_start discovery".to_string(),
+    ];
+    assert_eq!(dp.thread_names(), expected);
+
+    // Discovery has started when build returns, but the event loop may not
+    // have got that far yet.
+    let deadline = Instant::now() + StdDuration::from_secs(5);
+    while started.lock().unwrap().len() < 2 && Instant::now() < deadline {
+      std::thread::sleep(StdDuration::from_millis(10));
+    }
+    let mut started = started.lock().unwrap().clone();
+    started.sort();
+    let mut expected = expected;
+    expected.sort();
+    assert_eq!(started, expected);
+
+    let manual = DomainParticipantBuilder::new(0)
+      .manual_event_loop(true)
+      .build()
+      .unwrap();
+    assert!(manual.thread_names().is_empty());
+  }
+
+  #[test]
+  fn dp_basic_domain_participant() {
+    // let _dp = DomainParticipant::new();
 
     let sender = UDPSender::new(11401).unwrap();
     let data: Vec<u8> = vec![0, 1, 2, 3, 4];
@@ -1479,6 +2823,917 @@ mod tests {
 
     // TODO: get result data from Reader
   }
+  #[test]
+  fn dp_health_reports_failure() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dp_health_reports_failure".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = publisher
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    // Wait for both background threads to get going, and the first SPDP
+    // announcement to go out
+    let start = std::time::Instant::now();
+    let is_up =
+      |h: &crate::ParticipantHealth| h.is_healthy() && h.since_last_socket_write.is_some();
+    while !is_up(&dp.health()) && start.elapsed() < std::time::Duration::from_secs(5) {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let health = dp.health();
+    assert!(health.is_healthy(), "{health:?}");
+    assert!(health.event_loop.heartbeats > 0);
+    assert!(health.since_last_socket_write.is_some()); // SPDP announcement
+
+    // Simulate what a background thread panic does
+    dp.health_monitor().set_failed("test failure".to_string());
+    assert_eq!(dp.health().failure.as_deref(), Some("test failure"));
+    assert!(matches!(
+      dp.create_publisher(&qos),
+      Err(CreateError::Poisoned { .. })
+    ));
+    assert!(matches!(
+      writer.write(
+        RandomData {
+          a: 1,
+          b: "x".to_string()
+        },
+        None
+      ),
+      Err(WriteError::Poisoned { .. })
+    ));
+  }
+
+  #[test]
+  fn dp_reports_listening_and_remote_locators() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let listening = dp.listening_locators();
+    assert!(!listening.is_empty());
+    assert!(listening.iter().all(Locator::is_udp));
+
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_reports_listening_and_remote_locators".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    // Wait for the local Reader and Writer to match each other
+    let start = std::time::Instant::now();
+    let mut remote_readers = vec![];
+    while remote_readers.is_empty() && start.elapsed() < std::time::Duration::from_secs(5) {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+      remote_readers = writer.remote_reader_locators().unwrap();
+    }
+    assert_eq!(remote_readers.len(), 1);
+    assert_eq!(remote_readers[0].guid, reader.guid());
+    // Our own Reader is reached through the locators we listen on
+    assert!(remote_readers[0]
+      .unicast_locators
+      .iter()
+      .all(|l| listening.contains(l)));
+
+    let remote_writers = reader.remote_writer_locators().unwrap();
+    assert_eq!(remote_writers.len(), 1);
+    assert_eq!(remote_writers[0].guid, writer.guid());
+
+    writer
+      .write(
+        RandomData {
+          a: 1,
+          b: "x".to_string(),
+        },
+        None,
+      )
+      .unwrap();
+    let mut last_used = None;
+    while last_used.is_none() && start.elapsed() < std::time::Duration::from_secs(5) {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+      last_used = writer.remote_reader_locators().unwrap()[0].last_used;
+    }
+    assert!(last_used.is_some());
+  }
+
+  #[test]
+  fn dp_print_diagnostic() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_print_diagnostic".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let start = std::time::Instant::now();
+    while writer.remote_reader_locators().unwrap().is_empty()
+      && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    writer
+      .write(
+        RandomData {
+          a: 1,
+          b: "x".to_string(),
+        },
+        None,
+      )
+      .unwrap();
+
+    let mut writer_text = vec![];
+    writer.print_diagnostic(&mut writer_text).unwrap();
+    let writer_text = String::from_utf8(writer_text).unwrap();
+    assert!(writer_text.contains("dp_print_diagnostic"), "{writer_text}");
+    assert!(
+      writer_text.contains(&format!("{:?}", reader.guid())),
+      "{writer_text}"
+    );
+
+    let mut reader_text = vec![];
+    reader.print_diagnostic(&mut reader_text).unwrap();
+    let reader_text = String::from_utf8(reader_text).unwrap();
+    assert!(reader_text.contains("dp_print_diagnostic"), "{reader_text}");
+    assert!(
+      reader_text.contains(&format!("{:?}", writer.guid())),
+      "{reader_text}"
+    );
+  }
+
+  #[test]
+  fn dp_publication_description() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_publication_description".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    // Write until received, as the Reader may match the Writer only after the
+    // Writer has matched the Reader.
+    let start = std::time::Instant::now();
+    let mut sample = None;
+    while sample.is_none() && start.elapsed() < std::time::Duration::from_secs(10) {
+      writer
+        .write(
+          RandomData {
+            a: 1,
+            b: "x".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      std::thread::sleep(std::time::Duration::from_millis(50));
+      sample = reader.take_next_sample().unwrap();
+    }
+    let handle = sample.unwrap().sample_info().publication_handle();
+    assert_eq!(handle, writer.guid());
+
+    let description = reader.publication_description(handle).unwrap();
+    assert_eq!(description.guid, writer.guid());
+    assert_eq!(description.topic_name, "dp_publication_description");
+    assert_eq!(description.type_name, "RandomData");
+    // Second lookup comes from the cache
+    assert_eq!(
+      reader.publication_description(handle).unwrap().updated_time,
+      description.updated_time
+    );
+    assert!(reader.publication_description(reader.guid()).is_none());
+
+    // The cached description goes away with the Writer.
+    drop(writer);
+    let start = std::time::Instant::now();
+    while reader
+      .get_subscription_matched_status()
+      .current_count
+      .count()
+      > 0
+      && start.elapsed() < std::time::Duration::from_secs(10)
+    {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(reader.publication_description(handle).is_none());
+  }
+
+  #[test]
+  fn dp_manual_event_loop() {
+    let dp = DomainParticipantBuilder::new(0)
+      .manual_event_loop(true)
+      .build()
+      .unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_manual_event_loop".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    // Matching and delivery happen only as we poll.
+    let start = std::time::Instant::now();
+    let mut sample = None;
+    while sample.is_none() && start.elapsed() < std::time::Duration::from_secs(10) {
+      writer
+        .write(
+          RandomData {
+            a: 7,
+            b: "manual".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      for _ in 0..5 {
+        assert!(dp.poll_timeout(std::time::Duration::from_millis(10)));
+      }
+      sample = reader.take_next_sample().unwrap();
+    }
+    assert_eq!(sample.unwrap().into_value().unwrap().a, 7);
+
+    let health = dp.health();
+    assert!(health.is_healthy(), "{health:?}");
+    assert!(health.discovery.heartbeats > 0);
+  }
+
+  #[test]
+  fn dp_discovery_config() {
+    use crate::DiscoveryConfig;
+
+    let invalid = DomainParticipantBuilder::new(0)
+      .discovery_config(DiscoveryConfig {
+        spdp_initial_announcements: 0,
+        ..DiscoveryConfig::default()
+      })
+      .build();
+    assert!(matches!(invalid, Err(CreateError::BadParameter { .. })));
+
+    let dp = DomainParticipantBuilder::new(0)
+      .discovery_config(DiscoveryConfig {
+        spdp_initial_announcements: 3,
+        sedp_reader_history_depth: 16,
+        builtin_heartbeat_period: std::time::Duration::from_millis(500),
+        ..DiscoveryConfig::default()
+      })
+      .build()
+      .unwrap();
+    assert_eq!(dp.health().failure, None);
+  }
+
+  #[test]
+  fn dp_builder_spdp_fields() {
+    use crate::{
+      dds::ddsdata::DDSData,
+      discovery::{BuiltinEndpointSet, SpdpDiscoveredParticipantData},
+      network::constant::spdp_well_known_unicast_port,
+      rtps::constant::builtin_topic_names,
+      serialization::pl_cdr_adapters::PlCdrDeserialize,
+      structure::time::Timestamp,
+    };
+
+    let endpoints = BuiltinEndpointSet::PARTICIPANT_ANNOUNCER
+      | BuiltinEndpointSet::PARTICIPANT_DETECTOR
+      | BuiltinEndpointSet::PUBLICATIONS_ANNOUNCER
+      | BuiltinEndpointSet::PUBLICATIONS_DETECTOR
+      | BuiltinEndpointSet::SUBSCRIPTIONS_ANNOUNCER
+      | BuiltinEndpointSet::SUBSCRIPTIONS_DETECTOR;
+    let dp = DomainParticipantBuilder::new(0)
+      .participant_id(97)
+      .builtin_endpoint_set(endpoints)
+      .spdp_heartbeat_period(StdDuration::from_secs(2))
+      .manual_event_loop(true)
+      .build()
+      .unwrap();
+    assert_eq!(dp.participant_id(), 97);
+
+    // What Discovery announces, as it was written to the SPDP Writer
+    let spdp_writer = GUID::new(dp.guid().prefix, EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER);
+    let topic_cache = dp
+      .dds_cache()
+      .read()
+      .unwrap()
+      .get_existing_topic_cache(builtin_topic_names::DCPS_PARTICIPANT)
+      .unwrap();
+    let announcements = || {
+      topic_cache
+        .lock()
+        .unwrap()
+        .get_changes_in_range_best_effort(Timestamp::ZERO, Timestamp::now())
+        .filter(|(_, cc)| cc.writer_guid == spdp_writer)
+        .map(|(_, cc)| match &cc.data_value {
+          DDSData::Data { serialized_payload } => serialized_payload.clone(),
+          other => panic!("Expected SPDP data, got {other:?}"),
+        })
+        .collect::<Vec<_>>()
+    };
+    let announced_before = announcements().len();
+    dp.with_discovery(|discovery| discovery.spdp_publish(&dp))
+      .unwrap();
+    let deadline = Instant::now() + StdDuration::from_secs(5);
+    while announcements().len() == announced_before && Instant::now() < deadline {
+      dp.poll_timeout(StdDuration::from_millis(10));
+    }
+    let payload = announcements().pop().expect("Discovery did not announce");
+    let spdp_data = SpdpDiscoveredParticipantData::from_pl_cdr_bytes(
+      &payload.value,
+      payload.representation_identifier,
+    )
+    .unwrap();
+    assert_eq!(spdp_data.participant_guid, dp.guid());
+    assert_eq!(
+      spdp_data.available_builtin_endpoints,
+      BuiltinEndpointSet::from_u32(endpoints)
+    );
+    // The lease is five spdp_heartbeat_periods.
+    assert_eq!(
+      spdp_data.lease_duration,
+      Some(Duration::from(StdDuration::from_secs(10)))
+    );
+    let discovery_port = spdp_well_known_unicast_port(0, 97);
+    assert!(!spdp_data.metatraffic_unicast_locators.is_empty());
+    assert!(spdp_data.metatraffic_unicast_locators.iter().all(
+      |loc| matches!(loc, Locator::UdpV4(a) if a.port() == discovery_port)
+        || matches!(loc, Locator::UdpV6(a) if a.port() == discovery_port)
+    ));
+
+    // The requested id is taken, and another one is not picked silently.
+    let taken = DomainParticipantBuilder::new(0).participant_id(97).build();
+    assert!(matches!(taken, Err(CreateError::OutOfResources { .. })));
+
+    let bad_id = DomainParticipantBuilder::new(0).participant_id(120).build();
+    assert!(matches!(bad_id, Err(CreateError::BadParameter { .. })));
+    let no_announcer = DomainParticipantBuilder::new(0)
+      .builtin_endpoint_set(BuiltinEndpointSet::PARTICIPANT_DETECTOR)
+      .build();
+    assert!(matches!(
+      no_announcer,
+      Err(CreateError::BadParameter { .. })
+    ));
+  }
+
+  #[test]
+  fn dp_participant_message_receiver() {
+    use crate::{discovery::ParticipantMessageDataKind, policy::Liveliness};
+
+    let dp = DomainParticipant::new(0).unwrap();
+    let messages = dp.participant_message_receiver().unwrap();
+    let qos = QosPolicyBuilder::new()
+      .liveliness(Liveliness::Automatic {
+        lease_duration: crate::Duration::from_millis(200),
+      })
+      .build();
+    let topic = dp
+      .create_topic(
+        "dp_participant_message_receiver".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let _writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    // Our own automatic liveliness updates are received, too.
+    let start = std::time::Instant::now();
+    let mut received = None;
+    while received.is_none() && start.elapsed() < std::time::Duration::from_secs(5) {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+      while let Ok(message) = messages.try_recv() {
+        if message.guid == dp.guid().prefix {
+          received = Some(message);
+        }
+      }
+    }
+    let message = received.expect("No ParticipantMessage received");
+    assert_eq!(
+      message.kind,
+      ParticipantMessageDataKind::AUTOMATIC_LIVELINESS_UPDATE
+    );
+  }
+
+  #[test]
+  fn dp_health_topic() {
+    use crate::{dds::health::ParticipantHealthSample, with_key::Sample, TopicKind};
+
+    let invalid = DomainParticipantBuilder::new(0)
+      .enable_health_topic(std::time::Duration::ZERO)
+      .build();
+    assert!(matches!(invalid, Err(CreateError::BadParameter { .. })));
+
+    let dp = DomainParticipantBuilder::new(0)
+      .enable_health_topic(std::time::Duration::from_millis(100))
+      .build()
+      .unwrap();
+    let qos = ParticipantHealthSample::qos();
+    let topic = dp
+      .create_topic(
+        ParticipantHealthSample::TOPIC_NAME.to_string(),
+        ParticipantHealthSample::TYPE_NAME.to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<ParticipantHealthSample>(&topic, None)
+      .unwrap();
+
+    let mut received = None;
+    for _ in 0..50 {
+      std::thread::sleep(std::time::Duration::from_millis(100));
+      if let Ok(Some(sample)) = reader.take_next_sample() {
+        if let Sample::Value(health) = sample.into_value() {
+          if health.participant == dp.guid() {
+            received = Some(health);
+            break;
+          }
+        }
+      }
+    }
+    let health = received.expect("No health sample received");
+    assert!(health.healthy);
+    // the health topic writer itself
+    assert!(health.local_writers >= 1);
+  }
+
+  #[test]
+  fn dp_simple_datareader_event_stream() {
+    use futures::{FutureExt, StreamExt};
+
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_simple_datareader_event_stream".to_string(),
+        "u32".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_simple_datareader_no_key::<u32, CDRDeserializerAdapter<u32>>(&topic, None)
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key::<u32, CDRSerializerAdapter<u32>>(&topic, None)
+      .unwrap();
+
+    let mut events = reader.as_async_event_stream();
+    let start = std::time::Instant::now();
+    let mut matched_writer = None;
+    while matched_writer.is_none() && start.elapsed() < std::time::Duration::from_secs(10) {
+      match events.next().now_or_never() {
+        Some(Some(DataReaderStatus::SubscriptionMatched {
+          writer,
+          current,
+          writer_description,
+          ..
+        })) => {
+          assert_eq!(current.count(), 1);
+          // Discovery data of the Writer comes with the match
+          let description = writer_description.expect("No writer description");
+          assert_eq!(description.guid, writer);
+          assert_eq!(description.topic_name, "dp_simple_datareader_event_stream");
+          assert_eq!(description.type_name, "u32");
+          matched_writer = Some(writer);
+        }
+        Some(_) => (),
+        None => std::thread::sleep(std::time::Duration::from_millis(10)),
+      }
+    }
+    assert_eq!(matched_writer, Some(writer.guid()));
+  }
+
+  #[test]
+  fn dp_memory_usage() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_memory_usage".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let before = dp.memory_usage();
+    // The local writer is in the DiscoveryDB already
+    assert!(before.discovery_db > 0, "{before:?}");
+
+    writer
+      .write(
+        RandomData {
+          a: 1,
+          b: "x".repeat(1000),
+        },
+        None,
+      )
+      .unwrap();
+    // The Writer stores the sample in its own thread
+    let start = Instant::now();
+    let mut usage = dp.memory_usage();
+    while usage.writer_histories < before.writer_histories + 1000
+      && start.elapsed() < StdDuration::from_secs(5)
+    {
+      std::thread::sleep(StdDuration::from_millis(10));
+      usage = dp.memory_usage();
+    }
+    assert!(
+      usage.writer_histories >= before.writer_histories + 1000,
+      "{usage:?}"
+    );
+  }
+
+  #[test]
+  fn dp_statistics_snapshot() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dp_statistics_snapshot".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let _writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let _reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let start = std::time::Instant::now();
+    let mut statistics = dp.get_statistics_snapshot();
+    while (statistics.matched_readers == 0 || statistics.matched_writers == 0)
+      && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+      statistics = dp.get_statistics_snapshot();
+    }
+    assert_eq!(statistics.matched_readers, 1, "{statistics:?}");
+    assert_eq!(statistics.matched_writers, 1, "{statistics:?}");
+    // At least the SPDP announcement has been sent
+    assert!(statistics.messages_sent > 0, "{statistics:?}");
+    assert!(
+      statistics.bytes_sent >= statistics.messages_sent,
+      "{statistics:?}"
+    );
+    assert_eq!(statistics.security_handshakes_failed, 0);
+  }
+
+  #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+  struct Zeros {
+    data: Vec<u8>,
+  }
+
+  // Counts compressed samples
+  struct CountingZeroRle(Arc<AtomicUsize>);
+
+  impl PayloadCompressor for CountingZeroRle {
+    fn name(&self) -> &str {
+      ZeroRle.name()
+    }
+
+    fn id(&self) -> u8 {
+      ZeroRle.id()
+    }
+
+    fn compress(&self, data: &Bytes) -> io::Result<Bytes> {
+      self.0.fetch_add(1, Ordering::Relaxed);
+      ZeroRle.compress(data)
+    }
+
+    fn decompress(&self, data: &Bytes, uncompressed_size: usize) -> io::Result<Bytes> {
+      ZeroRle.decompress(data, uncompressed_size)
+    }
+  }
+
+  // Writes one large sample after `expected_readers` have matched, and
+  // returns how many times it was compressed.
+  fn compression_round_trip(
+    topic_name: &str,
+    fallback: CompressionFallback,
+    decompressing_readers: usize,
+    plain_readers: usize,
+    expected_readers: usize,
+  ) -> usize {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(History::KeepAll)
+      .build();
+    let topic = dp
+      .create_topic(
+        topic_name.to_string(),
+        "Zeros".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let compressed = Arc::new(AtomicUsize::new(0));
+    let publisher = dp.create_publisher(&qos).unwrap();
+    publisher.set_payload_compression(Some(
+      PayloadCompression::new(CountingZeroRle(compressed.clone())).fallback(fallback),
+    ));
+    let writer = publisher
+      .create_datawriter_no_key_cdr::<Zeros>(&topic, None)
+      .unwrap();
+
+    let mut readers = vec![];
+    for _ in 0..decompressing_readers {
+      let subscriber = dp.create_subscriber(&qos).unwrap();
+      subscriber.add_payload_decompressor(ZeroRle);
+      readers.push(
+        subscriber
+          .create_datareader_no_key_cdr::<Zeros>(&topic, None)
+          .unwrap(),
+      );
+    }
+    let mut plain = vec![];
+    for _ in 0..plain_readers {
+      plain.push(
+        dp.create_subscriber(&qos)
+          .unwrap()
+          .create_datareader_no_key_cdr::<Zeros>(&topic, None)
+          .unwrap(),
+      );
+    }
+
+    let start = std::time::Instant::now();
+    while writer.remote_reader_locators().unwrap().len() < expected_readers
+      && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    // Give the Readers that should not match a chance to do so
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert_eq!(
+      writer.remote_reader_locators().unwrap().len(),
+      expected_readers
+    );
+
+    // Large enough to be fragmented, unless compressed
+    let sample = Zeros {
+      data: vec![0; 100_000],
+    };
+    writer.write(sample.clone(), None).unwrap();
+
+    if expected_readers < decompressing_readers + plain_readers {
+      readers.truncate(expected_readers);
+    } else {
+      readers.append(&mut plain);
+    }
+    for reader in &mut readers {
+      let mut received = None;
+      while received.is_none() && start.elapsed() < std::time::Duration::from_secs(10) {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        received = reader.take_next_sample().unwrap();
+      }
+      assert_eq!(received.map(|s| s.into_value()), Some(sample.clone()));
+    }
+    compressed.load(Ordering::Relaxed)
+  }
+
+  #[test]
+  fn dp_payload_compression() {
+    let compressed = compression_round_trip(
+      "dp_payload_compression",
+      CompressionFallback::DoNotMatch,
+      1,
+      1,
+      1,
+    );
+    assert_eq!(compressed, 1);
+  }
+
+  #[test]
+  fn dp_payload_compression_fallback() {
+    let compressed = compression_round_trip(
+      "dp_payload_compression_fallback",
+      CompressionFallback::SendUncompressed,
+      1,
+      1,
+      2,
+    );
+    assert_eq!(compressed, 0);
+  }
+
+  #[test]
+  fn dp_deadline_missed_per_instance() {
+    let deadline = Duration::from_millis(200);
+    let qos = QosPolicyBuilder::new()
+      .reliable(Duration::from_millis(100))
+      .deadline(crate::dds::qos::policy::Deadline(deadline))
+      .build();
+    let dp = DomainParticipant::new(0).unwrap();
+    let topic = dp
+      .create_topic(
+        "dp_deadline_missed_per_instance".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let start = std::time::Instant::now();
+    while writer.remote_reader_locators().unwrap().is_empty()
+      && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // Writes the given instances for a while, and returns the keys of the
+    // instances that the Reader and the Writer reported as missed.
+    let mut run = |keys: &[i64], duration_ms: u64| {
+      let phase_start = std::time::Instant::now();
+      while phase_start.elapsed() < std::time::Duration::from_millis(duration_ms) {
+        for &a in keys {
+          writer
+            .write(
+              RandomData {
+                a,
+                b: "deadline".to_string(),
+              },
+              None,
+            )
+            .unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+      }
+      let mut reader_missed = BTreeSet::new();
+      while let Some(status) = reader.try_recv_status() {
+        if let DataReaderStatus::RequestedDeadlineMissed {
+          last_instance: Some(handle),
+          ..
+        } = status
+        {
+          reader_missed.insert(reader.instance_for_handle(handle).unwrap().unwrap());
+        }
+      }
+      let mut writer_missed = BTreeSet::new();
+      while let Some(status) = writer.try_recv_status() {
+        if let DataWriterStatus::OfferedDeadlineMissed {
+          last_instance: Some(handle),
+          ..
+        } = status
+        {
+          let key = [1, 2]
+            .into_iter()
+            .find(|k| writer.lookup_instance(k) == Some(handle));
+          writer_missed.insert(key.unwrap());
+        }
+      }
+      (reader_missed, writer_missed)
+    };
+
+    // Both instances are written. Then each in turn goes silent. Events from
+    // the first deadline period of each phase are discarded, as the check
+    // may still see the previous phase.
+    run(&[1, 2], 500);
+    run(&[1], 300);
+    assert_eq!(run(&[1], 600), (BTreeSet::from([2]), BTreeSet::from([2])));
+    run(&[2], 300);
+    assert_eq!(run(&[2], 600), (BTreeSet::from([1]), BTreeSet::from([1])));
+  }
+
+  #[test]
+  fn dp_preempt_discovery() {
+    // A domain of its own, so that participants of other tests do not interfere.
+    // Rediscovery waits for the next periodic announcement of dp2.
+    let config = DiscoveryConfig {
+      spdp_announcement_period: StdDuration::from_millis(500),
+      ..DiscoveryConfig::default()
+    };
+    let dp1 = DomainParticipant::new(42).expect("Participant creation failed!");
+    let dp2 = DomainParticipantBuilder::new(42)
+      .discovery_config(config)
+      .build()
+      .expect("Participant creation failed!");
+    let remote = dp2.guid().prefix;
+    let status = dp1.status_listener();
+    let knows_remote = || {
+      dp1
+        .discovery_snapshot()
+        .participants
+        .iter()
+        .any(|p| p.guid.prefix == remote)
+    };
+    let wait_until = |cond: &dyn Fn() -> bool| {
+      let start = Instant::now();
+      loop {
+        if cond() {
+          break true;
+        }
+        if start.elapsed() > StdDuration::from_secs(10) {
+          break false;
+        }
+        std::thread::sleep(StdDuration::from_millis(20));
+      }
+    };
+
+    assert!(wait_until(&knows_remote));
+    while status.try_recv_status().is_some() {}
+
+    dp1.preempt_discovery(remote).unwrap();
+    let lost = || {
+      std::iter::from_fn(|| status.try_recv_status()).any(|e| {
+        matches!(e, DomainParticipantStatusEvent::ParticipantLost {
+          id,
+          reason: LostReason::Preempted,
+        } if id == remote)
+      })
+    };
+    assert!(wait_until(&lost));
+    // dp2 is still there, so it is discovered again
+    assert!(wait_until(&knows_remote));
+  }
+
   #[test]
   fn dp_writer_heartbeat_test() {
     let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
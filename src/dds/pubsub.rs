@@ -1,6 +1,6 @@
 use std::{
   fmt::Debug,
-  sync::{Arc, Mutex, MutexGuard, RwLock},
+  sync::{atomic::AtomicBool, Arc, Mutex, MutexGuard, RwLock},
   time::Duration,
 };
 
@@ -11,9 +11,11 @@ use byteorder::LittleEndian;
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_dropped, create_error_internal, create_error_poisoned,
+  create_error_bad_parameter, create_error_dropped, create_error_internal, create_error_poisoned,
   dds::{
     adapters,
+    endpoint_options::EndpointOptions,
+    health::HealthMonitor,
     key::Keyed,
     no_key,
     no_key::{
@@ -22,23 +24,37 @@ use crate::{
     participant::*,
     qos::*,
     result::{CreateError, CreateResult, WaitResult},
-    statusevents::{sync_status_channel, DataReaderStatus},
+    statusevents::{
+      sync_status_channel, DataReaderStatus, EndpointDescription, LivelinessChangedStatus,
+      OfferedIncompatibleQosStatus, PublicationMatchedState, SubscriptionMatchedState,
+    },
     topic::*,
+    wildcard::WildcardSubscription,
     with_key,
     with_key::{
       datareader::DataReader as WithKeyDataReader, datawriter::DataWriter as WithKeyDataWriter,
     },
   },
   discovery::{
-    discovery::DiscoveryCommand, discovery_db::DiscoveryDB, sedp_messages::DiscoveredWriterData,
+    discovery::DiscoveryCommand,
+    discovery_db::DiscoveryDB,
+    payload_compression_property::PayloadCompressionProperty,
+    sedp_messages::{
+      DiscoveredWriterData, PublicationBuiltinTopicData, SubscriptionBuiltinTopicData,
+    },
   },
   mio_source,
   rtps::{
-    reader::ReaderIngredients,
-    writer::{WriterCommand, WriterIngredients},
+    observed_gaps::ObservedGaps,
+    reader::{ReaderFrontEnd, ReaderIngredients},
+    writer::{HistorySpace, SendQueue, WriterCommand, WriterIngredients},
+  },
+  serialization::{
+    compression::{builtin_decompressors, PayloadCompression, PayloadCompressor},
+    CDRDeserializerAdapter, CDRSerializerAdapter,
   },
-  serialization::{CDRDeserializerAdapter, CDRSerializerAdapter},
   structure::{
+    dds_cache::TopicCache,
     entity::RTPSEntity,
     guid::{EntityId, EntityKind, GUID},
   },
@@ -56,6 +72,26 @@ use crate::{
 #[cfg(not(feature = "security"))]
 use crate::no_security::{security_plugins::SecurityPluginsHandle, EndpointSecurityInfo};
 
+// Two local endpoints with the same GUID would receive each other's traffic.
+// This can happen only with a caller-specified EntityId. The check is
+// reliable only when done under the same DiscoveryDB write lock that adds the
+// new endpoint.
+fn check_guid_unused(db: &DiscoveryDB, guid: GUID) -> CreateResult<()> {
+  if db.is_local_endpoint(guid) {
+    return create_error_bad_parameter!("GUID {:?} is already used by a local endpoint", guid);
+  }
+  Ok(())
+}
+
+// Early check, so that e.g. the security plugins are not touched for a GUID
+// that is in use.
+fn precheck_guid_unused(discovery_db: &RwLock<DiscoveryDB>, guid: GUID) -> CreateResult<()> {
+  let db = discovery_db
+    .read()
+    .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
+  check_guid_unused(&db, guid)
+}
+
 // -------------------------------------------------------------------
 
 /// DDS Publisher
@@ -73,6 +109,26 @@ use crate::no_security::{security_plugins::SecurityPluginsHandle, EndpointSecuri
 /// delivered to the readers, or none are. The transaction can span several
 /// readers, writers, and topics in a single publisher/subscriber.
 ///
+/// # QoS of created DataWriters and DataReaders
+///
+/// The QoS given to
+/// [`create_publisher`](crate::DomainParticipant::create_publisher) or
+/// [`create_subscriber`](crate::DomainParticipant::create_subscriber) is the
+/// default for DataWriters and DataReaders created in it. Each policy of a new
+/// DataWriter or DataReader is taken from the first of these that sets it:
+///
+/// 1. The QoS given when creating the DataWriter or DataReader. With `None`,
+///    this step is skipped.
+/// 2. The default DataWriter QoS of the Publisher (see
+///    [`set_default_datawriter_qos`](Self::set_default_datawriter_qos)), or
+///    the QoS of the Subscriber.
+/// 3. The QoS of the Topic.
+/// 4. The [default Topic QoS](crate::DomainParticipant::get_default_topic_qos)
+///    of the DomainParticipant.
+///
+/// Policies that none of these sets have their DDS default values. The
+/// defaults are read when the endpoint is created, so changing them later
+/// does not affect existing endpoints.
 ///
 /// # Examples
 ///
@@ -102,6 +158,7 @@ impl Publisher {
     remove_writer_sender: mio_channel::SyncSender<GUID>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    health: Arc<HealthMonitor>,
   ) -> Self {
     Self {
       inner: Arc::new(Mutex::new(InnerPublisher::new(
@@ -113,6 +170,7 @@ impl Publisher {
         remove_writer_sender,
         discovery_command,
         security_plugins_handle,
+        health,
       ))),
     }
   }
@@ -130,7 +188,8 @@ impl Publisher {
   ///
   /// * `entity_id` - Custom entity id if necessary for the user to define it
   /// * `topic` - Reference to DDS Topic this writer is created to
-  /// * `qos` - Not currently in use
+  /// * `qos` - QoS policies for this DataWriter. These override the defaults,
+  ///   see [QoS of created DataWriters](Self#qos-of-created-datawriters-and-datareaders).
   ///
   /// # Examples
   ///
@@ -162,13 +221,59 @@ impl Publisher {
     topic: &Topic,
     qos: Option<QosPolicies>,
   ) -> CreateResult<WithKeyDataWriter<D, SA>>
+  where
+    D: Keyed,
+    SA: adapters::with_key::SerializerAdapter<D>,
+  {
+    self.create_datawriter_with_options(topic, qos, &EndpointOptions::default())
+  }
+
+  /// Like [`create_datawriter`](Self::create_datawriter), but with
+  /// per-endpoint [`EndpointOptions`], e.g. locators to advertise instead of
+  /// the participant defaults.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use std::net::SocketAddr;
+  /// # use rustdds::*;
+  /// use rustdds::serialization::CDRSerializerAdapter;
+  /// use serde::Serialize;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// // The router forwards 203.0.113.7:7411 to this host.
+  /// let public: SocketAddr = "203.0.113.7:7411".parse().unwrap();
+  /// let options = EndpointOptions::new().unicast_locators(vec![Locator::from(public)]);
+  /// let data_writer = publisher
+  ///   .create_datawriter_with_options::<SomeType, CDRSerializerAdapter<_>>(&topic, None, &options);
+  /// ```
+  pub fn create_datawriter_with_options<D, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    options: &EndpointOptions,
+  ) -> CreateResult<WithKeyDataWriter<D, SA>>
   where
     D: Keyed,
     SA: adapters::with_key::SerializerAdapter<D>,
   {
     self
       .inner_lock()
-      .create_datawriter(self, None, topic, qos, false)
+      .create_datawriter(self, None, topic, qos, false, options)
   }
 
   /// Shorthand for crate_datawriter with Common Data Representation Little
@@ -191,7 +296,8 @@ impl Publisher {
   ///
   /// * `entity_id` - Custom entity id if necessary for the user to define it
   /// * `topic` - Reference to DDS Topic this writer is created to
-  /// * `qos` - QoS policies for this DataWriter
+  /// * `qos` - QoS policies for this DataWriter. These override the defaults,
+  ///   see [QoS of created DataWriters](Self#qos-of-created-datawriters-and-datareaders).
   ///
   /// # Examples
   ///
@@ -216,12 +322,26 @@ impl Publisher {
     topic: &Topic,
     qos: Option<QosPolicies>,
   ) -> CreateResult<NoKeyDataWriter<D, SA>>
+  where
+    SA: adapters::no_key::SerializerAdapter<D>,
+  {
+    self.create_datawriter_no_key_with_options(topic, qos, &EndpointOptions::default())
+  }
+
+  /// Like [`create_datawriter_no_key`](Self::create_datawriter_no_key), but
+  /// with per-endpoint [`EndpointOptions`].
+  pub fn create_datawriter_no_key_with_options<D, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    options: &EndpointOptions,
+  ) -> CreateResult<NoKeyDataWriter<D, SA>>
   where
     SA: adapters::no_key::SerializerAdapter<D>,
   {
     self
       .inner_lock()
-      .create_datawriter_no_key(self, None, topic, qos, false)
+      .create_datawriter_no_key(self, None, topic, qos, false, options)
   }
 
   pub fn create_datawriter_no_key_cdr<D>(
@@ -248,9 +368,14 @@ impl Publisher {
     D: Keyed,
     SA: adapters::with_key::SerializerAdapter<D>,
   {
-    self
-      .inner_lock()
-      .create_datawriter(self, Some(entity_id), topic, qos, writer_like_stateless)
+    self.inner_lock().create_datawriter(
+      self,
+      Some(entity_id),
+      topic,
+      qos,
+      writer_like_stateless,
+      &EndpointOptions::default(),
+    )
   }
 
   #[cfg(feature = "security")] // to avoid "never used" warning
@@ -270,6 +395,7 @@ impl Publisher {
       topic,
       qos,
       writer_like_stateless,
+      &EndpointOptions::default(),
     )
   }
 
@@ -377,10 +503,73 @@ impl Publisher {
     self.inner_lock().set_default_datawriter_qos(q);
   }
 
+  /// Sets payload compression for DataWriters created after this call.
+  /// `None` disables compression.
+  ///
+  /// The algorithm is advertised in Discovery, and samples are compressed
+  /// only while all matched DataReaders can decompress them. See
+  /// [`compression`](crate::serialization::compression) for details.
+  ///
+  /// Changing the setting does not affect existing DataWriters.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use std::io;
+  /// # use bytes::Bytes;
+  /// # use rustdds::*;
+  /// use rustdds::serialization::compression::{
+  ///   CompressionFallback, PayloadCompression, PayloadCompressor,
+  /// };
+  ///
+  /// struct Identity;
+  ///
+  /// impl PayloadCompressor for Identity {
+  ///   fn name(&self) -> &str { "identity" }
+  ///   fn id(&self) -> u8 { 128 }
+  ///   fn compress(&self, data: &Bytes) -> io::Result<Bytes> { Ok(data.clone()) }
+  ///   fn decompress(&self, data: &Bytes, _size: usize) -> io::Result<Bytes> { Ok(data.clone()) }
+  /// }
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// publisher.set_payload_compression(Some(
+  ///   PayloadCompression::new(Identity).fallback(CompressionFallback::DoNotMatch),
+  /// ));
+  /// assert_eq!(
+  ///   publisher.payload_compression().map(|pc| pc.compressor().name().to_string()),
+  ///   Some("identity".to_string())
+  /// );
+  /// ```
+  pub fn set_payload_compression(&self, payload_compression: Option<PayloadCompression>) {
+    self.inner_lock().payload_compression = payload_compression;
+  }
+
+  /// Payload compression for new DataWriters. See
+  /// [`set_payload_compression`](Self::set_payload_compression).
+  pub fn payload_compression(&self) -> Option<PayloadCompression> {
+    self.inner_lock().payload_compression.clone()
+  }
+
   // This is used on DataWriter .drop()
   pub(crate) fn remove_writer(&self, guid: GUID) {
     self.inner_lock().remove_writer(guid);
   }
+
+  // Subscription data of a Reader, as known by Discovery
+  pub(crate) fn reader_data(&self, reader: GUID) -> Option<SubscriptionBuiltinTopicData> {
+    let discovery_db = self.inner_lock().discovery_db.clone();
+    let db = match discovery_db.read() {
+      Ok(db) => db,
+      Err(e) => {
+        error!("Cannot lock discovery_db: {e}");
+        return None;
+      }
+    };
+    db.get_reader(reader)
+      .map(|r| r.subscription_topic_data.clone())
+  }
 } // impl
 
 impl PartialEq for Publisher {
@@ -410,6 +599,8 @@ struct InnerPublisher {
   remove_writer_sender: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  health: Arc<HealthMonitor>,
+  payload_compression: Option<PayloadCompression>, // used when creating a new DataWriter
 }
 
 // public interface for Publisher
@@ -424,6 +615,7 @@ impl InnerPublisher {
     remove_writer_sender: mio_channel::SyncSender<GUID>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    health: Arc<HealthMonitor>,
   ) -> Self {
     // We generate an arbitrary but unique id to distinguish Publishers from each
     // other. EntityKind is just some value, since we do not show it to anyone.
@@ -440,6 +632,8 @@ impl InnerPublisher {
       remove_writer_sender,
       discovery_command,
       security_plugins_handle,
+      health,
+      payload_compression: None,
     }
   }
 
@@ -450,28 +644,40 @@ impl InnerPublisher {
     topic: &Topic,
     optional_qos: Option<QosPolicies>,
     writer_like_stateless: bool, // Create a stateless-like RTPS writer? Usually false
+    options: &EndpointOptions,
   ) -> CreateResult<WithKeyDataWriter<D, SA>>
   where
     D: Keyed,
     SA: adapters::with_key::SerializerAdapter<D>,
   {
+    if let Some(reason) = self.health.failure() {
+      return create_error_poisoned!("Participant failed: {}", reason);
+    }
+    options.validate()?;
     // Data samples from DataWriter to HistoryCache
     let (dwcc_upload, hccc_download) = mio_channel::sync_channel::<WriterCommand>(16);
     let writer_waker = Arc::new(Mutex::new(None));
     // Status reports back from Writer to DataWriter.
     let (status_sender, status_receiver) = sync_status_channel(4)?;
-
-    // DDS Spec 2.2.2.4.1.5 create_datawriter:
-    // If no QoS is specified, we should take the Publisher default
-    // QoS, modify it to match any QoS settings (that are set) in the
-    // Topic QoS and use that.
-
-    // Use Publisher QoS as basis, modify by Topic settings, and modify by specified
-    // QoS.
-    let writer_qos = self
-      .default_datawriter_qos
-      .modify_by(&topic.qos())
+    let publication_matched = Arc::new(Mutex::new(PublicationMatchedState::default()));
+    let offered_incompatible_qos = Arc::new(Mutex::new(OfferedIncompatibleQosStatus::default()));
+    // Set by the Writer when all matched Readers can decompress
+    let compress_payloads = Arc::new(AtomicBool::new(false));
+    // Set by the Writer when some matched Reader gets the key hash inline
+    let send_key_hash = Arc::new(AtomicBool::new(false));
+    // Samples queued by the DataWriter, but not yet processed by the Writer
+    let send_queue = Arc::new(SendQueue::default());
+
+    // Use Topic QoS (which includes the participant default Topic QoS) as basis,
+    // modify by Publisher default, and modify by specified QoS. See the Publisher
+    // docs.
+    let writer_qos = topic
+      .qos()
+      .modify_by(&self.default_datawriter_qos)
       .modify_by(&optional_qos.unwrap_or_else(QosPolicies::qos_none));
+    writer_qos.validate()?;
+    // Room in the Writer history, when limited by QoS
+    let history_space = Arc::new(HistorySpace::for_qos(&writer_qos));
 
     let entity_id =
       self.unwrap_or_new_entity_id(entity_id_opt, EntityKind::WRITER_WITH_KEY_USER_DEFINED);
@@ -481,6 +687,12 @@ impl InnerPublisher {
       .or_else(|e| create_error_dropped!("Where is my DomainParticipant? {}", e))?;
 
     let guid = GUID::new_with_prefix_and_id(dp.guid().prefix, entity_id);
+    precheck_guid_unused(&self.discovery_db, guid)?;
+
+    let memory_budget = match dp.dds_cache().read() {
+      Ok(dds_cache) => dds_cache.memory_budget(),
+      Err(e) => return create_error_poisoned!("Cannot lock DDScache. Error: {}", e),
+    };
 
     #[cfg(feature = "security")]
     if let Some(sec_handle) = self.security_plugins_handle.as_ref() {
@@ -539,35 +751,20 @@ impl InnerPublisher {
       like_stateless: writer_like_stateless,
       qos_policies: writer_qos.clone(),
       status_sender,
+      memory_budget: memory_budget.clone(),
+      publication_matched: publication_matched.clone(),
+      offered_incompatible_qos: offered_incompatible_qos.clone(),
       security_plugins: self.security_plugins_handle.clone(),
+      payload_compression: self.payload_compression.clone(),
+      compress_payloads: Arc::clone(&compress_payloads),
+      send_key_hash: Arc::clone(&send_key_hash),
+      send_queue: Arc::clone(&send_queue),
+      history_space: Arc::clone(&history_space),
+      heartbeat_period: None,
+      nack_response_delay: None,
+      max_message_size: dp.max_message_size(),
     };
 
-    // Send writer ingredients to DP event loop, where the actual writer will be
-    // constructed
-    self
-      .add_writer_sender
-      .send(new_writer)
-      .or_else(|e| create_error_poisoned!("Adding a new writer failed: {}", e))?;
-
-    let data_writer = WithKeyDataWriter::<D, SA>::new(
-      outer.clone(),
-      topic.clone(),
-      writer_qos,
-      guid,
-      dwcc_upload,
-      writer_waker,
-      self.discovery_command.clone(),
-      status_receiver,
-    )?;
-
-    // notify Discovery DB
-    let mut db = self
-      .discovery_db
-      .write()
-      .map_err(|e| CreateError::Poisoned {
-        reason: format!("Discovery DB: {e}"),
-      })?;
-
     #[cfg(not(feature = "security"))]
     let security_info = None;
     #[cfg(feature = "security")]
@@ -596,9 +793,17 @@ impl InnerPublisher {
     };
 
     // Update topic to DiscoveryDB & inform Discovery about it
-    let dwd = DiscoveredWriterData::new(&data_writer, topic, &dp, security_info);
-    db.update_local_topic_writer(dwd);
-    db.update_topic_data_p(topic);
+    {
+      let mut db = self
+        .discovery_db
+        .write()
+        .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
+      check_guid_unused(&db, guid)?;
+      let dwd = DiscoveredWriterData::from_ingredients(&new_writer, topic, &dp, security_info);
+      db.set_local_endpoint_options(guid, options);
+      db.update_local_topic_writer(dwd);
+      db.update_topic_data_p(topic);
+    }
 
     if let Err(e) = self.discovery_command.try_send(DiscoveryCommand::AddTopic {
       topic_name: topic.name(),
@@ -612,6 +817,37 @@ impl InnerPublisher {
       );
     }
 
+    // Send writer ingredients to DP event loop, where the actual writer will be
+    // constructed
+    if let Err(e) = self.add_writer_sender.send(new_writer) {
+      if let Ok(mut db) = self.discovery_db.write() {
+        db.remove_local_topic_writer(guid);
+      }
+      return create_error_poisoned!("Adding a new writer failed: {}", e);
+    }
+    self.health.add_writer.sent();
+
+    let data_writer = WithKeyDataWriter::<D, SA>::new(
+      outer.clone(),
+      topic.clone(),
+      writer_qos,
+      guid,
+      dwcc_upload,
+      writer_waker,
+      self.discovery_command.clone(),
+      status_receiver,
+      publication_matched,
+      offered_incompatible_qos,
+      memory_budget,
+      Arc::clone(&self.health),
+      dp.always_include_source_timestamp(),
+      self.payload_compression.clone(),
+      compress_payloads,
+      send_key_hash,
+      send_queue,
+      history_space,
+    )?;
+
     // Inform Discovery about the new writer
     let writer_guid = self.domain_participant.guid().from_prefix(entity_id);
     self
@@ -635,6 +871,7 @@ impl InnerPublisher {
     topic: &Topic,
     qos: Option<QosPolicies>,
     writer_like_stateless: bool, // Create a stateless-like RTPS writer? Usually false
+    options: &EndpointOptions,
   ) -> CreateResult<NoKeyDataWriter<D, SA>>
   where
     SA: adapters::no_key::SerializerAdapter<D>,
@@ -647,6 +884,7 @@ impl InnerPublisher {
       topic,
       qos,
       writer_like_stateless,
+      options,
     )?;
     Ok(NoKeyDataWriter::<D, SA>::from_keyed(d))
   }
@@ -674,8 +912,10 @@ impl InnerPublisher {
   }
 
   pub(crate) fn remove_writer(&self, guid: GUID) {
-    try_send_timeout(&self.remove_writer_sender, guid, None)
-      .unwrap_or_else(|e| error!("Cannot remove Writer {guid:?} : {e:?}"));
+    match try_send_timeout(&self.remove_writer_sender, guid, None) {
+      Ok(()) => self.health.remove_writer.sent(),
+      Err(e) => error!("Cannot remove Writer {guid:?} : {e:?}"),
+    }
   }
 
   pub(crate) fn identity(&self) -> EntityId {
@@ -725,6 +965,7 @@ pub struct Subscriber {
 }
 
 impl Subscriber {
+  #[allow(clippy::too_many_arguments)]
   pub(super) fn new(
     domain_participant: DomainParticipantWeak,
     discovery_db: Arc<RwLock<DiscoveryDB>>,
@@ -733,6 +974,7 @@ impl Subscriber {
     sender_remove_reader: mio_channel::SyncSender<GUID>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    health: Arc<HealthMonitor>,
   ) -> Self {
     Self {
       inner: Arc::new(InnerSubscriber::new(
@@ -743,6 +985,7 @@ impl Subscriber {
         sender_remove_reader,
         discovery_command,
         security_plugins_handle,
+        health,
       )),
     }
   }
@@ -755,7 +998,8 @@ impl Subscriber {
   ///   reads from
   /// * `entity_id` - Optional [EntityId](data_types/struct.EntityId.html) if
   ///   necessary for DDS communication (random if None)
-  /// * `qos` - Not in use
+  /// * `qos` - QoS policies for this DataReader. These override the defaults,
+  ///   see [QoS of created DataReaders](Publisher#qos-of-created-datawriters-and-datareaders).
   ///
   /// # Examples
   ///
@@ -792,7 +1036,28 @@ impl Subscriber {
     D: 'static + Keyed,
     SA: adapters::with_key::DeserializerAdapter<D>,
   {
-    self.inner.create_datareader(self, topic, None, qos, false)
+    self.create_datareader_with_options(topic, qos, &EndpointOptions::default())
+  }
+
+  /// Like [`create_datareader`](Self::create_datareader), but with
+  /// per-endpoint [`EndpointOptions`], e.g. locators to advertise instead of
+  /// the participant defaults.
+  ///
+  /// A DataReader with its own locators always gets its own RTPS Reader, even
+  /// if [Reader sharing](Self::set_reader_fan_out) is enabled.
+  pub fn create_datareader_with_options<D, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    options: &EndpointOptions,
+  ) -> CreateResult<WithKeyDataReader<D, SA>>
+  where
+    D: 'static + Keyed,
+    SA: adapters::with_key::DeserializerAdapter<D>,
+  {
+    self
+      .inner
+      .create_datareader(self, topic, None, qos, false, options)
   }
 
   pub fn create_datareader_cdr<D>(
@@ -807,6 +1072,53 @@ impl Subscriber {
     self.create_datareader::<D, CDRDeserializerAdapter<D>>(topic, qos)
   }
 
+  /// Create a CDR DataReader that inherits the QoS of the Topic, except for
+  /// the policies set in `override_qos`.
+  ///
+  /// `override_qos` is typically built with
+  /// [`QosPolicyBuilder`](crate::QosPolicyBuilder), which leaves the policies
+  /// that were not explicitly set undefined. For example, to change only the
+  /// History depth and keep the Reliability of the Topic:
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// # use serde::Deserialize;
+  /// # #[derive(Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #   fn key(&self) -> Self::K { self.a }
+  /// # }
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// let topic = domain_participant
+  ///   .create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey)
+  ///   .unwrap();
+  ///
+  /// let depth = QosPolicyBuilder::new()
+  ///   .history(policy::History::KeepLast { depth: 10 })
+  ///   .build();
+  /// let data_reader = subscriber
+  ///   .create_datareader_cdr_with_qos_override::<SomeType>(&topic, depth)
+  ///   .unwrap();
+  /// ```
+  ///
+  /// The Subscriber QoS is applied between the two, as with
+  /// [`create_datareader_cdr`](Self::create_datareader_cdr), so this is the
+  /// same as `create_datareader_cdr(topic, Some(override_qos))`.
+  pub fn create_datareader_cdr_with_qos_override<D>(
+    &self,
+    topic: &Topic,
+    override_qos: QosPolicies,
+  ) -> CreateResult<WithKeyDataReader<D, CDRDeserializerAdapter<D>>>
+  where
+    D: 'static + serde::de::DeserializeOwned + Keyed,
+    for<'de> <D as Keyed>::K: Deserialize<'de>,
+  {
+    self.create_datareader_cdr(topic, Some(override_qos))
+  }
+
   /// Create DDS DataReader for non keyed Topics
   ///
   /// # Arguments
@@ -815,7 +1127,8 @@ impl Subscriber {
   ///   reads from
   /// * `entity_id` - Optional [EntityId](data_types/struct.EntityId.html) if
   ///   necessary for DDS communication (random if None)
-  /// * `qos` - Not in use
+  /// * `qos` - QoS policies for this DataReader. These override the defaults,
+  ///   see [QoS of created DataReaders](Publisher#qos-of-created-datawriters-and-datareaders).
   ///
   /// # Examples
   ///
@@ -841,13 +1154,28 @@ impl Subscriber {
     topic: &Topic,
     qos: Option<QosPolicies>,
   ) -> CreateResult<NoKeyDataReader<D, SA>>
+  where
+    D: 'static,
+    SA: adapters::no_key::DeserializerAdapter<D>,
+  {
+    self.create_datareader_no_key_with_options(topic, qos, &EndpointOptions::default())
+  }
+
+  /// Like [`create_datareader_no_key`](Self::create_datareader_no_key), but
+  /// with per-endpoint [`EndpointOptions`].
+  pub fn create_datareader_no_key_with_options<D, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    options: &EndpointOptions,
+  ) -> CreateResult<NoKeyDataReader<D, SA>>
   where
     D: 'static,
     SA: adapters::no_key::DeserializerAdapter<D>,
   {
     self
       .inner
-      .create_datareader_no_key(self, topic, None, qos, false)
+      .create_datareader_no_key(self, topic, None, qos, false, options)
   }
 
   pub fn create_simple_datareader_no_key<D, DA>(
@@ -875,6 +1203,60 @@ impl Subscriber {
     self.create_datareader_no_key::<D, CDRDeserializerAdapter<D>>(topic, qos)
   }
 
+  /// Subscribes to all topics whose name matches `pattern`, e.g.
+  /// `"telemetry/*"`, including topics that are discovered later.
+  ///
+  /// A DataReader with QoS `qos` is created for each matching topic that has
+  /// DataWriters. It is deleted when the topic has had no DataWriters for
+  /// `grace_period`. See [`WildcardSubscription`] for details. Topics of
+  /// unknown types can be read with
+  /// [`RawDeserializerAdapter`](crate::serialization::RawDeserializerAdapter).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use std::time::Duration;
+  /// # use rustdds::*;
+  /// use serde::Deserialize;
+  /// use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Debug, Deserialize)]
+  /// struct Telemetry {
+  ///   value: f64,
+  /// }
+  ///
+  /// let mut telemetry = subscriber
+  ///   .create_wildcard_subscription::<Telemetry, CDRDeserializerAdapter<_>>(
+  ///     "telemetry/*",
+  ///     Duration::from_secs(10),
+  ///     None,
+  ///   );
+  /// while let Ok(Some(event)) = telemetry.take_next_event() {
+  ///   match event {
+  ///     WildcardEvent::Sample { topic_name, sample } => {
+  ///       println!("{topic_name}: {:?}", sample.value())
+  ///     }
+  ///     other => println!("{other:?}"),
+  ///   }
+  /// }
+  /// ```
+  pub fn create_wildcard_subscription<D, DA>(
+    &self,
+    pattern: &str,
+    grace_period: std::time::Duration,
+    qos: Option<QosPolicies>,
+  ) -> WildcardSubscription<D, DA>
+  where
+    D: 'static,
+    DA: adapters::no_key::DefaultDecoder<D>,
+  {
+    WildcardSubscription::new(self.clone(), pattern, grace_period, qos)
+  }
+
   // versions with callee-specified EntityId. These are for Discovery use only.
 
   pub(crate) fn create_datareader_with_entity_id_with_key<D, SA>(
@@ -888,9 +1270,14 @@ impl Subscriber {
     D: 'static + Keyed,
     SA: adapters::with_key::DeserializerAdapter<D>,
   {
-    self
-      .inner
-      .create_datareader(self, topic, Some(entity_id), qos, reader_like_stateless)
+    self.inner.create_datareader(
+      self,
+      topic,
+      Some(entity_id),
+      qos,
+      reader_like_stateless,
+      &EndpointOptions::default(),
+    )
   }
 
   #[cfg(feature = "security")] // to avoid "never used" warning
@@ -905,9 +1292,14 @@ impl Subscriber {
     D: 'static,
     SA: adapters::no_key::DeserializerAdapter<D>,
   {
-    self
-      .inner
-      .create_datareader_no_key(self, topic, Some(entity_id), qos, reader_like_stateless)
+    self.inner.create_datareader_no_key(
+      self,
+      topic,
+      Some(entity_id),
+      qos,
+      reader_like_stateless,
+      &EndpointOptions::default(),
+    )
   }
 
   // Retrieves a previously created DataReader belonging to the Subscriber.
@@ -946,11 +1338,157 @@ impl Subscriber {
     self.inner.participant()
   }
 
-  pub(crate) fn remove_reader(&self, guid: GUID) {
-    self.inner.remove_reader(guid);
+  /// Enables or disables sharing of the underlying RTPS Reader between
+  /// DataReaders of this Subscriber ("fan-out"). Disabled by default.
+  ///
+  /// When enabled, a DataReader created on a Topic, for which this Subscriber
+  /// already has a DataReader created while fan-out was enabled, and that has
+  /// the same resulting QoS, shares the RTPS Reader of the existing one. Each
+  /// sharing DataReader is an independent view to the received samples, but
+  /// the differences to separate DataReaders are:
+  ///
+  /// * There is only one Reader visible to the network. The sharing
+  ///   DataReaders have the same GUID, and they are matched to Writers and
+  ///   acknowledge samples as one. Received data is stored only once.
+  /// * Reading or taking samples affects only the DataReader that does it.
+  ///   Taking does not free the sample from the shared storage. Samples are
+  ///   held according to the History and ResourceLimits QoS policies,
+  ///   regardless of whether some DataReader has not yet read them, so a slow
+  ///   DataReader may miss samples.
+  /// * Status events from the Reader, such as matching and liveliness changes,
+  ///   are delivered to each DataReader. Status values returned by e.g.
+  ///   [`get_subscription_matched_status`](crate::with_key::DataReader::get_subscription_matched_status)
+  ///   are shared, so getting one resets the `count_change` values for all of
+  ///   the sharing DataReaders.
+  /// * The Reader is removed only when all of the sharing DataReaders have
+  ///   been dropped.
+  ///
+  /// Changing the setting does not affect existing DataReaders.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::Deserialize;
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # #[derive(Deserialize)]
+  /// # struct SomeType {}
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// subscriber.set_reader_fan_out(true);
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// let reader_1 = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  /// let reader_2 = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  /// assert_eq!(reader_1.guid(), reader_2.guid());
+  /// ```
+  pub fn set_reader_fan_out(&self, enabled: bool) {
+    self.inner.reader_fan_out.lock().unwrap().enabled = enabled;
+  }
+
+  /// Is sharing of RTPS Readers enabled. See
+  /// [`set_reader_fan_out`](Self::set_reader_fan_out).
+  pub fn reader_fan_out(&self) -> bool {
+    self.inner.reader_fan_out.lock().unwrap().enabled
+  }
+
+  /// Allows DataReaders created after this call to decompress payloads
+  /// compressed with `decompressor`. The algorithms enabled by cargo
+  /// features are available without this. See
+  /// [`compression`](crate::serialization::compression).
+  ///
+  /// A decompressor replaces any previous one with the same name.
+  pub fn add_payload_decompressor(&self, decompressor: impl PayloadCompressor + 'static) {
+    let mut decompressors = self.inner.payload_decompressors.lock().unwrap();
+    decompressors.retain(|d| d.name() != decompressor.name());
+    decompressors.push(Arc::new(decompressor));
+  }
+
+  pub(crate) fn payload_decompressors(&self) -> Vec<Arc<dyn PayloadCompressor>> {
+    self.inner.payload_decompressors.lock().unwrap().clone()
+  }
+
+  // Returns false, if the Reader is still used by other DataReaders, and
+  // was not removed.
+  pub(crate) fn remove_reader(&self, guid: GUID) -> bool {
+    self.inner.remove_reader(guid)
+  }
+
+  // QoS of a Writer, as known by Discovery
+  pub(crate) fn writer_qos(&self, writer: GUID) -> Option<QosPolicies> {
+    match self.inner.discovery_db.read() {
+      Ok(db) => db.writer_qos(writer),
+      Err(e) => {
+        error!("Cannot lock discovery_db: {e}");
+        None
+      }
+    }
+  }
+
+  // Description of a Writer, as known by Discovery
+  pub(crate) fn writer_description(&self, writer: GUID) -> Option<EndpointDescription> {
+    match self.inner.discovery_db.read() {
+      Ok(db) => db.get_writer(writer).map(EndpointDescription::from),
+      Err(e) => {
+        error!("Cannot lock discovery_db: {e}");
+        None
+      }
+    }
+  }
+
+  // Publication data of a Writer, as known by Discovery
+  pub(crate) fn writer_data(&self, writer: GUID) -> Option<PublicationBuiltinTopicData> {
+    match self.inner.discovery_db.read() {
+      Ok(db) => db
+        .get_writer(writer)
+        .map(|w| w.publication_topic_data.clone()),
+      Err(e) => {
+        error!("Cannot lock discovery_db: {e}");
+        None
+      }
+    }
+  }
+}
+
+impl PartialEq for Subscriber {
+  // Clones of a Subscriber are the same Subscriber
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.inner, &other.inner)
   }
 }
 
+impl Debug for Subscriber {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_fmt(format_args!("{:?}", self.participant()))?;
+    f.write_fmt(format_args!("Subscriber QoS: {:?}", self.inner.qos))
+  }
+}
+
+// Readers that can be shared by DataReaders. See
+// Subscriber::set_reader_fan_out.
+#[derive(Default)]
+struct ReaderFanOut {
+  enabled: bool,
+  shared_readers: Vec<SharedReader>,
+}
+
+// What another DataReader needs to share an existing Reader
+struct SharedReader {
+  guid: GUID,
+  topic_name: String,
+  qos: QosPolicies,
+  topic_cache_handle: Arc<Mutex<TopicCache>>,
+  reader_command: mio_channel::SyncSender<ReaderCommand>,
+  observed_gaps: Arc<Mutex<ObservedGaps>>,
+  subscription_matched: Arc<Mutex<SubscriptionMatchedState>>,
+  liveliness_changed: Arc<Mutex<LivelinessChangedStatus>>,
+  additional_front_ends: Arc<Mutex<Vec<ReaderFrontEnd>>>,
+  data_reader_count: usize,
+}
+
 #[derive(Clone)]
 pub struct InnerSubscriber {
   domain_participant: DomainParticipantWeak,
@@ -960,9 +1498,13 @@ pub struct InnerSubscriber {
   sender_remove_reader: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  health: Arc<HealthMonitor>,
+  reader_fan_out: Arc<Mutex<ReaderFanOut>>,
+  payload_decompressors: Arc<Mutex<Vec<Arc<dyn PayloadCompressor>>>>,
 }
 
 impl InnerSubscriber {
+  #[allow(clippy::too_many_arguments)]
   pub(super) fn new(
     domain_participant: DomainParticipantWeak,
     discovery_db: Arc<RwLock<DiscoveryDB>>,
@@ -971,6 +1513,7 @@ impl InnerSubscriber {
     sender_remove_reader: mio_channel::SyncSender<GUID>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    health: Arc<HealthMonitor>,
   ) -> Self {
     Self {
       domain_participant,
@@ -980,6 +1523,9 @@ impl InnerSubscriber {
       sender_remove_reader,
       discovery_command,
       security_plugins_handle,
+      health,
+      reader_fan_out: Arc::default(),
+      payload_decompressors: Arc::new(Mutex::new(builtin_decompressors())),
     }
   }
 
@@ -990,6 +1536,7 @@ impl InnerSubscriber {
     topic: &Topic,
     optional_qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
+    options: &EndpointOptions,
   ) -> CreateResult<WithKeyDataReader<D, SA>>
   where
     D: 'static + Keyed,
@@ -1001,6 +1548,7 @@ impl InnerSubscriber {
       topic,
       optional_qos,
       reader_like_stateless,
+      options,
     )?;
     Ok(with_key::DataReader::<D, SA>::from_simple_data_reader(
       simple_dr,
@@ -1014,11 +1562,16 @@ impl InnerSubscriber {
     topic: &Topic,
     optional_qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
+    options: &EndpointOptions,
   ) -> CreateResult<with_key::SimpleDataReader<D, SA>>
   where
     D: 'static + Keyed,
     SA: adapters::with_key::DeserializerAdapter<D>,
   {
+    if let Some(reason) = self.health.failure() {
+      return create_error_poisoned!("Participant failed: {}", reason);
+    }
+    options.validate()?;
     // incoming data notification channel from Reader to DataReader
     let (send, rec) = mio_channel::sync_channel::<()>(4);
     // status change channel from Reader to DataReader
@@ -1026,18 +1579,29 @@ impl InnerSubscriber {
 
     // reader command channel from Datareader to Reader
     let (reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(0);
-    // The buffer length is zero, i.e. sender and receiver must rendezvous at
-    // send/receive. This is needed to synchronize sending of wakers from
-    // DataReader to Reader. If the capacity is increased, then some data
-    // available for reading notifications may be missed.
-
-    // Use subscriber QoS as basis, modify by Topic settings, and modify by
-    // specified QoS.
-    let qos = self
-      .qos
-      .modify_by(&topic.qos())
+      mio_channel::sync_channel::<ReaderCommand>(4);
+    // Wakers are not sent over this channel, but shared through
+    // data_reader_waker, so the channel may buffer. It must, because the event
+    // loop only polls it, so a rendezvous send would never succeed.
+
+    // Use Topic QoS as basis, modify by Subscriber QoS, and modify by specified
+    // QoS. The same order as with DataWriters, see the Publisher docs.
+    let qos = topic
+      .qos()
+      .modify_by(&self.qos)
       .modify_by(&optional_qos.unwrap_or_else(QosPolicies::qos_none));
+    qos.validate()?;
+
+    // Built-in Readers and Readers with their own locators are never shared
+    let shareable = !reader_like_stateless
+      && entity_id_opt.map_or(true, |e| e.entity_kind.is_user_defined())
+      && !options.has_locators()
+      && self.reader_fan_out.lock().unwrap().enabled;
+    if shareable {
+      if let Some(datareader) = self.join_shared_reader(outer, topic, &qos)? {
+        return Ok(datareader);
+      }
+    }
 
     let entity_id =
       self.unwrap_or_new_entity_id(entity_id_opt, EntityKind::READER_WITH_KEY_USER_DEFINED);
@@ -1059,6 +1623,7 @@ impl InnerSubscriber {
     };
 
     let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), entity_id);
+    precheck_guid_unused(&self.discovery_db, reader_guid)?;
 
     #[cfg(feature = "security")]
     if let Some(sec_handle) = self.security_plugins_handle.as_ref() {
@@ -1110,13 +1675,17 @@ impl InnerSubscriber {
     }
 
     let data_reader_waker = Arc::new(Mutex::new(None));
+    let observed_gaps = Arc::new(Mutex::new(ObservedGaps::new()));
+    let subscription_matched = Arc::new(Mutex::new(SubscriptionMatchedState::default()));
+    let liveliness_changed = Arc::new(Mutex::new(LivelinessChangedStatus::default()));
 
     let (poll_event_source, poll_event_sender) = mio_source::make_poll_channel()?;
+    let additional_front_ends = Arc::new(Mutex::new(Vec::new()));
 
     let new_reader = ReaderIngredients {
       guid: reader_guid,
       notification_sender: send,
-      status_sender,
+      status_sender: status_sender.clone(),
       topic_name: topic.name(),
       topic_cache_handle: topic_cache_handle.clone(),
       like_stateless: reader_like_stateless,
@@ -1124,7 +1693,12 @@ impl InnerSubscriber {
       data_reader_command_receiver: reader_command_receiver,
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender,
+      observed_gaps: observed_gaps.clone(),
+      subscription_matched: subscription_matched.clone(),
+      liveliness_changed: liveliness_changed.clone(),
+      additional_front_ends: additional_front_ends.clone(),
       security_plugins: self.security_plugins_handle.clone(),
+      initial_acknack_delay: dp.initial_acknack_delay(),
     };
 
     #[cfg(not(feature = "security"))]
@@ -1160,7 +1734,15 @@ impl InnerSubscriber {
         .discovery_db
         .write()
         .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
-      db.update_local_topic_reader(&dp, topic, &new_reader, security_info);
+      check_guid_unused(&db, reader_guid)?;
+      db.set_local_endpoint_options(reader_guid, options);
+      db.update_local_topic_reader(
+        &dp,
+        topic,
+        &new_reader,
+        security_info,
+        self.payload_compression_property(reader_guid),
+      );
       db.update_topic_data_p(topic);
 
       if let Err(e) = self.discovery_command.try_send(DiscoveryCommand::AddTopic {
@@ -1176,6 +1758,26 @@ impl InnerSubscriber {
       }
     }
 
+    if shareable {
+      self
+        .reader_fan_out
+        .lock()
+        .unwrap()
+        .shared_readers
+        .push(SharedReader {
+          guid: reader_guid,
+          topic_name: topic.name(),
+          qos: qos.clone(),
+          topic_cache_handle: topic_cache_handle.clone(),
+          reader_command: reader_command_sender.clone(),
+          observed_gaps: observed_gaps.clone(),
+          subscription_matched: subscription_matched.clone(),
+          liveliness_changed: liveliness_changed.clone(),
+          additional_front_ends,
+          data_reader_count: 1,
+        });
+    }
+
     let datareader = with_key::SimpleDataReader::<D, SA>::new(
       outer.clone(),
       entity_id,
@@ -1185,8 +1787,12 @@ impl InnerSubscriber {
       topic_cache_handle,
       self.discovery_command.clone(),
       status_receiver,
+      status_sender,
       reader_command_sender,
       data_reader_waker,
+      observed_gaps,
+      subscription_matched,
+      liveliness_changed,
       poll_event_source,
     )?;
 
@@ -1196,6 +1802,7 @@ impl InnerSubscriber {
       .sender_add_reader
       .try_send(new_reader)
       .or_else(|e| create_error_poisoned!("Cannot add DataReader. Error: {}", e))?;
+    self.health.add_reader.sent();
 
     // Inform Discovery about the new reader
     let reader_guid = self.domain_participant.guid().from_prefix(entity_id);
@@ -1213,6 +1820,87 @@ impl InnerSubscriber {
     Ok(datareader)
   }
 
+  // Advertised in Discovery. Built-in Readers do not decompress.
+  fn payload_compression_property(&self, reader_guid: GUID) -> Option<PayloadCompressionProperty> {
+    if !reader_guid.entity_id.entity_kind.is_user_defined() {
+      return None;
+    }
+    let algorithms: Vec<String> = self
+      .payload_decompressors
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|d| d.name().to_string())
+      .collect();
+    if algorithms.is_empty() {
+      None
+    } else {
+      Some(PayloadCompressionProperty {
+        algorithms,
+        required: false,
+      })
+    }
+  }
+
+  // Creates a DataReader that shares an existing Reader with the same Topic and
+  // QoS, if there is one.
+  fn join_shared_reader<D, SA>(
+    &self,
+    outer: &Subscriber,
+    topic: &Topic,
+    qos: &QosPolicies,
+  ) -> CreateResult<Option<with_key::SimpleDataReader<D, SA>>>
+  where
+    D: 'static + Keyed,
+    SA: adapters::with_key::DeserializerAdapter<D>,
+  {
+    let mut fan_out = self.reader_fan_out.lock().unwrap();
+    let shared = match fan_out
+      .shared_readers
+      .iter_mut()
+      .find(|s| s.topic_name == topic.name() && s.qos == *qos)
+    {
+      Some(shared) => shared,
+      None => return Ok(None),
+    };
+
+    let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(4);
+    let (status_sender, status_receiver) = sync_status_channel::<DataReaderStatus>(4)?;
+    let (poll_event_source, poll_event_sender) = mio_source::make_poll_channel()?;
+    let data_reader_waker = Arc::new(Mutex::new(None));
+
+    let datareader = with_key::SimpleDataReader::<D, SA>::new(
+      outer.clone(),
+      shared.guid.entity_id,
+      topic.clone(),
+      qos.clone(),
+      notification_receiver,
+      shared.topic_cache_handle.clone(),
+      self.discovery_command.clone(),
+      status_receiver,
+      status_sender.clone(),
+      shared.reader_command.clone(),
+      data_reader_waker.clone(),
+      shared.observed_gaps.clone(),
+      shared.subscription_matched.clone(),
+      shared.liveliness_changed.clone(),
+      poll_event_source,
+    )?;
+
+    shared
+      .additional_front_ends
+      .lock()
+      .unwrap()
+      .push(ReaderFrontEnd {
+        notification_sender,
+        status_sender,
+        data_reader_waker,
+        poll_event_sender,
+      });
+    shared.data_reader_count += 1;
+    Ok(Some(datareader))
+  }
+
   pub fn create_datareader<D, SA>(
     &self,
     outer: &Subscriber,
@@ -1220,6 +1908,7 @@ impl InnerSubscriber {
     entity_id: Option<EntityId>,
     qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
+    options: &EndpointOptions,
   ) -> CreateResult<WithKeyDataReader<D, SA>>
   where
     D: 'static + Keyed,
@@ -1228,7 +1917,7 @@ impl InnerSubscriber {
     if topic.kind() != TopicKind::WithKey {
       return Err(CreateError::TopicKind(TopicKind::WithKey));
     }
-    self.create_datareader_internal(outer, entity_id, topic, qos, reader_like_stateless)
+    self.create_datareader_internal(outer, entity_id, topic, qos, reader_like_stateless, options)
   }
 
   pub fn create_datareader_no_key<D: 'static, SA>(
@@ -1238,6 +1927,7 @@ impl InnerSubscriber {
     entity_id_opt: Option<EntityId>,
     qos: Option<QosPolicies>,
     reader_like_stateless: bool, // Create a stateless-like RTPS reader? Usually false
+    options: &EndpointOptions,
   ) -> CreateResult<NoKeyDataReader<D, SA>>
   where
     SA: adapters::no_key::DeserializerAdapter<D>,
@@ -1255,6 +1945,7 @@ impl InnerSubscriber {
       topic,
       qos,
       reader_like_stateless,
+      options,
     )?;
 
     Ok(NoKeyDataReader::<D, SA>::from_keyed(d))
@@ -1283,6 +1974,7 @@ impl InnerSubscriber {
       topic,
       qos,
       false,
+      &EndpointOptions::default(),
     )?;
 
     Ok(no_key::SimpleDataReader::<D, SA>::from_keyed(d))
@@ -1292,9 +1984,22 @@ impl InnerSubscriber {
     self.domain_participant.clone().upgrade()
   }
 
-  pub(crate) fn remove_reader(&self, guid: GUID) {
-    try_send_timeout(&self.sender_remove_reader, guid, None)
-      .unwrap_or_else(|e| error!("Cannot remove Reader {guid:?} : {e:?}"));
+  pub(crate) fn remove_reader(&self, guid: GUID) -> bool {
+    {
+      let mut fan_out = self.reader_fan_out.lock().unwrap();
+      if let Some(pos) = fan_out.shared_readers.iter().position(|s| s.guid == guid) {
+        fan_out.shared_readers[pos].data_reader_count -= 1;
+        if fan_out.shared_readers[pos].data_reader_count > 0 {
+          return false;
+        }
+        fan_out.shared_readers.remove(pos);
+      }
+    }
+    match try_send_timeout(&self.sender_remove_reader, guid, None) {
+      Ok(()) => self.health.remove_reader.sent(),
+      Err(e) => error!("Cannot remove Reader {guid:?} : {e:?}"),
+    }
+    true
   }
 
   fn unwrap_or_new_entity_id(
@@ -1311,4 +2016,306 @@ impl InnerSubscriber {
 // -------------------------------------------------------------------
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+  use std::net::SocketAddr;
+
+  use super::*;
+  use crate::{
+    dds::{
+      discovery_snapshot::EndpointSnapshot,
+      qos::policy::Reliability,
+      with_key::{DataReader, DataWriter},
+    },
+    structure::locator::Locator,
+    test::random_data::RandomData,
+  };
+
+  #[test]
+  fn duplicate_entity_id_rejected() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "duplicate_guid".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData>> =
+      publisher.create_datawriter(&topic, None).unwrap();
+    let duplicate = publisher
+      .create_datawriter_with_entity_id_with_key::<RandomData, CDRSerializerAdapter<RandomData>>(
+        writer.guid().entity_id,
+        &topic,
+        None,
+        false,
+      );
+    assert!(matches!(duplicate, Err(CreateError::BadParameter { .. })));
+
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    let reader: DataReader<RandomData> = subscriber.create_datareader(&topic, None).unwrap();
+    let duplicate = subscriber
+      .create_datareader_with_entity_id_with_key::<RandomData, CDRDeserializerAdapter<RandomData>>(
+        &topic,
+        reader.guid().entity_id,
+        None,
+        false,
+      );
+    assert!(matches!(duplicate, Err(CreateError::BadParameter { .. })));
+
+    // A free EntityId is fine
+    let entity_id = dp.new_entity_id(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    assert!(publisher
+      .create_datawriter_with_entity_id_with_key::<RandomData, CDRSerializerAdapter<RandomData>>(
+        entity_id, &topic, None, false,
+      )
+      .is_ok());
+  }
+
+  #[test]
+  fn subscriber_reader_fan_out() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "fan_out".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let create = |subscriber: &Subscriber, qos: Option<QosPolicies>| -> DataReader<RandomData> {
+      subscriber.create_datareader(&topic, qos).unwrap()
+    };
+
+    // Not shared by default
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    assert!(!subscriber.reader_fan_out());
+    assert_ne!(
+      create(&subscriber, None).guid(),
+      create(&subscriber, None).guid()
+    );
+
+    subscriber.set_reader_fan_out(true);
+    let reader_1 = create(&subscriber, None);
+    let reader_2 = create(&subscriber, None);
+    assert_eq!(reader_1.guid(), reader_2.guid());
+
+    // Different QoS needs a Reader of its own
+    let reliable = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::from_millis(100),
+      })
+      .build();
+    assert_ne!(create(&subscriber, Some(reliable)).guid(), reader_1.guid());
+
+    // The shared Reader stays as long as some DataReader uses it
+    let guid = reader_1.guid();
+    drop(reader_1);
+    let reader_3 = create(&subscriber, None);
+    assert_eq!(reader_3.guid(), guid);
+    drop(reader_2);
+    drop(reader_3);
+    assert_ne!(create(&subscriber, None).guid(), guid);
+  }
+
+  #[test]
+  fn matched_endpoint_data() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "matched_endpoint_data".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let best_effort = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let writer: DataWriter<RandomData> = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter(&topic, Some(best_effort))
+      .unwrap();
+    // Requests Reliable, which the BestEffort writer does not offer
+    let reliable = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::ZERO,
+      })
+      .build();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    let reader: DataReader<RandomData> = subscriber.create_datareader(&topic, None).unwrap();
+    let unmatched_reader: DataReader<RandomData> = subscriber
+      .create_datareader(&topic, Some(reliable))
+      .unwrap();
+
+    // Matching is done by the event loop thread
+    let eventually = |f: &dyn Fn() -> bool| {
+      let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+      while !f() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+      }
+    };
+    eventually(&|| {
+      writer
+        .get_matched_subscription_data(reader.guid())
+        .is_some()
+        && reader.get_matched_publication_data(writer.guid()).is_some()
+    });
+    let subscription = writer
+      .get_matched_subscription_data(reader.guid())
+      .expect("Reader not matched");
+    assert_eq!(subscription.topic_name(), "matched_endpoint_data");
+    assert_eq!(subscription.type_name(), "RandomData");
+    let publication = reader
+      .get_matched_publication_data(writer.guid())
+      .expect("Writer not matched");
+    assert_eq!(publication.topic_name(), "matched_endpoint_data");
+
+    assert!(writer
+      .get_matched_subscription_data(unmatched_reader.guid())
+      .is_none());
+    assert!(unmatched_reader
+      .get_matched_publication_data(writer.guid())
+      .is_none());
+    assert!(writer
+      .get_matched_subscription_data(writer.guid())
+      .is_none());
+    assert!(reader.get_matched_publication_data(reader.guid()).is_none());
+
+    // A Reader that is gone is no longer matched
+    let reader_guid = reader.guid();
+    drop(reader);
+    eventually(&|| writer.get_matched_subscription_data(reader_guid).is_none());
+    assert!(writer.get_matched_subscription_data(reader_guid).is_none());
+  }
+
+  #[test]
+  fn endpoint_qos_precedence() {
+    use crate::{
+      dds::{
+        qos::{policy::*, HasQoSPolicy},
+        with_key::DataWriter,
+      },
+      structure::duration::Duration as DdsDuration,
+      CDRSerializerAdapter,
+    };
+
+    let deadline = |s| Deadline(DdsDuration::from_secs(s));
+    let keep_last = |depth| History::KeepLast { depth };
+
+    // Each level sets one policy fewer than the one before, so that every
+    // policy is decided at a different level.
+    let dp = DomainParticipant::new(0).unwrap();
+    dp.set_default_topic_qos(
+      &QosPolicyBuilder::new()
+        .durability(Durability::TransientLocal)
+        .reliability(Reliability::BestEffort)
+        .deadline(deadline(1))
+        .history(keep_last(1))
+        .build(),
+    );
+    let topic = dp
+      .create_topic(
+        "endpoint_qos_precedence".to_string(),
+        "RandomData".to_string(),
+        &QosPolicyBuilder::new()
+          .reliability(Reliability::Reliable {
+            max_blocking_time: DdsDuration::ZERO,
+          })
+          .deadline(deadline(2))
+          .history(keep_last(2))
+          .build(),
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let endpoint_default = QosPolicyBuilder::new()
+      .deadline(deadline(3))
+      .history(keep_last(3))
+      .build();
+    let explicit = QosPolicyBuilder::new().history(keep_last(4)).build();
+
+    let check = |qos: QosPolicies, history_depth| {
+      assert_eq!(qos.durability(), Some(Durability::TransientLocal));
+      assert!(qos.is_reliable());
+      assert_eq!(qos.deadline(), Some(deadline(3)));
+      assert_eq!(qos.history(), Some(keep_last(history_depth)));
+    };
+
+    let publisher = dp.create_publisher(&endpoint_default).unwrap();
+    let create_writer = |qos| -> DataWriter<RandomData, CDRSerializerAdapter<RandomData>> {
+      publisher.create_datawriter(&topic, qos).unwrap()
+    };
+    check(create_writer(None).qos(), 3);
+    check(create_writer(Some(explicit.clone())).qos(), 4);
+
+    let subscriber = dp.create_subscriber(&endpoint_default).unwrap();
+    let create_reader =
+      |qos| -> DataReader<RandomData> { subscriber.create_datareader(&topic, qos).unwrap() };
+    check(create_reader(None).qos(), 3);
+    check(create_reader(Some(explicit.clone())).qos(), 4);
+    let overridden = subscriber
+      .create_datareader_cdr_with_qos_override::<RandomData>(&topic, explicit)
+      .unwrap();
+    check(overridden.qos(), 4);
+  }
+
+  #[test]
+  fn endpoint_options_locators() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "endpoint_options".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let unicast = Locator::from(SocketAddr::from(([203, 0, 113, 7], 7411)));
+    let multicast = Locator::from(SocketAddr::from(([239, 255, 0, 7], 7401)));
+    let options = EndpointOptions::new()
+      .unicast_locators(vec![unicast])
+      .multicast_locators(vec![multicast]);
+
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData>> = publisher
+      .create_datawriter_with_options(&topic, None, &options)
+      .unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    subscriber.set_reader_fan_out(true);
+    let default_reader: DataReader<RandomData> =
+      subscriber.create_datareader(&topic, None).unwrap();
+    let reader: DataReader<RandomData> = subscriber
+      .create_datareader_with_options(&topic, None, &options)
+      .unwrap();
+    // Not shared, as the locators differ
+    assert_ne!(reader.guid(), default_reader.guid());
+
+    let snapshot = dp.discovery_snapshot();
+    let endpoint = |endpoints: &[EndpointSnapshot], guid| {
+      let e = endpoints.iter().find(|e| e.guid == guid).unwrap();
+      (e.unicast_locators.clone(), e.multicast_locators.clone())
+    };
+    let advertised = (vec![unicast.to_string()], vec![multicast.to_string()]);
+    assert_eq!(endpoint(&snapshot.local_writers, writer.guid()), advertised);
+    assert_eq!(endpoint(&snapshot.local_readers, reader.guid()), advertised);
+    assert_ne!(
+      endpoint(&snapshot.local_readers, default_reader.guid()),
+      advertised
+    );
+
+    let bad = EndpointOptions::new()
+      .unicast_locators(vec![Locator::from(SocketAddr::from(([203, 0, 113, 7], 0)))]);
+    assert!(matches!(
+      publisher.create_datawriter_with_options::<RandomData, CDRSerializerAdapter<RandomData>>(
+        &topic, None, &bad
+      ),
+      Err(CreateError::BadParameter { .. })
+    ));
+  }
+}
@@ -32,7 +32,7 @@ pub trait MutQosPolicy {
 /// DDS spec 2.3.3 defines this as "long" with named constants from 0 to 22.
 /// numbering is from IDL PSM, but it should be unnecessary at the Rust
 /// application interface
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize)]
 pub enum QosPolicyId {
   // Invalid  // We should represent this using Option<QosPolicyId> where needed
   // UserData,  // 1
@@ -55,13 +55,63 @@ pub enum QosPolicyId {
   ResourceLimits,
   // EntityFactory, // 15
   // WriterDataLifeCycle,
-  // ReaderDataLifeCycle, // 17
+  ReaderDataLifecycle, // 17
   // TopicData, // 18
   // GroupData,
   // TransportPriority, // 20
   Lifespan,
   // DurabilityService, // 22
-  Property, // No Id in the security spec (But this is from older DDS/RTPs spec.)
+  Property,     // No Id in the security spec (But this is from older DDS/RTPs spec.)
+  MaxSampleAge, // RustDDS extension, no Id
+  StalledReaderDetection, // RustDDS extension, no Id
+}
+
+/// Consistency rules checked by [`QosPolicies::validate`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum QosConsistencyRule {
+  /// HISTORY KeepLast depth must be positive.
+  HistoryDepthNotPositive,
+  /// RESOURCE_LIMITS values must be positive or LENGTH_UNLIMITED (-1).
+  ResourceLimitNotPositive,
+  /// RESOURCE_LIMITS max_samples must be at least max_samples_per_instance.
+  MaxSamplesBelowMaxSamplesPerInstance,
+  /// HISTORY KeepLast depth must not exceed RESOURCE_LIMITS
+  /// max_samples_per_instance.
+  HistoryDepthAboveMaxSamplesPerInstance,
+  /// DEADLINE period must be at least TIME_BASED_FILTER minimum_separation.
+  DeadlineBelowMinimumSeparation,
+  /// LIFESPAN duration must be positive. RustDDS-specific: a zero lifespan
+  /// would expire all samples immediately.
+  LifespanNotPositive,
+  /// MAX_SAMPLE_AGE duration must be positive.
+  MaxSampleAgeNotPositive,
+  /// READER_DATA_LIFECYCLE autopurge delays must not be negative.
+  AutopurgeDelayNegative,
+  /// STALLED_READER_DETECTION stall timeout must be positive.
+  StallTimeoutNotPositive,
+}
+
+impl std::fmt::Display for QosConsistencyRule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let text = match self {
+      Self::HistoryDepthNotPositive => "History depth must be positive",
+      Self::ResourceLimitNotPositive => "Resource limits must be positive or unlimited (-1)",
+      Self::MaxSamplesBelowMaxSamplesPerInstance => {
+        "max_samples must be at least max_samples_per_instance"
+      }
+      Self::HistoryDepthAboveMaxSamplesPerInstance => {
+        "History depth must not exceed max_samples_per_instance"
+      }
+      Self::DeadlineBelowMinimumSeparation => {
+        "Deadline period must be at least TimeBasedFilter minimum_separation"
+      }
+      Self::LifespanNotPositive => "Lifespan duration must be positive",
+      Self::MaxSampleAgeNotPositive => "MaxSampleAge duration must be positive",
+      Self::AutopurgeDelayNegative => "ReaderDataLifecycle autopurge delays must not be negative",
+      Self::StallTimeoutNotPositive => "StalledReaderDetection stall timeout must be positive",
+    };
+    f.write_str(text)
+  }
 }
 
 /// Utility for building [QosPolicies]
@@ -79,6 +129,10 @@ pub struct QosPolicyBuilder {
   history: Option<policy::History>,
   resource_limits: Option<policy::ResourceLimits>,
   lifespan: Option<policy::Lifespan>,
+  reader_data_lifecycle: Option<policy::ReaderDataLifecycle>,
+  max_sample_age: Option<policy::MaxSampleAge>,
+  stalled_reader_detection: Option<policy::StalledReaderDetection>,
+  inline_qos: Option<policy::InlineQos>,
   // #[cfg(feature = "security")]
   // property: Option<policy::Property>,
   //
@@ -104,6 +158,10 @@ impl QosPolicyBuilder {
       history: None,
       resource_limits: None,
       lifespan: None,
+      reader_data_lifecycle: None,
+      max_sample_age: None,
+      stalled_reader_detection: None,
+      inline_qos: None,
     }
   }
 
@@ -191,6 +249,60 @@ impl QosPolicyBuilder {
     self
   }
 
+  /// For DataReaders. See [`policy::ReaderDataLifecycle`].
+  #[must_use]
+  pub const fn reader_data_lifecycle(
+    mut self,
+    reader_data_lifecycle: policy::ReaderDataLifecycle,
+  ) -> Self {
+    self.reader_data_lifecycle = Some(reader_data_lifecycle);
+    self
+  }
+
+  /// RustDDS extension for DataReaders. See [`policy::MaxSampleAge`].
+  #[must_use]
+  pub const fn max_sample_age(mut self, max_sample_age: policy::MaxSampleAge) -> Self {
+    self.max_sample_age = Some(max_sample_age);
+    self
+  }
+
+  /// RustDDS extension for DataWriters. See
+  /// [`policy::StalledReaderDetection`].
+  #[must_use]
+  pub const fn stalled_reader_detection(
+    mut self,
+    stalled_reader_detection: policy::StalledReaderDetection,
+  ) -> Self {
+    self.stalled_reader_detection = Some(stalled_reader_detection);
+    self
+  }
+
+  /// RustDDS extension for DataWriters. See [`policy::InlineQos`].
+  #[must_use]
+  pub const fn inline_qos(mut self, inline_qos: policy::InlineQos) -> Self {
+    self.inline_qos = Some(inline_qos);
+    self
+  }
+
+  /// Like [`build`](Self::build), but also checks that the policies are
+  /// consistent. See [`QosPolicies::validate`].
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  ///
+  /// let result = QosPolicyBuilder::new()
+  ///   .history(policy::History::KeepLast { depth: 0 })
+  ///   .build_validated();
+  /// assert!(result.is_err());
+  /// ```
+  pub fn build_validated(self) -> Result<QosPolicies, QosError> {
+    let qos = self.build();
+    qos.validate()?;
+    Ok(qos)
+  }
+
   pub const fn build(self) -> QosPolicies {
     QosPolicies {
       durability: self.durability,
@@ -205,6 +317,10 @@ impl QosPolicyBuilder {
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      reader_data_lifecycle: self.reader_data_lifecycle,
+      max_sample_age: self.max_sample_age,
+      stalled_reader_detection: self.stalled_reader_detection,
+      inline_qos: self.inline_qos,
       #[cfg(feature = "security")]
       property: None,
     }
@@ -229,6 +345,10 @@ pub struct QosPolicies {
   pub(crate) history: Option<policy::History>,
   pub(crate) resource_limits: Option<policy::ResourceLimits>,
   pub(crate) lifespan: Option<policy::Lifespan>,
+  pub(crate) reader_data_lifecycle: Option<policy::ReaderDataLifecycle>,
+  pub(crate) max_sample_age: Option<policy::MaxSampleAge>,
+  pub(crate) stalled_reader_detection: Option<policy::StalledReaderDetection>,
+  pub(crate) inline_qos: Option<policy::InlineQos>,
   #[cfg(feature = "security")]
   pub(crate) property: Option<policy::Property>,
 }
@@ -308,6 +428,22 @@ impl QosPolicies {
     self.lifespan
   }
 
+  pub const fn reader_data_lifecycle(&self) -> Option<policy::ReaderDataLifecycle> {
+    self.reader_data_lifecycle
+  }
+
+  pub const fn max_sample_age(&self) -> Option<policy::MaxSampleAge> {
+    self.max_sample_age
+  }
+
+  pub const fn stalled_reader_detection(&self) -> Option<policy::StalledReaderDetection> {
+    self.stalled_reader_detection
+  }
+
+  pub const fn inline_qos(&self) -> Option<policy::InlineQos> {
+    self.inline_qos
+  }
+
   #[cfg(feature = "security")]
   pub fn property(&self) -> Option<policy::Property> {
     self.property.clone()
@@ -333,6 +469,12 @@ impl QosPolicies {
       history: other.history.or(self.history),
       resource_limits: other.resource_limits.or(self.resource_limits),
       lifespan: other.lifespan.or(self.lifespan),
+      reader_data_lifecycle: other.reader_data_lifecycle.or(self.reader_data_lifecycle),
+      max_sample_age: other.max_sample_age.or(self.max_sample_age),
+      stalled_reader_detection: other
+        .stalled_reader_detection
+        .or(self.stalled_reader_detection),
+      inline_qos: other.inline_qos.or(self.inline_qos),
       #[cfg(feature = "security")]
       property: other.property.clone().or(self.property.clone()),
     }
@@ -438,6 +580,103 @@ impl QosPolicies {
     None
   }
 
+  /// Checks that the policies have valid values and are consistent with each
+  /// other, as required by DDS spec v1.4 Section "2.2.3 Supported QoS", plus
+  /// some RustDDS-specific constraints. See [`QosConsistencyRule`].
+  ///
+  /// Policies that are not set are not checked. Entity creation checks the
+  /// QoS resulting from inheritance, so it may fail even if the given QoS
+  /// itself validates.
+  pub fn validate(&self) -> Result<(), QosError> {
+    use QosConsistencyRule as Rule;
+
+    let fail = |policies: &[QosPolicyId], rule| {
+      Err(QosError::Inconsistent {
+        policies: policies.to_vec(),
+        rule,
+      })
+    };
+
+    if let Some(policy::History::KeepLast { depth }) = self.history {
+      if depth <= 0 {
+        return fail(&[QosPolicyId::History], Rule::HistoryDepthNotPositive);
+      }
+    }
+
+    if let Some(limits) = self.resource_limits {
+      let is_valid = |limit: i32| limit > 0 || limit == LENGTH_UNLIMITED;
+      if !(is_valid(limits.max_samples)
+        && is_valid(limits.max_instances)
+        && is_valid(limits.max_samples_per_instance))
+      {
+        return fail(
+          &[QosPolicyId::ResourceLimits],
+          Rule::ResourceLimitNotPositive,
+        );
+      }
+      if limits.max_samples > 0
+        && limits.max_samples_per_instance > 0
+        && limits.max_samples < limits.max_samples_per_instance
+      {
+        return fail(
+          &[QosPolicyId::ResourceLimits],
+          Rule::MaxSamplesBelowMaxSamplesPerInstance,
+        );
+      }
+      if let Some(policy::History::KeepLast { depth }) = self.history {
+        if limits.max_samples_per_instance > 0 && depth > limits.max_samples_per_instance {
+          return fail(
+            &[QosPolicyId::History, QosPolicyId::ResourceLimits],
+            Rule::HistoryDepthAboveMaxSamplesPerInstance,
+          );
+        }
+      }
+    }
+
+    if let (Some(deadline), Some(filter)) = (self.deadline, self.time_based_filter) {
+      if deadline.0 < filter.minimum_separation {
+        return fail(
+          &[QosPolicyId::Deadline, QosPolicyId::TimeBasedFilter],
+          Rule::DeadlineBelowMinimumSeparation,
+        );
+      }
+    }
+
+    if let Some(lifespan) = self.lifespan {
+      if lifespan.duration <= Duration::ZERO {
+        return fail(&[QosPolicyId::Lifespan], Rule::LifespanNotPositive);
+      }
+    }
+
+    if let Some(max_sample_age) = self.max_sample_age {
+      if max_sample_age.duration <= Duration::ZERO {
+        return fail(&[QosPolicyId::MaxSampleAge], Rule::MaxSampleAgeNotPositive);
+      }
+    }
+
+    if let Some(lifecycle) = self.reader_data_lifecycle {
+      if lifecycle.autopurge_nowriters_samples_delay < Duration::ZERO
+        || lifecycle.autopurge_disposed_samples_delay < Duration::ZERO
+      {
+        return fail(
+          &[QosPolicyId::ReaderDataLifecycle],
+          Rule::AutopurgeDelayNegative,
+        );
+      }
+    }
+
+    if let Some(detection) = self.stalled_reader_detection {
+      if detection.stall_timeout <= Duration::ZERO {
+        return fail(
+          &[QosPolicyId::StalledReaderDetection],
+          Rule::StallTimeoutNotPositive,
+        );
+      }
+    }
+
+    Ok(())
+  }
+
   // serialization
   pub fn to_parameter_list(
     &self,
@@ -459,6 +698,10 @@ impl QosPolicies {
       history,
       resource_limits,
       lifespan,
+      reader_data_lifecycle: _, // local to the DataReader, not sent in Discovery
+      max_sample_age: _,        // local to the DataReader, not sent in Discovery
+      stalled_reader_detection: _, // local to the DataWriter, not sent in Discovery
+      inline_qos: _,            // local to the DataWriter, not sent in Discovery
       #[cfg(feature = "security")]
         property: _, // TODO: properties to parameter list?
     } = self;
@@ -623,6 +866,10 @@ impl QosPolicies {
       history,
       resource_limits,
       lifespan,
+      reader_data_lifecycle: None,
+      max_sample_age: None,
+      stalled_reader_detection: None,
+      inline_qos: None,
       #[cfg(feature = "security")]
       property,
     })
@@ -702,6 +949,111 @@ pub mod policy {
     pub duration: Duration,
   }
 
+  /// DDS 2.2.3.21 READER_DATA_LIFECYCLE
+  ///
+  /// How long a DataReader keeps instances that are no longer alive. When an
+  /// instance has been NOT_ALIVE_DISPOSED for `autopurge_disposed_samples_delay`,
+  /// or NOT_ALIVE_NO_WRITERS for `autopurge_nowriters_samples_delay`, the
+  /// DataReader removes the instance and all of its samples, also those not
+  /// yet read. No status event is sent. This bounds the memory used by
+  /// instances that come and go, e.g. one instance per remote participant.
+  ///
+  /// The expired instances are purged when the DataReader is read or taken,
+  /// and also periodically: the DataReader is woken up every few seconds, so
+  /// that a DataReader waiting on its async stream or on a mio poll purges
+  /// them even if no new samples arrive. The default delays are
+  /// [`Duration::INFINITE`], i.e. non-alive instances are kept.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub struct ReaderDataLifecycle {
+    pub autopurge_nowriters_samples_delay: Duration,
+    pub autopurge_disposed_samples_delay: Duration,
+  }
+
+  impl Default for ReaderDataLifecycle {
+    fn default() -> Self {
+      Self {
+        autopurge_nowriters_samples_delay: Duration::INFINITE,
+        autopurge_disposed_samples_delay: Duration::INFINITE,
+      }
+    }
+  }
+
+  /// RustDDS extension: bounded-latency delivery for DataReaders.
+  ///
+  /// Samples whose source timestamp is older than `duration` are not
+  /// delivered to the application, but counted as lost (see
+  /// [`DataReaderStatus::SampleLost`](crate::DataReaderStatus::SampleLost)).
+  /// A Reliable DataReader does not wait for such samples to be repaired, so
+  /// that newer samples are not blocked behind them: a sample that is still
+  /// missing is given up, when a later sample from the same DataWriter is
+  /// older than `duration`.
+  ///
+  /// This relies on source timestamps, so the clocks of the DataWriter and
+  /// DataReader hosts should be synchronized. Samples without a source
+  /// timestamp are never dropped.
+  ///
+  /// This policy is not sent in Discovery, and does not affect matching.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub struct MaxSampleAge {
+    pub duration: Duration,
+  }
+
+  /// RustDDS extension: detection of stalled Reliable DataReaders.
+  ///
+  /// A matched Reliable DataReader stalls, when it has not acknowledged any
+  /// new samples for `stall_timeout`, although some samples are waiting for
+  /// its acknowledgement. This happens e.g. when the remote process is stopped,
+  /// but its participant lease has not yet expired, or when a NAT mapping
+  /// breaks. Then the DataWriter reports
+  /// [`DataWriterStatus::ReaderStalled`](crate::DataWriterStatus::ReaderStalled),
+  /// and [`ReaderRecovered`](crate::DataWriterStatus::ReaderRecovered) when
+  /// the DataReader acknowledges progress again. The check is done once per
+  /// heartbeat period.
+  ///
+  /// If `exclude_stalled_readers` is set, a stalled DataReader does not hold
+  /// back the DataWriter: history is released regardless of its
+  /// acknowledgements, so that writes do not block on it, and
+  /// [`wait_for_acknowledgments`](crate::with_key::DataWriter::wait_for_acknowledgments)
+  /// does not wait for it. It may then miss some samples, if it recovers.
+  ///
+  /// A stalled DataReader stays matched. It is unmatched only when Discovery
+  /// notices that it is gone, e.g. its participant lease expires. Stall
+  /// detection is meant to act before that, so `stall_timeout` should be
+  /// shorter than the lease duration of the remote participants. If the
+  /// lease expires first, the DataReader is unmatched without a stall report,
+  /// and a stalled DataReader that gets unmatched is not reported as
+  /// recovered. If the DataReader is discovered again, its stall timeout
+  /// starts from the new match.
+  ///
+  /// Heartbeats, including manual liveliness assertions, are still sent to
+  /// stalled DataReaders, so that they do not lose the liveliness of the
+  /// DataWriter while stalled.
+  ///
+  /// This policy is not sent in Discovery, and does not affect matching.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub struct StalledReaderDetection {
+    pub stall_timeout: Duration,
+    pub exclude_stalled_readers: bool,
+  }
+
+  /// RustDDS extension: Which DataReaders get optional inline QoS, i.e. the
+  /// key hash, in each DATA submessage.
+  ///
+  /// By default, a DataWriter sends inline QoS only to DataReaders that
+  /// announce `expectsInlineQos` in Discovery. Some peers will not process
+  /// keyed samples or disposes without the key hash, but do not announce that
+  /// they expect it. Inline QoS that a sample cannot do without, e.g. the
+  /// status info of a dispose, is always sent.
+  ///
+  /// This policy is not sent in Discovery, and does not affect matching.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub enum InlineQos {
+    /// Only to DataReaders that expect it. Same as not setting the policy.
+    AsExpected,
+    /// To all DataReaders
+    Always,
+  }
+
   /// DDS 2.2.3.4 DURABILITY
   ///
   /// DDS Spec 1.4:
@@ -1072,3 +1424,141 @@ pub mod policy {
     }
   }
 } // mod policy
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{dds::result::CreateError, DomainParticipant, TopicKind};
+
+  #[test]
+  fn qos_validation_rules() {
+    use policy::*;
+    use QosConsistencyRule as Rule;
+
+    let limits = |max_samples, max_instances, max_samples_per_instance| ResourceLimits {
+      max_samples,
+      max_instances,
+      max_samples_per_instance,
+    };
+
+    let cases = [
+      (
+        QosPolicyBuilder::new().history(History::KeepLast { depth: 0 }),
+        Rule::HistoryDepthNotPositive,
+      ),
+      (
+        QosPolicyBuilder::new().resource_limits(limits(10, 0, 5)),
+        Rule::ResourceLimitNotPositive,
+      ),
+      (
+        QosPolicyBuilder::new().resource_limits(limits(-2, 1, 1)),
+        Rule::ResourceLimitNotPositive,
+      ),
+      (
+        QosPolicyBuilder::new()
+          .history(History::KeepAll)
+          .resource_limits(limits(5, 1, 10)),
+        Rule::MaxSamplesBelowMaxSamplesPerInstance,
+      ),
+      (
+        QosPolicyBuilder::new()
+          .history(History::KeepLast { depth: 20 })
+          .resource_limits(limits(100, 10, 10)),
+        Rule::HistoryDepthAboveMaxSamplesPerInstance,
+      ),
+      (
+        QosPolicyBuilder::new()
+          .deadline(Deadline(Duration::from_millis(10)))
+          .time_based_filter(TimeBasedFilter {
+            minimum_separation: Duration::from_millis(100),
+          }),
+        Rule::DeadlineBelowMinimumSeparation,
+      ),
+      (
+        QosPolicyBuilder::new().lifespan(Lifespan {
+          duration: Duration::ZERO,
+        }),
+        Rule::LifespanNotPositive,
+      ),
+      (
+        QosPolicyBuilder::new().max_sample_age(MaxSampleAge {
+          duration: Duration::ZERO,
+        }),
+        Rule::MaxSampleAgeNotPositive,
+      ),
+      (
+        QosPolicyBuilder::new().reader_data_lifecycle(ReaderDataLifecycle {
+          autopurge_disposed_samples_delay: Duration::from_secs(-1),
+          ..ReaderDataLifecycle::default()
+        }),
+        Rule::AutopurgeDelayNegative,
+      ),
+      (
+        QosPolicyBuilder::new().stalled_reader_detection(StalledReaderDetection {
+          stall_timeout: Duration::ZERO,
+          exclude_stalled_readers: false,
+        }),
+        Rule::StallTimeoutNotPositive,
+      ),
+    ];
+
+    for (builder, expected_rule) in cases {
+      match builder.clone().build_validated() {
+        Err(QosError::Inconsistent { rule, .. }) => assert_eq!(rule, expected_rule),
+        other => panic!("{builder:?} should fail with {expected_rule:?}, got {other:?}"),
+      }
+    }
+
+    // Valid combinations, including unlimited resources
+    let valid = [
+      QosPolicyBuilder::new(),
+      QosPolicyBuilder::new()
+        .history(History::KeepLast { depth: 10 })
+        .resource_limits(limits(LENGTH_UNLIMITED, 5, 10)),
+      QosPolicyBuilder::new()
+        .history(History::KeepAll)
+        .resource_limits(limits(100, LENGTH_UNLIMITED, LENGTH_UNLIMITED)),
+      QosPolicyBuilder::new()
+        .deadline(Deadline(Duration::from_millis(100)))
+        .time_based_filter(TimeBasedFilter {
+          minimum_separation: Duration::from_millis(100),
+        })
+        .lifespan(Lifespan {
+          duration: Duration::from_secs(1),
+        }),
+    ];
+    for builder in valid {
+      assert!(builder.clone().build_validated().is_ok(), "{builder:?}");
+    }
+  }
+
+  #[test]
+  fn qos_validation_applies_to_inherited_qos() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let topic_qos = QosPolicyBuilder::new()
+      .time_based_filter(policy::TimeBasedFilter {
+        minimum_separation: Duration::from_millis(100),
+      })
+      .build();
+    let topic = dp
+      .create_topic(
+        "qos_validation_topic".to_string(),
+        "Foo".to_string(),
+        &topic_qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let subscriber = dp.create_subscriber(&QosPolicies::qos_none()).unwrap();
+
+    // The reader QoS is fine on its own, but not combined with the Topic QoS.
+    let reader_qos = QosPolicyBuilder::new()
+      .deadline(policy::Deadline(Duration::from_millis(10)))
+      .build();
+    assert!(reader_qos.validate().is_ok());
+    let result = subscriber.create_datareader_no_key::<u32, crate::CDRDeserializerAdapter<u32>>(
+      &topic,
+      Some(reader_qos),
+    );
+    assert!(matches!(result, Err(CreateError::BadParameter { .. })));
+  }
+}
@@ -8,6 +8,23 @@ use crate::dds::sampleinfo::*;
 /// To be selected, the current state of the sample must be included in the
 /// corresponding bitflags.
 /// See DDS Specification 1.4 Section "2.2.2.5.8 ReadCondition"
+///
+/// Common conditions have shorthand constructors [`any`](Self::any) and
+/// [`not_read`](Self::not_read). Others can be made with
+/// [`builder`](Self::builder).
+///
+/// # Examples
+///
+/// ```
+/// # use rustdds::*;
+/// // Unread samples of alive instances, regardless of view state
+/// let condition = ReadCondition::builder()
+///   .sample_state(SampleState::NotRead)
+///   .view_state(ViewState::New | ViewState::NotNew)
+///   .instance_state(InstanceState::Alive)
+///   .build();
+/// assert_eq!(*condition.instance_state_mask(), InstanceState::Alive);
+/// ```
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct ReadCondition {
   sample_state_mask: BitFlags<SampleState>,
@@ -36,6 +53,12 @@ impl ReadCondition {
     }
   }
 
+  /// Start building a condition. States that are not set in the builder
+  /// accept any value.
+  pub fn builder() -> ReadConditionBuilder {
+    ReadConditionBuilder::new()
+  }
+
   pub fn sample_state_mask(&self) -> &BitFlags<SampleState> {
     &self.sample_state_mask
   }
@@ -47,4 +70,122 @@ impl ReadCondition {
   pub fn instance_state_mask(&self) -> &BitFlags<InstanceState> {
     &self.instance_state_mask
   }
+
+  /// Would a sample with this [`SampleInfo`] be selected by this condition?
+  pub fn matches(&self, sample_info: &SampleInfo) -> bool {
+    self.matches_states(
+      sample_info.sample_state(),
+      sample_info.view_state(),
+      sample_info.instance_state(),
+    )
+  }
+
+  pub(crate) fn matches_states(
+    &self,
+    sample_state: SampleState,
+    view_state: ViewState,
+    instance_state: InstanceState,
+  ) -> bool {
+    self.sample_state_mask.contains(sample_state)
+      && self.view_state_mask.contains(view_state)
+      && self.instance_state_mask.contains(instance_state)
+  }
+}
+
+/// Builder for [`ReadCondition`], created with
+/// [`ReadCondition::builder`].
+///
+/// Each setter takes either a single state or a set of states combined with
+/// `|`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReadConditionBuilder {
+  condition: ReadCondition,
+}
+
+impl ReadConditionBuilder {
+  pub fn new() -> Self {
+    Self {
+      condition: ReadCondition::any(),
+    }
+  }
+
+  #[must_use]
+  pub fn sample_state(mut self, sample_state: impl Into<BitFlags<SampleState>>) -> Self {
+    self.condition.sample_state_mask = sample_state.into();
+    self
+  }
+
+  #[must_use]
+  pub fn view_state(mut self, view_state: impl Into<BitFlags<ViewState>>) -> Self {
+    self.condition.view_state_mask = view_state.into();
+    self
+  }
+
+  #[must_use]
+  pub fn instance_state(mut self, instance_state: impl Into<BitFlags<InstanceState>>) -> Self {
+    self.condition.instance_state_mask = instance_state.into();
+    self
+  }
+
+  pub fn build(self) -> ReadCondition {
+    self.condition
+  }
+}
+
+impl Default for ReadConditionBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn read_condition_builder() {
+    assert_eq!(ReadCondition::builder().build(), ReadCondition::any());
+    assert_eq!(
+      ReadCondition::builder()
+        .sample_state(SampleState::NotRead)
+        .build(),
+      ReadCondition::not_read()
+    );
+
+    let condition = ReadCondition::builder()
+      .sample_state(SampleState::NotRead)
+      .view_state(ViewState::New)
+      .instance_state(InstanceState::not_alive())
+      .build();
+    assert_eq!(*condition.sample_state_mask(), SampleState::NotRead);
+    assert_eq!(*condition.view_state_mask(), ViewState::New);
+    assert_eq!(*condition.instance_state_mask(), InstanceState::not_alive());
+  }
+
+  #[test]
+  fn read_condition_matches_states() {
+    let condition = ReadCondition::builder()
+      .sample_state(SampleState::NotRead)
+      .view_state(ViewState::New | ViewState::NotNew)
+      .instance_state(InstanceState::Alive)
+      .build();
+    assert!(condition.matches_states(SampleState::NotRead, ViewState::New, InstanceState::Alive));
+    assert!(condition.matches_states(
+      SampleState::NotRead,
+      ViewState::NotNew,
+      InstanceState::Alive
+    ));
+    assert!(!condition.matches_states(SampleState::Read, ViewState::New, InstanceState::Alive));
+    assert!(!condition.matches_states(
+      SampleState::NotRead,
+      ViewState::New,
+      InstanceState::NotAliveDisposed
+    ));
+
+    // An empty mask selects nothing
+    let nothing = ReadCondition::builder()
+      .instance_state(BitFlags::empty())
+      .build();
+    assert!(!nothing.matches_states(SampleState::NotRead, ViewState::New, InstanceState::Alive));
+  }
 }
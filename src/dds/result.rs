@@ -8,12 +8,20 @@
 
 use std::sync::PoisonError;
 
-use crate::{no_key::wrappers::NoKeyWrapper, serialization, TopicKind};
+use crate::{
+  dds::qos::{QosConsistencyRule, QosPolicyId},
+  no_key::wrappers::NoKeyWrapper,
+  serialization, TopicKind,
+};
 #[cfg(feature = "security")]
 use crate::security::SecurityError;
 
 /// Error type for DDS "read" type operations.
+///
+/// New kinds of errors may be added in future versions, so matching must
+/// include a wildcard arm.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum ReadError {
   /// Data received over RTPS could not be decoded.
   /// Reason field gives more details on what went wrong.
@@ -39,10 +47,24 @@ pub enum ReadError {
   )]
   Poisoned { reason: String },
 
+  /// A command could not be passed to the RTPS processing thread, because
+  /// its queue is full. The operation may be retried later.
+  #[error("Would block: {reason}")]
+  WouldBlock { reason: String },
+
   /// Something that should not go wrong went wrong anyway.
   /// This is usually a bug in RustDDS
   #[error("Internal error: {reason}")]
   Internal { reason: String },
+
+  /// A received sample was decoded, but an application-supplied conversion,
+  /// e.g. in
+  /// [`with_key::DataReaderStream::try_map_value`](crate::with_key::DataReaderStream::try_map_value),
+  /// rejected it. The `source` field is the error returned by the conversion.
+  #[error("Conversion error: {source}")]
+  Conversion {
+    source: Box<dyn std::error::Error + Send + Sync>,
+  },
 }
 
 #[doc(hidden)]
@@ -118,10 +140,40 @@ pub enum WriteError<D> {
   #[error("std:io:Error {0}")]
   Io(#[from] std::io::Error),
 
-  /// The operation would block, or blocked until specified timeout expired.
-  #[error("Write operation timed out while blocking")]
+  /// The operation would block, and blocking is not possible.
+  ///
+  /// A write that blocks, and runs out of time, is reported as
+  /// [`Timeout`](WriteError::Timeout) instead.
+  ///
+  /// This is also returned, when the memory limit of the DomainParticipant is
+  /// reached, and evicting BestEffort samples did not make room. See
+  /// [`DomainParticipantBuilder::memory_limit`](crate::DomainParticipantBuilder::memory_limit).
+  /// The write may succeed later, when old samples have been removed.
+  #[error("Write operation would block")]
   WouldBlock { data: D },
 
+  /// The write blocked for the `max_blocking_time` of
+  /// [`Reliability::Reliable`](crate::policy::Reliability::Reliable), but
+  /// there was no room for the sample. The sample was not written.
+  ///
+  /// There is no room, when a KeepAll history has `max_samples` of
+  /// [`ResourceLimits`](crate::policy::ResourceLimits) samples that matched
+  /// Reliable Readers have not yet acknowledged. Note that this used to be
+  /// reported as [`WouldBlock`](WriteError::WouldBlock).
+  #[error("Write operation timed out while blocking")]
+  Timeout { data: D },
+
+  /// The instance to dispose has not been written, disposed, or registered
+  /// by this DataWriter.
+  #[error("Unknown instance: {reason}")]
+  UnknownInstance { reason: String, data: D },
+
+  /// No DataReader is matched, and the DataWriter is configured to require
+  /// at least one. The sample was not written. The write may succeed once a
+  /// DataReader has been matched.
+  #[error("No matched DataReaders")]
+  NoMatchedReaders { data: D },
+
   /// Something that should not go wrong went wrong anyway.
   /// This is usually a bug in RustDDS
   #[error("Internal error: {reason}")]
@@ -157,9 +209,53 @@ impl<D> WriteError<D> {
       WriteError::Poisoned { reason, data: _ } => WriteError::Poisoned { reason, data: () },
       WriteError::Io(e) => WriteError::Io(e),
       WriteError::WouldBlock { data: _ } => WriteError::WouldBlock { data: () },
+      WriteError::Timeout { data: _ } => WriteError::Timeout { data: () },
+      WriteError::UnknownInstance { reason, data: _ } => {
+        WriteError::UnknownInstance { reason, data: () }
+      }
+      WriteError::NoMatchedReaders { data: _ } => WriteError::NoMatchedReaders { data: () },
       WriteError::Internal { reason } => WriteError::Internal { reason },
     }
   }
+
+  /// Tells if the same write may succeed when retried later, i.e. the
+  /// DataWriter was out of room or resources, or had no matched DataReaders.
+  /// Other errors are permanent: retrying does not help.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::dds::WriteError;
+  /// let e = WriteError::Timeout { data: 42 };
+  /// assert!(e.is_transient());
+  /// assert_eq!(e.into_data(), Some(42));
+  /// ```
+  pub fn is_transient(&self) -> bool {
+    match self {
+      WriteError::WouldBlock { .. }
+      | WriteError::Timeout { .. }
+      | WriteError::NoMatchedReaders { .. } => true,
+      WriteError::Serialization { .. }
+      | WriteError::Poisoned { .. }
+      | WriteError::Io(_)
+      | WriteError::UnknownInstance { .. }
+      | WriteError::Internal { .. } => false,
+    }
+  }
+
+  /// Gives back the data that was not written, e.g. to retry with it.
+  /// Returns `None` if the error does not carry it.
+  pub fn into_data(self) -> Option<D> {
+    match self {
+      WriteError::Serialization { data, .. }
+      | WriteError::Poisoned { data, .. }
+      | WriteError::WouldBlock { data }
+      | WriteError::Timeout { data }
+      | WriteError::UnknownInstance { data, .. }
+      | WriteError::NoMatchedReaders { data } => Some(data),
+      WriteError::Io(_) | WriteError::Internal { .. } => None,
+    }
+  }
 }
 
 /// This is a specialized Result, similar to [`std::io::Result`].
@@ -178,6 +274,12 @@ pub(crate) fn unwrap_no_key_write_error<D>(
       data: data.d,
     },
     WriteError::WouldBlock { data } => WriteError::WouldBlock { data: data.d },
+    WriteError::Timeout { data } => WriteError::Timeout { data: data.d },
+    WriteError::UnknownInstance { reason, data } => WriteError::UnknownInstance {
+      reason,
+      data: data.d,
+    },
+    WriteError::NoMatchedReaders { data } => WriteError::NoMatchedReaders { data: data.d },
     WriteError::Internal { reason } => WriteError::Internal { reason },
     WriteError::Io(io) => WriteError::Io(io),
   }
@@ -292,6 +394,14 @@ impl From<SecurityError> for CreateError {
   }
 }
 
+impl From<QosError> for CreateError {
+  fn from(qos_error: QosError) -> Self {
+    CreateError::BadParameter {
+      reason: qos_error.to_string(),
+    }
+  }
+}
+
 /// This is a specialized Result, similar to [`std::io::Result`].
 pub type CreateResult<T> = std::result::Result<T, CreateError>;
 
@@ -307,4 +417,13 @@ pub type WaitResult<T> = std::result::Result<T, WaitError>;
 pub enum QosError {
   #[error("Parameter value or combination of values was bad. Details: {details}")]
   BadParameter { details: String },
+
+  /// A QoS policy has an invalid value, or some policies are inconsistent
+  /// with each other. See
+  /// [`QosPolicies::validate`](crate::QosPolicies::validate).
+  #[error("Inconsistent QoS policies {policies:?}: {rule}")]
+  Inconsistent {
+    policies: Vec<QosPolicyId>,
+    rule: QosConsistencyRule,
+  },
 }
@@ -1,7 +1,9 @@
+use std::sync::Arc;
+
 use enumflags2::{bitflags, BitFlags};
 
 use crate::{
-  dds::with_key::datawriter::WriteOptions,
+  dds::{qos::QosPolicies, with_key::datawriter::WriteOptions},
   structure::{guid::GUID, rpc::SampleIdentity, sequence_number::SequenceNumber, time::Timestamp},
 };
 
@@ -120,6 +122,28 @@ impl InstanceState {
   }
 }
 
+/// Status flags that the DataWriter sent with a sample, decoded from the RTPS
+/// StatusInfo (PID_STATUS_INFO) inline QoS.
+///
+/// These tell apart the reasons why a sample carries no data: the instance
+/// was disposed, the DataWriter unregistered it (will no longer update it),
+/// or the sample was filtered away. Several flags may be set at once, e.g.
+/// dispose and unregister. Data samples normally have no flags set.
+///
+/// See RTPS spec v2.5 Section 9.6.4.9 StatusInfo_t (PID_STATUS_INFO).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+#[bitflags]
+pub enum SampleFlag {
+  /// The DataWriter disposed the instance.
+  Disposed = 0b0001,
+  /// The DataWriter unregistered the instance, i.e. it will not update the
+  /// instance anymore.
+  Unregistered = 0b0010,
+  /// The sample was filtered away by the DataWriter, so there is no data.
+  Filtered = 0b0100,
+}
+
 /// A double counter for counting how many times an instance as become Alive.
 ///
 /// > For each instance the middleware internally maintains two counts: the
@@ -226,10 +250,17 @@ pub struct SampleInfo {
 
   pub(crate) write_options: WriteOptions,
 
+  /// When the sample was received by this participant.
+  pub(crate) reception_timestamp: Timestamp,
+
   /// publication_handle identifies the DataWriter that modified
   /// the instance (i.e. wrote this sample)
   pub(crate) publication_handle: GUID,
   pub(crate) sequence_number: SequenceNumber,
+
+  /// QoS of the DataWriter that wrote this sample, as known by Discovery
+  /// when the sample was received.
+  pub(crate) writer_qos: Option<Arc<QosPolicies>>,
 }
 
 impl SampleInfo {
@@ -239,6 +270,11 @@ impl SampleInfo {
     self.write_options.source_timestamp()
   }
 
+  /// Time when this sample was received from the network.
+  pub fn reception_timestamp(&self) -> Timestamp {
+    self.reception_timestamp
+  }
+
   pub fn sample_state(&self) -> SampleState {
     self.sample_state
   }
@@ -255,10 +291,20 @@ impl SampleInfo {
   //   self.view_state = view_state;
   // }
 
+  /// Instance state at the time of reading. An instance is
+  /// [`NotAliveDisposed`](InstanceState::NotAliveDisposed) after a dispose,
+  /// and [`NotAliveNoWriters`](InstanceState::NotAliveNoWriters) after an
+  /// unregister without dispose.
   pub fn instance_state(&self) -> InstanceState {
     self.instance_state
   }
 
+  /// StatusInfo flags that were received with this sample. Use this to tell
+  /// apart disposes and unregisters of an instance.
+  pub fn sample_flags(&self) -> BitFlags<SampleFlag> {
+    self.write_options.sample_flags()
+  }
+
   // pub fn set_instance_state(&mut self, instance_state: InstanceState) {
   //   self.instance_state = instance_state;
   // }
@@ -285,6 +331,9 @@ impl SampleInfo {
 
   /// publication_handle identifies the DataWriter that modified
   /// the instance (i.e. wrote this sample)
+  ///
+  /// The DataWriter can be looked up with
+  /// [`DataReader::publication_description`](crate::with_key::DataReader::publication_description).
   pub fn publication_handle(&self) -> GUID {
     self.publication_handle
   }
@@ -293,6 +342,17 @@ impl SampleInfo {
     self.publication_handle
   }
 
+  /// QoS policies of the DataWriter that wrote this sample, as announced by
+  /// the DataWriter via Discovery. This is useful e.g. to tell apart samples
+  /// from Reliable and BestEffort Writers on the same Topic.
+  ///
+  /// The QoS is looked up when the sample is moved to the DataReader. It is
+  /// `None` if the Writer was not (or no longer) known to Discovery at that
+  /// time.
+  pub fn writer_qos(&self) -> Option<&QosPolicies> {
+    self.writer_qos.as_deref()
+  }
+
   pub fn related_sample_identity(&self) -> Option<SampleIdentity> {
     self.write_options.related_sample_identity()
   }
@@ -308,3 +368,41 @@ impl SampleInfo {
   //   self.publication_handle = publication_handle
   // }
 }
+
+/// What a DataReader knows about an instance, whether or not samples of it
+/// are still available for reading.
+///
+/// See [`DataReader::instance_info`](crate::with_key::DataReader::instance_info).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceInfo {
+  pub(crate) instance_state: InstanceState,
+  pub(crate) generation_counts: NotAliveGenerationCounts,
+  pub(crate) last_change: SampleInfo,
+}
+
+impl InstanceInfo {
+  pub fn instance_state(&self) -> InstanceState {
+    self.instance_state
+  }
+
+  /// Current generation counts of the instance.
+  pub fn generation_counts(&self) -> NotAliveGenerationCounts {
+    self.generation_counts
+  }
+
+  /// SampleInfo of the latest sample received for the instance. If the
+  /// instance is disposed, this describes the dispose, i.e. which DataWriter
+  /// disposed the instance and when.
+  ///
+  /// The ranks in this SampleInfo are zero, as it is not part of any
+  /// collection of samples.
+  pub fn last_change(&self) -> &SampleInfo {
+    &self.last_change
+  }
+
+  /// The DataWriter that disposed the instance, if it is currently disposed.
+  pub fn disposed_by(&self) -> Option<GUID> {
+    (self.instance_state == InstanceState::NotAliveDisposed)
+      .then_some(self.last_change.publication_handle)
+  }
+}
@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+/// Operational counters of a [`DomainParticipant`](crate::DomainParticipant).
+///
+/// See
+/// [`DomainParticipant::get_statistics_snapshot`](crate::DomainParticipant::get_statistics_snapshot).
+///
+/// The message and byte counts cover RTPS messages on all UDP sockets,
+/// including Discovery traffic. They are totals since the participant was
+/// created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ParticipantStatistics {
+  /// Remote DomainParticipants currently known to Discovery.
+  pub discovered_participants: usize,
+  /// DataWriters currently matched, summed over all user-defined DataReaders
+  /// of this participant. Both local and remote DataWriters are counted.
+  pub matched_writers: usize,
+  /// DataReaders currently matched, summed over all user-defined DataWriters
+  /// of this participant. Both local and remote DataReaders are counted.
+  pub matched_readers: usize,
+  pub messages_sent: u64,
+  pub messages_received: u64,
+  pub bytes_sent: u64,
+  pub bytes_received: u64,
+  /// Authentication handshakes completed successfully. Always zero without
+  /// the "security" feature.
+  pub security_handshakes_completed: u64,
+  /// Authentication handshakes that failed, or after which the remote
+  /// DomainParticipant was rejected.
+  pub security_handshakes_failed: u64,
+}
+
+// Updated by the event loop, Discovery and the UDP sender. Each counter is
+// updated independently, so a snapshot taken during an update may see
+// e.g. a message counted, but not yet its bytes.
+#[derive(Debug, Default)]
+pub(crate) struct StatisticsCounters {
+  discovered_participants: AtomicUsize,
+  matched_writers: AtomicUsize,
+  matched_readers: AtomicUsize,
+  messages_sent: AtomicU64,
+  messages_received: AtomicU64,
+  bytes_sent: AtomicU64,
+  bytes_received: AtomicU64,
+  security_handshakes_completed: AtomicU64,
+  security_handshakes_failed: AtomicU64,
+}
+
+impl StatisticsCounters {
+  pub fn message_sent(&self, bytes: usize) {
+    self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+  }
+
+  pub fn message_received(&self, bytes: usize) {
+    self.messages_received.fetch_add(1, Ordering::Relaxed);
+    self
+      .bytes_received
+      .fetch_add(bytes as u64, Ordering::Relaxed);
+  }
+
+  pub fn set_discovered_participants(&self, count: usize) {
+    self.discovered_participants.store(count, Ordering::Relaxed);
+  }
+
+  pub fn set_matched(&self, writers: usize, readers: usize) {
+    self.matched_writers.store(writers, Ordering::Relaxed);
+    self.matched_readers.store(readers, Ordering::Relaxed);
+  }
+
+  #[cfg_attr(not(feature = "security"), allow(dead_code))]
+  pub fn handshake_completed(&self) {
+    self
+      .security_handshakes_completed
+      .fetch_add(1, Ordering::Relaxed);
+  }
+
+  #[cfg_attr(not(feature = "security"), allow(dead_code))]
+  pub fn handshake_failed(&self) {
+    self
+      .security_handshakes_failed
+      .fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn snapshot(&self) -> ParticipantStatistics {
+    ParticipantStatistics {
+      discovered_participants: self.discovered_participants.load(Ordering::Relaxed),
+      matched_writers: self.matched_writers.load(Ordering::Relaxed),
+      matched_readers: self.matched_readers.load(Ordering::Relaxed),
+      messages_sent: self.messages_sent.load(Ordering::Relaxed),
+      messages_received: self.messages_received.load(Ordering::Relaxed),
+      bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+      bytes_received: self.bytes_received.load(Ordering::Relaxed),
+      security_handshakes_completed: self.security_handshakes_completed.load(Ordering::Relaxed),
+      security_handshakes_failed: self.security_handshakes_failed.load(Ordering::Relaxed),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn statistics_counters() {
+    let counters = StatisticsCounters::default();
+    assert_eq!(counters.snapshot(), ParticipantStatistics::default());
+
+    counters.message_sent(100);
+    counters.message_sent(20);
+    counters.message_received(64);
+    counters.set_discovered_participants(2);
+    counters.set_matched(3, 1);
+    counters.handshake_completed();
+    counters.handshake_failed();
+    counters.handshake_failed();
+
+    assert_eq!(
+      counters.snapshot(),
+      ParticipantStatistics {
+        discovered_participants: 2,
+        matched_writers: 3,
+        matched_readers: 1,
+        messages_sent: 2,
+        messages_received: 1,
+        bytes_sent: 120,
+        bytes_received: 64,
+        security_handshakes_completed: 1,
+        security_handshakes_failed: 2,
+      }
+    );
+  }
+}
@@ -6,7 +6,9 @@
 // Communication statues are detailed in Figure 2.13 and tables in Section
 // 2.2.4.1 in DDS Specification v1.4
 use std::{
-  io,
+  collections::{BTreeMap, BTreeSet},
+  fmt, io,
+  net::IpAddr,
   pin::Pin,
   sync::{Arc, Mutex},
   task::{Context, Poll, Waker},
@@ -21,15 +23,17 @@ use mio_08::{event, Interest, Registry, Token};
 use chrono::Utc;
 
 use crate::{
-  dds::{qos::QosPolicyId, topic::TopicData},
-  discovery::SpdpDiscoveredParticipantData,
+  dds::{key::InstanceHandle, qos::QosPolicyId, topic::TopicData},
+  discovery::{DiscoveredReaderData, DiscoveredWriterData, SpdpDiscoveredParticipantData},
   messages::{protocol_version::ProtocolVersion, vendor_id::VendorId},
   mio_source::*,
-  structure::guid::GuidPrefix,
-  Duration, QosPolicies, GUID,
+  structure::{
+    guid::GuidPrefix, locator::Locator, memory_budget::MemoryUsage, sequence_number::SequenceNumber,
+  },
+  Duration, QosPolicies, Timestamp, GUID,
 };
 #[cfg(feature = "security")]
-use crate::discovery::secure_discovery::AuthenticationStatus;
+use crate::discovery::secure_discovery::{AuthenticationStatus, HandshakeProgress};
 
 /// This trait corresponds to set_listener() of the Entity class in DDS spec.
 /// Types implementing this trait can be registered to a poll and
@@ -56,12 +60,18 @@ where
 // poll channel, so that it can be used together with mio-0.8
 // This is only used so that a mio-0.6 channel can pose as a
 // mio-0.8 event::Source.
+//
+// Each status is stamped with the time it was sent, so that a receiver can
+// order statuses with respect to samples, which are stamped with their receive
+// time. Both stamps come from Timestamp::now_monotonic, so they are unique and
+// in the order things happened, even if the system clock is coarse or steps
+// backwards.
 
 pub(crate) fn sync_status_channel<T>(
   capacity: usize,
 ) -> io::Result<(StatusChannelSender<T>, StatusChannelReceiver<T>)> {
   let (signal_receiver, signal_sender) = make_poll_channel()?;
-  let (actual_sender, actual_receiver) = mio_channel::sync_channel(capacity);
+  let (actual_sender, actual_receiver) = mio_channel::sync_channel::<(Timestamp, T)>(capacity);
   let waker = Arc::new(Mutex::new(None));
   Ok((
     StatusChannelSender {
@@ -80,13 +90,13 @@ pub(crate) fn sync_status_channel<T>(
 // TODO: try to make this (and the Receiver) private types
 #[derive(Clone)]
 pub struct StatusChannelSender<T> {
-  actual_sender: mio_channel::SyncSender<T>,
+  actual_sender: mio_channel::SyncSender<(Timestamp, T)>,
   signal_sender: PollEventSender,
   waker: Arc<Mutex<Option<Waker>>>,
 }
 
 pub struct StatusChannelReceiver<T> {
-  actual_receiver: Mutex<mio_channel::Receiver<T>>,
+  actual_receiver: Mutex<mio_channel::Receiver<(Timestamp, T)>>,
   signal_receiver: PollEventSource,
   waker: Arc<Mutex<Option<Waker>>>,
 }
@@ -95,7 +105,7 @@ impl<T> StatusChannelSender<T> {
   /// Best-effort send. If there is no receiver, this will fail silently.
   pub fn try_send(&self, t: T) -> Result<(), mio_channel::TrySendError<T>> {
     let mut w = self.waker.lock().unwrap(); // lock already at the beginning
-    match self.actual_sender.try_send(t) {
+    match self.actual_sender.try_send((Timestamp::now_monotonic(), t)) {
       Ok(()) => {
         self.signal_sender.send();
         w.as_ref().map(|w| w.wake_by_ref());
@@ -113,19 +123,43 @@ impl<T> StatusChannelSender<T> {
         // The caller loses the payload object (tt), even though it is not sent.
         Ok(())
       }
-      Err(other_fail) => Err(other_fail),
+      Err(mio_channel::TrySendError::Disconnected((_, t))) => {
+        Err(mio_channel::TrySendError::Disconnected(t))
+      }
+      Err(mio_channel::TrySendError::Io(e)) => Err(mio_channel::TrySendError::Io(e)),
     }
   }
 }
 
 impl<T> StatusChannelReceiver<T> {
   pub fn try_recv(&self) -> Result<T, std::sync::mpsc::TryRecvError> {
+    self.try_recv_stamped().map(|(_, t)| t)
+  }
+
+  // Like try_recv, but also gives the time when the status was sent.
+  pub(crate) fn try_recv_stamped(&self) -> Result<(Timestamp, T), std::sync::mpsc::TryRecvError> {
     // We do not manipulate waker here, because the
     // synchronous and asynchronous receiving are not supposed to be mixed.
     self.signal_receiver.drain();
     self.actual_receiver.lock().unwrap().try_recv()
   }
 
+  // Async version of try_recv_stamped. Ready(None) means that the channel is
+  // disconnected.
+  pub(crate) fn poll_recv_stamped(&self, cx: &mut Context<'_>) -> Poll<Option<(Timestamp, T)>> {
+    let mut w = self.get_waker_update_lock();
+    // lock already at the beginning, before try_recv
+    match self.try_recv_stamped() {
+      Err(std::sync::mpsc::TryRecvError::Empty) => {
+        // nothing available
+        *w = Some(cx.waker().clone());
+        Poll::Pending
+      }
+      Err(std::sync::mpsc::TryRecvError::Disconnected) => Poll::Ready(None),
+      Ok(t) => Poll::Ready(Some(t)), // got data
+    }
+  }
+
   pub(crate) fn get_waker_update_lock(&self) -> std::sync::MutexGuard<'_, Option<Waker>> {
     self.waker.lock().unwrap()
   }
@@ -224,20 +258,14 @@ impl<T> Stream for StatusReceiverStream<'_, T> {
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     // debug!("poll_next");
-    let mut w = self.sync_receiver.get_waker_update_lock();
-    // lock already at the beginning, before try_recv
-    match self.sync_receiver.try_recv() {
-      Err(std::sync::mpsc::TryRecvError::Empty) => {
-        // nothing available
-        *w = Some(cx.waker().clone());
-        Poll::Pending
-      }
-      Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+    match self.sync_receiver.poll_recv_stamped(cx) {
+      Poll::Pending => Poll::Pending,
+      Poll::Ready(None) => {
         self.terminated.store(true, Ordering::SeqCst);
         warn!("StatusReceiver channel disconnected");
         Poll::Ready(None)
       }
-      Ok(t) => Poll::Ready(Some(t)), // got data
+      Poll::Ready(Some((_, t))) => Poll::Ready(Some(t)),
     }
   } // fn
 }
@@ -262,6 +290,11 @@ pub enum DomainParticipantStatusEvent {
     id: GuidPrefix,
     reason: LostReason,
   },
+  /// An already known Participant announced materially different data, e.g.
+  /// new locators, lease duration or name.
+  ParticipantUpdated {
+    dpd: ParticipantDescription,
+  },
   InconsistentTopic {
     previous_topic_data: Box<TopicData>, // What was our ide aof the Topic
     previous_source: GUID,
@@ -272,6 +305,7 @@ pub enum DomainParticipantStatusEvent {
   TopicDetected {
     name: String,
     type_name: String,
+    topic_data: Box<TopicData>, // Discovered Topic definition, including QoS
   },
   /// Topics are lost when there are no more known Readers or Writers in them.
   TopicLost {
@@ -290,11 +324,15 @@ pub enum DomainParticipantStatusEvent {
   ReaderLost {
     guid: GUID,
     reason: LostReason,
+    /// What was known of the Reader before it was lost, if anything.
+    last_known: Option<EndpointDescription>,
   },
   /// Remote Writer was lost (disconnected)
   WriterLost {
     guid: GUID,
     reason: LostReason,
+    /// What was known of the Writer before it was lost, if anything.
+    last_known: Option<EndpointDescription>,
   },
   RemoteReaderMatched {
     local_writer: GUID,
@@ -316,11 +354,58 @@ pub enum DomainParticipantStatusEvent {
     requested_qos: Box<QosPolicies>,
     offered_qos: Box<QosPolicies>,
   },
+  /// A remote Reader or Writer was announced with the GUID of a local
+  /// endpoint, but with a different topic or type. Traffic of the two
+  /// endpoints may be misrouted. Reported only if
+  /// [`DiscoveryConfig::detect_guid_conflicts`](crate::DiscoveryConfig::detect_guid_conflicts)
+  /// is set.
+  GuidConflict {
+    local: Box<EndpointDescription>,
+    remote: Box<EndpointDescription>,
+  },
+  /// The set of local network interface addresses changed, e.g. when
+  /// switching from wired to wireless network. Our locators have been updated
+  /// and re-announced accordingly.
+  NetworkInterfacesChanged {
+    added: Vec<IpAddr>,
+    removed: Vec<IpAddr>,
+  },
+  /// A background thread of the DomainParticipant has panicked. This is the
+  /// last event: the participant is no longer functional, and further
+  /// operations on it and its entities fail. See
+  /// [`DomainParticipant::health`](crate::DomainParticipant::health).
+  ParticipantFailed {
+    reason: String,
+  },
+  /// A newly discovered Participant speaks an RTPS protocol version or comes
+  /// from a vendor that may not interoperate fully with this implementation.
+  /// Communication is still attempted. Unknown submessages are skipped.
+  ProtocolMismatch {
+    dpd: ParticipantDescription,
+    reason: String,
+  },
+  /// The memory limit set by
+  /// [`DomainParticipantBuilder::memory_limit`](crate::DomainParticipantBuilder::memory_limit)
+  /// was reached. BestEffort samples are being evicted, and writes or
+  /// received samples may be refused. This is reported once, and again only
+  /// after memory use has fallen to 3/4 of the limit.
+  MemoryLimitReached {
+    limit: usize,
+    usage: MemoryUsage,
+  },
+  /// Authentication status of a remote Participant has changed.
   #[cfg(feature = "security")]
   Authentication {
     participant: GuidPrefix,
     status: AuthenticationStatus,
   },
+  /// The authentication handshake with a remote Participant has advanced by
+  /// one step, or failed.
+  #[cfg(feature = "security")]
+  HandshakeProgress {
+    participant: GuidPrefix,
+    progress: HandshakeProgress,
+  },
   /// The CA has revoked the identity of some Participant.
   /// We may be currently communicating with the Participant, or it may be
   /// unknown to us.
@@ -342,6 +427,21 @@ pub enum DomainParticipantStatusEvent {
   },
 }
 
+#[cfg(feature = "security")]
+impl DomainParticipantStatusEvent {
+  /// Is this event about authentication or access control of remote
+  /// Participants?
+  pub fn is_security_event(&self) -> bool {
+    matches!(
+      self,
+      Self::Authentication { .. }
+        | Self::HandshakeProgress { .. }
+        | Self::IdentityRevoked { .. }
+        | Self::PermissionsRevoked { .. }
+    )
+  }
+}
+
 /// Why some remote entity is considered to be no longer with us.
 #[derive(Debug, Clone)]
 pub enum LostReason {
@@ -352,6 +452,9 @@ pub enum LostReason {
     lease: Duration,   // What was the discovered lease duration
     elapsed: Duration, // How much time has actually elapsed from last contact
   },
+  /// Discovery of the participant was restarted locally by
+  /// [`DomainParticipant::preempt_discovery`](crate::DomainParticipant::preempt_discovery)
+  Preempted,
 }
 
 /// This is a rewrite/summary of SpdpDiscoveredParticipantData from discovery.
@@ -385,6 +488,19 @@ impl From<&SpdpDiscoveredParticipantData> for ParticipantDescription {
   }
 }
 
+/// One line summary, e.g.
+/// `GUID {..} "talker" RustDDS RTPS 2.4`. The vendor is shown by name if
+/// it is a known one.
+impl fmt::Display for ParticipantDescription {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}", self.guid)?;
+    if let Some(name) = &self.entity_name {
+      write!(f, " {name:?}")?;
+    }
+    write!(f, " {} RTPS {:?}", self.vendor_id, self.protocol_version)
+  }
+}
+
 /// This is a summary of SubscriptionBuiltinTopicData /
 /// PublicationBuiltinTopicData from discovery. The original is not used to
 /// avoid circular dependency between participant and discovery.
@@ -397,7 +513,71 @@ pub struct EndpointDescription {
   pub qos: QosPolicies,
 }
 
+impl From<&DiscoveredReaderData> for EndpointDescription {
+  fn from(drd: &DiscoveredReaderData) -> Self {
+    EndpointDescription {
+      updated_time: Utc::now(),
+      guid: drd.reader_proxy.remote_reader_guid,
+      topic_name: drd.subscription_topic_data.topic_name().clone(),
+      type_name: drd.subscription_topic_data.type_name().clone(),
+      qos: drd.subscription_topic_data.qos(),
+    }
+  }
+}
+
+impl From<&DiscoveredWriterData> for EndpointDescription {
+  fn from(dwd: &DiscoveredWriterData) -> Self {
+    EndpointDescription {
+      updated_time: Utc::now(),
+      guid: dwd.writer_proxy.remote_writer_guid,
+      topic_name: dwd.publication_topic_data.topic_name.clone(),
+      type_name: dwd.publication_topic_data.type_name.clone(),
+      qos: dwd.publication_topic_data.qos(),
+    }
+  }
+}
+
+/// Locators of a matched remote Reader or Writer, as currently used by a
+/// local DataWriter or DataReader.
+///
+/// See [`DataWriter::remote_reader_locators`](crate::with_key::DataWriter::remote_reader_locators)
+/// and [`DataReader::remote_writer_locators`](crate::with_key::DataReader::remote_writer_locators).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteLocators {
+  pub guid: GUID,
+  pub unicast_locators: Vec<Locator>,
+  pub multicast_locators: Vec<Locator>,
+  /// The locator that a message to this endpoint was last successfully sent
+  /// to. `None` if nothing has been sent yet. A DataReader sends only
+  /// acknowledgements, so this stays `None` with BestEffort Writers.
+  pub last_used: Option<Locator>,
+}
+
+/// How full the history cache of a DataWriter is.
+///
+/// See [`DataWriter::history_cache_status`](crate::with_key::DataWriter::history_cache_status).
+/// Samples stay in the cache until all matched Reliable Readers have
+/// acknowledged them and they are no longer needed to satisfy the History
+/// QoS policy. A growing depth means that some Reader is not keeping up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryCacheStatus {
+  /// Number of samples (including dispose and unregister messages) in the
+  /// cache.
+  pub depth: usize,
+  /// Sequence number of the oldest sample in the cache. If the cache is
+  /// empty, this is the next sequence number to be written.
+  pub oldest_sequence_number: SequenceNumber,
+}
+
+// How long DataWriter and DataReader wait for the event loop to answer a
+// RemoteLocators query.
+pub(crate) const REMOTE_LOCATORS_QUERY_TIMEOUT: std::time::Duration =
+  std::time::Duration::from_secs(2);
+
+/// New kinds of status may be added in future versions, so matching must
+/// include a wildcard arm.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum DataReaderStatus {
   /// Sample was rejected, because resource limits would have been exceeded.
   SampleRejected {
@@ -412,12 +592,23 @@ pub enum DataReaderStatus {
     // last_publication_key:
   },
   /// Deadline requested by this DataReader was missed.
+  ///
+  /// `last_instance` identifies the instance that missed its deadline, if the
+  /// remote Writer sends key hashes. Use
+  /// [`DataReader::instance_for_handle`](crate::with_key::DataReader::instance_for_handle)
+  /// to resolve it to a key. It is `None` for NO_KEY topics and when only
+  /// the Writer as a whole is known to be late.
   RequestedDeadlineMissed {
     count: CountWithChange,
-    // last_instance_key:
+    last_instance: Option<InstanceHandle>,
   },
   /// This DataReader has requested a QoS policy that is incompatible with what
   /// is offered.
+  ///
+  /// This is reported once per remote DataWriter and incompatible policy, not
+  /// on every re-announcement of the same Writer. It is reported again if the
+  /// incompatible policy changes, or if the Writer is lost and rediscovered.
+  /// `count` is the number of reported incompatibilities.
   RequestedIncompatibleQos {
     count: CountWithChange,
     last_policy_id: QosPolicyId,
@@ -430,36 +621,63 @@ pub enum DataReaderStatus {
   // DataAvailable variant is not implemented, as it seems to bring little additional value,
   // because the normal data waiting mechanism already uses the same mio::poll structure.
   /// A sample has been lost (never received).
-  /// TODO: Implement this.
-  /// * Check that the following interpretation is correct:
-  /// * For a BEST_EFFORT reader: Whenever we skip ahead in SequenceNumber,
-  ///   possibly because a message is lost, or messages arrive out of order.
-  /// * For a RELIABLE reader: Whenever we skip ahead in SequenceNumbers that
-  ///   are delivered via DataReader. The reason may be that we receive a
-  ///   HEARTBEAT or GAP submessage indicating that some samples we are
-  ///   expecting are not available.
+  ///
+  /// This is reported for BEST_EFFORT readers, whenever a DATA from a matched
+  /// Writer skips ahead in SequenceNumber. Each skipped SequenceNumber counts
+  /// as one lost sample, even if it arrives later out of order. Nothing is
+  /// counted before the first sample received from a Writer.
+  ///
+  /// TODO: Not yet reported for RELIABLE readers. These should report it
+  /// whenever we skip ahead in SequenceNumbers that are delivered via
+  /// DataReader, e.g. because we receive a HEARTBEAT or GAP submessage
+  /// indicating that some samples we are expecting are not available.
   SampleLost { count: CountWithChange },
 
   /// The DataReader has found a DataWriter that matches the Topic and has
   /// compatible QoS, or has ceased to be matched with a DataWriter that was
   /// previously considered to be matched.
+  ///
+  /// `writer_description` is what Discovery knew of the Writer when it was
+  /// matched. It is `None` when the Writer is unmatched, and for built-in
+  /// Writers.
   SubscriptionMatched {
     total: CountWithChange,
     current: CountWithChange,
     writer: GUID,
+    writer_description: Option<Box<EndpointDescription>>,
     // last_publication_key:
   },
+
+  /// An instance was removed from the DataReader cache to make room for a new
+  /// instance, because `max_instances` of ResourceLimits was reached. This is
+  /// only generated when
+  /// [`InstanceEviction::LruEvict`](crate::with_key::InstanceEviction::LruEvict)
+  /// is in use. Not part of the DDS spec.
+  InstanceEvicted { count: CountWithChange },
 }
 
+/// New kinds of status may be added in future versions, so matching must
+/// include a wildcard arm.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum DataWriterStatus {
   LivelinessLost {
     count: CountWithChange,
   },
+  /// Deadline offered by this DataWriter was missed, i.e. an instance was
+  /// not written within the deadline period.
+  ///
+  /// `last_instance` is the instance that missed its deadline. It is `None`
+  /// for NO_KEY topics.
   OfferedDeadlineMissed {
     count: CountWithChange,
-    // last_instance_key:
+    last_instance: Option<InstanceHandle>,
   },
+  /// A DataReader has requested a QoS policy that this DataWriter does not
+  /// offer.
+  ///
+  /// Like [`DataReaderStatus::RequestedIncompatibleQos`], this is reported
+  /// once per remote DataReader and incompatible policy.
   OfferedIncompatibleQos {
     count: CountWithChange,
     last_policy_id: QosPolicyId,
@@ -468,12 +686,314 @@ pub enum DataWriterStatus {
     offered_qos: Box<QosPolicies>,
     //policies: Vec<QosPolicyCount>,  // Not implemented
   },
+  /// The DataWriter has found a DataReader that matches the Topic and has
+  /// compatible QoS, or has ceased to be matched with a DataReader.
+  ///
+  /// `reader_description` is what Discovery knew of the Reader when it was
+  /// matched, including its requested QoS. It is `None` when the Reader is
+  /// unmatched, and for built-in Readers.
   PublicationMatched {
     total: CountWithChange,
     current: CountWithChange,
     reader: GUID,
+    reader_description: Option<Box<EndpointDescription>>,
     // last_subscription_key:
   },
+
+  /// A matched Reliable DataReader has not acknowledged any new samples for
+  /// the stall timeout. `unacked` is the number of samples it has not
+  /// acknowledged, and `since` how long it has not made progress. Only
+  /// reported with the
+  /// [`StalledReaderDetection`](crate::policy::StalledReaderDetection) QoS
+  /// policy. Not part of the DDS spec.
+  ReaderStalled {
+    reader: GUID,
+    unacked: u64,
+    since: Duration,
+  },
+
+  /// A DataReader reported as [`ReaderStalled`](Self::ReaderStalled) has
+  /// acknowledged new samples again. A stalled DataReader that is lost instead,
+  /// e.g. because its participant lease expires, is reported only with
+  /// [`PublicationMatched`](Self::PublicationMatched). Not part of the DDS
+  /// spec.
+  ReaderRecovered {
+    reader: GUID,
+  },
+}
+
+/// Snapshot of the DataReader's matched Writers, see
+/// [`DataReader::get_subscription_matched_status`](crate::with_key::DataReader::get_subscription_matched_status).
+///
+/// The `count_change` fields tell the change since the status was last read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubscriptionMatchedStatus {
+  /// How many Writers have ever been matched.
+  pub total_count: CountWithChange,
+  /// How many Writers are currently matched.
+  pub current_count: CountWithChange,
+  /// The Writer that was most recently matched or unmatched.
+  pub last_publication_key: Option<GUID>,
+}
+
+impl Default for SubscriptionMatchedStatus {
+  fn default() -> Self {
+    Self {
+      total_count: CountWithChange::new(0, 0),
+      current_count: CountWithChange::new(0, 0),
+      last_publication_key: None,
+    }
+  }
+}
+
+impl SubscriptionMatchedStatus {
+  pub(crate) fn writer_matched(&mut self, current_count: i32, writer: GUID) {
+    self.total_count.count += 1;
+    self.total_count.count_change += 1;
+    self.current_count.count = current_count;
+    self.current_count.count_change += 1;
+    self.last_publication_key = Some(writer);
+  }
+
+  pub(crate) fn writer_unmatched(&mut self, current_count: i32, writer: GUID) {
+    self.current_count.count = current_count;
+    self.current_count.count_change -= 1;
+    self.last_publication_key = Some(writer);
+  }
+
+  // Returns the current status and resets the changes.
+  pub(crate) fn take(&mut self) -> Self {
+    let status = *self;
+    self.total_count.count_change = 0;
+    self.current_count.count_change = 0;
+    status
+  }
+}
+
+// The subscription matched status together with the Writers it counts. This
+// is shared between the RTPS Reader, which updates it, and the DataReader.
+#[derive(Debug, Default)]
+pub(crate) struct SubscriptionMatchedState {
+  status: SubscriptionMatchedStatus,
+  writers: BTreeSet<GUID>,
+  // Descriptions of matched Writers already looked up from Discovery. These
+  // are dropped when the Writer is unmatched.
+  writer_descriptions: BTreeMap<GUID, EndpointDescription>,
+}
+
+impl SubscriptionMatchedState {
+  pub(crate) fn writer_matched(&mut self, writer: GUID) {
+    if self.writers.insert(writer) {
+      self
+        .status
+        .writer_matched(self.writers.len() as i32, writer);
+    }
+  }
+
+  pub(crate) fn writer_unmatched(&mut self, writer: GUID) {
+    self.writer_descriptions.remove(&writer);
+    if self.writers.remove(&writer) {
+      self
+        .status
+        .writer_unmatched(self.writers.len() as i32, writer);
+    }
+  }
+
+  pub(crate) fn is_matched(&self, writer: GUID) -> bool {
+    self.writers.contains(&writer)
+  }
+
+  pub(crate) fn writer_description(&self, writer: GUID) -> Option<&EndpointDescription> {
+    self.writer_descriptions.get(&writer)
+  }
+
+  // Descriptions of Writers that are not matched (anymore) are not kept.
+  pub(crate) fn remember_writer_description(
+    &mut self,
+    writer: GUID,
+    description: EndpointDescription,
+  ) {
+    if self.is_matched(writer) {
+      self.writer_descriptions.insert(writer, description);
+    }
+  }
+
+  pub(crate) fn take(&mut self) -> SubscriptionMatchedStatus {
+    self.status.take()
+  }
+}
+
+/// Snapshot of the liveliness of the DataReader's matched Writers, see
+/// [`DataReader::get_liveliness_changed_status`](crate::with_key::DataReader::get_liveliness_changed_status).
+///
+/// The `count_change` fields tell the change since the status was last read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LivelinessChangedStatus {
+  /// How many matched Writers are currently alive.
+  pub alive_count: CountWithChange,
+  /// How many matched Writers are currently not alive.
+  pub not_alive_count: CountWithChange,
+  /// The Writer whose liveliness most recently changed.
+  pub last_publication_key: Option<GUID>,
+}
+
+impl Default for LivelinessChangedStatus {
+  fn default() -> Self {
+    Self {
+      alive_count: CountWithChange::new(0, 0),
+      not_alive_count: CountWithChange::new(0, 0),
+      last_publication_key: None,
+    }
+  }
+}
+
+impl LivelinessChangedStatus {
+  pub(crate) fn update(&mut self, writer: GUID, alive_change: i32, not_alive_change: i32) {
+    self.alive_count.count += alive_change;
+    self.alive_count.count_change += alive_change;
+    self.not_alive_count.count += not_alive_change;
+    self.not_alive_count.count_change += not_alive_change;
+    self.last_publication_key = Some(writer);
+  }
+
+  // Returns the current status and resets the changes.
+  pub(crate) fn take(&mut self) -> Self {
+    let status = *self;
+    self.alive_count.count_change = 0;
+    self.not_alive_count.count_change = 0;
+    status
+  }
+}
+
+/// Snapshot of the DataWriter's matched Readers, see
+/// [`DataWriter::get_publication_matched_status`](crate::with_key::DataWriter::get_publication_matched_status).
+///
+/// The `count_change` fields tell the change since the status was last read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PublicationMatchedStatus {
+  /// How many Readers have ever been matched.
+  pub total_count: CountWithChange,
+  /// How many Readers are currently matched.
+  pub current_count: CountWithChange,
+  /// The Reader that was most recently matched or unmatched.
+  pub last_subscription_key: Option<GUID>,
+}
+
+impl Default for PublicationMatchedStatus {
+  fn default() -> Self {
+    Self {
+      total_count: CountWithChange::new(0, 0),
+      current_count: CountWithChange::new(0, 0),
+      last_subscription_key: None,
+    }
+  }
+}
+
+impl PublicationMatchedStatus {
+  pub(crate) fn reader_matched(&mut self, current_count: i32, reader: GUID) {
+    self.total_count.count += 1;
+    self.total_count.count_change += 1;
+    self.current_count.count = current_count;
+    self.current_count.count_change += 1;
+    self.last_subscription_key = Some(reader);
+  }
+
+  pub(crate) fn reader_unmatched(&mut self, current_count: i32, reader: GUID) {
+    self.current_count.count = current_count;
+    self.current_count.count_change -= 1;
+    self.last_subscription_key = Some(reader);
+  }
+
+  // Returns the current status and resets the changes.
+  pub(crate) fn take(&mut self) -> Self {
+    let status = *self;
+    self.total_count.count_change = 0;
+    self.current_count.count_change = 0;
+    status
+  }
+}
+
+// The publication matched status together with the Readers it counts. This
+// is shared between the RTPS Writer, which updates it, and the DataWriter.
+#[derive(Debug, Default)]
+pub(crate) struct PublicationMatchedState {
+  status: PublicationMatchedStatus,
+  readers: BTreeSet<GUID>,
+}
+
+impl PublicationMatchedState {
+  pub(crate) fn reader_matched(&mut self, reader: GUID) {
+    if self.readers.insert(reader) {
+      self
+        .status
+        .reader_matched(self.readers.len() as i32, reader);
+    }
+  }
+
+  pub(crate) fn reader_unmatched(&mut self, reader: GUID) {
+    if self.readers.remove(&reader) {
+      self
+        .status
+        .reader_unmatched(self.readers.len() as i32, reader);
+    }
+  }
+
+  pub(crate) fn is_matched(&self, reader: GUID) -> bool {
+    self.readers.contains(&reader)
+  }
+
+  pub(crate) fn current_count(&self) -> usize {
+    self.readers.len()
+  }
+
+  pub(crate) fn take(&mut self) -> PublicationMatchedStatus {
+    self.status.take()
+  }
+}
+
+/// Snapshot of the QoS incompatibilities between a DataWriter and the
+/// Readers it has seen, see
+/// [`DataWriter::get_offered_incompatible_qos_status`](crate::with_key::DataWriter::get_offered_incompatible_qos_status).
+///
+/// The `count_change` field tells the change since the status was last read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfferedIncompatibleQosStatus {
+  /// How many times a Reader requested QoS that this DataWriter does not offer.
+  pub count: CountWithChange,
+  /// The policy that made the most recent match fail. `None` until the first
+  /// incompatibility.
+  pub last_policy_id: Option<QosPolicyId>,
+  /// Each policy that has caused an incompatibility, and how many times.
+  pub policies: Vec<(QosPolicyId, i32)>,
+}
+
+impl Default for OfferedIncompatibleQosStatus {
+  fn default() -> Self {
+    Self {
+      count: CountWithChange::new(0, 0),
+      last_policy_id: None,
+      policies: Vec::new(),
+    }
+  }
+}
+
+impl OfferedIncompatibleQosStatus {
+  pub(crate) fn reader_incompatible(&mut self, policy_id: QosPolicyId) {
+    self.count.count += 1;
+    self.count.count_change += 1;
+    self.last_policy_id = Some(policy_id);
+    match self.policies.iter_mut().find(|(id, _)| *id == policy_id) {
+      Some((_, count)) => *count += 1,
+      None => self.policies.push((policy_id, 1)),
+    }
+  }
+
+  // Returns the current status and resets the change.
+  pub(crate) fn take(&mut self) -> Self {
+    let status = self.clone();
+    self.count.count_change = 0;
+    status
+  }
 }
 
 /// Helper to contain same count actions across statuses
@@ -523,6 +1043,10 @@ pub enum SampleRejectedStatusKind {
   ByInstancesLimit,
   BySamplesLimit,
   BySamplesPerInstanceLimit,
+  /// The participant memory limit would have been exceeded. This is a RustDDS
+  /// extension, see
+  /// [`DomainParticipantBuilder::memory_limit`](crate::DomainParticipantBuilder::memory_limit).
+  ByMemoryLimit,
 }
 
 /* commented out for now, as it is not used.
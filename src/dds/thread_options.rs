@@ -0,0 +1,183 @@
+use std::{io, sync::Arc, thread};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+/// Real-time scheduling policy and priority for a background thread.
+///
+/// See
+/// [`DomainParticipantBuilder::thread_priority`](crate::DomainParticipantBuilder::thread_priority).
+/// The priority range depends on the operating system. On Linux it is
+/// 1 (lowest) to 99 (highest), and setting it usually requires the
+/// `CAP_SYS_NICE` capability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadPriority {
+  /// `SCHED_FIFO`
+  Fifo(i32),
+  /// `SCHED_RR`
+  RoundRobin(i32),
+}
+
+// Settings for the event loop thread of a DomainParticipant.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ThreadOptions {
+  pub name: Option<String>,
+  pub priority: Option<ThreadPriority>,
+  pub affinity: Option<Vec<usize>>,
+}
+
+type ThreadStartCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+// Settings for all background threads that a DomainParticipant starts.
+#[derive(Clone, Default)]
+pub(crate) struct ThreadSpawnOptions {
+  pub name_prefix: Option<String>,
+  pub stack_size: Option<usize>,
+  pub on_start: Option<ThreadStartCallback>,
+}
+
+impl ThreadSpawnOptions {
+  pub fn event_loop_name(&self, event_loop: &ThreadOptions, participant_id: u16) -> String {
+    event_loop
+      .name
+      .clone()
+      .or_else(|| self.prefixed("event loop"))
+      .unwrap_or_else(|| format!("RustDDS Participant {participant_id} event loop"))
+  }
+
+  pub fn discovery_name(&self) -> String {
+    self
+      .prefixed("discovery")
+      .unwrap_or_else(|| "RustDDS discovery thread".to_string())
+  }
+
+  fn prefixed(&self, name: &str) -> Option<String> {
+    self
+      .name_prefix
+      .as_ref()
+      .map(|prefix| format!("{prefix} {name}"))
+  }
+
+  pub fn builder(&self, name: String) -> thread::Builder {
+    let builder = thread::Builder::new().name(name);
+    match self.stack_size {
+      Some(size) => builder.stack_size(size),
+      None => builder,
+    }
+  }
+
+  // Called from the thread itself, after it has started, and after
+  // ThreadOptions have been applied, so that the application has the last say.
+  pub fn thread_started(&self, name: &str) {
+    if let Some(on_start) = &self.on_start {
+      on_start(name);
+    }
+  }
+}
+
+impl ThreadOptions {
+  // Called from the thread itself, after it has started. Failures are not
+  // fatal: the thread just runs with default scheduling.
+  pub fn apply_to_current_thread(&self) {
+    if let Some(priority) = self.priority {
+      match set_priority(priority) {
+        Ok(()) => info!("Thread priority set to {priority:?}"),
+        Err(e) => warn!("Cannot set thread priority to {priority:?}: {e}"),
+      }
+    }
+    if let Some(cpus) = &self.affinity {
+      match set_affinity(cpus) {
+        Ok(()) => info!("Thread affinity set to CPUs {cpus:?}"),
+        Err(e) => warn!("Cannot set thread affinity to CPUs {cpus:?}: {e}"),
+      }
+    }
+  }
+}
+
+#[cfg(unix)]
+fn set_priority(priority: ThreadPriority) -> io::Result<()> {
+  let (policy, priority) = match priority {
+    ThreadPriority::Fifo(p) => (libc::SCHED_FIFO, p),
+    ThreadPriority::RoundRobin(p) => (libc::SCHED_RR, p),
+  };
+  // sched_param has platform-specific padding fields, so start from zero.
+  // SAFETY: sched_param is a plain C struct, for which all zeroes is valid.
+  let mut param: libc::sched_param = unsafe { std::mem::zeroed() };
+  param.sched_priority = priority;
+  // SAFETY: pthread_self() is always a valid thread, and param outlives the
+  // call.
+  match unsafe { libc::pthread_setschedparam(libc::pthread_self(), policy, &param) } {
+    0 => Ok(()),
+    errno => Err(io::Error::from_raw_os_error(errno)),
+  }
+}
+
+#[cfg(not(unix))]
+fn set_priority(_priority: ThreadPriority) -> io::Result<()> {
+  Err(io::Error::new(
+    io::ErrorKind::Unsupported,
+    "not supported on this platform",
+  ))
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[usize]) -> io::Result<()> {
+  // SAFETY: cpu_set_t is a plain bit mask, for which all zeroes is valid.
+  let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+  let max_cpus = 8 * std::mem::size_of::<libc::cpu_set_t>();
+  for &cpu in cpus {
+    if cpu >= max_cpus {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("CPU index {cpu} is too large"),
+      ));
+    }
+    // SAFETY: cpu was checked to be within the set.
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+  }
+  // SAFETY: pid 0 means the calling thread, and set is a valid cpu_set_t.
+  match unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) } {
+    0 => Ok(()),
+    _ => Err(io::Error::last_os_error()),
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cpus: &[usize]) -> io::Result<()> {
+  Err(io::Error::new(
+    io::ErrorKind::Unsupported,
+    "not supported on this platform",
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn affinity() {
+    // In a separate thread, so that the test runner threads are not affected
+    std::thread::spawn(|| {
+      // Every CPU, whichever of them this process is allowed to use
+      let all = (0..8 * std::mem::size_of::<libc::cpu_set_t>()).collect::<Vec<_>>();
+      assert!(set_affinity(&all).is_ok());
+      assert!(set_affinity(&[1 << 20]).is_err());
+    })
+    .join()
+    .unwrap();
+  }
+
+  #[test]
+  fn failures_are_not_fatal() {
+    let options = ThreadOptions {
+      name: None,
+      // Out of range on every platform
+      priority: Some(ThreadPriority::Fifo(-1000)),
+      affinity: Some(vec![usize::MAX]),
+    };
+    std::thread::spawn(move || options.apply_to_current_thread())
+      .join()
+      .unwrap();
+  }
+}
@@ -0,0 +1,627 @@
+use std::{
+  collections::{BTreeMap, VecDeque},
+  pin::Pin,
+  task::{Context, Poll},
+  time::{Duration, Instant},
+};
+
+use futures::stream::{FusedStream, Stream};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  dds::{
+    adapters::no_key::{DefaultDecoder, DeserializerAdapter},
+    helpers::Alarm,
+    no_key::{datareader::DataReader, datareader::DataReaderStream, datasample::DataSample},
+    pubsub::Subscriber,
+    qos::QosPolicies,
+    result::{CreateError, CreateResult, ReadResult},
+    topic::TopicKind,
+  },
+  create_error_dropped,
+  discovery::discovery_db::discovery_db_read,
+  serialization::CDRDeserializerAdapter,
+};
+
+// How often Discovery is checked for new and vanished topics.
+const REFRESH_PERIOD: Duration = Duration::from_millis(500);
+
+/// Event from a [`WildcardSubscription`].
+#[derive(Debug)]
+pub enum WildcardEvent<D> {
+  /// A topic matching the pattern was discovered, and a DataReader was
+  /// created for it.
+  TopicAdded {
+    topic_name: String,
+    type_name: String,
+  },
+  /// A sample was received on a matching topic.
+  Sample {
+    topic_name: String,
+    sample: DataSample<D>,
+  },
+  /// A matching topic has had no DataWriters for the grace period, so its
+  /// DataReader was deleted. If DataWriters appear again, the topic is added
+  /// again.
+  TopicRemoved { topic_name: String },
+}
+
+/// Subscribes to all topics whose name matches a pattern.
+///
+/// Created with
+/// [`Subscriber::create_wildcard_subscription`](crate::Subscriber::create_wildcard_subscription).
+///
+/// The pattern may contain `*`, which matches any sequence of characters
+/// (including `/`), and `?`, which matches any single character. Other
+/// characters match themselves.
+///
+/// A topic is subscribed when Discovery knows a DataWriter on it, local or
+/// remote. When all its DataWriters are gone, the DataReader is kept for the
+/// grace period, so that restarting publishers do not cause churn.
+///
+/// All topics are read with the same data type `D` and adapter `DA`, as
+/// no_key topics. Keyed topics can be read this way also, but disposals are
+/// not reported. For topics of unknown or different types, use
+/// [`RawDeserializerAdapter`](crate::serialization::RawDeserializerAdapter),
+/// which does not decode the payload, but delivers it as a
+/// [`RawSample`](crate::serialization::RawSample).
+pub struct WildcardSubscription<D: 'static, DA: DeserializerAdapter<D> = CDRDeserializerAdapter<D>>
+{
+  tracker: TopicTracker,
+  readers: BTreeMap<String, DataReader<D, DA>>,
+  pending: VecDeque<WildcardEvent<D>>,
+  // Round-robin position in `readers`, so that a busy topic does not starve
+  // the others.
+  next_reader: usize,
+}
+
+impl<D, DA> WildcardSubscription<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  pub(crate) fn new(
+    subscriber: Subscriber,
+    pattern: &str,
+    grace_period: Duration,
+    qos: Option<QosPolicies>,
+  ) -> Self {
+    Self {
+      tracker: TopicTracker::new(subscriber, pattern, grace_period, qos),
+      readers: BTreeMap::new(),
+      pending: VecDeque::new(),
+      next_reader: 0,
+    }
+  }
+
+  /// Names of the topics currently subscribed.
+  pub fn topic_names(&self) -> impl Iterator<Item = &String> {
+    self.readers.keys()
+  }
+
+  /// Returns the next topic lifecycle event or sample, or `None` if there is
+  /// nothing new. This does not block.
+  pub fn take_next_event(&mut self) -> ReadResult<Option<WildcardEvent<D>>> {
+    if self.tracker.refresh_due() {
+      let readers = &mut self.readers;
+      self
+        .tracker
+        .refresh(&mut self.pending, |name, reader| match reader {
+          Some(reader) => {
+            readers.insert(name.to_string(), reader);
+          }
+          None => {
+            readers.remove(name);
+          }
+        });
+    }
+    if let Some(event) = self.pending.pop_front() {
+      return Ok(Some(event));
+    }
+
+    let count = self.readers.len();
+    for i in 0..count {
+      let index = (self.next_reader + i) % count;
+      let Some((topic_name, reader)) = self.readers.iter_mut().nth(index) else {
+        break;
+      };
+      if let Some(sample) = reader.take_next_sample()? {
+        self.next_reader = index + 1;
+        return Ok(Some(WildcardEvent::Sample {
+          topic_name: topic_name.clone(),
+          sample,
+        }));
+      }
+    }
+    Ok(None)
+  }
+
+  /// Converts to an async stream of events.
+  ///
+  /// The stream checks Discovery for new and vanished topics periodically,
+  /// also when no samples arrive.
+  pub fn async_event_stream(self) -> WildcardEventStream<D, DA> {
+    WildcardEventStream {
+      tracker: self.tracker,
+      streams: self
+        .readers
+        .into_iter()
+        .map(|(name, reader)| (name, reader.async_sample_stream()))
+        .collect(),
+      pending: self.pending,
+      next_stream: self.next_reader,
+      refresh_alarm: None,
+    }
+  }
+}
+
+/// Async stream of events from a [`WildcardSubscription`].
+///
+/// See [`WildcardSubscription::async_event_stream`].
+pub struct WildcardEventStream<D: 'static, DA: DeserializerAdapter<D> + 'static> {
+  tracker: TopicTracker,
+  streams: BTreeMap<String, DataReaderStream<D, DA>>,
+  pending: VecDeque<WildcardEvent<D>>,
+  next_stream: usize,
+  // Wakes the task for the next Discovery check, which is due at the Instant
+  refresh_alarm: Option<(Instant, Alarm)>,
+}
+
+impl<D, DA> WildcardEventStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  /// Names of the topics currently subscribed.
+  pub fn topic_names(&self) -> impl Iterator<Item = &String> {
+    self.streams.keys()
+  }
+}
+
+impl<D, DA> Unpin for WildcardEventStream<D, DA>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+}
+
+impl<D, DA> Stream for WildcardEventStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  type Item = ReadResult<WildcardEvent<D>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    if this.tracker.refresh_due() {
+      let streams = &mut this.streams;
+      this
+        .tracker
+        .refresh(&mut this.pending, |name, reader| match reader {
+          Some(reader) => {
+            streams.insert(name.to_string(), reader.async_sample_stream());
+          }
+          None => {
+            streams.remove(name);
+          }
+        });
+    }
+    if let Some(event) = this.pending.pop_front() {
+      return Poll::Ready(Some(Ok(event)));
+    }
+
+    // Poll every stream, unless one is ready, so that all of them have our
+    // waker.
+    let count = this.streams.len();
+    for i in 0..count {
+      let index = (this.next_stream + i) % count;
+      let Some((topic_name, stream)) = this.streams.iter_mut().nth(index) else {
+        break;
+      };
+      match Pin::new(stream).poll_next(cx) {
+        Poll::Ready(Some(result)) => {
+          this.next_stream = index + 1;
+          return Poll::Ready(Some(result.map(|sample| WildcardEvent::Sample {
+            topic_name: topic_name.clone(),
+            sample,
+          })));
+        }
+        Poll::Ready(None) | Poll::Pending => (),
+      }
+    }
+
+    let next_refresh = this.tracker.next_refresh;
+    match &this.refresh_alarm {
+      Some((deadline, alarm)) if *deadline == next_refresh => alarm.set_waker(cx.waker()),
+      _ => this.refresh_alarm = Some((next_refresh, Alarm::new(next_refresh, cx.waker()))),
+    }
+    Poll::Pending
+  }
+}
+
+impl<D, DA> FusedStream for WildcardEventStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  fn is_terminated(&self) -> bool {
+    false // Never terminate. This means it is always valid to call poll_next().
+  }
+}
+
+// Keeps track of the matching topics and when they last had writers. This is
+// shared between the sync and async versions.
+struct TopicTracker {
+  subscriber: Subscriber,
+  pattern: String,
+  grace_period: Duration,
+  qos: Option<QosPolicies>,
+  // Subscribed topic name -> last time it had writers
+  writers_seen: BTreeMap<String, Instant>,
+  next_refresh: Instant,
+}
+
+impl TopicTracker {
+  fn new(
+    subscriber: Subscriber,
+    pattern: &str,
+    grace_period: Duration,
+    qos: Option<QosPolicies>,
+  ) -> Self {
+    Self {
+      subscriber,
+      pattern: pattern.to_string(),
+      grace_period,
+      qos,
+      writers_seen: BTreeMap::new(),
+      next_refresh: Instant::now(),
+    }
+  }
+
+  fn refresh_due(&self) -> bool {
+    Instant::now() >= self.next_refresh
+  }
+
+  // Compares the subscribed topics to Discovery. Calls `update` with a new
+  // DataReader for each added topic, and with None for each removed topic, and
+  // queues the corresponding events.
+  fn refresh<D, DA, F>(&mut self, events: &mut VecDeque<WildcardEvent<D>>, mut update: F)
+  where
+    D: 'static,
+    DA: DeserializerAdapter<D>,
+    F: FnMut(&str, Option<DataReader<D, DA>>),
+  {
+    let now = Instant::now();
+    self.next_refresh = now + REFRESH_PERIOD;
+    let Some(participant) = self.subscriber.participant() else {
+      return;
+    };
+    let written_topics = discovery_db_read(&participant.discovery_db()).written_topics();
+
+    for (topic_name, type_name) in written_topics {
+      if !glob_match(&self.pattern, &topic_name) {
+        continue;
+      }
+      if let Some(seen) = self.writers_seen.get_mut(&topic_name) {
+        *seen = now;
+        continue;
+      }
+      match self.create_reader(&topic_name, &type_name) {
+        Ok(reader) => {
+          debug!("Wildcard subscription {} added {topic_name}", self.pattern);
+          update(&topic_name, Some(reader));
+          self.writers_seen.insert(topic_name.clone(), now);
+          events.push_back(WildcardEvent::TopicAdded {
+            topic_name,
+            type_name,
+          });
+        }
+        // Retried on the next refresh
+        Err(e) => warn!("Wildcard subscription cannot read {topic_name}: {e}"),
+      }
+    }
+
+    let grace_period = self.grace_period;
+    let expired: Vec<String> = self
+      .writers_seen
+      .iter()
+      .filter(|(_name, seen)| now.duration_since(**seen) > grace_period)
+      .map(|(name, _seen)| name.clone())
+      .collect();
+    for topic_name in expired {
+      debug!(
+        "Wildcard subscription {} removed {topic_name}",
+        self.pattern
+      );
+      self.writers_seen.remove(&topic_name);
+      update(&topic_name, None);
+      events.push_back(WildcardEvent::TopicRemoved { topic_name });
+    }
+  }
+
+  fn create_reader<D, DA>(
+    &self,
+    topic_name: &str,
+    type_name: &str,
+  ) -> CreateResult<DataReader<D, DA>>
+  where
+    D: 'static,
+    DA: DeserializerAdapter<D>,
+  {
+    let Some(participant) = self.subscriber.participant() else {
+      return create_error_dropped!("DomainParticipant has been dropped.");
+    };
+    let topic_qos = self
+      .qos
+      .clone()
+      .unwrap_or_else(|| participant.get_default_topic_qos());
+    let topic = participant.create_topic(
+      topic_name.to_string(),
+      type_name.to_string(),
+      &topic_qos,
+      TopicKind::NoKey,
+    )?;
+    self
+      .subscriber
+      .create_datareader_no_key::<D, DA>(&topic, self.qos.clone())
+  }
+}
+
+// Matches `name` to a pattern, where `*` matches any sequence of characters and
+// `?` matches any single character.
+fn glob_match(pattern: &str, name: &str) -> bool {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let name: Vec<char> = name.chars().collect();
+  let (mut p, mut n) = (0, 0);
+  // Position of the last `*` in pattern, and the name position it was tried at
+  let mut backtrack: Option<(usize, usize)> = None;
+  while n < name.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        backtrack = Some((p, n));
+        p += 1;
+      }
+      Some(c) if *c == '?' || *c == name[n] => {
+        p += 1;
+        n += 1;
+      }
+      _ => match backtrack {
+        // Let the last `*` match one more character
+        Some((star, star_n)) => {
+          p = star + 1;
+          n = star_n + 1;
+          backtrack = Some((star, star_n + 1));
+        }
+        None => return false,
+      },
+    }
+  }
+  pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+  use std::thread;
+
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::{
+    serialization::{RawDeserializerAdapter, RawSample},
+    DomainParticipant, QosPolicyBuilder, RepresentationIdentifier,
+  };
+
+  #[test]
+  fn wildcard_glob_match() {
+    assert!(glob_match("telemetry/*", "telemetry/imu"));
+    assert!(glob_match("telemetry/*", "telemetry/"));
+    assert!(glob_match("telemetry/*", "telemetry/a/b"));
+    assert!(!glob_match("telemetry/*", "telemetry"));
+    assert!(!glob_match("telemetry/*", "control/telemetry/imu"));
+    assert!(glob_match("*/imu", "telemetry/imu"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("a?c", "abc"));
+    assert!(!glob_match("a?c", "ac"));
+    assert!(glob_match("a*b*c", "aXbYbZc"));
+    assert!(!glob_match("a*b*c", "aXbYbZ"));
+    assert!(glob_match("exact", "exact"));
+    assert!(!glob_match("exact", "exact2"));
+  }
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Telemetry {
+    value: i32,
+  }
+
+  #[test]
+  fn wildcard_subscription_follows_topics() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new().build();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+
+    let matching = dp
+      .create_topic(
+        "wildcard_test/telemetry".to_string(),
+        "Telemetry".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let other = dp
+      .create_topic(
+        "wildcard_test_other".to_string(),
+        "Telemetry".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer = publisher
+      .create_datawriter_no_key_cdr::<Telemetry>(&matching, None)
+      .unwrap();
+    let _other_writer = publisher
+      .create_datawriter_no_key_cdr::<Telemetry>(&other, None)
+      .unwrap();
+
+    let mut wildcard = subscriber
+      .create_wildcard_subscription::<Telemetry, CDRDeserializerAdapter<_>>(
+        "wildcard_test/*",
+        Duration::from_millis(200),
+        None,
+      );
+
+    let next_event = |wildcard: &mut WildcardSubscription<Telemetry>| {
+      for _ in 0..100 {
+        if let Some(event) = wildcard.take_next_event().unwrap() {
+          return event;
+        }
+        thread::sleep(Duration::from_millis(50));
+      }
+      panic!("No wildcard event");
+    };
+
+    match next_event(&mut wildcard) {
+      WildcardEvent::TopicAdded {
+        topic_name,
+        type_name,
+      } => {
+        assert_eq!(topic_name, "wildcard_test/telemetry");
+        assert_eq!(type_name, "Telemetry");
+      }
+      e => panic!("Unexpected {e:?}"),
+    }
+    assert_eq!(
+      wildcard.topic_names().collect::<Vec<_>>(),
+      vec!["wildcard_test/telemetry"]
+    );
+
+    // The reader may need a moment to be matched.
+    thread::sleep(Duration::from_millis(200));
+    writer.write(Telemetry { value: 42 }, None).unwrap();
+    match next_event(&mut wildcard) {
+      WildcardEvent::Sample { topic_name, sample } => {
+        assert_eq!(topic_name, "wildcard_test/telemetry");
+        assert_eq!(sample.into_value(), Telemetry { value: 42 });
+      }
+      e => panic!("Unexpected {e:?}"),
+    }
+
+    drop(writer);
+    match next_event(&mut wildcard) {
+      WildcardEvent::TopicRemoved { topic_name } => {
+        assert_eq!(topic_name, "wildcard_test/telemetry");
+      }
+      e => panic!("Unexpected {e:?}"),
+    }
+    assert_eq!(wildcard.topic_names().count(), 0);
+  }
+
+  #[test]
+  fn wildcard_event_stream() {
+    use futures::StreamExt;
+
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp
+      .create_topic(
+        "wildcard_stream_test/telemetry".to_string(),
+        "Telemetry".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<Telemetry>(&topic, None)
+      .unwrap();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_wildcard_subscription::<Telemetry, CDRDeserializerAdapter<_>>(
+        "wildcard_stream_test/*",
+        Duration::from_secs(10),
+        None,
+      )
+      .async_event_stream();
+
+    futures::executor::block_on(async {
+      match stream.next().await {
+        Some(Ok(WildcardEvent::TopicAdded { topic_name, .. })) => {
+          assert_eq!(topic_name, "wildcard_stream_test/telemetry");
+        }
+        e => panic!("Unexpected {e:?}"),
+      }
+      thread::sleep(Duration::from_millis(200));
+      writer.write(Telemetry { value: 7 }, None).unwrap();
+      match stream.next().await {
+        Some(Ok(WildcardEvent::Sample { sample, .. })) => {
+          assert_eq!(sample.into_value(), Telemetry { value: 7 });
+        }
+        e => panic!("Unexpected {e:?}"),
+      }
+    });
+  }
+
+  #[test]
+  fn wildcard_raw_event_stream() {
+    use futures::StreamExt;
+
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new().build();
+    let topic = dp
+      .create_topic(
+        "wildcard_raw_test/telemetry".to_string(),
+        "Telemetry".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<Telemetry>(&topic, None)
+      .unwrap();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_wildcard_subscription::<RawSample, RawDeserializerAdapter>(
+        "wildcard_raw_test/*",
+        Duration::from_millis(200),
+        None,
+      )
+      .async_event_stream();
+
+    futures::executor::block_on(async {
+      match stream.next().await {
+        Some(Ok(WildcardEvent::TopicAdded { type_name, .. })) => {
+          assert_eq!(type_name, "Telemetry");
+        }
+        e => panic!("Unexpected {e:?}"),
+      }
+      thread::sleep(Duration::from_millis(200));
+      writer.write(Telemetry { value: 7 }, None).unwrap();
+      match stream.next().await {
+        Some(Ok(WildcardEvent::Sample { sample, .. })) => {
+          let raw = sample.into_value();
+          assert_eq!(raw.encoding, RepresentationIdentifier::CDR_LE);
+          let decoded: Telemetry =
+            CDRDeserializerAdapter::from_bytes(&raw.payload, raw.encoding).unwrap();
+          assert_eq!(decoded, Telemetry { value: 7 });
+        }
+        e => panic!("Unexpected {e:?}"),
+      }
+
+      // Removal is noticed without any samples to wake up the stream.
+      drop(writer);
+      match stream.next().await {
+        Some(Ok(WildcardEvent::TopicRemoved { topic_name })) => {
+          assert_eq!(topic_name, "wildcard_raw_test/telemetry");
+        }
+        e => panic!("Unexpected {e:?}"),
+      }
+    });
+  }
+}
@@ -1,5 +1,7 @@
 use std::{
+  collections::HashMap,
   io,
+  ops::Range,
   pin::Pin,
   sync::{Arc, Mutex, MutexGuard},
   task::{Context, Poll},
@@ -7,23 +9,33 @@ use std::{
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
-use futures::stream::{FusedStream, Stream};
+use futures::{
+  future::{self, FusedFuture},
+  stream::{FusedStream, Stream, StreamExt, TakeUntil},
+  Future,
+};
 
-use super::datasample_cache::DataSampleCache;
+use super::datasample_cache::{AddSampleOutcome, DataSampleCache};
 use crate::{
   dds::{
     adapters::with_key::{DefaultDecoder, *},
     key::*,
+    pubsub::Subscriber,
     qos::*,
     readcondition::*,
+    sampleinfo::{InstanceInfo, SampleInfo},
     result::ReadResult,
     statusevents::*,
+    topic::{Topic, TopicDescription, TopicKind},
     with_key::{datasample::*, simpledatareader::*},
     ReadError,
   },
   discovery::sedp_messages::PublicationBuiltinTopicData,
   serialization::CDRDeserializerAdapter,
-  structure::{duration::Duration, entity::RTPSEntity, guid::GUID, time::Timestamp},
+  structure::{
+    duration::Duration, entity::RTPSEntity, guid::GUID, sequence_number::SequenceNumber,
+    time::Timestamp,
+  },
 };
 
 /// Simplified type for CDR encoding
@@ -37,6 +49,22 @@ pub enum SelectByKey {
   Next,
 }
 
+/// What a [`DataReader`] does when it receives a sample for a new instance,
+/// but the `max_instances` limit of
+/// [`ResourceLimits`](crate::policy::ResourceLimits) QoS has already been
+/// reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InstanceEviction {
+  /// The new sample is rejected. This is the DDS-specified behaviour.
+  /// [`DataReaderStatus::SampleRejected`] is generated.
+  #[default]
+  Reject,
+  /// The least recently updated instance is removed from the DataReader,
+  /// including its samples, to make room for the new instance.
+  /// [`DataReaderStatus::InstanceEvicted`] is generated.
+  LruEvict,
+}
+
 /// DDS DataReader for with_key topics.
 ///
 /// # Examples
@@ -69,9 +97,16 @@ pub enum SelectByKey {
 /// *Note:* Many DataReader methods require mutable access to `self`, because
 /// they need to mutate the datasample cache, which is an essential content of
 /// this struct.
+///
+/// Samples are deserialized in the thread that calls the read and take
+/// methods, or polls the async stream, not in the RTPS event loop thread. To
+/// keep a slow DeserializerAdapter from delaying other topics, read its
+/// DataReader from a thread or task of its own.
 pub struct DataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializerAdapter<D>> {
   simple_data_reader: SimpleDataReader<D, DA>,
   datasample_cache: DataSampleCache<D>, // DataReader-local cache of deserialized samples
+  rejected_by_instances_count: i32,
+  evicted_instances_count: i32,
 }
 
 impl<D: 'static, DA> DataReader<D, DA>
@@ -80,13 +115,31 @@ where
   DA: DeserializerAdapter<D>,
 {
   pub(crate) fn from_simple_data_reader(simple_data_reader: SimpleDataReader<D, DA>) -> Self {
-    let dsc = DataSampleCache::new(simple_data_reader.qos().clone());
+    let mut dsc = DataSampleCache::new(simple_data_reader.qos().clone());
+    dsc.set_keyless(simple_data_reader.topic().kind() == TopicKind::NoKey);
 
     Self {
       simple_data_reader,
       datasample_cache: dsc,
+      rejected_by_instances_count: 0,
+      evicted_instances_count: 0,
     }
   }
+
+  /// Sets the behaviour when the ResourceLimits `max_instances` QoS limit is
+  /// reached. The default is [`InstanceEviction::Reject`].
+  ///
+  /// This is useful for keyed topics with an unpredictable number of
+  /// instances, where old instances may be forgotten.
+  pub fn set_instance_eviction(&mut self, instance_eviction: InstanceEviction) {
+    self
+      .datasample_cache
+      .set_instance_eviction(instance_eviction);
+  }
+
+  pub fn instance_eviction(&self) -> InstanceEviction {
+    self.datasample_cache.instance_eviction()
+  }
 }
 
 impl<D: 'static, DA> DataReader<D, DA>
@@ -98,11 +151,45 @@ where
   // the serialized payload and stores the DataSamples (the actual data and the
   // samplestate) to local container, datasample_cache.
   fn fill_and_lock_local_datasample_cache(&mut self) -> ReadResult<()> {
+    // Writer QoS is looked up from Discovery only once per Writer per fill.
+    let mut writer_qos_map: HashMap<GUID, Option<Arc<QosPolicies>>> = HashMap::new();
     while let Some(dcc) = self.simple_data_reader.try_take_one()? {
-      self
+      let writer_qos = writer_qos_map
+        .entry(dcc.writer_guid)
+        .or_insert_with(|| {
+          self
+            .simple_data_reader
+            .writer_qos(dcc.writer_guid)
+            .map(Arc::new)
+        })
+        .clone();
+      match self
         .datasample_cache
-        .fill_from_deserialized_cache_change(dcc);
+        .fill_from_deserialized_cache_change(dcc, writer_qos)
+      {
+        AddSampleOutcome::Added => (),
+        AddSampleOutcome::RejectedByInstancesLimit => {
+          self.rejected_by_instances_count += 1;
+          self
+            .simple_data_reader
+            .send_status(DataReaderStatus::SampleRejected {
+              count: CountWithChange::new(self.rejected_by_instances_count, 1),
+              last_reason: SampleRejectedStatusKind::ByInstancesLimit,
+            });
+        }
+        AddSampleOutcome::AddedWithEviction => {
+          self.evicted_instances_count += 1;
+          self
+            .simple_data_reader
+            .send_status(DataReaderStatus::InstanceEvicted {
+              count: CountWithChange::new(self.evicted_instances_count, 1),
+            });
+        }
+      }
     }
+    self
+      .datasample_cache
+      .autopurge_not_alive_instances(Timestamp::now());
     Ok(())
   }
 
@@ -251,6 +338,118 @@ where
     Ok(result)
   }
 
+  /// Reads samples like [`read`](Self::read), but only those whose value
+  /// satisfies `predicate`.
+  ///
+  /// Samples that do not match are left in the DataReader untouched: they are
+  /// not marked read, and their instances are not marked viewed. Dispose
+  /// samples have no value, so they never match.
+  ///
+  /// The predicate is evaluated at most once per sample, and only until
+  /// `max_samples` matching samples have been found. The `sample_rank`,
+  /// `generation_rank`, and `absolute_generation_rank` in the [`SampleInfo`]
+  /// given to the predicate are not final, because they depend on which
+  /// samples are selected.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let positive = data_reader.read_matching(10, ReadCondition::not_read(), |d, _info| d.a > 0);
+  /// ```
+  pub fn read_matching(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    predicate: impl Fn(&D, &SampleInfo) -> bool,
+  ) -> ReadResult<Vec<DataSample<&D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let (selected, _rejected) =
+      self
+        .datasample_cache
+        .select_matching_keys_for_access(read_condition, max_samples, predicate);
+
+    Ok(self.datasample_cache.read_by_keys(&selected))
+  }
+
+  /// Takes samples like [`take`](Self::take), but only those whose value
+  /// satisfies `predicate`.
+  ///
+  /// Samples that do not match are left in the DataReader untouched, and can
+  /// be read or taken later. The taken samples keep their relative order.
+  /// See [`read_matching`](Self::read_matching) for details.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// // Only consume samples from a specific writer
+  /// let writer = GUID::GUID_UNKNOWN;
+  /// let from_writer =
+  ///   data_reader.take_matching(10, ReadCondition::any(), |_d, info| info.writer_guid() == writer);
+  /// ```
+  pub fn take_matching(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    predicate: impl Fn(&D, &SampleInfo) -> bool,
+  ) -> ReadResult<Vec<DataSample<D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let (selected, _rejected) =
+      self
+        .datasample_cache
+        .select_matching_keys_for_access(read_condition, max_samples, predicate);
+    trace!("take_matching selected count = {}", selected.len());
+
+    Ok(self.take_by_keys(&selected))
+  }
+
   /// Reads next unread sample
   ///
   /// # Examples
@@ -290,6 +489,54 @@ where
     Ok(ds.pop())
   }
 
+  /// Returns samples borrowed by [`read`](Self::read), ending the loan.
+  ///
+  /// This corresponds to `return_loan` in the DDS specification. In RustDDS
+  /// the loan is tracked by the borrow checker: the DataReader cannot be
+  /// used, and no new data is added to its cache, while the borrowed samples
+  /// are alive. Dropping the samples ends the loan just as well, but calling
+  /// this makes the end explicit.
+  ///
+  /// The samples remain in the cache in state
+  /// [`Read`](crate::SampleState::Read). Use
+  /// [`take`](Self::take) to remove samples from the cache.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// # let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let samples = data_reader.read(10, ReadCondition::not_read()).unwrap();
+  /// // do something with the samples
+  /// DataReader::<SomeType, CDRDeserializerAdapter<_>>::return_loan(samples);
+  ///
+  /// // The DataReader can be used again.
+  /// let _samples = data_reader.take(10, ReadCondition::any()).unwrap();
+  /// ```
+  pub fn return_loan(samples: Vec<DataSample<&D>>) {
+    drop(samples);
+  }
+
   /// Takes next unread sample
   ///
   /// # Examples
@@ -329,6 +576,63 @@ where
     Ok(ds.pop())
   }
 
+  /// Reads the oldest NOT_READ sample without blocking, returning a copy of
+  /// it. Returns `Ok(None)` immediately, if there is nothing new.
+  ///
+  /// Unlike [`read_next_sample`](Self::read_next_sample), this does not scan
+  /// the whole local sample cache, so it is suitable for polling in tight
+  /// loops, e.g. after a `mio` readiness event. The sample is marked read, but
+  /// remains in the cache.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize, Clone)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// # let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// // Nothing has been written, so this returns immediately.
+  /// assert!(data_reader.try_read_one().unwrap().is_none());
+  /// ```
+  pub fn try_read_one(&mut self) -> ReadResult<Option<DataSample<D>>>
+  where
+    D: Clone,
+  {
+    Ok(
+      self
+        .read_first_not_read()?
+        .map(|ds| DataSample::new(ds.sample_info, ds.value.map_value(D::clone))),
+    )
+  }
+
+  pub(crate) fn read_first_not_read(&mut self) -> ReadResult<Option<DataSample<&D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    match self.datasample_cache.first_not_read_key() {
+      Some(key) => Ok(self.datasample_cache.read_by_keys(&[key]).pop()),
+      None => Ok(None),
+    }
+  }
+
   // Iterator interface
 
   fn read_bare(
@@ -639,6 +943,95 @@ where
     Ok(result)
   }
 
+  /// Gets the state of an instance and the [`SampleInfo`] of the latest change
+  /// to it. The information is retained also after the samples of the instance
+  /// have been taken, so e.g. the source of a dispose can be inspected with
+  /// [`InstanceInfo::disposed_by`].
+  ///
+  /// Returns `None` if the instance is not known to this DataReader. This does
+  /// not mark any samples as read.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// if let Ok(Some(info)) = data_reader.instance_info(&3) {
+  ///   if let Some(writer) = info.disposed_by() {
+  ///     println!("Instance 3 was disposed by {writer:?}");
+  ///   }
+  /// }
+  /// ```
+  pub fn instance_info(&mut self, key: &D::K) -> ReadResult<Option<InstanceInfo>> {
+    self.fill_and_lock_local_datasample_cache()?;
+    Ok(self.datasample_cache.instance_info(key))
+  }
+
+  /// Resolves an [`InstanceHandle`] to the key of the instance, e.g. the
+  /// `last_instance` of a
+  /// [`DataReaderStatus::RequestedDeadlineMissed`].
+  ///
+  /// Returns `None` if no sample or dispose of the instance has been received.
+  /// This does not mark any samples as read.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// if let Some(DataReaderStatus::RequestedDeadlineMissed { last_instance: Some(handle), .. }) =
+  ///   data_reader.try_recv_status()
+  /// {
+  ///   if let Ok(Some(key)) = data_reader.instance_for_handle(handle) {
+  ///     println!("Instance {key} missed its deadline");
+  ///   }
+  /// }
+  /// ```
+  pub fn instance_for_handle(&mut self, handle: InstanceHandle) -> ReadResult<Option<D::K>> {
+    self.fill_and_lock_local_datasample_cache()?;
+    Ok(self.simple_data_reader.instance_for_handle(handle))
+  }
+
   /// Similar to read_instance, but will return owned datasamples
   /// This should cover DDS DataReader methods take_instance,
   /// take_next_instance, take_next_instance_w_condition.
@@ -706,220 +1099,607 @@ where
     Ok(result)
   }
 
-  /// Return values:
-  /// true - got all historical data
-  /// false - timeout before all historical data was received
-  pub fn wait_for_historical_data(&mut self, _max_wait: Duration) -> bool {
-    todo!()
-  }
-
-  // Spec calls for two separate functions:
-  // get_matched_publications returns a list of handles
-  // get_matched_publication_data returns PublicationBuiltinTopicData for a handle
-  // But we do not believe in handle-oriented programming, so just return
-  // the actual data right away. Since the handles are quite opaque, about the
-  // only thing that could be done with the handles would be counting how many
-  // we got.
-
-  pub fn get_matched_publications(&self) -> impl Iterator<Item = PublicationBuiltinTopicData> {
-    // TODO: Obviously not implemented
-    vec![].into_iter()
-  }
-
-  /// An async stream for reading the (bare) data samples.
-  /// The resulting Stream can be used to get another stream of status events.
-  pub fn async_bare_sample_stream(self) -> BareDataReaderStream<D, DA> {
-    BareDataReaderStream {
-      datareader: Arc::new(Mutex::new(self)),
-    }
-  }
-
-  /// An async stream for reading the data samples.
-  /// The resulting Stream can be used to get another stream of status events.
-  pub fn async_sample_stream(self) -> DataReaderStream<D, DA> {
-    DataReaderStream {
-      datareader: Arc::new(Mutex::new(self)),
-    }
-  }
-} // impl
-
-// -------------------
-
-impl<D, DA> mio_06::Evented for DataReader<D, DA>
-where
-  D: Keyed,
-  DA: DeserializerAdapter<D>,
-{
-  // We just delegate all the operations to notification_receiver, since it
-  // already implements mio_06::Evented
-  fn register(
-    &self,
-    poll: &mio_06::Poll,
-    token: mio_06::Token,
-    interest: mio_06::Ready,
-    opts: mio_06::PollOpt,
-  ) -> io::Result<()> {
-    self
-      .simple_data_reader
-      .register(poll, token, interest, opts)
+  /// Blocks the calling thread until there is at least one NOT_READ sample
+  /// available, or `timeout` expires.
+  ///
+  /// Returns `Ok(true)` if data is available, and `Ok(false)` on timeout.
+  /// Returns immediately if unread samples already exist. This is meant to be
+  /// followed by e.g. [`read`](Self::read) or [`take`](Self::take).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// if let Ok(true) = data_reader.wait_for_data(Duration::from_millis(10)) {
+  ///   let datas = data_reader.take(10, ReadCondition::not_read());
+  /// }
+  /// ```
+  pub fn wait_for_data(&mut self, timeout: Duration) -> ReadResult<bool> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+    if !self
+      .select_keys_for_access(ReadCondition::not_read())
+      .is_empty()
+    {
+      return Ok(true);
+    }
+    self.simple_data_reader.wait_for_data(timeout)
   }
 
-  fn reregister(
-    &self,
-    poll: &mio_06::Poll,
-    token: mio_06::Token,
-    interest: mio_06::Ready,
-    opts: mio_06::PollOpt,
-  ) -> io::Result<()> {
-    self
-      .simple_data_reader
-      .reregister(poll, token, interest, opts)
+  /// Ranges of sequence numbers that have not been received, per matched
+  /// Writer.
+  ///
+  /// This is observational only, and is available also in BestEffort mode,
+  /// where RTPS does not try to recover lost samples. The application may use
+  /// this to request retransmission by some other means.
+  ///
+  /// See
+  /// [`SimpleDataReader::observed_gaps`](crate::with_key::SimpleDataReader::observed_gaps)
+  /// for details.
+  pub fn observed_gaps(&self) -> Vec<(GUID, Vec<Range<SequenceNumber>>)> {
+    self.simple_data_reader.observed_gaps()
   }
 
-  fn deregister(&self, poll: &mio_06::Poll) -> io::Result<()> {
-    self.simple_data_reader.deregister(poll)
+  /// Locators of the matched DataWriters, and which of them an
+  /// acknowledgement was last successfully sent to.
+  ///
+  /// This is meant for debugging connectivity. It asks the RTPS processing
+  /// thread for a snapshot, so it blocks for a moment.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// for writer in data_reader.remote_writer_locators().unwrap() {
+  ///   println!("{:?} last reached at {:?}", writer.guid, writer.last_used);
+  /// }
+  /// ```
+  pub fn remote_writer_locators(&self) -> ReadResult<Vec<RemoteLocators>> {
+    self.simple_data_reader.remote_writer_locators()
   }
-}
 
-impl<D, DA> mio_08::event::Source for DataReader<D, DA>
-where
-  D: Keyed,
-  DA: DeserializerAdapter<D>,
-{
-  fn register(
-    &mut self,
-    registry: &mio_08::Registry,
-    token: mio_08::Token,
-    interests: mio_08::Interest,
-  ) -> io::Result<()> {
-    // SimpleDataReader implements .register() for two traits, so need to
-    // use disambiguation syntax to call .register() here.
-    <SimpleDataReader<D, DA> as mio_08::event::Source>::register(
-      &mut self.simple_data_reader,
-      registry,
-      token,
-      interests,
-    )
+  /// Applies backpressure to reliable DataWriters when the application cannot
+  /// keep up with reading.
+  ///
+  /// While paused, the Reader does not send acknowledgements (ACKNACKs) for
+  /// received samples. A reliable DataWriter then stops sending once its
+  /// history is full of unacknowledged samples, or blocks in `write`
+  /// according to its Reliability `max_blocking_time`. Samples that still
+  /// arrive are received normally. When resumed, an acknowledgement is sent
+  /// to all matched DataWriters immediately.
+  ///
+  /// Best-effort DataReaders are not affected. DataReaders created with the
+  /// same Subscriber, Topic and QoS may share an RTPS Reader, and pausing
+  /// affects all of them.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_reader.set_flow_control_pause(true).unwrap();
+  /// // ... catch up with processing ...
+  /// data_reader.set_flow_control_pause(false).unwrap();
+  /// ```
+  pub fn set_flow_control_pause(&self, paused: bool) -> ReadResult<()> {
+    self.simple_data_reader.set_flow_control_pause(paused)
   }
 
-  fn reregister(
-    &mut self,
-    registry: &mio_08::Registry,
-    token: mio_08::Token,
-    interests: mio_08::Interest,
-  ) -> io::Result<()> {
-    <SimpleDataReader<D, DA> as mio_08::event::Source>::reregister(
-      &mut self.simple_data_reader,
-      registry,
-      token,
-      interests,
-    )
+  /// Pauses receiving samples from the network.
+  ///
+  /// While paused, incoming samples are dropped before they reach the
+  /// DataReader cache, and nothing is acknowledged to the DataWriters.
+  /// Reliable DataWriters keep the unacknowledged samples in their history
+  /// and resend them after [`resume`](Self::resume), unless a KeepLast
+  /// history has replaced them meanwhile. Best-effort samples arriving while
+  /// paused are lost. Samples already in the cache can still be read.
+  ///
+  /// Unlike [`set_flow_control_pause`](Self::set_flow_control_pause), this
+  /// stops samples from arriving, not only the acknowledgements. Like it,
+  /// this affects all DataReaders sharing the same RTPS Reader.
+  pub fn pause(&self) -> ReadResult<()> {
+    self.simple_data_reader.set_delivery_pause(true)
   }
 
-  fn deregister(&mut self, registry: &mio_08::Registry) -> io::Result<()> {
-    <SimpleDataReader<D, DA> as mio_08::event::Source>::deregister(
-      &mut self.simple_data_reader,
-      registry,
-    )
+  /// Resumes receiving samples after [`pause`](Self::pause). Reliable
+  /// DataWriters are asked to resend what was missed.
+  pub fn resume(&self) -> ReadResult<()> {
+    self.simple_data_reader.set_delivery_pause(false)
   }
-}
 
-impl<'a, D, DA> StatusEvented<'a, DataReaderStatus, SimpleDataReaderEventStream<'a, D, DA>>
-  for DataReader<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D>,
-{
-  fn as_status_evented(&mut self) -> &dyn mio_06::Evented {
-    self.simple_data_reader.as_status_evented()
+  /// Writes a human-readable summary of the state of this DataReader to
+  /// `out`: the number of cached samples per instance, the matched Writers,
+  /// and which sequence numbers are still missing from each.
+  ///
+  /// This is meant for diagnosing e.g. why samples are not arriving. The
+  /// format is not stable. It asks the RTPS processing thread for a snapshot,
+  /// so it blocks for a moment.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_reader.print_diagnostic(&mut std::io::stderr()).unwrap();
+  /// ```
+  pub fn print_diagnostic(&mut self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+    self
+      .fill_and_lock_local_datasample_cache()
+      .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let instances = self.datasample_cache.instance_summary();
+    writeln!(
+      out,
+      "DataReader {:?} topic={:?}, {} instances in cache",
+      self.guid(),
+      self.topic().name(),
+      instances.len()
+    )?;
+    for (key_hash, sample_count, instance_state) in instances {
+      let hex: String = key_hash
+        .to_vec()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+      writeln!(
+        out,
+        "  instance {hex}: {sample_count} samples, {instance_state:?}"
+      )?;
+    }
+    self.simple_data_reader.print_diagnostic(out)
   }
 
-  fn as_status_source(&mut self) -> &mut dyn mio_08::event::Source {
-    self.simple_data_reader.as_status_source()
+  /// Returns the counts of matched Writers, and resets the `count_change`
+  /// fields.
+  ///
+  /// See
+  /// [`SimpleDataReader::get_subscription_matched_status`](crate::with_key::SimpleDataReader::get_subscription_matched_status).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status = data_reader.get_subscription_matched_status();
+  /// if status.current_count.count_change() > 0 {
+  ///   // new Writers have been matched since the last call
+  /// }
+  /// ```
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self.simple_data_reader.get_subscription_matched_status()
   }
 
-  fn as_async_status_stream(&'a self) -> SimpleDataReaderEventStream<'a, D, DA> {
-    self.simple_data_reader.as_async_status_stream()
+  /// Returns the counts of alive and not alive matched Writers, and resets
+  /// the `count_change` fields.
+  ///
+  /// See
+  /// [`SimpleDataReader::get_liveliness_changed_status`](crate::with_key::SimpleDataReader::get_liveliness_changed_status).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status = data_reader.get_liveliness_changed_status();
+  /// if status.not_alive_count.count() > 0 {
+  ///   // some matched Writer has lost liveliness
+  /// }
+  /// ```
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.simple_data_reader.get_liveliness_changed_status()
   }
 
-  fn try_recv_status(&self) -> Option<DataReaderStatus> {
-    self.simple_data_reader.try_recv_status()
+  /// Topic this DataReader reads from.
+  ///
+  /// Topic name, type name and [`TopicKind`](crate::TopicKind) are available
+  /// through the [`TopicDescription`](crate::TopicDescription) trait.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// assert_eq!(data_reader.topic(), &topic);
+  /// assert_eq!(data_reader.topic().name(), "some_topic");
+  /// assert_eq!(data_reader.topic().get_type().name(), "SomeType");
+  /// assert_eq!(data_reader.topic().kind(), TopicKind::WithKey);
+  /// ```
+  pub fn topic(&self) -> &Topic {
+    self.simple_data_reader.topic()
   }
-}
 
-impl<D, DA> HasQoSPolicy for DataReader<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D>,
-{
-  fn qos(&self) -> QosPolicies {
-    self.simple_data_reader.qos().clone()
+  /// Subscriber this DataReader belongs to.
+  ///
+  /// The DataReader holds a handle to its Subscriber, so the Subscriber stays
+  /// alive at least as long as its readers. The Subscriber in turn refers to
+  /// the DomainParticipant only weakly, see
+  /// [`Subscriber::participant`](crate::Subscriber::participant).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// assert_eq!(data_reader.subscriber(), &subscriber);
+  /// ```
+  pub fn subscriber(&self) -> &Subscriber {
+    self.simple_data_reader.subscriber()
   }
-}
 
-impl<D, DA> RTPSEntity for DataReader<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D>,
-{
-  fn guid(&self) -> GUID {
-    self.simple_data_reader.guid()
+  /// Computes the RTPS key hash of the instance that `data` belongs to.
+  ///
+  /// This is the same hash as given by
+  /// [`DataWriter::get_key_hash_bytes`](crate::with_key::DataWriter::get_key_hash_bytes).
+  pub fn get_key_hash_bytes(&self, data: &D) -> [u8; 16] {
+    data.key().hash_key(false).to_bytes()
   }
-}
 
-// ----------------------------------------------
-// ----------------------------------------------
+  /// Return values:
+  /// true - got all historical data
+  /// false - timeout before all historical data was received
+  pub fn wait_for_historical_data(&mut self, _max_wait: Duration) -> bool {
+    todo!()
+  }
 
-// Async interface to the (bare) DataReader
+  // Spec calls for two separate functions:
+  // get_matched_publications returns a list of handles
+  // get_matched_publication_data returns PublicationBuiltinTopicData for a handle
+  // But we do not believe in handle-oriented programming, so just return
+  // the actual data right away. Since the handles are quite opaque, about the
+  // only thing that could be done with the handles would be counting how many
+  // we got.
 
-pub struct BareDataReaderStream<
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
-> {
-  datareader: Arc<Mutex<DataReader<D, DA>>>,
-}
+  pub fn get_matched_publications(&self) -> impl Iterator<Item = PublicationBuiltinTopicData> {
+    // TODO: Obviously not implemented
+    vec![].into_iter()
+  }
 
-impl<D, DA> BareDataReaderStream<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D>,
-{
-  /// Get a stream of status events
-  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
-    DataReaderEventStream {
-      datareader: Arc::clone(&self.datareader),
+  /// Gets the Discovery data of one matched DataWriter: its Topic, type and
+  /// offered QoS.
+  ///
+  /// Returns `None` if `writer_guid` is not currently matched with this
+  /// DataReader.
+  pub fn get_matched_publication_data(
+    &self,
+    writer_guid: GUID,
+  ) -> Option<PublicationBuiltinTopicData> {
+    self
+      .simple_data_reader
+      .get_matched_publication_data(writer_guid)
+  }
+
+  /// Describes the DataWriter that wrote a sample, given
+  /// [`SampleInfo::publication_handle`](crate::SampleInfo::publication_handle):
+  /// its Topic, type, QoS and GUID, from which the DomainParticipant can be
+  /// identified.
+  ///
+  /// This is the counterpart of `get_matched_publication_data` in the DDS
+  /// specification. Descriptions of matched DataWriters are cached in the
+  /// DataReader until the DataWriter is unmatched, so calling this for every
+  /// sample is cheap. Returns `None` if the DataWriter is not known to
+  /// Discovery, e.g. because it has been lost.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// if let Ok(Some(sample)) = data_reader.take_next_sample() {
+  ///   let writer = data_reader.publication_description(sample.sample_info().publication_handle());
+  ///   println!("from {:?}", writer.map(|w| w.guid.prefix));
+  /// }
+  /// ```
+  pub fn publication_description(&self, publication_handle: GUID) -> Option<EndpointDescription> {
+    self
+      .simple_data_reader
+      .publication_description(publication_handle)
+  }
+
+  /// An async stream for reading the (bare) data samples.
+  /// The resulting Stream can be used to get another stream of status events.
+  pub fn async_bare_sample_stream(self) -> BareDataReaderStream<D, DA> {
+    BareDataReaderStream {
+      datareader: Arc::new(Mutex::new(self)),
     }
   }
-  fn lock_datareader(&self) -> ReadResult<MutexGuard<'_, DataReader<D, DA>>> {
-    self.datareader.lock().map_err(|e| ReadError::Poisoned {
-      reason: format!("BareDataReaderStream could not lock datareader: {e:?}"),
-    })
+
+  /// An async stream for reading the data samples.
+  /// The resulting Stream can be used to get another stream of status events.
+  pub fn async_sample_stream(self) -> DataReaderStream<D, DA> {
+    DataReaderStream {
+      datareader: Arc::new(Mutex::new(self)),
+    }
   }
-}
 
-// https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
-impl<D, DA> Unpin for BareDataReaderStream<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D>,
-{
-}
+  /// An async stream of both data samples and status events.
+  ///
+  /// Unlike separate sample and event streams, this gives an ordering
+  /// guarantee: samples and status events are delivered in the order in which
+  /// the samples were received and the events occurred. E.g. the
+  /// [`SubscriptionMatched`](DataReaderStatus::SubscriptionMatched) event of
+  /// a Writer always comes before the first sample from it.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use futures::StreamExt;
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::{Sample, SampleOrEvent};
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let mut stream = data_reader.async_sample_and_event_stream();
+  /// # let _ = async move {
+  /// while let Some(item) = stream.next().await {
+  ///   match item {
+  ///     Ok(SampleOrEvent::Event(DataReaderStatus::SubscriptionMatched { writer, .. })) => {
+  ///       println!("Matched {writer:?}");
+  ///     }
+  ///     Ok(SampleOrEvent::Sample(sample)) => {
+  ///       if let Sample::Value(data) = sample.into_value() {
+  ///         println!("Got {}", data.a);
+  ///       }
+  ///     }
+  ///     Ok(SampleOrEvent::Event(_)) => (),
+  ///     Err(e) => println!("Read error {e:?}"),
+  ///   }
+  /// }
+  /// # };
+  /// ```
+  pub fn async_sample_and_event_stream(self) -> DataReaderSampleAndEventStream<D, DA> {
+    DataReaderSampleAndEventStream {
+      datareader: Arc::new(Mutex::new(self)),
+      pending_event: None,
+    }
+  }
 
-impl<D, DA> Stream for BareDataReaderStream<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
-{
-  type Item = ReadResult<Sample<D, D::K>>;
+  // Takes the oldest not read sample, if it was received before `instant`.
+  // On a tie, the status event sent at `instant` goes first.
+  fn take_next_received_before(&mut self, instant: Timestamp) -> ReadResult<Option<DataSample<D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
 
-  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-    debug!("poll_next");
-    let mut datareader = match self.lock_datareader() {
-      Ok(g) => g,
-      Err(e) => return Poll::Ready(Some(Err(e))),
-    }; //TODO: Upgrade to ?-operator: https://github.com/rust-lang/rust/issues/84277
+    match self.datasample_cache.first_not_read_key() {
+      Some(key) if key.0 < instant => Ok(self.take_by_keys(&[key]).pop()),
+      _ => Ok(None),
+    }
+  }
 
-    match datareader.take_bare(1, ReadCondition::not_read()) {
+  // Takes the next not read sample that satisfies `predicate`. Not read samples
+  // before it that do not are taken and dropped, so that they are not
+  // evaluated again.
+  fn take_next_matching_or_drop(
+    &mut self,
+    predicate: &mut impl FnMut(&D, &SampleInfo) -> bool,
+  ) -> ReadResult<Option<DataSample<D>>> {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let (selected, rejected) = self.datasample_cache.select_matching_keys_for_access(
+      ReadCondition::not_read(),
+      1,
+      predicate,
+    );
+    self.take_bare_by_keys(&rejected);
+    Ok(self.take_by_keys(&selected).pop())
+  }
+
+  // Like poll_take_next, but for DataReaderStream::filtered
+  fn poll_take_next_matching(
+    &mut self,
+    cx: &mut Context<'_>,
+    predicate: &mut impl FnMut(&D, &SampleInfo) -> bool,
+  ) -> Poll<Option<ReadResult<DataSample<D>>>> {
+    match self.take_next_matching_or_drop(predicate) {
+      Err(e) => Poll::Ready(Some(Err(e))),
+      Ok(Some(d)) => Poll::Ready(Some(Ok(d))),
+      Ok(None) => {
+        // Store waker, and try again, in case something arrived just now.
+        self.simple_data_reader.set_waker(Some(cx.waker().clone()));
+        match self.take_next_matching_or_drop(predicate) {
+          Err(e) => Poll::Ready(Some(Err(e))),
+          Ok(Some(d)) => Poll::Ready(Some(Ok(d))),
+          Ok(None) => Poll::Pending,
+        }
+      }
+    }
+  }
+
+  // Takes the next not read sample, or stores the waker and returns Pending.
+  fn poll_take_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<ReadResult<DataSample<D>>>>
+  where
+    DA: DefaultDecoder<D>,
+  {
+    match self.take(1, ReadCondition::not_read()) {
       Err(e) =>
       // DDS fails
       {
@@ -933,12 +1713,10 @@ where
             // Did not get any data.
             // --> Store waker.
             // 1. synchronously store waker to background thread (must rendezvous)
-            // 2. try take_bare again, in case something arrived just now
+            // 2. try take again, in case something arrived just now
             // 3. if nothing still, return pending.
-            datareader
-              .simple_data_reader
-              .set_waker(Some(cx.waker().clone()));
-            match datareader.take_bare(1, ReadCondition::not_read()) {
+            self.simple_data_reader.set_waker(Some(cx.waker().clone()));
+            match self.take(1, ReadCondition::not_read()) {
               Err(e) => Poll::Ready(Some(Err(e))),
               Ok(mut v) => match v.pop() {
                 None => Poll::Pending,
@@ -950,59 +1728,176 @@ where
       }
     }
   }
-}
-
-impl<D, DA> FusedStream for BareDataReaderStream<D, DA>
-where
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
-{
-  fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
-  }
-}
-
-// Async interface to the (non-bare) DataReader
+} // impl
 
-pub struct DataReaderStream<
-  D: Keyed + 'static,
-  DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
-> {
-  datareader: Arc<Mutex<DataReader<D, DA>>>,
-}
+// -------------------
 
-impl<D, DA> DataReaderStream<D, DA>
+impl<D, DA> mio_06::Evented for DataReader<D, DA>
 where
-  D: Keyed + 'static,
+  D: Keyed,
   DA: DeserializerAdapter<D>,
 {
-  /// Get a stream of status events
-  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
-    DataReaderEventStream {
-      datareader: Arc::clone(&self.datareader),
-    }
+  // We just delegate all the operations to notification_receiver, since it
+  // already implements mio_06::Evented
+  fn register(
+    &self,
+    poll: &mio_06::Poll,
+    token: mio_06::Token,
+    interest: mio_06::Ready,
+    opts: mio_06::PollOpt,
+  ) -> io::Result<()> {
+    self
+      .simple_data_reader
+      .register(poll, token, interest, opts)
   }
-  fn lock_datareader(&self) -> ReadResult<MutexGuard<'_, DataReader<D, DA>>> {
-    self.datareader.lock().map_err(|e| ReadError::Poisoned {
-      reason: format!("DataReaderStream could not lock datareader: {e:?}"),
-    })
+
+  fn reregister(
+    &self,
+    poll: &mio_06::Poll,
+    token: mio_06::Token,
+    interest: mio_06::Ready,
+    opts: mio_06::PollOpt,
+  ) -> io::Result<()> {
+    self
+      .simple_data_reader
+      .reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &mio_06::Poll) -> io::Result<()> {
+    self.simple_data_reader.deregister(poll)
   }
 }
 
-// https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
-impl<D, DA> Unpin for DataReaderStream<D, DA>
+impl<D, DA> mio_08::event::Source for DataReader<D, DA>
 where
-  D: Keyed + 'static,
+  D: Keyed,
   DA: DeserializerAdapter<D>,
 {
-}
+  fn register(
+    &mut self,
+    registry: &mio_08::Registry,
+    token: mio_08::Token,
+    interests: mio_08::Interest,
+  ) -> io::Result<()> {
+    // SimpleDataReader implements .register() for two traits, so need to
+    // use disambiguation syntax to call .register() here.
+    <SimpleDataReader<D, DA> as mio_08::event::Source>::register(
+      &mut self.simple_data_reader,
+      registry,
+      token,
+      interests,
+    )
+  }
 
-impl<D, DA> Stream for DataReaderStream<D, DA>
+  fn reregister(
+    &mut self,
+    registry: &mio_08::Registry,
+    token: mio_08::Token,
+    interests: mio_08::Interest,
+  ) -> io::Result<()> {
+    <SimpleDataReader<D, DA> as mio_08::event::Source>::reregister(
+      &mut self.simple_data_reader,
+      registry,
+      token,
+      interests,
+    )
+  }
+
+  fn deregister(&mut self, registry: &mio_08::Registry) -> io::Result<()> {
+    <SimpleDataReader<D, DA> as mio_08::event::Source>::deregister(
+      &mut self.simple_data_reader,
+      registry,
+    )
+  }
+}
+
+impl<'a, D, DA> StatusEvented<'a, DataReaderStatus, SimpleDataReaderEventStream<'a, D, DA>>
+  for DataReader<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn as_status_evented(&mut self) -> &dyn mio_06::Evented {
+    self.simple_data_reader.as_status_evented()
+  }
+
+  fn as_status_source(&mut self) -> &mut dyn mio_08::event::Source {
+    self.simple_data_reader.as_status_source()
+  }
+
+  fn as_async_status_stream(&'a self) -> SimpleDataReaderEventStream<'a, D, DA> {
+    self.simple_data_reader.as_async_status_stream()
+  }
+
+  fn try_recv_status(&self) -> Option<DataReaderStatus> {
+    self.simple_data_reader.try_recv_status()
+  }
+}
+
+impl<D, DA> HasQoSPolicy for DataReader<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn qos(&self) -> QosPolicies {
+    self.simple_data_reader.qos().clone()
+  }
+}
+
+impl<D, DA> RTPSEntity for DataReader<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn guid(&self) -> GUID {
+    self.simple_data_reader.guid()
+  }
+}
+
+// ----------------------------------------------
+// ----------------------------------------------
+
+// Async interface to the (bare) DataReader
+
+pub struct BareDataReaderStream<
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
+> {
+  datareader: Arc<Mutex<DataReader<D, DA>>>,
+}
+
+impl<D, DA> BareDataReaderStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// Get a stream of status events
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    DataReaderEventStream {
+      datareader: Arc::clone(&self.datareader),
+    }
+  }
+  fn lock_datareader(&self) -> ReadResult<MutexGuard<'_, DataReader<D, DA>>> {
+    self.datareader.lock().map_err(|e| ReadError::Poisoned {
+      reason: format!("BareDataReaderStream could not lock datareader: {e:?}"),
+    })
+  }
+}
+
+// https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
+impl<D, DA> Unpin for BareDataReaderStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+}
+
+impl<D, DA> Stream for BareDataReaderStream<D, DA>
 where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D> + DefaultDecoder<D>,
 {
-  type Item = ReadResult<DataSample<D>>;
+  type Item = ReadResult<Sample<D, D::K>>;
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     debug!("poll_next");
@@ -1011,7 +1906,7 @@ where
       Err(e) => return Poll::Ready(Some(Err(e))),
     }; //TODO: Upgrade to ?-operator: https://github.com/rust-lang/rust/issues/84277
 
-    match datareader.take(1, ReadCondition::not_read()) {
+    match datareader.take_bare(1, ReadCondition::not_read()) {
       Err(e) =>
       // DDS fails
       {
@@ -1025,12 +1920,12 @@ where
             // Did not get any data.
             // --> Store waker.
             // 1. synchronously store waker to background thread (must rendezvous)
-            // 2. try take again, in case something arrived just now
+            // 2. try take_bare again, in case something arrived just now
             // 3. if nothing still, return pending.
             datareader
               .simple_data_reader
               .set_waker(Some(cx.waker().clone()));
-            match datareader.take(1, ReadCondition::not_read()) {
+            match datareader.take_bare(1, ReadCondition::not_read()) {
               Err(e) => Poll::Ready(Some(Err(e))),
               Ok(mut v) => match v.pop() {
                 None => Poll::Pending,
@@ -1044,7 +1939,7 @@ where
   }
 }
 
-impl<D, DA> FusedStream for DataReaderStream<D, DA>
+impl<D, DA> FusedStream for BareDataReaderStream<D, DA>
 where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D> + DefaultDecoder<D>,
@@ -1054,70 +1949,625 @@ where
   }
 }
 
-// ----------------------------------------------------------------------------------------------------
-// ----------------------------------------------------------------------------------------------------
+// Async interface to the (non-bare) DataReader
 
-pub struct DataReaderEventStream<
+pub struct DataReaderStream<
   D: Keyed + 'static,
   DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
 > {
   datareader: Arc<Mutex<DataReader<D, DA>>>,
 }
 
-impl<D, DA> DataReaderEventStream<D, DA>
+impl<D, DA> DataReaderStream<D, DA>
 where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D>,
 {
+  /// Get a stream of status events
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    DataReaderEventStream {
+      datareader: Arc::clone(&self.datareader),
+    }
+  }
   fn lock_datareader(&self) -> ReadResult<MutexGuard<'_, DataReader<D, DA>>> {
     self.datareader.lock().map_err(|e| ReadError::Poisoned {
-      reason: format!("DataReaderEventStream could not lock datareader: {e:?}"),
+      reason: format!("DataReaderStream could not lock datareader: {e:?}"),
     })
   }
 }
 
-impl<D, DA> Stream for DataReaderEventStream<D, DA>
+impl<D, DA> DataReaderStream<D, DA>
 where
   D: Keyed + 'static,
-  DA: DeserializerAdapter<D>,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
 {
-  type Item = DataReaderStatus;
+  /// Pauses or resumes acknowledging received samples, e.g. when the
+  /// consumer of this stream falls behind.
+  ///
+  /// See [`DataReader::set_flow_control_pause`].
+  pub fn set_flow_control_pause(&self, paused: bool) -> ReadResult<()> {
+    self.lock_datareader()?.set_flow_control_pause(paused)
+  }
 
-  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-    let datareader = match self.lock_datareader() {
-      Ok(g) => g,
-      Err(_e) => return Poll::Ready(None),
-      // If the locking failed, it is due to lock poisoning. This is not recoverable.
-      // We just indicate that the stream of events has ended, because there is
-      // no Result to return here.
-    };
+  /// Forwards samples from this stream until `signal` resolves, after which
+  /// the returned stream terminates. Samples that are already available when
+  /// the signal resolves are not delivered.
+  ///
+  /// This is intended for e.g. shutdown signals, so that the application does
+  /// not need a separate `select!` loop around the sample stream.
+  pub fn take_until_signal<F>(self, signal: F) -> TakeUntil<Self, F>
+  where
+    F: Future<Output = ()>,
+  {
+    self.take_until(signal)
+  }
 
-    Pin::new(&mut datareader.simple_data_reader.as_async_status_stream()).poll_next(cx)
+  /// Delivers only samples whose value satisfies `predicate`.
+  ///
+  /// Unlike [`StreamExt::filter`], this filters in the DataReader: samples
+  /// that do not match are dropped there, without constructing
+  /// [`DataSample`]s out of them. Dispose samples have no value, so they never
+  /// match, and are dropped as well. Use
+  /// [`DataReader::take_matching`] to leave non-matching samples in the
+  /// DataReader instead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let positive_stream = data_reader
+  ///   .async_sample_stream()
+  ///   .filtered(|d, _info| d.a > 0);
+  /// ```
+  pub fn filtered<P>(self, predicate: P) -> Filtered<D, DA, P>
+  where
+    P: FnMut(&D, &SampleInfo) -> bool,
+  {
+    Filtered {
+      stream: self,
+      predicate,
+    }
+  }
+
+  /// Calls `f` with each sample as it passes through the stream, e.g. for
+  /// logging or metrics, without consuming it.
+  ///
+  /// Unlike [`StreamExt::inspect`], which sees each `ReadResult`, `f` only
+  /// sees the successfully read samples. Read errors pass through as is.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize, Debug)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let mut received = 0;
+  /// let logged_stream = data_reader
+  ///   .async_sample_stream()
+  ///   .inspect(move |sample| {
+  ///     received += 1;
+  ///     println!("#{received}: {:?}", sample.value());
+  ///   });
+  /// ```
+  pub fn inspect<F>(self, f: F) -> InspectDataReaderStream<D, DA, F>
+  where
+    F: FnMut(&DataSample<D>),
+  {
+    InspectDataReaderStream { stream: self, f }
+  }
+
+  /// Converts the value of each sample with `f`, e.g. from the wire type to
+  /// an application domain type with the same key.
+  ///
+  /// Dispose samples have no value, so they pass through with their key. If
+  /// `f` fails, the stream produces [`ReadError::Conversion`] carrying the
+  /// error in place of that sample, and continues with the next one, just
+  /// like after a read error.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// #[derive(Serialize, Deserialize)]
+  /// struct Celsius {
+  ///   sensor: u32,
+  ///   value: f64,
+  /// }
+  /// # impl Keyed for Celsius {
+  /// #   type K = u32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.sensor
+  /// #   }
+  /// # }
+  /// struct Kelvin {
+  ///   sensor: u32,
+  ///   value: f64,
+  /// }
+  /// # impl Keyed for Kelvin {
+  /// #   type K = u32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.sensor
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("temperature".to_string(), "Celsius".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_reader = subscriber.create_datareader::<Celsius, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let kelvin_stream = data_reader
+  ///   .async_sample_stream()
+  ///   .try_map_value(|c: Celsius| {
+  ///     if c.value < -273.15 {
+  ///       Err(format!("{} is below absolute zero", c.value))
+  ///     } else {
+  ///       Ok(Kelvin {
+  ///         sensor: c.sensor,
+  ///         value: c.value + 273.15,
+  ///       })
+  ///     }
+  ///   });
+  /// ```
+  pub fn try_map_value<T, E, F>(self, f: F) -> TryMapValue<D, DA, F>
+  where
+    T: Keyed<K = D::K>,
+    F: FnMut(D) -> Result<T, E>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+  {
+    TryMapValue { stream: self, f }
   }
 }
 
-impl<D, DA> FusedStream for DataReaderEventStream<D, DA>
+// https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
+impl<D, DA> Unpin for DataReaderStream<D, DA>
 where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D>,
 {
-  fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
-  }
 }
 
-// ----------------------------------------------------------------------------------------------------
-// ----------------------------------------------------------------------------------------------------
-// ----------------------------------------------------------------------------------------------------
-// ----------------------------------------------------------------------------------------------------
-// ----------------------------------------------------------------------------------------------------
-// ----------------------------------------------------------------------------------------------------
-
-#[cfg(test)]
-mod tests {
-  use std::rc::Rc;
+impl<D, DA> Stream for DataReaderStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  type Item = ReadResult<DataSample<D>>;
 
-  use bytes::Bytes;
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    debug!("poll_next");
+    let mut datareader = match self.lock_datareader() {
+      Ok(g) => g,
+      Err(e) => return Poll::Ready(Some(Err(e))),
+    }; //TODO: Upgrade to ?-operator: https://github.com/rust-lang/rust/issues/84277
+
+    datareader.poll_take_next(cx)
+  }
+}
+
+impl<D, DA> FusedStream for DataReaderStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  fn is_terminated(&self) -> bool {
+    false // Never terminate. This means it is always valid to call poll_next().
+  }
+}
+
+/// Stream returned by [`DataReaderStream::filtered`].
+pub struct Filtered<D: Keyed + 'static, DA: DeserializerAdapter<D> + 'static, P> {
+  stream: DataReaderStream<D, DA>,
+  predicate: P,
+}
+
+impl<D, DA, P> Filtered<D, DA, P>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// Get a stream of status events
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    self.stream.async_event_stream()
+  }
+}
+
+impl<D, DA, P> Stream for Filtered<D, DA, P>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+  P: FnMut(&D, &SampleInfo) -> bool + Unpin,
+{
+  type Item = ReadResult<DataSample<D>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    let mut datareader = match this.stream.lock_datareader() {
+      Ok(g) => g,
+      Err(e) => return Poll::Ready(Some(Err(e))),
+    };
+    datareader.poll_take_next_matching(cx, &mut this.predicate)
+  }
+}
+
+impl<D, DA, P> FusedStream for Filtered<D, DA, P>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+  P: FnMut(&D, &SampleInfo) -> bool + Unpin,
+{
+  fn is_terminated(&self) -> bool {
+    self.stream.is_terminated()
+  }
+}
+
+/// Stream returned by [`DataReaderStream::inspect`].
+pub struct InspectDataReaderStream<D: Keyed + 'static, DA: DeserializerAdapter<D> + 'static, F> {
+  stream: DataReaderStream<D, DA>,
+  f: F,
+}
+
+impl<D, DA, F> InspectDataReaderStream<D, DA, F>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// Get a stream of status events
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    self.stream.async_event_stream()
+  }
+}
+
+impl<D, DA, F> Stream for InspectDataReaderStream<D, DA, F>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+  F: FnMut(&DataSample<D>) + Unpin,
+{
+  type Item = ReadResult<DataSample<D>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    let poll = Pin::new(&mut this.stream).poll_next(cx);
+    if let Poll::Ready(Some(Ok(sample))) = &poll {
+      (this.f)(sample);
+    }
+    poll
+  }
+}
+
+impl<D, DA, F> FusedStream for InspectDataReaderStream<D, DA, F>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+  F: FnMut(&DataSample<D>) + Unpin,
+{
+  fn is_terminated(&self) -> bool {
+    self.stream.is_terminated()
+  }
+}
+
+/// Stream returned by [`DataReaderStream::try_map_value`].
+pub struct TryMapValue<D: Keyed + 'static, DA: DeserializerAdapter<D> + 'static, F> {
+  stream: DataReaderStream<D, DA>,
+  f: F,
+}
+
+impl<D, DA, F> TryMapValue<D, DA, F>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// Get a stream of status events
+  pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
+    self.stream.async_event_stream()
+  }
+}
+
+impl<D, DA, F, T, E> Stream for TryMapValue<D, DA, F>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+  T: Keyed<K = D::K>,
+  F: FnMut(D) -> Result<T, E> + Unpin,
+  E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+  type Item = ReadResult<DataSample<T>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    Pin::new(&mut this.stream).poll_next(cx).map(|item| {
+      item.map(|result| {
+        let DataSample { sample_info, value } = result?;
+        let value = match value {
+          Sample::Value(d) => {
+            Sample::Value((this.f)(d).map_err(|e| ReadError::Conversion { source: e.into() })?)
+          }
+          Sample::Dispose(k) => Sample::Dispose(k),
+        };
+        Ok(DataSample::new(sample_info, value))
+      })
+    })
+  }
+}
+
+impl<D, DA, F, T, E> FusedStream for TryMapValue<D, DA, F>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+  T: Keyed<K = D::K>,
+  F: FnMut(D) -> Result<T, E> + Unpin,
+  E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+  fn is_terminated(&self) -> bool {
+    self.stream.is_terminated()
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+
+/// Item of [`DataReaderSampleAndEventStream`]
+#[derive(Debug, Clone)]
+pub enum SampleOrEvent<S> {
+  Sample(S),
+  Event(DataReaderStatus),
+}
+
+/// Merged stream of samples and status events, see
+/// [`DataReader::async_sample_and_event_stream`].
+pub struct DataReaderSampleAndEventStream<
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
+> {
+  datareader: Arc<Mutex<DataReader<D, DA>>>,
+  // Status event that waits for the samples received before it
+  pending_event: Option<(Timestamp, DataReaderStatus)>,
+}
+
+impl<D, DA> Unpin for DataReaderSampleAndEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+}
+
+impl<D, DA> Stream for DataReaderSampleAndEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  type Item = ReadResult<SampleOrEvent<DataSample<D>>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = Pin::into_inner(self);
+    let mut datareader = match this.datareader.lock() {
+      Ok(g) => g,
+      Err(e) => {
+        return Poll::Ready(Some(Err(ReadError::Poisoned {
+          reason: format!("DataReaderSampleAndEventStream could not lock datareader: {e:?}"),
+        })))
+      }
+    };
+
+    // Status events are stamped with their send time, and samples with their
+    // receive time. Samples received before the next event are delivered
+    // first, so that both come out in the order they happened.
+    if this.pending_event.is_none() {
+      if let Poll::Ready(Some(stamped)) = datareader.simple_data_reader.poll_status_stamped(cx) {
+        this.pending_event = Some(stamped);
+      }
+      // Otherwise the waker for events is now stored, or the status channel
+      // has closed. In either case, continue with samples.
+    }
+
+    match this.pending_event {
+      Some((sent, _)) => match datareader.take_next_received_before(sent) {
+        Err(e) => Poll::Ready(Some(Err(e))),
+        Ok(Some(sample)) => Poll::Ready(Some(Ok(SampleOrEvent::Sample(sample)))),
+        Ok(None) => {
+          let (_, event) = this.pending_event.take().unwrap(); // matched Some above
+          Poll::Ready(Some(Ok(SampleOrEvent::Event(event))))
+        }
+      },
+      None => datareader
+        .poll_take_next(cx)
+        .map(|opt| opt.map(|res| res.map(SampleOrEvent::Sample))),
+    }
+  }
+}
+
+impl<D, DA> FusedStream for DataReaderSampleAndEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  fn is_terminated(&self) -> bool {
+    false // Never terminate. This means it is always valid to call poll_next().
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+pub struct DataReaderEventStream<
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
+> {
+  datareader: Arc<Mutex<DataReader<D, DA>>>,
+}
+
+impl<D, DA> DataReaderEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn lock_datareader(&self) -> ReadResult<MutexGuard<'_, DataReader<D, DA>>> {
+    self.datareader.lock().map_err(|e| ReadError::Poisoned {
+      reason: format!("DataReaderEventStream could not lock datareader: {e:?}"),
+    })
+  }
+}
+
+impl<D, DA> DataReaderEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// Calls `f` for every
+  /// [`SubscriptionMatched`](DataReaderStatus::SubscriptionMatched) event
+  /// with the GUID of the remote Writer and the current count of matched
+  /// Writers. Other events are dropped.
+  ///
+  /// The returned Future drives this event stream, so it must be polled, e.g.
+  /// by spawning it as a task or in a `select!` loop. It never completes,
+  /// because the event stream does not end.
+  pub fn on_subscription_matched<F>(self, mut f: F) -> impl FusedFuture<Output = ()>
+  where
+    F: FnMut(GUID, CountWithChange),
+  {
+    self.for_each(move |event| {
+      if let DataReaderStatus::SubscriptionMatched {
+        writer, current, ..
+      } = event
+      {
+        f(writer, current);
+      }
+      future::ready(())
+    })
+  }
+
+  /// Calls `f` for every
+  /// [`LivelinessChanged`](DataReaderStatus::LivelinessChanged) event with the
+  /// alive and not alive counts. Other events are dropped.
+  ///
+  /// See [`on_subscription_matched`](Self::on_subscription_matched) on how to
+  /// use the returned Future.
+  pub fn on_liveliness_changed<F>(self, mut f: F) -> impl FusedFuture<Output = ()>
+  where
+    F: FnMut(CountWithChange, CountWithChange),
+  {
+    self.for_each(move |event| {
+      if let DataReaderStatus::LivelinessChanged {
+        alive_total,
+        not_alive_total,
+      } = event
+      {
+        f(alive_total, not_alive_total);
+      }
+      future::ready(())
+    })
+  }
+
+  /// Calls `f` for every
+  /// [`RequestedIncompatibleQos`](DataReaderStatus::RequestedIncompatibleQos)
+  /// event with the GUID of the offending remote Writer and the id of the
+  /// (last) incompatible policy. Other events are dropped.
+  ///
+  /// See [`on_subscription_matched`](Self::on_subscription_matched) on how to
+  /// use the returned Future.
+  pub fn on_requested_incompatible_qos<F>(self, mut f: F) -> impl FusedFuture<Output = ()>
+  where
+    F: FnMut(GUID, QosPolicyId),
+  {
+    self.for_each(move |event| {
+      if let DataReaderStatus::RequestedIncompatibleQos {
+        writer,
+        last_policy_id,
+        ..
+      } = event
+      {
+        f(writer, last_policy_id);
+      }
+      future::ready(())
+    })
+  }
+}
+
+impl<D, DA> Stream for DataReaderEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  type Item = DataReaderStatus;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let datareader = match self.lock_datareader() {
+      Ok(g) => g,
+      Err(_e) => return Poll::Ready(None),
+      // If the locking failed, it is due to lock poisoning. This is not recoverable.
+      // We just indicate that the stream of events has ended, because there is
+      // no Result to return here.
+    };
+
+    Pin::new(&mut datareader.simple_data_reader.as_async_status_stream()).poll_next(cx)
+  }
+}
+
+impl<D, DA> FusedStream for DataReaderEventStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn is_terminated(&self) -> bool {
+    false // Never terminate. This means it is always valid to call poll_next().
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use bytes::Bytes;
   use mio_extras::channel as mio_channel;
   use log::info;
   use byteorder::LittleEndian;
@@ -1125,11 +2575,16 @@ mod tests {
   use super::*;
   use crate::{
     dds::{
-      participant::DomainParticipant,
+      participant::{DomainParticipant, DomainParticipantBuilder},
+      sampleinfo::{InstanceState, SampleFlag},
       topic::{TopicDescription, TopicKind},
     },
     messages::submessages::{
-      elements::serialized_payload::SerializedPayload, submessage_flag::*, submessages::Data,
+      elements::{
+        parameter::Parameter, parameter_list::ParameterList, serialized_payload::SerializedPayload,
+      },
+      submessage_flag::*,
+      submessages::Data,
     },
     mio_source,
     network::udp_sender::UDPSender,
@@ -1140,26 +2595,421 @@ mod tests {
     serialization::to_vec,
     structure::{
       guid::{EntityId, EntityKind, GuidPrefix},
+      parameter_id::ParameterId,
       sequence_number::SequenceNumber,
     },
     test::random_data::*,
     RepresentationIdentifier,
   };
 
-  #[test]
-  fn read_and_take() {
-    // Test the read and take methods of the DataReader
+  #[test]
+  fn read_and_take() {
+    // Test the read and take methods of the DataReader
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr read".to_string(),
+        "read fn test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (_notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let data_reader_waker = Arc::new(Mutex::new(None));
+
+    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let (_reader_command_sender, reader_command_receiver) =
+      mio_channel::sync_channel::<ReaderCommand>(10);
+
+    let default_id = EntityId::default();
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
+
+    let reader_ing = ReaderIngredients {
+      guid: reader_guid,
+      notification_sender,
+      status_sender,
+      topic_name: topic.name(),
+      topic_cache_handle: topic_cache,
+      like_stateless: false,
+      qos_policy: QosPolicies::qos_none(),
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_waker,
+      poll_event_sender: notification_event_sender,
+      observed_gaps: Default::default(),
+      subscription_matched: Default::default(),
+      liveliness_changed: Default::default(),
+      additional_front_ends: Default::default(),
+      security_plugins: None,
+      initial_acknack_delay: None,
+    };
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    // Create the corresponding matching DataReader
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.to_vec(),
+      mr_state.multicast_reply_locator_list.to_vec(),
+      &QosPolicies::qos_none(),
+    );
+
+    // Reader and datareader ready, feed reader some data
+    let test_data = RandomData {
+      a: 10,
+      b: ":DDD".to_string(),
+    };
+
+    let test_data2 = RandomData {
+      a: 11,
+      b: ":)))".to_string(),
+    };
+    let data_msg = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(1),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+
+    let data_msg2 = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(2),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data2).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+
+    let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
+
+    reader.handle_data_msg(data_msg, data_flags, &mr_state);
+    reader.handle_data_msg(data_msg2, data_flags, &mr_state);
+
+    // Test that reading does not consume data samples, i.e. they can be read
+    // multiple times
+    {
+      let result_vec = datareader.read(100, ReadCondition::any()).unwrap();
+      assert_eq!(result_vec.len(), 2);
+      let d = result_vec[0].value().clone().unwrap();
+      assert_eq!(&test_data, d);
+    }
+    {
+      let result_vec2 = datareader.read(100, ReadCondition::any()).unwrap();
+      assert_eq!(result_vec2.len(), 2);
+      let d2 = result_vec2[1].value().clone().unwrap();
+      assert_eq!(&test_data2, d2);
+    }
+    {
+      let result_vec3 = datareader.read(100, ReadCondition::any()).unwrap();
+      let d3 = result_vec3[0].value().clone().unwrap();
+      assert_eq!(&test_data, d3);
+    }
+
+    // Test that taking consumes the data samples
+    let mut result_vec = datareader.take(100, ReadCondition::any()).unwrap();
+    let datasample2 = result_vec.pop().unwrap();
+    let datasample1 = result_vec.pop().unwrap();
+    let data2 = datasample2.into_value().unwrap();
+    let data1 = datasample1.into_value().unwrap();
+    assert_eq!(test_data2, data2);
+    assert_eq!(test_data, data1);
+
+    let result_vec2 = datareader.take(100, ReadCondition::any());
+    assert!(result_vec2.is_ok());
+    assert_eq!(result_vec2.unwrap().len(), 0);
+  }
+
+  #[test]
+  fn read_and_take_with_instance() {
+    // Test the methods read_instance and take_instance of the DataReader
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll); // Just for testing
+
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr read".to_string(),
+        "read fn test?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    // Create a Reader
+    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (_notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let data_reader_waker = Arc::new(Mutex::new(None));
+
+    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let (_reader_command_sender, reader_command_receiver) =
+      mio_channel::sync_channel::<ReaderCommand>(10);
+
+    let default_id = EntityId::default();
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
+
+    let reader_ing = ReaderIngredients {
+      guid: reader_guid,
+      notification_sender,
+      status_sender,
+      topic_name: topic.name(),
+      topic_cache_handle: topic_cache,
+      like_stateless: false,
+      qos_policy: QosPolicies::qos_none(),
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_waker,
+      poll_event_sender: notification_event_sender,
+      observed_gaps: Default::default(),
+      subscription_matched: Default::default(),
+      liveliness_changed: Default::default(),
+      additional_front_ends: Default::default(),
+      security_plugins: None,
+      initial_acknack_delay: None,
+    };
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    // Create the corresponding matching DataReader
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.to_vec(),
+      mr_state.multicast_reply_locator_list.to_vec(),
+      &QosPolicies::qos_none(),
+    );
+
+    // Create 4 data items, 3 of which have the same key
+    let data_key1 = RandomData {
+      a: 1,
+      b: ":D".to_string(),
+    };
+    let data_key2_1 = RandomData {
+      a: 2,
+      b: ":(".to_string(),
+    };
+    let data_key2_2 = RandomData {
+      a: 2,
+      b: "??".to_string(),
+    };
+    let data_key2_3 = RandomData {
+      a: 2,
+      b: "xD".to_string(),
+    };
+
+    let key1 = data_key1.key();
+    let key2 = data_key2_1.key();
+
+    assert!(data_key2_1.key() == data_key2_2.key());
+    assert!(data_key2_3.key() == key2);
+
+    // Create data messages from the data items
+    // Note that sequence numbering needs to continue as expected
+    let data_msg = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(1),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key1).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+    let data_msg2 = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(2),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_1).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+    let data_msg3 = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(3),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_2).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+    let data_msg4 = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(4),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_3).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+
+    let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
+
+    // Feed the data messages to the reader
+    reader.handle_data_msg(data_msg, data_flags, &mr_state);
+    reader.handle_data_msg(data_msg2, data_flags, &mr_state);
+    reader.handle_data_msg(data_msg3, data_flags, &mr_state);
+    reader.handle_data_msg(data_msg4, data_flags, &mr_state);
+
+    // Check that calling read_instance with different keys and SelectByKey options
+    // works as expected
+
+    info!("calling read with key 1 and this");
+    let results =
+      datareader.read_instance(100, ReadCondition::any(), Some(key1), SelectByKey::This);
+    assert_eq!(&data_key1, results.unwrap()[0].value().clone().unwrap());
+
+    info!("calling read with None and this");
+    // Takes the smallest key, 1 in this case.
+    let results = datareader.read_instance(100, ReadCondition::any(), None, SelectByKey::This);
+    assert_eq!(&data_key1, results.unwrap()[0].value().clone().unwrap());
+
+    info!("calling read with key 1 and next");
+    let results =
+      datareader.read_instance(100, ReadCondition::any(), Some(key1), SelectByKey::Next);
+    assert_eq!(results.as_ref().unwrap().len(), 3);
+    assert_eq!(&data_key2_1, results.unwrap()[0].value().clone().unwrap());
+
+    // Check that calling take_instance returns all 3 samples with the same key
+    info!("calling take with key 2 and this");
+    let results =
+      datareader.take_instance(100, ReadCondition::any(), Some(key2), SelectByKey::This);
+    assert_eq!(results.as_ref().unwrap().len(), 3);
+    let mut vec = results.unwrap();
+    let d3 = vec.pop().unwrap();
+    let d3 = d3.into_value().unwrap();
+    let d2 = vec.pop().unwrap();
+    let d2 = d2.into_value().unwrap();
+    let d1 = vec.pop().unwrap();
+    let d1 = d1.into_value().unwrap();
+    assert_eq!(data_key2_3, d3);
+    assert_eq!(data_key2_2, d2);
+    assert_eq!(data_key2_1, d1);
+
+    // Check that calling take_instance again returns nothing because all samples
+    // have been consumed
+    info!("calling take with key 2 and this");
+    let results =
+      datareader.take_instance(100, ReadCondition::any(), Some(key2), SelectByKey::This);
+    assert!(results.is_ok());
+    assert!(results.unwrap().is_empty());
+  }
 
+  #[test]
+  fn dispose_by_key_hash_only() {
+    // Writers may dispose with only a KeyHash in inline QoS, and may use the
+    // MD5 form even if the key would fit in the KeyHash verbatim.
     let dp = DomainParticipant::new(0).expect("Participant creation failed!");
 
-    let mut qos = QosPolicies::qos_none();
-    qos.history = Some(policy::History::KeepAll); // Just for testing
-
+    let qos = QosPolicies::qos_none();
     let sub = dp.create_subscriber(&qos).unwrap();
     let topic = dp
       .create_topic(
-        "dr read".to_string(),
-        "read fn test?".to_string(),
+        "dr dispose by key hash".to_string(),
+        "key hash test".to_string(),
         &qos,
         TopicKind::WithKey,
       )
@@ -1171,24 +3021,17 @@ mod tests {
         .unwrap()
         .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
 
-    // Create a Reader
     let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
     let (_notification_event_source, notification_event_sender) =
       mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
     let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
-
     let (_reader_command_sender, reader_command_receiver) =
       mio_channel::sync_channel::<ReaderCommand>(10);
 
-    let default_id = EntityId::default();
-    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
-
     let reader_ing = ReaderIngredients {
-      guid: reader_guid,
+      guid: GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default()),
       notification_sender,
       status_sender,
       topic_name: topic.name(),
@@ -1196,19 +3039,23 @@ mod tests {
       like_stateless: false,
       qos_policy: QosPolicies::qos_none(),
       data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
+      data_reader_waker: Arc::new(Mutex::new(None)),
       poll_event_sender: notification_event_sender,
+      observed_gaps: Default::default(),
+      subscription_matched: Default::default(),
+      liveliness_changed: Default::default(),
+      additional_front_ends: Default::default(),
       security_plugins: None,
+      initial_acknack_delay: None,
     };
 
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
 
-    // Create the corresponding matching DataReader
     let mut datareader = sub
       .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
       .unwrap();
@@ -1232,152 +3079,624 @@ mod tests {
       &QosPolicies::qos_none(),
     );
 
-    // Reader and datareader ready, feed reader some data
+    let reader_id = reader.entity_id();
+    let data_msg = |sn: i64, data: &RandomData| Data {
+      reader_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(sn),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(data).unwrap()),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+    let status_msg = |sn: i64, key_hash: KeyHash, disposed: bool| Data {
+      reader_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(sn),
+      inline_qos: Some(ParameterList {
+        parameters: vec![
+          Parameter::new(ParameterId::PID_KEY_HASH, key_hash.to_vec()),
+          Parameter::create_pid_status_info_parameter(disposed, true, false),
+        ],
+      }),
+      serialized_payload: None,
+    };
+    let dispose_msg = |sn: i64, key_hash: KeyHash| status_msg(sn, key_hash, true);
+    let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
+    let dispose_flags = DATA_Flags::Endianness | DATA_Flags::InlineQos;
+
     let test_data = RandomData {
       a: 10,
-      b: ":DDD".to_string(),
+      b: "key hash".to_string(),
     };
+    let msg = data_msg(1, &test_data);
+    reader.handle_data_msg(msg, data_flags, &mr_state);
+    let result = datareader.take(100, ReadCondition::any()).unwrap();
+    assert_eq!(result.len(), 1);
+    assert!(result[0].sample_info().sample_flags().is_empty());
+
+    // i64 key fits in 16 bytes, so the writer could have sent it verbatim.
+    assert_ne!(10_i64.hash_key(true), 10_i64.hash_key(false));
+    let msg = dispose_msg(2, 10_i64.hash_key(true));
+    reader.handle_data_msg(msg, dispose_flags, &mr_state);
+    let disposed = datareader.take(100, ReadCondition::any()).unwrap();
+    assert_eq!(disposed.len(), 1);
+    assert!(matches!(disposed[0].value(), Sample::Dispose(10)));
+    assert_eq!(
+      disposed[0].sample_info().sample_flags(),
+      SampleFlag::Disposed | SampleFlag::Unregistered
+    );
+    assert_eq!(
+      disposed[0].sample_info().instance_state(),
+      InstanceState::NotAliveDisposed
+    );
 
+    // Dispose of an instance we have never seen is skipped, but does not block
+    // the following samples.
+    let msg = dispose_msg(3, 99_i64.hash_key(false));
+    reader.handle_data_msg(msg, dispose_flags, &mr_state);
     let test_data2 = RandomData {
       a: 11,
-      b: ":)))".to_string(),
+      b: "after unknown".to_string(),
     };
-    let data_msg = Data {
-      reader_id: reader.entity_id(),
+    let msg = data_msg(4, &test_data2);
+    reader.handle_data_msg(msg, data_flags, &mr_state);
+    let result = datareader.take(100, ReadCondition::any()).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].value().clone().unwrap(), test_data2);
+    assert!(datareader
+      .take(100, ReadCondition::any())
+      .unwrap()
+      .is_empty());
+
+    // Unregister without dispose
+    let msg = status_msg(5, 11_i64.hash_key(false), false);
+    reader.handle_data_msg(msg, dispose_flags, &mr_state);
+    let unregistered = datareader.take(100, ReadCondition::any()).unwrap();
+    assert_eq!(unregistered.len(), 1);
+    assert!(matches!(unregistered[0].value(), Sample::Dispose(11)));
+    assert_eq!(
+      unregistered[0].sample_info().sample_flags(),
+      SampleFlag::Unregistered
+    );
+    assert_eq!(
+      unregistered[0].sample_info().instance_state(),
+      InstanceState::NotAliveNoWriters
+    );
+  }
+
+  #[test]
+  fn no_key_reader_skips_disposes() {
+    // A Writer that thinks the topic is WITH_KEY may send disposes. NO_KEY
+    // Readers skip them.
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr no_key dispose".to_string(),
+        "no_key dispose test".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
+    let (_notification_event_source, notification_event_sender) =
+      mio_source::make_poll_channel().unwrap();
+    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let (_reader_command_sender, reader_command_receiver) =
+      mio_channel::sync_channel::<ReaderCommand>(10);
+
+    let reader_ing = ReaderIngredients {
+      guid: GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default()),
+      notification_sender,
+      status_sender,
+      topic_name: topic.name(),
+      topic_cache_handle: topic_cache,
+      like_stateless: false,
+      qos_policy: QosPolicies::qos_none(),
+      data_reader_command_receiver: reader_command_receiver,
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender: notification_event_sender,
+      observed_gaps: Default::default(),
+      subscription_matched: Default::default(),
+      liveliness_changed: Default::default(),
+      additional_front_ends: Default::default(),
+      security_plugins: None,
+      initial_acknack_delay: None,
+    };
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let mut datareader = sub
+      .create_datareader_no_key::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.to_vec(),
+      mr_state.multicast_reply_locator_list.to_vec(),
+      &QosPolicies::qos_none(),
+    );
+
+    let reader_id = reader.entity_id();
+    let data_msg = |sn: i64, data: &RandomData| Data {
+      reader_id,
       writer_id: writer_guid.entity_id,
-      writer_sn: SequenceNumber::from(1),
+      writer_sn: SequenceNumber::from(sn),
       serialized_payload: Some(
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data).unwrap()),
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(data).unwrap()),
         }
         .into(),
       ),
       ..Data::default()
     };
+    let dispose_msg = |sn: i64, key_hash: KeyHash| Data {
+      reader_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(sn),
+      inline_qos: Some(ParameterList {
+        parameters: vec![
+          Parameter::new(ParameterId::PID_KEY_HASH, key_hash.to_vec()),
+          Parameter::create_pid_status_info_parameter(true, true, false),
+        ],
+      }),
+      serialized_payload: None,
+    };
+    let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
+    let dispose_flags = DATA_Flags::Endianness | DATA_Flags::InlineQos;
 
-    let data_msg2 = Data {
+    let test_data = RandomData {
+      a: 10,
+      b: "before".to_string(),
+    };
+    reader.handle_data_msg(data_msg(1, &test_data), data_flags, &mr_state);
+    let mut values: Vec<RandomData> = datareader
+      .take(100, ReadCondition::any())
+      .unwrap()
+      .into_iter()
+      .map(|s| s.into_value())
+      .collect();
+    // Dispose of the single instance, and of an unknown instance
+    reader.handle_data_msg(dispose_msg(2, KeyHash::zero()), dispose_flags, &mr_state);
+    reader.handle_data_msg(
+      dispose_msg(3, 99_i64.hash_key(false)),
+      dispose_flags,
+      &mr_state,
+    );
+    let test_data2 = RandomData {
+      a: 11,
+      b: "after".to_string(),
+    };
+    reader.handle_data_msg(data_msg(4, &test_data2), data_flags, &mr_state);
+
+    values.extend(
+      datareader
+        .take(100, ReadCondition::any())
+        .unwrap()
+        .into_iter()
+        .map(|s| s.into_value()),
+    );
+    assert_eq!(values, vec![test_data, test_data2]);
+  }
+
+  #[test]
+  fn compression_flag_needs_advertised_compression() {
+    // Other implementations may set the bit that RustDDS uses to mark
+    // compressed payloads. Only Writers that advertised compression in
+    // Discovery send compressed payloads.
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr compression flag".to_string(),
+        "compression flag test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let topic_cache =
+      dp.dds_cache()
+        .write()
+        .unwrap()
+        .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let (reader_ing, _ends) = ReaderIngredients::for_test(
+      GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default()),
+      &topic.name(),
+      topic_cache,
+      QosPolicies::qos_none(),
+    );
+
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let writer_guid = GUID {
+      prefix: GuidPrefix::new(&[1; 12]),
+      entity_id: EntityId::create_custom_entity_id(
+        [1; 3],
+        EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      ),
+    };
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.to_vec(),
+      mr_state.multicast_reply_locator_list.to_vec(),
+      &QosPolicies::qos_none(),
+    );
+
+    let test_data = RandomData {
+      a: 10,
+      b: "not compressed".to_string(),
+    };
+    let msg = Data {
       reader_id: reader.entity_id(),
       writer_id: writer_guid.entity_id,
-      writer_sn: SequenceNumber::from(2),
+      writer_sn: SequenceNumber::from(1),
       serialized_payload: Some(
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
-          representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data2).unwrap()),
+          representation_options: [0x80, 0],
+          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data).unwrap()),
         }
         .into(),
       ),
       ..Data::default()
     };
+    reader.handle_data_msg(msg, DATA_Flags::Endianness | DATA_Flags::Data, &mr_state);
+
+    let result = datareader.take(100, ReadCondition::any()).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].value().clone().unwrap(), test_data);
+  }
+
+  #[test]
+  fn wait_for_data_times_out() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr wait_for_data".to_string(),
+        "wait_for_data test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let start = std::time::Instant::now();
+    let got_data = datareader.wait_for_data(Duration::from_millis(50)).unwrap();
+    assert!(!got_data);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+  }
+
+  #[test]
+  fn wait_for_data_wakes_pending_stream() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct FlagWaker(AtomicBool);
+    impl futures::task::ArcWake for FlagWaker {
+      fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::SeqCst);
+      }
+    }
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr wait_for_data stream".to_string(),
+        "wait_for_data test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let mut datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    // As if an async stream of this reader were waiting for data
+    let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+    datareader
+      .simple_data_reader
+      .set_waker(Some(futures::task::waker(flag.clone())));
+
+    assert!(!datareader.wait_for_data(Duration::from_millis(10)).unwrap());
+    assert!(flag.0.load(Ordering::SeqCst));
+  }
+
+  #[test]
+  fn flow_control_pause_reports_full_command_queue() {
+    // Nothing processes Reader commands, because the event loop is not polled.
+    let dp = DomainParticipantBuilder::new(0)
+      .manual_event_loop(true)
+      .build()
+      .expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr flow_control_pause".to_string(),
+        "flow_control_pause test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    let results: Vec<_> = (0..10)
+      .map(|i| datareader.set_flow_control_pause(i % 2 == 0))
+      .collect();
+    assert!(results[0].is_ok(), "{results:?}");
+    assert!(
+      matches!(results.last(), Some(Err(ReadError::WouldBlock { .. }))),
+      "{results:?}"
+    );
+  }
+
+  #[test]
+  fn take_until_signal_terminates_stream() {
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr take_until".to_string(),
+        "take_until test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let datareader = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
 
-    let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
+    // Signal is already resolved, so the stream must end without waiting for
+    // any samples.
+    let mut stream = datareader
+      .async_sample_stream()
+      .take_until_signal(futures::future::ready(()));
+    let next = futures::executor::block_on(stream.next());
+    assert!(next.is_none());
+    assert!(stream.is_terminated());
+  }
 
-    reader.handle_data_msg(data_msg, data_flags, &mr_state);
-    reader.handle_data_msg(data_msg2, data_flags, &mr_state);
+  #[test]
+  fn try_map_value_converts_values_and_passes_disposes() {
+    use futures::FutureExt;
 
-    // Test that reading does not consume data samples, i.e. they can be read
-    // multiple times
-    {
-      let result_vec = datareader.read(100, ReadCondition::any()).unwrap();
-      assert_eq!(result_vec.len(), 2);
-      let d = result_vec[0].value().clone().unwrap();
-      assert_eq!(&test_data, d);
-    }
-    {
-      let result_vec2 = datareader.read(100, ReadCondition::any()).unwrap();
-      assert_eq!(result_vec2.len(), 2);
-      let d2 = result_vec2[1].value().clone().unwrap();
-      assert_eq!(&test_data2, d2);
-    }
-    {
-      let result_vec3 = datareader.read(100, ReadCondition::any()).unwrap();
-      let d3 = result_vec3[0].value().clone().unwrap();
-      assert_eq!(&test_data, d3);
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    // Samples written before matching are delivered once the reader matches.
+    let qos = QosPolicies::builder()
+      .durability(policy::Durability::TransientLocal)
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let topic = dp
+      .create_topic(
+        "dr try_map_value".to_string(),
+        "try_map_value test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap()
+      .async_sample_stream()
+      .try_map_value(|d: RandomData| {
+        d.b.parse::<u8>().map(|n| RandomData {
+          a: d.a,
+          b: (n * 2).to_string(),
+        })
+      });
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, crate::CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+
+    for (a, b) in [(1, "1"), (2, "x"), (3, "3")] {
+      writer
+        .write(
+          RandomData {
+            a,
+            b: b.to_string(),
+          },
+          None,
+        )
+        .unwrap();
     }
+    writer.dispose(&1, None).unwrap();
 
-    // Test that taking consumes the data samples
-    let mut result_vec = datareader.take(100, ReadCondition::any()).unwrap();
-    let datasample2 = result_vec.pop().unwrap();
-    let datasample1 = result_vec.pop().unwrap();
-    let data2 = datasample2.into_value().unwrap();
-    let data1 = datasample1.into_value().unwrap();
-    assert_eq!(test_data2, data2);
-    assert_eq!(test_data, data1);
+    let mut items = Vec::new();
+    let start = std::time::Instant::now();
+    while items.len() < 4 && start.elapsed() < std::time::Duration::from_secs(10) {
+      while let Some(Some(item)) = stream.next().now_or_never() {
+        items.push(item);
+      }
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
 
-    let result_vec2 = datareader.take(100, ReadCondition::any());
-    assert!(result_vec2.is_ok());
-    assert_eq!(result_vec2.unwrap().len(), 0);
+    assert_eq!(items.len(), 4, "{items:?}");
+    assert_eq!(items[0].as_ref().unwrap().value().as_ref().unwrap().b, "2");
+    match &items[1] {
+      Err(ReadError::Conversion { source }) => {
+        assert!(source.is::<std::num::ParseIntError>(), "{source:?}");
+      }
+      other => panic!("Expected a conversion error, got {other:?}"),
+    }
+    assert_eq!(items[2].as_ref().unwrap().value().as_ref().unwrap().b, "6");
+    assert_eq!(
+      *items[3].as_ref().unwrap().value(),
+      Sample::Dispose(1),
+      "{items:?}"
+    );
   }
 
   #[test]
-  fn read_and_take_with_instance() {
-    // Test the methods read_instance and take_instance of the DataReader
+  fn sample_and_event_stream_orders_match_before_sample() {
+    use futures::FutureExt;
 
     let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "dr sample_and_event_stream".to_string(),
+        "sample_and_event_stream test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap()
+      .async_sample_and_event_stream();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, crate::CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
 
-    let mut qos = QosPolicies::qos_none();
-    qos.history = Some(policy::History::KeepAll); // Just for testing
+    // Write right away, so that the sample races with the match.
+    let mut items = Vec::new();
+    let start = std::time::Instant::now();
+    while !items.iter().any(|i| matches!(i, SampleOrEvent::Sample(_)))
+      && start.elapsed() < std::time::Duration::from_secs(10)
+    {
+      writer
+        .write(
+          RandomData {
+            a: 1,
+            b: "first".to_string(),
+          },
+          None,
+        )
+        .unwrap();
+      while let Some(Some(item)) = stream.next().now_or_never() {
+        items.push(item.unwrap());
+      }
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
 
-    let sub = dp.create_subscriber(&qos).unwrap();
+    let matched = items.iter().position(|i| {
+      matches!(
+        i,
+        SampleOrEvent::Event(DataReaderStatus::SubscriptionMatched { writer: w, .. })
+          if *w == writer.guid()
+      )
+    });
+    let first_sample = items
+      .iter()
+      .position(|i| matches!(i, SampleOrEvent::Sample(_)));
+    assert!(first_sample.is_some());
+    assert!(matched.is_some() && matched < first_sample, "{items:?}");
+  }
+
+  #[test]
+  fn sample_and_event_stream_keeps_order_of_samples_and_events() {
+    use futures::FutureExt;
+
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
     let topic = dp
       .create_topic(
-        "dr read".to_string(),
-        "read fn test?".to_string(),
+        "dr sample_and_event_order".to_string(),
+        "sample_and_event_order test".to_string(),
         &qos,
         TopicKind::WithKey,
       )
       .unwrap();
+    let mut stream = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap()
+      .async_sample_and_event_stream();
 
+    // A Reader that feeds the same topic cache
     let topic_cache =
       dp.dds_cache()
         .write()
         .unwrap()
         .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
-
-    // Create a Reader
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    let default_id = EntityId::default();
-    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), default_id);
-
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic.name(),
-      topic_cache_handle: topic_cache,
-      like_stateless: false,
-      qos_policy: QosPolicies::qos_none(),
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
-
+    let reader_guid = GUID::new_with_prefix_and_id(dp.guid_prefix(), EntityId::default());
+    let (reader_ing, _ends) =
+      ReaderIngredients::for_test(reader_guid, &topic.name(), topic_cache, qos.clone());
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
-
-    // Create the corresponding matching DataReader
-    let mut datareader = sub
-      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
-      .unwrap();
-
     let writer_guid = GUID {
       prefix: GuidPrefix::new(&[1; 12]),
       entity_id: EntityId::create_custom_entity_id(
@@ -1389,146 +3708,105 @@ mod tests {
       source_guid_prefix: writer_guid.prefix,
       ..Default::default()
     };
-    reader.matched_writer_add(
-      writer_guid,
-      EntityId::UNKNOWN,
-      mr_state.unicast_reply_locator_list.to_vec(),
-      mr_state.multicast_reply_locator_list.to_vec(),
-      &QosPolicies::qos_none(),
-    );
-
-    // Create 4 data items, 3 of which have the same key
-    let data_key1 = RandomData {
-      a: 1,
-      b: ":D".to_string(),
-    };
-    let data_key2_1 = RandomData {
-      a: 2,
-      b: ":(".to_string(),
-    };
-    let data_key2_2 = RandomData {
-      a: 2,
-      b: "??".to_string(),
-    };
-    let data_key2_3 = RandomData {
-      a: 2,
-      b: "xD".to_string(),
-    };
-
-    let key1 = data_key1.key();
-    let key2 = data_key2_1.key();
-
-    assert!(data_key2_1.key() == data_key2_2.key());
-    assert!(data_key2_3.key() == key2);
-
-    // Create data messages from the data items
-    // Note that sequence numbering needs to continue as expected
-    let data_msg = Data {
-      reader_id: reader.entity_id(),
-      writer_id: writer_guid.entity_id,
-      writer_sn: SequenceNumber::from(1),
-      serialized_payload: Some(
-        SerializedPayload {
-          representation_identifier: RepresentationIdentifier::CDR_LE,
-          representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key1).unwrap()),
-        }
-        .into(),
-      ),
-      ..Data::default()
-    };
-    let data_msg2 = Data {
-      reader_id: reader.entity_id(),
-      writer_id: writer_guid.entity_id,
-      writer_sn: SequenceNumber::from(2),
-      serialized_payload: Some(
-        SerializedPayload {
-          representation_identifier: RepresentationIdentifier::CDR_LE,
-          representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_1).unwrap()),
-        }
-        .into(),
-      ),
-      ..Data::default()
-    };
-    let data_msg3 = Data {
-      reader_id: reader.entity_id(),
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &qos);
+    let data_msg = |sn: i64| Data {
+      reader_id: reader_guid.entity_id,
       writer_id: writer_guid.entity_id,
-      writer_sn: SequenceNumber::from(3),
-      serialized_payload: Some(
-        SerializedPayload {
-          representation_identifier: RepresentationIdentifier::CDR_LE,
-          representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_2).unwrap()),
-        }
-        .into(),
-      ),
-      ..Data::default()
-    };
-    let data_msg4 = Data {
-      reader_id: reader.entity_id(),
-      writer_id: writer_guid.entity_id,
-      writer_sn: SequenceNumber::from(4),
+      writer_sn: SequenceNumber::from(sn),
       serialized_payload: Some(
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_3).unwrap()),
+          value: Bytes::from(
+            to_vec::<RandomData, LittleEndian>(&RandomData {
+              a: sn,
+              b: String::new(),
+            })
+            .unwrap(),
+          ),
         }
         .into(),
       ),
       ..Data::default()
     };
-
     let data_flags = DATA_Flags::Endianness | DATA_Flags::Data;
 
-    // Feed the data messages to the reader
-    reader.handle_data_msg(data_msg, data_flags, &mr_state);
-    reader.handle_data_msg(data_msg2, data_flags, &mr_state);
-    reader.handle_data_msg(data_msg3, data_flags, &mr_state);
-    reader.handle_data_msg(data_msg4, data_flags, &mr_state);
-
-    // Check that calling read_instance with different keys and SelectByKey options
-    // works as expected
-
-    info!("calling read with key 1 and this");
-    let results =
-      datareader.read_instance(100, ReadCondition::any(), Some(key1), SelectByKey::This);
-    assert_eq!(&data_key1, results.unwrap()[0].value().clone().unwrap());
+    // sample, event, sample
+    reader.handle_data_msg(data_msg(1), data_flags, &mr_state);
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    stream
+      .datareader
+      .lock()
+      .unwrap()
+      .simple_data_reader
+      .send_status(DataReaderStatus::SampleLost {
+        count: CountWithChange::new(1, 1),
+      });
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    reader.handle_data_msg(data_msg(2), data_flags, &mr_state);
+
+    let mut items = Vec::new();
+    while let Some(Some(item)) = stream.next().now_or_never() {
+      match item.unwrap() {
+        SampleOrEvent::Sample(sample) => {
+          items.push(format!("sample {}", sample.value().clone().unwrap().a));
+        }
+        SampleOrEvent::Event(DataReaderStatus::SampleLost { .. }) => items.push("lost".to_string()),
+        SampleOrEvent::Event(_) => (),
+      }
+    }
+    assert_eq!(items, vec!["sample 1", "lost", "sample 2"]);
+  }
 
-    info!("calling read with None and this");
-    // Takes the smallest key, 1 in this case.
-    let results = datareader.read_instance(100, ReadCondition::any(), None, SelectByKey::This);
-    assert_eq!(&data_key1, results.unwrap()[0].value().clone().unwrap());
+  #[test]
+  fn event_stream_on_subscription_matched() {
+    use futures::FutureExt;
 
-    info!("calling read with key 1 and next");
-    let results =
-      datareader.read_instance(100, ReadCondition::any(), Some(key1), SelectByKey::Next);
-    assert_eq!(results.as_ref().unwrap().len(), 3);
-    assert_eq!(&data_key2_1, results.unwrap()[0].value().clone().unwrap());
+    let dp = DomainParticipant::new(0).expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let sub = dp.create_subscriber(&qos).unwrap();
+    let topic = dp
+      .create_topic(
+        "dr on_subscription_matched".to_string(),
+        "on_subscription_matched test".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let sample_stream = sub
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap()
+      .async_sample_stream();
 
-    // Check that calling take_instance returns all 3 samples with the same key
-    info!("calling take with key 2 and this");
-    let results =
-      datareader.take_instance(100, ReadCondition::any(), Some(key2), SelectByKey::This);
-    assert_eq!(results.as_ref().unwrap().len(), 3);
-    let mut vec = results.unwrap();
-    let d3 = vec.pop().unwrap();
-    let d3 = d3.into_value().unwrap();
-    let d2 = vec.pop().unwrap();
-    let d2 = d2.into_value().unwrap();
-    let d1 = vec.pop().unwrap();
-    let d1 = d1.into_value().unwrap();
-    assert_eq!(data_key2_3, d3);
-    assert_eq!(data_key2_2, d2);
-    assert_eq!(data_key2_1, d1);
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    {
+      let datareader = sample_stream.datareader.lock().unwrap();
+      datareader
+        .simple_data_reader
+        .send_status(DataReaderStatus::RequestedDeadlineMissed {
+          count: CountWithChange::new(1, 1),
+          last_instance: None,
+        });
+      datareader
+        .simple_data_reader
+        .send_status(DataReaderStatus::SubscriptionMatched {
+          total: CountWithChange::new(1, 1),
+          current: CountWithChange::new(1, 1),
+          writer,
+          writer_description: None,
+        });
+    }
 
-    // Check that calling take_instance again returns nothing because all samples
-    // have been consumed
-    info!("calling take with key 2 and this");
-    let results =
-      datareader.take_instance(100, ReadCondition::any(), Some(key2), SelectByKey::This);
-    assert!(results.is_ok());
-    assert!(results.unwrap().is_empty());
+    let mut matched = Vec::new();
+    {
+      let mut handler = Box::pin(
+        sample_stream
+          .async_event_stream()
+          .on_subscription_matched(|w, current| matched.push((w, current.count()))),
+      );
+      // Handles the queued events and then waits for more.
+      assert!(handler.as_mut().now_or_never().is_none());
+    }
+    assert_eq!(matched, vec![(writer, 1)]);
   }
 }
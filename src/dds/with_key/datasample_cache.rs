@@ -1,6 +1,8 @@
 use std::{
+  cmp::max,
   collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
   ops::Bound,
+  sync::Arc,
 };
 
 #[allow(unused_imports)]
@@ -8,13 +10,16 @@ use log::{debug, error, info, warn};
 
 use crate::{
   dds::{
-    key::Keyed,
+    key::{Key, KeyHash, Keyed},
     qos::{policy, QosPolicies},
     readcondition::ReadCondition,
     sampleinfo::*,
-    with_key::datasample::{DataSample, DeserializedCacheChange, Sample},
+    with_key::{
+      datareader::InstanceEviction,
+      datasample::{DataSample, DeserializedCacheChange, Sample},
+    },
   },
-  structure::{guid::GUID, sequence_number::SequenceNumber, time::Timestamp},
+  structure::{duration::Duration, guid::GUID, sequence_number::SequenceNumber, time::Timestamp},
   with_key::WriteOptions,
 };
 
@@ -32,6 +37,13 @@ pub struct DataSampleCache<D: Keyed> {
   datasamples: BTreeMap<Timestamp, SampleWithMetaData<D>>, /* ordered storage for deserialized
                                                             * samples */
   pub(crate) instance_map: BTreeMap<D::K, InstanceMetaData>, // ordered storage for instances
+  instance_eviction: InstanceEviction, // what to do when max_instances is reached
+  not_read: BTreeSet<Timestamp>,       // index of samples in NotRead state
+  // Earliest time when a not alive instance may be purged by ReaderDataLifecycle
+  next_autopurge: Option<Timestamp>,
+  // NO_KEY topic: every sample belongs to the single instance, so samples are
+  // not indexed per instance and instance limits do not apply.
+  keyless: bool,
 }
 
 pub(crate) struct InstanceMetaData {
@@ -39,6 +51,32 @@ pub(crate) struct InstanceMetaData {
   instance_state: InstanceState,         // latest known alive/not_alive state for this instance
   latest_generation_available: NotAliveGenerationCounts, // in this instance
   last_generation_accessed: NotAliveGenerationCounts, // in this instance
+  last_update: Timestamp,                // receive time of the latest sample, for LRU eviction
+  not_alive_since: Option<Timestamp>,    // receive time of the change to not alive state
+  live_writers: BTreeSet<GUID>,          // Writers that have written and not unregistered
+  // Metadata of the latest sample, kept even after the sample has been taken
+  last_change: ChangeMetaData,
+}
+
+#[derive(Clone)]
+struct ChangeMetaData {
+  receive_timestamp: Timestamp,
+  generation_counts: NotAliveGenerationCounts,
+  writer_guid: GUID,
+  sequence_number: SequenceNumber,
+  write_options: WriteOptions,
+  writer_qos: Option<Arc<QosPolicies>>,
+}
+
+// Result of adding a sample to the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AddSampleOutcome {
+  Added,
+  // Sample was for a new instance, but max_instances was already reached.
+  RejectedByInstancesLimit,
+  // Sample was added, but the least recently updated instance was evicted
+  // to make room for it.
+  AddedWithEviction,
 }
 
 struct SampleWithMetaData<D: Keyed> {
@@ -49,6 +87,8 @@ struct SampleWithMetaData<D: Keyed> {
   sequence_number: SequenceNumber, // as sent by the Writer
   write_options: WriteOptions,     // as stamped by Writer
   sample_has_been_read: bool,      // sample_state
+  // Writer QoS, as known by Discovery when this sample was received
+  writer_qos: Option<Arc<QosPolicies>>,
 
   // the data sample (or key) itself is stored here
   sample: Sample<D, D::K>, // TODO: maybe this should be boxed for moving performance.
@@ -75,13 +115,30 @@ where
       qos,
       datasamples: BTreeMap::new(),
       instance_map: BTreeMap::new(),
+      instance_eviction: InstanceEviction::Reject,
+      not_read: BTreeSet::new(),
+      next_autopurge: None,
+      keyless: false,
     }
   }
 
+  pub(crate) fn set_keyless(&mut self, keyless: bool) {
+    self.keyless = keyless;
+  }
+
+  pub(crate) fn set_instance_eviction(&mut self, instance_eviction: InstanceEviction) {
+    self.instance_eviction = instance_eviction;
+  }
+
+  pub(crate) fn instance_eviction(&self) -> InstanceEviction {
+    self.instance_eviction
+  }
+
   pub(crate) fn fill_from_deserialized_cache_change(
     &mut self,
     deserialized_cc: DeserializedCacheChange<D>,
-  ) {
+    writer_qos: Option<Arc<QosPolicies>>,
+  ) -> AddSampleOutcome {
     // TODO list.
 
     self.add_sample(
@@ -90,7 +147,8 @@ where
       deserialized_cc.sequence_number,
       deserialized_cc.receive_instant,
       deserialized_cc.write_options,
-    );
+      writer_qos,
+    )
   }
 
   fn add_sample(
@@ -100,16 +158,69 @@ where
     sequence_number: SequenceNumber,
     receive_timestamp: Timestamp,
     write_options: WriteOptions,
-  ) {
+    writer_qos: Option<Arc<QosPolicies>>,
+  ) -> AddSampleOutcome {
     let instance_key = match &new_sample {
       Sample::Value(d) => d.key(),
       Sample::Dispose(k) => k.clone(),
     };
+    let sample_flags = write_options.sample_flags();
+    // Unregister without dispose: the Writer will not update the instance
+    // anymore, but it still exists.
+    let unregister_only = matches!(new_sample, Sample::Dispose(_))
+      && sample_flags.contains(SampleFlag::Unregistered)
+      && !sample_flags.contains(SampleFlag::Disposed);
+
+    // The instance has no writers only after the last live Writer has
+    // unregistered. Until then, an unregistration does not change the
+    // instance, so there is no sample to deliver.
+    if unregister_only {
+      if let Some(imd) = self.instance_map.get_mut(&instance_key) {
+        imd.live_writers.remove(&writer_guid);
+        if !imd.live_writers.is_empty() {
+          return AddSampleOutcome::Added;
+        }
+      }
+    }
+
+    // Check ResourceLimits max_instances before creating a new instance.
+    // Non-positive values mean unlimited (LENGTH_UNLIMITED = -1).
+    let mut outcome = AddSampleOutcome::Added;
+    if let Some(policy::ResourceLimits { max_instances, .. }) = self.qos.resource_limits {
+      if max_instances > 0
+        && !self.keyless
+        && self.instance_map.len() >= max_instances as usize
+        && !self.instance_map.contains_key(&instance_key)
+      {
+        match self.instance_eviction {
+          InstanceEviction::Reject => {
+            debug!("Rejected sample for new instance: max_instances={max_instances} reached.");
+            return AddSampleOutcome::RejectedByInstancesLimit;
+          }
+          InstanceEviction::LruEvict => {
+            if let Some(lru_key) = self.least_recently_updated_instance() {
+              self.remove_instance(&lru_key);
+              outcome = AddSampleOutcome::AddedWithEviction;
+            }
+          }
+        }
+      }
+    }
 
     let new_instance_state = match new_sample {
       Sample::Value(_) => InstanceState::Alive,
+      Sample::Dispose(_) if unregister_only => InstanceState::NotAliveNoWriters,
       Sample::Dispose(_) => InstanceState::NotAliveDisposed,
     };
+    let autopurge_delay = Self::autopurge_delay(&self.qos, new_instance_state);
+    let change = ChangeMetaData {
+      receive_timestamp,
+      generation_counts: NotAliveGenerationCounts::zero(), // updated below
+      writer_guid,
+      sequence_number,
+      write_options: write_options.clone(),
+      writer_qos: writer_qos.clone(),
+    };
 
     // find or create metadata record
     let instance_metadata = if let Some(imd) = self.instance_map.get_mut(&instance_key) {
@@ -122,6 +233,10 @@ where
         latest_generation_available: NotAliveGenerationCounts::zero(), /* this is new instance,
                                                                         * so start from zero */
         last_generation_accessed: NotAliveGenerationCounts::sub_zero(), // never accessed
+        last_update: receive_timestamp,
+        not_alive_since: None, // updated below
+        live_writers: BTreeSet::new(),
+        last_change: change.clone(),
       };
       self.instance_map.insert(instance_key.clone(), imd);
       self
@@ -132,7 +247,15 @@ where
     };
 
     // update instance metadata
-    instance_metadata.instance_samples.insert(receive_timestamp);
+    if !self.keyless {
+      instance_metadata.instance_samples.insert(receive_timestamp);
+    }
+    instance_metadata.last_update = max(instance_metadata.last_update, receive_timestamp);
+    if new_instance_state == InstanceState::Alive {
+      instance_metadata.live_writers.insert(writer_guid);
+    } else if sample_flags.contains(SampleFlag::Unregistered) {
+      instance_metadata.live_writers.remove(&writer_guid);
+    }
 
     match (instance_metadata.instance_state, new_instance_state) {
       (InstanceState::Alive, _) => (), // was Alive, does not change counts
@@ -157,7 +280,27 @@ where
 
       (InstanceState::NotAliveNoWriters, _) => (), // you can only die once
     }
-    instance_metadata.instance_state = new_instance_state;
+    // A disposed instance stays disposed, even if it is also unregistered.
+    if !(instance_metadata.instance_state == InstanceState::NotAliveDisposed
+      && new_instance_state == InstanceState::NotAliveNoWriters)
+    {
+      instance_metadata.instance_state = new_instance_state;
+    }
+    if new_instance_state == InstanceState::Alive {
+      instance_metadata.not_alive_since = None;
+    } else if instance_metadata.not_alive_since.is_none() {
+      instance_metadata.not_alive_since = Some(receive_timestamp);
+      if let Some(delay) = autopurge_delay {
+        let purge_at = receive_timestamp + delay;
+        self.next_autopurge = Some(self.next_autopurge.map_or(purge_at, |t| t.min(purge_at)));
+      }
+    }
+    if receive_timestamp >= instance_metadata.last_change.receive_timestamp {
+      instance_metadata.last_change = ChangeMetaData {
+        generation_counts: instance_metadata.latest_generation_available,
+        ..change
+      };
+    }
 
     // insert new_sample to main table
     self
@@ -170,6 +313,7 @@ where
           sequence_number,
           write_options,
           sample_has_been_read: false,
+          writer_qos,
           sample: new_sample,
         },
       )
@@ -184,6 +328,7 @@ where
           panic!("Tried to add duplicate datasample with the same key {receive_timestamp:?}");
         },
       );
+    self.not_read.insert(receive_timestamp);
 
     // garbage collect
     let sample_keep_history_limit: Option<i32> = match self.qos.history() {
@@ -197,12 +342,22 @@ where
       max_samples_per_instance,
     }) = self.qos.resource_limits
     {
-      Some(max_samples_per_instance)
+      // Non-positive value means LENGTH_UNLIMITED
+      Some(max_samples_per_instance).filter(|m| *m > 0)
     } else {
       None
     };
 
     if let Some(instance_keep_count) = sample_keep_history_limit.or(sample_keep_resource_limit) {
+      if self.keyless {
+        // All samples belong to the single instance, so the oldest ones go.
+        while self.datasamples.len() > max(instance_keep_count, 0) as usize {
+          if let Some((k, _)) = self.datasamples.pop_first() {
+            self.not_read.remove(&k);
+          }
+        }
+        return outcome;
+      }
       let remove_count = instance_metadata.instance_samples.len() as i32 - instance_keep_count;
       if remove_count > 0 {
         let keys_to_remove: Vec<_> = instance_metadata
@@ -214,12 +369,101 @@ where
         for k in keys_to_remove {
           instance_metadata.instance_samples.remove(&k);
           self.datasamples.remove(&k);
+          self.not_read.remove(&k);
         }
       }
     }
 
-    // TODO: Implement other resource_limit settings than max_instances_per
-    // sample, i.e.
+    // TODO: Implement max_samples resource limit.
+
+    outcome
+  }
+
+  // ReaderDataLifecycle: Removes instances that have been not alive for longer
+  // than the autopurge delay, together with their samples. This is done
+  // silently, as the instances are already not alive. The instances are
+  // scanned only when the earliest purge time has been reached.
+  pub(crate) fn autopurge_not_alive_instances(&mut self, now: Timestamp) {
+    match self.next_autopurge {
+      Some(next) if next <= now => (),
+      _ => return,
+    }
+    let mut next_autopurge: Option<Timestamp> = None;
+    let mut expired = Vec::new();
+    for (key, imd) in &self.instance_map {
+      let (Some(since), Some(delay)) = (
+        imd.not_alive_since,
+        Self::autopurge_delay(&self.qos, imd.instance_state),
+      ) else {
+        continue;
+      };
+      let purge_at = since + delay;
+      if purge_at <= now {
+        expired.push(key.clone());
+      } else {
+        next_autopurge = Some(next_autopurge.map_or(purge_at, |t| t.min(purge_at)));
+      }
+    }
+    if !expired.is_empty() {
+      debug!("Autopurge {} not alive instances", expired.len());
+    }
+    for key in expired {
+      self.remove_instance(&key);
+    }
+    self.next_autopurge = next_autopurge;
+  }
+
+  // Autopurge delay for instances in the given state, or None if they are kept
+  // indefinitely.
+  fn autopurge_delay(qos: &QosPolicies, state: InstanceState) -> Option<Duration> {
+    let lifecycle = qos.reader_data_lifecycle()?;
+    let delay = match state {
+      InstanceState::Alive => return None,
+      InstanceState::NotAliveDisposed => lifecycle.autopurge_disposed_samples_delay,
+      InstanceState::NotAliveNoWriters => lifecycle.autopurge_nowriters_samples_delay,
+    };
+    Some(delay).filter(|d| *d != Duration::INFINITE)
+  }
+
+  // Linear search is fine here, because this is only needed when the
+  // max_instances limit is hit.
+  fn least_recently_updated_instance(&self) -> Option<D::K> {
+    self
+      .instance_map
+      .iter()
+      .min_by_key(|(_k, imd)| imd.last_update)
+      .map(|(k, _imd)| k.clone())
+  }
+
+  // Per instance: key hash, number of samples in the cache, and instance state.
+  pub(crate) fn instance_summary(&self) -> Vec<(KeyHash, usize, InstanceState)> {
+    self
+      .instance_map
+      .iter()
+      .map(|(key, imd)| {
+        let sample_count = if self.keyless {
+          self.datasamples.len()
+        } else {
+          imd.instance_samples.len()
+        };
+        (key.hash_key(false), sample_count, imd.instance_state)
+      })
+      .collect()
+  }
+
+  // Removes an instance and all of its samples from the cache.
+  fn remove_instance(&mut self, key: &D::K) {
+    if let Some(imd) = self.instance_map.remove(key) {
+      if self.keyless {
+        self.datasamples.clear();
+        self.not_read.clear();
+        return;
+      }
+      for ts in imd.instance_samples {
+        self.datasamples.remove(&ts);
+        self.not_read.remove(&ts);
+      }
+    }
   }
 
   // Helper for select_keys and select_instance_keys
@@ -247,13 +491,25 @@ where
   // Samples are marked read or viewed only when "read" or "take" methods (below)
   // are called.
   pub fn select_keys_for_access(&self, rc: ReadCondition) -> Vec<(Timestamp, D::K)> {
+    // Without keys, there is at most one instance, so look it up only once.
+    let single_instance = if self.keyless {
+      self.instance_map.iter().next()
+    } else {
+      None
+    };
     let mut keys: Vec<(Timestamp, D::K)> = self
       .datasamples
       .iter()
       .filter_map(|(ts, dsm)| {
-        let key = dsm.key();
-        // Instance meta wouldn't be cleaned with samples belongs to it.
-        let instance_meta = self.instance_map.get(&key).unwrap();
+        let (key, instance_meta) = match single_instance {
+          Some((key, imd)) => (key.clone(), imd),
+          None => {
+            let key = dsm.key();
+            // Instance meta wouldn't be cleaned with samples belongs to it.
+            let imd = self.instance_map.get(&key).unwrap();
+            (key, imd)
+          }
+        };
         if self.sample_selector(&rc, instance_meta, dsm) {
           Some((*ts, key))
         } else {
@@ -265,6 +521,16 @@ where
     keys
   }
 
+  // Oldest received sample that has not been read yet, regardless of view
+  // or instance state. Unlike select_keys_for_access, this does not scan the
+  // cache.
+  pub fn first_not_read_key(&self) -> Option<(Timestamp, D::K)> {
+    self.not_read.first().map(|ts| {
+      // The index only contains timestamps that are present in datasamples.
+      (*ts, self.datasamples.get(ts).unwrap().key())
+    })
+  }
+
   pub fn select_instance_keys_for_access(
     &self,
     instance: &D::K,
@@ -272,6 +538,8 @@ where
   ) -> Vec<(Timestamp, D::K)> {
     match self.instance_map.get(instance) {
       None => Vec::new(),
+      // All samples belong to the single instance.
+      Some(_) if self.keyless => self.select_keys_for_access(rc),
       Some(imd) => {
         let mut keys: Vec<(Timestamp, D::K)> = imd
           .instance_samples
@@ -294,6 +562,58 @@ where
     }
   }
 
+  // Like select_keys_for_access, but selects at most `max_samples` samples
+  // that have a value satisfying `predicate`. Dispose samples have no value,
+  // so they never match. Evaluating the predicate does not constitute access.
+  //
+  // Also returns the samples that were evaluated, but did not match. Samples
+  // after the last selected one are not evaluated, so each call evaluates
+  // every candidate at most once.
+  #[allow(clippy::type_complexity)]
+  pub fn select_matching_keys_for_access(
+    &self,
+    rc: ReadCondition,
+    max_samples: usize,
+    mut predicate: impl FnMut(&D, &SampleInfo) -> bool,
+  ) -> (Vec<(Timestamp, D::K)>, Vec<(Timestamp, D::K)>) {
+    let mrs_total = self
+      .datasamples
+      .values()
+      .next_back()
+      .map_or(0, |dswm| dswm.generation_counts.total());
+    let mut selected = Vec::new();
+    let mut rejected = Vec::new();
+    for (ts, key) in self.select_keys_for_access(rc) {
+      if selected.len() >= max_samples {
+        break;
+      }
+      // The keys were just selected from these maps.
+      let dswm = self.datasamples.get(&ts).unwrap();
+      let imd = self.instance_map.get(&key).unwrap();
+      let matches = match &dswm.sample {
+        Sample::Value(value) => {
+          // Ranks depend on the final selection, so they are not known yet.
+          let sample_info = Self::make_sample_info(
+            ts,
+            dswm,
+            imd,
+            0,
+            mrs_total,
+            imd.latest_generation_available.total(),
+          );
+          predicate(value, &sample_info)
+        }
+        Sample::Dispose(_) => false,
+      };
+      if matches {
+        selected.push((ts, key));
+      } else {
+        rejected.push((ts, key));
+      }
+    }
+    (selected, rejected)
+  }
+
   // select helper
   fn sample_selector(
     &self,
@@ -301,30 +621,21 @@ where
     imd: &InstanceMetaData,
     d: &SampleWithMetaData<D>,
   ) -> bool {
-    // check sample state
-    (*rc.sample_state_mask() == SampleState::any()
-      || rc.sample_state_mask()
-          .contains( if d.sample_has_been_read { SampleState::Read } else {SampleState::NotRead} ) )
-    &&
-    // check view state
-    (*rc.view_state_mask() == ViewState::any()
-      ||
-      { let sample_gen = d.generation_counts.total();
-        let last_accessed = imd.last_generation_accessed.total();
-        let is_new = sample_gen > last_accessed;
-        rc.view_state_mask()
-          .contains( if is_new { ViewState::New} else { ViewState::NotNew }  )
-      }
-    )
-    &&
-    // check instance state
-    (*rc.instance_state_mask() == InstanceState::any()
-      || rc.instance_state_mask()
-          .contains( imd.instance_state )
-    )
+    let sample_state = if d.sample_has_been_read {
+      SampleState::Read
+    } else {
+      SampleState::NotRead
+    };
+    let view_state = if d.generation_counts.total() > imd.last_generation_accessed.total() {
+      ViewState::New
+    } else {
+      ViewState::NotNew
+    };
+    rc.matches_states(sample_state, view_state, imd.instance_state)
   }
 
   fn make_sample_info(
+    reception_timestamp: Timestamp,
     dswm: &SampleWithMetaData<D>,
     imd: &InstanceMetaData,
     sample_rank: usize,
@@ -348,8 +659,10 @@ where
       generation_rank: mrsic_generations - dswm.generation_counts.total(),
       absolute_generation_rank: mrs_generations - dswm.generation_counts.total(),
       write_options: dswm.write_options.clone(),
+      reception_timestamp,
       publication_handle: dswm.writer_guid,
       sequence_number: dswm.sequence_number,
+      writer_qos: dswm.writer_qos.clone(),
     }
   }
 
@@ -425,8 +738,10 @@ where
       let dswm = self.datasamples.get_mut(ts).unwrap();
       let imd = self.instance_map.get(key).unwrap();
 
-      let sample_info = Self::make_sample_info(dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      let sample_info =
+        Self::make_sample_info(*ts, dswm, imd, len - index - 1, mrs_total, mrsic_total);
       dswm.sample_has_been_read = true; // mark as read
+      self.not_read.remove(ts);
       Self::record_instance_generation_viewed(
         &mut instance_generations,
         dswm.generation_counts,
@@ -491,8 +806,10 @@ where
     // collect result
     for (index, (ts, key)) in keys.iter().enumerate() {
       let dswm = self.datasamples.remove(ts).unwrap();
+      self.not_read.remove(ts);
       let imd = self.instance_map.get(key).unwrap();
-      let sample_info = Self::make_sample_info(&dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      let sample_info =
+        Self::make_sample_info(*ts, &dswm, imd, len - index - 1, mrs_total, mrsic_total);
       // dwsm.sample_has_been_read = true; // no need to mark read, as the dswm is
       // about to be destroyed
       Self::record_instance_generation_viewed(
@@ -528,6 +845,7 @@ where
     for (ts, key) in keys.iter() {
       let dswm = self.datasamples.get_mut(ts).unwrap();
       dswm.sample_has_been_read = true; // mark as read
+      self.not_read.remove(ts);
       Self::record_instance_generation_viewed(
         &mut instance_generations,
         dswm.generation_counts,
@@ -569,6 +887,7 @@ where
 
     for (ts, key) in keys.iter() {
       let dswm = self.datasamples.remove(ts).unwrap();
+      self.not_read.remove(ts);
       // dwsm.sample_has_been_read = true; // no need to mark read, as the dswm is
       // about to be destroyed
       Self::record_instance_generation_viewed(
@@ -583,6 +902,42 @@ where
     result
   }
 
+  // Does not count as access to the instance
+  pub(in crate::dds::with_key) fn instance_info(&self, key: &D::K) -> Option<InstanceInfo> {
+    let imd = self.instance_map.get(key)?;
+    let change = &imd.last_change;
+    let sample_has_been_read = self
+      .datasamples
+      .get(&change.receive_timestamp)
+      .map_or(true, |dswm| dswm.sample_has_been_read); // taken, if not present
+    Some(InstanceInfo {
+      instance_state: imd.instance_state,
+      generation_counts: imd.latest_generation_available,
+      last_change: SampleInfo {
+        sample_state: if sample_has_been_read {
+          SampleState::Read
+        } else {
+          SampleState::NotRead
+        },
+        view_state: if change.generation_counts.total() > imd.last_generation_accessed.total() {
+          ViewState::New
+        } else {
+          ViewState::NotNew
+        },
+        instance_state: imd.instance_state,
+        generation_counts: change.generation_counts,
+        sample_rank: 0,
+        generation_rank: 0,
+        absolute_generation_rank: 0,
+        write_options: change.write_options.clone(),
+        reception_timestamp: change.receive_timestamp,
+        publication_handle: change.writer_guid,
+        sequence_number: change.sequence_number,
+        writer_qos: change.writer_qos.clone(),
+      },
+    })
+  }
+
   pub(in crate::dds::with_key) fn next_key(&self, key: &D::K) -> Option<D::K> {
     self
       .instance_map
@@ -603,6 +958,345 @@ pub(crate) fn result_ok_as_ref_err_clone<T, E: Clone>(r: &Sample<T, E>) -> Sampl
 
 #[cfg(test)]
 mod tests {
+  use super::*;
+  use crate::{structure::guid::GUID, test::random_data::*};
+
+  fn limited_cache(max_instances: i32, eviction: InstanceEviction) -> DataSampleCache<RandomData> {
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepAll);
+    qos.resource_limits = Some(policy::ResourceLimits {
+      max_samples: -1,
+      max_instances,
+      max_samples_per_instance: -1,
+    });
+    let mut dsc = DataSampleCache::new(qos);
+    dsc.set_instance_eviction(eviction);
+    dsc
+  }
+
+  fn add(dsc: &mut DataSampleCache<RandomData>, a: i64, sn: i64) -> AddSampleOutcome {
+    dsc.add_sample(
+      Sample::Value(RandomData {
+        a,
+        b: "x".to_string(),
+      }),
+      GUID::GUID_UNKNOWN,
+      SequenceNumber::from(sn),
+      Timestamp::now(),
+      WriteOptions::default(),
+      None,
+    )
+  }
+
+  #[test]
+  fn dsc_max_instances_reject() {
+    let mut dsc = limited_cache(2, InstanceEviction::Reject);
+    assert_eq!(add(&mut dsc, 1, 1), AddSampleOutcome::Added);
+    assert_eq!(add(&mut dsc, 2, 2), AddSampleOutcome::Added);
+    assert_eq!(
+      add(&mut dsc, 3, 3),
+      AddSampleOutcome::RejectedByInstancesLimit
+    );
+    // existing instances can still be updated
+    assert_eq!(add(&mut dsc, 1, 4), AddSampleOutcome::Added);
+    assert_eq!(dsc.instance_map.keys().copied().collect::<Vec<_>>(), [1, 2]);
+  }
+
+  #[test]
+  fn dsc_max_instances_lru_evict() {
+    let mut dsc = limited_cache(2, InstanceEviction::LruEvict);
+    assert_eq!(add(&mut dsc, 1, 1), AddSampleOutcome::Added);
+    assert_eq!(add(&mut dsc, 2, 2), AddSampleOutcome::Added);
+    // Instance 1 is now more recently updated than 2
+    assert_eq!(add(&mut dsc, 1, 3), AddSampleOutcome::Added);
+    assert_eq!(add(&mut dsc, 3, 4), AddSampleOutcome::AddedWithEviction);
+    assert_eq!(dsc.instance_map.keys().copied().collect::<Vec<_>>(), [1, 3]);
+    // Samples of the evicted instance are gone, too.
+    assert_eq!(dsc.select_keys_for_access(ReadCondition::any()).len(), 3);
+  }
+
+  #[test]
+  fn dsc_instance_info_survives_take() {
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    let writer =
+      GUID::dummy_test_guid(crate::structure::guid::EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    assert!(dsc.instance_info(&1).is_none());
+    assert_eq!(add(&mut dsc, 1, 1), AddSampleOutcome::Added);
+    let dispose_ts = Timestamp::now();
+    dsc.add_sample(
+      Sample::Dispose(1),
+      writer,
+      SequenceNumber::from(2),
+      dispose_ts,
+      WriteOptions::default(),
+      None,
+    );
+    let keys = dsc.select_keys_for_access(ReadCondition::any());
+    assert_eq!(dsc.take_by_keys(&keys).len(), 2);
+
+    let info = dsc.instance_info(&1).unwrap();
+    assert_eq!(info.instance_state(), InstanceState::NotAliveDisposed);
+    assert_eq!(info.disposed_by(), Some(writer));
+    assert_eq!(info.last_change().sequence_number, SequenceNumber::from(2));
+    assert_eq!(info.last_change().reception_timestamp(), dispose_ts);
+    assert_eq!(info.last_change().sample_state, SampleState::Read);
+  }
+
+  #[test]
+  fn dsc_autopurge_not_alive_instances() {
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    dsc.qos.reader_data_lifecycle = Some(policy::ReaderDataLifecycle {
+      autopurge_disposed_samples_delay: Duration::from_secs(10),
+      ..policy::ReaderDataLifecycle::default()
+    });
+    let dispose = |dsc: &mut DataSampleCache<RandomData>, a: i64, sn: i64, ts: Timestamp| {
+      dsc.add_sample(
+        Sample::Dispose(a),
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::from(sn),
+        ts,
+        WriteOptions::default(),
+        None,
+      )
+    };
+    let t0 = Timestamp::now();
+    let secs = |s| t0 + Duration::from_secs(s);
+    add(&mut dsc, 1, 1);
+    add(&mut dsc, 2, 2);
+    add(&mut dsc, 3, 3);
+    dispose(&mut dsc, 1, 4, secs(1));
+    dispose(&mut dsc, 2, 5, secs(2));
+    // Instance 2 is reborn, so it is not purged.
+    add(&mut dsc, 2, 6);
+
+    dsc.autopurge_not_alive_instances(secs(10));
+    assert_eq!(dsc.instance_map.len(), 3);
+    dsc.autopurge_not_alive_instances(secs(11));
+    assert_eq!(dsc.instance_map.keys().copied().collect::<Vec<_>>(), [2, 3]);
+    // The samples of the purged instance are gone, too.
+    assert_eq!(dsc.select_keys_for_access(ReadCondition::any()).len(), 4);
+    dsc.autopurge_not_alive_instances(secs(100));
+    assert_eq!(dsc.instance_map.len(), 2);
+
+    // Without the policy, nothing is purged.
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    add(&mut dsc, 1, 1);
+    dispose(&mut dsc, 1, 2, t0);
+    dsc.autopurge_not_alive_instances(Timestamp::INFINITE);
+    assert_eq!(dsc.instance_map.len(), 1);
+  }
+
+  #[test]
+  fn dsc_unregister_waits_for_last_live_writer() {
+    use enumflags2::BitFlags;
+
+    use crate::{structure::guid::EntityKind, with_key::WriteOptionsBuilder};
+
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    let writer_a = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let writer_b = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mut sn = 0;
+    let mut write = |dsc: &mut DataSampleCache<RandomData>, sample, writer, flags| {
+      sn += 1;
+      dsc.add_sample(
+        sample,
+        writer,
+        SequenceNumber::from(sn),
+        Timestamp::now(),
+        WriteOptionsBuilder::new().sample_flags(flags).build(),
+        None,
+      )
+    };
+    let value = || {
+      Sample::Value(RandomData {
+        a: 1,
+        b: "x".to_string(),
+      })
+    };
+    let unregistered = SampleFlag::Unregistered | SampleFlag::Filtered;
+
+    write(&mut dsc, value(), writer_a, BitFlags::empty());
+    write(&mut dsc, value(), writer_b, BitFlags::empty());
+    // Writer B is still alive, so the instance stays alive.
+    write(&mut dsc, Sample::Dispose(1), writer_a, unregistered);
+    assert_eq!(
+      dsc.instance_info(&1).unwrap().instance_state(),
+      InstanceState::Alive
+    );
+    assert_eq!(dsc.select_keys_for_access(ReadCondition::any()).len(), 2);
+
+    write(&mut dsc, Sample::Dispose(1), writer_b, unregistered);
+    assert_eq!(
+      dsc.instance_info(&1).unwrap().instance_state(),
+      InstanceState::NotAliveNoWriters
+    );
+    assert_eq!(dsc.select_keys_for_access(ReadCondition::any()).len(), 3);
+
+    // Dispose is not mistaken for unregistration, even if both flags are set.
+    write(&mut dsc, value(), writer_a, BitFlags::empty());
+    write(&mut dsc, value(), writer_b, BitFlags::empty());
+    write(
+      &mut dsc,
+      Sample::Dispose(1),
+      writer_a,
+      SampleFlag::Disposed | SampleFlag::Unregistered,
+    );
+    assert_eq!(
+      dsc.instance_info(&1).unwrap().instance_state(),
+      InstanceState::NotAliveDisposed
+    );
+  }
+
+  #[test]
+  fn dsc_first_not_read_follows_read_and_take() {
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    assert!(dsc.first_not_read_key().is_none());
+    add(&mut dsc, 1, 1);
+    add(&mut dsc, 2, 2);
+    add(&mut dsc, 1, 3);
+
+    let first = dsc.first_not_read_key().unwrap();
+    assert_eq!(first.1, 1);
+    dsc.read_by_keys(&[first]);
+
+    // Taking samples also removes them from the NotRead index
+    let second = dsc.first_not_read_key().unwrap();
+    assert_eq!(second.1, 2);
+    dsc.take_by_keys(&[second]);
+
+    let third = dsc.first_not_read_key().unwrap();
+    assert_eq!(third.1, 1);
+    dsc.read_by_keys(&[third]);
+    assert!(dsc.first_not_read_key().is_none());
+
+    // Read samples remain in the cache
+    assert_eq!(dsc.select_keys_for_access(ReadCondition::any()).len(), 2);
+    assert!(dsc
+      .select_keys_for_access(ReadCondition::not_read())
+      .is_empty());
+  }
+
+  #[test]
+  fn dsc_select_matching_leaves_others_untouched() {
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    for (a, sn) in [(1, 1), (2, 2), (1, 3), (3, 4)] {
+      add(&mut dsc, a, sn);
+    }
+
+    let mut evaluated = 0;
+    let (selected, rejected) =
+      dsc.select_matching_keys_for_access(ReadCondition::not_read(), 2, |d, _info| {
+        evaluated += 1;
+        d.a != 2
+      });
+    // The last sample was not needed
+    assert_eq!(evaluated, 3);
+    assert_eq!(rejected.iter().map(|(_, k)| *k).collect::<Vec<_>>(), [2]);
+
+    let taken = dsc.take_by_keys(&selected);
+    let taken_sns: Vec<SequenceNumber> = taken
+      .iter()
+      .map(|ds| ds.sample_info().sequence_number)
+      .collect();
+    assert_eq!(
+      taken_sns,
+      [SequenceNumber::from(1), SequenceNumber::from(3)]
+    );
+
+    // The non-matching sample is still not read
+    assert_eq!(dsc.first_not_read_key().map(|(_, k)| k), Some(2));
+    assert_eq!(
+      dsc.select_keys_for_access(ReadCondition::not_read()).len(),
+      2
+    );
+  }
+
+  #[test]
+  fn dsc_sample_info_carries_writer_qos() {
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    let writer_qos = QosPolicies::builder()
+      .reliability(policy::Reliability::BestEffort)
+      .build();
+    dsc.add_sample(
+      Sample::Value(RandomData {
+        a: 1,
+        b: "x".to_string(),
+      }),
+      GUID::GUID_UNKNOWN,
+      SequenceNumber::from(1),
+      Timestamp::now(),
+      WriteOptions::default(),
+      Some(Arc::new(writer_qos.clone())),
+    );
+    add(&mut dsc, 2, 2); // writer QoS not known
+
+    let keys = dsc.select_keys_for_access(ReadCondition::any());
+    let samples = dsc.take_by_keys(&keys);
+    assert_eq!(samples[0].sample_info().writer_qos(), Some(&writer_qos));
+    assert_eq!(samples[1].sample_info().writer_qos(), None);
+  }
+
+  #[test]
+  fn dsc_read_condition_matches_sample_info() {
+    let mut dsc = limited_cache(-1, InstanceEviction::Reject);
+    add(&mut dsc, 1, 1);
+    add(&mut dsc, 2, 2);
+    let first = dsc.select_keys_for_access(ReadCondition::any())[0];
+    dsc.read_by_keys(&[first]);
+
+    let unread_alive = ReadCondition::builder()
+      .sample_state(SampleState::NotRead)
+      .view_state(ViewState::New | ViewState::NotNew)
+      .instance_state(InstanceState::Alive)
+      .build();
+    let keys = dsc.select_keys_for_access(unread_alive);
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].1, 2);
+
+    // SampleInfo agrees with the selection made by the cache
+    let keys = dsc.select_keys_for_access(ReadCondition::any());
+    let samples = dsc.read_by_keys(&keys);
+    assert!(!unread_alive.matches(samples[0].sample_info()));
+    assert!(unread_alive.matches(samples[1].sample_info()));
+  }
+
+  #[test]
+  fn dsc_keyless_skips_instance_index() {
+    use crate::dds::no_key::wrappers::NoKeyWrapper;
+
+    let mut qos = QosPolicies::qos_none();
+    qos.history = Some(policy::History::KeepLast { depth: 2 });
+    let mut dsc = DataSampleCache::<NoKeyWrapper<RandomData>>::new(qos);
+    dsc.set_keyless(true);
+    for sn in 1..=3 {
+      let sample = RandomData {
+        a: sn,
+        b: "x".to_string(),
+      };
+      dsc.add_sample(
+        Sample::Value(sample.into()),
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::from(sn),
+        Timestamp::now(),
+        WriteOptions::default(),
+        None,
+      );
+    }
+
+    // Samples are not indexed per instance
+    assert!(dsc.instance_map[&()].instance_samples.is_empty());
+    // History depth still applies to the single instance
+    assert_eq!(dsc.instance_summary()[0].1, 2);
+    let keys = dsc.select_instance_keys_for_access(&(), ReadCondition::any());
+    let samples = dsc.take_by_keys(&keys);
+    let values: Vec<i64> = samples
+      .iter()
+      .map(|s| s.value().as_ref().unwrap().a)
+      .collect();
+    assert_eq!(values, [2, 3]);
+    assert!(dsc.first_not_read_key().is_none());
+  }
+
   // use super::*;
   // use crate::{
   //   structure::{time::Timestamp},
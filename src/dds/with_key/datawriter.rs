@@ -1,15 +1,19 @@
 use std::{
+  collections::BTreeSet,
   marker::PhantomData,
   pin::Pin,
   sync::{
-    atomic::{AtomicI64, Ordering},
+    atomic::{AtomicBool, Ordering},
+    mpsc::RecvTimeoutError,
     Arc, Mutex,
   },
   task::{Context, Poll, Waker},
+  thread,
   time::{Duration, Instant},
 };
 
-use futures::{Future, Stream};
+use futures::{future, Future, Stream};
+use enumflags2::BitFlags;
 use mio_06::{Events, PollOpt, Ready, Token};
 use mio_extras::channel::{self as mio_channel, SendError, TrySendError};
 #[allow(unused_imports)]
@@ -19,27 +23,98 @@ use crate::{
   dds::{
     adapters::with_key::SerializerAdapter,
     ddsdata::DDSData,
+    health::HealthMonitor,
     helpers::*,
+    key::{InstanceHandle, KeyHash},
     pubsub::Publisher,
     qos::{
       policy::{Liveliness, Reliability},
       HasQoSPolicy, QosPolicies,
     },
     result::{CreateResult, WriteError, WriteResult},
+    sampleinfo::SampleFlag,
     statusevents::*,
-    topic::Topic,
+    topic::{Topic, TopicKind},
   },
   discovery::{discovery::DiscoveryCommand, sedp_messages::SubscriptionBuiltinTopicData},
   messages::submessages::elements::serialized_payload::SerializedPayload,
-  rtps::writer::WriterCommand,
-  serialization::CDRSerializerAdapter,
+  rtps::writer::{AckListener, HistorySpace, SendQueue, WriterCommand},
+  serialization::{
+    compression::{self, PayloadCompression},
+    CDRSerializerAdapter,
+  },
   structure::{
-    cache_change::ChangeKind, duration, entity::RTPSEntity, guid::GUID, rpc::SampleIdentity,
-    sequence_number::SequenceNumber, time::Timestamp,
+    cache_change::ChangeKind,
+    entity::RTPSEntity,
+    guid::GUID,
+    memory_budget::{dds_data_size, MemoryBudget},
+    rpc::SampleIdentity,
+    sequence_number::SequenceNumber,
+    time::Timestamp,
   },
-  Keyed, TopicDescription,
+  Key, Keyed, TopicDescription,
 };
 
+/// Handle to the periodic liveliness assertions started by
+/// [`DataWriter::assert_liveliness_periodic`].
+///
+/// Dropping the handle stops the assertions.
+pub struct LivelinessAssertionHandle {
+  // Dropping this stops the background thread.
+  stop_sender: std::sync::mpsc::Sender<()>,
+  join_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl LivelinessAssertionHandle {
+  /// Stops the assertions, and waits for the background thread to exit.
+  pub fn cancel(self) {
+    let Self {
+      stop_sender,
+      join_handle,
+    } = self;
+    drop(stop_sender);
+    if let Some(join_handle) = join_handle {
+      join_handle.join().unwrap_or(());
+    }
+  }
+
+  /// True, if the assertions have stopped, because the DataWriter was
+  /// dropped.
+  pub fn is_finished(&self) -> bool {
+    self
+      .join_handle
+      .as_ref()
+      .map_or(true, thread::JoinHandle::is_finished)
+  }
+}
+
+// Sends liveliness assertion of a DataWriter to Discovery. This is shared by
+// DataWriter::assert_liveliness and the periodic assertion thread.
+fn send_liveliness_assertion(
+  liveliness: Option<Liveliness>,
+  discovery_command: &mio_channel::SyncSender<DiscoveryCommand>,
+  writer_guid: GUID,
+) -> WriteResult<(), ()> {
+  match liveliness {
+    Some(Liveliness::ManualByParticipant { .. }) => {
+      if let Err(e) = discovery_command.send(DiscoveryCommand::ManualAssertLiveliness) {
+        error!("Failed to send DiscoveryCommand - Refresh. {e:?}");
+      }
+      Ok(())
+    }
+    Some(Liveliness::ManualByTopic { lease_duration: _ }) => discovery_command
+      .send(DiscoveryCommand::AssertTopicLiveliness {
+        writer_guid,
+        manual_assertion: true, // by definition of this function
+      })
+      .map_err(|e| {
+        error!("assert_liveness - Failed to send DiscoveryCommand. {e:?}");
+        WriteError::WouldBlock { data: () }
+      }),
+    _other => Ok(()),
+  }
+}
+
 // TODO: Move the write options and the builder type to some lower-level module
 // to avoid circular dependencies.
 #[derive(Debug, Default)]
@@ -47,6 +122,8 @@ pub struct WriteOptionsBuilder {
   related_sample_identity: Option<SampleIdentity>,
   source_timestamp: Option<Timestamp>,
   to_single_reader: Option<GUID>,
+  key_hash: Option<KeyHash>,
+  sample_flags: BitFlags<SampleFlag>,
 }
 
 impl WriteOptionsBuilder {
@@ -59,6 +136,8 @@ impl WriteOptionsBuilder {
       related_sample_identity: self.related_sample_identity,
       source_timestamp: self.source_timestamp,
       to_single_reader: self.to_single_reader,
+      key_hash: self.key_hash,
+      sample_flags: self.sample_flags.bits(),
     }
   }
 
@@ -88,6 +167,20 @@ impl WriteOptionsBuilder {
     self.to_single_reader = Some(reader);
     self
   }
+
+  // Set by DataWriter and by Reader when receiving. Not for applications.
+  #[must_use]
+  pub(crate) fn key_hash(mut self, key_hash: KeyHash) -> Self {
+    self.key_hash = Some(key_hash);
+    self
+  }
+
+  // Set by Reader when receiving.
+  #[must_use]
+  pub(crate) fn sample_flags(mut self, sample_flags: BitFlags<SampleFlag>) -> Self {
+    self.sample_flags = sample_flags;
+    self
+  }
 }
 
 /// Type to be used with write_with_options.
@@ -98,6 +191,8 @@ pub struct WriteOptions {
   source_timestamp: Option<Timestamp>,             // from DDS spec
   to_single_reader: Option<GUID>,                  /* try to send to one Reader only
                                                     * future extension room fo other fields. */
+  key_hash: Option<KeyHash>, // instance, sent as inline QoS for Deadline tracking
+  sample_flags: u32,         // received StatusInfo. Bits, because BitFlags is not Ord.
 }
 
 impl WriteOptions {
@@ -112,6 +207,17 @@ impl WriteOptions {
   pub fn to_single_reader(&self) -> Option<GUID> {
     self.to_single_reader
   }
+
+  /// Key hash of the written instance, if it is sent along with the sample.
+  /// DataWriters send this when they have a Deadline QoS policy, so that
+  /// Readers can track the deadline per instance.
+  pub fn key_hash(&self) -> Option<KeyHash> {
+    self.key_hash
+  }
+
+  pub(crate) fn sample_flags(&self) -> BitFlags<SampleFlag> {
+    BitFlags::from_bits_truncate(self.sample_flags)
+  }
 }
 
 impl From<Option<Timestamp>> for WriteOptions {
@@ -120,10 +226,39 @@ impl From<Option<Timestamp>> for WriteOptions {
       related_sample_identity: None,
       source_timestamp,
       to_single_reader: None,
+      key_hash: None,
+      sample_flags: 0,
     }
   }
 }
 
+/// How many matched DataReaders must acknowledge a sample, before
+/// [`DataWriter::ack_listener`] reports it [`AckStatus::Achieved`].
+///
+/// Only Reliable DataReaders that were matched when the sample was written
+/// are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AckQuorum {
+  /// All of them. If there are none, the sample is acknowledged immediately.
+  All,
+  /// At least this many of them
+  AtLeast(usize),
+}
+
+/// Outcome of the acknowledgements of a sample, reported by
+/// [`DataWriter::ack_listener`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AckStatus {
+  /// The quorum has acknowledged the sample.
+  Achieved,
+  /// The quorum did not acknowledge the sample within the timeout.
+  TimedOut,
+  /// The quorum can no longer be reached: DataReaders that had not
+  /// acknowledged the sample were unmatched, or excluded as stalled. Also
+  /// reported right away, if there were too few DataReaders to begin with.
+  ReaderSetChanged,
+}
+
 /// Simplified type for CDR encoding
 pub type DataWriterCdr<D> = DataWriter<D, CDRSerializerAdapter<D>>;
 
@@ -166,7 +301,38 @@ pub struct DataWriter<D: Keyed, SA: SerializerAdapter<D> = CDRSerializerAdapter<
   cc_upload_waker: Arc<Mutex<Option<Waker>>>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusChannelReceiver<DataWriterStatus>,
-  available_sequence_number: AtomicI64,
+  // Updated by the RTPS Writer as Readers are matched and unmatched
+  publication_matched: Arc<Mutex<PublicationMatchedState>>,
+  // Updated by the RTPS Writer when a Reader requests incompatible QoS
+  offered_incompatible_qos: Arc<Mutex<OfferedIncompatibleQosStatus>>,
+  // Sequence number for the next sample queued to the Writer. The lock is held
+  // while queuing, so that samples enter the queue in sequence number order,
+  // and a number is consumed only if the sample is actually queued.
+  next_sequence_number: Mutex<SequenceNumber>,
+  // Key hashes of instances that have been written, disposed, or registered.
+  // Both the plain and the MD5 form are recorded, so that a key hash obtained
+  // from elsewhere is recognized in either form.
+  known_instances: Mutex<BTreeSet<KeyHash>>,
+  // NO_KEY topic: there is only one instance, so instance bookkeeping is
+  // skipped.
+  keyless: bool,
+  memory_budget: Arc<MemoryBudget>,
+  health: Arc<HealthMonitor>,
+  // Stamp samples without a source timestamp with the current time
+  always_include_source_timestamp: bool,
+  payload_compression: Option<PayloadCompression>,
+  // Set by the RTPS Writer, when all matched Readers can decompress
+  compress_payloads: Arc<AtomicBool>,
+  // Set by the RTPS Writer, when some matched Reader gets the key hash inline
+  send_key_hash: Arc<AtomicBool>,
+  // Samples queued to the RTPS Writer, but not yet sent by it
+  send_queue: Arc<SendQueue>,
+  // Room in the RTPS Writer history. Writes block while there is none.
+  history_space: Arc<HistorySpace>,
+  // Fail writes when no Reader is matched
+  require_matched_readers: AtomicBool,
+  // Dropped with the DataWriter. Background threads hold a Weak to notice it.
+  alive: Arc<()>,
 }
 
 impl<D, SA> Drop for DataWriter<D, SA>
@@ -210,6 +376,16 @@ where
     cc_upload_waker: Arc<Mutex<Option<Waker>>>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_receiver: StatusChannelReceiver<DataWriterStatus>,
+    publication_matched: Arc<Mutex<PublicationMatchedState>>,
+    offered_incompatible_qos: Arc<Mutex<OfferedIncompatibleQosStatus>>,
+    memory_budget: Arc<MemoryBudget>,
+    health: Arc<HealthMonitor>,
+    always_include_source_timestamp: bool,
+    payload_compression: Option<PayloadCompression>,
+    compress_payloads: Arc<AtomicBool>,
+    send_key_hash: Arc<AtomicBool>,
+    send_queue: Arc<SendQueue>,
+    history_space: Arc<HistorySpace>,
   ) -> CreateResult<Self> {
     if let Some(lv) = qos.liveliness {
       match lv {
@@ -221,6 +397,7 @@ where
         }
       }
     };
+    let keyless = topic.kind() == TopicKind::NoKey;
     Ok(Self {
       data_phantom: PhantomData,
       ser_phantom: PhantomData,
@@ -232,22 +409,235 @@ where
       cc_upload_waker,
       discovery_command,
       status_receiver,
-      available_sequence_number: AtomicI64::new(1), // valid numbering starts from 1
+      publication_matched,
+      offered_incompatible_qos,
+      next_sequence_number: Mutex::new(SequenceNumber::new(1)), // valid numbering starts from 1
+      known_instances: Mutex::new(BTreeSet::new()),
+      keyless,
+      memory_budget,
+      health,
+      always_include_source_timestamp,
+      payload_compression,
+      compress_payloads,
+      send_key_hash,
+      send_queue,
+      history_space,
+      require_matched_readers: AtomicBool::new(false),
+      alive: Arc::new(()),
     })
   }
 
-  fn next_sequence_number(&self) -> SequenceNumber {
-    SequenceNumber::from(
-      self
-        .available_sequence_number
-        .fetch_add(1, Ordering::Relaxed),
-    )
+  // Compress the serialized sample, if configured and all matched Readers
+  // can decompress.
+  fn compress_payload(&self, payload: SerializedPayload) -> Result<SerializedPayload, String> {
+    match &self.payload_compression {
+      Some(pc) if self.compress_payloads.load(Ordering::Acquire) => {
+        compression::compress(pc.compressor().as_ref(), payload)
+          .map_err(|e| format!("Payload compression failed: {e}"))
+      }
+      _ => Ok(payload),
+    }
+  }
+
+  // Writing to a failed participant would just queue data that is never sent.
+  fn check_participant(&self) -> Result<(), String> {
+    match self.health.failure() {
+      None => Ok(()),
+      Some(reason) => Err(format!("Participant failed: {reason}")),
+    }
+  }
+
+  // Samples are admitted only if they fit into the participant memory limit,
+  // possibly after evicting BestEffort samples from reader caches.
+  // The Writer charges the budget when it stores the sample, so concurrent
+  // writes may exceed the limit by the samples in flight.
+  fn check_memory_limit(&self, ddsdata: &DDSData) -> bool {
+    let size = dds_data_size(ddsdata);
+    let has_room =
+      self.memory_budget.has_room_for(size) || self.memory_budget.relieve_pressure(size, None);
+    if !has_room {
+      debug!(
+        "Participant memory limit {:?} reached. Used {} bytes, cannot write {} more. topic={:?}",
+        self.memory_budget.limit(),
+        self.memory_budget.used(),
+        size,
+        self.my_topic.name(),
+      );
+    }
+    has_room
+  }
+
+  // With require_matched_readers, writing when nobody is listening is an
+  // error. The matched count is kept up to date by the RTPS Writer from its
+  // reader proxies.
+  fn has_required_readers(&self) -> bool {
+    !self.require_matched_readers.load(Ordering::Acquire)
+      || self.publication_matched.lock().unwrap().current_count() > 0
+  }
+
+  fn remember_instance(&self, key: &<D as Keyed>::K) -> InstanceHandle {
+    if self.keyless {
+      return InstanceHandle(KeyHash::zero());
+    }
+    let key_hash = key.hash_key(false);
+    let mut known_instances = self.known_instances.lock().unwrap();
+    known_instances.insert(key_hash);
+    known_instances.insert(key.hash_key(true));
+    InstanceHandle(key_hash)
+  }
+
+  // Removes both key hash forms of an unregistered instance. Returns false if
+  // the instance was not known.
+  fn forget_instance(&self, key: &<D as Keyed>::K) -> bool {
+    let mut known_instances = self.known_instances.lock().unwrap();
+    let was_known = known_instances.remove(&key.hash_key(false));
+    known_instances.remove(&key.hash_key(true));
+    was_known
+  }
+
+  // The key hash is sent along with each sample, if some Reader gets it in
+  // inline QoS. With a Deadline policy, the RTPS Writer also needs it to check
+  // the deadline per instance.
+  fn sends_key_hash(&self) -> bool {
+    !self.keyless
+      && (self.qos_policy.deadline.is_some() || self.send_key_hash.load(Ordering::Acquire))
+  }
+
+  fn inline_key_hash(&self, key: &<D as Keyed>::K) -> Option<KeyHash> {
+    self.sends_key_hash().then(|| key.hash_key(false))
+  }
+
+  fn with_inline_key_hash(&self, write_options: WriteOptions, data: &D) -> WriteOptions {
+    if !self.sends_key_hash() {
+      return write_options;
+    }
+    WriteOptions {
+      key_hash: Some(data.key().hash_key(false)),
+      ..write_options
+    }
+  }
+
+  // Source timestamp is added here, before queuing, so that it is the time of
+  // the write call, also if the write has to wait for room in the queue.
+  fn with_source_timestamp(&self, write_options: WriteOptions) -> WriteOptions {
+    match write_options.source_timestamp {
+      None if self.always_include_source_timestamp => WriteOptions {
+        source_timestamp: Some(Timestamp::now()),
+        ..write_options
+      },
+      _ => write_options,
+    }
+  }
+
+  // Assigns the next sequence number to the sample and tries to queue it to
+  // the Writer without blocking. On failure, the sample is given back and no
+  // sequence number is consumed. Full means that either the Writer history or
+  // the queue to the Writer is full, and `waker` is woken when there is room.
+  #[allow(clippy::result_large_err)] // the error gives the sample back
+  fn try_enqueue(
+    &self,
+    ddsdata: DDSData,
+    write_options: WriteOptions,
+    waker: &Waker,
+  ) -> Result<SequenceNumber, TrySendError<(DDSData, WriteOptions)>> {
+    if !self.history_space.try_take(waker) {
+      return Err(TrySendError::Full((ddsdata, write_options)));
+    }
+    // Waker must be set before trying, because otherwise the Writer making
+    // room between the try and the caller waiting would not wake it.
+    *self.cc_upload_waker.lock().unwrap() = Some(waker.clone());
+    let mut next_sequence_number = self.next_sequence_number.lock().unwrap();
+    let sequence_number = *next_sequence_number;
+    let writer_command = WriterCommand::DDSData {
+      ddsdata,
+      write_options,
+      sequence_number,
+    };
+    let give_back = |writer_command| match writer_command {
+      WriterCommand::DDSData {
+        ddsdata,
+        write_options,
+        ..
+      } => (ddsdata, write_options),
+      _ => unreachable!(),
+    };
+    // Count the sample before sending it, so that the Writer cannot process it
+    // before it is counted.
+    self.send_queue.enqueued();
+    match self.cc_upload.try_send(writer_command) {
+      Ok(()) => {
+        *next_sequence_number = sequence_number + SequenceNumber::new(1);
+        Ok(sequence_number)
+      }
+      Err(e) => {
+        self.send_queue.dequeued();
+        self.history_space.give_back(1);
+        if let TrySendError::Disconnected(_) = e {
+          self.send_queue.close();
+        }
+        match e {
+          TrySendError::Full(wc) => Err(TrySendError::Full(give_back(wc))),
+          TrySendError::Disconnected(wc) => Err(TrySendError::Disconnected(give_back(wc))),
+          TrySendError::Io(e) => Err(TrySendError::Io(e)),
+        }
+      }
+    }
+  }
+
+  // Queues the sample to the Writer. If the Writer history or the queue to the
+  // Writer is full, blocks until there is room, but at most for
+  // max_blocking_time. Timeout is reported as TrySendError::Full.
+  fn enqueue_blocking(
+    &self,
+    ddsdata: DDSData,
+    write_options: WriteOptions,
+  ) -> Result<SequenceNumber, TrySendError<()>> {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let (mut ddsdata, mut write_options) = match self.try_enqueue(ddsdata, write_options, &waker) {
+      Ok(sequence_number) => return Ok(sequence_number), // the common case
+      Err(TrySendError::Full(sample)) => sample,
+      Err(TrySendError::Disconnected(_)) => return Err(TrySendError::Disconnected(())),
+      Err(TrySendError::Io(e)) => return Err(TrySendError::Io(e)),
+    };
+
+    let timeout = self.max_blocking_time();
+    // None means the deadline is too far in the future to represent, i.e.
+    // wait forever.
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+      match self.try_enqueue(ddsdata, write_options, &waker) {
+        Ok(sequence_number) => return Ok(sequence_number),
+        Err(TrySendError::Full(sample)) => (ddsdata, write_options) = sample,
+        Err(TrySendError::Disconnected(_)) => return Err(TrySendError::Disconnected(())),
+        Err(TrySendError::Io(e)) => return Err(TrySendError::Io(e)),
+      }
+      match deadline {
+        None => thread::park(),
+        Some(deadline) => {
+          let now = Instant::now();
+          if now >= deadline {
+            warn!(
+              "Write timed out: topic={:?}  timeout={:?}",
+              self.my_topic.name(),
+              timeout,
+            );
+            return Err(TrySendError::Full(()));
+          }
+          thread::park_timeout(deadline - now);
+        }
+      }
+      // Wakeup may be spurious, or another writer may have taken the room, so
+      // loop and try again.
+    }
   }
 
-  fn undo_sequence_number(&self) {
+  // How long a write may block waiting for room in the queue to the Writer.
+  fn max_blocking_time(&self) -> Duration {
     self
-      .available_sequence_number
-      .fetch_sub(1, Ordering::Relaxed);
+      .qos()
+      .reliable_max_blocking_time()
+      .unwrap_or(TIMEOUT_FALLBACK)
+      .to_std()
   }
 
   /// Manually refreshes liveliness
@@ -299,6 +689,13 @@ where
 
   /// Writes single data instance to a topic.
   ///
+  /// If the DataWriter cannot accept the sample right away, the call blocks
+  /// until it can, but at most for the `max_blocking_time` of
+  /// [`Reliability::Reliable`], and then returns [`WriteError::Timeout`].
+  /// With KeepAll history, the DataWriter accepts no more than the
+  /// `max_samples` of ResourceLimits samples that are not yet acknowledged by
+  /// all matched Reliable Readers.
+  ///
   /// # Examples
   ///
   /// ```
@@ -338,6 +735,12 @@ where
     data: D,
     write_options: WriteOptions,
   ) -> WriteResult<SampleIdentity, D> {
+    if let Err(reason) = self.check_participant() {
+      return Err(WriteError::Poisoned { reason, data });
+    }
+    if !self.has_required_readers() {
+      return Err(WriteError::NoMatchedReaders { data });
+    }
     // serialize
     let send_buffer = match SA::to_bytes(&data) {
       Ok(b) => b,
@@ -349,47 +752,36 @@ where
       }
     };
 
-    let ddsdata = DDSData::new(SerializedPayload::new_from_bytes(
+    let payload = match self.compress_payload(SerializedPayload::new_from_bytes(
       SA::output_encoding(),
       send_buffer,
-    ));
-    let sequence_number = self.next_sequence_number();
-    let writer_command = WriterCommand::DDSData {
-      ddsdata,
-      write_options,
-      sequence_number,
+    )) {
+      Ok(p) => p,
+      Err(reason) => return Err(WriteError::Serialization { reason, data }),
     };
+    let ddsdata = DDSData::new(payload);
+    if !self.check_memory_limit(&ddsdata) {
+      return Err(WriteError::WouldBlock { data });
+    }
 
-    let timeout = self.qos().reliable_max_blocking_time();
-
-    match try_send_timeout(&self.cc_upload, writer_command, timeout) {
-      Ok(_) => {
+    let write_options = self.with_source_timestamp(self.with_inline_key_hash(write_options, &data));
+    match self.enqueue_blocking(ddsdata, write_options) {
+      Ok(sequence_number) => {
+        if !self.keyless {
+          self.remember_instance(&data.key());
+        }
         self.refresh_manual_liveliness();
         Ok(SampleIdentity {
           writer_guid: self.my_guid,
           sequence_number,
         })
       }
-      Err(TrySendError::Full(_writer_command)) => {
-        warn!(
-          "Write timed out: topic={:?}  timeout={:?}",
-          self.my_topic.name(),
-          timeout,
-        );
-        self.undo_sequence_number();
-        Err(WriteError::WouldBlock { data })
-      }
-      Err(TrySendError::Disconnected(_)) => {
-        self.undo_sequence_number();
-        Err(WriteError::Poisoned {
-          reason: "Cannot send to Writer".to_string(),
-          data,
-        })
-      }
-      Err(TrySendError::Io(e)) => {
-        self.undo_sequence_number();
-        Err(e.into())
-      }
+      Err(TrySendError::Full(())) => Err(WriteError::Timeout { data }),
+      Err(TrySendError::Disconnected(())) => Err(WriteError::Poisoned {
+        reason: "Cannot send to Writer".to_string(),
+        data,
+      }),
+      Err(TrySendError::Io(e)) => Err(e.into()),
     }
   }
 
@@ -475,21 +867,31 @@ where
     } // match
   }
 
-  /*
-
-  /// Unimplemented. <b>Do not use</b>.
+  /// Reports the acknowledgements of each sample written after this call.
+  ///
+  /// `callback` is called once for each sample, with its sequence number (see
+  /// [`write_with_options`](Self::write_with_options)), when the `quorum` of
+  /// matched Reliable DataReaders has acknowledged it, or when that can no
+  /// longer happen: `timeout` has passed, or DataReaders were unmatched.
+  /// DataReaders matched after the sample was written do not count.
+  ///
+  /// The callback runs in the RTPS processing thread, so it must return
+  /// quickly, and not panic. Calling this again replaces the callback, also
+  /// for samples still waiting for acknowledgements.
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
+  /// # use std::time::Duration;
   /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::with_key::{AckQuorum, AckStatus, DataWriter};
   /// # use rustdds::serialization::CDRSerializerAdapter;
   /// #
   /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
+  /// let qos = QosPolicyBuilder::new()
+  ///   .reliable(rustdds::Duration::from_millis(100))
+  ///   .build();
   /// let publisher = domain_participant.create_publisher(&qos).unwrap();
   ///
   /// #[derive(Serialize, Deserialize, Debug)]
@@ -502,29 +904,56 @@ where
   ///   }
   /// }
   ///
-  /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(lls) = data_writer.get_liveliness_lost_status() {
-  ///   // do something
-  /// }
+  /// let (receipt_sender, receipt_receiver) = std::sync::mpsc::channel();
+  /// data_writer
+  ///   .ack_listener(AckQuorum::All, Duration::from_secs(5), move |sn, status| {
+  ///     let _ = receipt_sender.send((sn, status));
+  ///   })
+  ///   .unwrap();
+  ///
+  /// let sample_id = data_writer
+  ///   .write_with_options(SomeType { a: 1 }, WriteOptions::default())
+  ///   .unwrap();
+  /// // No DataReaders are matched, so all of them have acknowledged.
+  /// let (sn, status) = receipt_receiver.recv().unwrap();
+  /// assert_eq!(sn, sample_id.sequence_number);
+  /// assert_eq!(status, AckStatus::Achieved);
   /// ```
-  pub fn get_liveliness_lost_status(&self) -> Result<LivelinessLostStatus> {
-    todo!()
+  pub fn ack_listener(
+    &self,
+    quorum: AckQuorum,
+    timeout: Duration,
+    callback: impl Fn(SequenceNumber, AckStatus) + Send + 'static,
+  ) -> WriteResult<(), ()> {
+    let listener = AckListener {
+      quorum,
+      timeout,
+      callback: Box::new(callback),
+    };
+    self
+      .cc_upload
+      .send(WriterCommand::SetAckListener { listener })
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("Cannot send to Writer: {e}"),
+        data: (),
+      })
   }
 
-  /// Should get latest offered deadline missed status. <b>Do not use yet</b> use `get_status_lister` instead for the moment.
+  /// Locators of the matched DataReaders, as currently used by this
+  /// DataWriter to send to them. Each entry also tells which locator was last
+  /// used successfully.
+  ///
+  /// This is meant for debugging connectivity. It asks the RTPS processing
+  /// thread for a snapshot, so it blocks for a moment.
   ///
   /// # Examples
   ///
   /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
   /// #
   /// let domain_participant = DomainParticipant::new(0).unwrap();
   /// let qos = QosPolicyBuilder::new().build();
@@ -540,48 +969,29 @@ where
   ///   }
   /// }
   ///
-  /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Deadline missed status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(odms) = data_writer.get_offered_deadline_missed_status() {
-  ///   // do something
+  /// for reader in data_writer.remote_reader_locators().unwrap() {
+  ///   let unicast: Vec<String> = reader.unicast_locators.iter().map(|l| l.to_string()).collect();
+  ///   println!("{:?}: {}", reader.guid, unicast.join(", "));
   /// }
   /// ```
-  pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
-    let mut fstatus = OfferedDeadlineMissedStatus::new();
-    while let Ok(status) = self.status_receiver.try_recv() {
-      match status {
-        StatusChange::OfferedDeadlineMissedStatus(status) => fstatus = status,
-  // TODO: possibly save old statuses
-        _ => (),
-      }
-    }
-
-    match self
-      .cc_upload
-      .try_send(WriterCommand::ResetOfferedDeadlineMissedStatus {
-        writer_guid: self.guid(),
-      }) {
-      Ok(_) => (),
-      Err(e) => error!("Unable to send ResetOfferedDeadlineMissedStatus. {e:?}"),
-    };
-
-    Ok(fstatus)
+  pub fn remote_reader_locators(&self) -> WriteResult<Vec<RemoteLocators>, ()> {
+    self.query_writer(|reply| WriterCommand::GetRemoteLocators { reply })
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// Reports how many samples the RTPS Writer currently keeps in its history
+  /// cache, and the oldest of them. Useful for monitoring whether some
+  /// matched Reader is falling behind.
+  ///
+  /// This asks the RTPS processing thread, so it blocks for a moment.
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
   /// #
   /// let domain_participant = DomainParticipant::new(0).unwrap();
   /// let qos = QosPolicyBuilder::new().build();
@@ -597,30 +1007,29 @@ where
   ///   }
   /// }
   ///
-  /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(oiqs) = data_writer.get_offered_incompatible_qos_status() {
-  ///   // do something
-  /// }
+  /// let status = data_writer.history_cache_status().unwrap();
+  /// println!("{} samples cached", status.depth);
   /// ```
-  pub fn get_offered_incompatible_qos_status(&self) -> Result<OfferedIncompatibleQosStatus> {
-    todo!()
+  pub fn history_cache_status(&self) -> WriteResult<HistoryCacheStatus, ()> {
+    self.query_writer(|reply| WriterCommand::GetHistoryCacheStatus { reply })
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// Writes a human-readable summary of the state of this DataWriter to
+  /// `out`: sequence numbers, history buffer size, matched Readers, and the
+  /// acknowledgement state of each Reader.
+  ///
+  /// This is meant for diagnosing e.g. why a Reliable DataWriter is not
+  /// advancing. The format is not stable. It asks the RTPS processing thread
+  /// for a snapshot, so it blocks for a moment.
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
   /// #
   /// let domain_participant = DomainParticipant::new(0).unwrap();
   /// let qos = QosPolicyBuilder::new().build();
@@ -636,27 +1045,63 @@ where
   ///   }
   /// }
   ///
-  /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(pms) = data_writer.get_publication_matched_status() {
-  ///   // do something
-  /// }
+  /// data_writer.print_diagnostic(&mut std::io::stderr()).unwrap();
   /// ```
-  pub fn get_publication_matched_status(&self) -> Result<PublicationMatchedStatus> {
-    todo!()
+  pub fn print_diagnostic(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+    writeln!(
+      out,
+      "DataWriter {:?} topic={:?} type={:?}",
+      self.my_guid,
+      self.my_topic.name(),
+      self.my_topic.get_type().name()
+    )?;
+    writeln!(
+      out,
+      "Next sequence number {}, known instances {}",
+      i64::from(*self.next_sequence_number.lock().unwrap()),
+      self.known_instances.lock().unwrap().len()
+    )?;
+    let rtps_state = self
+      .query_writer(|reply| WriterCommand::GetDiagnostic { reply })
+      .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    out.write_all(rtps_state.as_bytes())
   }
 
-  */
+  // Asks the RTPS Writer for something, and waits for the answer.
+  fn query_writer<T>(
+    &self,
+    command: impl FnOnce(std::sync::mpsc::SyncSender<T>) -> WriterCommand,
+  ) -> WriteResult<T, ()> {
+    let (reply_sender, reply_receiver) = std::sync::mpsc::sync_channel(1);
+    self
+      .cc_upload
+      .try_send(command(reply_sender))
+      .map_err(|e| match e {
+        TrySendError::Full(_) => WriteError::WouldBlock { data: () },
+        e => WriteError::Poisoned {
+          reason: format!("Cannot send to Writer: {e}"),
+          data: (),
+        },
+      })?;
+    reply_receiver
+      .recv_timeout(REMOTE_LOCATORS_QUERY_TIMEOUT)
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("No answer from Writer: {e}"),
+        data: (),
+      })
+  }
 
-  /// Topic assigned to this DataWriter
+  /*
+
+  /// Unimplemented. <b>Do not use</b>.
   ///
   /// # Examples
   ///
-  /// ```
+  /// ```no_run
+  // TODO: enable when functional
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::with_key::DataWriter;
@@ -678,15 +1123,19 @@ where
   ///
   /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
   ///
-  /// assert_eq!(data_writer.topic(), &topic);
+  /// // Liveliness lost status has changed
+  ///
+  /// if let Ok(lls) = data_writer.get_liveliness_lost_status() {
+  ///   // do something
+  /// }
   /// ```
-  pub fn topic(&self) -> &Topic {
-    &self.my_topic
+  pub fn get_liveliness_lost_status(&self) -> Result<LivelinessLostStatus> {
+    todo!()
   }
 
-  /// Publisher assigned to this DataWriter
+  /// Should get latest offered deadline missed status. <b>Do not use yet</b> use `get_status_lister` instead for the moment.
   ///
   /// # Examples
   ///
@@ -712,30 +1161,259 @@ where
   ///
   /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
-  ///
-  /// assert_eq!(data_writer.publisher(), &publisher);
-  pub fn publisher(&self) -> &Publisher {
-    &self.my_publisher
-  }
-
-  /// Manually asserts liveliness (use this instead of refresh) according to QoS
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
   ///
-  /// # Examples
+  /// // Deadline missed status has changed
   ///
+  /// if let Ok(odms) = data_writer.get_offered_deadline_missed_status() {
+  ///   // do something
+  /// }
   /// ```
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// #[derive(Serialize, Deserialize, Debug)]
-  /// struct SomeType { a: i32 }
-  /// impl Keyed for SomeType {
+  pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
+    let mut fstatus = OfferedDeadlineMissedStatus::new();
+    while let Ok(status) = self.status_receiver.try_recv() {
+      match status {
+        StatusChange::OfferedDeadlineMissedStatus(status) => fstatus = status,
+  // TODO: possibly save old statuses
+        _ => (),
+      }
+    }
+
+    match self
+      .cc_upload
+      .try_send(WriterCommand::ResetOfferedDeadlineMissedStatus {
+        writer_guid: self.guid(),
+      }) {
+      Ok(_) => (),
+      Err(e) => error!("Unable to send ResetOfferedDeadlineMissedStatus. {e:?}"),
+    };
+
+    Ok(fstatus)
+  }
+  */
+
+  /// Returns how many times Readers have requested QoS that this DataWriter
+  /// does not offer, and which policies caused it. Resets the `count_change`
+  /// field, so that the next call reports changes since this one.
+  ///
+  /// This is the same information as in
+  /// [`DataWriterStatus::OfferedIncompatibleQos`] events, but can be polled
+  /// without reading the status event stream.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status = data_writer.get_offered_incompatible_qos_status();
+  /// for (policy_id, count) in status.policies {
+  ///   println!("{policy_id:?} was incompatible {count} times");
+  /// }
+  /// ```
+  pub fn get_offered_incompatible_qos_status(&self) -> OfferedIncompatibleQosStatus {
+    self.offered_incompatible_qos.lock().unwrap().take()
+  }
+
+  /// Returns the counts of matched Readers, and resets the `count_change`
+  /// fields, so that the next call reports changes since this one.
+  ///
+  /// This is the same information as in
+  /// [`DataWriterStatus::PublicationMatched`] events, but can be polled
+  /// without reading the status event stream.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let status = data_writer.get_publication_matched_status();
+  /// if status.current_count.count() == 0 {
+  ///   // nobody is listening
+  /// }
+  /// ```
+  pub fn get_publication_matched_status(&self) -> PublicationMatchedStatus {
+    self.publication_matched.lock().unwrap().take()
+  }
+
+  /// Makes [`write`](Self::write) and the other write operations fail with
+  /// [`WriteError::NoMatchedReaders`], instead of succeeding, when no
+  /// DataReader is matched at the time of writing.
+  ///
+  /// This is off by default: writing without matched DataReaders is
+  /// normally not an error. Disposes and unregisters are not affected.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::dds::WriteError;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_writer.set_require_matched_readers(true);
+  /// // Nobody is listening
+  /// assert!(matches!(
+  ///   data_writer.write(SomeType { a: 1 }, None),
+  ///   Err(WriteError::NoMatchedReaders { .. })
+  /// ));
+  /// ```
+  pub fn set_require_matched_readers(&self, require: bool) {
+    self
+      .require_matched_readers
+      .store(require, Ordering::Release);
+  }
+
+  /// See [`set_require_matched_readers`](Self::set_require_matched_readers).
+  pub fn require_matched_readers(&self) -> bool {
+    self.require_matched_readers.load(Ordering::Acquire)
+  }
+
+  /// Topic assigned to this DataWriter
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// // WithKey is important
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// assert_eq!(data_writer.topic(), &topic);
+  /// ```
+  pub fn topic(&self) -> &Topic {
+    &self.my_topic
+  }
+
+  pub(crate) fn payload_compression(&self) -> Option<&PayloadCompression> {
+    self.payload_compression.as_ref()
+  }
+
+  /// Publisher assigned to this DataWriter
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// // WithKey is important
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// assert_eq!(data_writer.publisher(), &publisher);
+  pub fn publisher(&self) -> &Publisher {
+    &self.my_publisher
+  }
+
+  /// Manually asserts liveliness (use this instead of refresh) according to QoS
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
   ///   type K = i32;
   ///
   ///   fn key(&self) -> Self::K {
@@ -752,22 +1430,82 @@ where
   /// An `Err` result means that livelines assertion message could not be sent,
   /// likely because Discovery has too much work to do.
   pub fn assert_liveliness(&self) -> WriteResult<(), ()> {
-    self.refresh_manual_liveliness();
+    send_liveliness_assertion(self.qos().liveliness, &self.discovery_command, self.guid())
+  }
 
-    match self.qos().liveliness {
-      Some(Liveliness::ManualByTopic { lease_duration: _ }) => {
-        self
-          .discovery_command
-          .send(DiscoveryCommand::AssertTopicLiveliness {
-            writer_guid: self.guid(),
-            manual_assertion: true, // by definition of this function
-          })
-          .map_err(|e| {
-            error!("assert_liveness - Failed to send DiscoveryCommand. {e:?}");
-            WriteError::WouldBlock { data: () }
-          })
-      }
-      _other => Ok(()),
+  /// Starts a background thread that calls
+  /// [`assert_liveliness`](Self::assert_liveliness) right away and then every
+  /// `interval`.
+  ///
+  /// This is a convenience for the `Manual` [`Liveliness`] kinds, where the
+  /// application must assert liveliness within the lease duration, but has no
+  /// data to write. `interval` should be comfortably shorter than the lease
+  /// duration.
+  ///
+  /// The assertions stop when the returned handle is dropped or
+  /// [cancelled](LivelinessAssertionHandle::cancel), or when this DataWriter
+  /// is dropped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use std::time::Duration;
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new()
+  ///   .liveliness(policy::Liveliness::ManualByTopic {
+  ///     lease_duration: rustdds::Duration::from_secs(1),
+  ///   })
+  ///   .build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// # #[derive(Serialize, Deserialize, Debug)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let assertions = data_writer.assert_liveliness_periodic(Duration::from_millis(300));
+  /// // ... the writer stays alive without writing ...
+  /// assertions.cancel();
+  /// ```
+  pub fn assert_liveliness_periodic(&self, interval: Duration) -> LivelinessAssertionHandle {
+    let liveliness = self.qos().liveliness;
+    let discovery_command = self.discovery_command.clone();
+    let writer_guid = self.guid();
+    let writer_alive = Arc::downgrade(&self.alive);
+    let (stop_sender, stop_receiver) = std::sync::mpsc::channel();
+    let join_handle = thread::Builder::new()
+      .name("RustDDS liveliness assertion".to_string())
+      .spawn(move || loop {
+        if writer_alive.upgrade().is_none() {
+          debug!("DataWriter {writer_guid:?} dropped, stopping liveliness assertions.");
+          return;
+        }
+        // On error, just try again on the next round.
+        let _ = send_liveliness_assertion(liveliness, &discovery_command, writer_guid);
+        match stop_receiver.recv_timeout(interval) {
+          Err(RecvTimeoutError::Timeout) => (),
+          // cancelled or handle dropped
+          Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+        }
+      })
+      .map_err(|e| error!("Cannot start liveliness assertion thread: {e}"))
+      .ok();
+    LivelinessAssertionHandle {
+      stop_sender,
+      join_handle,
     }
   }
 
@@ -809,8 +1547,103 @@ where
     todo!()
   }
 
+  /// Gets the Discovery data of one matched DataReader: its Topic, type and
+  /// requested QoS.
+  ///
+  /// Returns `None` if `reader_guid` is not currently matched with this
+  /// DataWriter.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// if let Some(reader) = data_writer.get_publication_matched_status().last_subscription_key {
+  ///   let requested_qos = data_writer.get_matched_subscription_data(reader).map(|r| r.qos());
+  /// }
+  /// ```
+  pub fn get_matched_subscription_data(
+    &self,
+    reader_guid: GUID,
+  ) -> Option<SubscriptionBuiltinTopicData> {
+    if !self
+      .publication_matched
+      .lock()
+      .unwrap()
+      .is_matched(reader_guid)
+    {
+      return None;
+    }
+    self.my_publisher.reader_data(reader_guid)
+  }
+
+  /// Computes the RTPS key hash of the instance that `data` belongs to.
+  ///
+  /// The hash is the CDR Big-Endian encoding of the key, zero-padded to 16
+  /// bytes, or its MD5 digest if the key may be longer than 16 bytes. See RTPS
+  /// Spec v2.3 Section 9.6.3.8 KeyHash.
+  ///
+  /// This is useful for identifying instances in external systems that use
+  /// key hashes.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let hash = data_writer.get_key_hash_bytes(&SomeType { a: 1 });
+  /// assert_eq!(hash, [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+  /// ```
+  pub fn get_key_hash_bytes(&self, data: &D) -> [u8; 16] {
+    data.key().hash_key(false).to_bytes()
+  }
+
   /// Disposes data instance with specified key
   ///
+  /// The dispose occupies a sequence number and a place in the Writer history
+  /// like a data sample, and blocks like [`write`](Self::write) when the
+  /// history is full.
+  ///
   /// # Arguments
   ///
   /// * `key` - Key of the instance
@@ -862,28 +1695,236 @@ where
     key: &<D as Keyed>::K,
     source_timestamp: Option<Timestamp>,
   ) -> WriteResult<(), ()> {
+    let (ddsdata, write_options) =
+      self.instance_change(ChangeKind::NotAliveDisposed, key, source_timestamp)?;
+    self.send_dispose(ddsdata, write_options)?;
+    self.remember_instance(key);
+    Ok(())
+  }
+
+  /// Disposes the instance identified by `key` and unregisters it from this
+  /// DataWriter in a single sample.
+  ///
+  /// This is sent with both the disposed and unregistered flags in
+  /// PID_STATUS_INFO, which is what some other DDS implementations send when
+  /// a DataWriter is deleted with `autodispose_unregistered_instances`.
+  pub fn dispose_and_unregister(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    let (ddsdata, write_options) = self.instance_change(
+      ChangeKind::NotAliveDisposedUnregistered,
+      key,
+      source_timestamp,
+    )?;
+    self.send_dispose(ddsdata, write_options)?;
+    self.forget_instance(key);
+    Ok(())
+  }
+
+  /// Informs Readers that this DataWriter will no longer update the instance
+  /// identified by `key`. Unlike [`dispose`](Self::dispose), this does not
+  /// mean that the instance has been deleted: other DataWriters may still
+  /// update it.
+  ///
+  /// Like a dispose, this is sent as a sample with the key only, so it
+  /// occupies a sequence number and a place in the Writer history, and it
+  /// blocks like [`write`](Self::write) when the history is full.
+  ///
+  /// Fails with [`WriteError::UnknownInstance`], if this DataWriter has not
+  /// written, disposed, or registered the instance.
+  ///
+  /// See DDS Spec v1.4 Section 2.2.2.4.2.7 unregister_instance.
+  pub fn unregister_instance(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    // Forget first, so that concurrent unregisters cannot both succeed
+    if !self.forget_instance(key) {
+      return Err(self.unknown_instance());
+    }
+    let result = self
+      .instance_change(ChangeKind::NotAliveUnregistered, key, source_timestamp)
+      .and_then(|(ddsdata, write_options)| self.send_dispose(ddsdata, write_options));
+    if result.is_err() {
+      self.remember_instance(key);
+    }
+    result
+  }
+
+  /// Disposes the instance identified by `handle`.
+  ///
+  /// This does not need the key value, so it is sent to Readers as a key hash
+  /// only (PID_KEY_HASH in inline QoS, no serialized key).
+  ///
+  /// Fails with [`WriteError::UnknownInstance`], if the handle was not
+  /// obtained from this DataWriter.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32, val: usize }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// data_writer.write(SomeType { a: 1, val: 3 }, None).unwrap();
+  ///
+  /// let handle = data_writer.lookup_instance(&1).unwrap();
+  /// data_writer.dispose_instance(handle, None).unwrap();
+  /// ```
+  pub fn dispose_instance(
+    &self,
+    handle: InstanceHandle,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    self.dispose_by_key_hash(handle.key_hash(), false, source_timestamp)
+  }
+
+  /// Disposes the instance identified by a raw RTPS key hash, e.g. one
+  /// received from another DDS implementation. This is meant for bridging
+  /// applications, which do not necessarily know the key value.
+  ///
+  /// Only the key hash is sent (PID_KEY_HASH in inline QoS), like other DDS
+  /// implementations do in this case. Both the plain and MD5 forms of the
+  /// key hash are accepted, see
+  /// [`get_key_hash_bytes`](Self::get_key_hash_bytes).
+  ///
+  /// Unless `allow_unknown` is set, fails with
+  /// [`WriteError::UnknownInstance`] if this DataWriter has not written,
+  /// disposed, or registered an instance with this key hash.
+  pub fn dispose_by_key_hash(
+    &self,
+    key_hash: KeyHash,
+    allow_unknown: bool,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    let is_known = self.known_instances.lock().unwrap().contains(&key_hash);
+    if !is_known && !allow_unknown {
+      return Err(WriteError::UnknownInstance {
+        reason: format!(
+          "No instance with key hash {:02x?} in topic {:?}",
+          key_hash.to_bytes(),
+          self.my_topic.name()
+        ),
+        data: (),
+      });
+    }
+    let ddsdata = DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, key_hash);
+    // The key hash is already in the DDSData
+    self.send_dispose(ddsdata, Self::dispose_write_options(None, source_timestamp))?;
+    if !is_known {
+      self.known_instances.lock().unwrap().insert(key_hash);
+    }
+    Ok(())
+  }
+
+  /// Informs the DataWriter that the application will be modifying the
+  /// instance of `instance`, and returns a handle to it. The handle can be
+  /// used e.g. in [`dispose_instance`](Self::dispose_instance).
+  ///
+  /// Nothing is sent to Readers.
+  ///
+  /// See DDS Spec v1.4 Section 2.2.2.4.2.5 register_instance.
+  pub fn register_instance(&self, instance: &D) -> InstanceHandle {
+    self.remember_instance(&instance.key())
+  }
+
+  /// Handle of the instance with the given key, if this DataWriter has
+  /// written, disposed, or registered it.
+  ///
+  /// See DDS Spec v1.4 Section 2.2.2.4.2.11 lookup_instance.
+  pub fn lookup_instance(&self, key: &<D as Keyed>::K) -> Option<InstanceHandle> {
+    let key_hash = key.hash_key(false);
+    if self.known_instances.lock().unwrap().contains(&key_hash) {
+      Some(InstanceHandle(key_hash))
+    } else {
+      None
+    }
+  }
+
+  fn unknown_instance(&self) -> WriteError<()> {
+    WriteError::UnknownInstance {
+      reason: format!("No such instance in topic {:?}", self.my_topic.name()),
+      data: (),
+    }
+  }
+
+  // Serializes the key for a dispose or unregister of an instance.
+  fn instance_change(
+    &self,
+    change_kind: ChangeKind,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(DDSData, WriteOptions), ()> {
     let send_buffer = SA::key_to_bytes(key).map_err(|e| WriteError::Serialization {
       reason: format!("{e}"),
       data: (),
     })?; // serialize key
 
     let ddsdata = DDSData::new_disposed_by_key(
-      ChangeKind::NotAliveDisposed,
+      change_kind,
       SerializedPayload::new_from_bytes(SA::output_encoding(), send_buffer),
     );
+    let write_options = Self::dispose_write_options(self.inline_key_hash(key), source_timestamp);
+    Ok((ddsdata, write_options))
+  }
+
+  fn dispose_write_options(
+    key_hash: Option<KeyHash>,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteOptions {
+    // Disposes are always timestamped, so that readers can tell when an
+    // instance was disposed.
+    let source_timestamp = source_timestamp.unwrap_or_else(Timestamp::now);
+    WriteOptions {
+      key_hash,
+      ..WriteOptions::from(Some(source_timestamp))
+    }
+  }
+
+  // Disposes and unregisters are subject to the same checks as data samples.
+  fn check_dispose(&self, ddsdata: &DDSData) -> WriteResult<(), ()> {
     self
-      .cc_upload
-      .send(WriterCommand::DDSData {
-        ddsdata,
-        write_options: WriteOptions::from(source_timestamp),
-        sequence_number: self.next_sequence_number(),
-      })
-      .map_err(|e| {
-        self.undo_sequence_number();
-        WriteError::Serialization {
-          reason: format!("{e}"),
+      .check_participant()
+      .map_err(|reason| WriteError::Poisoned { reason, data: () })?;
+    if self.check_memory_limit(ddsdata) {
+      Ok(())
+    } else {
+      Err(WriteError::WouldBlock { data: () })
+    }
+  }
+
+  fn send_dispose(&self, ddsdata: DDSData, write_options: WriteOptions) -> WriteResult<(), ()> {
+    self.check_dispose(&ddsdata)?;
+    self
+      .enqueue_blocking(ddsdata, self.with_source_timestamp(write_options))
+      .map_err(|e| match e {
+        TrySendError::Full(()) => WriteError::Timeout { data: () },
+        TrySendError::Disconnected(()) => WriteError::Poisoned {
+          reason: "Cannot send to Writer".to_string(),
           data: (),
-        }
+        },
+        TrySendError::Io(e) => e.into(),
       })?;
 
     self.refresh_manual_liveliness();
@@ -938,83 +1979,115 @@ where
 // async writing implementation
 //
 
-// A future for an asynchronous write operation
+// A future for an asynchronous write, dispose, or unregister operation.
+//
+// The sample is queued to the Writer within a single poll, or not at all, so
+// dropping the future before completion abandons the write cleanly: no sequence
+// number is consumed and nothing is left in the queue.
+//
+// T is what is given back on failure: the sample for writes, nothing for
+// disposes and unregisters.
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-pub struct AsyncWrite<'a, D, SA>
+pub struct AsyncWrite<'a, D, SA, T = D>
 where
   D: Keyed,
   SA: SerializerAdapter<D>,
 {
   writer: &'a DataWriter<D, SA>,
-  writer_command: Option<WriterCommand>,
-  sequence_number: SequenceNumber,
-  timeout: Option<duration::Duration>,
-  timeout_instant: Instant,
-  sample: Option<D>,
+  // DDSData and WriteOptions are given to the Writer in a WriterCommand,
+  // once there is room in the queue.
+  pending: Option<(DDSData, WriteOptions)>,
+  timeout: Duration,
+  // None means that the deadline is too far in the future to represent.
+  deadline: Option<Instant>,
+  // Wakes the task at the deadline, if the Writer has not made room before
+  // that.
+  deadline_alarm: Option<Alarm>,
+  // Instance to remember as known, once queued. None if already known.
+  instance: Option<<D as Keyed>::K>,
+  sample: Option<T>,
 }
 
 // This is required, because AsyncWrite contains "D".
 // TODO: Is it ok to promise Unpin here?
-impl<D, SA> Unpin for AsyncWrite<'_, D, SA>
+impl<D, SA, T> Unpin for AsyncWrite<'_, D, SA, T>
+where
+  D: Keyed,
+  SA: SerializerAdapter<D>,
+{
+}
+
+impl<D, SA, T> AsyncWrite<'_, D, SA, T>
 where
   D: Keyed,
   SA: SerializerAdapter<D>,
 {
+  fn set_deadline_alarm(&mut self, waker: &Waker) {
+    match (&self.deadline_alarm, self.deadline) {
+      (Some(alarm), _) => alarm.set_waker(waker),
+      (None, Some(deadline)) => self.deadline_alarm = Some(Alarm::new(deadline, waker)),
+      (None, None) => (), // no deadline, no alarm
+    }
+  }
 }
 
-impl<D, SA> Future for AsyncWrite<'_, D, SA>
+impl<D, SA, T> Future for AsyncWrite<'_, D, SA, T>
 where
   D: Keyed,
   SA: SerializerAdapter<D>,
 {
-  type Output = WriteResult<SampleIdentity, D>;
+  type Output = WriteResult<SampleIdentity, T>;
 
   fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-    match self.writer_command.take() {
-      Some(wc) => {
-        match self.writer.cc_upload.try_send(wc) {
-          Ok(()) => {
-            self.writer.refresh_manual_liveliness();
-            Poll::Ready(Ok(SampleIdentity {
-              writer_guid: self.writer.my_guid,
-              sequence_number: self.sequence_number,
-            }))
-          }
-          Err(TrySendError::Full(wc)) => {
-            *self.writer.cc_upload_waker.lock().unwrap() = Some(cx.waker().clone());
-            if Instant::now() < self.timeout_instant {
-              // Put our command back
-              self.writer_command = Some(wc);
-              Poll::Pending
-            } else {
-              // TODO: unwrap
-              Poll::Ready(Err(WriteError::WouldBlock {
-                data: self.sample.take().unwrap(),
-              }))
-            }
-          }
-          Err(other_err) => {
-            warn!(
-              "Failed to write new data: topic={:?}  reason={:?}  timeout={:?}",
-              self.writer.my_topic.name(),
-              other_err,
-              self.timeout
-            );
-            // TODO: Is this (undo) the right thing to do, if there are
-            // several futures in progress? (Can this result in confused numbering?)
-            self.writer.undo_sequence_number();
-            Poll::Ready(Err(WriteError::Poisoned {
-              reason: format!("{other_err}"),
-              data: self.sample.take().unwrap(),
-            }))
-          }
-        }
-      }
+    let (ddsdata, write_options) = match self.pending.take() {
+      Some(p) => p,
       None => {
         // the dog ate my homework
-        // this should not happen
-        Poll::Ready(Err(WriteError::Internal {
+        // this should not happen, unless polled after completion
+        return Poll::Ready(Err(WriteError::Internal {
           reason: "someone stole my WriterCommand".to_owned(),
+        }));
+      }
+    };
+    match self.writer.try_enqueue(ddsdata, write_options, cx.waker()) {
+      Ok(sequence_number) => {
+        if let Some(key) = &self.instance {
+          self.writer.remember_instance(key);
+        }
+        self.writer.refresh_manual_liveliness();
+        Poll::Ready(Ok(SampleIdentity {
+          writer_guid: self.writer.my_guid,
+          sequence_number,
+        }))
+      }
+      Err(TrySendError::Full(sample)) => {
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+          warn!(
+            "Write timed out: topic={:?}  timeout={:?}",
+            self.writer.my_topic.name(),
+            self.timeout,
+          );
+          // TODO: unwrap
+          Poll::Ready(Err(WriteError::Timeout {
+            data: self.sample.take().unwrap(),
+          }))
+        } else {
+          // Put our sample back
+          self.pending = Some(sample);
+          self.set_deadline_alarm(cx.waker());
+          Poll::Pending
+        }
+      }
+      Err(other_err) => {
+        warn!(
+          "Failed to write new data: topic={:?}  reason={:?}  timeout={:?}",
+          self.writer.my_topic.name(),
+          other_err,
+          self.timeout
+        );
+        Poll::Ready(Err(WriteError::Poisoned {
+          reason: format!("{other_err}"),
+          data: self.sample.take().unwrap(),
         }))
       }
     }
@@ -1131,6 +2204,10 @@ where
   D: Keyed,
   SA: SerializerAdapter<D>,
 {
+  /// Async version of [`write`](Self::write).
+  ///
+  /// Resolves to [`WriteError::Timeout`] like the synchronous version. If the
+  /// future is dropped before it resolves, the sample is not written.
   pub async fn async_write(
     &self,
     data: D,
@@ -1152,6 +2229,12 @@ where
   ) -> WriteResult<SampleIdentity, D> {
     // Construct a future for an async write operation and await for its completion
 
+    if let Err(reason) = self.check_participant() {
+      return Err(WriteError::Poisoned { reason, data });
+    }
+    if !self.has_required_readers() {
+      return Err(WriteError::NoMatchedReaders { data });
+    }
     let send_buffer = match SA::to_bytes(&data) {
       Ok(s) => s,
       Err(e) => {
@@ -1162,31 +2245,109 @@ where
       }
     };
 
-    let dds_data = DDSData::new(SerializedPayload::new_from_bytes(
+    let payload = match self.compress_payload(SerializedPayload::new_from_bytes(
       SA::output_encoding(),
       send_buffer,
-    ));
-    let sequence_number = self.next_sequence_number();
-    let writer_command = WriterCommand::DDSData {
-      ddsdata: dds_data,
-      write_options,
-      sequence_number,
+    )) {
+      Ok(p) => p,
+      Err(reason) => return Err(WriteError::Serialization { reason, data }),
     };
+    let dds_data = DDSData::new(payload);
+    if !self.check_memory_limit(&dds_data) {
+      return Err(WriteError::WouldBlock { data });
+    }
 
-    let timeout = self.qos().reliable_max_blocking_time();
+    let write_options = self.with_source_timestamp(self.with_inline_key_hash(write_options, &data));
+    let timeout = self.max_blocking_time();
+    let write_future = AsyncWrite {
+      writer: self,
+      pending: Some((dds_data, write_options)),
+      timeout,
+      deadline: Instant::now().checked_add(timeout),
+      deadline_alarm: None,
+      instance: (!self.keyless).then(|| data.key()),
+      sample: Some(data),
+    };
+    write_future.await
+  }
+
+  /// Async version of [`dispose`](Self::dispose).
+  ///
+  /// Like [`async_write`](Self::async_write), this resolves when the dispose
+  /// is queued to the Writer, or with [`WriteError::Timeout`] after
+  /// `max_blocking_time` if the Writer history stays full. If the future is
+  /// dropped before it resolves, nothing is sent.
+  pub async fn async_dispose(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    let (ddsdata, write_options) =
+      self.instance_change(ChangeKind::NotAliveDisposed, key, source_timestamp)?;
+    self
+      .async_send_dispose(ddsdata, write_options, Some(key.clone()))
+      .await
+  }
+
+  /// Async version of [`dispose_and_unregister`](Self::dispose_and_unregister).
+  ///
+  /// Resolves like [`async_dispose`](Self::async_dispose).
+  pub async fn async_dispose_and_unregister(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    let (ddsdata, write_options) = self.instance_change(
+      ChangeKind::NotAliveDisposedUnregistered,
+      key,
+      source_timestamp,
+    )?;
+    self
+      .async_send_dispose(ddsdata, write_options, None)
+      .await?;
+    self.forget_instance(key);
+    Ok(())
+  }
+
+  /// Async version of [`unregister_instance`](Self::unregister_instance).
+  ///
+  /// Resolves like [`async_dispose`](Self::async_dispose).
+  pub async fn async_unregister_instance(
+    &self,
+    key: &<D as Keyed>::K,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    // Forgotten only once sent, as the future may be dropped before that
+    if self.lookup_instance(key).is_none() {
+      return Err(self.unknown_instance());
+    }
+    let (ddsdata, write_options) =
+      self.instance_change(ChangeKind::NotAliveUnregistered, key, source_timestamp)?;
+    self
+      .async_send_dispose(ddsdata, write_options, None)
+      .await?;
+    self.forget_instance(key);
+    Ok(())
+  }
 
-    let write_future = AsyncWrite {
+  async fn async_send_dispose(
+    &self,
+    ddsdata: DDSData,
+    write_options: WriteOptions,
+    instance: Option<<D as Keyed>::K>,
+  ) -> WriteResult<(), ()> {
+    self.check_dispose(&ddsdata)?;
+    let timeout = self.max_blocking_time();
+    let dispose_future: AsyncWrite<'_, D, SA, ()> = AsyncWrite {
       writer: self,
-      writer_command: Some(writer_command),
-      sequence_number,
+      pending: Some((ddsdata, self.with_source_timestamp(write_options))),
       timeout,
-      timeout_instant: std::time::Instant::now()
-        + timeout
-          .map(|t| t.to_std())
-          .unwrap_or(crate::dds::helpers::TIMEOUT_FALLBACK.to_std()),
-      sample: Some(data),
+      deadline: Instant::now().checked_add(timeout),
+      deadline_alarm: None,
+      instance,
+      sample: Some(()),
     };
-    write_future.await
+    dispose_future.await.map(|_sample_identity| ())
   }
 
   /// Like the synchronous version.
@@ -1210,6 +2371,64 @@ where
       }
     }
   }
+
+  /// Returns a future that completes when all samples written so far have been
+  /// handed over to the network.
+  ///
+  /// Writing only queues the sample to the RTPS Writer, which sends it out in
+  /// the background. Await this before dropping the DataWriter or the
+  /// DomainParticipant, so that the last samples are not lost in the queue.
+  ///
+  /// This does not wait for Readers to receive or acknowledge the samples.
+  /// Use [`async_wait_for_acknowledgments`](Self::async_wait_for_acknowledgments)
+  /// for that. If the Publisher is not in push mode, samples are counted as
+  /// sent when they are stored to the history cache.
+  ///
+  /// The future also completes if the RTPS Writer is gone, e.g. the
+  /// DomainParticipant was dropped, as the queue will then never be sent.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataWriter;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize, Debug)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// futures::executor::block_on(async {
+  ///   data_writer.async_write(SomeType { a: 1 }, None).await.unwrap();
+  ///   data_writer.drain().await;
+  /// });
+  /// drop(data_writer);
+  /// drop(domain_participant);
+  /// ```
+  pub fn drain(&self) -> impl Future<Output = ()> {
+    let send_queue = Arc::clone(&self.send_queue);
+    future::poll_fn(move |cx| {
+      if send_queue.wake_when_empty(cx.waker()) {
+        Poll::Ready(())
+      } else {
+        Poll::Pending
+      }
+    })
+  }
 } // impl
 
 #[cfg(test)]
@@ -1221,8 +2440,21 @@ mod tests {
 
   use super::*;
   use crate::{
-    dds::{key::Key, participant::DomainParticipant},
-    structure::topic_kind::TopicKind,
+    dds::{
+      key::Key,
+      participant::{DomainParticipant, DomainParticipantBuilder},
+      qos::{
+        policy::{History, ResourceLimits},
+        QosPolicyBuilder,
+      },
+    },
+    messages::submessages::submessages::{AckNack, AckSubmessage},
+    network::udp_sender::UDPSender,
+    rtps::{
+      rtps_reader_proxy::RtpsReaderProxy,
+      writer::{Writer, WriterIngredients},
+    },
+    structure::{guid::EntityKind, sequence_number::SequenceNumberSet, topic_kind::TopicKind},
     test::random_data::*,
   };
 
@@ -1267,6 +2499,45 @@ mod tests {
     // TODO: write also with timestamp
   }
 
+  #[test]
+  fn dw_write_fails_over_memory_limit() {
+    let domain_participant = DomainParticipantBuilder::new(0)
+      .memory_limit(1)
+      .build()
+      .expect("Participant creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "Aasii".to_string(),
+        "Huh?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher
+        .create_datawriter(&topic, None)
+        .expect("Failed to create datawriter");
+
+    let data = RandomData {
+      a: 4,
+      b: "Fobar".to_string(),
+    };
+
+    match data_writer.write(data.clone(), None) {
+      Err(WriteError::WouldBlock { data: returned }) => assert_eq!(returned, data),
+      other => panic!("Expected WouldBlock, got {other:?}"),
+    }
+    assert!(matches!(
+      data_writer.dispose(&4, None),
+      Err(WriteError::WouldBlock { .. })
+    ));
+  }
+
   #[test]
   fn dw_dispose_test() {
     let domain_participant = DomainParticipant::new(0).expect("Publisher creation failed!");
@@ -1342,4 +2613,717 @@ mod tests {
     assert!(res); // we should get "true" immediately, because we have
                   // no Reliable QoS
   }
+
+  // DataWriter with a queue of one sample to a Writer that does not take
+  // anything from the queue, unless the test does so.
+  fn dw_with_blocked_writer(
+    domain_participant: &DomainParticipant,
+    max_blocking_time: Duration,
+  ) -> (
+    DataWriter<RandomData>,
+    mio_channel::Receiver<WriterCommand>,
+    Arc<Mutex<Option<Waker>>>,
+  ) {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: max_blocking_time.into(),
+      })
+      .history(History::KeepAll)
+      .build();
+    let publisher = domain_participant.create_publisher(&qos).unwrap();
+    let topic = domain_participant
+      .create_topic(
+        "blocked".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let (cc_upload, cc_download) = mio_channel::sync_channel::<WriterCommand>(1);
+    let cc_upload_waker = Arc::new(Mutex::new(None));
+    let (discovery_command, _discovery_receiver) = mio_channel::sync_channel(16);
+    let (_status_sender, status_receiver) = sync_status_channel(4).unwrap();
+    let data_writer = DataWriter::new(
+      publisher,
+      topic,
+      qos,
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      cc_upload,
+      Arc::clone(&cc_upload_waker),
+      discovery_command,
+      status_receiver,
+      Arc::default(),
+      Arc::default(),
+      Arc::new(MemoryBudget::new(None)),
+      Arc::default(),
+      domain_participant.always_include_source_timestamp(),
+      None,
+      Arc::default(),
+      Arc::default(),
+      Arc::default(),
+      Arc::default(),
+    )
+    .unwrap();
+    (data_writer, cc_download, cc_upload_waker)
+  }
+
+  // DataWriter connected to an RTPS Writer that the test drives. The history is
+  // KeepAll with room for `max_samples`, and the only matched Reader is
+  // Reliable and acknowledges only when the test calls `ack`.
+  fn dw_with_writer(
+    domain_participant: &DomainParticipant,
+    max_blocking_time: Duration,
+    max_samples: i32,
+  ) -> (DataWriter<RandomData>, Writer, GUID) {
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: max_blocking_time.into(),
+      })
+      .history(History::KeepAll)
+      .resource_limits(ResourceLimits {
+        max_samples,
+        max_instances: -1,
+        max_samples_per_instance: -1,
+      })
+      .build();
+    let publisher = domain_participant.create_publisher(&qos).unwrap();
+    let topic = domain_participant
+      .create_topic(
+        "full history".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let (mut writer_ing, ends) = WriterIngredients::for_test(guid, "full history", qos.clone());
+    writer_ing.history_space = Arc::new(HistorySpace::for_qos(&qos));
+    let (discovery_command, _discovery_receiver) = mio_channel::sync_channel(16);
+    let data_writer = DataWriter::new(
+      publisher,
+      topic,
+      qos.clone(),
+      guid,
+      ends.command_sender,
+      Arc::clone(&writer_ing.writer_command_receiver_waker),
+      discovery_command,
+      ends.status_receiver,
+      Arc::clone(&writer_ing.publication_matched),
+      Arc::clone(&writer_ing.offered_incompatible_qos),
+      Arc::clone(&writer_ing.memory_budget),
+      Arc::default(),
+      false,
+      None,
+      Arc::clone(&writer_ing.compress_payloads),
+      Arc::clone(&writer_ing.send_key_hash),
+      Arc::clone(&writer_ing.send_queue),
+      Arc::clone(&writer_ing.history_space),
+    )
+    .unwrap();
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    writer.update_reader_proxy(&RtpsReaderProxy::new(reader_guid, qos.clone(), false), &qos);
+    (data_writer, writer, reader_guid)
+  }
+
+  // The Reader acknowledges everything before `acked_before`.
+  fn ack(writer: &mut Writer, reader_guid: GUID, acked_before: i64) {
+    let ack_nack = AckSubmessage::AckNack(AckNack {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer.entity_id(),
+      reader_sn_state: SequenceNumberSet::new_empty(SequenceNumber::new(acked_before)),
+      count: acked_before as i32,
+    });
+    writer.handle_ack_nack(reader_guid.prefix, &ack_nack);
+  }
+
+  // Take one command from the queue, like the Writer does.
+  fn take_queued_sn(
+    cc_download: &mio_channel::Receiver<WriterCommand>,
+    cc_upload_waker: &Mutex<Option<Waker>>,
+  ) -> Option<SequenceNumber> {
+    let sn = match cc_download.try_recv().ok()? {
+      WriterCommand::DDSData {
+        sequence_number, ..
+      } => sequence_number,
+      _ => panic!("Unexpected WriterCommand"),
+    };
+    if let Some(w) = cc_upload_waker.lock().unwrap().as_ref() {
+      w.wake_by_ref();
+    }
+    Some(sn)
+  }
+
+  #[test]
+  fn dw_always_include_source_timestamp() {
+    let domain_participant = DomainParticipantBuilder::new(0)
+      .always_include_source_timestamp(true)
+      .build()
+      .unwrap();
+    let (data_writer, cc_download, _cc_upload_waker) =
+      dw_with_blocked_writer(&domain_participant, Duration::from_millis(10));
+    let queued_source_timestamp = || match cc_download.try_recv().unwrap() {
+      WriterCommand::DDSData { write_options, .. } => write_options.source_timestamp(),
+      _ => panic!("Unexpected WriterCommand"),
+    };
+    let data = RandomData {
+      a: 1,
+      b: "stamped".to_string(),
+    };
+
+    let before = Timestamp::now();
+    data_writer.write(data.clone(), None).unwrap();
+    let stamped = queued_source_timestamp().expect("No source timestamp");
+    assert!(stamped >= before && stamped <= Timestamp::now());
+
+    // An explicit timestamp is kept as is
+    let explicit = Timestamp::ZERO;
+    data_writer.write(data, Some(explicit)).unwrap();
+    assert_eq!(queued_source_timestamp(), Some(explicit));
+  }
+
+  #[test]
+  fn dw_async_always_include_source_timestamp() {
+    let domain_participant = DomainParticipantBuilder::new(0)
+      .always_include_source_timestamp(true)
+      .build()
+      .unwrap();
+    let (data_writer, cc_download, _cc_upload_waker) =
+      dw_with_blocked_writer(&domain_participant, Duration::from_millis(10));
+    let queued_source_timestamp = || match cc_download.try_recv().unwrap() {
+      WriterCommand::DDSData { write_options, .. } => write_options.source_timestamp(),
+      _ => panic!("Unexpected WriterCommand"),
+    };
+    let data = RandomData {
+      a: 1,
+      b: "stamped".to_string(),
+    };
+
+    let before = Timestamp::now();
+    futures::executor::block_on(data_writer.async_write(data.clone(), None)).unwrap();
+    let stamped = queued_source_timestamp().expect("No source timestamp");
+    assert!(stamped >= before && stamped <= Timestamp::now());
+
+    futures::executor::block_on(data_writer.async_dispose(&data.key(), None)).unwrap();
+    assert!(queued_source_timestamp().is_some());
+
+    // An explicit timestamp is kept as is
+    let explicit = Timestamp::ZERO;
+    futures::executor::block_on(data_writer.async_write(data, Some(explicit))).unwrap();
+    assert_eq!(queued_source_timestamp(), Some(explicit));
+  }
+
+  #[test]
+  fn dw_write_blocks_on_full_history() {
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let max_blocking_time = Duration::from_millis(100);
+    let (data_writer, mut writer, reader_guid) =
+      dw_with_writer(&domain_participant, max_blocking_time, 2);
+    let data = |a| RandomData {
+      a,
+      b: "blocked".to_string(),
+    };
+
+    data_writer.write(data(1), None).unwrap();
+    data_writer.write(data(2), None).unwrap();
+    writer.process_writer_command();
+
+    // History is full of unacknowledged samples
+    let start = Instant::now();
+    match data_writer.write(data(3), None) {
+      Err(WriteError::Timeout { data: d }) => assert_eq!(d, data(3)),
+      other => panic!("Expected timeout, got {other:?}"),
+    }
+    assert!(start.elapsed() >= max_blocking_time);
+
+    // The Reader acknowledges while the write is blocking, so it succeeds. The
+    // timed out write did not consume a sequence number.
+    let start = Instant::now();
+    let sample_id = thread::scope(|scope| {
+      let blocked =
+        scope.spawn(|| data_writer.write_with_options(data(3), WriteOptions::default()));
+      thread::sleep(Duration::from_millis(20));
+      ack(&mut writer, reader_guid, 2);
+      blocked.join().unwrap()
+    });
+    assert_eq!(sample_id.unwrap().sequence_number, SequenceNumber::new(3));
+    assert!(start.elapsed() < max_blocking_time);
+    writer.process_writer_command();
+
+    // Samples 2 and 3 are not acknowledged yet
+    assert!(matches!(
+      data_writer.write(data(4), None),
+      Err(WriteError::Timeout { .. })
+    ));
+    ack(&mut writer, reader_guid, 4);
+    data_writer.write(data(4), None).unwrap();
+    data_writer.write(data(5), None).unwrap();
+    writer.process_writer_command();
+
+    // Without Reliable Readers, nothing is kept for acknowledgement
+    writer.reader_lost(reader_guid);
+    data_writer.write(data(6), None).unwrap();
+    data_writer.write(data(7), None).unwrap();
+  }
+
+  #[test]
+  fn dw_drain_waits_for_writer_to_process_queue() {
+    use futures::FutureExt;
+
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let (data_writer, mut writer, _reader_guid) =
+      dw_with_writer(&domain_participant, Duration::from_millis(10), -1);
+    let data = |a| RandomData {
+      a,
+      b: "drain".to_string(),
+    };
+
+    // Nothing queued yet
+    assert_eq!(data_writer.drain().now_or_never(), Some(()));
+
+    data_writer.write(data(1), None).unwrap();
+    data_writer.write(data(2), None).unwrap();
+    let mut drain = Box::pin(data_writer.drain());
+    assert_eq!(drain.as_mut().now_or_never(), None);
+
+    writer.process_writer_command();
+    assert_eq!(drain.now_or_never(), Some(()));
+  }
+
+  #[test]
+  fn dw_drain_completes_when_writer_is_gone() {
+    use futures::FutureExt;
+
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let (data_writer, writer, _reader_guid) =
+      dw_with_writer(&domain_participant, Duration::from_millis(10), -1);
+    let data = |a| RandomData {
+      a,
+      b: "drain".to_string(),
+    };
+
+    data_writer.write(data(1), None).unwrap();
+    let mut drain = Box::pin(data_writer.drain());
+    assert_eq!(drain.as_mut().now_or_never(), None);
+
+    // The queued sample is never processed
+    drop(writer);
+    assert_eq!(drain.now_or_never(), Some(()));
+    assert!(data_writer.write(data(2), None).is_err());
+    assert_eq!(data_writer.drain().now_or_never(), Some(()));
+  }
+
+  #[test]
+  fn dw_async_write_times_out_and_cancels_cleanly() {
+    use futures::{
+      task::{waker, ArcWake},
+      FutureExt,
+    };
+
+    struct WakeFlag(AtomicBool);
+    impl ArcWake for WakeFlag {
+      fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::Release);
+      }
+    }
+
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let max_blocking_time = Duration::from_millis(100);
+    let (data_writer, mut writer, reader_guid) =
+      dw_with_writer(&domain_participant, max_blocking_time, 1);
+    let data = |a| RandomData {
+      a,
+      b: "blocked".to_string(),
+    };
+
+    futures::executor::block_on(data_writer.async_write(data(1), None)).unwrap();
+    writer.process_writer_command();
+
+    // Nothing wakes the task except the deadline
+    let start = Instant::now();
+    match futures::executor::block_on(data_writer.async_write(data(2), None)) {
+      Err(WriteError::Timeout { data: d }) => assert_eq!(d, data(2)),
+      other => panic!("Expected timeout, got {other:?}"),
+    }
+    assert!(start.elapsed() >= max_blocking_time);
+
+    // Polled once and dropped while waiting for room
+    assert!(data_writer
+      .async_write(data(3), None)
+      .now_or_never()
+      .is_none());
+
+    // Acknowledgement wakes a waiting write
+    let wake_flag = Arc::new(WakeFlag(AtomicBool::new(false)));
+    let flag_waker = waker(Arc::clone(&wake_flag));
+    let mut write =
+      Box::pin(data_writer.async_write_with_options(data(4), WriteOptions::default()));
+    assert!(write
+      .as_mut()
+      .poll(&mut Context::from_waker(&flag_waker))
+      .is_pending());
+    ack(&mut writer, reader_guid, 2);
+    assert!(wake_flag.0.load(Ordering::Acquire));
+    // The cancelled writes consumed no sequence number and left no room taken.
+    match write.now_or_never() {
+      Some(Ok(sample_id)) => assert_eq!(sample_id.sequence_number, SequenceNumber::new(2)),
+      other => panic!("Expected a write, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn dw_async_dispose_and_unregister_with_full_queue() {
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let max_blocking_time = Duration::from_millis(100);
+    let (data_writer, cc_download, cc_upload_waker) =
+      dw_with_blocked_writer(&domain_participant, max_blocking_time);
+    let data = |a| RandomData {
+      a,
+      b: "blocked".to_string(),
+    };
+
+    // Not written yet
+    assert!(matches!(
+      futures::executor::block_on(data_writer.async_unregister_instance(&1, None)),
+      Err(WriteError::UnknownInstance { .. })
+    ));
+
+    // Writes, disposes, and unregisters each wait for room in the queue, and
+    // get consecutive sequence numbers.
+    let instances = 1..=20;
+    let (queued, cc_download) = thread::scope(|scope| {
+      // Receiver is not Sync, so it is moved to the other thread and back.
+      let cc_upload_waker = &cc_upload_waker;
+      let count = 3 * instances.clone().count();
+      let taker = scope.spawn(move || {
+        let mut queued = Vec::new();
+        while queued.len() < count {
+          match cc_download.try_recv() {
+            Ok(WriterCommand::DDSData {
+              ddsdata,
+              sequence_number,
+              ..
+            }) => queued.push((sequence_number, ddsdata.change_kind())),
+            Ok(_) => panic!("Unexpected WriterCommand"),
+            Err(_) => thread::sleep(Duration::from_millis(1)),
+          }
+          if let Some(w) = cc_upload_waker.lock().unwrap().as_ref() {
+            w.wake_by_ref();
+          }
+        }
+        (queued, cc_download)
+      });
+      futures::executor::block_on(async {
+        for a in instances.clone() {
+          data_writer.async_write(data(a), None).await.unwrap();
+          data_writer.async_dispose(&a, None).await.unwrap();
+          data_writer
+            .async_unregister_instance(&a, None)
+            .await
+            .unwrap();
+        }
+      });
+      taker.join().unwrap()
+    });
+    let expected: Vec<_> = [
+      ChangeKind::Alive,
+      ChangeKind::NotAliveDisposed,
+      ChangeKind::NotAliveUnregistered,
+    ]
+    .into_iter()
+    .cycle()
+    .zip(1..)
+    .take(queued.len())
+    .map(|(kind, sn)| (SequenceNumber::new(sn), kind))
+    .collect();
+    assert_eq!(queued, expected);
+
+    // Dispose of an instance never written. It becomes known.
+    futures::executor::block_on(data_writer.async_dispose(&100, None)).unwrap();
+    assert!(data_writer.lookup_instance(&100).is_some());
+    assert!(take_queued_sn(&cc_download, &cc_upload_waker).is_some());
+  }
+
+  #[test]
+  fn dw_async_dispose_and_unregister_with_full_history() {
+    use futures::FutureExt;
+
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let max_blocking_time = Duration::from_millis(100);
+    let (data_writer, mut writer, reader_guid) =
+      dw_with_writer(&domain_participant, max_blocking_time, 1);
+    let data = |a| RandomData {
+      a,
+      b: "blocked".to_string(),
+    };
+
+    futures::executor::block_on(data_writer.async_write(data(1), None)).unwrap();
+    writer.process_writer_command();
+    ack(&mut writer, reader_guid, 2);
+    // Fills the history
+    futures::executor::block_on(data_writer.async_dispose(&100, None)).unwrap();
+    writer.process_writer_command();
+
+    // History stays full
+    let start = Instant::now();
+    assert!(matches!(
+      futures::executor::block_on(data_writer.async_dispose(&1, None)),
+      Err(WriteError::Timeout { .. })
+    ));
+    assert!(start.elapsed() >= max_blocking_time);
+
+    // Polled once and dropped while waiting for room
+    assert!(data_writer
+      .async_unregister_instance(&1, None)
+      .now_or_never()
+      .is_none());
+
+    // Synchronous unregister uses the next sequence number
+    ack(&mut writer, reader_guid, 3);
+    data_writer.unregister_instance(&1, None).unwrap();
+    writer.process_writer_command();
+    ack(&mut writer, reader_guid, 4);
+    let sample_id = data_writer
+      .write_with_options(data(2), WriteOptions::default())
+      .unwrap();
+    assert_eq!(sample_id.sequence_number, SequenceNumber::new(4));
+  }
+
+  #[test]
+  fn dw_unregister_forgets_instance() {
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let (data_writer, mut writer, _reader_guid) =
+      dw_with_writer(&domain_participant, Duration::from_millis(10), -1);
+    let data = |a| RandomData {
+      a,
+      b: "unregister".to_string(),
+    };
+    let is_unknown =
+      |result: WriteResult<(), ()>| matches!(result, Err(WriteError::UnknownInstance { .. }));
+
+    data_writer.write(data(1), None).unwrap();
+    writer.process_writer_command();
+    data_writer.unregister_instance(&1, None).unwrap();
+    writer.process_writer_command();
+    assert_eq!(data_writer.lookup_instance(&1), None);
+    assert!(is_unknown(data_writer.unregister_instance(&1, None)));
+    // Neither form of the key hash is known anymore
+    for md5 in [false, true] {
+      assert!(is_unknown(data_writer.dispose_by_key_hash(
+        1.hash_key(md5),
+        false,
+        None
+      )));
+    }
+
+    data_writer.write(data(2), None).unwrap();
+    writer.process_writer_command();
+    futures::executor::block_on(data_writer.async_unregister_instance(&2, None)).unwrap();
+    writer.process_writer_command();
+    assert_eq!(data_writer.lookup_instance(&2), None);
+    assert!(is_unknown(futures::executor::block_on(
+      data_writer.async_unregister_instance(&2, None)
+    )));
+
+    // Written again after unregistering
+    data_writer.write(data(1), None).unwrap();
+    writer.process_writer_command();
+    data_writer.unregister_instance(&1, None).unwrap();
+    writer.process_writer_command();
+    assert!(data_writer.known_instances.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn dw_require_matched_readers() {
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let (data_writer, cc_download, _cc_upload_waker) =
+      dw_with_blocked_writer(&domain_participant, Duration::from_millis(10));
+    let data = RandomData {
+      a: 1,
+      b: "Foo".to_string(),
+    };
+
+    // Off by default
+    assert!(!data_writer.require_matched_readers());
+    data_writer.write(data.clone(), None).unwrap();
+    assert!(cc_download.try_recv().is_ok());
+
+    data_writer.set_require_matched_readers(true);
+    match data_writer.write(data.clone(), None) {
+      Err(WriteError::NoMatchedReaders { data: d }) => assert_eq!(d, data),
+      other => panic!("Expected NoMatchedReaders, got {other:?}"),
+    }
+    assert!(matches!(
+      futures::executor::block_on(data_writer.async_write(data.clone(), None)),
+      Err(WriteError::NoMatchedReaders { .. })
+    ));
+    assert!(cc_download.try_recv().is_err());
+    // Disposes are not affected
+    data_writer.dispose(&1, None).unwrap();
+    assert!(cc_download.try_recv().is_ok());
+
+    // The RTPS Writer reports a matched Reader
+    data_writer
+      .publication_matched
+      .lock()
+      .unwrap()
+      .reader_matched(GUID::dummy_test_guid(
+        EntityKind::READER_WITH_KEY_USER_DEFINED,
+      ));
+    data_writer.write(data, None).unwrap();
+    assert!(cc_download.try_recv().is_ok());
+  }
+
+  #[test]
+  fn dw_dispose_and_unregister() {
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let (data_writer, cc_download, _cc_upload_waker) =
+      dw_with_blocked_writer(&domain_participant, Duration::from_millis(10));
+    let take_queued = || match cc_download.try_recv().expect("Nothing was queued") {
+      WriterCommand::DDSData { ddsdata, .. } => ddsdata.change_kind(),
+      _ => panic!("Unexpected WriterCommand"),
+    };
+
+    // Works also for instances never written. Unregistered instances are not
+    // known.
+    data_writer.dispose_and_unregister(&7, None).unwrap();
+    assert_eq!(take_queued(), ChangeKind::NotAliveDisposedUnregistered);
+    assert!(data_writer.lookup_instance(&7).is_none());
+
+    futures::executor::block_on(data_writer.async_dispose_and_unregister(&7, None)).unwrap();
+    assert_eq!(take_queued(), ChangeKind::NotAliveDisposedUnregistered);
+  }
+
+  #[test]
+  fn dw_dispose_by_instance_handle_and_key_hash() {
+    let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
+    let (data_writer, cc_download, _cc_upload_waker) =
+      dw_with_blocked_writer(&domain_participant, Duration::from_millis(10));
+    let take_queued = || match cc_download.try_recv().expect("Nothing was queued") {
+      WriterCommand::DDSData { ddsdata, .. } => ddsdata,
+      _ => panic!("Unexpected WriterCommand"),
+    };
+
+    let data = RandomData {
+      a: 4,
+      b: "Fobar".to_string(),
+    };
+    assert_eq!(data_writer.lookup_instance(&4), None);
+    data_writer.write(data.clone(), None).unwrap();
+    take_queued();
+
+    let handle = data_writer.lookup_instance(&4).unwrap();
+    assert_eq!(handle.key_hash(), data.key().hash_key(false));
+    data_writer.dispose_instance(handle, None).unwrap();
+    assert_eq!(
+      take_queued(),
+      DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, handle.key_hash())
+    );
+
+    // The MD5 form of the key hash is recognized, too.
+    let md5_hash = data.key().hash_key(true);
+    data_writer
+      .dispose_by_key_hash(md5_hash, false, None)
+      .unwrap();
+    assert_eq!(
+      take_queued(),
+      DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, md5_hash)
+    );
+
+    // Never seen by this writer
+    let unknown_hash = KeyHash::from_bytes([0xab; 16]);
+    assert!(matches!(
+      data_writer.dispose_by_key_hash(unknown_hash, false, None),
+      Err(WriteError::UnknownInstance { .. })
+    ));
+    assert!(cc_download.try_recv().is_err());
+    data_writer
+      .dispose_by_key_hash(unknown_hash, true, None)
+      .unwrap();
+    assert_eq!(
+      take_queued(),
+      DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, unknown_hash)
+    );
+
+    // Registering sends nothing, but makes the instance known.
+    let other = RandomData {
+      a: 5,
+      b: "Baz".to_string(),
+    };
+    let other_handle = data_writer.register_instance(&other);
+    assert_eq!(data_writer.lookup_instance(&5), Some(other_handle));
+    assert!(cc_download.try_recv().is_err());
+  }
+
+  #[test]
+  fn dw_history_cache_status() {
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new().history(History::KeepAll).build();
+    let publisher = domain_participant.create_publisher(&qos).unwrap();
+    let topic = domain_participant
+      .create_topic(
+        "history_cache_status".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher.create_datawriter(&topic, None).unwrap();
+
+    let status = data_writer.history_cache_status().unwrap();
+    assert_eq!(status.depth, 0);
+    assert_eq!(status.oldest_sequence_number, SequenceNumber::new(1));
+
+    for a in 0..3 {
+      let data = RandomData {
+        a,
+        b: "Fobar".to_string(),
+      };
+      data_writer.write(data, None).unwrap();
+    }
+    // The query is handled after the queued samples.
+    let status = data_writer.history_cache_status().unwrap();
+    assert_eq!(status.depth, 3);
+    assert_eq!(status.oldest_sequence_number, SequenceNumber::new(1));
+  }
+
+  #[test]
+  fn dw_periodic_liveliness_assertion() {
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new()
+      .liveliness(Liveliness::ManualByTopic {
+        lease_duration: crate::Duration::from_millis(200),
+      })
+      .build();
+    let publisher = domain_participant.create_publisher(&qos).unwrap();
+    let topic = domain_participant
+      .create_topic(
+        "dw_periodic_liveliness_assertion".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher.create_datawriter(&topic, None).unwrap();
+
+    let cancelled = data_writer.assert_liveliness_periodic(Duration::from_millis(20));
+    let until_dropped = data_writer.assert_liveliness_periodic(Duration::from_millis(20));
+    thread::sleep(Duration::from_millis(100));
+    assert!(!cancelled.is_finished());
+    cancelled.cancel();
+
+    assert!(!until_dropped.is_finished());
+    drop(data_writer);
+    thread::sleep(Duration::from_millis(100));
+    assert!(until_dropped.is_finished());
+  }
 }
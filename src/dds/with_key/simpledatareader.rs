@@ -3,9 +3,12 @@ use std::{
   collections::BTreeMap,
   io,
   marker::PhantomData,
+  ops::Range,
   pin::Pin,
   sync::{Arc, Mutex, MutexGuard},
   task::{Context, Poll, Waker},
+  thread,
+  time::Instant,
 };
 
 use futures::stream::{FusedStream, Stream};
@@ -18,20 +21,27 @@ use crate::{
   dds::{
     adapters::with_key::{Decode, DefaultDecoder, DeserializerAdapter},
     ddsdata::*,
+    helpers::ThreadWaker,
     key::*,
     pubsub::Subscriber,
     qos::*,
     result::*,
+    sampleinfo::SampleFlag,
     statusevents::*,
-    topic::{Topic, TopicDescription},
+    topic::{Topic, TopicDescription, TopicKind},
     with_key::datasample::{DeserializedCacheChange, Sample},
   },
-  discovery::discovery::DiscoveryCommand,
+  discovery::{discovery::DiscoveryCommand, sedp_messages::PublicationBuiltinTopicData},
   mio_source::PollEventSource,
-  serialization::CDRDeserializerAdapter,
+  rtps::observed_gaps::ObservedGaps,
+  serialization::{
+    compression::{self, PayloadCompressor},
+    CDRDeserializerAdapter,
+  },
   structure::{
     cache_change::CacheChange,
     dds_cache::TopicCache,
+    duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GUID},
     sequence_number::SequenceNumber,
@@ -43,6 +53,18 @@ use crate::{
 pub(crate) enum ReaderCommand {
   #[allow(dead_code)] // TODO: Implement this (resetting) feature
   ResetRequestedDeadlineStatus,
+  GetRemoteLocators {
+    reply: std::sync::mpsc::SyncSender<Vec<RemoteLocators>>,
+  },
+  GetDiagnostic {
+    reply: std::sync::mpsc::SyncSender<String>,
+  },
+  SetFlowControlPause {
+    paused: bool,
+  },
+  SetDeliveryPause {
+    paused: bool,
+  },
 }
 
 // This is helper struct.
@@ -86,6 +108,13 @@ impl<K: Key> ReadState<K> {
 
 /// SimpleDataReaders can only do "take" semantics and does not have
 /// any deduplication or other DataSampleCache functionality.
+///
+/// Received samples are stored in serialized form by the RTPS Reader, which
+/// runs in the DomainParticipant event loop thread. Deserialization is done
+/// only when samples are taken, in the calling thread, or in the task that
+/// polls the async stream. Expensive deserialization therefore does not delay
+/// the processing of other Readers, unless the application itself reads them
+/// from the same thread.
 pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializerAdapter<D>> {
   my_subscriber: Subscriber,
 
@@ -100,16 +129,30 @@ pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializ
   topic_cache: Arc<Mutex<TopicCache>>,
 
   read_state: Mutex<ReadState<<D as Keyed>::K>>,
+  // NO_KEY topic: the single key is mapped only once, not on every sample.
+  keyless: bool,
 
   deserializer_type: PhantomData<DA>, // This is to provide use for DA
 
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusChannelReceiver<DataReaderStatus>,
+  // For status events that originate from the DataReader side, e.g. cache
+  // resource limits.
+  status_sender: StatusChannelSender<DataReaderStatus>,
 
   #[allow(dead_code)] // TODO: This is currently unused, because we do not implement
   // resetting deadline missed status. Remove attribute when it is supported.
   reader_command: mio_channel::SyncSender<ReaderCommand>,
   data_reader_waker: Arc<Mutex<Option<Waker>>>,
+  // Updated by the RTPS Reader as it receives DATA and GAP
+  observed_gaps: Arc<Mutex<ObservedGaps>>,
+  // Updated by the RTPS Reader as Writers are matched and unmatched. This
+  // also caches descriptions of the matched Writers.
+  subscription_matched: Arc<Mutex<SubscriptionMatchedState>>,
+  // Updated by the RTPS Reader as Writers lose and regain liveliness
+  liveliness_changed: Arc<Mutex<LivelinessChangedStatus>>,
+  // For compressed payloads. These were advertised in Discovery.
+  payload_decompressors: Vec<Arc<dyn PayloadCompressor>>,
 
   event_source: PollEventSource,
 }
@@ -120,8 +163,10 @@ where
   DA: DeserializerAdapter<D>,
 {
   fn drop(&mut self) {
-    // Tell dp_event_loop
-    self.my_subscriber.remove_reader(self.my_guid);
+    // Tell dp_event_loop. A Reader still shared with other DataReaders stays.
+    if !self.my_subscriber.remove_reader(self.my_guid) {
+      return;
+    }
 
     // Tell discovery
     match self
@@ -153,8 +198,12 @@ where
     topic_cache: Arc<Mutex<TopicCache>>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_receiver: StatusChannelReceiver<DataReaderStatus>,
+    status_sender: StatusChannelSender<DataReaderStatus>,
     reader_command: mio_channel::SyncSender<ReaderCommand>,
     data_reader_waker: Arc<Mutex<Option<Waker>>>,
+    observed_gaps: Arc<Mutex<ObservedGaps>>,
+    subscription_matched: Arc<Mutex<SubscriptionMatchedState>>,
+    liveliness_changed: Arc<Mutex<LivelinessChangedStatus>>,
     event_source: PollEventSource,
   ) -> CreateResult<Self> {
     let dp = match subscriber.participant() {
@@ -180,6 +229,8 @@ where
       });
     }
 
+    let payload_decompressors = subscriber.payload_decompressors();
+
     Ok(Self {
       my_subscriber: subscriber,
       qos_policy,
@@ -187,12 +238,18 @@ where
       notification_receiver: Mutex::new(notification_receiver),
       topic_cache,
       read_state: Mutex::new(ReadState::new()),
+      keyless: topic.kind() == TopicKind::NoKey,
       my_topic: topic,
       deserializer_type: PhantomData,
       discovery_command,
       status_receiver,
+      status_sender,
       reader_command,
       data_reader_waker,
+      observed_gaps,
+      subscription_matched,
+      liveliness_changed,
+      payload_decompressors,
       event_source,
     })
   }
@@ -200,12 +257,87 @@ where
     *self.data_reader_waker.lock().unwrap() = w;
   }
 
+  pub(crate) fn send_status(&self, status: DataReaderStatus) {
+    self
+      .status_sender
+      .try_send(status)
+      .unwrap_or_else(|e| error!("send_status: Cannot send status to DataReader: {e:?}"));
+  }
+
+  // Next status event, with the time it was sent
+  pub(crate) fn poll_status_stamped(
+    &self,
+    cx: &mut Context<'_>,
+  ) -> Poll<Option<(Timestamp, DataReaderStatus)>> {
+    self.status_receiver.poll_recv_stamped(cx)
+  }
+
+  // QoS of a (matched) Writer, as known by Discovery
+  pub(crate) fn writer_qos(&self, writer: GUID) -> Option<QosPolicies> {
+    self.my_subscriber.writer_qos(writer)
+  }
+
+  /// Gets the Discovery data of one matched DataWriter.
+  ///
+  /// See [`DataReader::get_matched_publication_data`](crate::with_key::DataReader::get_matched_publication_data).
+  pub fn get_matched_publication_data(
+    &self,
+    writer_guid: GUID,
+  ) -> Option<PublicationBuiltinTopicData> {
+    if !self
+      .subscription_matched
+      .lock()
+      .unwrap()
+      .is_matched(writer_guid)
+    {
+      return None;
+    }
+    self.my_subscriber.writer_data(writer_guid)
+  }
+
+  /// Describes the DataWriter identified by a
+  /// [`SampleInfo::publication_handle`](crate::SampleInfo::publication_handle).
+  ///
+  /// See [`DataReader::publication_description`](crate::with_key::DataReader::publication_description).
+  pub fn publication_description(&self, publication_handle: GUID) -> Option<EndpointDescription> {
+    if let Some(description) = self
+      .subscription_matched
+      .lock()
+      .unwrap()
+      .writer_description(publication_handle)
+    {
+      return Some(description.clone());
+    }
+    // Discovery is not consulted while holding the lock, because the RTPS
+    // Reader needs it to update the matched Writers.
+    let description = self.my_subscriber.writer_description(publication_handle)?;
+    self
+      .subscription_matched
+      .lock()
+      .unwrap()
+      .remember_writer_description(publication_handle, description.clone());
+    Some(description)
+  }
+
   pub(crate) fn drain_read_notifications(&self) {
     let rec = self.notification_receiver.lock().unwrap();
     while rec.try_recv().is_ok() {}
     self.event_source.drain();
   }
 
+  /// Key of the instance identified by `handle`, e.g. the `last_instance` of
+  /// a [`DataReaderStatus::RequestedDeadlineMissed`]. The key is known only
+  /// after some sample or dispose of the instance has been taken.
+  pub fn instance_for_handle(&self, handle: InstanceHandle) -> Option<D::K> {
+    self
+      .read_state
+      .lock()
+      .unwrap()
+      .hash_to_key_map
+      .get(&handle.key_hash())
+      .cloned()
+  }
+
   fn try_take_undecoded<'a, 'b: 'a>(
     is_reliable: bool,
     topic_cache: &'a TopicCache,
@@ -215,15 +347,42 @@ where
     topic_cache.get_changes_in_range(is_reliable, latest_instant, last_read_sn)
   }
 
+  // Pointers must be given in the order the changes were read.
+  fn advance_read_pointers(
+    read_state: &mut ReadState<D::K>,
+    read: Vec<(Timestamp, GUID, SequenceNumber)>,
+  ) {
+    for (timestamp, writer_guid, sequence_number) in read {
+      read_state.latest_instant = max(read_state.latest_instant, timestamp);
+      read_state.last_read_sn.insert(writer_guid, sequence_number);
+    }
+  }
+
   fn update_hash_to_key_map(
+    &self,
     hash_to_key_map: &mut BTreeMap<KeyHash, D::K>,
     deserialized: &Sample<D, D::K>,
   ) {
+    // The map is still needed on NO_KEY topics, so that a dispose by key hash
+    // from a peer that thinks the topic is WITH_KEY can be resolved, and
+    // then skipped.
+    if self.keyless && !hash_to_key_map.is_empty() {
+      return;
+    }
     let instance_key = match deserialized {
       Sample::Value(d) => d.key(),
       Sample::Dispose(k) => k.clone(),
     };
-    hash_to_key_map.insert(instance_key.hash_key(false), instance_key);
+    // The remote Writer decides which form of KeyHash it sends. For keys
+    // that fit in 16 bytes the RTPS spec says the hash is the key itself, but
+    // DDS Security (v1.1 Section 7.3.4) requires MD5 regardless of key size,
+    // and some implementations always use MD5. So remember both forms.
+    let verbatim_or_md5 = instance_key.hash_key(false);
+    let md5 = instance_key.hash_key(true);
+    if md5 != verbatim_or_md5 {
+      hash_to_key_map.insert(md5, instance_key.clone());
+    }
+    hash_to_key_map.insert(verbatim_or_md5, instance_key);
   }
 
   fn deserialize_with<S>(
@@ -240,6 +399,21 @@ where
       DDSData::Data {
         ref serialized_payload,
       } => {
+        let decompressed;
+        let serialized_payload = if compression::is_compressed(serialized_payload) {
+          decompressed = compression::decompress(&self.payload_decompressors, serialized_payload)
+            .map_err(|e| ReadError::Deserialization {
+            reason: format!(
+              "Failed to decompress sample: {}, Topic = {}",
+              e,
+              self.my_topic.name()
+            ),
+          })?;
+          &decompressed
+        } else {
+          serialized_payload
+        };
+
         // what is our data serialization format (representation identifier) ?
         if let Some(recognized_rep_id) = DA::supported_encodings()
           .iter()
@@ -248,8 +422,18 @@ where
           match DA::from_bytes_with(&serialized_payload.value, *recognized_rep_id, decoder) {
             // Data update, decoded ok
             Ok(payload) => {
-              let p = Sample::Value(payload);
-              Self::update_hash_to_key_map(hash_to_key_map, &p);
+              // Some implementations send disposes and unregisters with the
+              // full data, so the StatusInfo decides what this is.
+              let p = if cc
+                .write_options
+                .sample_flags()
+                .intersects(SampleFlag::Disposed | SampleFlag::Unregistered)
+              {
+                Sample::Dispose(payload.key())
+              } else {
+                Sample::Value(payload)
+              };
+              self.update_hash_to_key_map(hash_to_key_map, &p);
               Ok(DeserializedCacheChange::new(timestamp, cc, p))
             }
             Err(e) => Err(ReadError::Deserialization {
@@ -291,7 +475,7 @@ where
         ) {
           Ok(key) => {
             let k = Sample::Dispose(key);
-            Self::update_hash_to_key_map(hash_to_key_map, &k);
+            self.update_hash_to_key_map(hash_to_key_map, &k);
             Ok(DeserializedCacheChange::new(timestamp, cc, k))
           }
           Err(e) => Err(ReadError::Deserialization {
@@ -317,8 +501,8 @@ where
         } else {
           Err(ReadError::UnknownKey {
             details: format!(
-              "Received dispose with unknown key hash: {:x?}, Topic = {}, Type = {:?}",
-              key_hash,
+              "Received dispose with unknown key hash: {:02x?}, Topic = {}, Type = {:?}",
+              key_hash.to_bytes(),
               self.my_topic.name(),
               self.my_topic.get_type()
             ),
@@ -358,31 +542,39 @@ where
     let mut changes =
       Self::try_take_undecoded(is_reliable, &topic_cache, latest_instant, last_read_sn);
 
+    // Disposes with an unknown key hash are skipped. Their read pointers are
+    // recorded here and applied once the iterator is no longer borrowing the
+    // read state.
+    let mut skipped = Vec::new();
+
     // loop in case we get a sample that should be ignored, so we try next.
     loop {
       let (timestamp, cc) = match changes.next() {
-        None => return Ok(None), // no more data available right now
+        None => {
+          // no more data available right now
+          drop(changes);
+          Self::advance_read_pointers(&mut read_state_ref, skipped);
+          return Ok(None);
+        }
         Some((ts, cc)) => (ts, cc),
       };
 
       let result = self.deserialize_with(timestamp, cc, hash_to_key_map, decoder.clone());
 
-      if let Err(ReadError::UnknownKey { .. }) = result {
-        // ignore unknown key hash, continue looping
+      if let Err(ReadError::UnknownKey { ref details }) = result {
+        // We cannot produce a Sample without the key, but do not read this
+        // change again.
+        warn!("{details}");
+        skipped.push((timestamp, cc.writer_guid, cc.sequence_number));
       } else {
         // explicitly drop the cache changes to update the reader state
         drop(changes);
 
         // return with this result
-        // make copies of guid and SN to calm down borrow checker.
-        let writer_guid = cc.writer_guid;
-        let sequence_number = cc.sequence_number;
         // Advance read pointer, error or not, because otherwise
         // the SimpleDatareader is stuck.
-        read_state_ref.latest_instant = max(latest_instant, timestamp);
-        read_state_ref
-          .last_read_sn
-          .insert(writer_guid, sequence_number);
+        skipped.push((timestamp, cc.writer_guid, cc.sequence_number));
+        Self::advance_read_pointers(&mut read_state_ref, skipped);
 
         // // Debug sanity check:
         // use crate::Duration;
@@ -398,6 +590,178 @@ where
     }
   }
 
+  /// Blocks the calling thread until there is unread data available, or
+  /// `timeout` expires. Returns `true` if data is available.
+  ///
+  /// Returns immediately if unread data already exists. Data available
+  /// means that a following [`try_take_one`](Self::try_take_one) is likely to
+  /// produce a sample.
+  ///
+  /// This uses the same waker slot as the async streams, so it must not be
+  /// called concurrently with polling a stream of this reader. A stream that
+  /// was already waiting is woken up afterwards, so that it checks for data
+  /// again.
+  pub fn wait_for_data(&self, timeout: Duration) -> ReadResult<bool> {
+    // None means the deadline is too far in the future to represent, i.e.
+    // wait forever.
+    let deadline = Instant::now().checked_add(timeout.to_std());
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    // Waker of a pending async stream. It is replaced while waiting, so data
+    // arriving meanwhile does not wake the stream.
+    let stream_waker = self.data_reader_waker.lock().unwrap().take();
+
+    let result = loop {
+      // Waker must be set before checking, because otherwise data arriving
+      // between the check and parking would not unpark us.
+      self.set_waker(Some(waker.clone()));
+      if self.has_unread_data() {
+        break true;
+      }
+      match deadline {
+        None => thread::park(),
+        Some(deadline) => {
+          let now = Instant::now();
+          if now >= deadline {
+            break false;
+          }
+          thread::park_timeout(deadline - now);
+        }
+      }
+      // Wakeup may be spurious, so loop and check again.
+    };
+    self.set_waker(stream_waker.clone());
+    if let Some(stream_waker) = stream_waker {
+      stream_waker.wake();
+    }
+    Ok(result)
+  }
+
+  /// Ranges of sequence numbers that have not been received, per matched
+  /// Writer.
+  ///
+  /// This is purely observational and works regardless of Reliability: a
+  /// range is recorded when a sample arrives and some sequence numbers before
+  /// it were skipped. Samples arriving late, or GAP messages from the Writer
+  /// saying that the sequence numbers do not exist, remove them again. For a
+  /// BestEffort Reader, the result lists samples that were lost in transport,
+  /// so that the application may recover them by other means.
+  ///
+  /// Only a limited number of most recent ranges is remembered per Writer.
+  /// Writers without gaps are not listed.
+  pub fn observed_gaps(&self) -> Vec<(GUID, Vec<Range<SequenceNumber>>)> {
+    self.observed_gaps.lock().unwrap().snapshot()
+  }
+
+  /// Locators of the matched Writers, as currently known to this Reader.
+  /// Each entry also tells which locator an acknowledgement was last
+  /// successfully sent to.
+  ///
+  /// This is meant for debugging connectivity. It asks the RTPS processing
+  /// thread for a snapshot, so it blocks for a moment.
+  pub fn remote_writer_locators(&self) -> ReadResult<Vec<RemoteLocators>> {
+    self.query_reader(|reply| ReaderCommand::GetRemoteLocators { reply })
+  }
+
+  /// Writes a human-readable summary of the RTPS state of this Reader to
+  /// `out`: the matched Writers, and which sequence numbers have been
+  /// received from each. The format is not stable.
+  ///
+  /// See [`DataReader::print_diagnostic`](crate::with_key::DataReader::print_diagnostic).
+  pub fn print_diagnostic(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+    let rtps_state = self
+      .query_reader(|reply| ReaderCommand::GetDiagnostic { reply })
+      .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    out.write_all(rtps_state.as_bytes())
+  }
+
+  /// Pauses or resumes acknowledging received samples to reliable Writers.
+  ///
+  /// See [`DataReader::set_flow_control_pause`](crate::with_key::DataReader::set_flow_control_pause).
+  pub fn set_flow_control_pause(&self, paused: bool) -> ReadResult<()> {
+    self.send_reader_command(ReaderCommand::SetFlowControlPause { paused })
+  }
+
+  /// Pauses or resumes receiving samples from the network.
+  ///
+  /// See [`DataReader::pause`](crate::with_key::DataReader::pause).
+  pub fn set_delivery_pause(&self, paused: bool) -> ReadResult<()> {
+    self.send_reader_command(ReaderCommand::SetDeliveryPause { paused })
+  }
+
+  // Passes a command to the RTPS Reader, without waiting for it to be
+  // processed.
+  fn send_reader_command(&self, command: ReaderCommand) -> ReadResult<()> {
+    self.reader_command.try_send(command).map_err(|e| match e {
+      mio_channel::TrySendError::Full(_) => ReadError::WouldBlock {
+        reason: "Reader command queue is full".to_string(),
+      },
+      mio_channel::TrySendError::Disconnected(_) => ReadError::Poisoned {
+        reason: "Cannot send to Reader: channel disconnected".to_string(),
+      },
+      mio_channel::TrySendError::Io(e) => ReadError::Internal {
+        reason: format!("Cannot send to Reader: {e}"),
+      },
+    })
+  }
+
+  // Asks the RTPS Reader for something, and waits for the answer.
+  fn query_reader<T>(
+    &self,
+    command: impl FnOnce(std::sync::mpsc::SyncSender<T>) -> ReaderCommand,
+  ) -> ReadResult<T> {
+    let (reply_sender, reply_receiver) = std::sync::mpsc::sync_channel(1);
+    self.send_reader_command(command(reply_sender))?;
+    reply_receiver
+      .recv_timeout(REMOTE_LOCATORS_QUERY_TIMEOUT)
+      .map_err(|e| ReadError::Poisoned {
+        reason: format!("No answer from Reader: {e}"),
+      })
+  }
+
+  /// Returns the counts of matched Writers, and resets the `count_change`
+  /// fields, so that the next call reports changes since this one.
+  ///
+  /// This is the same information as in
+  /// [`DataReaderStatus::SubscriptionMatched`] events, but can be polled
+  /// without reading the status event stream.
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self.subscription_matched.lock().unwrap().take()
+  }
+
+  /// Returns the counts of alive and not alive matched Writers, and resets
+  /// the `count_change` fields, so that the next call reports changes since
+  /// this one.
+  ///
+  /// A Writer is alive when it is matched. It becomes not alive, if it has
+  /// [`Liveliness::ManualByTopic`](crate::policy::Liveliness::ManualByTopic)
+  /// QoS, and nothing is received from it during its lease duration. Writers
+  /// with other kinds of liveliness are unmatched instead, when their
+  /// DomainParticipant is lost.
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.liveliness_changed.lock().unwrap().take()
+  }
+
+  // Checks if the topic cache has something for us, without advancing
+  // the read pointers.
+  fn has_unread_data(&self) -> bool {
+    let is_reliable = matches!(
+      self.qos_policy.reliability(),
+      Some(policy::Reliability::Reliable { .. })
+    );
+    let topic_cache = self.acquire_the_topic_cache_guard();
+    let read_state = self.read_state.lock().unwrap();
+
+    let has_data = Self::try_take_undecoded(
+      is_reliable,
+      &topic_cache,
+      read_state.latest_instant,
+      &read_state.last_read_sn,
+    )
+    .next()
+    .is_some();
+    has_data
+  }
+
   pub fn qos(&self) -> &QosPolicies {
     &self.qos_policy
   }
@@ -406,10 +770,23 @@ where
     self.my_guid
   }
 
+  /// Topic this SimpleDataReader reads from.
+  ///
+  /// Topic name, type name and [`TopicKind`](crate::TopicKind) are available
+  /// through the [`TopicDescription`] trait.
   pub fn topic(&self) -> &Topic {
     &self.my_topic
   }
 
+  /// Subscriber this SimpleDataReader belongs to.
+  ///
+  /// The SimpleDataReader holds a handle to its Subscriber, so the Subscriber
+  /// stays alive at least as long as its readers. The Subscriber in turn
+  /// refers to the DomainParticipant only weakly.
+  pub fn subscriber(&self) -> &Subscriber {
+    &self.my_subscriber
+  }
+
   pub fn as_async_stream<S>(&self) -> SimpleDataReaderStream<'_, D, S, DA>
   where
     DA: DefaultDecoder<D, Decoder = S>,
@@ -429,6 +806,19 @@ where
     }
   }
 
+  /// Stream of status events, such as
+  /// [`SubscriptionMatched`](DataReaderStatus::SubscriptionMatched) and
+  /// [`RequestedIncompatibleQos`](DataReaderStatus::RequestedIncompatibleQos).
+  ///
+  /// This is the same as
+  /// [`as_async_status_stream`](StatusEvented::as_async_status_stream), but
+  /// does not need the trait in scope.
+  pub fn as_async_event_stream(&self) -> SimpleDataReaderEventStream<'_, D, DA> {
+    SimpleDataReaderEventStream {
+      simple_datareader: self,
+    }
+  }
+
   fn acquire_the_topic_cache_guard(&self) -> MutexGuard<'_, TopicCache> {
     self.topic_cache.lock().unwrap_or_else(|e| {
       panic!(
@@ -525,9 +915,7 @@ where
   }
 
   fn as_async_status_stream(&'a self) -> SimpleDataReaderEventStream<'a, D, DA> {
-    SimpleDataReaderEventStream {
-      simple_datareader: self,
-    }
+    self.as_async_event_stream()
   }
 
   fn try_recv_status(&self) -> Option<DataReaderStatus> {
@@ -1,8 +1,10 @@
 pub(crate) mod builtin_endpoint;
+pub(crate) mod config;
 pub(crate) mod content_filter_property;
 #[allow(clippy::module_inception)]
 pub(crate) mod discovery;
 pub(crate) mod discovery_db;
+pub(crate) mod payload_compression_property;
 
 #[cfg(feature = "security")]
 pub(crate) mod secure_discovery;
@@ -12,3 +14,4 @@ pub(crate) mod spdp_participant_data;
 
 pub use sedp_messages::*;
 pub use spdp_participant_data::*;
+pub use builtin_endpoint::BuiltinEndpointSet;
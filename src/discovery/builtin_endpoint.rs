@@ -43,10 +43,26 @@ impl BuiltinEndpointSet {
   pub const TOPICS_ANNOUNCER: u32 = 0x08000000;
   pub const TOPICS_DETECTOR: u32 = 0x10000000;
 
+  // What RustDDS announces, when security is not enabled.
+  pub const DEFAULT_SET: u32 = Self::PARTICIPANT_ANNOUNCER
+    | Self::PARTICIPANT_DETECTOR
+    | Self::PUBLICATIONS_ANNOUNCER
+    | Self::PUBLICATIONS_DETECTOR
+    | Self::SUBSCRIPTIONS_ANNOUNCER
+    | Self::SUBSCRIPTIONS_DETECTOR
+    | Self::PARTICIPANT_MESSAGE_DATA_WRITER
+    | Self::PARTICIPANT_MESSAGE_DATA_READER
+    | Self::TOPICS_ANNOUNCER
+    | Self::TOPICS_DETECTOR;
+
   pub fn from_u32(val: u32) -> Self {
     Self { value: val }
   }
 
+  pub fn as_u32(&self) -> u32 {
+    self.value
+  }
+
   pub fn contains(&self, other: u32) -> bool {
     (self.value & other) == other
   }
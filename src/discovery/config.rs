@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use crate::{
+  create_error_bad_parameter,
+  dds::{
+    qos::{policy, QosPolicies, QosPolicyBuilder},
+    result::{CreateError, CreateResult},
+  },
+  discovery::discovery::Discovery,
+};
+
+/// Tuning of the built-in Discovery endpoints (SPDP and SEDP).
+///
+/// The defaults suit small and medium-sized domains. Give this to
+/// [`DomainParticipantBuilder::discovery_config`](crate::DomainParticipantBuilder::discovery_config)
+/// to change them. The values are local to this DomainParticipant. They are
+/// not sent to, or negotiated with, remote participants.
+///
+/// In a large domain, e.g. hundreds of participants, Discovery traffic grows
+/// roughly with the square of the number of participants. Then a longer
+/// `spdp_announcement_period` and `builtin_heartbeat_period` reduce the
+/// traffic, and a deeper SEDP history avoids losing endpoint announcements that
+/// arrive in bursts. In a small embedded setup, shallower histories and
+/// [`sedp_resource_limits`](Self::sedp_resource_limits) save memory.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rustdds::{DiscoveryConfig, DomainParticipantBuilder};
+/// let domain_participant = DomainParticipantBuilder::new(0)
+///   .discovery_config(DiscoveryConfig {
+///     spdp_announcement_period: Duration::from_secs(30),
+///     spdp_initial_announcements: 3,
+///     sedp_reader_history_depth: 16,
+///     ..DiscoveryConfig::default()
+///   })
+///   .build()
+///   .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveryConfig {
+  /// How often this participant announces itself (SPDP). Remote participants
+  /// consider us lost after five periods of silence, so this also determines
+  /// how quickly a crashed participant is noticed. A shorter period discovers
+  /// late joiners faster, but every participant sends to every other one.
+  /// Default 10 s.
+  pub spdp_announcement_period: Duration,
+  /// Number of SPDP announcements sent
+  /// `spdp_initial_announcement_interval` apart at startup, before settling
+  /// to `spdp_announcement_period`. More announcements make startup discovery
+  /// robust against packet loss. Must be at least 1. Default 1.
+  pub spdp_initial_announcements: u32,
+  /// Interval of the initial SPDP announcements. Default 100 ms.
+  pub spdp_initial_announcement_interval: Duration,
+  /// History depth of the SPDP reader. Participant announcements that arrive
+  /// faster than Discovery processes them are dropped beyond this depth.
+  /// Default 8.
+  pub spdp_reader_history_depth: i32,
+  /// History depth of the SEDP readers for publications, subscriptions and
+  /// topics, per remote endpoint. Updates of the same endpoint that arrive
+  /// faster than Discovery processes them are dropped beyond this depth.
+  /// Default 4.
+  pub sedp_reader_history_depth: i32,
+  /// Resource limits of the SEDP readers and writers. If set, an instance is
+  /// one endpoint or topic, so `max_instances` limits the number of endpoints
+  /// that are remembered. Endpoints beyond the limit are not discovered.
+  /// Default `None`, i.e. unlimited.
+  pub sedp_resource_limits: Option<policy::ResourceLimits>,
+  /// Heartbeat period of the built-in reliable writers (SEDP and participant
+  /// messages). A shorter period repairs lost announcements faster, at the
+  /// cost of more traffic to every matched remote participant. Default 1 s.
+  pub builtin_heartbeat_period: Duration,
+  /// How long built-in reliable writers wait before repairing data that a
+  /// remote reader reports missing. Waiting lets repair requests from several
+  /// readers be combined. Default 200 ms.
+  pub builtin_nack_response_delay: Duration,
+  /// Report a
+  /// [`GuidConflict`](crate::dds::statusevents::DomainParticipantStatusEvent::GuidConflict)
+  /// when a remote Reader or Writer is announced with the GUID of a local one.
+  /// Default `true`.
+  pub detect_guid_conflicts: bool,
+}
+
+impl Default for DiscoveryConfig {
+  fn default() -> Self {
+    Self {
+      spdp_announcement_period: Duration::from_secs(10),
+      spdp_initial_announcements: 1,
+      spdp_initial_announcement_interval: Duration::from_millis(100),
+      spdp_reader_history_depth: 8,
+      sedp_reader_history_depth: 4,
+      sedp_resource_limits: None,
+      builtin_heartbeat_period: Duration::from_secs(1),
+      builtin_nack_response_delay: Duration::from_millis(200),
+      detect_guid_conflicts: true,
+    }
+  }
+}
+
+impl DiscoveryConfig {
+  // Checks the values, as done by DomainParticipantBuilder::build.
+  pub(crate) fn validate(&self) -> CreateResult<()> {
+    let positive = [
+      ("spdp_announcement_period", self.spdp_announcement_period),
+      (
+        "spdp_initial_announcement_interval",
+        self.spdp_initial_announcement_interval,
+      ),
+      ("builtin_heartbeat_period", self.builtin_heartbeat_period),
+    ];
+    for (name, duration) in positive {
+      if duration.is_zero() {
+        return create_error_bad_parameter!("DiscoveryConfig: {name} must be positive");
+      }
+    }
+    if self.spdp_initial_announcements == 0 {
+      return create_error_bad_parameter!(
+        "DiscoveryConfig: spdp_initial_announcements must be at least 1"
+      );
+    }
+    for (name, qos) in [
+      ("SPDP reader", self.spdp_reader_qos()),
+      ("SEDP reader", self.sedp_reader_qos()),
+      ("SEDP writer", self.sedp_writer_qos()),
+    ] {
+      if let Err(e) = qos.validate() {
+        return create_error_bad_parameter!("DiscoveryConfig: {name} QoS: {e}");
+      }
+    }
+    Ok(())
+  }
+
+  // Lease duration announced in SPDP. Five periods, so that the lease does not
+  // expire if an announcement or two is lost.
+  pub(crate) fn spdp_lease_duration(&self) -> Duration {
+    self.spdp_announcement_period * 5
+  }
+
+  pub(crate) fn spdp_reader_qos(&self) -> QosPolicies {
+    Discovery::create_spdp_participant_qos()
+      .modify_by(&self.history_qos(self.spdp_reader_history_depth))
+  }
+
+  pub(crate) fn sedp_reader_qos(&self) -> QosPolicies {
+    Discovery::builtin_subscriber_qos()
+      .modify_by(&self.history_qos(self.sedp_reader_history_depth))
+      .modify_by(&self.resource_limits_qos())
+  }
+
+  pub(crate) fn sedp_writer_qos(&self) -> QosPolicies {
+    Discovery::builtin_publisher_qos().modify_by(&self.resource_limits_qos())
+  }
+
+  fn history_qos(&self, depth: i32) -> QosPolicies {
+    QosPolicyBuilder::new()
+      .history(policy::History::KeepLast { depth })
+      .build()
+  }
+
+  fn resource_limits_qos(&self) -> QosPolicies {
+    match self.sedp_resource_limits {
+      Some(limits) => QosPolicyBuilder::new().resource_limits(limits).build(),
+      None => QosPolicies::qos_none(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn discovery_config_validation() {
+    assert!(DiscoveryConfig::default().validate().is_ok());
+    assert_eq!(
+      DiscoveryConfig::default().sedp_reader_qos(),
+      Discovery::builtin_subscriber_qos()
+    );
+    assert_eq!(
+      DiscoveryConfig::default().spdp_reader_qos(),
+      Discovery::create_spdp_participant_qos()
+    );
+
+    let invalid = [
+      DiscoveryConfig {
+        spdp_announcement_period: Duration::ZERO,
+        ..DiscoveryConfig::default()
+      },
+      DiscoveryConfig {
+        spdp_initial_announcements: 0,
+        ..DiscoveryConfig::default()
+      },
+      DiscoveryConfig {
+        sedp_reader_history_depth: 0,
+        ..DiscoveryConfig::default()
+      },
+      // History deeper than resource limits allow
+      DiscoveryConfig {
+        sedp_reader_history_depth: 8,
+        sedp_resource_limits: Some(policy::ResourceLimits {
+          max_samples: 100,
+          max_instances: 50,
+          max_samples_per_instance: 2,
+        }),
+        ..DiscoveryConfig::default()
+      },
+    ];
+    for config in invalid {
+      assert!(config.validate().is_err(), "{config:?}");
+    }
+
+    let limited = DiscoveryConfig {
+      sedp_reader_history_depth: 2,
+      sedp_resource_limits: Some(policy::ResourceLimits {
+        max_samples: 100,
+        max_instances: 50,
+        max_samples_per_instance: 2,
+      }),
+      ..DiscoveryConfig::default()
+    };
+    assert!(limited.validate().is_ok());
+    assert_eq!(
+      limited.sedp_writer_qos().resource_limits(),
+      limited.sedp_resource_limits
+    );
+  }
+}
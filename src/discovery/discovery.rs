@@ -1,6 +1,7 @@
 use std::{
-  sync::{Arc, RwLock},
-  time::Duration as StdDuration,
+  net::IpAddr,
+  sync::{Arc, Mutex, RwLock},
+  time::{Duration as StdDuration, Instant},
 };
 
 #[allow(unused_imports)]
@@ -11,6 +12,7 @@ use paste::paste; // token pasting macro
 
 use crate::{
   dds::{
+    health::{HealthMonitor, MonitoredThread, ParticipantHealthSample},
     participant::DomainParticipantWeak,
     qos::{
       policy::{
@@ -21,16 +23,23 @@ use crate::{
     },
     readcondition::ReadCondition,
     result::{CreateError, CreateResult},
-    statusevents::{DomainParticipantStatusEvent, LostReason, StatusChannelSender},
+    statusevents::{
+      DomainParticipantStatusEvent, EndpointDescription, LostReason, StatusChannelSender,
+    },
   },
   discovery::{
-    discovery_db::{discovery_db_read, discovery_db_write, DiscoveredVia, DiscoveryDB},
+    config::DiscoveryConfig,
+    discovery_db::{
+      discovery_db_read, discovery_db_write, DiscoveredVia, DiscoveryDB, ParticipantUpdate,
+    },
     sedp_messages::{
       DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData, Endpoint_GUID,
       ParticipantMessageData, ParticipantMessageDataKind,
     },
     spdp_participant_data::{Participant_GUID, SpdpDiscoveredParticipantData},
   },
+  messages::{protocol_version::ProtocolVersion, vendor_id::VendorId},
+  network::util::{get_local_unicast_ip_addrs, ip_addr_changes},
   polling::{new_simple_timer, TimerPolicy},
   rtps::constant::*,
   serialization::{pl_cdr_adapters::*, CDRDeserializerAdapter, CDRSerializerAdapter},
@@ -40,8 +49,8 @@ use crate::{
     guid::{EntityId, GuidPrefix, GUID},
     time::Timestamp,
   },
-  with_key::{DataReader, DataWriter, Sample},
-  DomainParticipant,
+  with_key::{DataReader, DataWriter, DataWriterCdr, Sample},
+  DomainParticipant, TopicKind,
 };
 // This module implements the control logic of the Discovery process.
 //
@@ -93,6 +102,12 @@ pub enum DiscoveryCommand {
     guid: GUID,
   },
   ManualAssertLiveliness,
+  // Our own locators have changed. Announce the new ones.
+  RefreshLocators,
+  // Forget a remote participant now and announce ourselves to rediscover it.
+  PreemptDiscovery {
+    guid_prefix: GuidPrefix,
+  },
   AssertTopicLiveliness {
     writer_guid: GUID,
     manual_assertion: bool,
@@ -188,6 +203,20 @@ mod no_key {
   }
 }
 
+// Receivers of ParticipantMessageData, see
+// DomainParticipant::participant_message_receiver
+pub(crate) type ParticipantMessageObservers =
+  Arc<Mutex<Vec<StatusChannelSender<ParticipantMessageData>>>>;
+
+// Periodic publishing of ParticipantHealthSample, see
+// DomainParticipantBuilder::enable_health_topic
+struct HealthTopic {
+  writer: DataWriterCdr<ParticipantHealthSample>,
+  timer: Timer<()>,
+  interval: StdDuration,
+  started: Instant,
+}
+
 // Enum indicating if secure discovery allows normal discovery to process
 // something
 #[derive(PartialEq)]
@@ -208,12 +237,16 @@ pub(crate) struct Discovery {
   discovery_updated_sender: mio_channel::SyncSender<DiscoveryNotificationType>,
   // Discovery gets commands from dp_event_loop from this channel
   discovery_command_receiver: mio_channel::Receiver<DiscoveryCommand>,
-  spdp_liveness_receiver: mio_channel::Receiver<GuidPrefix>,
+  spdp_liveness_receiver: mio_channel::Receiver<(GuidPrefix, Option<VendorId>)>,
 
   liveliness_state: LivelinessState,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 
+  health: Arc<HealthMonitor>,
+
+  participant_message_observers: ParticipantMessageObservers,
+
   // DDS Subscriber and Publisher for Discovery
   // ...but these are not actually used after initialization
   // discovery_subscriber: Subscriber,
@@ -226,6 +259,15 @@ pub(crate) struct Discovery {
   dcps_participant: with_key::DiscoveryTopicPlCdr<SpdpDiscoveredParticipantData>,
   participant_cleanup_timer: Timer<()>, // garbage collection timer for dead remote participants
 
+  // Periodic check for changes in local network interfaces. When they change,
+  // our locators are updated and re-announced.
+  network_check_timer: Timer<()>,
+  known_interface_addrs: Vec<IpAddr>,
+
+  config: DiscoveryConfig,
+  // SPDP announcements still to be sent at the initial interval
+  spdp_initial_announcements_left: u32,
+
   // Topic "DCPSSubscription" - announcing and detecting Readers
   dcps_subscription: with_key::DiscoveryTopicPlCdr<DiscoveredReaderData>,
 
@@ -239,6 +281,8 @@ pub(crate) struct Discovery {
   // DCPSParticipantMessage - used by participants to communicate liveness
   dcps_participant_message: with_key::DiscoveryTopicCDR<ParticipantMessageData>,
 
+  health_topic: Option<HealthTopic>,
+
   // If security is enabled, this field contains a SecureDiscovery struct, an appendix
   // which is used for Secure functionality
   security_opt: Option<SecureDiscovery>,
@@ -280,8 +324,10 @@ pub(crate) struct Discovery {
 impl Discovery {
   const PARTICIPANT_CLEANUP_PERIOD: StdDuration = StdDuration::from_secs(2);
   const TOPIC_CLEANUP_PERIOD: StdDuration = StdDuration::from_secs(60); // timer for cleaning up inactive topics
-  const SPDP_PUBLISH_PERIOD: StdDuration = StdDuration::from_secs(10);
   const CHECK_PARTICIPANT_MESSAGES: StdDuration = StdDuration::from_secs(1);
+  const NETWORK_CHECK_PERIOD: StdDuration = StdDuration::from_secs(5);
+  // Used only if security is compiled in, but not enabled. Otherwise the period
+  // comes from the handshake retry policy.
   #[cfg(feature = "security")]
   const CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_PERIOD: StdDuration = StdDuration::from_secs(1);
 
@@ -292,9 +338,13 @@ impl Discovery {
     discovery_started_sender: std::sync::mpsc::Sender<CreateResult<()>>,
     discovery_updated_sender: mio_channel::SyncSender<DiscoveryNotificationType>,
     discovery_command_receiver: mio_channel::Receiver<DiscoveryCommand>,
-    spdp_liveness_receiver: mio_channel::Receiver<GuidPrefix>,
+    spdp_liveness_receiver: mio_channel::Receiver<(GuidPrefix, Option<VendorId>)>,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     security_plugins_opt: Option<SecurityPluginsHandle>,
+    health: Arc<HealthMonitor>,
+    participant_message_observers: ParticipantMessageObservers,
+    config: DiscoveryConfig,
+    health_topic_interval: Option<StdDuration>,
   ) -> CreateResult<Self> {
     // helper macro to handle initialization failures.
     macro_rules! try_construct {
@@ -317,8 +367,8 @@ impl Discovery {
     }
 
     let poll = try_construct!(mio_06::Poll::new(), "Failed to allocate discovery poll.");
-    let discovery_subscriber_qos = Self::builtin_subscriber_qos();
-    let discovery_publisher_qos = Self::builtin_publisher_qos();
+    let discovery_subscriber_qos = config.sedp_reader_qos();
+    let discovery_publisher_qos = config.sedp_writer_qos();
 
     // Create DDS Publisher and Subscriber for Discovery.
     // These are needed to create DataWriter and DataReader objects
@@ -385,9 +435,10 @@ impl Discovery {
           .expect("Failed to register a discovery reader to poll.");
 
         let mut timer: Timer<TimerPolicy> = new_simple_timer();
-        let timeout_and_timer_token_opt: Option<mio_06::Token> = $timeout_and_timer_token_opt;
-        if let Some(timer_token) = timeout_and_timer_token_opt {
-          timer.set_timeout(StdDuration::from_millis(100), TimerPolicy::Repeat);
+        let timeout_and_timer_token_opt: Option<(StdDuration, mio_06::Token)> =
+          $timeout_and_timer_token_opt;
+        if let Some((timeout, timer_token)) = timeout_and_timer_token_opt {
+          timer.set_timeout(timeout, TimerPolicy::Repeat);
           poll
             .register(&timer, timer_token, Ready::readable(), PollOpt::edge())
             .expect("Unable to register timer token. ");
@@ -423,12 +474,15 @@ impl Discovery {
       builtin_topic_names::DCPS_PARTICIPANT,
       builtin_topic_type_names::DCPS_PARTICIPANT,
       SpdpDiscoveredParticipantData,
-      Some(Self::create_spdp_participant_qos()),
+      Some(config.spdp_reader_qos()),
       false, // Regular stateful RTPS Reader & Writer
       EntityId::SPDP_BUILTIN_PARTICIPANT_READER,
       DISCOVERY_PARTICIPANT_DATA_TOKEN,
       EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER,
-      Some(DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN),
+      Some((
+        config.spdp_initial_announcement_interval,
+        DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN
+      )),
     );
 
     // create lease duration check timer
@@ -444,6 +498,19 @@ impl Discovery {
       "Unable to create participant cleanup timer."
     );
 
+    // create network interface check timer
+    let mut network_check_timer: Timer<()> = new_simple_timer();
+    network_check_timer.set_timeout(Self::NETWORK_CHECK_PERIOD, ());
+    try_construct!(
+      poll.register(
+        &network_check_timer,
+        DISCOVERY_NETWORK_CHECK_TIMER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      ),
+      "Unable to create network interface check timer."
+    );
+
     // Subscriptions: What are the Readers on the network and what are they
     // subscribing to?
     let dcps_subscription = construct_topic_and_poll!(
@@ -515,7 +582,10 @@ impl Discovery {
       EntityId::P2P_BUILTIN_PARTICIPANT_MESSAGE_READER,
       DISCOVERY_PARTICIPANT_MESSAGE_TOKEN,
       EntityId::P2P_BUILTIN_PARTICIPANT_MESSAGE_WRITER,
-      Some(DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN),
+      Some((
+        StdDuration::from_millis(100),
+        DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN
+      )),
     );
 
     // DDS Security
@@ -618,13 +688,37 @@ impl Discovery {
       None, // No timer.
     );
 
+    #[cfg(not(feature = "security"))]
+    let security_opt = security_plugins_opt.and(None); // = None, but avoid warning.
+
+    #[cfg(feature = "security")]
+    let security_opt = if let Some(plugins_handle) = security_plugins_opt {
+      // Plugins is Some so security is enabled. Initialize SecureDiscovery
+      let security = try_construct!(
+        SecureDiscovery::new(
+          &domain_participant,
+          &discovery_db,
+          plugins_handle,
+          participant_status_sender.clone(),
+          Arc::clone(&health),
+        ),
+        "Could not initialize Secure Discovery."
+      );
+      Some(security)
+    } else {
+      None // no security configured
+    };
+
     // Create a timer to periodically check whether to resend any cached security
     // (authentication, key exchange) messages
     #[cfg(feature = "security")]
     let secure_message_resend_timer = {
       let mut secure_message_resend_timer: Timer<()> = new_simple_timer();
-      secure_message_resend_timer
-        .set_timeout(Self::CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_PERIOD, ());
+      let resend_period = security_opt.as_ref().map_or(
+        Self::CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_PERIOD,
+        SecureDiscovery::resend_period,
+      );
+      secure_message_resend_timer.set_timeout(resend_period, ());
       try_construct!(
         poll.register(
           &secure_message_resend_timer,
@@ -637,19 +731,47 @@ impl Discovery {
       secure_message_resend_timer
     };
 
-    #[cfg(not(feature = "security"))]
-    let security_opt = security_plugins_opt.and(None); // = None, but avoid warning.
-
-    #[cfg(feature = "security")]
-    let security_opt = if let Some(plugins_handle) = security_plugins_opt {
-      // Plugins is Some so security is enabled. Initialize SecureDiscovery
-      let security = try_construct!(
-        SecureDiscovery::new(&domain_participant, &discovery_db, plugins_handle),
-        "Could not initialize Secure Discovery."
-      );
-      Some(security)
-    } else {
-      None // no security configured
+    // The health topic is a normal user topic, so it is discovered and
+    // matched like any other.
+    let health_topic = match health_topic_interval {
+      None => None,
+      Some(interval) => {
+        let qos = ParticipantHealthSample::qos();
+        let topic = try_construct!(
+          domain_participant.create_topic(
+            ParticipantHealthSample::TOPIC_NAME.to_string(),
+            ParticipantHealthSample::TYPE_NAME.to_string(),
+            &qos,
+            TopicKind::WithKey,
+          ),
+          "Unable to create health topic."
+        );
+        let publisher = try_construct!(
+          domain_participant.create_publisher(&qos),
+          "Unable to create health topic Publisher."
+        );
+        let writer = try_construct!(
+          publisher.create_datawriter_cdr::<ParticipantHealthSample>(&topic, None),
+          "Unable to create health topic DataWriter."
+        );
+        let mut timer: Timer<()> = new_simple_timer();
+        timer.set_timeout(interval, ());
+        try_construct!(
+          poll.register(
+            &timer,
+            DISCOVERY_HEALTH_TOPIC_TIMER_TOKEN,
+            Ready::readable(),
+            PollOpt::edge(),
+          ),
+          "Unable to create health topic timer."
+        );
+        Some(HealthTopic {
+          writer,
+          timer,
+          interval,
+          started: Instant::now(),
+        })
+      }
     };
 
     Ok(Self {
@@ -661,6 +783,8 @@ impl Discovery {
       discovery_command_receiver,
       spdp_liveness_receiver,
       participant_status_sender,
+      health,
+      participant_message_observers,
 
       liveliness_state: LivelinessState::new(),
 
@@ -668,11 +792,16 @@ impl Discovery {
       // discovery_publisher,
       dcps_participant,
       participant_cleanup_timer, // SPDP
+      network_check_timer,
+      known_interface_addrs: get_local_unicast_ip_addrs(),
+      config,
+      spdp_initial_announcements_left: config.spdp_initial_announcements - 1,
       dcps_subscription,
       dcps_publication, // SEDP
       dcps_topic,
       topic_cleanup_timer,      // SEDP
       dcps_participant_message, // liveliness messages
+      health_topic,
 
       security_opt,
       #[cfg(feature = "security")]
@@ -693,6 +822,14 @@ impl Discovery {
   }
 
   pub fn discovery_event_loop(&mut self) {
+    self.start();
+    let mut events = Events::with_capacity(32);
+    while self.poll_once(&mut events, std::time::Duration::from_millis(5000)) {}
+  }
+
+  // Announces our participant and reports to DomainParticipantBuilder that
+  // Discovery is running. This must be called once before poll_once.
+  pub(crate) fn start(&mut self) {
     self.initialize_participant();
 
     // Send out info about user (=non-built-in) Writers and Readers that we have.
@@ -716,200 +853,222 @@ impl Discovery {
     // If this triggers, then discovery_started channel has been
     // closed, which likely means the receiver (app thread) has paniced also.
     // They will wait for up to 10 sec for us to send this handshake.
+  }
 
-    let mut events = Events::with_capacity(32);
-    loop {
-      match self
-        .poll
-        .poll(&mut events, Some(std::time::Duration::from_millis(5000)))
-      {
-        Ok(_) => (),
-        Err(e) => {
-          error!("Failed in waiting of poll in discovery. {e:?}");
-          return;
-        }
-      }
-      if events.is_empty() {
-        debug!("Discovery event loop idling.");
+  // Waits for events for at most `timeout` and processes them.
+  // Returns false when Discovery has stopped and must not be polled again.
+  pub(crate) fn poll_once(&mut self, events: &mut Events, timeout: std::time::Duration) -> bool {
+    match self.poll.poll(events, Some(timeout)) {
+      Ok(_) => (),
+      Err(e) => {
+        error!("Failed in waiting of poll in discovery. {e:?}");
+        return false;
       }
+    }
+    self.health.heartbeat(MonitoredThread::Discovery);
+    if events.is_empty() {
+      debug!("Discovery event loop idling.");
+    }
 
-      for event in events.iter() {
-        match event.token() {
-          DISCOVERY_COMMAND_TOKEN => {
-            while let Ok(command) = self.discovery_command_receiver.try_recv() {
-              match command {
-                DiscoveryCommand::StopDiscovery => {
-                  info!("Stopping Discovery");
-                  self.on_participant_shutting_down();
-                  info!("Stopped Discovery");
-                  return; // terminate event loop
-                }
-                DiscoveryCommand::AddLocalWriter { guid } => {
-                  self.add_local_writer(guid);
-                }
-                DiscoveryCommand::AddLocalReader { guid } => {
-                  self.add_local_reader(guid);
-                }
-                DiscoveryCommand::AddTopic { topic_name } => {
-                  self.sedp_publish_topic(&topic_name);
-                }
-                DiscoveryCommand::RemoveLocalWriter { guid } => {
-                  if guid == self.dcps_publication.writer.guid() {
-                    continue;
-                  }
-                  self.send_endpoint_dispose_message(guid);
-                  discovery_db_write(&self.discovery_db).remove_local_topic_writer(guid);
-                }
-                DiscoveryCommand::RemoveLocalReader { guid } => {
-                  if guid == self.dcps_subscription.writer.guid() {
-                    continue;
-                  }
-                  self.send_endpoint_dispose_message(guid);
-                  discovery_db_write(&self.discovery_db).remove_local_topic_reader(guid);
+    for event in events.iter() {
+      match event.token() {
+        DISCOVERY_COMMAND_TOKEN => {
+          while let Ok(command) = self.discovery_command_receiver.try_recv() {
+            match command {
+              DiscoveryCommand::StopDiscovery => {
+                info!("Stopping Discovery");
+                self.on_participant_shutting_down();
+                info!("Stopped Discovery");
+                return false; // terminate event loop
+              }
+              DiscoveryCommand::AddLocalWriter { guid } => {
+                self.add_local_writer(guid);
+              }
+              DiscoveryCommand::AddLocalReader { guid } => {
+                self.add_local_reader(guid);
+              }
+              DiscoveryCommand::AddTopic { topic_name } => {
+                self.sedp_publish_topic(&topic_name);
+              }
+              DiscoveryCommand::RemoveLocalWriter { guid } => {
+                if guid == self.dcps_publication.writer.guid() {
+                  continue;
                 }
-                DiscoveryCommand::ManualAssertLiveliness => {
-                  self
-                    .liveliness_state
-                    .manual_participant_liveness_refresh_requested = true;
+                self.send_endpoint_dispose_message(guid);
+                discovery_db_write(&self.discovery_db).remove_local_topic_writer(guid);
+              }
+              DiscoveryCommand::RemoveLocalReader { guid } => {
+                if guid == self.dcps_subscription.writer.guid() {
+                  continue;
                 }
-                DiscoveryCommand::AssertTopicLiveliness {
-                  writer_guid,
-                  manual_assertion,
-                } => {
-                  self.send_discovery_notification(
-                    DiscoveryNotificationType::AssertTopicLiveliness {
-                      writer_guid,
-                      manual_assertion,
-                    },
+                self.send_endpoint_dispose_message(guid);
+                discovery_db_write(&self.discovery_db).remove_local_topic_reader(guid);
+              }
+              DiscoveryCommand::ManualAssertLiveliness => {
+                self
+                  .liveliness_state
+                  .manual_participant_liveness_refresh_requested = true;
+              }
+              DiscoveryCommand::RefreshLocators => {
+                self.refresh_locators();
+              }
+              DiscoveryCommand::PreemptDiscovery { guid_prefix } => {
+                self.preempt_discovery(guid_prefix);
+              }
+              DiscoveryCommand::AssertTopicLiveliness {
+                writer_guid,
+                manual_assertion,
+              } => {
+                self.send_discovery_notification(
+                  DiscoveryNotificationType::AssertTopicLiveliness {
+                    writer_guid,
+                    manual_assertion,
+                  },
+                );
+              }
+              #[cfg(feature = "security")]
+              DiscoveryCommand::StartKeyExchangeWithRemoteParticipant {
+                participant_guid_prefix,
+              } => {
+                if let Some(security) = self.security_opt.as_mut() {
+                  security.start_key_exchange_with_remote_participant(
+                    participant_guid_prefix,
+                    &self.dcps_participant_volatile_message_secure.writer,
+                    &self.discovery_db,
                   );
                 }
-                #[cfg(feature = "security")]
-                DiscoveryCommand::StartKeyExchangeWithRemoteParticipant {
-                  participant_guid_prefix,
-                } => {
-                  if let Some(security) = self.security_opt.as_mut() {
-                    security.start_key_exchange_with_remote_participant(
-                      participant_guid_prefix,
-                      &self.dcps_participant_volatile_message_secure.writer,
-                      &self.discovery_db,
-                    );
-                  }
-                }
-                #[cfg(feature = "security")]
-                DiscoveryCommand::StartKeyExchangeWithRemoteEndpoint {
-                  local_endpoint_guid,
-                  remote_endpoint_guid,
-                } => {
-                  if let Some(security) = self.security_opt.as_mut() {
-                    security.start_key_exchange_with_remote_endpoint(
-                      local_endpoint_guid,
-                      remote_endpoint_guid,
-                      &self.dcps_participant_volatile_message_secure.writer,
-                      &self.discovery_db,
-                    );
-                  }
+              }
+              #[cfg(feature = "security")]
+              DiscoveryCommand::StartKeyExchangeWithRemoteEndpoint {
+                local_endpoint_guid,
+                remote_endpoint_guid,
+              } => {
+                if let Some(security) = self.security_opt.as_mut() {
+                  security.start_key_exchange_with_remote_endpoint(
+                    local_endpoint_guid,
+                    remote_endpoint_guid,
+                    &self.dcps_participant_volatile_message_secure.writer,
+                    &self.discovery_db,
+                  );
                 }
-              };
-            }
+              }
+            };
           }
+        }
 
-          DISCOVERY_PARTICIPANT_DATA_TOKEN => {
-            debug!("triggered participant reader");
-            self.spdp_receive();
-          }
+        DISCOVERY_PARTICIPANT_DATA_TOKEN => {
+          debug!("triggered participant reader");
+          self.spdp_receive();
+        }
 
-          DISCOVERY_PARTICIPANT_CLEANUP_TOKEN => {
-            self.participant_cleanup();
-            // setting next cleanup timeout
-            self
-              .participant_cleanup_timer
-              .set_timeout(Self::PARTICIPANT_CLEANUP_PERIOD, ());
-          }
+        DISCOVERY_PARTICIPANT_CLEANUP_TOKEN => {
+          self.participant_cleanup();
+          // setting next cleanup timeout
+          self
+            .participant_cleanup_timer
+            .set_timeout(Self::PARTICIPANT_CLEANUP_PERIOD, ());
+        }
 
-          DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN => {
-            if let Some(dp) = self.domain_participant.clone().upgrade() {
-              self.spdp_publish(&dp);
-            } else {
-              error!("DomainParticipant doesn't exist anymore, exiting Discovery.");
-              return;
-            };
-            // reschedule timer
-            while let Some(policy) = self.dcps_participant.timer.poll() {
-              match policy {
-                TimerPolicy::Repeat => {
-                  self
-                    .dcps_participant
-                    .timer
-                    .set_timeout(Self::SPDP_PUBLISH_PERIOD, TimerPolicy::Repeat);
-                }
-                TimerPolicy::OneShot => {
-                  // Do not set again, since it was one-shot.
-                }
+        DISCOVERY_NETWORK_CHECK_TIMER_TOKEN => {
+          self.check_network_interfaces();
+          self
+            .network_check_timer
+            .set_timeout(Self::NETWORK_CHECK_PERIOD, ());
+        }
+
+        DISCOVERY_SEND_PARTICIPANT_INFO_TOKEN => {
+          if let Some(dp) = self.domain_participant.clone().upgrade() {
+            self.spdp_publish(&dp);
+          } else {
+            error!("DomainParticipant doesn't exist anymore, exiting Discovery.");
+            return false;
+          };
+          // reschedule timer
+          while let Some(policy) = self.dcps_participant.timer.poll() {
+            match policy {
+              TimerPolicy::Repeat => {
+                let period = if self.spdp_initial_announcements_left > 0 {
+                  self.spdp_initial_announcements_left -= 1;
+                  self.config.spdp_initial_announcement_interval
+                } else {
+                  self.config.spdp_announcement_period
+                };
+                self
+                  .dcps_participant
+                  .timer
+                  .set_timeout(period, TimerPolicy::Repeat);
+              }
+              TimerPolicy::OneShot => {
+                // Do not set again, since it was one-shot.
               }
             }
           }
-          DISCOVERY_READER_DATA_TOKEN => {
-            self.sedp_receive_subscription(None);
-          }
-          DISCOVERY_WRITER_DATA_TOKEN => {
-            self.sedp_receive_publication(None);
-          }
-          DISCOVERY_TOPIC_DATA_TOKEN => {
-            self.sedp_receive_topic_data(None);
-          }
-          DISCOVERY_TOPIC_CLEANUP_TOKEN => {
-            self.topic_cleanup();
+        }
+        DISCOVERY_READER_DATA_TOKEN => {
+          self.sedp_receive_subscription(None);
+        }
+        DISCOVERY_WRITER_DATA_TOKEN => {
+          self.sedp_receive_publication(None);
+        }
+        DISCOVERY_TOPIC_DATA_TOKEN => {
+          self.sedp_receive_topic_data(None);
+        }
+        DISCOVERY_TOPIC_CLEANUP_TOKEN => {
+          self.topic_cleanup();
 
-            self
-              .topic_cleanup_timer
-              .set_timeout(Self::TOPIC_CLEANUP_PERIOD, ());
-          }
-          DISCOVERY_PARTICIPANT_MESSAGE_TOKEN | P2P_SECURE_DISCOVERY_PARTICIPANT_MESSAGE_TOKEN => {
-            self.receive_participant_message();
-          }
-          DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN => {
-            self.publish_participant_message();
-            self
-              .dcps_participant_message
-              .timer
-              .set_timeout(Self::CHECK_PARTICIPANT_MESSAGES, TimerPolicy::Repeat);
-          }
-          SPDP_LIVENESS_TOKEN => {
-            while let Ok(guid_prefix) = self.spdp_liveness_receiver.try_recv() {
-              discovery_db_write(&self.discovery_db).participant_is_alive(guid_prefix);
-            }
-          }
-          P2P_PARTICIPANT_STATELESS_MESSAGE_TOKEN => {
-            #[cfg(feature = "security")]
-            self.receive_participant_stateless_message();
-          }
-          CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_TIMER_TOKEN => {
-            #[cfg(feature = "security")]
-            self.on_secure_discovery_message_resend_triggered();
-          }
-          P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_TOKEN => {
-            #[cfg(feature = "security")]
-            self.receive_participant_volatile_message();
-          }
-          SECURE_DISCOVERY_PARTICIPANT_DATA_TOKEN => {
-            #[cfg(feature = "security")]
-            self.secure_spdp_receive();
-          }
-          SECURE_DISCOVERY_READER_DATA_TOKEN => {
-            #[cfg(feature = "security")]
-            self.secure_sedp_receive_subscription(None);
-          }
-          SECURE_DISCOVERY_WRITER_DATA_TOKEN => {
-            #[cfg(feature = "security")]
-            self.secure_sedp_receive_publication(None);
+          self
+            .topic_cleanup_timer
+            .set_timeout(Self::TOPIC_CLEANUP_PERIOD, ());
+        }
+        DISCOVERY_PARTICIPANT_MESSAGE_TOKEN | P2P_SECURE_DISCOVERY_PARTICIPANT_MESSAGE_TOKEN => {
+          self.receive_participant_message();
+        }
+        DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN => {
+          self.publish_participant_message();
+          self
+            .dcps_participant_message
+            .timer
+            .set_timeout(Self::CHECK_PARTICIPANT_MESSAGES, TimerPolicy::Repeat);
+        }
+        DISCOVERY_HEALTH_TOPIC_TIMER_TOKEN => {
+          self.publish_health_sample();
+        }
+        SPDP_LIVENESS_TOKEN => {
+          while let Ok((guid_prefix, vendor_id)) = self.spdp_liveness_receiver.try_recv() {
+            discovery_db_write(&self.discovery_db).participant_is_alive(guid_prefix, vendor_id);
           }
+        }
+        P2P_PARTICIPANT_STATELESS_MESSAGE_TOKEN => {
+          #[cfg(feature = "security")]
+          self.receive_participant_stateless_message();
+        }
+        CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_TIMER_TOKEN => {
+          #[cfg(feature = "security")]
+          self.on_secure_discovery_message_resend_triggered();
+        }
+        P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_TOKEN => {
+          #[cfg(feature = "security")]
+          self.receive_participant_volatile_message();
+        }
+        SECURE_DISCOVERY_PARTICIPANT_DATA_TOKEN => {
+          #[cfg(feature = "security")]
+          self.secure_spdp_receive();
+        }
+        SECURE_DISCOVERY_READER_DATA_TOKEN => {
+          #[cfg(feature = "security")]
+          self.secure_sedp_receive_subscription(None);
+        }
+        SECURE_DISCOVERY_WRITER_DATA_TOKEN => {
+          #[cfg(feature = "security")]
+          self.secure_sedp_receive_publication(None);
+        }
 
-          other_token => {
-            error!("discovery event loop got token: {other_token:?}");
-          }
-        } // match
-      } // for
-    } // loop
+        other_token => {
+          error!("discovery event loop got token: {other_token:?}");
+        }
+      } // match
+    } // for
+    true
   } // fn
 
   // Initialize our own participant data into the Discovery DB.
@@ -943,6 +1102,74 @@ impl Discovery {
     });
   }
 
+  // Polls for changes in local interface addresses. This is a portable
+  // replacement for OS-specific change notifications, e.g. netlink.
+  fn check_network_interfaces(&mut self) {
+    let current = get_local_unicast_ip_addrs();
+    let (added, removed) = ip_addr_changes(&self.known_interface_addrs, &current);
+    if added.is_empty() && removed.is_empty() {
+      return;
+    }
+    info!("Network interfaces changed: added {added:?} removed {removed:?}");
+    self.known_interface_addrs = current;
+
+    match self.domain_participant.clone().upgrade() {
+      Some(dp) => {
+        // This also rejoins multicast groups and creates senders on new
+        // interfaces in the event loop.
+        if let Err(e) = dp.update_self_locators() {
+          error!("Cannot update locators after network change: {e:?}");
+        }
+      }
+      None => {
+        error!("Cannot get actual DomainParticipant in check_network_interfaces! Giving up.");
+        return;
+      }
+    }
+
+    // Announce immediately, even if the locators happen to be the same, so that
+    // remote participants reachable over the new interfaces find us quickly.
+    self.refresh_locators();
+    self.send_participant_status(DomainParticipantStatusEvent::NetworkInterfacesChanged {
+      added,
+      removed,
+    });
+  }
+
+  // Called when our own locators have changed.
+  fn refresh_locators(&self) {
+    let dp = if let Some(dp) = self.domain_participant.clone().upgrade() {
+      dp
+    } else {
+      error!("Cannot get actual DomainParticipant in refresh_locators! Giving up.");
+      return;
+    };
+
+    // Update our own participant in Discovery DB and local proxies, and
+    // announce it right away instead of waiting for the next periodic
+    // announcement.
+    self.initialize_participant();
+    self.spdp_publish(&dp);
+
+    discovery_db_write(&self.discovery_db).update_local_endpoint_locators(&dp.self_locators());
+
+    // Re-announce local endpoints and update their proxies in local Writers and
+    // Readers, as in add_local_reader and add_local_writer.
+    let db = discovery_db_read(&self.discovery_db);
+    for reader in db.get_all_local_topic_readers() {
+      self.sedp_publish_single_user_reader(reader);
+      self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
+        discovered_reader_data: reader.clone(),
+      });
+    }
+    for writer in db.get_all_local_topic_writers() {
+      self.sedp_publish_single_user_writer(writer);
+      self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
+        discovered_writer_data: writer.clone(),
+      });
+    }
+  }
+
   pub fn spdp_receive(&mut self) {
     loop {
       let s = self.dcps_participant.reader.take_next_sample();
@@ -993,11 +1220,30 @@ impl Discovery {
     } // loop
   }
 
+  // Check if a remote Participant may have trouble talking to us. Older minor
+  // versions of RTPS 2.x are compatible, but newer ones may use submessages or
+  // parameters that we do not understand, and other major versions are not
+  // compatible at all.
+  fn protocol_mismatch(participant_data: &SpdpDiscoveredParticipantData) -> Option<String> {
+    let ours = ProtocolVersion::THIS_IMPLEMENTATION;
+    let theirs = participant_data.protocol_version;
+    if theirs.major != ours.major || theirs.minor > ours.minor {
+      Some(format!(
+        "RTPS protocol version {}.{} differs from ours {}.{}",
+        theirs.major, theirs.minor, ours.major, ours.minor
+      ))
+    } else if participant_data.vendor_id == VendorId::VENDOR_UNKNOWN {
+      Some("vendor id is unknown".to_string())
+    } else {
+      None
+    }
+  }
+
   fn process_discovered_participant_data(
     &mut self,
     participant_data: &SpdpDiscoveredParticipantData,
   ) {
-    let was_new = discovery_db_write(&self.discovery_db).update_participant(participant_data);
+    let update = discovery_db_write(&self.discovery_db).update_participant(participant_data);
     let guid_prefix = participant_data.participant_guid.prefix;
 
     // Send notification to dp_event_loop. It will update local reader/writer
@@ -1005,12 +1251,26 @@ impl Discovery {
     // available built-in endpoints.
     self.send_discovery_notification(DiscoveryNotificationType::ParticipantUpdated { guid_prefix });
 
-    if was_new {
+    if update == ParticipantUpdate::Changed {
+      self.send_participant_status(DomainParticipantStatusEvent::ParticipantUpdated {
+        dpd: participant_data.into(),
+      });
+    }
+
+    if update == ParticipantUpdate::New {
       // Inform DDS Applications
       self.send_participant_status(DomainParticipantStatusEvent::ParticipantDiscovered {
         dpd: participant_data.into(),
       });
 
+      if let Some(reason) = Self::protocol_mismatch(participant_data) {
+        warn!("Participant {guid_prefix:?} may not interoperate: {reason}");
+        self.send_participant_status(DomainParticipantStatusEvent::ProtocolMismatch {
+          dpd: participant_data.into(),
+          reason,
+        });
+      }
+
       // Send a quick response to make discovery faster.
       //
       // RTPS spec v2.5 Section "8.5.3.1 General Approach" [to SPDP] says
@@ -1049,6 +1309,33 @@ impl Discovery {
     });
   }
 
+  // Same as a participant timeout, but right away. The participant is then
+  // rediscovered from its next SPDP announcement. Announcing ourselves right
+  // away lets a restarted remote, which does not know us yet, discover us.
+  fn preempt_discovery(&mut self, guid_prefix: GuidPrefix) {
+    let known = discovery_db_read(&self.discovery_db)
+      .find_participant_proxy(guid_prefix)
+      .is_some();
+    if known {
+      info!("Preempting discovery of participant {guid_prefix:?}");
+      // false = not disposed, so endpoints go to the attic and are restored
+      // if the same participant is rediscovered.
+      discovery_db_write(&self.discovery_db).remove_participant(guid_prefix, false);
+      self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost { guid_prefix });
+      self.send_participant_status(DomainParticipantStatusEvent::ParticipantLost {
+        id: guid_prefix,
+        reason: LostReason::Preempted,
+      });
+    } else {
+      debug!("preempt_discovery: participant {guid_prefix:?} is not known");
+    }
+
+    match self.domain_participant.clone().upgrade() {
+      Some(dp) => self.spdp_publish(&dp),
+      None => error!("Cannot get actual DomainParticipant in preempt_discovery!"),
+    }
+  }
+
   fn send_endpoint_dispose_message(&self, endpoint_guid: GUID) {
     let is_writer = endpoint_guid.entity_id.entity_kind.is_writer();
     if is_writer {
@@ -1080,6 +1367,13 @@ impl Discovery {
   }
 
   fn on_participant_shutting_down(&mut self) {
+    if let Some(health_topic) = &self.health_topic {
+      health_topic
+        .writer
+        .dispose(&self.domain_participant.guid(), None)
+        .unwrap_or(());
+    }
+
     let db = discovery_db_read(&self.discovery_db);
 
     for reader in db.get_all_local_topic_readers() {
@@ -1142,6 +1436,7 @@ impl Discovery {
         match d {
           Sample::Value(d) => {
             trace!("sedp_receive_subscription - {d:?}");
+            self.check_guid_conflict(EndpointDescription::from(&d));
             let drd = discovery_db_write(&self.discovery_db).update_subscription(&d);
             debug!(
               "sedp_receive_subscription - send_discovery_notification ReaderUpdated  {:?}",
@@ -1160,13 +1455,14 @@ impl Discovery {
           }
           Sample::Dispose(reader_key) => {
             info!("Dispose Reader {reader_key:?}");
-            discovery_db_write(&self.discovery_db).remove_topic_reader(reader_key);
+            let last_known = discovery_db_write(&self.discovery_db).remove_topic_reader(reader_key);
             self.send_discovery_notification(DiscoveryNotificationType::ReaderLost {
               reader_guid: reader_key,
             });
             self.send_participant_status(DomainParticipantStatusEvent::ReaderLost {
               guid: reader_key,
               reason: LostReason::Disposed,
+              last_known: last_known.as_ref().map(EndpointDescription::from),
             });
           }
         }
@@ -1216,6 +1512,7 @@ impl Discovery {
         match d {
           Sample::Value(dwd) => {
             trace!("sedp_receive_publication discovered {:?}", &dwd);
+            self.check_guid_conflict(EndpointDescription::from(&dwd));
             let discovered_writer_data =
               discovery_db_write(&self.discovery_db).update_publication(&dwd);
             self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
@@ -1224,13 +1521,14 @@ impl Discovery {
             debug!("Discovered Writer {:?}", &dwd);
           }
           Sample::Dispose(writer_key) => {
-            discovery_db_write(&self.discovery_db).remove_topic_writer(writer_key);
+            let last_known = discovery_db_write(&self.discovery_db).remove_topic_writer(writer_key);
             self.send_discovery_notification(DiscoveryNotificationType::WriterLost {
               writer_guid: writer_key,
             });
             self.send_participant_status(DomainParticipantStatusEvent::WriterLost {
               guid: writer_key,
               reason: LostReason::Disposed,
+              last_known: last_known.as_ref().map(EndpointDescription::from),
             });
 
             debug!("Disposed Writer {writer_key:?}");
@@ -1240,6 +1538,24 @@ impl Discovery {
     } // loop
   }
 
+  // Reports an announced endpoint that has the GUID of a local endpoint.
+  fn check_guid_conflict(&self, remote: EndpointDescription) {
+    if !self.config.detect_guid_conflicts {
+      return;
+    }
+    let conflict = discovery_db_read(&self.discovery_db).local_guid_conflict(&remote);
+    if let Some(local) = conflict {
+      warn!(
+        "Remote endpoint on topic {} has the GUID of local endpoint on topic {}: {:?}",
+        remote.topic_name, local.topic_name, remote.guid
+      );
+      self.send_participant_status(DomainParticipantStatusEvent::GuidConflict {
+        local: Box::new(local),
+        remote: Box::new(remote),
+      });
+    }
+  }
+
   // TODO: Try to remember why the read_history parameter below was introduced
   // in the first place. Git history should help here.
   // Likely it is something to do with an unreliable network and
@@ -1358,23 +1674,31 @@ impl Discovery {
 
     samples.append(&mut secure_samples);
 
-    let msgs = samples
+    let msgs: Vec<ParticipantMessageData> = samples
       .into_iter()
-      .filter_map(|p| p.value().clone().value());
+      .filter_map(|p| p.value().clone().value())
+      .collect();
 
     let mut db = discovery_db_write(&self.discovery_db);
-    for msg in msgs {
-      db.update_lease_duration(&msg);
+    for msg in &msgs {
+      db.update_lease_duration(msg);
+    }
+    drop(db);
+
+    // Report to observers. Those that have been dropped are forgotten.
+    let mut observers = self.participant_message_observers.lock().unwrap();
+    if !observers.is_empty() {
+      for msg in msgs {
+        observers.retain(|o| o.try_send(msg.clone()).is_ok());
+      }
     }
   }
 
-  fn spdp_publish(&self, local_dp: &DomainParticipant) {
-    // setting 5 times the duration so lease doesn't break if update fails once or
-    // twice
+  pub(crate) fn spdp_publish(&self, local_dp: &DomainParticipant) {
     let data = SpdpDiscoveredParticipantData::from_local_participant(
       local_dp,
       &self.security_opt,
-      5.0 * Duration::from(Self::SPDP_PUBLISH_PERIOD),
+      Duration::from(self.config.spdp_lease_duration()),
     );
 
     #[cfg(feature = "security")]
@@ -1391,6 +1715,29 @@ impl Discovery {
       });
   }
 
+  fn publish_health_sample(&mut self) {
+    let Some(health_topic) = &mut self.health_topic else {
+      return;
+    };
+    let db = discovery_db_read(&self.discovery_db);
+    let (matched_remote_readers, matched_remote_writers) = db.matched_remote_endpoint_counts();
+    let sample = ParticipantHealthSample {
+      participant: self.domain_participant.guid(),
+      uptime: health_topic.started.elapsed().into(),
+      healthy: self.health.snapshot().is_healthy(),
+      discovered_participants: db.remote_participant_count() as u32,
+      local_writers: db.get_all_local_topic_writers().count() as u32,
+      local_readers: db.get_all_local_topic_readers().count() as u32,
+      matched_remote_readers: matched_remote_readers as u32,
+      matched_remote_writers: matched_remote_writers as u32,
+    };
+    drop(db);
+    if let Err(e) = health_topic.writer.write(sample, None) {
+      warn!("Failed to publish participant health: {e:?}");
+    }
+    health_topic.timer.set_timeout(health_topic.interval, ());
+  }
+
   pub fn publish_participant_message(&mut self) {
     // Inspect if we need to send liveness messages
     // See 8.4.13.5 "Implementing Writer Liveliness Protocol .." in the RPTS spec
@@ -1621,11 +1968,13 @@ impl Discovery {
           }
           Sample::Dispose(reader_guid) => {
             info!("Secure Dispose Reader {reader_guid:?}");
-            discovery_db_write(&self.discovery_db).remove_topic_reader(reader_guid);
+            let last_known =
+              discovery_db_write(&self.discovery_db).remove_topic_reader(reader_guid);
             self.send_discovery_notification(DiscoveryNotificationType::ReaderLost { reader_guid });
             self.send_participant_status(DomainParticipantStatusEvent::ReaderLost {
               guid: reader_guid,
               reason: LostReason::Disposed,
+              last_known: last_known.as_ref().map(EndpointDescription::from),
             });
           }
         }
@@ -1681,11 +2030,13 @@ impl Discovery {
           }
           Sample::Dispose(writer_guid) => {
             info!("Secure Dispose Writer {writer_guid:?}");
-            discovery_db_write(&self.discovery_db).remove_topic_writer(writer_guid);
+            let last_known =
+              discovery_db_write(&self.discovery_db).remove_topic_writer(writer_guid);
             self.send_discovery_notification(DiscoveryNotificationType::WriterLost { writer_guid });
             self.send_participant_status(DomainParticipantStatusEvent::WriterLost {
               guid: writer_guid,
               reason: LostReason::Disposed,
+              last_known: last_known.as_ref().map(EndpointDescription::from),
             });
           }
         }
@@ -1698,6 +2049,8 @@ impl Discovery {
     if let Some(security) = self.security_opt.as_mut() {
       // Security is enabled
       security.resend_cached_secure_discovery_messages(
+        &self.discovery_db,
+        &self.discovery_updated_sender,
         &self.dcps_participant_stateless_message.writer,
         &self.dcps_participant_volatile_message_secure.writer,
       );
@@ -1705,7 +2058,7 @@ impl Discovery {
       // Reset timer for resending security messages
       self
         .cached_secure_discovery_messages_resend_timer
-        .set_timeout(Self::CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_PERIOD, ());
+        .set_timeout(security.resend_period(), ());
     }
   }
 
@@ -2342,4 +2695,324 @@ mod tests {
 
     udp_sender.send_to_all(&rr, &addresses);
   }
+
+  #[test]
+  fn discovery_reports_guid_conflict() {
+    use crate::{
+      dds::statusevents::StatusEvented, test::random_data::RandomData, CDRSerializerAdapter,
+      DomainParticipantBuilder, TopicKind,
+    };
+
+    let dp = DomainParticipantBuilder::new(0)
+      .manual_event_loop(true)
+      .build()
+      .unwrap();
+    let qos = QosPolicies::qos_none();
+    let topic = dp
+      .create_topic(
+        "guid_conflict".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    let status_listener = dp.status_listener();
+    let guid_conflicts = || {
+      std::iter::from_fn(|| status_listener.try_recv_status())
+        .filter_map(|event| match event {
+          DomainParticipantStatusEvent::GuidConflict { local, remote } => Some((local, remote)),
+          _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+    let announced = |topic_name: &str| EndpointDescription {
+      updated_time: Utc::now(),
+      guid: writer.guid(),
+      topic_name: topic_name.to_string(),
+      type_name: "RandomData".to_string(),
+      qos: qos.clone(),
+    };
+
+    // Our own announcement coming back is not a conflict
+    dp.with_discovery(|discovery| discovery.check_guid_conflict(announced("guid_conflict")))
+      .unwrap();
+    assert!(guid_conflicts().is_empty());
+
+    dp.with_discovery(|discovery| discovery.check_guid_conflict(announced("other_topic")))
+      .unwrap();
+    match guid_conflicts().as_slice() {
+      [(local, remote)] => {
+        assert_eq!(local.guid, writer.guid());
+        assert_eq!(local.topic_name, "guid_conflict");
+        assert_eq!(remote.guid, writer.guid());
+        assert_eq!(remote.topic_name, "other_topic");
+      }
+      other => panic!("Expected one GuidConflict, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn discovery_protocol_mismatch() {
+    use crate::test::test_data::spdp_participant_data;
+
+    let ours = ProtocolVersion::THIS_IMPLEMENTATION;
+    let mut data = spdp_participant_data().unwrap();
+    data.vendor_id = VendorId::THIS_IMPLEMENTATION;
+    let mismatch = |data: &SpdpDiscoveredParticipantData, version| {
+      Discovery::protocol_mismatch(&SpdpDiscoveredParticipantData {
+        protocol_version: version,
+        ..data.clone()
+      })
+    };
+
+    assert_eq!(mismatch(&data, ours), None);
+    // Older minor versions are compatible
+    let older_minor = ProtocolVersion {
+      minor: ours.minor - 1,
+      ..ours
+    };
+    assert_eq!(mismatch(&data, older_minor), None);
+
+    let other_major = ProtocolVersion {
+      major: ours.major + 1,
+      ..ours
+    };
+    assert!(mismatch(&data, other_major)
+      .unwrap()
+      .contains("protocol version"));
+    let newer_minor = ProtocolVersion {
+      minor: ours.minor + 1,
+      ..ours
+    };
+    assert!(mismatch(&data, newer_minor)
+      .unwrap()
+      .contains("protocol version"));
+
+    data.vendor_id = VendorId::VENDOR_UNKNOWN;
+    assert_eq!(
+      mismatch(&data, ours),
+      Some("vendor id is unknown".to_string())
+    );
+  }
+
+  #[test]
+  fn discovery_reports_protocol_mismatch() {
+    use crate::{
+      dds::statusevents::StatusEvented, structure::guid::GuidPrefix,
+      test::test_data::spdp_participant_data, DomainParticipantBuilder,
+    };
+
+    let dp = DomainParticipantBuilder::new(0)
+      .manual_event_loop(true)
+      .build()
+      .unwrap();
+    let status_listener = dp.status_listener();
+    let mismatches = || {
+      std::iter::from_fn(|| status_listener.try_recv_status())
+        .filter_map(|event| match event {
+          DomainParticipantStatusEvent::ProtocolMismatch { dpd, reason } => Some((dpd, reason)),
+          _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+    let remote = |prefix: [u8; 12], protocol_version| {
+      let data = spdp_participant_data().unwrap();
+      SpdpDiscoveredParticipantData {
+        participant_guid: GUID::new(GuidPrefix::new(&prefix), data.participant_guid.entity_id),
+        protocol_version,
+        vendor_id: VendorId::THIS_IMPLEMENTATION,
+        ..data
+      }
+    };
+
+    let compatible = remote([1; 12], ProtocolVersion::THIS_IMPLEMENTATION);
+    dp.with_discovery(|discovery| discovery.process_discovered_participant_data(&compatible))
+      .unwrap();
+    assert!(mismatches().is_empty());
+
+    let newer = remote(
+      [2; 12],
+      ProtocolVersion {
+        major: ProtocolVersion::THIS_IMPLEMENTATION.major + 1,
+        minor: 0,
+      },
+    );
+    dp.with_discovery(|discovery| discovery.process_discovered_participant_data(&newer))
+      .unwrap();
+    match mismatches().as_slice() {
+      [(dpd, reason)] => {
+        assert_eq!(dpd.guid, newer.participant_guid);
+        assert!(reason.contains("protocol version"), "{reason}");
+      }
+      other => panic!("Expected one ProtocolMismatch, got {other:?}"),
+    }
+
+    // Reported only when the participant is first discovered
+    dp.with_discovery(|discovery| discovery.process_discovered_participant_data(&newer))
+      .unwrap();
+    assert!(mismatches().is_empty());
+  }
+
+  // Handshake messages are resent every 100 ms, and only once, so that
+  // handshakes time out quickly.
+  #[cfg(feature = "security")]
+  fn secure_participant_with_manual_event_loop() -> DomainParticipant {
+    use crate::{
+      security::config::{DomainParticipantSecurityConfigFiles, HandshakeRetryPolicy},
+      DomainParticipantBuilder,
+    };
+
+    let config = DomainParticipantSecurityConfigFiles::with_ros_default_names(
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/examples/security_configuration_files"
+      ),
+      "password123".to_string(),
+    )
+    .with_handshake_retry_policy(HandshakeRetryPolicy {
+      resend_period: StdDuration::from_millis(100),
+      max_resends: 1,
+    });
+    DomainParticipantBuilder::new(0)
+      .builtin_security(config)
+      .manual_event_loop(true)
+      .build()
+      .unwrap()
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn discovery_handshake_timeout_fails_authentication() {
+    use crate::{
+      dds::statusevents::StatusEvented, discovery::secure_discovery::AuthenticationStatus,
+      security::authentication::authentication_builtin::DiscHandshakeState,
+      structure::guid::GuidPrefix,
+    };
+
+    let dp = secure_participant_with_manual_event_loop();
+    let status_listener = dp.status_listener();
+    let failed_events = |remote| {
+      std::iter::from_fn(|| status_listener.try_recv_status())
+        .filter(|event| {
+          matches!(
+            event,
+            DomainParticipantStatusEvent::Authentication {
+              participant,
+              status: AuthenticationStatus::Failed,
+            } if *participant == remote
+          )
+        })
+        .count()
+    };
+    let remote = GuidPrefix::new(&[7; 12]);
+    let auth_status = |discovery: &mut Discovery| {
+      discovery.on_secure_discovery_message_resend_triggered();
+      discovery_db_read(&discovery.discovery_db).get_authentication_status(remote)
+    };
+
+    // Let the participant start up, so that its status events do not fill the
+    // status channel.
+    for _ in 0..20 {
+      dp.poll_timeout(StdDuration::from_millis(10));
+      failed_events(remote);
+    }
+
+    // The remote should send us a handshake request, but never does
+    dp.with_discovery(|discovery| {
+      discovery
+        .security_opt
+        .as_mut()
+        .unwrap()
+        .update_handshake_state(remote, DiscHandshakeState::PendingRequestMessage);
+    })
+    .unwrap();
+    assert_eq!(dp.with_discovery(auth_status).unwrap(), None);
+
+    // Waiting is allowed as long as resending would take: two resend periods
+    std::thread::sleep(StdDuration::from_millis(300));
+    assert_eq!(
+      dp.with_discovery(auth_status).unwrap(),
+      Some(AuthenticationStatus::Failed)
+    );
+
+    // The event loop reports the failure to the application
+    let deadline = std::time::Instant::now() + StdDuration::from_secs(5);
+    let mut reported = 0;
+    while reported == 0 && std::time::Instant::now() < deadline {
+      dp.poll_timeout(StdDuration::from_millis(10));
+      reported += failed_events(remote);
+    }
+    assert_eq!(reported, 1, "No Authentication event with status Failed");
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn discovery_reports_handshake_progress() {
+    use futures::{FutureExt, StreamExt};
+
+    use crate::{
+      dds::statusevents::StatusEvented, discovery::secure_discovery::HandshakeProgress,
+      security::authentication::authentication_builtin::DiscHandshakeState,
+      structure::guid::GuidPrefix,
+    };
+
+    let dp = secure_participant_with_manual_event_loop();
+    let status_listener = dp.status_listener();
+    while status_listener.try_recv_status().is_some() {}
+    let mut security_events = status_listener.as_async_security_status_stream();
+    let mut next_security_event = || security_events.next().now_or_never().flatten();
+
+    let awaiting = GuidPrefix::new(&[7; 12]);
+    let requested = GuidPrefix::new(&[8; 12]);
+    dp.with_discovery(|discovery| {
+      let not_security = DomainParticipantStatusEvent::NetworkInterfacesChanged {
+        added: vec![],
+        removed: vec![],
+      };
+      assert!(!not_security.is_security_event());
+      discovery.send_participant_status(not_security);
+      let security = discovery.security_opt.as_mut().unwrap();
+      security.update_handshake_state(awaiting, DiscHandshakeState::PendingRequestMessage);
+      security.update_handshake_state(requested, DiscHandshakeState::PendingReplyMessage);
+    })
+    .unwrap();
+
+    // Each step is reported, and other events are skipped by the stream.
+    for (remote, expected) in [
+      (awaiting, HandshakeProgress::AwaitingRequest),
+      (requested, HandshakeProgress::RequestSent),
+    ] {
+      let event = next_security_event().expect("Handshake progress was not reported");
+      assert!(event.is_security_event());
+      match event {
+        DomainParticipantStatusEvent::HandshakeProgress {
+          participant,
+          progress,
+        } => assert_eq!((participant, progress), (remote, expected)),
+        other => panic!("Expected {expected:?} for {remote:?}, got {other:?}"),
+      }
+    }
+    assert!(next_security_event().is_none());
+
+    // The remote never sends its request. The failure tells where the
+    // handshake stalled.
+    std::thread::sleep(StdDuration::from_millis(300));
+    dp.with_discovery(|discovery| discovery.on_secure_discovery_message_resend_triggered())
+      .unwrap();
+    match next_security_event() {
+      Some(DomainParticipantStatusEvent::HandshakeProgress {
+        participant,
+        progress: HandshakeProgress::Failed { reason },
+      }) => {
+        assert_eq!(participant, awaiting);
+        assert_eq!(reason, "Timed out in state Some(AwaitingRequest)");
+      }
+      other => panic!("Expected a failed handshake, got {other:?}"),
+    }
+  }
 }
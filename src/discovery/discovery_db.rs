@@ -1,5 +1,6 @@
 use std::{
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet, HashMap},
+  mem::size_of,
   sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
   time::Instant,
 };
@@ -10,22 +11,36 @@ use log::{debug, error, info, trace, warn};
 
 use crate::{
   dds::{
+    discovery_snapshot::{
+      DiscoverySnapshot, EndpointSnapshot, MatchSnapshot, ParticipantSnapshot, TopicSnapshot,
+    },
+    endpoint_options::EndpointOptions,
     participant::DomainParticipant,
-    qos::HasQoSPolicy,
-    statusevents::{DomainParticipantStatusEvent, LostReason, StatusChannelSender},
+    qos::{HasQoSPolicy, QosPolicies},
+    statusevents::{
+      DomainParticipantStatusEvent, EndpointDescription, LostReason, StatusChannelSender,
+    },
     topic::{Topic, TopicDescription},
   },
+  messages::vendor_id::VendorId,
   rtps::{
-    reader::ReaderIngredients, rtps_reader_proxy::RtpsReaderProxy,
+    constant::{
+      DISCOVERY_LISTENER_TOKEN, DISCOVERY_MUL_LISTENER_TOKEN, USER_TRAFFIC_LISTENER_TOKEN,
+      USER_TRAFFIC_MUL_LISTENER_TOKEN,
+    },
+    reader::ReaderIngredients,
+    rtps_reader_proxy::RtpsReaderProxy,
     rtps_writer_proxy::RtpsWriterProxy,
   },
   structure::{
     duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
+    locator::Locator,
   },
 };
 use super::{
+  payload_compression_property::PayloadCompressionProperty,
   sedp_messages::{
     topics_inconsistent, DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData,
     ParticipantMessageData, ReaderProxy, SubscriptionBuiltinTopicData, TopicBuiltinTopicData,
@@ -45,6 +60,17 @@ const DEFAULT_PARTICIPANT_LEASE_DURATION: Duration = Duration::from_secs(60);
 // How much longer to wait than lease duration before pronouncing lost.
 const PARTICIPANT_LEASE_DURATION_TOLERANCE: Duration = Duration::from_secs(0);
 
+// Outcome of DiscoveryDB::update_participant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParticipantUpdate {
+  // Participant was not known before
+  New,
+  // Participant was known, and its locators, lease or name changed
+  Changed,
+  // Nothing of interest changed
+  Unchanged,
+}
+
 // TODO: Let DiscoveryDB itself become thread-safe and support smaller-scope
 // lock
 pub(crate) struct DiscoveryDB {
@@ -60,6 +86,9 @@ pub(crate) struct DiscoveryDB {
   local_topic_writers: BTreeMap<GUID, DiscoveredWriterData>,
   // local reader proxies for topics (topic name acts as key)
   local_topic_readers: BTreeMap<GUID, DiscoveredReaderData>,
+  // Locators given by the application for local readers and writers. These
+  // replace the participant defaults, also when our interfaces change.
+  local_endpoint_options: BTreeMap<GUID, EndpointOptions>,
 
   // remote readers and writers (via discovery)
   external_topic_readers: BTreeMap<GUID, DiscoveredReaderData>,
@@ -133,6 +162,7 @@ impl DiscoveryDB {
       authentication_statuses: BTreeMap::new(),
       local_topic_writers: BTreeMap::new(),
       local_topic_readers: BTreeMap::new(),
+      local_endpoint_options: BTreeMap::new(),
       external_topic_readers: BTreeMap::new(),
       external_topic_writers: BTreeMap::new(),
       external_topic_readers_attic: BTreeMap::new(),
@@ -150,8 +180,9 @@ impl DiscoveryDB {
       .unwrap_or_else(|e| error!("Cannot report participant status: {e:?}"));
   }
 
-  // Returns if participant was previously unknown
-  pub fn update_participant(&mut self, data: &SpdpDiscoveredParticipantData) -> bool {
+  // Returns whether the participant was previously unknown, or known but its
+  // data changed materially.
+  pub fn update_participant(&mut self, data: &SpdpDiscoveredParticipantData) -> ParticipantUpdate {
     debug!("update_participant: {:?}", &data);
     let guid = data.participant_guid;
 
@@ -159,7 +190,7 @@ impl DiscoveryDB {
     if guid.entity_id != EntityId::PARTICIPANT {
       error!("Discovered participant GUID entity_id is not for participant: {guid:?}");
       // Maybe we should discard the participant here?
-      return false;
+      return ParticipantUpdate::Unchanged;
     }
 
     // We allow discovery to discover self, since our discovery readers
@@ -172,13 +203,18 @@ impl DiscoveryDB {
     //   return
     // }
 
-    let mut new_participant = false;
-    if !self.participant_proxies.contains_key(&guid.prefix) {
+    let mut update = ParticipantUpdate::Unchanged;
+    if let Some(previous) = self.participant_proxies.get(&guid.prefix) {
+      if guid != self.my_guid && previous.differs_materially(data) {
+        info!("Remote participant data changed: {:?}", &data);
+        update = ParticipantUpdate::Changed;
+      }
+    } else {
       info!("New remote participant: {:?}", &data);
-      new_participant = true;
+      update = ParticipantUpdate::New;
       if guid == self.my_guid {
         info!("Remote participant {guid:?} is myself, but some reflection is good.");
-        new_participant = false;
+        update = ParticipantUpdate::Unchanged;
       }
 
       // This may be a rediscovery: Participant lease time timed out, but then
@@ -198,16 +234,33 @@ impl DiscoveryDB {
 
     // In any case:
     // Update SpdpDiscoveredParticipantData to DB
-    self.participant_proxies.insert(guid.prefix, data.clone());
+    // If the SPDP data does not name the vendor, keep what we learned from
+    // the RTPS headers, see participant_is_alive().
+    let mut data = data.clone();
+    if data.vendor_id == VendorId::VENDOR_UNKNOWN {
+      if let Some(previous) = self.participant_proxies.get(&guid.prefix) {
+        data.vendor_id = previous.vendor_id;
+      }
+    }
+    self.participant_proxies.insert(guid.prefix, data);
     // Timestamp last life sign fro participant
     self
       .participant_last_life_signs
       .insert(guid.prefix, Instant::now());
 
-    new_participant
+    update
   }
 
-  pub fn participant_is_alive(&mut self, guid_prefix: GuidPrefix) {
+  // `vendor_id` is from the RTPS header of the SPDP message. It is recorded
+  // if the SPDP data did not name the vendor.
+  pub fn participant_is_alive(&mut self, guid_prefix: GuidPrefix, vendor_id: Option<VendorId>) {
+    if let (Some(vendor_id), Some(participant)) =
+      (vendor_id, self.participant_proxies.get_mut(&guid_prefix))
+    {
+      if participant.vendor_id == VendorId::VENDOR_UNKNOWN {
+        participant.vendor_id = vendor_id;
+      }
+    }
     if let Some(ts) = self.participant_last_life_signs.get_mut(&guid_prefix) {
       let now = Instant::now();
       if now.duration_since(*ts) > std::time::Duration::from_secs(1) {
@@ -250,6 +303,14 @@ impl DiscoveryDB {
     }
   }
 
+  pub fn remote_participant_count(&self) -> usize {
+    self
+      .participant_proxies
+      .keys()
+      .filter(|p| **p != self.my_guid.prefix)
+      .count()
+  }
+
   pub fn find_participant_proxy(
     &self,
     guid_prefix: GuidPrefix,
@@ -270,9 +331,10 @@ impl DiscoveryDB {
     }
   }
 
-  pub fn remove_topic_reader(&mut self, guid: GUID) {
+  // Returns the last known data of the removed reader, if it was known.
+  pub fn remove_topic_reader(&mut self, guid: GUID) -> Option<DiscoveredReaderData> {
     info!("remove_topic_reader {guid:?}");
-    self.external_topic_readers.remove(&guid);
+    self.external_topic_readers.remove(&guid)
   }
 
   #[cfg(feature = "security")]
@@ -298,8 +360,9 @@ impl DiscoveryDB {
     }
   }
 
-  pub fn remove_topic_writer(&mut self, guid: GUID) {
-    self.external_topic_writers.remove(&guid);
+  // Returns the last known data of the removed writer, if it was known.
+  pub fn remove_topic_writer(&mut self, guid: GUID) -> Option<DiscoveredWriterData> {
+    self.external_topic_writers.remove(&guid)
   }
 
   // Delete participant proxies, if we have not heard of them within
@@ -400,13 +463,45 @@ impl DiscoveryDB {
   }
 
   pub fn update_local_topic_writer(&mut self, writer: DiscoveredWriterData) {
-    self
-      .local_topic_writers
-      .insert(writer.writer_proxy.remote_writer_guid, writer);
+    let guid = writer.writer_proxy.remote_writer_guid;
+    self.local_topic_writers.insert(guid, writer);
+    self.apply_local_endpoint_options(guid);
   }
 
   pub fn remove_local_topic_writer(&mut self, guid: GUID) {
     self.local_topic_writers.remove(&guid);
+    self.local_endpoint_options.remove(&guid);
+  }
+
+  // Set before the endpoint is added with update_local_topic_writer or
+  // update_local_topic_reader.
+  pub fn set_local_endpoint_options(&mut self, guid: GUID, options: &EndpointOptions) {
+    if options.has_locators() {
+      self.local_endpoint_options.insert(guid, options.clone());
+    } else {
+      self.local_endpoint_options.remove(&guid);
+    }
+    self.apply_local_endpoint_options(guid);
+  }
+
+  fn apply_local_endpoint_options(&mut self, guid: GUID) {
+    let Some(options) = self.local_endpoint_options.get(&guid) else {
+      return;
+    };
+    if let Some(writer) = self.local_topic_writers.get_mut(&guid) {
+      let proxy = &mut writer.writer_proxy;
+      options.apply(
+        &mut proxy.unicast_locator_list,
+        &mut proxy.multicast_locator_list,
+      );
+    }
+    if let Some(reader) = self.local_topic_readers.get_mut(&guid) {
+      let proxy = &mut reader.reader_proxy;
+      options.apply(
+        &mut proxy.unicast_locator_list,
+        &mut proxy.multicast_locator_list,
+      );
+    }
   }
 
   // TODO: This is silly. Returns one of the parameters cloned, or None
@@ -615,6 +710,7 @@ impl DiscoveryDB {
       self.send_participant_status(DomainParticipantStatusEvent::TopicDetected {
         name: dtd.topic_data.name.clone(),
         type_name: dtd.topic_data.type_name.clone(),
+        topic_data: Box::new((&dtd.topic_data).into()),
       });
     };
     if let Some(ev) = inconsistency_event_to_send {
@@ -637,6 +733,7 @@ impl DiscoveryDB {
     topic: &Topic,
     reader: &ReaderIngredients,
     sec_info_opt: Option<EndpointSecurityInfo>,
+    payload_compression: Option<PayloadCompressionProperty>,
   ) {
     let reader_guid = reader.guid;
 
@@ -658,15 +755,18 @@ impl DiscoveryDB {
       reader_proxy: ReaderProxy::from(reader_proxy),
       subscription_topic_data: subscription_data,
       content_filter,
+      payload_compression,
     };
 
     self
       .local_topic_readers
       .insert(reader_guid, discovered_reader_data);
+    self.apply_local_endpoint_options(reader_guid);
   }
 
   pub fn remove_local_topic_reader(&mut self, guid: GUID) {
     self.local_topic_readers.remove(&guid);
+    self.local_endpoint_options.remove(&guid);
   }
 
   pub fn get_local_topic_reader(&self, guid: GUID) -> Option<&DiscoveredReaderData> {
@@ -677,6 +777,55 @@ impl DiscoveryDB {
     self.local_topic_writers.get(&guid)
   }
 
+  // Is the GUID used by a local Reader or Writer?
+  pub fn is_local_endpoint(&self, guid: GUID) -> bool {
+    self.local_topic_writers.contains_key(&guid) || self.local_topic_readers.contains_key(&guid)
+  }
+
+  // The local endpoint whose GUID an announced endpoint has, if the topic or
+  // type differs. Our own announcements also come back to us, but with the
+  // same topic and type, so they are not conflicts.
+  pub fn local_guid_conflict(
+    &self,
+    announced: &EndpointDescription,
+  ) -> Option<EndpointDescription> {
+    let local = self
+      .local_topic_writers
+      .get(&announced.guid)
+      .map(EndpointDescription::from)
+      .or_else(|| {
+        self
+          .local_topic_readers
+          .get(&announced.guid)
+          .map(EndpointDescription::from)
+      })?;
+    (local.topic_name != announced.topic_name || local.type_name != announced.type_name)
+      .then_some(local)
+  }
+
+  // A remote or local Writer
+  pub fn get_writer(&self, guid: GUID) -> Option<&DiscoveredWriterData> {
+    self
+      .external_topic_writers
+      .get(&guid)
+      .or_else(|| self.local_topic_writers.get(&guid))
+  }
+
+  // A remote or local Reader
+  pub fn get_reader(&self, guid: GUID) -> Option<&DiscoveredReaderData> {
+    self
+      .external_topic_readers
+      .get(&guid)
+      .or_else(|| self.local_topic_readers.get(&guid))
+  }
+
+  // QoS of a remote or local Writer
+  pub fn writer_qos(&self, guid: GUID) -> Option<QosPolicies> {
+    self
+      .get_writer(guid)
+      .map(|w| w.publication_topic_data.qos())
+  }
+
   pub fn get_all_local_topic_readers(&self) -> impl Iterator<Item = &DiscoveredReaderData> {
     self.local_topic_readers.values()
   }
@@ -685,6 +834,80 @@ impl DiscoveryDB {
     self.local_topic_writers.values()
   }
 
+  // Topics that currently have writers, local or remote: topic name -> type name
+  pub fn written_topics(&self) -> BTreeMap<String, String> {
+    self
+      .external_topic_writers
+      .values()
+      .chain(self.local_topic_writers.values())
+      .map(|w| {
+        (
+          w.publication_topic_data.topic_name.clone(),
+          w.publication_topic_data.type_name.clone(),
+        )
+      })
+      .collect()
+  }
+
+  // Counts remote readers on topics where we have a writer, and remote writers
+  // on topics where we have a reader. This is a topic name match only, QoS
+  // compatibility is not checked.
+  pub fn matched_remote_endpoint_counts(&self) -> (usize, usize) {
+    let written_topics: BTreeSet<&str> = self
+      .local_topic_writers
+      .values()
+      .map(|w| w.publication_topic_data.topic_name.as_str())
+      .collect();
+    let read_topics: BTreeSet<&str> = self
+      .local_topic_readers
+      .values()
+      .map(|r| r.subscription_topic_data.topic_name().as_str())
+      .collect();
+    let readers = self
+      .external_topic_readers
+      .values()
+      .filter(|r| written_topics.contains(r.subscription_topic_data.topic_name().as_str()))
+      .count();
+    let writers = self
+      .external_topic_writers
+      .values()
+      .filter(|w| read_topics.contains(w.publication_topic_data.topic_name.as_str()))
+      .count();
+    (readers, writers)
+  }
+
+  // Replace the locators of local readers and writers after our network
+  // interfaces have changed. Locators are chosen as in
+  // RtpsReaderProxy::from_reader and DiscoveredWriterData::new. Locators set
+  // by the application stay as they are.
+  pub fn update_local_endpoint_locators(
+    &mut self,
+    self_locators: &HashMap<mio_06::Token, Vec<Locator>>,
+  ) {
+    let locators = |token| self_locators.get(&token).cloned().unwrap_or_default();
+    let tokens = |guid: GUID| {
+      if guid.entity_id.kind().is_user_defined() {
+        (USER_TRAFFIC_LISTENER_TOKEN, USER_TRAFFIC_MUL_LISTENER_TOKEN)
+      } else {
+        (DISCOVERY_LISTENER_TOKEN, DISCOVERY_MUL_LISTENER_TOKEN)
+      }
+    };
+
+    for reader in self.local_topic_readers.values_mut() {
+      let (unicast_token, multicast_token) = tokens(reader.reader_proxy.remote_reader_guid);
+      reader.reader_proxy.unicast_locator_list = locators(unicast_token);
+      reader.reader_proxy.multicast_locator_list = locators(multicast_token);
+    }
+    for writer in self.local_topic_writers.values_mut() {
+      let (unicast_token, _) = tokens(writer.writer_proxy.remote_writer_guid);
+      writer.writer_proxy.unicast_locator_list = locators(unicast_token);
+    }
+    let guids: Vec<GUID> = self.local_endpoint_options.keys().copied().collect();
+    for guid in guids {
+      self.apply_local_endpoint_options(guid);
+    }
+  }
+
   // Note:
   // If multiple participants announce the same topic, this will
   // return duplicates, one per announcing participant.
@@ -697,6 +920,65 @@ impl DiscoveryDB {
       .flat_map(|(_, gm)| gm.values().map(|dtd| &dtd.1))
   }
 
+  // Rough estimate of the memory used by the database: the fixed size of the
+  // entries. Strings, locator lists, and map overhead are not counted.
+  pub fn approximate_size(&self) -> usize {
+    let writers = self.local_topic_writers.len()
+      + self.external_topic_writers.len()
+      + self.external_topic_writers_attic.len();
+    let readers = self.local_topic_readers.len()
+      + self.external_topic_readers.len()
+      + self.external_topic_readers_attic.len();
+    let topics: usize = self.topics.values().map(BTreeMap::len).sum();
+    self.participant_proxies.len() * size_of::<SpdpDiscoveredParticipantData>()
+      + writers * size_of::<DiscoveredWriterData>()
+      + readers * size_of::<DiscoveredReaderData>()
+      + topics * size_of::<(DiscoveredVia, DiscoveredTopicData)>()
+  }
+
+  // Type names in use, each with the Topics it is used on, both sorted by
+  // name. Collected from both local and remote endpoints, and from Topics
+  // announced by remote participants, because not all implementations
+  // announce Topics. Built-in endpoints are left out.
+  pub fn types_in_use(&self) -> Vec<(String, Vec<String>)> {
+    let mut types: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut add = |type_name: &str, topic_name: &str| {
+      types
+        .entry(type_name.to_string())
+        .or_default()
+        .insert(topic_name.to_string());
+    };
+    // Local Topics are not taken from here, as the built-in Topics are also
+    // defined locally. Remote participants do not announce built-in Topics.
+    for (via, dtd) in self.topics.values().flat_map(BTreeMap::values) {
+      if *via == DiscoveredVia::Topic {
+        add(dtd.type_name(), dtd.topic_name());
+      }
+    }
+    for w in self
+      .external_topic_writers
+      .values()
+      .chain(self.local_topic_writers.values())
+      .filter(|w| !w.writer_proxy.remote_writer_guid.is_built_in())
+    {
+      let topic = &w.publication_topic_data;
+      add(&topic.type_name, &topic.topic_name);
+    }
+    for r in self
+      .external_topic_readers
+      .values()
+      .chain(self.local_topic_readers.values())
+      .filter(|r| !r.reader_proxy.remote_reader_guid.is_built_in())
+    {
+      let topic = &r.subscription_topic_data;
+      add(topic.type_name(), topic.topic_name());
+    }
+    types
+      .into_iter()
+      .map(|(type_name, topics)| (type_name, topics.into_iter().collect()))
+      .collect()
+  }
+
   // a Topic may have multiple definitions, because there may be multiple
   // participants publishing the topic information.
   // At least the QoS details may be different.
@@ -793,6 +1075,121 @@ impl DiscoveryDB {
       .for_each(|(_guid, p)| p.last_updated = now);
   }
 
+  // Summary of the whole database, for DomainParticipant::discovery_snapshot
+  pub fn snapshot(&self) -> DiscoverySnapshot {
+    let now = Instant::now();
+    let locators = |list: &[Locator]| list.iter().map(Locator::to_string).collect::<Vec<_>>();
+    let reader_snapshot = |r: &DiscoveredReaderData| EndpointSnapshot {
+      guid: r.reader_proxy.remote_reader_guid,
+      topic_name: r.subscription_topic_data.topic_name().clone(),
+      type_name: r.subscription_topic_data.type_name().clone(),
+      qos: r.subscription_topic_data.qos(),
+      unicast_locators: locators(&r.reader_proxy.unicast_locator_list),
+      multicast_locators: locators(&r.reader_proxy.multicast_locator_list),
+      matches: Vec::new(),
+    };
+    let writer_snapshot = |w: &DiscoveredWriterData| EndpointSnapshot {
+      guid: w.writer_proxy.remote_writer_guid,
+      topic_name: w.publication_topic_data.topic_name.clone(),
+      type_name: w.publication_topic_data.type_name.clone(),
+      qos: w.publication_topic_data.qos(),
+      unicast_locators: locators(&w.writer_proxy.unicast_locator_list),
+      multicast_locators: locators(&w.writer_proxy.multicast_locator_list),
+      matches: Vec::new(),
+    };
+
+    // Compatibility is checked with the Writer QoS as offered, and the Reader
+    // QoS as requested, as the RTPS Writers and Readers do.
+    let local_writers = self
+      .local_topic_writers
+      .values()
+      .map(|w| {
+        let mut snapshot = writer_snapshot(w);
+        snapshot.matches = self
+          .readers_on_topic(&snapshot.topic_name)
+          .into_iter()
+          .filter(|r| r.reader_proxy.remote_reader_guid.prefix != self.my_guid.prefix)
+          .map(|r| MatchSnapshot {
+            remote: r.reader_proxy.remote_reader_guid,
+            incompatible_policy: snapshot
+              .qos
+              .compliance_failure_wrt(&r.subscription_topic_data.qos()),
+          })
+          .collect();
+        snapshot
+      })
+      .collect();
+    let local_readers = self
+      .local_topic_readers
+      .values()
+      .map(|r| {
+        let mut snapshot = reader_snapshot(r);
+        snapshot.matches = self
+          .writers_on_topic(&snapshot.topic_name)
+          .into_iter()
+          .filter(|w| w.writer_proxy.remote_writer_guid.prefix != self.my_guid.prefix)
+          .map(|w| MatchSnapshot {
+            remote: w.writer_proxy.remote_writer_guid,
+            incompatible_policy: w
+              .publication_topic_data
+              .qos()
+              .compliance_failure_wrt(&snapshot.qos),
+          })
+          .collect();
+        snapshot
+      })
+      .collect();
+
+    let participants = self
+      .participant_proxies
+      .iter()
+      .filter(|(prefix, _)| **prefix != self.my_guid.prefix)
+      .map(|(prefix, p)| ParticipantSnapshot {
+        guid: p.participant_guid,
+        entity_name: p.entity_name.clone(),
+        vendor_id: p.vendor_id.to_string(),
+        lease_duration: p.lease_duration.map(|d| d.to_std()),
+        since_last_life_sign: self
+          .participant_last_life_signs
+          .get(prefix)
+          .map(|t| now.saturating_duration_since(*t)),
+        metatraffic_unicast_locators: locators(&p.metatraffic_unicast_locators),
+        metatraffic_multicast_locators: locators(&p.metatraffic_multicast_locators),
+        default_unicast_locators: locators(&p.default_unicast_locators),
+        default_multicast_locators: locators(&p.default_multicast_locators),
+        #[cfg(feature = "security")]
+        authentication_status: self.get_authentication_status(*prefix),
+        writers: self
+          .external_topic_writers
+          .range(prefix.range())
+          .map(|(_, w)| writer_snapshot(w))
+          .collect(),
+        readers: self
+          .external_topic_readers
+          .range(prefix.range())
+          .map(|(_, r)| reader_snapshot(r))
+          .collect(),
+      })
+      .collect();
+
+    let topics = self
+      .all_user_topics()
+      .map(|t| TopicSnapshot {
+        name: t.topic_name().clone(),
+        type_name: t.type_name().clone(),
+        qos: t.topic_data.qos(),
+      })
+      .collect();
+
+    DiscoverySnapshot {
+      local_participant: self.my_guid,
+      local_writers,
+      local_readers,
+      participants,
+      topics,
+    }
+  }
+
   #[cfg(feature = "security")]
   pub fn get_authentication_status(&self, guid_prefix: GuidPrefix) -> Option<AuthenticationStatus> {
     self.authentication_statuses.get(&guid_prefix).copied()
@@ -810,7 +1207,7 @@ impl DiscoveryDB {
 
 #[cfg(test)]
 mod tests {
-  use std::{sync::Mutex, time::Duration as StdDuration};
+  use std::{net::SocketAddr, time::Duration as StdDuration};
 
   use byteorder::LittleEndian;
   use mio_extras::channel as mio_channel;
@@ -818,12 +1215,10 @@ mod tests {
   use super::*;
   use crate::{
     dds::{
-      qos::QosPolicies,
-      statusevents::{sync_status_channel, DataReaderStatus},
+      qos::{policy, QosPolicies, QosPolicyBuilder, QosPolicyId},
+      statusevents::sync_status_channel,
       topic::TopicKind,
-      with_key::simpledatareader::ReaderCommand,
     },
-    mio_source,
     serialization::CDRSerializerAdapter,
     structure::guid::*,
     test::{
@@ -859,6 +1254,73 @@ mod tests {
     // TODO: more operations tests
   }
 
+  #[test]
+  fn discdb_participant_vendor_id_from_header() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    // The SPDP data does not name the vendor
+    let mut data = spdp_participant_data().unwrap();
+    data.vendor_id = VendorId::VENDOR_UNKNOWN;
+    let prefix = data.participant_guid.prefix;
+    let vendor_id = |db: &DiscoveryDB| db.find_participant_proxy(prefix).unwrap().vendor_id;
+
+    discovery_db.update_participant(&data);
+    discovery_db.participant_is_alive(prefix, None);
+    assert_eq!(vendor_id(&discovery_db), VendorId::VENDOR_UNKNOWN);
+    discovery_db.participant_is_alive(prefix, Some(VendorId::ATOSTEK));
+    assert_eq!(vendor_id(&discovery_db), VendorId::ATOSTEK);
+
+    // Kept over later SPDP updates
+    discovery_db.update_participant(&data);
+    assert_eq!(vendor_id(&discovery_db), VendorId::ATOSTEK);
+
+    // The SPDP data wins, if it names the vendor
+    let eprosima = VendorId {
+      vendor_id: [0x01, 0x0f],
+    };
+    let mut named = data.clone();
+    named.vendor_id = eprosima;
+    discovery_db.update_participant(&named);
+    discovery_db.participant_is_alive(prefix, Some(VendorId::ATOSTEK));
+    assert_eq!(vendor_id(&discovery_db), eprosima);
+  }
+
+  #[test]
+  fn discdb_participant_update_detection() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+
+    let mut discoverydb = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+    let mut data = spdp_participant_data().unwrap();
+
+    assert_eq!(
+      discoverydb.update_participant(&data),
+      ParticipantUpdate::New
+    );
+    assert_eq!(
+      discoverydb.update_participant(&data),
+      ParticipantUpdate::Unchanged
+    );
+
+    data.lease_duration = Some(Duration::from_secs(42));
+    assert_eq!(
+      discoverydb.update_participant(&data),
+      ParticipantUpdate::Changed
+    );
+  }
+
   #[test]
   fn discdb_writer_proxies() {
     let (discovery_db_event_sender, _discovery_db_event_receiver) =
@@ -914,9 +1376,13 @@ mod tests {
       .unwrap();
 
     let writer_data = DiscoveredWriterData::new(&dw, &topic, &domain_participant, None);
+    let writer_guid = writer_data.writer_proxy.remote_writer_guid;
+    let writer_qos = writer_data.publication_topic_data.qos();
 
+    assert_eq!(discovery_db.writer_qos(writer_guid), None);
     discovery_db.update_local_topic_writer(writer_data);
     assert_eq!(discovery_db.local_topic_writers.len(), 1);
+    assert_eq!(discovery_db.writer_qos(writer_guid), Some(writer_qos));
 
     let publisher2 = domain_participant
       .create_publisher(&QosPolicies::qos_none())
@@ -939,6 +1405,7 @@ mod tests {
       reader_proxy: reader1.clone(),
       subscription_topic_data: reader1sub.clone(),
       content_filter: None,
+      payload_compression: None,
     };
     discovery_db.update_subscription(&dreader1);
 
@@ -950,6 +1417,7 @@ mod tests {
       reader_proxy: reader2,
       subscription_topic_data: reader2sub,
       content_filter: None,
+      payload_compression: None,
     };
     discovery_db.update_subscription(&dreader2);
 
@@ -959,6 +1427,7 @@ mod tests {
       reader_proxy: reader3,
       subscription_topic_data: reader3sub,
       content_filter: None,
+      payload_compression: None,
     };
     discovery_db.update_subscription(&dreader3);
 
@@ -986,76 +1455,282 @@ mod tests {
       status_sender,
     );
 
-    // Create reader ingredients
-    let (notification_sender1, _notification_receiver1) = mio_extras::channel::sync_channel(100);
-    let (_notification_event_source1, notification_event_sender1) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker1 = Arc::new(Mutex::new(None));
-
-    let (status_sender1, _status_receiver1) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (_reader_commander1, reader_command_receiver1) =
-      mio_extras::channel::sync_channel::<ReaderCommand>(100);
-
     let topic_cache =
       dp.dds_cache()
         .write()
         .unwrap()
         .add_new_topic(topic.name(), topic.get_type(), &topic.qos());
 
-    let reader1_ing = ReaderIngredients {
-      guid: GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
-      notification_sender: notification_sender1,
-      status_sender: status_sender1,
-      topic_name: topic.name(),
-      topic_cache_handle: topic_cache.clone(),
-      like_stateless: false,
-      qos_policy: QosPolicies::qos_none(),
-      data_reader_command_receiver: reader_command_receiver1,
-      data_reader_waker: data_reader_waker1,
-      poll_event_sender: notification_event_sender1,
-      security_plugins: None,
-    };
+    let (reader1_ing, _ends1) = ReaderIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      &topic.name(),
+      topic_cache.clone(),
+      QosPolicies::qos_none(),
+    );
 
     // Add the reader to the database and verify the info is updated
-    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None);
+    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None, None);
     assert_eq!(discoverydb.local_topic_readers.len(), 1);
     assert_eq!(discoverydb.get_local_topic_readers(&topic).len(), 1);
 
     // Verify that the info does not change if the reader is added a second time
-    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None);
+    discoverydb.update_local_topic_reader(&dp, &topic, &reader1_ing, None, None);
     assert_eq!(discoverydb.local_topic_readers.len(), 1);
     assert_eq!(discoverydb.get_local_topic_readers(&topic).len(), 1);
 
     // Create second reader ingredients for the same topic
-    let (notification_sender2, _notification_receiver2) = mio_extras::channel::sync_channel(100);
-    let (_notification_event_source2, notification_event_sender2) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker2 = Arc::new(Mutex::new(None));
-
-    let (status_sender2, _status_receiver2) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (_reader_commander2, reader_command_receiver2) =
-      mio_extras::channel::sync_channel::<ReaderCommand>(100);
-
     let mut guid2 = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
     guid2.prefix = GuidPrefix::new(b"Another fake"); // GUID needs to be different in order to be added
 
-    let reader2_ing = ReaderIngredients {
-      guid: guid2,
-      notification_sender: notification_sender2,
-      status_sender: status_sender2,
-      topic_name: topic.name(),
-      topic_cache_handle: topic_cache,
-      like_stateless: false,
-      qos_policy: QosPolicies::qos_none(),
-      data_reader_command_receiver: reader_command_receiver2,
-      data_reader_waker: data_reader_waker2,
-      poll_event_sender: notification_event_sender2,
-      security_plugins: None,
-    };
+    let (reader2_ing, _ends2) =
+      ReaderIngredients::for_test(guid2, &topic.name(), topic_cache, QosPolicies::qos_none());
 
     // Add the second reader to the database and verify the info is updated
-    discoverydb.update_local_topic_reader(&dp, &topic, &reader2_ing, None);
+    discoverydb.update_local_topic_reader(&dp, &topic, &reader2_ing, None, None);
     assert_eq!(discoverydb.get_local_topic_readers(&topic).len(), 2);
     assert_eq!(discoverydb.get_all_local_topic_readers().count(), 2);
   }
+
+  #[test]
+  fn discdb_snapshot() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let participant_data = spdp_participant_data().unwrap();
+    let remote_prefix = participant_data.participant_guid.prefix;
+    discovery_db.update_participant(&participant_data);
+
+    // A local Writer, and two remote Readers on its Topic: one requests
+    // stricter QoS than offered, the other does not.
+    let dp = DomainParticipant::new(0).expect("Failed to create participant");
+    let topic = dp
+      .create_topic(
+        "some topic name".to_string(),
+        "RandomData".to_string(),
+        &QosPolicies::qos_none(),
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&QosPolicies::qos_none()).unwrap();
+    let writer_qos = QosPolicyBuilder::new()
+      .durability(policy::Durability::Volatile)
+      .build();
+    let dw = publisher
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>>(
+        &topic,
+        Some(writer_qos),
+      )
+      .unwrap();
+    let writer_data = DiscoveredWriterData::new(&dw, &topic, &dp, None);
+    let writer_guid = writer_data.writer_proxy.remote_writer_guid;
+    discovery_db.update_local_topic_writer(writer_data);
+
+    let mut strict_reader = DiscoveredReaderData {
+      reader_proxy: reader_proxy_data().unwrap(),
+      subscription_topic_data: subscription_builtin_topic_data().unwrap(),
+      content_filter: None,
+      payload_compression: None,
+    };
+    strict_reader.reader_proxy.remote_reader_guid.prefix = remote_prefix;
+    let strict_guid = strict_reader.reader_proxy.remote_reader_guid;
+    discovery_db.update_subscription(&strict_reader);
+
+    let mut lenient_reader = strict_reader.clone();
+    lenient_reader.reader_proxy.remote_reader_guid.entity_id =
+      EntityId::new([7, 7, 7], EntityKind::READER_WITH_KEY_USER_DEFINED);
+    lenient_reader
+      .subscription_topic_data
+      .set_qos(&QosPolicies::qos_none());
+    let lenient_guid = lenient_reader.reader_proxy.remote_reader_guid;
+    discovery_db.update_subscription(&lenient_reader);
+
+    let snapshot = discovery_db.snapshot();
+    assert_eq!(snapshot.local_participant, discovery_db.my_guid);
+
+    assert_eq!(snapshot.participants.len(), 1);
+    let participant = &snapshot.participants[0];
+    assert_eq!(participant.guid, participant_data.participant_guid);
+    assert!(participant.since_last_life_sign.is_some());
+    let remote_readers: Vec<GUID> = participant.readers.iter().map(|r| r.guid).collect();
+    assert_eq!(remote_readers, vec![strict_guid, lenient_guid]);
+    assert!(participant.writers.is_empty());
+
+    assert_eq!(snapshot.local_writers.len(), 1);
+    let writer = &snapshot.local_writers[0];
+    assert_eq!(writer.guid, writer_guid);
+    assert_eq!(writer.topic_name, "some topic name");
+    assert_eq!(writer.matches.len(), 2);
+    for m in &writer.matches {
+      if m.remote == strict_guid {
+        assert_eq!(m.incompatible_policy, Some(QosPolicyId::Durability));
+      } else {
+        assert_eq!(m.remote, lenient_guid);
+        assert_eq!(m.incompatible_policy, None);
+      }
+    }
+    assert!(snapshot.local_readers.is_empty());
+    assert!(snapshot
+      .topics
+      .iter()
+      .any(|t| t.name == "some topic name" && t.type_name == "RandomData"));
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json["local_writers"][0]["topic_name"], "some topic name");
+    assert_eq!(
+      json["participants"][0]["readers"].as_array().unwrap().len(),
+      2
+    );
+  }
+
+  #[test]
+  fn discdb_local_endpoint_options() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let dp = DomainParticipant::new(0).expect("Failed to create participant");
+    let topic = dp
+      .create_topic(
+        "some topic name".to_string(),
+        "RandomData".to_string(),
+        &QosPolicies::qos_none(),
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&QosPolicies::qos_none()).unwrap();
+    let dw = publisher
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>>(&topic, None)
+      .unwrap();
+    let writer_data = DiscoveredWriterData::new(&dw, &topic, &dp, None);
+    let guid = writer_data.writer_proxy.remote_writer_guid;
+
+    let public = vec![Locator::from(SocketAddr::from(([203, 0, 113, 7], 7411)))];
+    let options = EndpointOptions::new().unicast_locators(public.clone());
+    discovery_db.set_local_endpoint_options(guid, &options);
+    discovery_db.update_local_topic_writer(writer_data);
+    let unicast = |db: &DiscoveryDB| {
+      db.get_local_topic_writer(guid)
+        .unwrap()
+        .writer_proxy
+        .unicast_locator_list
+        .clone()
+    };
+    assert_eq!(unicast(&discovery_db), public);
+
+    // Network interfaces change
+    let interface = vec![Locator::from(SocketAddr::from(([192, 168, 1, 2], 7411)))];
+    let self_locators = HashMap::from([(USER_TRAFFIC_LISTENER_TOKEN, interface.clone())]);
+    discovery_db.update_local_endpoint_locators(&self_locators);
+    assert_eq!(unicast(&discovery_db), public);
+
+    // Without the override, the participant locators are used
+    discovery_db.set_local_endpoint_options(guid, &EndpointOptions::default());
+    discovery_db.update_local_endpoint_locators(&self_locators);
+    assert_eq!(unicast(&discovery_db), interface);
+  }
+
+  #[test]
+  fn discdb_local_guid_conflict() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let dp = DomainParticipant::new(0).expect("Failed to create participant");
+    let topic = dp
+      .create_topic(
+        "some topic name".to_string(),
+        "RandomData".to_string(),
+        &QosPolicies::qos_none(),
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&QosPolicies::qos_none()).unwrap();
+    let dw = publisher
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>>(&topic, None)
+      .unwrap();
+    let writer_data = DiscoveredWriterData::new(&dw, &topic, &dp, None);
+    let guid = writer_data.writer_proxy.remote_writer_guid;
+    discovery_db.update_local_topic_writer(writer_data.clone());
+    assert!(discovery_db.is_local_endpoint(guid));
+
+    // Our own announcement coming back
+    let own = EndpointDescription::from(&writer_data);
+    assert!(discovery_db.local_guid_conflict(&own).is_none());
+
+    let mut other = own.clone();
+    other.topic_name = "other topic".to_string();
+    let local = discovery_db.local_guid_conflict(&other).unwrap();
+    assert_eq!(local.guid, guid);
+    assert_eq!(local.topic_name, "some topic name");
+
+    other.guid = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    assert!(discovery_db.local_guid_conflict(&other).is_none());
+  }
+
+  #[test]
+  fn discdb_types_in_use() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let dp = DomainParticipant::new(0).expect("Failed to create participant");
+    let publisher = dp.create_publisher(&QosPolicies::qos_none()).unwrap();
+    for (topic_name, type_name) in [
+      ("b_topic", "RandomData"),
+      ("a_topic", "RandomData"),
+      ("c_topic", "OtherData"),
+      // user Topic, even if the name looks like a built-in one
+      ("DCPSLookalike", "OtherData"),
+    ] {
+      let topic = dp
+        .create_topic(
+          topic_name.to_string(),
+          type_name.to_string(),
+          &QosPolicies::qos_none(),
+          TopicKind::WithKey,
+        )
+        .unwrap();
+      let dw = publisher
+        .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>>(
+          &topic, None,
+        )
+        .unwrap();
+      discovery_db.update_local_topic_writer(DiscoveredWriterData::new(&dw, &topic, &dp, None));
+    }
+
+    assert_eq!(
+      discovery_db.types_in_use(),
+      vec![
+        (
+          "OtherData".to_string(),
+          vec!["DCPSLookalike".to_string(), "c_topic".to_string()]
+        ),
+        (
+          "RandomData".to_string(),
+          vec!["a_topic".to_string(), "b_topic".to_string()]
+        ),
+      ]
+    );
+  }
 }
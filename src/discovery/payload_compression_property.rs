@@ -0,0 +1,128 @@
+use speedy::{Context, Readable, Reader, Writable, Writer};
+
+use crate::serialization::speedy_pl_cdr_helpers::*;
+
+// Property names. See serialization::compression.
+const ALGORITHMS: &str = "rustdds.compression";
+const REQUIRED: &str = "rustdds.compression.required";
+
+/// Payload compression capabilities of a RustDDS endpoint, announced in
+/// Discovery as properties in PID_PROPERTY_LIST.
+///
+/// A DataWriter announces the one algorithm it compresses with, and a
+/// DataReader the algorithms it can decompress.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PayloadCompressionProperty {
+  pub algorithms: Vec<String>,
+  /// DataWriter does not match with DataReaders that cannot decompress.
+  pub required: bool,
+}
+
+impl PayloadCompressionProperty {
+  pub fn supports(&self, algorithm: &str) -> bool {
+    self.algorithms.iter().any(|a| a == algorithm)
+  }
+}
+
+// The parameter is a PropertyQosPolicy (DDS Security spec v1.1 Section
+// 7.2.5): a sequence of (name, value) string pairs, followed by a sequence of
+// binary properties. Other properties are ignored.
+impl<'a, C: Context> Readable<'a, C> for PayloadCompressionProperty {
+  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+    let mut algorithms = Vec::new();
+    let mut required = false;
+
+    let count = reader.read_u32()?;
+    let mut prev_len = 0;
+    for _ in 0..count {
+      read_pad(reader, prev_len, 4)?;
+      let name: StringWithNul = reader.read_value()?;
+      read_pad(reader, name.len(), 4)?;
+      let value: StringWithNul = reader.read_value()?;
+      prev_len = value.len();
+
+      let name = String::from(name);
+      let value = String::from(value);
+      if name == ALGORITHMS {
+        algorithms = value
+          .split(',')
+          .filter(|a| !a.is_empty())
+          .map(String::from)
+          .collect();
+      } else if name == REQUIRED {
+        required = value == "true";
+      }
+    }
+    // Binary properties are not needed, so they are not read.
+
+    Ok(PayloadCompressionProperty {
+      algorithms,
+      required,
+    })
+  }
+}
+
+impl<C: Context> Writable<C> for PayloadCompressionProperty {
+  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+    let mut properties = vec![(ALGORITHMS, self.algorithms.join(","))];
+    if self.required {
+      properties.push((REQUIRED, "true".to_string()));
+    }
+
+    writer.write_u32(properties.len() as u32)?;
+    let mut prev_len = 0;
+    for (name, value) in properties {
+      write_pad(writer, prev_len, 4)?;
+      let name = StringWithNul::from(name.to_string());
+      writer.write_value(&name)?;
+      write_pad(writer, name.len(), 4)?;
+      let value = StringWithNul::from(value);
+      writer.write_value(&value)?;
+      prev_len = value.len();
+    }
+
+    // no binary properties
+    write_pad(writer, prev_len, 4)?;
+    writer.write_u32(0)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use speedy::Endianness;
+
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let property = PayloadCompressionProperty {
+      algorithms: vec!["lz4".to_string(), "zstd".to_string()],
+      required: true,
+    };
+    let bytes = property
+      .write_to_vec_with_ctx(Endianness::LittleEndian)
+      .unwrap();
+    assert_eq!(bytes.len() % 4, 0);
+    let decoded =
+      PayloadCompressionProperty::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes)
+        .unwrap();
+    assert_eq!(decoded, property);
+  }
+
+  #[test]
+  fn ignores_other_properties() {
+    // One property "a" = "b", no binary properties
+    let bytes = [
+      1, 0, 0, 0, // count
+      2, 0, 0, 0, b'a', 0, 0, 0, // name
+      2, 0, 0, 0, b'b', 0, 0, 0, // value
+      0, 0, 0, 0, // binary count
+    ];
+    let decoded =
+      PayloadCompressionProperty::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes)
+        .unwrap();
+    assert!(decoded.algorithms.is_empty());
+    assert!(!decoded.required);
+  }
+}
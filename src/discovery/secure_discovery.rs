@@ -1,17 +1,21 @@
 use std::{
   collections::{HashMap, HashSet},
   sync::{Arc, RwLock},
+  time::{Duration, Instant},
 };
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 use mio_extras::channel as mio_channel;
+use serde::Serialize;
 
 use crate::{
   create_security_error_and_log,
   dds::{
+    health::HealthMonitor,
     no_key,
     participant::DomainParticipantWeak,
+    statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
     with_key::{DataSample, Sample, WriteOptionsBuilder},
     WriteError,
   },
@@ -25,6 +29,7 @@ use crate::{
       authentication_builtin::DiscHandshakeState, HandshakeMessageToken, IdentityToken,
       ValidationOutcome, GMCLASSID_SECURITY_AUTH_HANDSHAKE,
     },
+    config::HandshakeRetryPolicy,
     cryptographic::{
       CryptoToken, GMCLASSID_SECURITY_DATAREADER_CRYPTO_TOKENS,
       GMCLASSID_SECURITY_DATAWRITER_CRYPTO_TOKENS, GMCLASSID_SECURITY_PARTICIPANT_CRYPTO_TOKENS,
@@ -52,29 +57,69 @@ use super::{
   ParticipantMessageData, Participant_GUID, SpdpDiscoveredParticipantData,
 };
 
-// Enum for authentication status of a remote participant
-#[derive(Clone, Copy, PartialEq, Debug)]
+/// Authentication status of a remote participant.
+///
+/// Reported in
+/// [`DomainParticipantStatusEvent::Authentication`](crate::dds::statusevents::DomainParticipantStatusEvent::Authentication).
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
 pub enum AuthenticationStatus {
   Authenticated,
   Authenticating, // In the process of being authenticated
   Unauthenticated, /* Not authenticated, but still allowed to communicate with in a limited way
                    * (see Security spec section 8.8.2.1) */
   Rejected, // Could not authenticate & should not communicate to
+  Failed,   /* Handshake got no answer within the retry policy. Not communicating
+             * with this participant. */
 }
 
-// How many times an authentication message is resent if we don't get an answer
-const STORED_AUTH_MESSAGE_MAX_RESEND_COUNT: u8 = 10;
+/// Progress of the authentication handshake with a remote participant.
+///
+/// Each step is reported in
+/// [`DomainParticipantStatusEvent::HandshakeProgress`](crate::dds::statusevents::DomainParticipantStatusEvent::HandshakeProgress),
+/// so that a stalled handshake can be located. Which of the two participants
+/// sends the handshake request is decided by comparing their GUIDs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum HandshakeProgress {
+  /// We are to send the handshake request, but have not managed to create it
+  /// yet.
+  RequestPending,
+  /// Waiting for the remote to send a handshake request.
+  AwaitingRequest,
+  /// Handshake request sent, waiting for a reply.
+  RequestSent,
+  /// Request received and replied to, waiting for the final message.
+  ReplySent,
+  /// Reply received and the final message sent. The handshake is complete.
+  FinalSent,
+  /// Final message received. The handshake is complete.
+  FinalReceived,
+  /// The handshake, or validating the remote after it, failed.
+  Failed { reason: String },
+}
+
+impl From<DiscHandshakeState> for HandshakeProgress {
+  fn from(state: DiscHandshakeState) -> Self {
+    match state {
+      DiscHandshakeState::PendingRequestSend => Self::RequestPending,
+      DiscHandshakeState::PendingRequestMessage => Self::AwaitingRequest,
+      DiscHandshakeState::PendingReplyMessage => Self::RequestSent,
+      DiscHandshakeState::PendingFinalMessage => Self::ReplySent,
+      DiscHandshakeState::CompletedWithFinalMessageSent => Self::FinalSent,
+      DiscHandshakeState::CompletedWithFinalMessageReceived => Self::FinalReceived,
+    }
+  }
+}
 
 struct StoredAuthenticationMessage {
   message: ParticipantStatelessMessage,
-  remaining_resend_counter: u8,
+  remaining_resend_counter: u32,
 }
 
 impl StoredAuthenticationMessage {
-  pub fn new(message: ParticipantStatelessMessage) -> Self {
+  pub fn new(message: ParticipantStatelessMessage, max_resends: u32) -> Self {
     Self {
       message,
-      remaining_resend_counter: STORED_AUTH_MESSAGE_MAX_RESEND_COUNT,
+      remaining_resend_counter: max_resends,
     }
   }
 }
@@ -102,6 +147,10 @@ pub(crate) struct SecureDiscovery {
   // Here we store the latest authentication message that we've sent to each remote,
   // in case they need to be sent again
   stored_authentication_messages: HashMap<GuidPrefix, StoredAuthenticationMessage>,
+  // When we started waiting for a handshake request from each remote. Nothing
+  // is resent in this state, so we time out on our own.
+  awaiting_handshake_request_since: HashMap<GuidPrefix, Instant>,
+  handshake_retry_policy: HandshakeRetryPolicy,
 
   cached_key_exchange_messages_for_resend: HashSet<ParticipantVolatileMessageSecure>,
 
@@ -111,6 +160,9 @@ pub(crate) struct SecureDiscovery {
 
   // A set for keeping track which remote readers are relay-only
   relay_only_remote_readers: HashSet<GUID>,
+
+  participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+  health: Arc<HealthMonitor>,
 }
 
 impl SecureDiscovery {
@@ -118,6 +170,8 @@ impl SecureDiscovery {
     domain_participant: &DomainParticipantWeak,
     discovery_db: &Arc<RwLock<DiscoveryDB>>,
     security_plugins: SecurityPluginsHandle,
+    participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+    health: Arc<HealthMonitor>,
   ) -> SecurityResult<Self> {
     // Run the Discovery-related initialization steps of DDS Security spec v1.1
     // Section "8.8.1 Authentication and AccessControl behavior with local
@@ -243,6 +297,8 @@ impl SecureDiscovery {
 
     drop(plugins); // Drop plugins so that they can be moved to self
 
+    let handshake_retry_policy = HandshakeRetryPolicy::from_property_policy(&property_qos);
+
     Ok(Self {
       security_plugins,
       domain_id: domain_participant.domain_id(),
@@ -255,9 +311,13 @@ impl SecureDiscovery {
       handshake_states: HashMap::new(),
       cached_key_exchange_messages_for_resend: HashSet::new(),
       stored_authentication_messages: HashMap::new(),
+      awaiting_handshake_request_since: HashMap::new(),
+      handshake_retry_policy,
       cached_received_key_exchange_messages: HashMap::new(),
       user_data_endpoints_with_keys_already_sent_to: HashSet::new(),
       relay_only_remote_readers: HashSet::new(),
+      participant_status_sender,
+      health,
     })
   }
 
@@ -336,6 +396,12 @@ impl SecureDiscovery {
                {:?}",
               participant_data.participant_guid
             );
+            self.report_handshake_progress(
+              guid_prefix,
+              HandshakeProgress::Failed {
+                reason: "Incompatible security configuration".to_string(),
+              },
+            );
             AuthenticationStatus::Rejected
           }
         }
@@ -1308,6 +1374,12 @@ impl SecureDiscovery {
         create_security_error_and_log!(
           "SpdpDiscoveredParticipantData is missing the Identity token"
         );
+        self.report_handshake_progress(
+          remote_guid.prefix,
+          HandshakeProgress::Failed {
+            reason: "Participant data is missing the Identity token".to_string(),
+          },
+        );
         return AuthenticationStatus::Rejected;
       }
     };
@@ -1347,6 +1419,12 @@ impl SecureDiscovery {
           return AuthenticationStatus::Unauthenticated;
         } else {
           // Reject the damn thing
+          self.report_handshake_progress(
+            remote_guid.prefix,
+            HandshakeProgress::Failed {
+              reason: format!("Remote identity validation failed: {}", e.msg),
+            },
+          );
           return AuthenticationStatus::Rejected;
         }
       }
@@ -1403,6 +1481,12 @@ impl SecureDiscovery {
           "Got an unexpected outcome when validating remote identity. Validation outcome: \
            {outcome:?}. Remote guid: {remote_guid:?}"
         );
+        self.report_handshake_progress(
+          remote_guid.prefix,
+          HandshakeProgress::Failed {
+            reason: format!("Unexpected identity validation outcome {outcome:?}"),
+          },
+        );
         AuthenticationStatus::Rejected // return value
       }
     }
@@ -1488,7 +1572,10 @@ impl SecureDiscovery {
     // resending it later if needed
     self.stored_authentication_messages.insert(
       remote_guid_prefix,
-      StoredAuthenticationMessage::new(request_message.clone()),
+      StoredAuthenticationMessage::new(
+        request_message.clone(),
+        self.handshake_retry_policy.max_resends,
+      ),
     );
 
     // Try to send the message
@@ -1503,11 +1590,20 @@ impl SecureDiscovery {
     self.update_handshake_state(remote_guid_prefix, DiscHandshakeState::PendingReplyMessage);
   }
 
+  // How often resend_cached_secure_discovery_messages should be called
+  pub fn resend_period(&self) -> Duration {
+    self.handshake_retry_policy.resend_period
+  }
+
   pub fn resend_cached_secure_discovery_messages(
     &mut self,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+    discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
     auth_msg_writer: &no_key::DataWriter<ParticipantStatelessMessage>,
     key_exchange_writer: &no_key::DataWriter<ParticipantVolatileMessageSecure>,
   ) {
+    let mut timed_out = Vec::new();
+
     // First resend authentication messages
     for (guid_prefix, stored_message) in self.stored_authentication_messages.iter_mut() {
       // Resend the message unless it's a final message (which needs to be requested
@@ -1515,6 +1611,11 @@ impl SecureDiscovery {
       if self.handshake_states.get(guid_prefix)
         != Some(&DiscHandshakeState::CompletedWithFinalMessageSent)
       {
+        if stored_message.remaining_resend_counter == 0 {
+          // Resends exhausted, and no answer during the last period either
+          timed_out.push(*guid_prefix);
+          continue;
+        }
         match auth_msg_writer.write(stored_message.message.clone(), None) {
           Ok(()) => {
             stored_message.remaining_resend_counter -= 1;
@@ -1533,10 +1634,22 @@ impl SecureDiscovery {
         }
       }
     }
-    // Remove authentication messages with no more resends
-    self
-      .stored_authentication_messages
-      .retain(|_guid_prefix, message| message.remaining_resend_counter > 0);
+
+    // Waiting for a handshake request is given as much time as resending would
+    // take.
+    let max_wait = self.handshake_retry_policy.resend_period
+      * self.handshake_retry_policy.max_resends.saturating_add(1);
+    timed_out.extend(
+      self
+        .awaiting_handshake_request_since
+        .iter()
+        .filter(|(_, since)| since.elapsed() > max_wait)
+        .map(|(guid_prefix, _)| *guid_prefix),
+    );
+
+    for guid_prefix in timed_out {
+      self.on_handshake_timed_out(guid_prefix, discovery_db, discovery_updated_sender);
+    }
 
     // Then try to send those key exchange messages that we haven't been able to
     // send yet
@@ -1562,12 +1675,50 @@ impl SecureDiscovery {
     self.cached_key_exchange_messages_for_resend = msgs_still_to_cache;
   }
 
+  // The remote did not answer our handshake messages in time. Give up on it.
+  fn on_handshake_timed_out(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+    discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
+  ) {
+    security_info!(
+      "Authentication handshake with remote participant {:?} timed out in state {:?}. Marking \
+       it as Failed.",
+      remote_guid_prefix,
+      self.get_handshake_state(&remote_guid_prefix)
+    );
+    self
+      .stored_authentication_messages
+      .remove(&remote_guid_prefix);
+    self
+      .awaiting_handshake_request_since
+      .remove(&remote_guid_prefix);
+    let state = self.handshake_states.remove(&remote_guid_prefix);
+    self.report_handshake_progress(
+      remote_guid_prefix,
+      HandshakeProgress::Failed {
+        reason: format!(
+          "Timed out in state {:?}",
+          state.map(HandshakeProgress::from)
+        ),
+      },
+    );
+    self.update_participant_authentication_status_and_notify_dp(
+      remote_guid_prefix,
+      AuthenticationStatus::Failed,
+      discovery_db,
+      discovery_updated_sender,
+    );
+  }
+
   fn reset_stored_message_resend_counter(&mut self, remote_guid_prefix: &GuidPrefix) {
+    let max_resends = self.handshake_retry_policy.max_resends;
     if let Some(msg) = self
       .stored_authentication_messages
       .get_mut(remote_guid_prefix)
     {
-      msg.remaining_resend_counter = STORED_AUTH_MESSAGE_MAX_RESEND_COUNT;
+      msg.remaining_resend_counter = max_resends;
     } else {
       debug!(
         "Did not find a stored authentication message for remote participant \
@@ -1713,7 +1864,7 @@ impl SecureDiscovery {
         // resending it later if needed
         self.stored_authentication_messages.insert(
           remote_guid_prefix,
-          StoredAuthenticationMessage::new(reply_message),
+          StoredAuthenticationMessage::new(reply_message, self.handshake_retry_policy.max_resends),
         );
 
         // Set handshake state as pending final message
@@ -1801,7 +1952,7 @@ impl SecureDiscovery {
         // resending it later if needed
         self.stored_authentication_messages.insert(
           remote_guid_prefix,
-          StoredAuthenticationMessage::new(final_message),
+          StoredAuthenticationMessage::new(final_message, self.handshake_retry_policy.max_resends),
         );
 
         // Set handshake state as completed with final message
@@ -2055,9 +2206,9 @@ impl SecureDiscovery {
           e,
           remote_guid_prefix
         );
-        self.update_participant_authentication_status_and_notify_dp(
+        self.reject_authenticated_remote(
           remote_guid_prefix,
-          AuthenticationStatus::Rejected,
+          format!("Validating permissions failed: {e}"),
           discovery_db,
           discovery_updated_sender,
         );
@@ -2085,9 +2236,9 @@ impl SecureDiscovery {
               "Remote participant {:?} is not allowed to join the domain. Rejecting the remote.",
               remote_guid_prefix
             );
-            self.update_participant_authentication_status_and_notify_dp(
+            self.reject_authenticated_remote(
               remote_guid_prefix,
-              AuthenticationStatus::Rejected,
+              "Not allowed to join the domain".to_string(),
               discovery_db,
               discovery_updated_sender,
             );
@@ -2102,9 +2253,9 @@ impl SecureDiscovery {
             e,
             remote_guid_prefix
           );
-          self.update_participant_authentication_status_and_notify_dp(
+          self.reject_authenticated_remote(
             remote_guid_prefix,
-            AuthenticationStatus::Rejected,
+            format!("Checking domain access failed: {e}"),
             discovery_db,
             discovery_updated_sender,
           );
@@ -2124,9 +2275,9 @@ impl SecureDiscovery {
         remote_guid_prefix,
         e,
       );
-      self.update_participant_authentication_status_and_notify_dp(
+      self.reject_authenticated_remote(
         remote_guid_prefix,
-        AuthenticationStatus::Rejected,
+        format!("Registering to crypto plugin failed: {e}"),
         discovery_db,
         discovery_updated_sender,
       );
@@ -2142,6 +2293,23 @@ impl SecureDiscovery {
     );
   }
 
+  // The handshake succeeded, but the remote participant cannot be let in
+  fn reject_authenticated_remote(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    reason: String,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+    discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
+  ) {
+    self.report_handshake_progress(remote_guid_prefix, HandshakeProgress::Failed { reason });
+    self.update_participant_authentication_status_and_notify_dp(
+      remote_guid_prefix,
+      AuthenticationStatus::Rejected,
+      discovery_db,
+      discovery_updated_sender,
+    );
+  }
+
   // Initiates the exchange of cryptographic keys with the remote participant.
   // The exchange is started for the secure built-in topics.
   // Note that this function needs to be called after the built-in endpoints have
@@ -2626,8 +2794,40 @@ impl SecureDiscovery {
     self.handshake_states.get(remote_guid_prefix).copied()
   }
 
-  fn update_handshake_state(&mut self, remote_guid_prefix: GuidPrefix, state: DiscHandshakeState) {
+  pub(crate) fn update_handshake_state(
+    &mut self,
+    remote_guid_prefix: GuidPrefix,
+    state: DiscHandshakeState,
+  ) {
+    if state == DiscHandshakeState::PendingRequestMessage {
+      self
+        .awaiting_handshake_request_since
+        .entry(remote_guid_prefix)
+        .or_insert_with(Instant::now);
+    } else {
+      self
+        .awaiting_handshake_request_since
+        .remove(&remote_guid_prefix);
+    }
     self.handshake_states.insert(remote_guid_prefix, state);
+    self.report_handshake_progress(remote_guid_prefix, state.into());
+  }
+
+  fn report_handshake_progress(&self, remote_guid_prefix: GuidPrefix, progress: HandshakeProgress) {
+    match progress {
+      HandshakeProgress::FinalSent | HandshakeProgress::FinalReceived => {
+        self.health.statistics.handshake_completed();
+      }
+      HandshakeProgress::Failed { .. } => self.health.statistics.handshake_failed(),
+      _ => {}
+    }
+    self
+      .participant_status_sender
+      .try_send(DomainParticipantStatusEvent::HandshakeProgress {
+        participant: remote_guid_prefix,
+        progress,
+      })
+      .unwrap_or_else(|e| debug!("Cannot report handshake progress: {e:?}"));
   }
 
   fn get_serialized_local_participant_data(
@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::{collections::BTreeMap, time::Instant};
 
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
@@ -22,14 +22,21 @@ use crate::{
     topic::{Topic, TopicDescription},
     with_key::datawriter::DataWriter,
   },
-  discovery::content_filter_property::ContentFilterProperty,
+  discovery::{
+    content_filter_property::ContentFilterProperty,
+    payload_compression_property::PayloadCompressionProperty,
+  },
   messages::submessages::elements::{
     parameter::Parameter,
     parameter_list::{ParameterList, ParameterListable},
   },
   network::{constant::user_traffic_unicast_port, util::get_local_unicast_locators},
-  rtps::{rtps_reader_proxy::RtpsReaderProxy, rtps_writer_proxy::RtpsWriterProxy},
+  rtps::{
+    rtps_reader_proxy::RtpsReaderProxy, rtps_writer_proxy::RtpsWriterProxy,
+    writer::WriterIngredients,
+  },
   serialization::{
+    compression::{CompressionFallback, PayloadCompression},
     pl_cdr_adapters::{
       PlCdrDeserialize, PlCdrDeserializeError, PlCdrSerialize, PlCdrSerializeError,
     },
@@ -103,6 +110,18 @@ impl PlCdrSerialize for Endpoint_GUID {
   }
 }
 
+// Other implementations may send property lists that do not parse as
+// PayloadCompressionProperty. They must not prevent discovery, so parsing
+// failures are ignored.
+fn get_payload_compression(
+  pl_map: &BTreeMap<ParameterId, Vec<&Parameter>>,
+  ctx: speedy::Endianness,
+) -> Option<PayloadCompressionProperty> {
+  get_option_from_pl_map(pl_map, ctx, ParameterId::PID_PROPERTY_LIST, "property list")
+    .unwrap_or(None)
+    .filter(|p: &PayloadCompressionProperty| !p.algorithms.is_empty())
+}
+
 // Topic data contains all topic related
 // (including reader and writer data structures for serialization and
 // deserialization)
@@ -170,6 +189,10 @@ pub struct SubscriptionBuiltinTopicData {
   // pub group_data: Option<GroupData>,
   // pub durability_service: Option<DurabilityService>,
   lifespan: Option<Lifespan>,
+  // Not in Figure 8.30, but sent by other implementations also. Some peers
+  // check these when matching.
+  history: Option<History>,
+  resource_limits: Option<ResourceLimits>,
 
   // From spec Remote Procedure Call over DDS:
   service_instance_name: Option<String>,
@@ -209,6 +232,8 @@ impl SubscriptionBuiltinTopicData {
       time_based_filter: None,
       presentation: None,
       lifespan: None,
+      history: None,
+      resource_limits: None,
       // DDS-RPC
       // TODO: these are not implemented
       service_instance_name: None,  // Note: Not implemented
@@ -256,8 +281,8 @@ impl SubscriptionBuiltinTopicData {
     self.time_based_filter = qos.time_based_filter;
     self.presentation = qos.presentation;
     self.lifespan = qos.lifespan;
-    // history does not exist
-    // resource_limits does not exist
+    self.history = qos.history;
+    self.resource_limits = qos.resource_limits;
   }
 
   pub fn qos(&self) -> QosPolicies {
@@ -271,9 +296,13 @@ impl SubscriptionBuiltinTopicData {
       time_based_filter: self.time_based_filter,
       reliability: self.reliability,
       destination_order: self.destination_order,
-      history: None, // SubscriptionBuiltinTopicData does not contain History QoS
-      resource_limits: None, // nor Resource Limits, see Figure 8.30 in RTPS spec 2.5
+      history: self.history,
+      resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      reader_data_lifecycle: None,
+      max_sample_age: None,
+      stalled_reader_detection: None,
+      inline_qos: None,
 
       #[cfg(feature = "security")]
       property: None, // TODO: no property QoS?
@@ -301,6 +330,8 @@ pub struct DiscoveredReaderData {
   pub reader_proxy: ReaderProxy,
   pub subscription_topic_data: SubscriptionBuiltinTopicData,
   pub content_filter: Option<ContentFilterProperty>,
+  // RustDDS extension, see serialization::compression
+  pub payload_compression: Option<PayloadCompressionProperty>,
 }
 
 impl DiscoveredReaderData {
@@ -321,6 +352,7 @@ impl DiscoveredReaderData {
       reader_proxy,
       subscription_topic_data,
       content_filter: None,
+      payload_compression: None,
     }
   }
 }
@@ -392,6 +424,8 @@ impl PlCdrDeserialize for DiscoveredReaderData {
       e
     })?;
 
+    let payload_compression = get_payload_compression(&pl_map, ctx);
+
     #[cfg(not(feature = "security"))]
     let security_info = None;
     #[cfg(feature = "security")]
@@ -420,6 +454,7 @@ impl PlCdrDeserialize for DiscoveredReaderData {
         security_info,
       ),
       content_filter,
+      payload_compression,
     })
   }
 }
@@ -467,6 +502,8 @@ impl ParameterListable for DiscoveredReaderData {
           time_based_filter: _,
           presentation: _,
           lifespan: _,
+          history: _,
+          resource_limits: _,
 
           service_instance_name,
           related_datawriter_key,
@@ -476,6 +513,7 @@ impl ParameterListable for DiscoveredReaderData {
           security_info,
         },
       content_filter,
+      payload_compression,
     } = self;
 
     let mut pl = ParameterList::new();
@@ -549,6 +587,11 @@ impl ParameterListable for DiscoveredReaderData {
       content_filter,
       ContentFilterProperty
     );
+    emit_option!(
+      PID_PROPERTY_LIST,
+      payload_compression,
+      PayloadCompressionProperty
+    );
 
     #[cfg(feature = "security")]
     emit_option!(
@@ -621,6 +664,10 @@ pub struct PublicationBuiltinTopicData {
   pub ownership: Option<Ownership>,
   pub destination_order: Option<DestinationOrder>,
   pub presentation: Option<Presentation>,
+  // Not in Figure 8.30, but sent by other implementations also. Some peers
+  // check these when matching.
+  pub history: Option<History>,
+  pub resource_limits: Option<ResourceLimits>,
 
   // From Remote Procedure Call over DDS:
   pub service_instance_name: Option<String>,
@@ -659,6 +706,8 @@ impl PublicationBuiltinTopicData {
       ownership: None,
       destination_order: None,
       presentation: None,
+      history: None,
+      resource_limits: None,
 
       service_instance_name: None,  // TODO: These are not supported/used
       related_datareader_key: None, // TODO
@@ -693,6 +742,8 @@ impl PublicationBuiltinTopicData {
     self.ownership = qos.ownership;
     self.destination_order = qos.destination_order;
     self.presentation = qos.presentation;
+    self.history = qos.history;
+    self.resource_limits = qos.resource_limits;
   }
 
   pub fn qos(&self) -> QosPolicies {
@@ -706,9 +757,13 @@ impl PublicationBuiltinTopicData {
       time_based_filter: self.time_based_filter,
       reliability: self.reliability,
       destination_order: self.destination_order,
-      history: None,         // PublicationBuiltinTopicData does not contain History QoS
-      resource_limits: None, // nor Resource Limits, see Figure 8.30 in RTPS spec 2.5
+      history: self.history,
+      resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      reader_data_lifecycle: None,
+      max_sample_age: None,
+      stalled_reader_detection: None,
+      inline_qos: None,
       #[cfg(feature = "security")]
       property: None, // TODO: no property Qos?
     }
@@ -747,6 +802,8 @@ pub struct DiscoveredWriterData {
 
   pub writer_proxy: WriterProxy,
   pub publication_topic_data: PublicationBuiltinTopicData,
+  // RustDDS extension, see serialization::compression
+  pub payload_compression: Option<PayloadCompressionProperty>,
 }
 
 impl Keyed for DiscoveredWriterData {
@@ -763,24 +820,65 @@ impl DiscoveredWriterData {
     topic: &Topic,
     dp: &DomainParticipant,
     security_info: Option<EndpointSecurityInfo>,
+  ) -> Self {
+    Self::new_local(
+      writer.guid(),
+      &writer.qos(),
+      writer.payload_compression(),
+      topic,
+      dp,
+      security_info,
+    )
+  }
+
+  // Local Writer, before its DataWriter exists
+  pub(crate) fn from_ingredients(
+    writer: &WriterIngredients,
+    topic: &Topic,
+    dp: &DomainParticipant,
+    security_info: Option<EndpointSecurityInfo>,
+  ) -> Self {
+    Self::new_local(
+      writer.guid,
+      &writer.qos_policies,
+      writer.payload_compression.as_ref(),
+      topic,
+      dp,
+      security_info,
+    )
+  }
+
+  fn new_local(
+    guid: GUID,
+    qos: &QosPolicies,
+    payload_compression: Option<&PayloadCompression>,
+    topic: &Topic,
+    dp: &DomainParticipant,
+    security_info: Option<EndpointSecurityInfo>,
   ) -> Self {
     let unicast_port = user_traffic_unicast_port(dp.domain_id(), dp.participant_id());
     let unicast_addresses = get_local_unicast_locators(unicast_port);
     // TODO: Why empty vector below? No multicast?
-    let writer_proxy = WriterProxy::new(writer.guid(), vec![], unicast_addresses);
+    let writer_proxy = WriterProxy::new(guid, vec![], unicast_addresses);
     let publication_topic_data = PublicationBuiltinTopicData::new_with_qos(
-      writer.guid(),
+      guid,
       Some(dp.guid()),
       topic.name(),
       topic.get_type().name().to_string(),
-      &writer.qos(),
+      qos,
       security_info,
     );
 
+    let payload_compression = payload_compression.map(|pc| PayloadCompressionProperty {
+      algorithms: vec![pc.compressor().name().to_string()],
+      required: pc.get_fallback() == CompressionFallback::DoNotMatch,
+    });
+
     Self {
       last_updated: Instant::now(),
       writer_proxy,
       publication_topic_data,
+      payload_compression,
     }
   }
 }
@@ -845,6 +943,8 @@ impl PlCdrDeserialize for DiscoveredWriterData {
     #[cfg(not(feature = "security"))]
     let security_info: Option<EndpointSecurityInfo> = None;
 
+    let payload_compression = get_payload_compression(&pl_map, ctx);
+
     let qos = QosPolicies::from_parameter_list(ctx, &pl_map)?;
 
     Ok(DiscoveredWriterData {
@@ -863,6 +963,7 @@ impl PlCdrDeserialize for DiscoveredWriterData {
         &qos,
         security_info,
       ),
+      payload_compression,
     })
   }
 }
@@ -911,6 +1012,8 @@ impl ParameterListable for DiscoveredWriterData {
           time_based_filter: _,
           presentation: _,
           lifespan: _,
+          history: _,
+          resource_limits: _,
 
           service_instance_name,
           related_datareader_key,
@@ -918,6 +1021,7 @@ impl ParameterListable for DiscoveredWriterData {
           #[cfg(feature = "security")]
           security_info,
         },
+      payload_compression,
     } = self;
 
     let mut pl = ParameterList::new();
@@ -986,6 +1090,11 @@ impl ParameterListable for DiscoveredWriterData {
         StringWithNul
       );
     }
+    emit_option!(
+      PID_PROPERTY_LIST,
+      payload_compression,
+      PayloadCompressionProperty
+    );
 
     #[cfg(feature = "security")]
     emit_option!(
@@ -1057,6 +1166,10 @@ impl HasQoSPolicy for TopicBuiltinTopicData {
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      reader_data_lifecycle: None,
+      max_sample_age: None,
+      stalled_reader_detection: None,
+      inline_qos: None,
       #[cfg(feature = "security")]
       property: None, // TODO: no property Qos?
     }
@@ -1239,6 +1352,12 @@ impl ParticipantMessageDataKind {
   pub const MANUAL_LIVELINESS_UPDATE: Self = Self {
     value: [0x00, 0x00, 0x00, 0x02],
   };
+
+  /// The kind as sent on the wire. Kinds with the most significant bit set
+  /// are vendor-specific.
+  pub fn value(&self) -> [u8; 4] {
+    self.value
+  }
 }
 
 // =======================================================================
@@ -1280,8 +1399,12 @@ mod tests {
   use test_log::test; // to capture logging macros run by test cases
 
   use crate::{
-    dds::adapters::no_key::{DeserializerAdapter, SerializerAdapter},
+    dds::{
+      adapters::no_key::{DeserializerAdapter, SerializerAdapter},
+      qos::{policy::PresentationAccessScope, QosPolicyBuilder},
+    },
     rtps::Message,
+    structure::duration::Duration,
     serialization::pl_cdr_adapters::*,
     test::test_data::{
       content_filter_data, publication_builtin_topic_data, reader_proxy_data,
@@ -1350,6 +1473,10 @@ mod tests {
       reader_proxy,
       subscription_topic_data: sub_topic_data,
       content_filter: Some(content_filter),
+      payload_compression: Some(PayloadCompressionProperty {
+        algorithms: vec!["lz4".to_string(), "zstd".to_string()],
+        required: false,
+      }),
     };
 
     // serialize
@@ -1406,6 +1533,10 @@ mod tests {
       last_updated: Instant::now(),
       writer_proxy,
       publication_topic_data: pub_topic_data,
+      payload_compression: Some(PayloadCompressionProperty {
+        algorithms: vec!["lz4".to_string()],
+        required: true,
+      }),
     };
 
     let sdata = dwd
@@ -1424,6 +1555,122 @@ mod tests {
     assert_eq!(sdata, sdata2);
   }
 
+  #[test]
+  fn td_discovered_endpoint_data_qos_pids() {
+    // Every QoS policy sent in Discovery must appear on the wire, also the ones
+    // that RustDDS itself does not use for matching.
+    let qos = QosPolicyBuilder::new()
+      .durability(Durability::TransientLocal)
+      .presentation(Presentation {
+        access_scope: PresentationAccessScope::Topic,
+        coherent_access: false,
+        ordered_access: true,
+      })
+      .deadline(Deadline(Duration::from_secs(10)))
+      .latency_budget(LatencyBudget {
+        duration: Duration::from_secs(1),
+      })
+      .ownership(Ownership::Exclusive { strength: 5 })
+      .liveliness(Liveliness::Automatic {
+        lease_duration: Duration::from_secs(20),
+      })
+      .time_based_filter(TimeBasedFilter {
+        minimum_separation: Duration::from_secs(2),
+      })
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_secs(3),
+      })
+      .destination_order(DestinationOrder::BySourceTimeStamp)
+      .history(History::KeepLast { depth: 7 })
+      .resource_limits(ResourceLimits {
+        max_samples: 70,
+        max_instances: 10,
+        max_samples_per_instance: 7,
+      })
+      .lifespan(Lifespan {
+        duration: Duration::from_secs(30),
+      })
+      .build();
+    let expected_pids = [
+      ParameterId::PID_DURABILITY,
+      ParameterId::PID_PRESENTATION,
+      ParameterId::PID_DEADLINE,
+      ParameterId::PID_LATENCY_BUDGET,
+      ParameterId::PID_OWNERSHIP,
+      ParameterId::PID_OWNERSHIP_STRENGTH,
+      ParameterId::PID_LIVELINESS,
+      ParameterId::PID_TIME_BASED_FILTER,
+      ParameterId::PID_RELIABILITY,
+      ParameterId::PID_DESTINATION_ORDER,
+      ParameterId::PID_HISTORY,
+      ParameterId::PID_RESOURCE_LIMITS,
+      ParameterId::PID_LIFESPAN,
+    ];
+    let ctx = speedy::Endianness::LittleEndian;
+
+    let mut writer_proxy = writer_proxy_data().unwrap();
+    let pub_topic_data = PublicationBuiltinTopicData::new_with_qos(
+      writer_proxy.remote_writer_guid,
+      None,
+      "qos topic".to_string(),
+      "QosType".to_string(),
+      &qos,
+      None,
+    );
+    writer_proxy.remote_writer_guid = pub_topic_data.key;
+    let dwd = DiscoveredWriterData {
+      last_updated: Instant::now(),
+      writer_proxy,
+      publication_topic_data: pub_topic_data,
+      payload_compression: None,
+    };
+    let sdata = dwd
+      .to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+      .unwrap();
+    let pl = ParameterList::read_from_buffer_with_ctx(ctx, &sdata).unwrap();
+    let pl_map = pl.to_map();
+    for pid in &expected_pids {
+      assert!(
+        pl_map.contains_key(pid),
+        "{pid:?} missing from publication data"
+      );
+    }
+    let dwd2: DiscoveredWriterData =
+      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+    assert_eq!(dwd2.publication_topic_data.qos(), qos);
+
+    let mut reader_proxy = reader_proxy_data().unwrap();
+    let sub_topic_data = SubscriptionBuiltinTopicData::new(
+      reader_proxy.remote_reader_guid,
+      None,
+      "qos topic".to_string(),
+      "QosType".to_string(),
+      &qos,
+      None,
+    );
+    reader_proxy.remote_reader_guid = sub_topic_data.key;
+    let drd = DiscoveredReaderData {
+      reader_proxy,
+      subscription_topic_data: sub_topic_data,
+      content_filter: None,
+      payload_compression: None,
+    };
+    let sdata = drd
+      .to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+      .unwrap();
+    let pl = ParameterList::read_from_buffer_with_ctx(ctx, &sdata).unwrap();
+    let pl_map = pl.to_map();
+    for pid in &expected_pids {
+      assert!(
+        pl_map.contains_key(pid),
+        "{pid:?} missing from subscription data"
+      );
+    }
+    let drd2: DiscoveredReaderData =
+      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+    assert_eq!(drd2.subscription_topic_data.qos(), qos);
+  }
+
   // Do not test ser/deser. This is never seen on the wire out of
   // DiscoveredTopicData #[test]
   // fn td_topic_data_ser_deser() {
@@ -91,6 +91,19 @@ impl SpdpDiscoveredParticipantData {
       && self.security_info.is_some()
   }
 
+  // Compares the parts of the announcement that matter to applications:
+  // where the participant can be reached, how long its lease is, and what it
+  // calls itself. Timestamps and liveliness counters change on every
+  // announcement and are ignored.
+  pub(crate) fn differs_materially(&self, other: &Self) -> bool {
+    self.metatraffic_unicast_locators != other.metatraffic_unicast_locators
+      || self.metatraffic_multicast_locators != other.metatraffic_multicast_locators
+      || self.default_unicast_locators != other.default_unicast_locators
+      || self.default_multicast_locators != other.default_multicast_locators
+      || self.lease_duration != other.lease_duration
+      || self.entity_name != other.entity_name
+  }
+
   pub(crate) fn get_builtin_reader_proxy(
     &self,
     entity_id: EntityId,
@@ -158,16 +171,7 @@ impl SpdpDiscoveredParticipantData {
       .unwrap_or_default();
 
     #[allow(unused_mut)] // only security feature mutates this
-    let mut builtin_endpoints = BuiltinEndpointSet::PARTICIPANT_ANNOUNCER
-      | BuiltinEndpointSet::PARTICIPANT_DETECTOR
-      | BuiltinEndpointSet::PUBLICATIONS_ANNOUNCER
-      | BuiltinEndpointSet::PUBLICATIONS_DETECTOR
-      | BuiltinEndpointSet::SUBSCRIPTIONS_ANNOUNCER
-      | BuiltinEndpointSet::SUBSCRIPTIONS_DETECTOR
-      | BuiltinEndpointSet::PARTICIPANT_MESSAGE_DATA_WRITER
-      | BuiltinEndpointSet::PARTICIPANT_MESSAGE_DATA_READER
-      | BuiltinEndpointSet::TOPICS_ANNOUNCER
-      | BuiltinEndpointSet::TOPICS_DETECTOR;
+    let mut builtin_endpoints = participant.builtin_endpoint_set();
 
     // Security-related items initially None
     #[cfg(feature = "security")]
@@ -249,8 +253,11 @@ impl PlCdrDeserialize for SpdpDiscoveredParticipantData {
       ParameterId::PID_PROTOCOL_VERSION,
       "Protocol Version",
     )?;
+    // Not all implementations send this. Discovery then uses the VendorId from
+    // the RTPS header instead.
     let vendor_id: VendorId =
-      get_first_from_pl_map(&pl_map, ctx, ParameterId::PID_VENDOR_ID, "Vendor Id")?;
+      get_option_from_pl_map(&pl_map, ctx, ParameterId::PID_VENDOR_ID, "Vendor Id")?
+        .unwrap_or_default();
     let expects_inline_qos : bool = // This one has default value false
       get_option_from_pl_map(&pl_map, ctx, ParameterId::PID_EXPECTS_INLINE_QOS, "Expects inline Qos")?
       .unwrap_or(false);
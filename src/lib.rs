@@ -178,7 +178,14 @@ mod rtps;
 #[cfg(feature = "security")]
 mod security;
 #[cfg(feature = "security")]
-pub use security::config::DomainParticipantSecurityConfigFiles;
+pub use security::{
+  authentication::authentication_builtin::{SecureRandomSource, SystemSecureRandom},
+  config::{DomainParticipantSecurityConfigFiles, HandshakeRetryPolicy, KeyAgreementAlgorithm},
+  self_test::{SelfTestReport, SelfTestStep},
+};
+#[cfg(feature = "security")]
+pub use discovery::secure_discovery::{AuthenticationStatus, HandshakeProgress};
+pub use discovery::config::DiscoveryConfig;
 
 #[cfg(not(feature = "security"))]
 mod no_security;
@@ -201,33 +208,47 @@ pub mod serialization;
 // Re-exports from crate root to simplify usage
 #[doc(inline)]
 pub use dds::{
-  key::{Key, Keyed},
+  health::{ChannelDepths, ParticipantHealth, ParticipantHealthSample, ThreadHealth},
+  discovery_snapshot::{
+    DiscoverySnapshot, EndpointSnapshot, MatchSnapshot, ParticipantSnapshot, TopicSnapshot,
+  },
+  endpoint_options::EndpointOptions,
+  key::{InstanceHandle, Key, Keyed},
   participant::{DomainParticipant, DomainParticipantBuilder},
   pubsub::{Publisher, Subscriber},
   qos,
   qos::{policy, QosPolicies, QosPolicyBuilder},
-  readcondition::ReadCondition,
-  sampleinfo::{InstanceState, NotAliveGenerationCounts, SampleInfo, SampleState, ViewState},
+  readcondition::{ReadCondition, ReadConditionBuilder},
+  sampleinfo::{
+    InstanceInfo, InstanceState, NotAliveGenerationCounts, SampleFlag, SampleInfo, SampleState,
+    ViewState,
+  },
+  statistics::ParticipantStatistics,
   statusevents::{
     DataReaderStatus, DataWriterStatus, DomainParticipantStatusEvent, EndpointDescription,
-    LostReason, ParticipantDescription, StatusEvented,
+    HistoryCacheStatus, LivelinessChangedStatus, LostReason, OfferedIncompatibleQosStatus,
+    ParticipantDescription, PublicationMatchedStatus, RemoteLocators, StatusEvented,
+    SubscriptionMatchedStatus,
   },
+  thread_options::ThreadPriority,
   topic::{Topic, TopicDescription, TopicKind},
   typedesc::TypeDesc,
-  with_key::{datareader::SelectByKey, WriteOptions, WriteOptionsBuilder},
+  wildcard::{WildcardEvent, WildcardEventStream, WildcardSubscription},
+  with_key::{
+    datareader::{InstanceEviction, SelectByKey},
+    AckQuorum, AckStatus, WriteOptions, WriteOptionsBuilder,
+  },
 };
 /// Needed to specify serialized data representation in case it is other than
 /// CDR.
 pub use serialization::RepresentationIdentifier;
 #[doc(inline)]
-pub use serialization::{
-  CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializer, CdrSerializer,
-};
+pub use serialization::{CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializer, CdrSerializer};
 /// Part of RTPS DATA submessage: 4-byte header + serialized data
 pub use messages::submessages::elements::serialized_payload::SerializedPayload;
 pub use structure::{
-  duration::Duration, entity::RTPSEntity, guid::GUID, sequence_number::SequenceNumber,
-  time::Timestamp,
+  duration::Duration, entity::RTPSEntity, guid::GUID, locator::Locator, memory_budget::MemoryUsage,
+  sequence_number::SequenceNumber, time::Timestamp,
 };
 // re-export from a helper crate
 /// Helper pacakge to compute the CDR-serialized size of data
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  dds::key::KeyHash,
+  dds::{key::KeyHash, sampleinfo::SampleFlag},
   messages::submessages::elements::{parameter_list::ParameterList, RepresentationIdentifier},
   serialization::{pl_cdr_adapters::PlCdrDeserializeError, speedy_pl_cdr_helpers::*},
   structure::{cache_change::ChangeKind, parameter_id::ParameterId, rpc::SampleIdentity},
@@ -161,16 +161,21 @@ impl StatusInfo {
     self.si.contains(sie)
   }
 
+  pub fn sample_flags(&self) -> BitFlags<SampleFlag> {
+    // The bits are defined the same way
+    BitFlags::from_bits_truncate(u32::from(self.si.bits()))
+  }
+
   pub fn change_kind(&self) -> ChangeKind {
-    if self.contains(StatusInfoEnum::Disposed) {
-      // DISPOSED is strongest
-      ChangeKind::NotAliveDisposed
-    } else if self.contains(StatusInfoEnum::Unregistered) {
-      // Checking unregistered second
-      ChangeKind::NotAliveUnregistered
-    } else {
+    match (
+      self.contains(StatusInfoEnum::Disposed),
+      self.contains(StatusInfoEnum::Unregistered),
+    ) {
+      (true, true) => ChangeKind::NotAliveDisposedUnregistered,
+      (true, false) => ChangeKind::NotAliveDisposed,
+      (false, true) => ChangeKind::NotAliveUnregistered,
       // Even if filtered is set it is still alive
-      ChangeKind::Alive
+      (false, false) => ChangeKind::Alive,
     }
   }
 
@@ -193,6 +198,7 @@ mod tests {
   use byteorder::{BigEndian, LittleEndian};
 
   use super::*;
+  use crate::messages::submessages::elements::parameter::Parameter;
 
   #[test]
   fn inline_qos_status_info() {
@@ -236,4 +242,111 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn inline_qos_status_info_sample_flags() {
+    let params = |disposed, unregistered, filtered| ParameterList {
+      parameters: vec![Parameter::create_pid_status_info_parameter(
+        disposed,
+        unregistered,
+        filtered,
+      )],
+    };
+    let sample_flags = |params: ParameterList| {
+      InlineQos::status_info(&params, RepresentationIdentifier::CDR_LE)
+        .unwrap()
+        .sample_flags()
+    };
+
+    assert_eq!(
+      sample_flags(params(true, false, false)),
+      SampleFlag::Disposed
+    );
+    assert_eq!(
+      sample_flags(params(false, true, false)),
+      SampleFlag::Unregistered
+    );
+    assert_eq!(
+      sample_flags(params(false, false, true)),
+      SampleFlag::Filtered
+    );
+    assert_eq!(sample_flags(params(true, true, true)), BitFlags::<SampleFlag>::all());
+    // No StatusInfo at all
+    assert!(sample_flags(ParameterList::new()).is_empty());
+  }
+
+  #[test]
+  fn inline_qos_status_info_change_kind() {
+    let change_kind = |disposed, unregistered, filtered| {
+      let params = ParameterList {
+        parameters: vec![Parameter::create_pid_status_info_parameter(
+          disposed,
+          unregistered,
+          filtered,
+        )],
+      };
+      InlineQos::status_info(&params, RepresentationIdentifier::CDR_LE)
+        .unwrap()
+        .change_kind()
+    };
+
+    assert_eq!(change_kind(false, false, false), ChangeKind::Alive);
+    assert_eq!(
+      change_kind(true, false, false),
+      ChangeKind::NotAliveDisposed
+    );
+    assert_eq!(
+      change_kind(false, true, false),
+      ChangeKind::NotAliveUnregistered
+    );
+    assert_eq!(
+      change_kind(true, true, false),
+      ChangeKind::NotAliveDisposedUnregistered
+    );
+    // Filtered alone does not change the instance
+    assert_eq!(change_kind(false, false, true), ChangeKind::Alive);
+    assert_eq!(
+      change_kind(true, true, true),
+      ChangeKind::NotAliveDisposedUnregistered
+    );
+  }
+
+  #[test]
+  fn inline_qos_status_info_from_wire() {
+    // Inline QoS of a key-only DATA for an instance that is disposed and
+    // unregistered at once: PID_KEY_HASH, PID_STATUS_INFO, PID_SENTINEL. The
+    // StatusInfo flags are in the last octet, regardless of endianness.
+    let bytes: Vec<u8> = vec![
+      0x70, 0x00, 0x10, 0x00, // PID_KEY_HASH, length 16
+      0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00, //
+      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+      0x71, 0x00, 0x04, 0x00, // PID_STATUS_INFO, length 4
+      0x00, 0x00, 0x00, 0x03, // disposed | unregistered
+      0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+    ];
+    let params =
+      ParameterList::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes).unwrap();
+    let status_info = InlineQos::status_info(&params, RepresentationIdentifier::CDR_LE).unwrap();
+    assert_eq!(
+      status_info.change_kind(),
+      ChangeKind::NotAliveDisposedUnregistered
+    );
+    assert_eq!(
+      status_info.sample_flags(),
+      SampleFlag::Disposed | SampleFlag::Unregistered
+    );
+    assert!(InlineQos::key_hash(&params).unwrap().is_some());
+
+    // Unregister only
+    let bytes: Vec<u8> = vec![
+      0x71, 0x00, 0x04, 0x00, // PID_STATUS_INFO, length 4
+      0x00, 0x00, 0x00, 0x02, // unregistered
+      0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+    ];
+    let params =
+      ParameterList::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes).unwrap();
+    let status_info = InlineQos::status_info(&params, RepresentationIdentifier::CDR_LE).unwrap();
+    assert_eq!(status_info.change_kind(), ChangeKind::NotAliveUnregistered);
+    assert_eq!(status_info.sample_flags(), SampleFlag::Unregistered);
+  }
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use enumflags2::BitFlags;
 use log::error;
 use speedy::{Readable, Writable};
@@ -65,6 +67,57 @@ impl Gap {
       original_bytes: None,
     })
   }
+
+  /// Combines two GAPs into one, which marks as irrelevant all the sequence
+  /// numbers of both.
+  ///
+  /// This is possible if both are from the same Writer to the same Reader, and
+  /// their contiguous ranges overlap or are adjacent. Sequence numbers in
+  /// `gap_list` that directly continue a range count as part of it. Returns
+  /// `None` if the GAPs cannot be merged.
+  pub fn merge(&self, other: &Gap) -> Option<Gap> {
+    if self.reader_id != other.reader_id || self.writer_id != other.writer_id {
+      return None;
+    }
+    let (start_1, end_1) = self.contiguous_range();
+    let (start_2, end_2) = other.contiguous_range();
+    if start_2 > end_1 || start_1 > end_2 {
+      return None;
+    }
+
+    let mut list_base = end_1.max(end_2);
+    let mut list_set: BTreeSet<SequenceNumber> = self
+      .gap_list
+      .iter()
+      .chain(other.gap_list.iter())
+      .filter(|sn| *sn >= list_base)
+      .collect();
+    // The base is not below either original one, so the remaining list fits
+    // into 256 bits, as did the original ones.
+    while list_set.remove(&list_base) {
+      list_base = list_base.plus_1();
+    }
+
+    Some(Gap {
+      reader_id: self.reader_id,
+      writer_id: self.writer_id,
+      gap_start: start_1.min(start_2),
+      gap_list: SequenceNumberSet::from_base_and_set(list_base, &list_set),
+    })
+  }
+
+  // The range gap_start <= sn < end, where end is gap_list.base, or beyond it
+  // if gap_list continues the range.
+  fn contiguous_range(&self) -> (SequenceNumber, SequenceNumber) {
+    let mut end = self.gap_list.base();
+    for sn in self.gap_list.iter() {
+      if sn != end {
+        break;
+      }
+      end = end.plus_1();
+    }
+    (self.gap_start, end)
+  }
 }
 
 impl HasEntityIds for Gap {
@@ -79,6 +132,7 @@ impl HasEntityIds for Gap {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::messages::submessages::submessage_flag::FromEndianness;
 
   serialization_test!( type = Gap,
   {
@@ -104,4 +158,129 @@ mod tests {
             0x00, 0x00, 0x00, 0x07,
             0x00, 0x00, 0x00, 0x00]
   });
+
+  fn gap(start: i64, base: i64, list: &[i64]) -> Gap {
+    let list_set: BTreeSet<SequenceNumber> =
+      list.iter().copied().map(SequenceNumber::from).collect();
+    Gap {
+      reader_id: EntityId::SEDP_BUILTIN_PUBLICATIONS_READER,
+      writer_id: EntityId::SEDP_BUILTIN_PUBLICATIONS_WRITER,
+      gap_start: SequenceNumber::from(start),
+      gap_list: SequenceNumberSet::from_base_and_set(SequenceNumber::from(base), &list_set),
+    }
+  }
+
+  fn irrelevant_sns(gap: &Gap) -> Vec<i64> {
+    (i64::from(gap.gap_start)..i64::from(gap.gap_list.base()))
+      .chain(gap.gap_list.iter().map(i64::from))
+      .collect()
+  }
+
+  // Merging must work both ways, with the same result
+  fn merge(a: &Gap, b: &Gap) -> Option<Gap> {
+    let merged = a.merge(b);
+    assert_eq!(merged, b.merge(a));
+    merged
+  }
+
+  #[test]
+  fn gap_merge_requires_same_endpoints() {
+    let a = gap(1, 5, &[]);
+    let mut b = gap(3, 8, &[]);
+    b.reader_id = EntityId::SEDP_BUILTIN_SUBSCRIPTIONS_READER;
+    assert_eq!(merge(&a, &b), None);
+
+    let mut b = gap(3, 8, &[]);
+    b.writer_id = EntityId::SEDP_BUILTIN_SUBSCRIPTIONS_WRITER;
+    assert_eq!(merge(&a, &b), None);
+  }
+
+  #[test]
+  fn gap_merge_overlapping_ranges() {
+    assert_eq!(
+      merge(&gap(1, 5, &[]), &gap(3, 8, &[])),
+      Some(gap(1, 8, &[]))
+    );
+    // One contains the other
+    assert_eq!(
+      merge(&gap(1, 10, &[]), &gap(3, 5, &[])),
+      Some(gap(1, 10, &[]))
+    );
+    // Identical
+    assert_eq!(
+      merge(&gap(4, 6, &[9]), &gap(4, 6, &[9])),
+      Some(gap(4, 6, &[9]))
+    );
+  }
+
+  #[test]
+  fn gap_merge_adjacent_ranges() {
+    assert_eq!(
+      merge(&gap(1, 5, &[]), &gap(5, 8, &[])),
+      Some(gap(1, 8, &[]))
+    );
+    assert_eq!(
+      merge(&gap(5, 6, &[]), &gap(6, 7, &[])),
+      Some(gap(5, 7, &[]))
+    );
+  }
+
+  #[test]
+  fn gap_merge_disjoint_ranges() {
+    assert_eq!(merge(&gap(1, 5, &[]), &gap(6, 8, &[])), None);
+    // gap_list elements are not a range, unless they continue one
+    assert_eq!(merge(&gap(1, 3, &[7]), &gap(6, 7, &[])), None);
+  }
+
+  #[test]
+  fn gap_merge_combines_lists() {
+    let merged = merge(&gap(1, 3, &[5, 7]), &gap(3, 4, &[10])).unwrap();
+    assert_eq!(merged, gap(1, 4, &[5, 7, 10]));
+    assert_eq!(irrelevant_sns(&merged), vec![1, 2, 3, 5, 7, 10]);
+
+    // List elements covered by the other range are dropped
+    let merged = merge(&gap(1, 3, &[5, 7]), &gap(2, 6, &[])).unwrap();
+    assert_eq!(merged, gap(1, 6, &[7]));
+  }
+
+  #[test]
+  fn gap_merge_list_continuing_range() {
+    // 3 and 4 continue the range 1..3, which then is adjacent to 5..6. The
+    // merged range continues to 6 from the list.
+    let merged = merge(&gap(1, 3, &[3, 4, 6, 9]), &gap(5, 6, &[])).unwrap();
+    assert_eq!(merged, gap(1, 7, &[9]));
+    assert_eq!(irrelevant_sns(&merged), vec![1, 2, 3, 4, 5, 6, 9]);
+
+    // Other list continues the merged range
+    let merged = merge(&gap(1, 3, &[]), &gap(3, 4, &[4, 5, 8])).unwrap();
+    assert_eq!(merged, gap(1, 6, &[8]));
+  }
+
+  #[test]
+  fn gap_merge_empty_range() {
+    // GAP with only a list, starting where the other range ends
+    let merged = merge(&gap(1, 5, &[]), &gap(5, 5, &[6, 8])).unwrap();
+    assert_eq!(irrelevant_sns(&merged), vec![1, 2, 3, 4, 6, 8]);
+  }
+
+  #[test]
+  fn gap_merge_wide_lists() {
+    // Both lists span the maximum 256 numbers from their bases
+    let merged = merge(&gap(1, 3, &[4, 258]), &gap(3, 60, &[315])).unwrap();
+    assert_eq!(merged, gap(1, 60, &[258, 315]));
+  }
+
+  #[test]
+  fn gap_merge_result_serializes() {
+    let merged = merge(&gap(1, 3, &[5, 7]), &gap(3, 4, &[10])).unwrap();
+    let submessage = merged
+      .clone()
+      .create_submessage(BitFlags::<GAP_Flags>::from_endianness(
+        speedy::Endianness::LittleEndian,
+      ))
+      .unwrap();
+    let bytes = merged.write_to_vec().unwrap();
+    assert_eq!(submessage.header.content_length as usize, bytes.len());
+    assert_eq!(Gap::read_from_buffer(&bytes).unwrap(), merged);
+  }
 }
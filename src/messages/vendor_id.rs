@@ -17,12 +17,24 @@ impl VendorId {
     vendor_id: [0x01, 0x12],
   };
 
-  pub const THIS_IMPLEMENTATION: Self = Self::ATOSTEK;
+  /// RustDDS. The vendor id is registered to Atostek, so this is the same as
+  /// [`ATOSTEK`](Self::ATOSTEK).
+  pub const RUSTDDS: Self = Self::ATOSTEK;
+
+  pub const THIS_IMPLEMENTATION: Self = Self::RUSTDDS;
 
   pub fn as_bytes(&self) -> [u8; 2] {
     self.vendor_id
   }
 
+  /// Product name of the DDS implementation, if the vendor id is a known
+  /// OMG-registered one.
+  pub fn name(self) -> Option<&'static str> {
+    self
+      .known_vendor_id_string()
+      .map(|(product, _vendor)| product)
+  }
+
   fn known_vendor_id_string(self) -> Option<(&'static str, &'static str)> {
     match self.vendor_id {
       // from https://www.dds-foundation.org/dds-rtps-vendor-and-product-ids/
@@ -76,6 +88,16 @@ impl fmt::Debug for VendorId {
   }
 }
 
+/// Shows the product name if known, otherwise the raw bytes, e.g. `01:7f`.
+impl fmt::Display for VendorId {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.name() {
+      Some(product) => write!(f, "{product}"),
+      None => write!(f, "{:02x}:{:02x}", self.vendor_id[0], self.vendor_id[1]),
+    }
+  }
+}
+
 impl<'a, C: Context> Readable<'a, C> for VendorId {
   #[inline]
   fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
@@ -116,6 +138,24 @@ mod tests {
     );
   }
 
+  #[test]
+  fn vendor_names() {
+    assert_eq!(VendorId::RUSTDDS.name(), Some("RustDDS"));
+    assert_eq!(
+      VendorId {
+        vendor_id: [0x01, 0x10]
+      }
+      .to_string(),
+      "Eclipse Cyclone DDS"
+    );
+    let unknown = VendorId {
+      vendor_id: [0x01, 0x7f],
+    };
+    assert_eq!(unknown.name(), None);
+    assert_eq!(unknown.to_string(), "01:7f");
+    assert_eq!(VendorId::VENDOR_UNKNOWN.to_string(), "00:00");
+  }
+
   serialization_test!( type = VendorId,
   {
       vendor_unknown,
@@ -8,6 +8,10 @@ const D1: u16 = 10;
 const D2: u16 = 1;
 const D3: u16 = 11;
 
+// Participant ids 0..PARTICIPANT_ID_LIMIT map to the default port numbers
+// without overlapping the next Domain.
+pub const PARTICIPANT_ID_LIMIT: u16 = 120;
+
 pub const fn spdp_well_known_multicast_port(domain_id: u16) -> u16 {
   PB + DG * domain_id + D0
 }
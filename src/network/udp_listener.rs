@@ -27,6 +27,8 @@ pub struct UDPListener {
   socket: mio_06::net::UdpSocket,
   receive_buffer: BytesMut,
   multicast_group: Option<Ipv4Addr>,
+  // Local interfaces, on which we have joined multicast_group
+  multicast_interfaces: Vec<Ipv4Addr>,
 }
 
 impl Drop for UDPListener {
@@ -109,6 +111,7 @@ impl UDPListener {
       socket: mio_socket,
       receive_buffer: BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK),
       multicast_group: None,
+      multicast_interfaces: vec![],
     })
   }
 
@@ -121,17 +124,17 @@ impl UDPListener {
     }
 
     let mio_socket = Self::new_listening_socket(host, port, true)?;
+    let mut multicast_interfaces = vec![];
 
     for multicast_if_ipaddr in get_local_multicast_ip_addrs()? {
       match multicast_if_ipaddr {
-        IpAddr::V4(a) => mio_socket
-          .join_multicast_v4(&multicast_group, &a)
-          .unwrap_or_else(|e| {
-            warn!(
-              "join_multicast_v4 failed: {e:?}. multicast_group [{multicast_group:?}] interface \
-               [{a:?}]"
-            );
-          }),
+        IpAddr::V4(a) => match mio_socket.join_multicast_v4(&multicast_group, &a) {
+          Ok(()) => multicast_interfaces.push(a),
+          Err(e) => warn!(
+            "join_multicast_v4 failed: {e:?}. multicast_group [{multicast_group:?}] interface \
+             [{a:?}]"
+          ),
+        },
 
         IpAddr::V6(addr) => {
           if let Err(e) = mio_socket.join_multicast_v6(&addr, 0) {
@@ -148,14 +151,74 @@ impl UDPListener {
       socket: mio_socket,
       receive_buffer: BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK),
       multicast_group: Some(multicast_group),
+      multicast_interfaces,
     })
   }
 
+  // Join the multicast group on interfaces that have appeared since the
+  // last call, and leave it on those that have disappeared.
+  // Returns true if anything changed. Unicast listeners are bound to the
+  // unspecified address, so they need no refresh.
+  pub fn refresh_multicast_interfaces(&mut self) -> io::Result<bool> {
+    let multicast_group = match self.multicast_group {
+      Some(mcg) => mcg,
+      None => return Ok(false),
+    };
+    let current: Vec<Ipv4Addr> = get_local_multicast_ip_addrs()?
+      .into_iter()
+      .filter_map(|ip| match ip {
+        IpAddr::V4(a) => Some(a),
+        IpAddr::V6(_) => None,
+      })
+      .collect();
+
+    let mut changed = false;
+    for gone in self
+      .multicast_interfaces
+      .iter()
+      .filter(|a| !current.contains(a))
+    {
+      // This usually fails, because the interface address is already gone.
+      self
+        .socket
+        .leave_multicast_v4(&multicast_group, gone)
+        .unwrap_or_else(|e| debug!("leave_multicast_v4 on {gone:?}: {e:?}"));
+      changed = true;
+    }
+    self.multicast_interfaces.retain(|a| current.contains(a));
+
+    for new in current {
+      if self.multicast_interfaces.contains(&new) {
+        continue;
+      }
+      match self.socket.join_multicast_v4(&multicast_group, &new) {
+        Ok(()) => {
+          info!("Joined multicast group {multicast_group:?} on new interface {new:?}");
+          self.multicast_interfaces.push(new);
+          changed = true;
+        }
+        // Still a member, because leaving did not succeed earlier.
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+          self.multicast_interfaces.push(new);
+          changed = true;
+        }
+        Err(e) => warn!(
+          "join_multicast_v4 failed: {e:?}. multicast_group [{multicast_group:?}] interface \
+           [{new:?}]"
+        ),
+      }
+    }
+    Ok(changed)
+  }
+
+  pub fn is_multicast(&self) -> bool {
+    self.multicast_group.is_some()
+  }
+
   pub fn mio_socket(&mut self) -> &mut mio_06::net::UdpSocket {
     &mut self.socket
   }
 
-  #[cfg(test)]
   pub fn port(&self) -> u16 {
     match self.socket.local_addr() {
       Ok(add) => add.port(),
@@ -321,4 +384,22 @@ mod tests {
     assert_eq!(rec_data.len(), 3);
     assert_eq!(rec_data, data);
   }
+
+  #[test]
+  fn udpl_refresh_multicast_interfaces() {
+    let mut listener =
+      UDPListener::new_multicast("0.0.0.0", 10003, Ipv4Addr::new(239, 255, 0, 1)).unwrap();
+    let joined = listener.multicast_interfaces.clone();
+
+    // Interfaces have not changed since creation
+    assert!(!listener.refresh_multicast_interfaces().unwrap());
+
+    // Simulate an interface that has gone away, and one that has appeared.
+    listener.multicast_interfaces = vec![Ipv4Addr::new(192, 0, 2, 1)];
+    assert!(listener.refresh_multicast_interfaces().unwrap());
+    assert_eq!(listener.multicast_interfaces, joined);
+
+    let mut unicast = UDPListener::new_unicast("0.0.0.0", 10004).unwrap();
+    assert!(!unicast.refresh_multicast_interfaces().unwrap());
+  }
 }
@@ -1,6 +1,7 @@
 use std::{
   io,
   net::{IpAddr, SocketAddr, UdpSocket},
+  sync::{Arc, Mutex},
 };
 #[cfg(test)]
 use std::net::Ipv4Addr;
@@ -11,14 +12,20 @@ use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 #[cfg(windows)]
 use local_ip_address::list_afinet_netifas;
 
-use crate::{network::util::get_local_multicast_ip_addrs, structure::locator::Locator};
+use crate::{
+  dds::health::HealthMonitor, network::util::get_local_multicast_ip_addrs,
+  structure::locator::Locator,
+};
 
 // We need one multicast sender socket per interface
 
 #[derive(Debug)]
 pub struct UDPSender {
   unicast_socket: mio_08::net::UdpSocket,
-  multicast_sockets: Vec<mio_08::net::UdpSocket>,
+  // Replaced when network interfaces change, see refresh_multicast_interfaces
+  multicast_sockets: Mutex<Vec<(IpAddr, mio_08::net::UdpSocket)>>,
+  // Successful sends are recorded here, if present
+  health: Option<Arc<HealthMonitor>>,
 }
 
 impl UDPSender {
@@ -38,60 +45,98 @@ impl UDPSender {
 
     let mut multicast_sockets = Vec::with_capacity(1);
     for multicast_if_ipaddr in get_local_multicast_ip_addrs()? {
-      // beef: specify output interface
-      trace!("UDPSender: Multicast sender on interface {multicast_if_ipaddr:?}");
-
-      let mc_socket = match multicast_if_ipaddr {
-        // ipv4 requires a little more work
-        IpAddr::V4(a) => {
-          let raw_socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
-          raw_socket.set_multicast_if_v4(&a)?;
-
-          // Handle windows.
-          //
-          // TODO: Check if necessary.
-          if cfg!(windows) {
-            raw_socket.set_reuse_address(true)?;
-          }
+      multicast_sockets.push((
+        multicast_if_ipaddr,
+        Self::new_multicast_socket(multicast_if_ipaddr)?,
+      ));
+    }
+
+    let sender = Self {
+      unicast_socket,
+      multicast_sockets: Mutex::new(multicast_sockets),
+      health: None,
+    };
+    info!("UDPSender::new() --> {sender:?}");
+    Ok(sender)
+  }
 
-          // bind to the multicast interface
-          raw_socket.bind(&SockAddr::from(SocketAddr::new(multicast_if_ipaddr, 0)))?;
+  pub fn with_health_monitor(mut self, health: Arc<HealthMonitor>) -> Self {
+    self.health = Some(health);
+    self
+  }
 
-          // make multicast sock
-          let mc_socket = UdpSocket::from(raw_socket);
-          mc_socket.set_multicast_loop_v4(true).unwrap_or_else(|e| {
-            error!("Cannot set IPv4 multicast loop. err: {e}");
-          });
-          mc_socket
+  fn new_multicast_socket(multicast_if_ipaddr: IpAddr) -> io::Result<mio_08::net::UdpSocket> {
+    // beef: specify output interface
+    trace!("UDPSender: Multicast sender on interface {multicast_if_ipaddr:?}");
+
+    let mc_socket = match multicast_if_ipaddr {
+      // ipv4 requires a little more work
+      IpAddr::V4(a) => {
+        let raw_socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        raw_socket.set_multicast_if_v4(&a)?;
+
+        // Handle windows.
+        //
+        // TODO: Check if necessary.
+        if cfg!(windows) {
+          raw_socket.set_reuse_address(true)?;
         }
 
-        // ipv6
-        IpAddr::V6(addr) => {
-          let raw_socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        // bind to the multicast interface
+        raw_socket.bind(&SockAddr::from(SocketAddr::new(multicast_if_ipaddr, 0)))?;
 
-          // note: you don't need to use set_multicast_if for ipv6 multicast.
-          // it comes for free!
-          raw_socket.bind(&SocketAddr::new(addr.into(), 0).into())?;
+        // make multicast sock
+        let mc_socket = UdpSocket::from(raw_socket);
+        mc_socket.set_multicast_loop_v4(true).unwrap_or_else(|e| {
+          error!("Cannot set IPv4 multicast loop. err: {e}");
+        });
+        mc_socket
+      }
 
-          // make multicast sock
-          let mc_socket = UdpSocket::from(raw_socket);
-          mc_socket.set_multicast_loop_v6(true).unwrap_or_else(|e| {
-            error!("Cannot set IPv6 multicast loop. err: {e}");
-          });
+      // ipv6
+      IpAddr::V6(addr) => {
+        let raw_socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
 
-          mc_socket
-        }
-      };
+        // note: you don't need to use set_multicast_if for ipv6 multicast.
+        // it comes for free!
+        raw_socket.bind(&SocketAddr::new(addr.into(), 0).into())?;
 
-      multicast_sockets.push(mio_08::net::UdpSocket::from_std(mc_socket));
-    } // end for
+        // make multicast sock
+        let mc_socket = UdpSocket::from(raw_socket);
+        mc_socket.set_multicast_loop_v6(true).unwrap_or_else(|e| {
+          error!("Cannot set IPv6 multicast loop. err: {e}");
+        });
 
-    let sender = Self {
-      unicast_socket,
-      multicast_sockets,
+        mc_socket
+      }
     };
-    info!("UDPSender::new() --> {sender:?}");
-    Ok(sender)
+
+    Ok(mio_08::net::UdpSocket::from_std(mc_socket))
+  }
+
+  // Create multicast sender sockets for new interfaces and drop the ones for
+  // interfaces that no longer exist. Returns true if anything changed.
+  pub fn refresh_multicast_interfaces(&self) -> io::Result<bool> {
+    let current = get_local_multicast_ip_addrs()?;
+    let mut sockets = self.multicast_sockets.lock().unwrap();
+    let count_before = sockets.len();
+    sockets.retain(|(ip, _)| current.contains(ip));
+    let mut changed = sockets.len() != count_before;
+
+    for ip in current {
+      if sockets.iter().any(|(existing, _)| *existing == ip) {
+        continue;
+      }
+      match Self::new_multicast_socket(ip) {
+        Ok(socket) => {
+          info!("UDPSender: New multicast sender on interface {ip:?}");
+          sockets.push((ip, socket));
+          changed = true;
+        }
+        Err(e) => warn!("UDPSender: Cannot create multicast sender on {ip:?}: {e:?}"),
+      }
+    }
+    Ok(changed)
   }
 
   #[cfg(test)]
@@ -99,22 +144,39 @@ impl UDPSender {
     Self::new(0)
   }
 
-  pub fn send_to_locator_list(&self, buffer: &[u8], ll: &[Locator]) {
+  // Returns the last locator that sending succeeded to, if any.
+  pub fn send_to_locator_list(&self, buffer: &[u8], ll: &[Locator]) -> Option<Locator> {
+    let mut last_sent = None;
     for loc in ll {
-      self.send_to_locator(buffer, loc);
+      if self.send_to_locator(buffer, loc) {
+        last_sent = Some(*loc);
+      }
     }
+    last_sent
   }
 
-  fn send_to_udp_socket(&self, buffer: &[u8], socket: &mio_08::net::UdpSocket, addr: &SocketAddr) {
+  // Returns true if the whole buffer was sent.
+  fn send_to_udp_socket(
+    &self,
+    buffer: &[u8],
+    socket: &mio_08::net::UdpSocket,
+    addr: &SocketAddr,
+  ) -> bool {
     match socket.send_to(buffer, *addr) {
       Ok(bytes_sent) => {
-        if bytes_sent == buffer.len() { // ok
+        if let Some(health) = &self.health {
+          health.socket_written();
+          health.statistics.message_sent(bytes_sent);
+        }
+        if bytes_sent == buffer.len() {
+          true
         } else {
           error!(
             "send_to_udp_socket - send_to tried {} bytes, sent only {}",
             buffer.len(),
             bytes_sent
           );
+          false
         }
       }
       Err(e) => {
@@ -124,21 +186,25 @@ impl UDPSender {
           e,
           buffer.len()
         );
+        false
       }
     }
   }
 
-  pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) {
+  // Returns true if the message was sent through at least one socket.
+  pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) -> bool {
     if buffer.len() > 1500 {
       warn!("send_to_locator: Message size = {}", buffer.len());
     }
     let send = |socket_address: SocketAddr| {
       if socket_address.ip().is_multicast() {
-        for socket in &self.multicast_sockets {
-          self.send_to_udp_socket(buffer, socket, &socket_address);
+        let mut sent = false;
+        for (_ip, socket) in self.multicast_sockets.lock().unwrap().iter() {
+          sent |= self.send_to_udp_socket(buffer, socket, &socket_address);
         }
+        sent
       } else {
-        self.send_to_udp_socket(buffer, &self.unicast_socket, &socket_address);
+        self.send_to_udp_socket(buffer, &self.unicast_socket, &socket_address)
       }
     };
 
@@ -147,12 +213,14 @@ impl UDPSender {
       Locator::UdpV6(socket_address) => send(SocketAddr::from(*socket_address)),
       Locator::Invalid | Locator::Reserved => {
         error!("send_to_locator: Cannot send to {locator:?}");
+        false
       }
       Locator::Other { kind, .. } =>
       // This is normal, as other implementations can define their own kinds.
       // We get those from Discovery.
       {
         trace!("send_to_locator: Unknown LocatorKind: {kind:?}");
+        false
       }
     }
   }
@@ -184,7 +252,7 @@ impl UDPSender {
     if address.is_multicast() {
       let address = SocketAddr::new(IpAddr::V4(address), port);
       let mut size = 0;
-      for s in self.multicast_sockets {
+      for (_ip, s) in self.multicast_sockets.into_inner().unwrap() {
         size = s.send_to(buffer, address)?;
       }
       Ok(size)
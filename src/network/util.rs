@@ -14,11 +14,21 @@ pub fn get_local_multicast_locators(port: u16) -> Vec<Locator> {
 }
 
 pub fn get_local_unicast_locators(port: u16) -> Vec<Locator> {
+  get_local_unicast_ip_addrs()
+    .into_iter()
+    .map(|ip| Locator::from(SocketAddr::new(ip, port)))
+    .collect()
+}
+
+/// Enumerates the non-loopback addresses of local network interfaces.
+///
+/// These are the addresses our unicast locators are made of.
+pub fn get_local_unicast_ip_addrs() -> Vec<IpAddr> {
   match if_addrs::get_if_addrs() {
     Ok(ifaces) => ifaces
       .iter()
       .filter(|ip| !ip.is_loopback())
-      .map(|ip| Locator::from(SocketAddr::new(ip.ip(), port)))
+      .map(|ip| ip.ip())
       .collect(),
     Err(e) => {
       error!("Cannot get local network interfaces: get_if_addrs() : {e:?}");
@@ -27,6 +37,14 @@ pub fn get_local_unicast_locators(port: u16) -> Vec<Locator> {
   }
 }
 
+/// Compares two sets of interface addresses. Returns the addresses that
+/// were (added, removed) in `new` compared to `old`.
+pub fn ip_addr_changes(old: &[IpAddr], new: &[IpAddr]) -> (Vec<IpAddr>, Vec<IpAddr>) {
+  let added = new.iter().filter(|ip| !old.contains(ip)).copied().collect();
+  let removed = old.iter().filter(|ip| !new.contains(ip)).copied().collect();
+  (added, removed)
+}
+
 /// Enumerates local interfaces that we may use for multicasting.
 ///
 /// The result of this function is used to set up senders and listeners.
@@ -58,7 +76,7 @@ fn get_local_multicast_ip_addrs_inner(interfaces: Vec<NetworkInterface>) -> Vec<
 mod tests {
   use std::{
     ffi::c_int,
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
   };
 
   use pnet::{
@@ -66,6 +84,22 @@ mod tests {
     ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network},
   };
 
+  #[test]
+  fn ip_addr_changes_detects_added_and_removed() {
+    let eth = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10));
+    let wifi = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7));
+    let v6 = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+
+    assert_eq!(
+      super::ip_addr_changes(&[eth, v6], &[v6, wifi]),
+      (vec![wifi], vec![eth])
+    );
+    assert_eq!(
+      super::ip_addr_changes(&[eth, wifi], &[wifi, eth]),
+      (vec![], vec![])
+    );
+  }
+
   /// Mocks the `get_local_multicast_ip_addrs` function.
   #[test]
   fn test_get_local_multicast_ip_addrs() {
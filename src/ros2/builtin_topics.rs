@@ -33,6 +33,10 @@ impl ROSDiscoveryTopic {
     lifespan: Some(Lifespan {
       duration: Duration::INFINITE,
     }),
+    reader_data_lifecycle: None,
+    max_sample_age: None,
+    stalled_reader_detection: None,
+    inline_qos: None,
     #[cfg(feature = "security")]
     property: None,
   };
@@ -71,6 +75,10 @@ impl ParameterEventsTopic {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
+    reader_data_lifecycle: None,
+    max_sample_age: None,
+    stalled_reader_detection: None,
+    inline_qos: None,
     #[cfg(feature = "security")]
     property: None,
   };
@@ -115,6 +123,10 @@ impl RosOutTopic {
     lifespan: Some(Lifespan {
       duration: Duration::from_secs(10),
     }),
+    reader_data_lifecycle: None,
+    max_sample_age: None,
+    stalled_reader_detection: None,
+    inline_qos: None,
     #[cfg(feature = "security")]
     property: None,
   };
@@ -4,6 +4,7 @@ pub(crate) mod constant;
 pub(crate) mod dp_event_loop;
 pub(crate) mod fragment_assembler;
 pub(crate) mod message_receiver;
+pub(crate) mod observed_gaps;
 pub(crate) mod reader;
 pub(crate) mod rtps_reader_proxy;
 pub(crate) mod rtps_writer_proxy;
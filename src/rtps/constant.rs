@@ -15,12 +15,30 @@ use crate::{
 
 pub const PREEMPTIVE_ACKNACK_PERIOD: Duration = Duration::from_secs(5);
 
+// Default delay from matching a Writer to sending it the first pre-emptive
+// ACKNACK. See DomainParticipantBuilder::initial_acknack_delay.
+pub const INITIAL_ACKNACK_DELAY: Duration = Duration::from_millis(10);
+
 pub const CACHE_CLEAN_PERIOD: Duration = Duration::from_secs(4);
 
 // RTPS spec Section 8.4.7.1.1  "Default Timing-Related Values"
 pub const NACK_RESPONSE_DELAY: Duration = Duration::from_millis(200);
 pub const NACK_SUPPRESSION_DURATION: Duration = Duration::from_millis(0);
 
+// Largest SerializedPayload a Writer sends in a DATA submessage, and the
+// fragment size of larger ones, unless a maximum message size is configured.
+pub const DEFAULT_DATA_MAX_SIZE_SERIALIZED: usize = 1024;
+
+// Conservative estimate of the bytes in a DATA_FRAG message other than the
+// fragment: RTPS header, INFO_TS, INFO_DST, DATA_FRAG with inline QoS, and
+// submessage and payload protection. RTPS message protection is not included.
+pub const DATA_FRAG_MESSAGE_OVERHEAD: usize = 320;
+
+pub const MIN_FRAGMENT_SIZE: usize = 64;
+
+// Smallest accepted DomainParticipantBuilder::max_message_size
+pub const MIN_MAX_MESSAGE_SIZE: usize = 512;
+
 // Helper list for initializing remote standard (non-secure) built-in readers
 // Structure is (builtin_writer_entity_id, builtin_reader_entity_id,
 // reader_as_BuiltinEndpointSet)
@@ -262,6 +280,8 @@ pub const DISCOVERY_TOPIC_DATA_TOKEN: Token = Token(37 + PTB);
 pub const DISCOVERY_TOPIC_CLEANUP_TOKEN: Token = Token(38 + PTB);
 pub const DISCOVERY_PARTICIPANT_MESSAGE_TOKEN: Token = Token(40 + PTB);
 pub const DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN: Token = Token(41 + PTB);
+pub const DISCOVERY_NETWORK_CHECK_TIMER_TOKEN: Token = Token(42 + PTB);
+pub const DISCOVERY_HEALTH_TOPIC_TIMER_TOKEN: Token = Token(43 + PTB);
 
 pub const DPEV_ACKNACK_TIMER_TOKEN: Token = Token(45 + PTB);
 pub const DPEV_CACHE_CLEAN_TIMER_TOKEN: Token = Token(46 + PTB);
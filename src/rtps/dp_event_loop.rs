@@ -1,26 +1,26 @@
 use std::{
   collections::HashMap,
-  rc::Rc,
   sync::{Arc, RwLock},
   time::{Duration, Instant},
 };
 
-use chrono::Utc;
 use log::{debug, error, info, trace, warn};
 use mio_06::{Event, Events, Poll, PollOpt, Ready, Token};
-use mio_extras::channel as mio_channel;
+use mio_extras::{channel as mio_channel, timer::Timer};
 
 use crate::{
   dds::{
+    health::{HealthMonitor, MonitoredThread},
     qos::policy,
     statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
   },
   discovery::{
+    config::DiscoveryConfig,
     discovery::DiscoveryCommand,
     discovery_db::{discovery_db_read, DiscoveryDB},
     sedp_messages::{DiscoveredReaderData, DiscoveredWriterData},
   },
-  messages::submessages::submessages::AckSubmessage,
+  messages::{submessages::submessages::AckSubmessage, vendor_id::VendorId},
   network::{udp_listener::UDPListener, udp_sender::UDPSender},
   polling::new_simple_timer,
   //qos::HasQoSPolicy,
@@ -39,7 +39,6 @@ use crate::{
   },
   //QosPolicyBuilder,
   //QosPolicies,
-  EndpointDescription,
 };
 #[cfg(feature = "security")]
 use crate::{
@@ -60,6 +59,9 @@ pub struct DomainInfo {
 pub(crate) enum EventLoopCommand {
   Stop,
   PrepareStop,
+  // Network interfaces may have changed. Update multicast memberships and
+  // senders.
+  RefreshNetworkInterfaces,
 }
 
 pub struct DPEventLoop {
@@ -87,13 +89,22 @@ pub struct DPEventLoop {
   ack_nack_receiver: mio_channel::Receiver<(GuidPrefix, AckSubmessage)>,
 
   writers: HashMap<EntityId, Writer>,
-  udp_sender: Rc<UDPSender>,
+  udp_sender: Arc<UDPSender>,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+  health: Arc<HealthMonitor>,
 
   discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
   #[cfg(feature = "security")]
   discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
+
+  // Reliability timing of built-in Writers
+  discovery_config: DiscoveryConfig,
+
+  acknack_timer: Timer<()>,
+  cache_gc_timer: Timer<()>,
+  poll_alive: Instant,
+  preparing_to_stop: bool,
 }
 
 impl DPEventLoop {
@@ -112,9 +123,11 @@ impl DPEventLoop {
     stop_poll_receiver: mio_channel::Receiver<EventLoopCommand>,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
     _discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
-    spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_liveness_sender: mio_channel::SyncSender<(GuidPrefix, Option<VendorId>)>,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     security_plugins_opt: Option<SecurityPluginsHandle>,
+    health: Arc<HealthMonitor>,
+    discovery_config: DiscoveryConfig,
   ) -> Self {
     #[cfg(not(feature = "security"))]
     let _dummy = _discovery_command_sender;
@@ -197,7 +210,31 @@ impl DPEventLoop {
       .expect("Failed to register reader update notification.");
 
     // port number 0 means OS chooses an available port number.
-    let udp_sender = UDPSender::new(0).expect("UDPSender construction fail"); // TODO
+    let udp_sender = UDPSender::new(0)
+      .expect("UDPSender construction fail") // TODO
+      .with_health_monitor(Arc::clone(&health));
+
+    let mut acknack_timer = new_simple_timer();
+    acknack_timer.set_timeout(PREEMPTIVE_ACKNACK_PERIOD, ());
+    poll
+      .register(
+        &acknack_timer,
+        DPEV_ACKNACK_TIMER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      )
+      .expect("Failed to register acknack timer.");
+
+    let mut cache_gc_timer = new_simple_timer();
+    cache_gc_timer.set_timeout(CACHE_CLEAN_PERIOD, ());
+    poll
+      .register(
+        &cache_gc_timer,
+        DPEV_CACHE_CLEAN_TIMER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      )
+      .expect("Failed to register cache clean timer.");
 
     #[cfg(not(feature = "security"))]
     let security_plugins_opt = security_plugins_opt.and(None); // make sure it is None an consume value
@@ -208,7 +245,7 @@ impl DPEventLoop {
       dds_cache,
       discovery_db,
       udp_listeners,
-      udp_sender: Rc::new(udp_sender),
+      udp_sender: Arc::new(udp_sender),
       message_receiver: MessageReceiver::new(
         participant_guid_prefix,
         acknack_sender,
@@ -226,233 +263,266 @@ impl DPEventLoop {
       ack_nack_receiver: acknack_receiver,
       discovery_update_notification_receiver,
       participant_status_sender,
+      health,
       #[cfg(feature = "security")]
       discovery_command_sender: _discovery_command_sender,
+      discovery_config,
+      acknack_timer,
+      cache_gc_timer,
+      poll_alive: Instant::now(),
+      preparing_to_stop: false,
     }
   }
 
-  pub fn event_loop(self) {
-    let mut events = Events::with_capacity(16); // too small capacity just delays events to next poll
-
-    let mut acknack_timer = new_simple_timer();
-    acknack_timer.set_timeout(PREEMPTIVE_ACKNACK_PERIOD, ());
-
-    let mut cache_gc_timer = new_simple_timer();
-    cache_gc_timer.set_timeout(CACHE_CLEAN_PERIOD, ());
+  pub fn event_loop(mut self) {
+    while self.poll_once(Duration::from_millis(2000)) {}
+  }
 
+  // Waits for events for at most `timeout` and processes them.
+  // Returns false when the event loop has been stopped and must not be polled
+  // again.
+  pub(crate) fn poll_once(&mut self, timeout: Duration) -> bool {
+    let mut events = Events::with_capacity(16); // too small capacity just delays events to next poll
     self
       .poll
-      .register(
-        &acknack_timer,
-        DPEV_ACKNACK_TIMER_TOKEN,
-        Ready::readable(),
-        PollOpt::edge(),
-      )
-      .unwrap();
-    self
-      .poll
-      .register(
-        &cache_gc_timer,
-        DPEV_CACHE_CLEAN_TIMER_TOKEN,
-        Ready::readable(),
-        PollOpt::edge(),
-      )
-      .unwrap();
-    let mut poll_alive = Instant::now();
-    let mut ev_wrapper = self;
-    let mut preparing_to_stop = false;
-
-    // loop starts here
-    loop {
-      ev_wrapper
-        .poll
-        .poll(&mut events, Some(Duration::from_millis(2000)))
-        .expect("Failed in waiting of poll.");
-
-      // liveness watchdog
-      let now = Instant::now();
-      if now > poll_alive + Duration::from_secs(2) {
-        debug!("Poll loop alive");
-        poll_alive = now;
-      }
+      .poll(&mut events, Some(timeout))
+      .expect("Failed in waiting of poll.");
+
+    // liveness watchdog
+    self.health.heartbeat(MonitoredThread::EventLoop);
+    let now = Instant::now();
+    if now > self.poll_alive + Duration::from_secs(2) {
+      debug!("Poll loop alive");
+      self.poll_alive = now;
+    }
 
-      if events.is_empty() {
-        debug!("dp_event_loop idling.");
-      } else {
-        for event in events.iter() {
-          match EntityId::from_token(event.token()) {
-            TokenDecode::FixedToken(fixed_token) => match fixed_token {
-              STOP_POLL_TOKEN => {
-                use std::sync::mpsc::TryRecvError;
-                // Read commands from the stop receiver until none left or quitting
-                // It would be nice turn the receiver into an iterator and avoid using the
-                // boolean..
-                let mut try_recv_more = true;
-                while try_recv_more {
-                  match ev_wrapper.stop_poll_receiver.try_recv() {
-                    Ok(EventLoopCommand::PrepareStop) => {
-                      info!("dp_event_loop preparing to stop.");
-                      preparing_to_stop = true;
-                      // There could still be an EventLoopCommand::Stop coming. Keep on receiving.
-                      try_recv_more = true;
+    if events.is_empty() {
+      debug!("dp_event_loop idling.");
+    } else {
+      for event in events.iter() {
+        match EntityId::from_token(event.token()) {
+          TokenDecode::FixedToken(fixed_token) => match fixed_token {
+            STOP_POLL_TOKEN => {
+              use std::sync::mpsc::TryRecvError;
+              // Read commands from the stop receiver until none left or quitting
+              // It would be nice turn the receiver into an iterator and avoid using the
+              // boolean..
+              let mut try_recv_more = true;
+              while try_recv_more {
+                let command = self.stop_poll_receiver.try_recv();
+                if command.is_ok() {
+                  self.health.event_loop_commands.received();
+                }
+                match command {
+                  Ok(EventLoopCommand::PrepareStop) => {
+                    info!("dp_event_loop preparing to stop.");
+                    self.preparing_to_stop = true;
+                    // There could still be an EventLoopCommand::Stop coming. Keep on receiving.
+                    try_recv_more = true;
+                  }
+                  Ok(EventLoopCommand::Stop) => {
+                    info!("Stopping dp_event_loop");
+                    return false;
+                  }
+                  Ok(EventLoopCommand::RefreshNetworkInterfaces) => {
+                    self.refresh_network_interfaces();
+                    try_recv_more = true;
+                  }
+                  Err(err) => match err {
+                    TryRecvError::Empty => {
+                      try_recv_more = false;
                     }
-                    Ok(EventLoopCommand::Stop) => {
-                      info!("Stopping dp_event_loop");
-                      return;
+                    TryRecvError::Disconnected => {
+                      error!(
+                        "Application thread has exited abnormally. Stopping RustDDS event loop."
+                      );
+                      return false;
                     }
-                    Err(err) => match err {
-                      TryRecvError::Empty => {
-                        try_recv_more = false;
-                      }
-                      TryRecvError::Disconnected => {
-                        error!(
-                          "Application thread has exited abnormally. Stopping RustDDS event loop."
-                        );
-                        return;
-                      }
-                    },
-                  }
-                }
-              }
-              DISCOVERY_LISTENER_TOKEN
-              | DISCOVERY_MUL_LISTENER_TOKEN
-              | USER_TRAFFIC_LISTENER_TOKEN
-              | USER_TRAFFIC_MUL_LISTENER_TOKEN => {
-                let udp_messages = ev_wrapper
-                  .udp_listeners
-                  .get_mut(&event.token())
-                  .map_or_else(
-                    || {
-                      error!("No listener with token {:?}", &event.token());
-                      vec![]
-                    },
-                    UDPListener::messages,
-                  );
-                for packet in udp_messages {
-                  ev_wrapper.message_receiver.handle_received_packet(&packet);
+                  },
                 }
               }
-              ADD_READER_TOKEN | REMOVE_READER_TOKEN => {
-                ev_wrapper.handle_reader_action(&event);
-              }
-              ADD_WRITER_TOKEN | REMOVE_WRITER_TOKEN => {
-                ev_wrapper.handle_writer_action(&event);
+            }
+            DISCOVERY_LISTENER_TOKEN
+            | DISCOVERY_MUL_LISTENER_TOKEN
+            | USER_TRAFFIC_LISTENER_TOKEN
+            | USER_TRAFFIC_MUL_LISTENER_TOKEN => {
+              let udp_messages = self.udp_listeners.get_mut(&event.token()).map_or_else(
+                || {
+                  error!("No listener with token {:?}", &event.token());
+                  vec![]
+                },
+                UDPListener::messages,
+              );
+              if !udp_messages.is_empty() {
+                self.health.socket_read();
               }
-              ACKNACK_MESSAGE_TO_LOCAL_WRITER_TOKEN => {
-                ev_wrapper.handle_writer_acknack_action(&event);
+              for packet in udp_messages {
+                self.health.statistics.message_received(packet.len());
+                self.message_receiver.handle_received_packet(&packet);
               }
-              DISCOVERY_UPDATE_NOTIFICATION_TOKEN => {
-                while let Ok(dnt) = ev_wrapper.discovery_update_notification_receiver.try_recv() {
-                  use DiscoveryNotificationType::*;
-                  match dnt {
-                    WriterUpdated {
-                      discovered_writer_data,
-                    } => ev_wrapper.remote_writer_discovered(&discovered_writer_data),
+            }
+            ADD_READER_TOKEN | REMOVE_READER_TOKEN => {
+              self.handle_reader_action(&event);
+              self.update_statistics();
+            }
+            ADD_WRITER_TOKEN | REMOVE_WRITER_TOKEN => {
+              self.handle_writer_action(&event);
+              self.update_statistics();
+            }
+            ACKNACK_MESSAGE_TO_LOCAL_WRITER_TOKEN => {
+              self.handle_writer_acknack_action(&event);
+            }
+            DISCOVERY_UPDATE_NOTIFICATION_TOKEN => {
+              while let Ok(dnt) = self.discovery_update_notification_receiver.try_recv() {
+                use DiscoveryNotificationType::*;
+                match dnt {
+                  WriterUpdated {
+                    discovered_writer_data,
+                  } => self.remote_writer_discovered(&discovered_writer_data),
 
-                    WriterLost { writer_guid } => ev_wrapper.remote_writer_lost(writer_guid),
+                  WriterLost { writer_guid } => self.remote_writer_lost(writer_guid),
 
-                    ReaderUpdated {
-                      discovered_reader_data,
-                    } => ev_wrapper.remote_reader_discovered(&discovered_reader_data),
+                  ReaderUpdated {
+                    discovered_reader_data,
+                  } => self.remote_reader_discovered(&discovered_reader_data),
 
-                    ReaderLost { reader_guid } => ev_wrapper.remote_reader_lost(reader_guid),
+                  ReaderLost { reader_guid } => self.remote_reader_lost(reader_guid),
 
-                    ParticipantUpdated { guid_prefix } => {
-                      ev_wrapper.update_participant(guid_prefix);
-                    }
+                  ParticipantUpdated { guid_prefix } => {
+                    self.update_participant(guid_prefix);
+                  }
 
-                    ParticipantLost { guid_prefix } => {
-                      ev_wrapper.remote_participant_lost(guid_prefix);
-                    }
+                  ParticipantLost { guid_prefix } => {
+                    self.remote_participant_lost(guid_prefix);
+                  }
 
-                    AssertTopicLiveliness {
-                      writer_guid,
-                      manual_assertion,
-                    } => {
-                      ev_wrapper
-                        .writers
-                        .get_mut(&writer_guid.entity_id)
-                        .map(|w| w.handle_heartbeat_tick(manual_assertion));
-                    }
+                  AssertTopicLiveliness {
+                    writer_guid,
+                    manual_assertion,
+                  } => {
+                    self
+                      .writers
+                      .get_mut(&writer_guid.entity_id)
+                      .map(|w| w.handle_heartbeat_tick(manual_assertion));
+                  }
 
-                    #[cfg(feature = "security")]
-                    ParticipantAuthenticationStatusChanged { guid_prefix } => {
-                      ev_wrapper.on_remote_participant_authentication_status_changed(guid_prefix);
-                    }
+                  #[cfg(feature = "security")]
+                  ParticipantAuthenticationStatusChanged { guid_prefix } => {
+                    self.on_remote_participant_authentication_status_changed(guid_prefix);
                   }
                 }
               }
-              DPEV_ACKNACK_TIMER_TOKEN => {
-                ev_wrapper.message_receiver.send_preemptive_acknacks();
-                acknack_timer.set_timeout(PREEMPTIVE_ACKNACK_PERIOD, ());
-              }
-              DPEV_CACHE_CLEAN_TIMER_TOKEN => {
-                debug!("Clean DDSCache on timer");
-                ev_wrapper.dds_cache.write().unwrap().garbage_collect();
-                cache_gc_timer.set_timeout(CACHE_CLEAN_PERIOD, ());
+              self.update_statistics();
+            }
+            DPEV_ACKNACK_TIMER_TOKEN => {
+              self.message_receiver.send_preemptive_acknacks();
+              self
+                .acknack_timer
+                .set_timeout(PREEMPTIVE_ACKNACK_PERIOD, ());
+            }
+            DPEV_CACHE_CLEAN_TIMER_TOKEN => {
+              debug!("Clean DDSCache on timer");
+              self.dds_cache.write().unwrap().garbage_collect();
+              for reader in self.message_receiver.available_readers.values_mut() {
+                reader.check_evicted_samples();
+                reader.check_autopurge();
               }
+              self.cache_gc_timer.set_timeout(CACHE_CLEAN_PERIOD, ());
+            }
 
-              fixed_unknown => {
-                error!(
-                  "Unknown event.token {:?} = 0x{:x?} , decoded as {:?}",
-                  event.token(),
-                  event.token().0,
-                  fixed_unknown
-                );
-              }
-            },
-
-            // Commands/actions
-            TokenDecode::Entity(eid) => {
-              if eid.kind().is_reader() {
-                ev_wrapper.message_receiver.reader_mut(eid).map_or_else(
-                  || {
-                    if !preparing_to_stop {
-                      error!("Event for unknown reader {eid:?}");
-                    }
-                  },
-                  Reader::process_command,
-                );
-              } else if eid.kind().is_writer() {
-                let local_readers = match ev_wrapper.writers.get_mut(&eid) {
-                  None => {
-                    if !preparing_to_stop {
-                      error!("Event for unknown writer {eid:?}");
-                    };
-                    vec![]
-                  }
-                  Some(writer) => {
-                    // Writer will record data to DDSCache and send it out.
-                    writer.process_writer_command();
-                    writer.local_readers()
+            fixed_unknown => {
+              error!(
+                "Unknown event.token {:?} = 0x{:x?} , decoded as {:?}",
+                event.token(),
+                event.token().0,
+                fixed_unknown
+              );
+            }
+          },
+
+          // Commands/actions
+          TokenDecode::Entity(eid) => {
+            if eid.kind().is_reader() {
+              self.message_receiver.reader_mut(eid).map_or_else(
+                || {
+                  if !self.preparing_to_stop {
+                    error!("Event for unknown reader {eid:?}");
                   }
-                };
-                // Notify local (same participant) readers that new data is available in the
-                // cache.
-                ev_wrapper
-                  .message_receiver
-                  .notify_data_to_readers(local_readers);
-              } else {
-                error!("Entity Event for unknown EntityKind {eid:?}");
-              }
+                },
+                Reader::process_command,
+              );
+            } else if eid.kind().is_writer() {
+              let local_readers = match self.writers.get_mut(&eid) {
+                None => {
+                  if !self.preparing_to_stop {
+                    error!("Event for unknown writer {eid:?}");
+                  };
+                  vec![]
+                }
+                Some(writer) => {
+                  // Writer will record data to DDSCache and send it out.
+                  writer.process_writer_command();
+                  writer.local_readers()
+                }
+              };
+              // Notify local (same participant) readers that new data is available in the
+              // cache.
+              self.message_receiver.notify_data_to_readers(local_readers);
+            } else {
+              error!("Entity Event for unknown EntityKind {eid:?}");
             }
+          }
 
-            // Timed Actions
-            TokenDecode::AltEntity(eid) => {
-              if eid.kind().is_reader() {
-                ev_wrapper.handle_reader_timed_event(eid);
-              } else if eid.kind().is_writer() {
-                ev_wrapper.handle_writer_timed_event(eid);
-              } else {
-                error!("AltEntity Event for unknown EntityKind {eid:?}");
-              }
+          // Timed Actions
+          TokenDecode::AltEntity(eid) => {
+            if eid.kind().is_reader() {
+              self.handle_reader_timed_event(eid);
+            } else if eid.kind().is_writer() {
+              self.handle_writer_timed_event(eid);
+            } else {
+              error!("AltEntity Event for unknown EntityKind {eid:?}");
             }
           }
-        } // for
-      } // if
-    } // loop
+        }
+      } // for
+    } // if
+    true
   } // fn
 
+  fn refresh_network_interfaces(&mut self) {
+    for (token, listener) in self.udp_listeners.iter_mut() {
+      if let Err(e) = listener.refresh_multicast_interfaces() {
+        error!("Cannot refresh multicast listener {token:?}: {e:?}");
+      }
+    }
+    if let Err(e) = self.udp_sender.refresh_multicast_interfaces() {
+      error!("Cannot refresh multicast senders: {e:?}");
+    }
+  }
+
+  // Matches change only on Discovery events and when local Readers or Writers
+  // are added or removed, so the counts are recomputed after those.
+  fn update_statistics(&self) {
+    let matched_writers = self
+      .message_receiver
+      .available_readers
+      .iter()
+      .filter(|(eid, _)| eid.kind().is_user_defined())
+      .map(|(_, reader)| reader.matched_writer_count())
+      .sum();
+    let matched_readers = self
+      .writers
+      .iter()
+      .filter(|(eid, _)| eid.kind().is_user_defined())
+      .map(|(_, writer)| writer.matched_reader_count())
+      .sum();
+    let statistics = &self.health.statistics;
+    statistics.set_matched(matched_writers, matched_readers);
+    statistics.set_discovered_participants(
+      discovery_db_read(&self.discovery_db).remote_participant_count(),
+    );
+  }
+
   #[cfg(feature = "security")] // Currently used only with security.
                                // Just remove attribute if used also without.
   fn send_participant_status(&self, event: DomainParticipantStatusEvent) {
@@ -467,11 +537,13 @@ impl DPEventLoop {
       ADD_READER_TOKEN => {
         trace!("add reader(s)");
         while let Ok(new_reader_ing) = self.add_reader_receiver.receiver.try_recv() {
+          self.health.add_reader.received();
           self.add_local_reader(new_reader_ing);
         }
       }
       REMOVE_READER_TOKEN => {
         while let Ok(old_reader_guid) = self.remove_reader_receiver.receiver.try_recv() {
+          self.health.remove_reader.received();
           self.remove_local_reader(old_reader_guid);
         }
       }
@@ -483,11 +555,13 @@ impl DPEventLoop {
     match event.token() {
       ADD_WRITER_TOKEN => {
         while let Ok(new_writer_ingredients) = self.add_writer_receiver.receiver.try_recv() {
+          self.health.add_writer.received();
           self.add_local_writer(new_writer_ingredients);
         }
       }
       REMOVE_WRITER_TOKEN => {
         while let Ok(writer_guid) = &self.remove_writer_receiver.receiver.try_recv() {
+          self.health.remove_writer.received();
           self.remove_local_writer(writer_guid);
         }
       }
@@ -701,13 +775,7 @@ impl DPEventLoop {
     self
       .participant_status_sender
       .try_send(DomainParticipantStatusEvent::ReaderDetected {
-        reader: EndpointDescription {
-          updated_time: Utc::now(),
-          guid: remote_reader.reader_proxy.remote_reader_guid,
-          topic_name: remote_reader.subscription_topic_data.topic_name.clone(),
-          type_name: remote_reader.subscription_topic_data.type_name().clone(),
-          qos: remote_reader.subscription_topic_data.qos(),
-        },
+        reader: remote_reader.into(),
       })
       .unwrap_or_else(|e| error!("Cannot report participant status: {e:?}"));
 
@@ -770,9 +838,10 @@ impl DPEventLoop {
         if match_to_reader {
           // Should we check if the participant has published a QoS for the topic?
           let requested_qos = remote_reader.subscription_topic_data.qos();
-          writer.update_reader_proxy(
+          writer.update_discovered_reader_proxy(
             &RtpsReaderProxy::from_discovered_reader_data(remote_reader, &[], &[]),
             &requested_qos,
+            Some(Box::new(remote_reader.into())),
           );
         }
       }
@@ -789,13 +858,7 @@ impl DPEventLoop {
     self
       .participant_status_sender
       .try_send(DomainParticipantStatusEvent::WriterDetected {
-        writer: EndpointDescription {
-          updated_time: Utc::now(),
-          guid: remote_writer.writer_proxy.remote_writer_guid,
-          topic_name: remote_writer.publication_topic_data.topic_name.clone(),
-          type_name: remote_writer.publication_topic_data.type_name.clone(),
-          qos: remote_writer.publication_topic_data.qos(),
-        },
+        writer: remote_writer.into(),
       })
       .unwrap_or_else(|e| error!("Cannot report participant status: {e:?}"));
 
@@ -854,12 +917,40 @@ impl DPEventLoop {
           true // match_to_writer
         };
 
+        // A compressing Writer may require that Readers can decompress
+        let match_to_writer = match_to_writer
+          && match remote_writer.payload_compression.as_ref() {
+            Some(writer_compression) if writer_compression.required => {
+              let can_decompress = discovery_db_read(&self.discovery_db)
+                .get_local_topic_reader(reader.guid())
+                .and_then(|drd| drd.payload_compression.as_ref())
+                .is_some_and(|local| {
+                  writer_compression
+                    .algorithms
+                    .iter()
+                    .any(|a| local.supports(a))
+                });
+              if !can_decompress {
+                info!(
+                  "Local reader {:?} cannot decompress {:?} required by remote writer {:?}, \
+                   ignoring the remote.",
+                  reader.guid(),
+                  writer_compression.algorithms,
+                  remote_writer.writer_proxy.remote_writer_guid
+                );
+              }
+              can_decompress
+            }
+            _ => true,
+          };
+
         if match_to_writer {
           let offered_qos = remote_writer.publication_topic_data.qos();
           // Should we check if the participant has published a QoS for the topic?
-          reader.update_writer_proxy(
+          reader.update_discovered_writer_proxy(
             RtpsWriterProxy::from_discovered_writer_data(remote_writer, &[], &[]),
             &offered_qos,
+            Some(Box::new(remote_writer.into())),
           );
         }
       }
@@ -935,7 +1026,12 @@ impl DPEventLoop {
     }
   }
 
-  fn add_local_writer(&mut self, writer_ing: WriterIngredients) {
+  fn add_local_writer(&mut self, mut writer_ing: WriterIngredients) {
+    if writer_ing.guid.entity_id.kind().is_built_in() {
+      writer_ing.heartbeat_period = Some(self.discovery_config.builtin_heartbeat_period.into());
+      writer_ing.nack_response_delay = Some(self.discovery_config.builtin_nack_response_delay);
+    }
+
     let timer = new_simple_timer();
     self
       .poll
@@ -1030,6 +1126,11 @@ impl DPEventLoop {
            {remote_guidp:?}. TODO!"
         );
       }
+      Some(AuthenticationStatus::Failed) => {
+        info!(
+          "Authentication handshake with {remote_guidp:?} timed out. Not communicating with it."
+        );
+      }
       other => {
         info!(
           "Status {other:?}, in on_remote_participant_authentication_status_changed. What to do?"
@@ -1172,6 +1273,8 @@ mod tests {
         spdp_liveness_sender,
         participant_status_sender,
         None,
+        Arc::default(),
+        DiscoveryConfig::default(),
       );
       dp_event_loop
         .poll
@@ -1221,7 +1324,12 @@ mod tests {
         data_reader_command_receiver: reader_command_receiver,
         data_reader_waker: data_reader_waker.clone(),
         poll_event_sender: notification_event_sender,
+        observed_gaps: Default::default(),
+        subscription_matched: Default::default(),
+        liveliness_changed: Default::default(),
+        additional_front_ends: Default::default(),
         security_plugins: None,
+        initial_acknack_delay: None,
       };
 
       reader_guids.push(new_reader_ing.guid);
@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, fmt, iter};
+use std::{
+  collections::{btree_map, BTreeMap},
+  fmt, iter,
+  sync::Arc,
+};
 
 use bit_vec::BitVec;
 use enumflags2::BitFlags;
@@ -14,6 +18,7 @@ use crate::{
   },
   structure::{
     cache_change::ChangeKind,
+    memory_budget::{MemoryBudget, MemoryKind},
     sequence_number::{FragmentNumber, SequenceNumber},
     time::Timestamp,
   },
@@ -138,11 +143,22 @@ impl AssemblyBuffer {
   }
 }
 
+pub(crate) enum DataFragResult {
+  Incomplete,
+  Completed(DDSData),
+  // New sample could not be started, because of memory limit.
+  Rejected,
+}
+
 // Assembles fragments from a single (remote) Writer
 // So there is only one sequence of SNs
 pub(crate) struct FragmentAssembler {
   fragment_size: u16, // number of bytes per fragment. Each writer must select one constant value.
   assembly_buffers: BTreeMap<SequenceNumber, AssemblyBuffer>,
+  // AssemblyBuffers are charged for their full data size on creation.
+  memory_budget: Arc<MemoryBudget>,
+  // Used to report rejection only once per sample, not for every fragment.
+  last_rejected: Option<SequenceNumber>,
 }
 
 impl fmt::Debug for FragmentAssembler {
@@ -154,11 +170,13 @@ impl fmt::Debug for FragmentAssembler {
 }
 
 impl FragmentAssembler {
-  pub fn new(fragment_size: u16) -> Self {
+  pub fn new(fragment_size: u16, memory_budget: Arc<MemoryBudget>) -> Self {
     debug!("new FragmentAssembler. frag_size = {fragment_size}");
     Self {
       fragment_size,
       assembly_buffers: BTreeMap::new(),
+      memory_budget,
+      last_rejected: None,
     }
   }
 
@@ -167,51 +185,77 @@ impl FragmentAssembler {
     &mut self,
     datafrag: &DataFrag,
     flags: BitFlags<DATAFRAG_Flags>,
-  ) -> Option<DDSData> {
+  ) -> DataFragResult {
     let writer_sn = datafrag.writer_sn;
     let frag_size = self.fragment_size;
 
-    let assembly_buffer = self
-      .assembly_buffers
-      .entry(datafrag.writer_sn)
-      .or_insert_with(|| AssemblyBuffer::new(datafrag));
+    let assembly_buffer = match self.assembly_buffers.entry(writer_sn) {
+      btree_map::Entry::Occupied(e) => e.into_mut(),
+      btree_map::Entry::Vacant(e) => {
+        let data_size = datafrag.data_size as usize;
+        let budget = &self.memory_budget;
+        let reserved = budget.try_reserve(MemoryKind::FragmentAssembly, data_size)
+          || (budget.relieve_pressure(data_size, None)
+            && budget.try_reserve(MemoryKind::FragmentAssembly, data_size));
+        if !reserved {
+          warn!(
+            "Memory limit {:?} reached. Cannot assemble {:?} of {} bytes.",
+            self.memory_budget.limit(),
+            writer_sn,
+            data_size
+          );
+          return if self.last_rejected.replace(writer_sn) == Some(writer_sn) {
+            DataFragResult::Incomplete
+          } else {
+            DataFragResult::Rejected
+          };
+        }
+        e.insert(AssemblyBuffer::new(datafrag))
+      }
+    };
 
     assembly_buffer.insert_frags(datafrag, frag_size);
 
     if assembly_buffer.is_complete() {
       debug!("new_datafrag: COMPLETED FRAGMENT");
       if let Some(assembly_buffer) = self.assembly_buffers.remove(&writer_sn) {
+        self.memory_budget.release(
+          MemoryKind::FragmentAssembly,
+          assembly_buffer.buffer_bytes.len(),
+        );
         // Return what we have assembled.
         let serialized_data_or_key =
-          SerializedPayload::from_bytes(&assembly_buffer.buffer_bytes.freeze()).map_or_else(
-            |e| {
+          match SerializedPayload::from_bytes(&assembly_buffer.buffer_bytes.freeze()) {
+            Ok(p) => p,
+            Err(e) => {
               error!("Deserializing SerializedPayload from DATAFRAG: {:?}", &e);
-              None
-            },
-            Some,
-          )?;
+              return DataFragResult::Incomplete;
+            }
+          };
         let dds_data = if flags.contains(DATAFRAG_Flags::Key) {
           DDSData::new_disposed_by_key(ChangeKind::NotAliveDisposed, serialized_data_or_key)
         } else {
           // it is data
           DDSData::new(serialized_data_or_key)
         };
-        Some(dds_data) // completed data from fragments
+        DataFragResult::Completed(dds_data) // completed data from fragments
       } else {
         error!("Assembly buffer mysteriously lost");
-        None
+        DataFragResult::Incomplete
       }
     } else {
       debug!("new_dataFrag: FRAGMENT NOT COMPLETED YET");
-      None
+      DataFragResult::Incomplete
     }
   }
 
   pub fn garbage_collect_before(&mut self, expire_before: Timestamp) {
+    let memory_budget = &self.memory_budget;
     self.assembly_buffers.retain(|sn, ab| {
       let retain = ab.modified_time >= expire_before;
       if !retain {
         info!("AssemblyBuffer dropping {sn:?}");
+        memory_budget.release(MemoryKind::FragmentAssembly, ab.buffer_bytes.len());
       }
       retain
     });
@@ -245,3 +289,16 @@ impl FragmentAssembler {
     }
   }
 }
+
+impl Drop for FragmentAssembler {
+  fn drop(&mut self) {
+    let size = self
+      .assembly_buffers
+      .values()
+      .map(|ab| ab.buffer_bytes.len())
+      .sum();
+    self
+      .memory_budget
+      .release(MemoryKind::FragmentAssembly, size);
+  }
+}
@@ -21,7 +21,7 @@ use crate::{
   },
   rtps::{Submessage, SubmessageBody},
   structure::{
-    cache_change::CacheChange,
+    cache_change::{CacheChange, ChangeKind},
     guid::{EntityId, GuidPrefix, GUID},
     parameter_id::ParameterId,
     sequence_number::{FragmentNumber, SequenceNumber, SequenceNumberSet},
@@ -72,15 +72,111 @@ impl Message {
     let mut submessages_left: Bytes = buffer.slice(20..); // header is 20 bytes
                                                           // submessage loop
     while !submessages_left.is_empty() {
-      if let Some(submessage) = Submessage::read_from_buffer(&mut submessages_left)? {
-        message.submessages.push(submessage);
+      match Submessage::read_from_buffer(&mut submessages_left) {
+        Ok(Some(submessage)) => message.submessages.push(submessage),
+        Ok(None) => {} // unknown or vendor-specific kind, skipped by length
+        Err(e) => {
+          // RTPS spec v2.5 Section 8.3.4.1: An invalid submessage invalidates
+          // the rest of the Message, but the preceding submessages are still
+          // processed.
+          warn!(
+            "Invalid submessage from {:?}: {e}. Ignoring the rest of the message.",
+            message.header.guid_prefix
+          );
+          break;
+        }
       }
     } // loop
 
     Ok(message)
   }
+
+  /// Split the message into messages that are at most `max_size` bytes long,
+  /// when serialized.
+  ///
+  /// INFO_DST and INFO_TS submessages are repeated at the start of each new
+  /// message, so that they still apply to the submessages that follow them. A
+  /// submessage protected with SEC_PREFIX and SEC_POSTFIX is kept together
+  /// with them. A submessage that does not fit even alone is sent in a message
+  /// of its own, which then exceeds `max_size`.
+  pub fn split_to_fit(self, max_size: usize) -> Vec<Message> {
+    fn submessage_len(submessage: &Submessage) -> usize {
+      4 + usize::from(submessage.header.content_length) // header + content
+    }
+    fn is_interpreter_state(submessage: &Submessage) -> bool {
+      matches!(
+        submessage.header.kind,
+        SubmessageKind::INFO_DST | SubmessageKind::INFO_TS
+      )
+    }
+
+    let Message {
+      header,
+      submessages,
+    } = self;
+
+    let mut messages = Vec::new();
+    let mut current = Message::new(header);
+    let mut current_len = HEADER_LEN;
+    let mut current_has_content = false;
+    // INFO_DST and INFO_TS in effect at the end of `current`
+    let mut interpreter_state: Vec<Submessage> = Vec::new();
+    // INFO_DST and INFO_TS not yet followed by any other submessage
+    let mut pending: Vec<Submessage> = Vec::new();
+
+    let mut submessages = submessages.into_iter();
+    while let Some(submessage) = submessages.next() {
+      if is_interpreter_state(&submessage) {
+        pending.push(submessage);
+        continue;
+      }
+      // Collect the next unit that must not be split
+      let mut unit = vec![submessage];
+      if unit[0].header.kind == SubmessageKind::SEC_PREFIX {
+        for protected in submessages.by_ref() {
+          let is_postfix = protected.header.kind == SubmessageKind::SEC_POSTFIX;
+          unit.push(protected);
+          if is_postfix {
+            break;
+          }
+        }
+      }
+      let unit_len: usize = pending.iter().chain(unit.iter()).map(submessage_len).sum();
+
+      if current_has_content && current_len + unit_len > max_size {
+        messages.push(std::mem::replace(&mut current, Message::new(header)));
+        current_len = HEADER_LEN;
+        // Restate the interpreter state, unless overridden by pending ones.
+        for state in &interpreter_state {
+          if pending.iter().all(|p| p.header.kind != state.header.kind) {
+            current_len += submessage_len(state);
+            current.submessages.push(state.clone());
+          }
+        }
+      }
+
+      for p in pending.drain(..) {
+        interpreter_state.retain(|state| state.header.kind != p.header.kind);
+        interpreter_state.push(p.clone());
+        current.submessages.push(p);
+      }
+      current.submessages.extend(unit);
+      current_len += unit_len;
+      current_has_content = true;
+    }
+    // Trailing interpreter submessages do not affect anything, but keep them.
+    current.submessages.extend(pending);
+
+    if !current.submessages.is_empty() || messages.is_empty() {
+      messages.push(current);
+    }
+    messages
+  }
 }
 
+// RTPS spec v2.5 Section "9.4.4 Header"
+const HEADER_LEN: usize = 20;
+
 impl Message {
   pub fn new(header: Header) -> Self {
     Self {
@@ -180,6 +276,7 @@ impl MessageBuilder {
     writer_guid: GUID,
     endianness: Endianness,
     security_plugins: Option<&SecurityPluginsHandle>,
+    optional_inline_qos: bool, // Does the receiving Reader get the key hash?
   ) -> Self {
     #[cfg(not(feature = "security"))]
     // Parameter not used
@@ -189,37 +286,54 @@ impl MessageBuilder {
 
     let mut param_list = ParameterList::new(); // inline QoS goes here
 
-    // Check if we are disposing (by key or by key hash).
-    // If yes, then Indicate Dispose by PID_STATUS_INFO in Inline QoS
+    // Check if we are disposing or unregistering (by key or by key hash).
+    // If yes, then indicate which by PID_STATUS_INFO in Inline QoS
     // RTPS Spec v2.5 Section "9.6.4.9 StatusInfo_t (PID_STATUS_INFO)"
-    // Dispose must be indicated in Inline QoS:
+    // Dispose and unregister must be indicated in Inline QoS:
     // RTPS Spec v2.5 Section "8.7.4 Changes in the Instance State"
-    //
-    // When disposing, we also indicate "unregistered", although we do not
-    // support whole register/unregister mechanism at all. TODO: Does this
-    // make sense?
+    let status_info = |change_kind: ChangeKind| {
+      Parameter::create_pid_status_info_parameter(
+        change_kind.is_disposed(),
+        change_kind.is_unregistered(),
+        /* filtered */ false,
+      )
+    };
     match cache_change.data_value {
       DDSData::Data { .. } => (), // data sample, not dispose
 
-      DDSData::DisposeByKey { .. } => {
-        param_list.push(Parameter::create_pid_status_info_parameter(
-          /* disposed */ true, /* unregistered */ true, /* filtered */ false,
-        ));
+      DDSData::DisposeByKey { change_kind, .. } => {
+        param_list.push(status_info(change_kind));
       }
-      DDSData::DisposeByKeyHash { key_hash, .. } => {
+      DDSData::DisposeByKeyHash {
+        key_hash,
+        change_kind,
+      } => {
         // yes, insert key hash to inline QoS
         param_list.push(Parameter {
           parameter_id: ParameterId::PID_KEY_HASH,
           value: key_hash.to_vec(),
         });
         // ... and tell what the key_hash means
-        let status_info = Parameter::create_pid_status_info_parameter(
-          /* disposed */ true, /* unregistered */ true, /* filtered */ false,
-        );
-        param_list.push(status_info);
+        param_list.push(status_info(change_kind));
       }
     }
 
+    // Key hash of a data sample or key is optional inline QoS. It is sent, if
+    // the DataWriter provided it. (RTPS Spec v2.5 Section 9.6.4.8 KeyHash)
+    // DisposeByKeyHash has it already.
+    if let (DDSData::Data { .. } | DDSData::DisposeByKey { .. }, Some(key_hash)) = (
+      &cache_change.data_value,
+      cache_change
+        .write_options
+        .key_hash()
+        .filter(|_| optional_inline_qos),
+    ) {
+      param_list.push(Parameter {
+        parameter_id: ParameterId::PID_KEY_HASH,
+        value: key_hash.to_vec(),
+      });
+    }
+
     // If we are sending related sample identity, then insert that.
     if let Some(si) = cache_change.write_options.related_sample_identity() {
       let related_sample_identity_serialized = si.write_to_vec_with_ctx(endianness).unwrap();
@@ -333,6 +447,7 @@ impl MessageBuilder {
     sample_size: u32, // all fragments together
     endianness: Endianness,
     security_plugins: Option<&SecurityPluginsHandle>,
+    optional_inline_qos: bool, // Does the receiving Reader get the key hash?
   ) -> Self {
     #[cfg(not(feature = "security"))]
     // Parameter not used
@@ -357,6 +472,17 @@ impl MessageBuilder {
       }
     }
 
+    if let Some(key_hash) = cache_change
+      .write_options
+      .key_hash()
+      .filter(|_| optional_inline_qos)
+    {
+      param_list.push(Parameter {
+        parameter_id: ParameterId::PID_KEY_HASH,
+        value: key_hash.to_vec(),
+      });
+    }
+
     // If we are sending related sample identity, then insert that.
     if let Some(si) = cache_change.write_options.related_sample_identity() {
       let related_sample_identity_serialized = si.write_to_vec_with_ctx(endianness).unwrap();
@@ -492,9 +618,7 @@ impl MessageBuilder {
           gap_list,
         };
         let gap_flags = BitFlags::<GAP_Flags>::from_endianness(writer_endianness);
-        gap
-          .create_submessage(gap_flags)
-          .map(|s| self.submessages.push(s));
+        self.push_gap(gap, gap_flags);
       }
       (_, _) => error!("gap_msg called with empty SN set. Skipping GAP submessage"),
     }
@@ -518,10 +642,29 @@ impl MessageBuilder {
     };
 
     let gap_flags = BitFlags::<GAP_Flags>::from_endianness(writer_endianness);
+    self.push_gap(gap, gap_flags);
+    self
+  }
+
+  // Adds a GAP submessage, or merges it into the previous one, if that is a
+  // GAP to the same Reader, and the two can be merged.
+  fn push_gap(&mut self, gap: Gap, flags: BitFlags<GAP_Flags>) {
+    let gap = match self.submessages.last() {
+      Some(Submessage {
+        body: SubmessageBody::Writer(WriterSubmessage::Gap(previous, previous_flags)),
+        ..
+      }) if *previous_flags == flags => match previous.merge(&gap) {
+        Some(merged) => {
+          self.submessages.pop();
+          merged
+        }
+        None => gap,
+      },
+      _ => gap,
+    };
     gap
-      .create_submessage(gap_flags)
+      .create_submessage(flags)
       .map(|s| self.submessages.push(s));
-    self
   }
 
   #[allow(clippy::too_many_arguments)] // Heartbeat just is complicated.
@@ -580,6 +723,7 @@ mod tests {
   use log::info;
 
   use super::*;
+  use crate::structure::guid::EntityKind;
 
   #[test]
 
@@ -793,6 +937,97 @@ mod tests {
     assert_eq!(bits1, serialized);
   }
 
+  #[test]
+  fn message_builder_merges_gaps() {
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let writer_id =
+      EntityId::create_custom_entity_id([1, 2, 3], EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let gaps = |message: Message| -> Vec<Gap> {
+      message
+        .submessages()
+        .into_iter()
+        .filter_map(|s| match s.body {
+          SubmessageBody::Writer(WriterSubmessage::Gap(gap, _)) => Some(gap),
+          _ => None,
+        })
+        .collect()
+    };
+
+    // 1..5 and 5,6,9 are merged
+    let sns: BTreeSet<SequenceNumber> = [5, 6, 9].into_iter().map(SequenceNumber::from).collect();
+    let message = MessageBuilder::new()
+      .gap_msg_before(
+        SequenceNumber::from(5),
+        writer_id,
+        Endianness::LittleEndian,
+        reader_guid,
+      )
+      .gap_msg(&sns, writer_id, Endianness::LittleEndian, reader_guid)
+      .add_header_and_build(GuidPrefix::default());
+    let merged = gaps(message);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].gap_start, SequenceNumber::from(1));
+    assert_eq!(merged[0].gap_list.base(), SequenceNumber::from(7));
+    assert_eq!(
+      merged[0].gap_list.iter().collect::<Vec<_>>(),
+      vec![SequenceNumber::from(9)]
+    );
+
+    // 1..3 and 5,6,9 are not
+    let message = MessageBuilder::new()
+      .gap_msg_before(
+        SequenceNumber::from(3),
+        writer_id,
+        Endianness::LittleEndian,
+        reader_guid,
+      )
+      .gap_msg(&sns, writer_id, Endianness::LittleEndian, reader_guid)
+      .add_header_and_build(GuidPrefix::default());
+    assert_eq!(gaps(message).len(), 2);
+  }
+
+  #[test]
+  fn split_to_fit_restates_interpreter_submessages() {
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let writer_id =
+      EntityId::create_custom_entity_id([1, 2, 3], EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let mut builder = MessageBuilder::new()
+      .dst_submessage(Endianness::LittleEndian, reader_guid.prefix)
+      .ts_msg(Endianness::LittleEndian, Some(Timestamp::now()));
+    for count in 0..10 {
+      builder = builder.heartbeat_msg(
+        writer_id,
+        SequenceNumber::from(1),
+        SequenceNumber::from(10),
+        count,
+        Endianness::LittleEndian,
+        reader_guid.entity_id,
+        false,
+        false,
+      );
+    }
+    let message = builder.add_header_and_build(GuidPrefix::default());
+
+    let max_size = 150;
+    let messages = message.split_to_fit(max_size);
+    assert_eq!(messages.len(), 4);
+
+    let mut heartbeat_count = 0;
+    for message in messages {
+      assert!(message.write_to_vec().unwrap().len() <= max_size);
+      let kinds: Vec<SubmessageKind> = message.submessages.iter().map(|s| s.header.kind).collect();
+      assert_eq!(
+        kinds[..2],
+        [SubmessageKind::INFO_DST, SubmessageKind::INFO_TS]
+      );
+      heartbeat_count += kinds
+        .iter()
+        .filter(|k| **k == SubmessageKind::HEARTBEAT)
+        .count();
+    }
+    assert_eq!(heartbeat_count, 10);
+  }
+
   #[test]
   fn fuzz_rtps() {
     // https://github.com/jhelovuo/RustDDS/issues/280
@@ -811,4 +1046,27 @@ mod tests {
     info!("read_from_buffer() --> {rtps:?}");
     // if we get here without panic, the test passes
   }
+
+  #[test]
+  fn invalid_submessage_keeps_preceding_ones() {
+    use hex_literal::hex;
+
+    let bits = Bytes::copy_from_slice(&hex!(
+      "
+      52 54 50 53
+      02 04 01 12 01 0f 45 d2 b3 f5 58 b9 01 00 00 00
+      09 01 08 00 1a 15 f3 5e 00 cc fb 13
+      fa 01 04 00 de ad be ef
+      09 01 10 00 1a 15 f3 5e 00 cc fb 13
+    "
+    ));
+    // INFO_TS, vendor-specific submessage (skipped), and an INFO_TS whose
+    // length runs past the end of the message.
+    let rtps = Message::read_from_buffer(&bits).unwrap();
+    assert_eq!(rtps.submessages.len(), 1);
+    assert!(matches!(
+      rtps.submessages[0].body,
+      SubmessageBody::Interpreter(_)
+    ));
+  }
 }
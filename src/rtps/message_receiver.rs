@@ -110,7 +110,7 @@ pub(crate) struct MessageReceiver {
   // We send notification of remote DomainParticipant liveness to Discovery to
   // bypass Reader, DDSCache, DatasampleCache, and DataReader, because these will drop
   // repeated messages with duplicate SequenceNumbers, but Discovery needs to see them.
-  spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+  spdp_liveness_sender: mio_channel::SyncSender<(GuidPrefix, Option<VendorId>)>,
   security_plugins: Option<SecurityPluginsHandle>,
 
   own_guid_prefix: GuidPrefix,
@@ -136,7 +136,7 @@ impl MessageReceiver {
   pub fn new(
     participant_guid_prefix: GuidPrefix,
     acknack_sender: mio_channel::SyncSender<(GuidPrefix, AckSubmessage)>,
-    spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_liveness_sender: mio_channel::SyncSender<(GuidPrefix, Option<VendorId>)>,
     security_plugins: Option<SecurityPluginsHandle>,
   ) -> Self {
     Self {
@@ -164,6 +164,16 @@ impl MessageReceiver {
     }
   }
 
+  /// VendorId of the message being processed, from the RTPS header or the
+  /// latest InfoSource. `None` if the sender did not say.
+  pub fn get_source_vendor_id(&self) -> Option<VendorId> {
+    if self.source_vendor_id == VendorId::VENDOR_UNKNOWN {
+      None
+    } else {
+      Some(self.source_vendor_id)
+    }
+  }
+
   pub fn reset(&mut self) {
     self.source_version = ProtocolVersion::THIS_IMPLEMENTATION;
     self.source_vendor_id = VendorId::VENDOR_UNKNOWN;
@@ -610,7 +620,7 @@ impl MessageReceiver {
         {
           self
             .spdp_liveness_sender
-            .try_send(source_guid_prefix)
+            .try_send((source_guid_prefix, self.get_source_vendor_id()))
             .unwrap_or_else(|e| {
               debug!("spdp_liveness_sender.try_send(): {e:?}. Is Discovery alive?");
             });
@@ -621,6 +631,7 @@ impl MessageReceiver {
         target_reader.handle_heartbeat_msg(
           &heartbeat,
           flags.contains(HEARTBEAT_Flags::Final),
+          flags.contains(HEARTBEAT_Flags::Liveliness),
           &mr_state,
         );
       }
@@ -1086,10 +1097,7 @@ impl MessageReceiver {
 
 #[cfg(test)]
 mod tests {
-  use std::{
-    rc::Rc,
-    sync::{Arc, Mutex, RwLock},
-  };
+  use std::sync::{Arc, RwLock};
 
   use speedy::{Readable, Writable};
   use log::info;
@@ -1098,14 +1106,8 @@ mod tests {
   use byteorder::LittleEndian;
 
   use crate::{
-    dds::{
-      qos::QosPolicies,
-      statusevents::{sync_status_channel, DataReaderStatus},
-      typedesc::TypeDesc,
-      with_key::simpledatareader::ReaderCommand,
-    },
+    dds::{qos::QosPolicies, statusevents::sync_status_channel, typedesc::TypeDesc},
     messages::header::Header,
-    mio_source,
     network::udp_sender::UDPSender,
     rtps::reader::ReaderIngredients,
     serialization::from_bytes,
@@ -1160,18 +1162,9 @@ mod tests {
       EntityId::create_custom_entity_id([0, 0, 0], EntityKind::READER_WITH_KEY_USER_DEFINED);
     let reader_guid = GUID::new_with_prefix_and_id(target_gui_prefix, entity);
 
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
     let qos_policy = QosPolicies::qos_none();
 
     let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
@@ -1181,23 +1174,12 @@ mod tests {
       TypeDesc::new("test".to_string()),
       &qos_policy,
     );
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: "test".to_string(),
-      topic_cache_handle: topic_cache_handle.clone(),
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker: data_reader_waker.clone(),
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (reader_ing, _ends) =
+      ReaderIngredients::for_test(reader_guid, "test", topic_cache_handle.clone(), qos_policy);
 
     let mut new_reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new_with_random_port().unwrap()),
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
@@ -1277,12 +1259,20 @@ mod tests {
     let (spdp_liveness_sender, _spdp_liveness_receiver) = mio_channel::sync_channel(8);
     let mut message_receiver =
       MessageReceiver::new(guid_new.prefix, acknack_sender, spdp_liveness_sender, None);
+    assert_eq!(message_receiver.get_source_vendor_id(), None);
 
     message_receiver.handle_received_packet(&udp_bits1);
     assert_eq!(message_receiver.submessage_count, 4);
 
     message_receiver.handle_received_packet(&udp_bits2);
     assert_eq!(message_receiver.submessage_count, 2);
+    // eProsima, from the header
+    assert_eq!(
+      message_receiver.get_source_vendor_id(),
+      Some(VendorId {
+        vendor_id: [0x01, 0x0f]
+      })
+    );
   }
 
   #[test]
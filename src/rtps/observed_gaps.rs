@@ -0,0 +1,144 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::structure::{guid::GUID, sequence_number::SequenceNumber};
+
+// How many separate missing ranges are remembered per Writer. When exceeded,
+// the oldest ranges are forgotten.
+const MAX_GAPS_PER_WRITER: usize = 64;
+
+// Purely observational record of sequence numbers that a Reader has not
+// received from each matched Writer. This is independent of reliability: a
+// BestEffort Reader never tries to recover these, but the application may want
+// to know about them anyway.
+//
+// A range is recorded when a DATA arrives with a sequence number beyond the
+// next expected one. Late-arriving DATA (reordering, or Reliable repair) and
+// GAP submessages from the Writer remove sequence numbers from the record.
+#[derive(Debug, Default)]
+pub(crate) struct ObservedGaps {
+  gaps: BTreeMap<GUID, Vec<Range<SequenceNumber>>>,
+}
+
+impl ObservedGaps {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // previous_highest is the largest sequence number received from the
+  // writer before writer_sn, or zero if nothing has been received yet.
+  pub fn record_received(
+    &mut self,
+    writer: GUID,
+    previous_highest: SequenceNumber,
+    writer_sn: SequenceNumber,
+  ) {
+    let next_expected = previous_highest + SequenceNumber::new(1);
+    if writer_sn < next_expected {
+      // Possibly fills a previously observed gap
+      self.remove_range(writer, writer_sn..writer_sn + SequenceNumber::new(1));
+    } else if writer_sn > next_expected && previous_highest > SequenceNumber::new(0) {
+      // Do not record anything before the first received sample, as we may
+      // have just joined late.
+      let writer_gaps = self.gaps.entry(writer).or_default();
+      writer_gaps.push(next_expected..writer_sn);
+      if writer_gaps.len() > MAX_GAPS_PER_WRITER {
+        let excess = writer_gaps.len() - MAX_GAPS_PER_WRITER;
+        writer_gaps.drain(..excess);
+      }
+    }
+  }
+
+  // Sequence numbers in removed are no longer considered missing.
+  pub fn remove_range(&mut self, writer: GUID, removed: Range<SequenceNumber>) {
+    if removed.start >= removed.end {
+      return;
+    }
+    if let Some(writer_gaps) = self.gaps.get_mut(&writer) {
+      *writer_gaps = writer_gaps
+        .iter()
+        .flat_map(|g| {
+          [
+            g.start..g.end.min(removed.start),
+            g.start.max(removed.end)..g.end,
+          ]
+        })
+        .filter(|g| g.start < g.end)
+        .collect();
+      if writer_gaps.is_empty() {
+        self.gaps.remove(&writer);
+      }
+    }
+  }
+
+  pub fn remove_writer(&mut self, writer: GUID) {
+    self.gaps.remove(&writer);
+  }
+
+  pub fn snapshot(&self) -> Vec<(GUID, Vec<Range<SequenceNumber>>)> {
+    self
+      .gaps
+      .iter()
+      .map(|(guid, gaps)| (*guid, gaps.clone()))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::EntityKind;
+
+  fn sn(n: i64) -> SequenceNumber {
+    SequenceNumber::new(n)
+  }
+
+  #[test]
+  fn observed_gaps_record_and_fill() {
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let mut og = ObservedGaps::new();
+
+    // First sample does not create a gap, even if it is not #1
+    og.record_received(writer, sn(0), sn(5));
+    assert!(og.snapshot().is_empty());
+
+    og.record_received(writer, sn(5), sn(6));
+    og.record_received(writer, sn(6), sn(10));
+    og.record_received(writer, sn(10), sn(13));
+    assert_eq!(
+      og.snapshot(),
+      vec![(writer, vec![sn(7)..sn(10), sn(11)..sn(13)])]
+    );
+
+    // late arrival splits a range
+    og.record_received(writer, sn(13), sn(8));
+    assert_eq!(
+      og.snapshot(),
+      vec![(writer, vec![sn(7)..sn(8), sn(9)..sn(10), sn(11)..sn(13)])]
+    );
+
+    // GAP submessage covering several ranges
+    og.remove_range(writer, sn(7)..sn(12));
+    assert_eq!(og.snapshot(), vec![(writer, vec![sn(12)..sn(13)])]);
+
+    og.remove_range(writer, sn(12)..sn(13));
+    assert!(og.snapshot().is_empty());
+  }
+
+  #[test]
+  fn observed_gaps_are_bounded() {
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let mut og = ObservedGaps::new();
+
+    og.record_received(writer, sn(0), sn(1));
+    let mut highest = sn(1);
+    for _ in 0..(MAX_GAPS_PER_WRITER + 10) {
+      let next = highest + sn(2);
+      og.record_received(writer, highest, next);
+      highest = next;
+    }
+    let snapshot = og.snapshot();
+    assert_eq!(snapshot[0].1.len(), MAX_GAPS_PER_WRITER);
+    // oldest were dropped
+    assert_eq!(snapshot[0].1[0].start, sn(22));
+  }
+}
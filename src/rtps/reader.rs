@@ -1,14 +1,17 @@
 use std::{
-  collections::BTreeMap,
+  cell::RefCell,
+  collections::{BTreeMap, BTreeSet},
   fmt, iter,
-  rc::Rc,
   sync::{Arc, Mutex, MutexGuard},
   task::Waker,
   time::Duration as StdDuration,
 };
 
 use mio_06::Token;
-use mio_extras::{channel as mio_channel, timer::Timer};
+use mio_extras::{
+  channel as mio_channel,
+  timer::{Timeout, Timer},
+};
 use log::{debug, error, info, trace, warn};
 use enumflags2::BitFlags;
 use speedy::{Endianness, Writable};
@@ -16,9 +19,13 @@ use speedy::{Endianness, Writable};
 use crate::{
   dds::{
     ddsdata::DDSData,
-    qos::{policy, HasQoSPolicy, QosPolicies},
+    key::{InstanceHandle, KeyHash},
+    qos::{policy, HasQoSPolicy, QosPolicies, QosPolicyId},
+    sampleinfo::SampleFlag,
     statusevents::{
-      CountWithChange, DataReaderStatus, DomainParticipantStatusEvent, StatusChannelSender,
+      CountWithChange, DataReaderStatus, DomainParticipantStatusEvent, EndpointDescription,
+      LivelinessChangedStatus, RemoteLocators, SampleRejectedStatusKind, StatusChannelSender,
+      SubscriptionMatchedState,
     },
     with_key::{
       datawriter::{WriteOptions, WriteOptionsBuilder},
@@ -40,9 +47,13 @@ use crate::{
   mio_source,
   network::udp_sender::UDPSender,
   rtps::{
-    fragment_assembler::FragmentAssembler, message_receiver::MessageReceiverState,
-    rtps_writer_proxy::RtpsWriterProxy, Message,
+    fragment_assembler::{DataFragResult, FragmentAssembler},
+    message_receiver::MessageReceiverState,
+    observed_gaps::ObservedGaps,
+    rtps_writer_proxy::RtpsWriterProxy,
+    Message,
   },
+  serialization::compression,
   structure::{
     cache_change::{CacheChange, ChangeKind},
     dds_cache::TopicCache,
@@ -50,6 +61,7 @@ use crate::{
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
     locator::Locator,
+    memory_budget::MemoryBudget,
     sequence_number::{FragmentNumber, FragmentNumberSet, SequenceNumber, SequenceNumberSet},
     time::Timestamp,
   },
@@ -64,6 +76,9 @@ use crate::no_security::SecurityPluginsHandle;
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum TimedEvent {
   DeadlineMissedCheck,
+  LivelinessCheck,
+  SkipStaleSamples,
+  InitialAckNack { writer: GUID },
 }
 
 // Some pieces necessary to construct a reader.
@@ -80,8 +95,17 @@ pub(crate) struct ReaderIngredients {
   pub data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
   pub(crate) data_reader_waker: Arc<Mutex<Option<Waker>>>,
   pub(crate) poll_event_sender: mio_source::PollEventSender,
+  pub(crate) observed_gaps: Arc<Mutex<ObservedGaps>>,
+  pub(crate) subscription_matched: Arc<Mutex<SubscriptionMatchedState>>,
+  pub(crate) liveliness_changed: Arc<Mutex<LivelinessChangedStatus>>,
+  // DataReaders sharing this Reader in addition to the one that created it.
+  // Added to by the Subscriber.
+  pub(crate) additional_front_ends: Arc<Mutex<Vec<ReaderFrontEnd>>>,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
+
+  // See DomainParticipantBuilder::initial_acknack_delay
+  pub(crate) initial_acknack_delay: Option<StdDuration>,
 }
 
 impl ReaderIngredients {
@@ -90,6 +114,61 @@ impl ReaderIngredients {
   }
 }
 
+// The other ends of the channels in ReaderIngredients::for_test. Some are
+// only kept to keep the channels open.
+#[cfg(test)]
+#[allow(dead_code)]
+pub(crate) struct ReaderTestEnds {
+  pub notification_receiver: mio_channel::Receiver<()>,
+  pub notification_event_source: mio_source::PollEventSource,
+  pub status_receiver: crate::dds::statusevents::StatusChannelReceiver<DataReaderStatus>,
+  pub command_sender: mio_channel::SyncSender<ReaderCommand>,
+}
+
+#[cfg(test)]
+impl ReaderIngredients {
+  // Ingredients with new channels and defaults for everything else. Tests set
+  // the fields that they need on the result.
+  pub(crate) fn for_test(
+    guid: GUID,
+    topic_name: &str,
+    topic_cache_handle: Arc<Mutex<TopicCache>>,
+    qos_policy: QosPolicies,
+  ) -> (Self, ReaderTestEnds) {
+    let (notification_sender, notification_receiver) = mio_channel::sync_channel(100);
+    let (notification_event_source, poll_event_sender) = mio_source::make_poll_channel().unwrap();
+    let (status_sender, status_receiver) =
+      crate::dds::statusevents::sync_status_channel(32).unwrap();
+    let (command_sender, data_reader_command_receiver) = mio_channel::sync_channel(10);
+
+    let ingredients = Self {
+      guid,
+      notification_sender,
+      status_sender,
+      topic_name: topic_name.to_string(),
+      topic_cache_handle,
+      like_stateless: false,
+      qos_policy,
+      data_reader_command_receiver,
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender,
+      observed_gaps: Default::default(),
+      subscription_matched: Default::default(),
+      liveliness_changed: Default::default(),
+      additional_front_ends: Default::default(),
+      security_plugins: None,
+      initial_acknack_delay: None,
+    };
+    let ends = ReaderTestEnds {
+      notification_receiver,
+      notification_event_source,
+      status_receiver,
+      command_sender,
+    };
+    (ingredients, ends)
+  }
+}
+
 impl fmt::Debug for ReaderIngredients {
   // Need manual implementation, because channels cannot be Debug formatted.
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -101,11 +180,42 @@ impl fmt::Debug for ReaderIngredients {
   }
 }
 
+// Channels to a DataReader that shares a Reader created for another
+// DataReader. See Subscriber::set_reader_fan_out.
+pub(crate) struct ReaderFrontEnd {
+  pub(crate) notification_sender: mio_channel::SyncSender<()>,
+  pub(crate) status_sender: StatusChannelSender<DataReaderStatus>,
+  pub(crate) data_reader_waker: Arc<Mutex<Option<Waker>>>,
+  pub(crate) poll_event_sender: mio_source::PollEventSender,
+}
+
+impl ReaderFrontEnd {
+  // Returns false if the DataReader is gone.
+  fn notify(&self) -> bool {
+    if let Some(w) = self.data_reader_waker.lock().unwrap().take() {
+      w.wake();
+    }
+    self.poll_event_sender.send();
+    !matches!(
+      self.notification_sender.try_send(()),
+      Err(mio_channel::TrySendError::Disconnected(_))
+    )
+  }
+
+  // Returns false if the DataReader is gone.
+  fn send_status(&self, status: DataReaderStatus) -> bool {
+    !matches!(
+      self.status_sender.try_send(status),
+      Err(mio_channel::TrySendError::Disconnected(_))
+    )
+  }
+}
+
 pub(crate) struct Reader {
   // Should the instant be sent?
   notification_sender: mio_channel::SyncSender<()>,
   status_sender: StatusChannelSender<DataReaderStatus>,
-  udp_sender: Rc<UDPSender>,
+  udp_sender: Arc<UDPSender>,
 
   // By default, this reader is a StatefulReader (see RTPS spec section 8.4.12)
   // If like_stateless is true, then the reader mimics the behavior of a StatelessReader
@@ -117,6 +227,8 @@ pub(crate) struct Reader {
   reliability: policy::Reliability,
   // Reader stores a pointer to a mutex on the topic cache
   topic_cache: Arc<Mutex<TopicCache>>,
+  // Participant-wide memory budget, same as in the topic cache
+  memory_budget: Arc<MemoryBudget>,
 
   #[cfg(test)]
   seqnum_instant_map: BTreeMap<SequenceNumber, Timestamp>,
@@ -127,6 +239,9 @@ pub(crate) struct Reader {
   my_guid: GUID,
 
   heartbeat_response_delay: StdDuration,
+  // Delay from matching a Writer to sending the first pre-emptive ACKNACK, if
+  // any
+  initial_acknack_delay: Option<StdDuration>,
 
   // TODO: Implement (use) this
   #[allow(dead_code)]
@@ -134,18 +249,58 @@ pub(crate) struct Reader {
 
   received_heartbeat_count: i32,
 
+  // When set, no ACKNACKs or NACKFRAGs are sent, so that reliable Writers stop
+  // sending more data once their send window is full.
+  flow_control_paused: bool,
+  // When set, incoming DATA and DATAFRAG are dropped unseen, and no ACKNACKs
+  // are sent, so that reliable Writers keep the samples until resumed.
+  delivery_paused: bool,
+
   fragment_assemblers: BTreeMap<GUID, FragmentAssembler>,
   last_fragment_garbage_collect: Timestamp,
   matched_writers: BTreeMap<GUID, RtpsWriterProxy>,
+  // Where an ACKNACK or NACKFRAG to each matched Writer was last successfully
+  // sent to. This is updated while sending, which only has a shared reference to
+  // self.
+  last_used_locators: RefCell<BTreeMap<GUID, Locator>>,
   writer_match_count_total: i32, // total count, never decreases
 
   requested_deadline_missed_count: i32,
+  // Latest update of each alive instance, and which Writer sent it. Only
+  // maintained with a Deadline policy, and only for instances whose key hash
+  // the Writer sends.
+  instance_last_received: BTreeMap<KeyHash, (Timestamp, GUID)>,
+  // Writers that have sent key hashes. These are checked per instance, and
+  // the others per Writer.
+  instance_writers: BTreeSet<GUID>,
   offered_incompatible_qos_count: i32,
+  // Writers whose QoS is incompatible, and the policy last reported for each.
+  // Used to report each incompatibility only once, not on every
+  // re-announcement.
+  incompatible_writers: BTreeMap<GUID, QosPolicyId>,
+  sample_rejected_count: i32,
+  sample_lost_count: i32,
+  // TopicCache::evicted_count already reported as SampleLost
+  evicted_samples_seen: u64,
 
   pub(crate) timed_event_timer: Timer<TimedEvent>,
   pub(crate) data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
   data_reader_waker: Arc<Mutex<Option<Waker>>>,
   poll_event_sender: mio_source::PollEventSender,
+  // Missing sequence numbers per Writer, shared with the DataReader
+  observed_gaps: Arc<Mutex<ObservedGaps>>,
+  // Matched Writer counts, shared with the DataReader
+  subscription_matched: Arc<Mutex<SubscriptionMatchedState>>,
+  // Alive and not alive Writer counts, shared with the DataReader
+  liveliness_changed: Arc<Mutex<LivelinessChangedStatus>>,
+  // Other DataReaders receiving the same notifications and status events
+  additional_front_ends: Arc<Mutex<Vec<ReaderFrontEnd>>>,
+  // The LivelinessCheck in the timer, if any, and when it is due
+  liveliness_check_timeout: Option<(Timeout, Timestamp)>,
+  // The SkipStaleSamples in the timer, if any, and when it is due
+  stale_sample_check_timeout: Option<(Timeout, Timestamp)>,
+  // How long a Writer may stay not alive before it is unmatched
+  not_alive_writer_prune_delay: Duration,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 
@@ -158,16 +313,28 @@ pub(crate) struct Reader {
 const FRAGMENT_ASSEMBLY_TIMEOUT: Duration = Duration::from_secs(10);
 // minimum interval (max frequency) of AssemblyBuffer GC
 const MIN_FRAGMENT_GC_INTERVAL: Duration = Duration::from_secs(2);
+// A matched Writer that has lost liveliness and stays silent for this long
+// after that is unmatched.
+const NOT_ALIVE_WRITER_PRUNE_DELAY: Duration = Duration::from_secs(60);
 
 impl Reader {
   pub(crate) fn new(
     i: ReaderIngredients,
-    udp_sender: Rc<UDPSender>,
+    udp_sender: Arc<UDPSender>,
     timed_event_timer: Timer<TimedEvent>,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
   ) -> Self {
+    let reliability = i
+      .qos_policy
+      .reliability() // use qos specification
+      .unwrap_or(policy::Reliability::BestEffort); // or default to BestEffort
+
     // Verify that the topic cache corresponds to the topic of the Reader
-    let topic_cache_name = i.topic_cache_handle.lock().unwrap().topic_name();
+    let (topic_cache_name, memory_budget, evicted_samples_seen) = {
+      let mut tc = i.topic_cache_handle.lock().unwrap();
+      let evicted_count = tc.add_reader(i.guid, &reliability);
+      (tc.topic_name(), tc.memory_budget(), evicted_count)
+    };
     if i.topic_name != topic_cache_name {
       panic!(
         "Topic name = {} and topic cache name = {} not equal when creating a Reader",
@@ -185,11 +352,9 @@ impl Reader {
       status_sender: i.status_sender,
       udp_sender,
       like_stateless: i.like_stateless,
-      reliability: i
-        .qos_policy
-        .reliability() // use qos specification
-        .unwrap_or(policy::Reliability::BestEffort), // or default to BestEffort
+      reliability,
       topic_cache: i.topic_cache_handle,
+      memory_budget,
       topic_name: i.topic_name,
       qos_policy: i.qos_policy,
 
@@ -198,21 +363,38 @@ impl Reader {
       my_guid: i.guid,
 
       heartbeat_response_delay: StdDuration::new(0, 500_000_000), // 0,5sec
+      initial_acknack_delay: i.initial_acknack_delay,
       heartbeat_suppression_duration: StdDuration::new(0, 0),
       received_heartbeat_count: 0,
+      flow_control_paused: false,
+      delivery_paused: false,
       fragment_assemblers: BTreeMap::new(),
       last_fragment_garbage_collect: Timestamp::now(),
       matched_writers: BTreeMap::new(),
       writer_match_count_total: 0,
       requested_deadline_missed_count: 0,
+      instance_last_received: BTreeMap::new(),
+      instance_writers: BTreeSet::new(),
       offered_incompatible_qos_count: 0,
+      incompatible_writers: BTreeMap::new(),
+      sample_rejected_count: 0,
+      sample_lost_count: 0,
+      evicted_samples_seen,
       timed_event_timer,
       data_reader_command_receiver: i.data_reader_command_receiver,
       data_reader_waker: i.data_reader_waker,
       poll_event_sender: i.poll_event_sender,
+      observed_gaps: i.observed_gaps,
+      subscription_matched: i.subscription_matched,
+      liveliness_changed: i.liveliness_changed,
+      additional_front_ends: i.additional_front_ends,
+      liveliness_check_timeout: None,
+      stale_sample_check_timeout: None,
+      not_alive_writer_prune_delay: NOT_ALIVE_WRITER_PRUNE_DELAY,
       participant_status_sender,
 
       security_plugins: i.security_plugins,
+      last_used_locators: RefCell::new(BTreeMap::new()),
     }
   }
   // TODO: check if it's necessary to implement different handlers for discovery
@@ -243,6 +425,12 @@ impl Reader {
   }
 
   pub fn send_status_change(&self, change: DataReaderStatus) {
+    self
+      .additional_front_ends
+      .lock()
+      .unwrap()
+      .retain(|front_end| front_end.send_status(change.clone()));
+
     match self.status_sender.try_send(change) {
       Ok(()) => (), // expected result
       Err(mio_channel::TrySendError::Full(_)) => {
@@ -284,7 +472,27 @@ impl Reader {
 
     let mut changes: Vec<DataReaderStatus> = vec![];
     let now = Timestamp::now();
-    for writer_proxy in self.matched_writers.values_mut() {
+
+    // Writers that send key hashes are checked per instance.
+    for (key_hash, (last_received, _writer)) in &self.instance_last_received {
+      let since_last = now.duration_since(*last_received);
+      trace!("Comparing deadlines: {since_last:?} - {deadline_duration:?}");
+      if since_last > deadline_duration {
+        debug!("Deadline missed: {key_hash:?} {since_last:?} - {deadline_duration:?}");
+        self.requested_deadline_missed_count += 1;
+        changes.push(DataReaderStatus::RequestedDeadlineMissed {
+          count: CountWithChange::start_from(self.requested_deadline_missed_count, 1),
+          last_instance: Some(InstanceHandle(*key_hash)),
+        });
+      }
+    }
+
+    // No instance information from other Writers, so check per Writer.
+    for writer_proxy in self
+      .matched_writers
+      .values_mut()
+      .filter(|wp| !self.instance_writers.contains(&wp.remote_writer_guid))
+    {
       if let Some(last_change) = writer_proxy.last_change_timestamp() {
         let since_last = now.duration_since(last_change);
         // if time singe last received message is greater than deadline increase status
@@ -295,6 +503,7 @@ impl Reader {
           self.requested_deadline_missed_count += 1;
           changes.push(DataReaderStatus::RequestedDeadlineMissed {
             count: CountWithChange::start_from(self.requested_deadline_missed_count, 1),
+            last_instance: None,
           });
         }
       } else {
@@ -303,12 +512,42 @@ impl Reader {
         self.requested_deadline_missed_count += 1;
         changes.push(DataReaderStatus::RequestedDeadlineMissed {
           count: CountWithChange::start_from(self.requested_deadline_missed_count, 1),
+          last_instance: None,
         });
       }
     } // for
     changes
   } // fn
 
+  // Keeps track of instance updates for the Deadline check. Disposed or
+  // unregistered instances are no longer expected to be updated.
+  fn record_instance_update(
+    &mut self,
+    dds_data: &DDSData,
+    write_options: &WriteOptions,
+    receive_timestamp: Timestamp,
+    writer_guid: GUID,
+  ) {
+    if self.qos_policy.deadline.is_none() {
+      return;
+    }
+    match (dds_data, write_options.key_hash()) {
+      (DDSData::Data { .. }, Some(key_hash)) => {
+        self
+          .instance_last_received
+          .insert(key_hash, (receive_timestamp, writer_guid));
+        self.instance_writers.insert(writer_guid);
+      }
+      (DDSData::DisposeByKey { .. }, Some(key_hash)) => {
+        self.instance_last_received.remove(&key_hash);
+      }
+      (DDSData::DisposeByKeyHash { key_hash, .. }, _) => {
+        self.instance_last_received.remove(key_hash);
+      }
+      _ => (), // Writer did not tell the instance
+    }
+  }
+
   pub fn handle_timed_event(&mut self) {
     while let Some(e) = self.timed_event_timer.poll() {
       match e {
@@ -316,6 +555,18 @@ impl Reader {
           self.handle_requested_deadline_event();
           self.set_requested_deadline_check_timer(); // re-prime timer
         }
+        TimedEvent::LivelinessCheck => {
+          self.liveliness_check_timeout = None;
+          self.handle_liveliness_check();
+          self.set_liveliness_check_timer(); // re-prime timer
+        }
+        TimedEvent::SkipStaleSamples => {
+          self.stale_sample_check_timeout = None;
+          self.skip_stale_missing_samples(); // also re-primes timer
+        }
+        TimedEvent::InitialAckNack { writer } => {
+          self.send_initial_acknack(writer);
+        }
       }
     }
   }
@@ -329,6 +580,23 @@ impl Reader {
           warn!("RESET_REQUESTED_DEADLINE_STATUS not implemented!");
           // TODO: This should be implemented.
         }
+        Ok(ReaderCommand::GetRemoteLocators { reply }) => {
+          // The DataReader may have timed out already, so ignore errors.
+          let _ = reply.try_send(self.remote_writer_locators());
+        }
+        Ok(ReaderCommand::GetDiagnostic { reply }) => {
+          let mut text = String::new();
+          self
+            .write_diagnostic(&mut text)
+            .unwrap_or_else(|e| error!("Cannot format Reader diagnostic: {e}"));
+          let _ = reply.try_send(text);
+        }
+        Ok(ReaderCommand::SetFlowControlPause { paused }) => {
+          self.set_flow_control_pause(paused);
+        }
+        Ok(ReaderCommand::SetDeliveryPause { paused }) => {
+          self.set_delivery_pause(paused);
+        }
         // Disconnected is normal when terminating
         Err(TryRecvError::Disconnected) => {
           trace!("DataReader disconnected");
@@ -342,6 +610,43 @@ impl Reader {
     }
   }
 
+  fn set_flow_control_pause(&mut self, paused: bool) {
+    if paused == self.flow_control_paused {
+      return;
+    }
+    debug!(
+      "Flow control {} topic={:?} reader={:?}",
+      if paused { "paused" } else { "resumed" },
+      self.topic_name,
+      self.my_guid
+    );
+    self.flow_control_paused = paused;
+    if !self.acknacks_paused() {
+      self.send_resume_acknacks();
+    }
+  }
+
+  fn set_delivery_pause(&mut self, paused: bool) {
+    if paused == self.delivery_paused {
+      return;
+    }
+    debug!(
+      "Delivery {} topic={:?} reader={:?}",
+      if paused { "paused" } else { "resumed" },
+      self.topic_name,
+      self.my_guid
+    );
+    self.delivery_paused = paused;
+    // The resume ACKNACK makes reliable Writers resend what was dropped.
+    if !self.acknacks_paused() {
+      self.send_resume_acknacks();
+    }
+  }
+
+  fn acknacks_paused(&self) -> bool {
+    self.flow_control_paused || self.delivery_paused
+  }
+
   fn handle_requested_deadline_event(&mut self) {
     debug!("handle_requested_deadline_event");
     for missed_deadline in self.calculate_if_requested_deadline_is_missed() {
@@ -349,6 +654,171 @@ impl Reader {
     }
   }
 
+  // Schedule a liveliness check for when the first lease of the alive matched
+  // Writers expires, or the first not alive Writer is to be pruned. The check
+  // is rescheduled, if something is now due earlier than the pending check.
+  fn set_liveliness_check_timer(&mut self) {
+    let prune_delay = self.not_alive_writer_prune_delay;
+    let first_expiry = self
+      .matched_writers
+      .values()
+      .filter_map(|wp| wp.lease_expires_at().or_else(|| wp.prune_at(prune_delay)))
+      .min();
+    let Some(expires_at) = first_expiry else {
+      return;
+    };
+    if let Some((timeout, due)) = self.liveliness_check_timeout.take() {
+      if due <= expires_at {
+        self.liveliness_check_timeout = Some((timeout, due));
+        return;
+      }
+      self.timed_event_timer.cancel_timeout(&timeout);
+    }
+    let delay = expires_at.duration_since(Timestamp::now()).to_std();
+    let timeout = self
+      .timed_event_timer
+      .set_timeout(delay, TimedEvent::LivelinessCheck);
+    self.liveliness_check_timeout = Some((timeout, expires_at));
+  }
+
+  fn handle_liveliness_check(&mut self) {
+    let now = Timestamp::now();
+    let lost_writers: Vec<GUID> = self
+      .matched_writers
+      .values_mut()
+      .filter_map(|wp| wp.check_liveliness(now).then_some(wp.remote_writer_guid))
+      .collect();
+    for writer in lost_writers {
+      info!(
+        "Writer lost liveliness: topic={:?} writer={:?}",
+        self.topic_name, writer
+      );
+      self.liveliness_changed(writer, -1, 1);
+    }
+
+    // Writers that have not been heard of for a long time are forgotten, so
+    // that proxies of departed Writers do not accumulate. If such a Writer is
+    // announced again by Discovery, it is matched again.
+    let prune_delay = self.not_alive_writer_prune_delay;
+    let pruned_writers: Vec<GUID> = self
+      .matched_writers
+      .values()
+      .filter(|wp| wp.prune_at(prune_delay).is_some_and(|t| now >= t))
+      .map(|wp| wp.remote_writer_guid)
+      .collect();
+    for writer in pruned_writers {
+      info!(
+        "Unmatching Writer that has not been alive for {:?}: topic={:?} writer={:?}",
+        prune_delay, self.topic_name, writer
+      );
+      self.remove_writer_proxy(writer);
+    }
+  }
+
+  // MaxSampleAge: Give up missing samples that would be too old to deliver
+  // anyway, so that a Reliable DataReader can deliver newer samples.
+  fn skip_stale_missing_samples(&mut self) {
+    let Some(max_age) = self.qos_policy.max_sample_age() else {
+      return;
+    };
+    if self.reliability == policy::Reliability::BestEffort || self.like_stateless {
+      return;
+    }
+    let stale_before = Timestamp::now() - max_age.duration;
+    let mut skipped_total = 0;
+    let mut advanced_writers = Vec::new();
+    for wp in self.matched_writers.values_mut() {
+      let skipped = wp.skip_stale_missing(stale_before);
+      if skipped > 0 {
+        skipped_total += skipped;
+        advanced_writers.push((wp.remote_writer_guid, wp.all_ackable_before()));
+      }
+    }
+
+    if !advanced_writers.is_empty() {
+      debug!(
+        "Gave up {} stale missing samples. topic={:?} reader={:?}",
+        skipped_total, self.topic_name, self.my_guid
+      );
+      {
+        let mut observed_gaps = self.observed_gaps.lock().unwrap();
+        for (writer, ack_base) in &advanced_writers {
+          observed_gaps.remove_range(*writer, SequenceNumber::new(0)..*ack_base);
+        }
+      }
+      {
+        let mut tc = self.acquire_the_topic_cache_guard();
+        for (writer, ack_base) in advanced_writers {
+          tc.mark_reliably_received_before(writer, ack_base);
+        }
+      }
+      self.report_samples_lost(skipped_total);
+      self.notify_cache_change();
+    }
+    self.set_stale_sample_check_timer();
+  }
+
+  // Schedule a stale sample check for when the first missing sample is known to
+  // be older than MaxSampleAge. Like the liveliness check, the check is
+  // rescheduled, if it is now due earlier than the pending check.
+  fn set_stale_sample_check_timer(&mut self) {
+    let Some(max_age) = self.qos_policy.max_sample_age() else {
+      return;
+    };
+    let first_stale = self
+      .matched_writers
+      .values()
+      .filter_map(|wp| wp.missing_stale_at(max_age.duration))
+      .min();
+    let Some(stale_at) = first_stale else {
+      return;
+    };
+    if let Some((timeout, due)) = self.stale_sample_check_timeout.take() {
+      if due <= stale_at {
+        self.stale_sample_check_timeout = Some((timeout, due));
+        return;
+      }
+      self.timed_event_timer.cancel_timeout(&timeout);
+    }
+    let delay = stale_at.duration_since(Timestamp::now()).to_std();
+    let timeout = self
+      .timed_event_timer
+      .set_timeout(delay, TimedEvent::SkipStaleSamples);
+    self.stale_sample_check_timeout = Some((timeout, stale_at));
+  }
+
+  // DATA and DATA_FRAG assert the liveliness of the Writer, as does a HEARTBEAT
+  // with the liveliness flag. Other HEARTBEATs, GAPs and HEARTBEAT_FRAGs do
+  // not, because a Writer keeps sending them even if the application has
+  // stopped asserting liveliness.
+  fn writer_is_active(&mut self, writer_guid: GUID, now: Timestamp) {
+    let regained = self
+      .matched_writers
+      .get_mut(&writer_guid)
+      .is_some_and(|wp| wp.assert_liveliness(now));
+    if regained {
+      info!(
+        "Writer regained liveliness: topic={:?} writer={:?}",
+        self.topic_name, writer_guid
+      );
+      self.liveliness_changed(writer_guid, 1, -1);
+      // The lease is running again
+      self.set_liveliness_check_timer();
+    }
+  }
+
+  fn liveliness_changed(&mut self, writer: GUID, alive_change: i32, not_alive_change: i32) {
+    let (alive_count, not_alive_count) = {
+      let mut status = self.liveliness_changed.lock().unwrap();
+      status.update(writer, alive_change, not_alive_change);
+      (status.alive_count.count(), status.not_alive_count.count())
+    };
+    self.send_status_change(DataReaderStatus::LivelinessChanged {
+      alive_total: CountWithChange::new(alive_count, alive_change),
+      not_alive_total: CountWithChange::new(not_alive_count, not_alive_change),
+    });
+  }
+
   // TODO Used for test/debugging purposes
   #[cfg(test)]
   pub fn history_cache_change_data(&self, sequence_number: SequenceNumber) -> Option<DDSData> {
@@ -377,6 +847,17 @@ impl Reader {
 
   // updates or adds a new writer proxy, doesn't touch changes
   pub fn update_writer_proxy(&mut self, proxy: RtpsWriterProxy, offered_qos: &QosPolicies) {
+    self.update_discovered_writer_proxy(proxy, offered_qos, None);
+  }
+
+  // Like update_writer_proxy, but with the Discovery data of the Writer, which
+  // is passed on to the DataReader when matched.
+  pub fn update_discovered_writer_proxy(
+    &mut self,
+    mut proxy: RtpsWriterProxy,
+    offered_qos: &QosPolicies,
+    writer_description: Option<Box<EndpointDescription>>,
+  ) {
     if self.like_stateless {
       debug!(
         "Attempted to update writer proxy for stateless reader. Ignoring. topic={:?}",
@@ -391,27 +872,61 @@ impl Reader {
     match offered_qos.compliance_failure_wrt(&self.qos_policy) {
       None => {
         // success, update or insert
+        self.incompatible_writers.remove(&writer);
+        proxy.set_liveliness_lease(match offered_qos.liveliness {
+          Some(policy::Liveliness::ManualByTopic { lease_duration })
+            if lease_duration != Duration::INFINITE =>
+          {
+            Some(lease_duration)
+          }
+          _ => None,
+        });
         let count_change = self.matched_writer_update(proxy);
         if count_change > 0 {
+          self
+            .subscription_matched
+            .lock()
+            .unwrap()
+            .writer_matched(writer);
           self.writer_match_count_total += count_change;
           self.send_status_change(DataReaderStatus::SubscriptionMatched {
             total: CountWithChange::new(self.writer_match_count_total, count_change),
             current: CountWithChange::new(self.matched_writers.len() as i32, count_change),
             writer,
+            writer_description,
           });
           self.send_participant_status(DomainParticipantStatusEvent::RemoteWriterMatched {
             local_reader: self.my_guid,
             remote_writer: writer,
           });
 
+          // A new Writer is considered alive
+          self.liveliness_changed(writer, 1, 0);
+
+          // Ask for the available history without waiting for a HEARTBEAT
+          match self.initial_acknack_delay {
+            Some(delay) if delay.is_zero() => self.send_initial_acknack(writer),
+            Some(delay) => {
+              self
+                .timed_event_timer
+                .set_timeout(delay, TimedEvent::InitialAckNack { writer });
+            }
+            None => (),
+          }
+
           info!(
             "Matched new remote writer on topic={:?} writer={:?}",
             self.topic_name, writer
           );
         }
+        self.set_liveliness_check_timer();
       }
       Some(bad_policy_id) => {
         // no QoS match.
+        if self.incompatible_writers.insert(writer, bad_policy_id) == Some(bad_policy_id) {
+          debug!("update_writer_proxy - QoS mismatch {bad_policy_id:?} already reported");
+          return;
+        }
         self.offered_incompatible_qos_count += 1;
         self.send_status_change(DataReaderStatus::RequestedIncompatibleQos {
           count: CountWithChange::new(self.offered_incompatible_qos_count, 1),
@@ -448,8 +963,14 @@ impl Reader {
   }
 
   pub fn remove_writer_proxy(&mut self, writer_guid: GUID) {
-    if self.matched_writers.contains_key(&writer_guid) {
-      self.matched_writers.remove(&writer_guid);
+    self.incompatible_writers.remove(&writer_guid);
+    self.last_used_locators.get_mut().remove(&writer_guid);
+    if let Some(writer_proxy) = self.matched_writers.remove(&writer_guid) {
+      self
+        .observed_gaps
+        .lock()
+        .unwrap()
+        .remove_writer(writer_guid);
       #[cfg(feature = "security")]
       if let Some(security_plugins_handle) = &self.security_plugins {
         security_plugins_handle
@@ -457,12 +978,72 @@ impl Reader {
           .unregister_remote_writer(&self.my_guid, &writer_guid)
           .unwrap_or_else(|e| error!("{e}"));
       }
+      self
+        .subscription_matched
+        .lock()
+        .unwrap()
+        .writer_unmatched(writer_guid);
+      // Instances of an unmatched Writer are no longer expected to be updated
+      self
+        .instance_last_received
+        .retain(|_, (_, writer)| *writer != writer_guid);
+      self.instance_writers.remove(&writer_guid);
       self.send_status_change(DataReaderStatus::SubscriptionMatched {
         total: CountWithChange::new(self.writer_match_count_total, 0),
         current: CountWithChange::new(self.matched_writers.len() as i32, -1),
         writer: writer_guid,
+        writer_description: None,
       });
+      if writer_proxy.is_alive() {
+        self.liveliness_changed(writer_guid, -1, 0);
+      } else {
+        self.liveliness_changed(writer_guid, 0, -1);
+      }
+    }
+  }
+
+  // Human-readable summary of the RTPS state, for DataReader::print_diagnostic
+  fn write_diagnostic(&self, out: &mut impl fmt::Write) -> fmt::Result {
+    writeln!(
+      out,
+      "RTPS Reader {:?} topic={:?} {}",
+      self.my_guid,
+      self.topic_name,
+      if self.like_stateless {
+        "stateless"
+      } else if matches!(self.reliability, policy::Reliability::Reliable { .. }) {
+        "reliable"
+      } else {
+        "best effort"
+      }
+    )?;
+    writeln!(
+      out,
+      "Samples lost {}, rejected {}, fragment assemblies in progress {}",
+      self.sample_lost_count,
+      self.sample_rejected_count,
+      self.fragment_assemblers.len(),
+    )?;
+    writeln!(out, "Matched writers: {}", self.matched_writers.len())?;
+    for writer in self.matched_writers.values() {
+      write!(out, "  ")?;
+      writer.write_diagnostic(out)?;
     }
+    Ok(())
+  }
+
+  fn remote_writer_locators(&self) -> Vec<RemoteLocators> {
+    let last_used = self.last_used_locators.borrow();
+    self
+      .matched_writers
+      .values()
+      .map(|wp| RemoteLocators {
+        guid: wp.remote_writer_guid,
+        unicast_locators: wp.unicast_locator_list.clone(),
+        multicast_locators: wp.multicast_locator_list.clone(),
+        last_used: last_used.get(&wp.remote_writer_guid).copied(),
+      })
+      .collect()
   }
 
   // Entire remote participant was lost.
@@ -472,6 +1053,12 @@ impl Reader {
       .matched_writers
       .range(guid_prefix.range())
       .map(|(g, _)| *g)
+      .chain(
+        self
+          .incompatible_writers
+          .range(guid_prefix.range())
+          .map(|(g, _)| *g),
+      )
       .collect();
     for writer in lost_writers {
       self.remove_writer_proxy(writer);
@@ -524,7 +1111,7 @@ impl Reader {
     mr_state: &MessageReceiverState,
   ) {
     // trace!("handle_data_msg entry");
-    let receive_timestamp = Timestamp::now();
+    let receive_timestamp = Timestamp::now_monotonic();
 
     // parse write_options out of the message
     let mut write_options_b = WriteOptionsBuilder::new();
@@ -545,18 +1132,50 @@ impl Reader {
     {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
+    if let Some(key_hash) = data.inline_qos.as_ref().and_then(Self::inline_qos_key_hash) {
+      write_options_b = write_options_b.key_hash(key_hash);
+    }
+    let sample_flags = data
+      .inline_qos
+      .as_ref()
+      .map_or_else(BitFlags::empty, |inline_qos| {
+        Self::inline_qos_sample_flags(inline_qos, representation_identifier)
+      });
 
     let writer_guid = GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, data.writer_id);
     let writer_seq_num = data.writer_sn; // for borrow checker
+    self.writer_is_active(writer_guid, receive_timestamp);
+    if self.delivery_paused {
+      return;
+    }
+
+    // A writer-side content filter may replace a sample with a DATA that has
+    // only the Filtered StatusInfo flag (RTPS spec v2.5 Section 9.6.4.9). The
+    // sequence number exists, but there is nothing to deliver.
+    if sample_flags.contains(SampleFlag::Filtered) && data.serialized_payload.is_none() {
+      self.handle_filtered_change(writer_guid, writer_seq_num);
+      return;
+    }
 
     match self.data_to_dds_data(data, data_flags) {
-      Ok(dds_data) => self.process_received_data(
-        dds_data,
-        receive_timestamp,
-        write_options_b.build(),
-        writer_guid,
-        writer_seq_num,
-      ),
+      Ok(dds_data) => {
+        // If the change kind was deduced without StatusInfo, make the sample
+        // flags tell the same.
+        let change_kind = dds_data.change_kind();
+        let sample_flags =
+          if sample_flags.intersects(SampleFlag::Disposed | SampleFlag::Unregistered) {
+            sample_flags
+          } else {
+            sample_flags | Self::change_kind_sample_flags(change_kind)
+          };
+        self.process_received_data(
+          dds_data,
+          receive_timestamp,
+          write_options_b.sample_flags(sample_flags).build(),
+          writer_guid,
+          writer_seq_num,
+        );
+      }
       Err(e) => debug!("Parsing DATA to DDSData failed: {e}"),
     }
   }
@@ -569,7 +1188,11 @@ impl Reader {
   ) {
     let writer_guid = GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, datafrag.writer_id);
     let seq_num = datafrag.writer_sn;
-    let receive_timestamp = Timestamp::now();
+    let receive_timestamp = Timestamp::now_monotonic();
+    self.writer_is_active(writer_guid, receive_timestamp);
+    if self.delivery_paused {
+      return;
+    }
     //trace!("DATAFRAG received topic={:?}", self.topic_name);
 
     // check if this submessage is expired already
@@ -611,12 +1234,33 @@ impl Reader {
     {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
+    if let Some(key_hash) = datafrag
+      .inline_qos
+      .as_ref()
+      .and_then(Self::inline_qos_key_hash)
+    {
+      write_options_b = write_options_b.key_hash(key_hash);
+    }
+    if let Some(inline_qos) = &datafrag.inline_qos {
+      write_options_b = write_options_b.sample_flags(Self::inline_qos_sample_flags(
+        inline_qos,
+        representation_identifier,
+      ));
+    }
 
     // Feed to fragment assembler ...
     let writer_seq_num = datafrag.writer_sn; // for borrow checker
-    let completed_dds_data = self
+    let completed_dds_data = match self
       .fragment_assembler_mutable(writer_guid, datafrag.fragment_size)
-      .new_datafrag(datafrag, datafrag_flags);
+      .new_datafrag(datafrag, datafrag_flags)
+    {
+      DataFragResult::Completed(dds_data) => Some(dds_data),
+      DataFragResult::Incomplete => None,
+      DataFragResult::Rejected => {
+        self.report_sample_rejected();
+        None
+      }
+    };
 
     // ... and continue processing, if data was completed.
     if let Some(dds_data) = completed_dds_data {
@@ -634,15 +1278,75 @@ impl Reader {
     }
   }
 
+  fn change_kind_sample_flags(change_kind: ChangeKind) -> BitFlags<SampleFlag> {
+    let mut flags = BitFlags::empty();
+    if change_kind.is_disposed() {
+      flags |= SampleFlag::Disposed;
+    }
+    if change_kind.is_unregistered() {
+      flags |= SampleFlag::Unregistered;
+    }
+    flags
+  }
+
+  // Treat a filtered-out change like a single-sequence-number GAP.
+  fn handle_filtered_change(&mut self, writer_guid: GUID, seq_num: SequenceNumber) {
+    debug!(
+      "Filtered DATA {:?} from {:?}. topic={:?}",
+      seq_num, writer_guid, self.topic_name
+    );
+    if self.like_stateless {
+      return;
+    }
+    let all_ackable_before = if let Some(wp) = self.matched_writer_mut(writer_guid) {
+      wp.set_irrelevant_change(seq_num);
+      wp.all_ackable_before()
+    } else {
+      return;
+    };
+    self
+      .observed_gaps
+      .lock()
+      .unwrap()
+      .remove_range(writer_guid, seq_num..seq_num + SequenceNumber::new(1));
+    let marker_moved = self
+      .acquire_the_topic_cache_guard()
+      .mark_reliably_received_before(writer_guid, all_ackable_before);
+    if marker_moved {
+      self.notify_cache_change();
+    }
+  }
+
+  fn inline_qos_key_hash(inline_qos_parameters: &ParameterList) -> Option<KeyHash> {
+    InlineQos::key_hash(inline_qos_parameters).unwrap_or_else(|e| {
+      error!("Deserializing key_hash: {:?}", &e);
+      None
+    })
+  }
+
+  fn inline_qos_sample_flags(
+    inline_qos_parameters: &ParameterList,
+    representation_identifier: RepresentationIdentifier,
+  ) -> BitFlags<SampleFlag> {
+    InlineQos::status_info(inline_qos_parameters, representation_identifier).map_or_else(
+      |e| {
+        error!("Deserializing status_info: {:?}", &e);
+        BitFlags::empty()
+      },
+      |status_info| status_info.sample_flags(),
+    )
+  }
+
   fn fragment_assembler_mutable(
     &mut self,
     writer_guid: GUID,
     frag_size: u16,
   ) -> &mut FragmentAssembler {
+    let memory_budget = &self.memory_budget;
     self
       .fragment_assemblers
       .entry(writer_guid)
-      .or_insert_with(|| FragmentAssembler::new(frag_size))
+      .or_insert_with(|| FragmentAssembler::new(frag_size, memory_budget.clone()))
   }
 
   fn garbage_collect_fragments(&mut self) {
@@ -690,7 +1394,7 @@ impl Reader {
   // received)
   fn process_received_data(
     &mut self,
-    dds_data: DDSData,
+    mut dds_data: DDSData,
     receive_timestamp: Timestamp,
     write_options: WriteOptions,
     writer_guid: GUID,
@@ -704,8 +1408,21 @@ impl Reader {
       self.reliability,
       self.like_stateless,
     );
+    // MaxSampleAge
+    let max_sample_age = self.qos_policy.max_sample_age();
+    let source_timestamp = write_options.source_timestamp();
+    let is_stale = match (max_sample_age, source_timestamp) {
+      (Some(max_age), Some(source_timestamp)) => {
+        receive_timestamp.duration_since(source_timestamp) > max_age.duration
+      }
+      _ => false,
+    };
+
     if !self.like_stateless {
       let my_entity_id = self.my_guid.entity_id; // to please borrow checker
+      let best_effort = self.reliability == policy::Reliability::BestEffort;
+      let previous_highest;
+      let mut lost_samples = 0;
       if let Some(writer_proxy) = self.matched_writer_mut(writer_guid) {
         if writer_proxy.should_ignore_change(writer_sn) {
           // change already present
@@ -720,7 +1437,17 @@ impl Reader {
           }
         }
         // Add the change and get the instant
+        previous_highest = Some(writer_proxy.last_received_sequence_number());
+        if best_effort {
+          writer_proxy.record_lost_samples(writer_sn);
+          lost_samples = writer_proxy.take_lost_samples();
+        }
         writer_proxy.received_changes_add(writer_sn, receive_timestamp);
+        if let (Some(_), Some(source_timestamp), false) =
+          (max_sample_age, source_timestamp, best_effort)
+        {
+          writer_proxy.record_source_timestamp(writer_sn, source_timestamp);
+        }
       } else {
         // no writer proxy found
         debug!(
@@ -733,29 +1460,77 @@ impl Reader {
         if writer_guid.entity_id.entity_kind.is_user_defined() {
           return;
         }
+        previous_highest = None;
+      }
+      if let Some(previous_highest) = previous_highest {
+        self.observed_gaps.lock().unwrap().record_received(
+          writer_guid,
+          previous_highest,
+          writer_sn,
+        );
+      }
+      if lost_samples > 0 {
+        self.report_samples_lost(lost_samples);
       }
     } else {
       // stateless reader: nothing to do before making cache change
     }
 
-    self.make_cache_change(
-      dds_data,
-      receive_timestamp,
-      write_options,
-      writer_guid,
-      writer_sn,
-    );
-
-    // Add to own track-keeping data structure
-    #[cfg(test)]
-    self.seqnum_instant_map.insert(writer_sn, receive_timestamp);
+    // Only Writers that advertised compression in Discovery send compressed
+    // payloads. Other implementations may use the same representation option
+    // bit for something else.
+    if let DDSData::Data {
+      ref mut serialized_payload,
+    } = dds_data
+    {
+      if compression::is_compressed(serialized_payload)
+        && !self
+          .matched_writer(writer_guid)
+          .is_some_and(RtpsWriterProxy::compresses_payloads)
+      {
+        compression::clear_compressed_flag(serialized_payload);
+      }
+    }
 
-    self.notify_cache_change();
-  }
+    self.record_instance_update(&dds_data, &write_options, receive_timestamp, writer_guid);
 
-  fn data_to_dds_data(
-    &self,
-    data: Data,
+    if is_stale {
+      // Treated as received, but not stored, so that it is neither requested
+      // again nor delivered.
+      debug!(
+        "Dropping stale sample {:?} from {:?}, source timestamp {:?}. topic={:?}",
+        writer_sn, writer_guid, source_timestamp, self.topic_name
+      );
+      if let Some(ack_base) = self
+        .matched_writer(writer_guid)
+        .map(RtpsWriterProxy::all_ackable_before)
+      {
+        self
+          .acquire_the_topic_cache_guard()
+          .mark_reliably_received_before(writer_guid, ack_base);
+      }
+      self.report_samples_lost(1);
+    } else {
+      self.make_cache_change(
+        dds_data,
+        receive_timestamp,
+        write_options,
+        writer_guid,
+        writer_sn,
+      );
+
+      // Add to own track-keeping data structure
+      #[cfg(test)]
+      self.seqnum_instant_map.insert(writer_sn, receive_timestamp);
+    }
+
+    self.skip_stale_missing_samples();
+    self.notify_cache_change();
+  }
+
+  fn data_to_dds_data(
+    &self,
+    data: Data,
     data_flags: BitFlags<DATA_Flags>,
   ) -> Result<DDSData, String> {
     let representation_identifier = DATA_Flags::cdr_representation_identifier(data_flags);
@@ -856,10 +1631,14 @@ impl Reader {
     &mut self,
     heartbeat: &Heartbeat,
     final_flag_set: bool,
+    liveliness_flag_set: bool,
     mr_state: &MessageReceiverState,
   ) -> bool {
     let writer_guid =
       GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, heartbeat.writer_id);
+    if liveliness_flag_set {
+      self.writer_is_active(writer_guid, Timestamp::now());
+    }
 
     if self.reliability == policy::Reliability::BestEffort || self.like_stateless {
       debug!(
@@ -925,6 +1704,11 @@ impl Reader {
         // having received all the data samples or may indicate that some data
         // samples are missing. The response may be delayed to avoid message storms.
 
+        if this.acknacks_paused() {
+          // Let the Writer wait. A full ACKNACK is sent when resuming.
+          return false;
+        }
+
         if !missing_seqnums.is_empty() || !final_flag_set {
           let mut partially_received = Vec::new();
           // report of what we have.
@@ -1112,6 +1896,15 @@ impl Reader {
       all_ackable_before = writer_proxy.all_ackable_before();
     }
 
+    // Irrelevant sequence numbers are not missing, they just do not exist.
+    {
+      let mut observed_gaps = self.observed_gaps.lock().unwrap();
+      observed_gaps.remove_range(writer_guid, gap.gap_start..gap.gap_list.base());
+      for seq_num in gap.gap_list.iter() {
+        observed_gaps.remove_range(writer_guid, seq_num..seq_num + SequenceNumber::new(1));
+      }
+    }
+
     // Get the topic cache and mark progress
     let marker_moved = self
       .acquire_the_topic_cache_guard()
@@ -1153,7 +1946,7 @@ impl Reader {
     no_writers: bool,
     representation_identifier: RepresentationIdentifier,
   ) -> ChangeKind {
-    match inline_qos.as_ref().and_then(|inline_qos_parameters| {
+    let status_info_kind = inline_qos.as_ref().and_then(|inline_qos_parameters| {
       InlineQos::status_info(inline_qos_parameters, representation_identifier).map_or_else(
         |e| {
           error!("Deserializing status_info: {:?}", &e);
@@ -1161,15 +1954,18 @@ impl Reader {
         },
         Some,
       )
-    }) {
-      Some(si) => si.change_kind(), // get from inline QoS
-      // TODO: What if si.change_kind() gives ALIVE ??
-      None => {
+    });
+    match status_info_kind.map(|si| si.change_kind()) {
+      // get from inline QoS
+      Some(kind) if kind != ChangeKind::Alive => kind,
+      // No StatusInfo, or one that claims a key-only DATA is alive. Neither
+      // makes sense, so guess from what we know about the writer.
+      _ => {
         if no_writers {
           ChangeKind::NotAliveUnregistered
         } else {
           ChangeKind::NotAliveDisposed
-        } // TODO: Is this reasonable default?
+        }
       }
     }
   }
@@ -1188,8 +1984,10 @@ impl Reader {
     // Get the topic cache
     let mut tc = self.acquire_the_topic_cache_guard();
 
-    tc.add_change(&receive_timestamp, cache_change);
-    // Mark seqnums as received if not behaving statelessly
+    let accepted = tc.add_change(&receive_timestamp, cache_change);
+    let evicted_count = tc.evicted_count();
+    // Mark seqnums as received if not behaving statelessly.
+    // This is done also for rejected changes: they are not requested again.
     if !self.like_stateless {
       self.matched_writer(writer_guid).map(|wp| {
         tc.mark_reliably_received_before(writer_guid, wp.all_ackable_before());
@@ -1197,6 +1995,59 @@ impl Reader {
         // the upper call level from here does it.
       });
     }
+    drop(tc);
+
+    self.report_evicted_samples(evicted_count);
+    if !accepted {
+      self.report_sample_rejected();
+    }
+  }
+
+  // Samples evicted from the TopicCache under memory pressure are lost to the
+  // DataReaders. This is checked when data is received, and periodically by
+  // the event loop, because the eviction may be caused by other Topics.
+  pub fn check_evicted_samples(&mut self) {
+    let evicted_count = self.acquire_the_topic_cache_guard().evicted_count();
+    self.report_evicted_samples(evicted_count);
+  }
+
+  // ReaderDataLifecycle: The not alive instances are in the DataSampleCache of
+  // the DataReader, which is not reachable from here. Wake up the DataReader
+  // periodically, so that it purges the expired instances even if no new
+  // samples arrive.
+  pub fn check_autopurge(&mut self) {
+    let Some(lifecycle) = self.qos_policy.reader_data_lifecycle() else {
+      return;
+    };
+    if lifecycle.autopurge_disposed_samples_delay != Duration::INFINITE
+      || lifecycle.autopurge_nowriters_samples_delay != Duration::INFINITE
+    {
+      self.notify_cache_change();
+    }
+  }
+
+  fn report_evicted_samples(&mut self, evicted_count: u64) {
+    let lost = evicted_count.saturating_sub(self.evicted_samples_seen);
+    if lost > 0 {
+      self.evicted_samples_seen = evicted_count;
+      self.report_samples_lost(i64::try_from(lost).unwrap_or(i64::MAX));
+    }
+  }
+
+  fn report_sample_rejected(&mut self) {
+    self.sample_rejected_count += 1;
+    self.send_status_change(DataReaderStatus::SampleRejected {
+      count: CountWithChange::start_from(self.sample_rejected_count, 1),
+      last_reason: SampleRejectedStatusKind::ByMemoryLimit,
+    });
+  }
+
+  fn report_samples_lost(&mut self, lost: i64) {
+    let lost = i32::try_from(lost).unwrap_or(i32::MAX);
+    self.sample_lost_count = self.sample_lost_count.saturating_add(lost);
+    self.send_status_change(DataReaderStatus::SampleLost {
+      count: CountWithChange::start_from(self.sample_lost_count, lost),
+    });
   }
 
   // notifies DataReaders (or any listeners that history cache has changed for
@@ -1226,22 +2077,38 @@ impl Reader {
         // TODO: What does this mean? Can we ever get here?
       }
     }
+
+    self
+      .additional_front_ends
+      .lock()
+      .unwrap()
+      .retain(ReaderFrontEnd::notify);
   }
 
   #[cfg(not(feature = "security"))]
   fn encode_and_send(
     &self,
     message: Message,
-    _destination_guid: GUID,
+    destination_guid: GUID,
     dst_locator_list: &[Locator],
   ) {
     let bytes = message
       .write_to_vec_with_ctx(Endianness::LittleEndian)
       .unwrap(); //TODO!
     let _dummy = message; // consume it to avoid clippy warning
-    self
+    self.send_and_record(&bytes, destination_guid, dst_locator_list);
+  }
+
+  fn send_and_record(&self, bytes: &[u8], destination_guid: GUID, dst_locator_list: &[Locator]) {
+    if let Some(loc) = self
       .udp_sender
-      .send_to_locator_list(&bytes, dst_locator_list);
+      .send_to_locator_list(bytes, dst_locator_list)
+    {
+      self
+        .last_used_locators
+        .borrow_mut()
+        .insert(destination_guid, loc);
+    }
   }
 
   #[cfg(feature = "security")]
@@ -1256,9 +2123,7 @@ impl Reader {
         let bytes = message
           .write_to_vec_with_ctx(Endianness::LittleEndian)
           .unwrap(); //TODO!!
-        self
-          .udp_sender
-          .send_to_locator_list(&bytes, dst_locator_list);
+        self.send_and_record(&bytes, destination_guid, dst_locator_list);
       }
       Err(e) => error!("Failed to send message to writers. Encoding failed: {e:?}"),
     }
@@ -1367,19 +2232,86 @@ impl Reader {
       );
       return;
     }
-
-    let flags = BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Endianness);
-    // Do not set final flag --> we are requesting immediate heartbeat from writers.
+    if self.acknacks_paused() {
+      return;
+    }
 
     // Detach the writer proxy set. This is a way to avoid multiple &mut self
     let mut writer_proxies = std::mem::take(&mut self.matched_writers);
 
-    let reader_id = self.entity_id();
     for (_, writer_proxy) in writer_proxies
       .iter_mut()
       .filter(|(_, p)| p.no_changes_received())
     {
+      self.send_preemptive_acknack_to(writer_proxy);
+    }
+    // put writer proxies back
+    self.matched_writers = writer_proxies;
+  }
+
+  // Pre-emptive ACKNACK to a newly matched Writer, unless it has sent
+  // something already.
+  fn send_initial_acknack(&mut self, writer_guid: GUID) {
+    if self.reliability == policy::Reliability::BestEffort || self.acknacks_paused() {
+      return;
+    }
+    // Detach the writer proxy to avoid multiple &mut self
+    let Some(mut writer_proxy) = self.matched_writers.remove(&writer_guid) else {
+      return; // unmatched already
+    };
+    if writer_proxy.no_changes_received() {
+      debug!(
+        "Sending initial ACKNACK to {writer_guid:?} topic={:?}",
+        self.topic_name
+      );
+      self.send_preemptive_acknack_to(&mut writer_proxy);
+    }
+    self.matched_writers.insert(writer_guid, writer_proxy);
+  }
+
+  fn send_preemptive_acknack_to(&self, writer_proxy: &mut RtpsWriterProxy) {
+    let flags = BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Endianness);
+    // Do not set final flag --> we are requesting immediate heartbeat from writers.
+
+    let acknack_count = writer_proxy.next_ack_nack_sequence_number();
+    let RtpsWriterProxy {
+      remote_writer_guid,
+      unicast_locator_list,
+      ..
+    } = writer_proxy;
+    self.send_acknack_to(
+      flags,
+      AckNack {
+        reader_id: self.entity_id(),
+        writer_id: remote_writer_guid.entity_id,
+        reader_sn_state: SequenceNumberSet::new_empty(SequenceNumber::new(1)),
+        count: acknack_count,
+      },
+      InfoDestination {
+        guid_prefix: remote_writer_guid.prefix,
+      },
+      unicast_locator_list,
+      *remote_writer_guid,
+    );
+  }
+
+  // Acknowledge everything received so far to all matched Writers, so that
+  // they can continue sending after a flow control pause.
+  fn send_resume_acknacks(&mut self) {
+    if self.reliability == policy::Reliability::BestEffort || self.like_stateless {
+      return;
+    }
+
+    let flags = BitFlags::<ACKNACK_Flags>::from_flag(ACKNACK_Flags::Endianness);
+    // No final flag: the Writers should respond with a HEARTBEAT, so that
+    // missing samples get requested.
+
+    let mut writer_proxies = std::mem::take(&mut self.matched_writers);
+
+    let reader_id = self.entity_id();
+    for writer_proxy in writer_proxies.values_mut() {
       let acknack_count = writer_proxy.next_ack_nack_sequence_number();
+      let reader_sn_state = SequenceNumberSet::new_empty(writer_proxy.all_ackable_before());
       let RtpsWriterProxy {
         remote_writer_guid,
         unicast_locator_list,
@@ -1390,7 +2322,7 @@ impl Reader {
         AckNack {
           reader_id,
           writer_id: remote_writer_guid.entity_id,
-          reader_sn_state: SequenceNumberSet::new_empty(SequenceNumber::new(1)),
+          reader_sn_state,
           count: acknack_count,
         },
         InfoDestination {
@@ -1400,7 +2332,6 @@ impl Reader {
         *remote_writer_guid,
       );
     }
-    // put writer proxies back
     self.matched_writers = writer_proxies;
   }
 
@@ -1408,6 +2339,10 @@ impl Reader {
     &self.topic_name
   }
 
+  pub fn matched_writer_count(&self) -> usize {
+    self.matched_writers.len()
+  }
+
   fn acquire_the_topic_cache_guard(&self) -> MutexGuard<'_, TopicCache> {
     self.topic_cache.lock().unwrap_or_else(|e| {
       panic!(
@@ -1418,6 +2353,14 @@ impl Reader {
   }
 } // impl Reader
 
+impl Drop for Reader {
+  fn drop(&mut self) {
+    self
+      .acquire_the_topic_cache_guard()
+      .remove_reader(self.my_guid);
+  }
+}
+
 impl HasQoSPolicy for Reader {
   fn qos(&self) -> QosPolicies {
     self.qos_policy.clone()
@@ -1448,6 +2391,7 @@ mod tests {
 
   use crate::{
     dds::{qos::policy::Reliability, statusevents::sync_status_channel, typedesc::TypeDesc},
+    rtps::constant::PREEMPTIVE_ACKNACK_PERIOD,
     structure::{dds_cache::DDSCache, guid::EntityKind},
     QosPolicyBuilder,
   };
@@ -1467,42 +2411,16 @@ mod tests {
       &qos_policy,
     );
 
-    // Create notification mechanisms
-    // mio-0.6 channel:
-    let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(100);
-    // mio-0.8 event source and sender:
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    // async notification waker
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    // Create status channel
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
 
-    // Create reader command channel
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
     // Then finally create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (reader_ing, ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
+      Arc::new(UDPSender::new(0).unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
@@ -1536,7 +2454,7 @@ mod tests {
 
     // 5. Verify that the reader sends a notification about the new data
     assert!(
-      notification_receiver.try_recv().is_ok(),
+      ends.notification_receiver.try_recv().is_ok(),
       "Reader did not send a notification through the mio-0.6 channel"
     );
     // TODO: Should the other notification mechanisms (mio-0.8 & async) be also
@@ -1557,37 +2475,20 @@ mod tests {
       &qos_policy,
     );
 
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
     // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle: topic_cache_handle.clone(),
-      like_stateless: false,
+    let (reader_ing, _ends) = ReaderIngredients::for_test(
+      reader_guid,
+      topic_name,
+      topic_cache_handle.clone(),
       qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    );
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
+      Arc::new(UDPSender::new(0).unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
@@ -1646,122 +2547,87 @@ mod tests {
   }
 
   #[test]
-  fn reader_handles_heartbeats() {
-    // 1. Create a reader for a topic with Reliable QoS
-    // Create the DDS cache and the topic
+  fn best_effort_reader_records_observed_gaps() {
     let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
     let topic_name = "test_name";
-    let reliable_qos = QosPolicyBuilder::new()
-      .reliability(Reliability::Reliable {
-        max_blocking_time: Duration::from_millis(100),
-      })
-      .build();
+    let qos_policy = QosPolicies::qos_none(); // BestEffort
 
     let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
       topic_name.to_string(),
       TypeDesc::new("test_type".to_string()),
-      &reliable_qos,
+      &qos_policy,
     );
 
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
+    let observed_gaps = Arc::new(Mutex::new(ObservedGaps::new()));
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy: reliable_qos.clone(),
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (mut reader_ing, ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
+    reader_ing.observed_gaps = observed_gaps.clone();
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
+      Arc::new(UDPSender::new(0).unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
 
-    // 2. Add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
-
     let mr_state = MessageReceiverState {
       source_guid_prefix: writer_guid.prefix,
       ..Default::default()
     };
-
     reader.matched_writer_add(
       writer_guid,
       EntityId::UNKNOWN,
-      mr_state.unicast_reply_locator_list.to_vec(),
-      mr_state.multicast_reply_locator_list.to_vec(),
-      &reliable_qos,
+      vec![],
+      vec![],
+      &QosPolicies::qos_none(),
     );
 
-    // 3. Send an initial heartbeat from the new writer, reader should not respond
-    // with acknack first_sn: 1, last_sn: 0 to indicate no samples available
-    let hb_new = Heartbeat {
-      reader_id: reader.entity_id(),
-      writer_id: writer_guid.entity_id,
-      first_sn: SequenceNumber::new(1),
-      last_sn: SequenceNumber::new(0),
-      count: 1,
-    };
-    assert!(!reader.handle_heartbeat_msg(&hb_new, true, &mr_state)); // should be false, no ack
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    for sn in [1, 2, 5, 9, 7] {
+      let data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::new(sn),
+        ..Data::default()
+      };
+      reader.handle_data_msg(data, data_flags, &mr_state);
+    }
 
-    // 4. Send the first proper heartbeat, reader should respond with acknack
-    let hb_one = Heartbeat {
-      reader_id: reader.entity_id(),
-      writer_id: writer_guid.entity_id,
-      first_sn: SequenceNumber::new(1), // Only one in writers cache
-      last_sn: SequenceNumber::new(1),
-      count: 2,
-    };
-    assert!(reader.handle_heartbeat_msg(&hb_one, false, &mr_state)); // Should send an ack_nack
+    let sn = SequenceNumber::new;
+    assert_eq!(
+      observed_gaps.lock().unwrap().snapshot(),
+      vec![(writer_guid, vec![sn(3)..sn(5), sn(6)..sn(7), sn(8)..sn(9)])]
+    );
 
-    // 5. Send a duplicate of the first heartbeat, reader should not respond with
-    // acknack
-    let hb_one2 = hb_one.clone();
-    assert!(!reader.handle_heartbeat_msg(&hb_one2, false, &mr_state)); // No acknack
+    // 2 -> 5 loses two samples, 5 -> 9 three more. Late #7 is not subtracted.
+    let lost_counts: Vec<(i32, i32)> = std::iter::from_fn(|| ends.status_receiver.try_recv().ok())
+      .filter_map(|status| match status {
+        DataReaderStatus::SampleLost { count } => Some((count.count(), count.count_change())),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(lost_counts, vec![(2, 2), (5, 3)]);
 
-    // 6. Send a second proper heartbeat, reader should respond with acknack
-    let hb_2 = Heartbeat {
-      reader_id: reader.entity_id(),
+    // Writer says #3 and #4 do not exist
+    let gap = Gap {
+      reader_id: reader_guid.entity_id,
       writer_id: writer_guid.entity_id,
-      first_sn: SequenceNumber::new(1), // writer has last 2 in cache
-      last_sn: SequenceNumber::new(3),  // writer has written 3 samples
-      count: 3,
+      gap_start: sn(3),
+      gap_list: SequenceNumberSet::new_empty(sn(5)),
     };
-    assert!(reader.handle_heartbeat_msg(&hb_2, false, &mr_state)); // Should send an ack_nack
-
-    // 7. Count of acknack sent should be 2
-    // The count is verified from the writer proxy
-    let writer_proxy = reader
-      .matched_writer(writer_guid)
-      .expect("Did not find a matched writer");
-    assert_eq!(writer_proxy.sent_ack_nack_count, 2);
+    reader.handle_gap_msg(&gap, &mr_state);
+    assert_eq!(
+      observed_gaps.lock().unwrap().snapshot(),
+      vec![(writer_guid, vec![sn(6)..sn(7), sn(8)..sn(9)])]
+    );
   }
 
   #[test]
-  fn reader_handles_gaps() {
-    // 1. Create a reader
-    // Create the DDS cache and a topic
+  fn reader_shares_subscription_matched_status() {
     let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
     let topic_name = "test_name";
     let qos_policy = QosPolicies::qos_none();
@@ -1772,79 +2638,708 @@ mod tests {
       &qos_policy,
     );
 
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
+    let subscription_matched = Arc::new(Mutex::new(SubscriptionMatchedState::default()));
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (mut reader_ing, _ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
+    reader_ing.subscription_matched = subscription_matched.clone();
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
+      Arc::new(UDPSender::new(0).unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
 
-    // 2. Add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
-
-    let mr_state = MessageReceiverState {
-      source_guid_prefix: writer_guid.prefix,
-      ..Default::default()
-    };
-
     reader.matched_writer_add(
       writer_guid,
       EntityId::UNKNOWN,
-      mr_state.unicast_reply_locator_list.to_vec(),
-      mr_state.multicast_reply_locator_list.to_vec(),
+      vec![],
+      vec![],
       &QosPolicies::qos_none(),
     );
 
-    // 3. Feed the reader a gap message which marks sequence numbers 1-2 & 4 as
-    // irrelevant
-    let gap_start = SequenceNumber::new(1);
-    let gap_list_base = SequenceNumber::new(3);
-    let mut gap_list = SequenceNumberSet::new(gap_list_base, 7);
-    gap_list.test_insert(SequenceNumber::new(4));
+    // Reading the status resets the changes
+    let status = subscription_matched.lock().unwrap().take();
+    assert_eq!(status.total_count, CountWithChange::new(1, 1));
+    assert_eq!(status.current_count, CountWithChange::new(1, 1));
+    assert_eq!(status.last_publication_key, Some(writer_guid));
+    let status = subscription_matched.lock().unwrap().take();
+    assert_eq!(status.total_count, CountWithChange::new(1, 0));
+    assert_eq!(status.current_count, CountWithChange::new(1, 0));
+
+    reader.remove_writer_proxy(writer_guid);
+    let status = subscription_matched.lock().unwrap().take();
+    assert_eq!(status.total_count, CountWithChange::new(1, 0));
+    assert_eq!(status.current_count, CountWithChange::new(0, -1));
+    assert_eq!(status.last_publication_key, Some(writer_guid));
+  }
 
-    let gap = Gap {
-      reader_id: reader.entity_id(),
-      writer_id: writer_guid.entity_id,
-      gap_start,
-      gap_list,
-    };
-    reader.handle_gap_msg(&gap, &mr_state);
+  #[test]
+  fn reader_tracks_writer_liveliness() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicies::qos_none();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
 
-    // 4. Verify that the writer proxy reports seqnums below 3 as ackable
-    // This should be the case since seqnums 1-2 were marked as irrelevant
-    assert_eq!(
-      reader
-        .matched_writer(writer_guid)
-        .unwrap()
-        .all_ackable_before(),
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let liveliness_changed = Arc::new(Mutex::new(LivelinessChangedStatus::default()));
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader_ing, ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
+    reader_ing.liveliness_changed = liveliness_changed.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    let writer_qos = QosPolicyBuilder::new()
+      .liveliness(policy::Liveliness::ManualByTopic {
+        lease_duration: Duration::from_millis(10),
+      })
+      .build();
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &writer_qos);
+
+    // Matched Writer is alive
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(1, 1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(0, 0));
+    assert_eq!(status.last_publication_key, Some(writer_guid));
+
+    // Lease expires, and the check is scheduled for that
+    std::thread::sleep(StdDuration::from_millis(250));
+    reader.handle_timed_event();
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(0, -1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(1, 1));
+
+    // DATA from the Writer brings it back to life
+    let data = Data {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(1),
+      ..Data::default()
+    };
+    reader.handle_data_msg(
+      data,
+      BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data),
+      &mr_state,
+    );
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(1, 1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(0, -1));
+
+    reader.remove_writer_proxy(writer_guid);
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(0, -1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(0, 0));
+
+    // The same transitions are reported as status events
+    let events: Vec<(i32, i32)> = std::iter::from_fn(|| ends.status_receiver.try_recv().ok())
+      .filter_map(|status| match status {
+        DataReaderStatus::LivelinessChanged {
+          alive_total,
+          not_alive_total,
+        } => Some((alive_total.count(), not_alive_total.count())),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(events, vec![(1, 0), (0, 1), (1, 0), (0, 0)]);
+  }
+
+  #[test]
+  fn reader_liveliness_lease_expires_while_heartbeats_continue() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicyBuilder::new().reliable(Duration::ZERO).build();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let liveliness_changed = Arc::new(Mutex::new(LivelinessChangedStatus::default()));
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader_ing, _ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
+    reader_ing.liveliness_changed = liveliness_changed.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    let writer_qos = QosPolicyBuilder::new()
+      .reliable(Duration::ZERO)
+      .liveliness(policy::Liveliness::ManualByTopic {
+        lease_duration: Duration::from_millis(10),
+      })
+      .build();
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &writer_qos);
+    liveliness_changed.lock().unwrap().take();
+
+    // The Writer keeps sending HEARTBEATs and GAPs, but the application has
+    // stopped asserting liveliness.
+    let heartbeat = |count| Heartbeat {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(0),
+      count,
+    };
+    let gap = Gap {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      gap_start: SequenceNumber::new(1),
+      gap_list: SequenceNumberSet::new_empty(SequenceNumber::new(2)),
+    };
+    for count in 1..=5 {
+      std::thread::sleep(StdDuration::from_millis(50));
+      reader.handle_heartbeat_msg(&heartbeat(count), false, false, &mr_state);
+      reader.handle_gap_msg(&gap, &mr_state);
+    }
+    reader.handle_timed_event();
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(0, -1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(1, 1));
+
+    // A HEARTBEAT with the liveliness flag is a manual assertion
+    reader.handle_heartbeat_msg(&heartbeat(6), false, true, &mr_state);
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(1, 1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(0, -1));
+  }
+
+  #[test]
+  fn reader_checks_deadline_per_instance_and_per_writer() {
+    use crate::{
+      messages::submessages::elements::parameter::Parameter, structure::parameter_id::ParameterId,
+    };
+
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicyBuilder::new()
+      .deadline(policy::Deadline(Duration::from_millis(10)))
+      .build();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let (reader_ing, _ends) = ReaderIngredients::for_test(
+      reader_guid,
+      topic_name,
+      topic_cache_handle,
+      qos_policy.clone(),
+    );
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    // One Writer sends key hashes, the other does not.
+    let keyed_writer = GUID::new(
+      GuidPrefix::new(b"keyed writer"),
+      EntityId::create_custom_entity_id([1, 2, 3], EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+    );
+    let plain_writer = GUID::new(
+      GuidPrefix::new(b"plain writer"),
+      EntityId::create_custom_entity_id([1, 2, 3], EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+    );
+    let key_hash = KeyHash::zero();
+    for writer in [keyed_writer, plain_writer] {
+      reader.matched_writer_add(writer, EntityId::UNKNOWN, vec![], vec![], &qos_policy);
+      let data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer.entity_id,
+        writer_sn: SequenceNumber::new(1),
+        inline_qos: (writer == keyed_writer).then(|| ParameterList {
+          parameters: vec![Parameter::new(ParameterId::PID_KEY_HASH, key_hash.to_vec())],
+        }),
+        serialized_payload: Some(SerializedPayload::default().into()),
+      };
+      let mr_state = MessageReceiverState {
+        source_guid_prefix: writer.prefix,
+        ..Default::default()
+      };
+      reader.handle_data_msg(
+        data,
+        DATA_Flags::Endianness | DATA_Flags::Data | DATA_Flags::InlineQos,
+        &mr_state,
+      );
+    }
+
+    // Both the instance and the Writer without instance information miss the
+    // deadline.
+    std::thread::sleep(StdDuration::from_millis(20));
+    let missed: Vec<Option<InstanceHandle>> = reader
+      .calculate_if_requested_deadline_is_missed()
+      .into_iter()
+      .filter_map(|status| match status {
+        DataReaderStatus::RequestedDeadlineMissed { last_instance, .. } => Some(last_instance),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(missed, vec![Some(InstanceHandle(key_hash)), None]);
+
+    // Lost Writers are no longer expected to update anything.
+    reader.remove_writer_proxy(keyed_writer);
+    reader.participant_lost(plain_writer.prefix);
+    assert!(reader.instance_last_received.is_empty());
+    assert!(reader.instance_writers.is_empty());
+    assert!(reader
+      .calculate_if_requested_deadline_is_missed()
+      .is_empty());
+  }
+
+  #[test]
+  fn reader_reports_incompatible_qos_once() {
+    use crate::dds::qos::{policy::Durability, QosPolicyId};
+
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicyBuilder::new()
+      .reliable(Duration::ZERO)
+      .durability(Durability::TransientLocal)
+      .build();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let (reader_ing, ends) = ReaderIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      topic_name,
+      topic_cache_handle,
+      qos_policy,
+    );
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let best_effort = QosPolicyBuilder::new()
+      .best_effort()
+      .durability(Durability::TransientLocal)
+      .build();
+    let volatile = QosPolicyBuilder::new()
+      .reliable(Duration::ZERO)
+      .durability(Durability::Volatile)
+      .build();
+    // Re-announcements of the same incompatible Writer are reported once, and a
+    // different incompatibility is reported again
+    for qos in [
+      &best_effort,
+      &best_effort,
+      &best_effort,
+      &volatile,
+      &volatile,
+      &best_effort,
+    ] {
+      reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], qos);
+    }
+
+    // Losing the Writer forgets what was reported
+    reader.remove_writer_proxy(writer_guid);
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &best_effort);
+    // So does losing its participant
+    reader.participant_lost(writer_guid.prefix);
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &best_effort);
+
+    let events: Vec<(i32, i32, QosPolicyId)> =
+      std::iter::from_fn(|| ends.status_receiver.try_recv().ok())
+        .filter_map(|status| match status {
+          DataReaderStatus::RequestedIncompatibleQos {
+            count,
+            last_policy_id,
+            writer,
+            ..
+          } => {
+            assert_eq!(writer, writer_guid);
+            Some((count.count(), count.count_change(), last_policy_id))
+          }
+          _ => None,
+        })
+        .collect();
+    assert_eq!(
+      events,
+      vec![
+        (1, 1, QosPolicyId::Reliability),
+        (2, 1, QosPolicyId::Durability),
+        (3, 1, QosPolicyId::Reliability),
+        (4, 1, QosPolicyId::Reliability),
+        (5, 1, QosPolicyId::Reliability),
+      ]
+    );
+  }
+
+  #[test]
+  fn reader_prunes_not_alive_writers() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicies::qos_none();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let liveliness_changed = Arc::new(Mutex::new(LivelinessChangedStatus::default()));
+    let subscription_matched = Arc::new(Mutex::new(SubscriptionMatchedState::default()));
+
+    let (mut reader_ing, _ends) = ReaderIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      topic_name,
+      topic_cache_handle,
+      qos_policy,
+    );
+    reader_ing.subscription_matched = subscription_matched.clone();
+    reader_ing.liveliness_changed = liveliness_changed.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    reader.not_alive_writer_prune_delay = Duration::from_millis(20);
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let writer_qos = QosPolicyBuilder::new()
+      .liveliness(policy::Liveliness::ManualByTopic {
+        lease_duration: Duration::from_millis(10),
+      })
+      .build();
+    reader.matched_writer_add(writer_guid, EntityId::UNKNOWN, vec![], vec![], &writer_qos);
+    liveliness_changed.lock().unwrap().take();
+
+    // Lease expires, but the Writer is still matched
+    std::thread::sleep(StdDuration::from_millis(15));
+    reader.handle_liveliness_check();
+    assert!(reader.matched_writers.contains_key(&writer_guid));
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(0, -1));
+    assert_eq!(status.not_alive_count, CountWithChange::new(1, 1));
+
+    // Not alive for too long, so the Writer is unmatched
+    std::thread::sleep(StdDuration::from_millis(30));
+    reader.handle_liveliness_check();
+    assert!(reader.matched_writers.is_empty());
+    let status = liveliness_changed.lock().unwrap().take();
+    assert_eq!(status.alive_count, CountWithChange::new(0, 0));
+    assert_eq!(status.not_alive_count, CountWithChange::new(0, -1));
+    let status = subscription_matched.lock().unwrap().take();
+    assert_eq!(status.current_count.count(), 0);
+  }
+
+  #[test]
+  fn reader_handles_heartbeats() {
+    // 1. Create a reader for a topic with Reliable QoS
+    // Create the DDS cache and the topic
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .build();
+
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &reliable_qos,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    // Then create the reader
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, _ends) = ReaderIngredients::for_test(
+      reader_guid,
+      topic_name,
+      topic_cache_handle,
+      reliable_qos.clone(),
+    );
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    // 2. Add info of a matched writer to the reader
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.to_vec(),
+      mr_state.multicast_reply_locator_list.to_vec(),
+      &reliable_qos,
+    );
+
+    // 3. Send an initial heartbeat from the new writer, reader should not respond
+    // with acknack first_sn: 1, last_sn: 0 to indicate no samples available
+    let hb_new = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(0),
+      count: 1,
+    };
+    assert!(!reader.handle_heartbeat_msg(&hb_new, true, false, &mr_state)); // should be false, no ack
+
+    // 4. Send the first proper heartbeat, reader should respond with acknack
+    let hb_one = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1), // Only one in writers cache
+      last_sn: SequenceNumber::new(1),
+      count: 2,
+    };
+    assert!(reader.handle_heartbeat_msg(&hb_one, false, false, &mr_state)); // Should send an ack_nack
+
+    // 5. Send a duplicate of the first heartbeat, reader should not respond with
+    // acknack
+    let hb_one2 = hb_one.clone();
+    assert!(!reader.handle_heartbeat_msg(&hb_one2, false, false, &mr_state)); // No acknack
+
+    // 6. Send a second proper heartbeat, reader should respond with acknack
+    let hb_2 = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1), // writer has last 2 in cache
+      last_sn: SequenceNumber::new(3),  // writer has written 3 samples
+      count: 3,
+    };
+    assert!(reader.handle_heartbeat_msg(&hb_2, false, false, &mr_state)); // Should send an ack_nack
+
+    // 7. Count of acknack sent should be 2
+    // The count is verified from the writer proxy
+    let writer_proxy = reader
+      .matched_writer(writer_guid)
+      .expect("Did not find a matched writer");
+    assert_eq!(writer_proxy.sent_ack_nack_count, 2);
+
+    // 8. Pause flow control, reader should not respond to heartbeats
+    reader.set_flow_control_pause(true);
+    let hb_3 = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(5),
+      count: 4,
+    };
+    assert!(!reader.handle_heartbeat_msg(&hb_3, false, false, &mr_state)); // No acknack
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .sent_ack_nack_count,
+      2
+    );
+
+    // 9. Resuming sends an acknack immediately, and heartbeats are answered again
+    reader.set_flow_control_pause(false);
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .sent_ack_nack_count,
+      3
+    );
+    let hb_4 = Heartbeat { count: 5, ..hb_3 };
+    assert!(reader.handle_heartbeat_msg(&hb_4, false, false, &mr_state)); // Should send an ack_nack
+  }
+
+  #[test]
+  fn reader_sends_initial_acknack_on_match() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "initial_acknack";
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .build();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &reliable_qos,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let initial_acknack_delay = StdDuration::from_millis(50);
+    let (mut reader_ing, _ends) = ReaderIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      topic_name,
+      topic_cache_handle,
+      reliable_qos.clone(),
+    );
+    reader_ing.initial_acknack_delay = Some(initial_acknack_delay);
+    let timer_tick = StdDuration::from_millis(10);
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default()
+        .tick_duration(timer_tick)
+        .build(),
+      participant_status_sender,
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let matched_at = std::time::Instant::now();
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &reliable_qos,
+    );
+    let sent_ack_nack_count = |reader: &Reader| {
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .sent_ack_nack_count
+    };
+    assert_eq!(sent_ack_nack_count(&reader), 0);
+
+    // The ACKNACK is sent after the delay, long before the periodic pre-emptive
+    // ACKNACKs or the first HEARTBEAT would have triggered one.
+    while sent_ack_nack_count(&reader) == 0 {
+      assert!(matched_at.elapsed() < PREEMPTIVE_ACKNACK_PERIOD / 4);
+      std::thread::sleep(StdDuration::from_millis(10));
+      reader.handle_timed_event();
+    }
+    // The timer fires on tick boundaries, so it may be up to a tick early.
+    let time_to_acknack = matched_at.elapsed();
+    assert!(time_to_acknack + timer_tick >= initial_acknack_delay);
+    assert_eq!(sent_ack_nack_count(&reader), 1);
+
+    // Matching again is an update, and does not send another one
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &reliable_qos,
+    );
+    std::thread::sleep(initial_acknack_delay * 3);
+    reader.handle_timed_event();
+    assert_eq!(sent_ack_nack_count(&reader), 1);
+  }
+
+  #[test]
+  fn reader_handles_gaps() {
+    // 1. Create a reader
+    // Create the DDS cache and a topic
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicies::qos_none();
+
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    // Then create the reader
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, _ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    // 2. Add info of a matched writer to the reader
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.to_vec(),
+      mr_state.multicast_reply_locator_list.to_vec(),
+      &QosPolicies::qos_none(),
+    );
+
+    // 3. Feed the reader a gap message which marks sequence numbers 1-2 & 4 as
+    // irrelevant
+    let gap_start = SequenceNumber::new(1);
+    let gap_list_base = SequenceNumber::new(3);
+    let mut gap_list = SequenceNumberSet::new(gap_list_base, 7);
+    gap_list.test_insert(SequenceNumber::new(4));
+
+    let gap = Gap {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      gap_start,
+      gap_list,
+    };
+    reader.handle_gap_msg(&gap, &mr_state);
+
+    // 4. Verify that the writer proxy reports seqnums below 3 as ackable
+    // This should be the case since seqnums 1-2 were marked as irrelevant
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
       SequenceNumber::new(3)
     );
 
@@ -1894,6 +3389,333 @@ mod tests {
     );
   }
 
+  #[test]
+  fn reliable_reader_drops_stale_samples() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let max_age = Duration::from_millis(250);
+    let qos_policy = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .history(policy::History::KeepAll)
+      .max_sample_age(policy::MaxSampleAge { duration: max_age })
+      .build();
+
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, ends) = ReaderIngredients::for_test(
+      reader_guid,
+      topic_name,
+      topic_cache_handle.clone(),
+      qos_policy,
+    );
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default()
+        .tick_duration(StdDuration::from_millis(10))
+        .build(),
+      participant_status_sender,
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &QosPolicies::qos_none(),
+    );
+
+    let receive = |reader: &mut Reader, sn: i64, source_timestamp: Timestamp| {
+      let mr_state = MessageReceiverState {
+        source_guid_prefix: writer_guid.prefix,
+        source_timestamp: Some(source_timestamp),
+        ..Default::default()
+      };
+      let data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::new(sn),
+        ..Data::default()
+      };
+      reader.handle_data_msg(data, BitFlags::from_flag(DATA_Flags::Data), &mr_state);
+    };
+    // Sequence numbers a Reliable DataReader could deliver now
+    let deliverable = || -> Vec<i64> {
+      topic_cache_handle
+        .lock()
+        .unwrap()
+        .get_changes_in_range(true, Timestamp::ZERO, &BTreeMap::new())
+        .map(|(_, cc)| i64::from(cc.sequence_number))
+        .collect()
+    };
+    let samples_lost = || -> Option<i32> {
+      std::iter::from_fn(|| ends.status_receiver.try_recv().ok())
+        .filter_map(|status| match status {
+          DataReaderStatus::SampleLost { count } => Some(count.count()),
+          _ => None,
+        })
+        .last()
+    };
+
+    // The network has been out for a second, and #1 to #10, written every
+    // 50 ms since then, were lost. Fresh #11 arrives after the outage, but it
+    // is blocked behind the missing samples.
+    let outage_start = Timestamp::now() - Duration::from_secs(1);
+    let written_at = |sn: i64| outage_start + Duration::from_millis(50 * (sn - 1));
+    receive(&mut reader, 11, Timestamp::now());
+    assert!(deliverable().is_empty());
+
+    // The Writer repairs the backlog, but it is too old to be delivered. The
+    // repairs are not replayed, and #11 is delivered right away.
+    for sn in 1..=10 {
+      receive(&mut reader, sn, written_at(sn));
+    }
+    assert_eq!(deliverable(), vec![11]);
+    assert_eq!(samples_lost(), Some(10));
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
+      SequenceNumber::new(12)
+    );
+
+    // Another outage. This time the Writer does not repair #12 and #13, but #14
+    // is delivered when they would be too old anyway.
+    receive(&mut reader, 14, Timestamp::now());
+    assert_eq!(deliverable(), vec![11]);
+    std::thread::sleep((max_age + Duration::from_millis(100)).to_std());
+    reader.handle_timed_event();
+    assert_eq!(deliverable(), vec![11, 14]);
+    assert_eq!(samples_lost(), Some(12));
+  }
+
+  #[test]
+  fn reader_delivery_pause() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "delivery_pause";
+    let qos_policy = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .history(policy::History::KeepAll)
+      .build();
+
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (reader_ing, _ends) = ReaderIngredients::for_test(
+      reader_guid,
+      topic_name,
+      topic_cache_handle.clone(),
+      qos_policy,
+    );
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default()
+        .tick_duration(StdDuration::from_millis(10))
+        .build(),
+      participant_status_sender,
+    );
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      vec![],
+      vec![],
+      &QosPolicies::qos_none(),
+    );
+
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    let receive = |reader: &mut Reader, sn: i64| {
+      let data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::new(sn),
+        ..Data::default()
+      };
+      reader.handle_data_msg(data, BitFlags::from_flag(DATA_Flags::Data), &mr_state);
+    };
+    let deliverable = || -> Vec<i64> {
+      topic_cache_handle
+        .lock()
+        .unwrap()
+        .get_changes_in_range(true, Timestamp::ZERO, &BTreeMap::new())
+        .map(|(_, cc)| i64::from(cc.sequence_number))
+        .collect()
+    };
+    let acknacks_sent = |reader: &Reader| {
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .sent_ack_nack_count
+    };
+
+    receive(&mut reader, 1);
+    assert_eq!(deliverable(), vec![1]);
+
+    // While paused, samples are dropped and heartbeats are not answered
+    reader.set_delivery_pause(true);
+    receive(&mut reader, 2);
+    receive(&mut reader, 3);
+    assert_eq!(deliverable(), vec![1]);
+    let heartbeat = Heartbeat {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(3),
+      count: 1,
+    };
+    assert!(!reader.handle_heartbeat_msg(&heartbeat, false, false, &mr_state));
+    let acknacks = acknacks_sent(&reader);
+
+    // Resuming acknowledges only #1, so the Writer resends the rest
+    reader.set_delivery_pause(false);
+    assert_eq!(acknacks_sent(&reader), acknacks + 1);
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
+      SequenceNumber::new(2)
+    );
+    receive(&mut reader, 2);
+    receive(&mut reader, 3);
+    assert_eq!(deliverable(), vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn reader_notifies_additional_front_ends() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let qos_policy = QosPolicies::qos_none();
+    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+      topic_name.to_string(),
+      TypeDesc::new("test_type".to_string()),
+      &qos_policy,
+    );
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    // Another DataReader sharing the Reader
+    let (front_notification_sender, front_notification_receiver) =
+      mio_channel::sync_channel::<()>(100);
+    let (_front_event_source, front_event_sender) = mio_source::make_poll_channel().unwrap();
+    let (front_status_sender, front_status_receiver) =
+      sync_status_channel::<DataReaderStatus>(4).unwrap();
+    let additional_front_ends = Arc::new(Mutex::new(vec![ReaderFrontEnd {
+      notification_sender: front_notification_sender,
+      status_sender: front_status_sender,
+      data_reader_waker: Arc::new(Mutex::new(None)),
+      poll_event_sender: front_event_sender,
+    }]));
+
+    let (mut reader_ing, _ends) = ReaderIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      topic_name,
+      topic_cache_handle,
+      qos_policy,
+    );
+    reader_ing.additional_front_ends = additional_front_ends.clone();
+    let mut reader = Reader::new(
+      reader_ing,
+      Arc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    reader.notify_cache_change();
+    assert!(front_notification_receiver.try_recv().is_ok());
+    reader.report_samples_lost(1);
+    assert!(matches!(
+      front_status_receiver.try_recv(),
+      Ok(DataReaderStatus::SampleLost { .. })
+    ));
+
+    // A front end whose DataReader is gone is forgotten
+    drop(front_notification_receiver);
+    reader.notify_cache_change();
+    assert!(additional_front_ends.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn reader_wakes_data_reader_for_autopurge() {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let topic_name = "test_name";
+    let reader_with_qos = |qos_policy: QosPolicies| {
+      let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
+        topic_name.to_string(),
+        TypeDesc::new("test_type".to_string()),
+        &qos_policy,
+      );
+      let (participant_status_sender, _participant_status_receiver) =
+        sync_status_channel(16).unwrap();
+      let (reader_ing, ends) = ReaderIngredients::for_test(
+        GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED),
+        topic_name,
+        topic_cache_handle,
+        qos_policy,
+      );
+      let reader = Reader::new(
+        reader_ing,
+        Arc::new(UDPSender::new(0).unwrap()),
+        mio_extras::timer::Builder::default().build(),
+        participant_status_sender,
+      );
+      (reader, ends)
+    };
+
+    // Finite delay: the DataReader is woken up on every check.
+    let (mut reader, ends) = reader_with_qos(
+      QosPolicyBuilder::new()
+        .reader_data_lifecycle(policy::ReaderDataLifecycle {
+          autopurge_disposed_samples_delay: Duration::from_secs(5),
+          ..policy::ReaderDataLifecycle::default()
+        })
+        .build(),
+    );
+    reader.check_autopurge();
+    assert!(ends.notification_receiver.try_recv().is_ok());
+    reader.check_autopurge();
+    assert!(ends.notification_receiver.try_recv().is_ok());
+
+    // Infinite delays or no policy: nothing to purge, no wake-ups.
+    for qos_policy in [
+      QosPolicyBuilder::new()
+        .reader_data_lifecycle(policy::ReaderDataLifecycle::default())
+        .build(),
+      QosPolicies::qos_none(),
+    ] {
+      let (mut reader, ends) = reader_with_qos(qos_policy);
+      reader.check_autopurge();
+      assert!(ends.notification_receiver.try_recv().is_err());
+    }
+  }
+
   #[test]
   fn stateless_reader_does_not_contain_writer_proxies() {
     // 1. Create a stateless-like reader
@@ -1910,37 +3732,17 @@ mod tests {
       &qos_policy,
     );
 
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
     let (participant_status_sender, _participant_status_receiver) =
       sync_status_channel(16).unwrap();
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
     let like_stateless = true;
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
+    let (mut reader_ing, _ends) =
+      ReaderIngredients::for_test(reader_guid, topic_name, topic_cache_handle, qos_policy);
+    reader_ing.like_stateless = like_stateless;
     let mut reader = Reader::new(
       reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
+      Arc::new(UDPSender::new(0).unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
@@ -1,6 +1,8 @@
 use std::{
   cmp::max,
   collections::{BTreeMap, BTreeSet},
+  fmt,
+  time::{Duration, Instant},
 };
 
 use bit_vec::BitVec;
@@ -61,6 +63,13 @@ pub(crate) struct RtpsReaderProxy {
   pub repair_mode: bool,
   qos: QosPolicies,
   frags_requested: BTreeMap<SequenceNumber, BitVec>,
+  // Payload compression algorithms the remote Reader can decompress
+  payload_decompressors: Vec<String>,
+  // Stalled reader detection: When the Reader last acked new changes, or had
+  // nothing left to ack.
+  ack_progress_at: Instant,
+  // The Writer has reported this Reader stalled, and not yet recovered.
+  pub stalled: bool,
 }
 
 impl RtpsReaderProxy {
@@ -78,6 +87,9 @@ impl RtpsReaderProxy {
       repair_mode: false,
       qos,
       frags_requested: BTreeMap::new(),
+      payload_decompressors: Vec::new(),
+      ack_progress_at: Instant::now(),
+      stalled: false,
     }
   }
 
@@ -129,6 +141,10 @@ impl RtpsReaderProxy {
       );
       self.qos = updated_qos;
     }
+
+    self
+      .payload_decompressors
+      .clone_from(&update.payload_decompressors);
   }
 
   pub fn qos(&self) -> &QosPolicies {
@@ -139,6 +155,10 @@ impl RtpsReaderProxy {
     self.expects_in_line_qos
   }
 
+  pub fn can_decompress(&self, algorithm: &str) -> bool {
+    self.payload_decompressors.iter().any(|a| a == algorithm)
+  }
+
   pub fn unsent_changes_iter(
     &self,
   ) -> impl std::iter::DoubleEndedIterator<Item = SequenceNumber> + '_ {
@@ -150,6 +170,31 @@ impl RtpsReaderProxy {
     self.unsent_changes_iter().collect()
   }
 
+  // One line summary for DataWriter::print_diagnostic
+  pub fn write_diagnostic(&self, out: &mut impl fmt::Write) -> fmt::Result {
+    let sn_list = |sns: &BTreeSet<SequenceNumber>| -> Vec<i64> {
+      sns.iter().map(|sn| i64::from(*sn)).collect()
+    };
+    writeln!(
+      out,
+      "{:?}: {}, acked before {}, unsent {:?}, pending gap {:?}, repair mode {}, fragments \
+       requested {}, active {}, stalled {}",
+      self.remote_reader_guid,
+      if self.qos.is_reliable() {
+        "reliable"
+      } else {
+        "best effort"
+      },
+      i64::from(self.all_acked_before),
+      sn_list(&self.unsent_changes),
+      sn_list(&self.pending_gap),
+      self.repair_mode,
+      self.frags_requested.len(),
+      self.is_active,
+      self.stalled,
+    )
+  }
+
   pub fn first_unsent_change(&self) -> Option<SequenceNumber> {
     self.unsent_changes_iter().next()
   }
@@ -179,7 +224,8 @@ impl RtpsReaderProxy {
       remote_group_entity_id: EntityId::UNKNOWN, // TODO
       unicast_locator_list,
       multicast_locator_list,
-      expects_in_line_qos: false,
+      // The Reader checks the Deadline per instance using the key hash
+      expects_in_line_qos: reader.qos_policy.deadline.is_some(),
       is_active: true,
       all_acked_before: SequenceNumber::zero(),
       unsent_changes: BTreeSet::new(),
@@ -187,6 +233,9 @@ impl RtpsReaderProxy {
       repair_mode: false,
       qos: reader.qos_policy.clone(),
       frags_requested: BTreeMap::new(),
+      payload_decompressors: Vec::new(),
+      ack_progress_at: Instant::now(),
+      stalled: false,
     }
   }
 
@@ -238,6 +287,13 @@ impl RtpsReaderProxy {
       repair_mode: false,
       qos: discovered_reader_data.subscription_topic_data.qos(),
       frags_requested: BTreeMap::new(),
+      payload_decompressors: discovered_reader_data
+        .payload_compression
+        .as_ref()
+        .map(|pc| pc.algorithms.clone())
+        .unwrap_or_default(),
+      ack_progress_at: Instant::now(),
+      stalled: false,
     }
   }
 
@@ -270,6 +326,9 @@ impl RtpsReaderProxy {
           );
         }
         self.remove_from_unsent_set_all_before(new_all_acked_before); // update anyway
+        if new_all_acked_before > max(self.all_acked_before, SequenceNumber::from(1)) {
+          self.ack_progress_at = Instant::now();
+        }
         self.all_acked_before = new_all_acked_before;
 
         // Insert the requested changes. These are (by construction) greater
@@ -333,6 +392,22 @@ impl RtpsReaderProxy {
     self.all_acked_before
   }
 
+  // Number of changes up to last_available that the Reader has not acked.
+  pub fn unacked_count(&self, last_available: SequenceNumber) -> u64 {
+    // SequenceNumbers start from 1, so acked before 0 means the same as before 1.
+    let first_unacked = max(self.all_acked_before, SequenceNumber::from(1));
+    max(i64::from(last_available.plus_1() - first_unacked), 0) as u64
+  }
+
+  // How long the Reader has had unacked changes without acking any new ones.
+  // Having nothing to ack counts as progress.
+  pub fn ack_stalled_for(&mut self, last_available: SequenceNumber, now: Instant) -> Duration {
+    if self.unacked_count(last_available) == 0 {
+      self.ack_progress_at = now;
+    }
+    now.saturating_duration_since(self.ack_progress_at)
+  }
+
   // Fragment handling
 
   pub fn mark_all_frags_requested(&mut self, seq_num: SequenceNumber, frag_count: u32) {
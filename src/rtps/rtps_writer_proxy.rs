@@ -1,5 +1,9 @@
 use core::ops::Bound::{Included, Unbounded};
-use std::{cmp::max, collections::BTreeMap};
+use std::{
+  cmp::max,
+  collections::{BTreeMap, VecDeque},
+  fmt,
+};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
@@ -7,6 +11,7 @@ use log::{debug, error, info, trace, warn};
 use crate::{
   discovery::sedp_messages::DiscoveredWriterData,
   structure::{
+    duration::Duration,
     guid::{EntityId, GUID},
     locator::Locator,
     sequence_number::SequenceNumber,
@@ -74,6 +79,28 @@ pub(crate) struct RtpsWriterProxy {
   // These are used for quick tracking of
   last_received_sequence_number: SequenceNumber,
   last_received_timestamp: Timestamp,
+
+  // Samples skipped over in sequence numbering, but not yet reported as
+  // SampleLost. Used only by BestEffort Readers.
+  unreported_lost_samples: i64,
+
+  // Source timestamps of samples received while some earlier samples were
+  // still missing, in increasing SequenceNumber order. The missing samples
+  // cannot be newer than these. Used only with the MaxSampleAge policy.
+  missing_age_bounds: VecDeque<(SequenceNumber, Timestamp)>,
+
+  // Lease duration of the Writer's ManualByTopic liveliness QoS. Only these
+  // Writers can be seen to lose liveliness while matched, as other kinds of
+  // liveliness are asserted by the remote participant, and losing the
+  // participant unmatches its Writers altogether.
+  liveliness_lease: Option<Duration>,
+  // When anything was last received from the Writer
+  last_activity: Timestamp,
+  alive: bool,
+
+  // The Writer advertised payload compression in Discovery. The compression
+  // flag in payloads from other Writers does not mean compression.
+  compresses_payloads: bool,
 }
 
 impl RtpsWriterProxy {
@@ -96,6 +123,12 @@ impl RtpsWriterProxy {
       ack_base: SequenceNumber::new(1),
       last_received_sequence_number: SequenceNumber::new(0),
       last_received_timestamp: Timestamp::INVALID,
+      unreported_lost_samples: 0,
+      missing_age_bounds: VecDeque::new(),
+      liveliness_lease: None,
+      last_activity: Timestamp::now(),
+      alive: true,
+      compresses_payloads: false,
     }
   }
 
@@ -111,10 +144,84 @@ impl RtpsWriterProxy {
     self.ack_base
   }
 
+  // One line summary for DataReader::print_diagnostic
+  pub fn write_diagnostic(&self, out: &mut impl fmt::Write) -> fmt::Result {
+    let missing: Vec<i64> = self
+      .missing_seqnums(self.ack_base, self.last_received_sequence_number)
+      .into_iter()
+      .map(i64::from)
+      .collect();
+    writeln!(
+      out,
+      "{:?}: received up to {}, ackable before {}, missing {:?}, last heartbeat count {}, \
+       acknacks sent {}, alive {}",
+      self.remote_writer_guid,
+      i64::from(self.last_received_sequence_number),
+      i64::from(self.ack_base),
+      missing,
+      self.received_heartbeat_count,
+      self.sent_ack_nack_count,
+      self.alive,
+    )
+  }
+
   pub fn update_contents(&mut self, other: Self) {
     self.unicast_locator_list = other.unicast_locator_list;
     self.multicast_locator_list = other.multicast_locator_list;
     self.remote_group_entity_id = other.remote_group_entity_id;
+    self.liveliness_lease = other.liveliness_lease;
+    self.compresses_payloads = other.compresses_payloads;
+  }
+
+  pub fn compresses_payloads(&self) -> bool {
+    self.compresses_payloads
+  }
+
+  pub fn set_liveliness_lease(&mut self, liveliness_lease: Option<Duration>) {
+    self.liveliness_lease = liveliness_lease;
+  }
+
+  pub fn is_alive(&self) -> bool {
+    self.alive
+  }
+
+  // Something was received from the Writer. Returns true, if the Writer
+  // regained liveliness.
+  pub fn assert_liveliness(&mut self, now: Timestamp) -> bool {
+    self.last_activity = now;
+    let regained = !self.alive;
+    self.alive = true;
+    regained
+  }
+
+  // When the lease of an alive Writer runs out, unless something is received
+  // from it before that.
+  pub fn lease_expires_at(&self) -> Option<Timestamp> {
+    self
+      .liveliness_lease
+      .filter(|_| self.alive)
+      .map(|lease| self.last_activity + lease)
+  }
+
+  // When a not alive Writer is presumed gone for good, unless something is
+  // received from it before that.
+  pub fn prune_at(&self, prune_delay: Duration) -> Option<Timestamp> {
+    self
+      .liveliness_lease
+      .filter(|_| !self.alive)
+      .map(|lease| self.last_activity + lease + prune_delay)
+  }
+
+  // Returns true, if the Writer lost liveliness, i.e. its lease has expired
+  // since the last check.
+  pub fn check_liveliness(&mut self, now: Timestamp) -> bool {
+    match self.lease_expires_at() {
+      Some(expires_at) if now >= expires_at => {
+        self.alive = false;
+        true
+      }
+      _ => false,
+    }
   }
 
   // This is used to check for DEADLINE policy
@@ -126,9 +233,15 @@ impl RtpsWriterProxy {
     }
   }
 
-  // Check if we no samples in the received state.
+  // Highest sequence number received as DATA so far, or zero if none.
+  pub fn last_received_sequence_number(&self) -> SequenceNumber {
+    self.last_received_sequence_number
+  }
+
+  // Check if we have no samples in the received state. Sequence numbers start
+  // at 1, so ack_base is 1 until something is received.
   pub fn no_changes_received(&self) -> bool {
-    self.ack_base == SequenceNumber::new(0) && self.changes.is_empty()
+    self.ack_base == SequenceNumber::new(1) && self.changes.is_empty()
   }
 
   // Given an availability range from a HEARTBEAT, find out what we are missing.
@@ -200,6 +313,72 @@ impl RtpsWriterProxy {
     seqnum < self.ack_base || self.changes.contains_key(&seqnum)
   }
 
+  // Count samples skipped over, if seq_num is beyond the next expected one.
+  // This must be called before received_changes_add(seq_num).
+  //
+  // Nothing is counted before the first received sample, as we may have just
+  // joined late. Samples arriving out of order are counted as lost, even if
+  // they arrive later.
+  pub fn record_lost_samples(&mut self, seq_num: SequenceNumber) {
+    let last = self.last_received_sequence_number;
+    if last > SequenceNumber::new(0) && seq_num > last + SequenceNumber::new(1) {
+      self.unreported_lost_samples += i64::from(seq_num - last) - 1;
+    }
+  }
+
+  // Returns lost samples counted since last call.
+  pub fn take_lost_samples(&mut self) -> i64 {
+    std::mem::take(&mut self.unreported_lost_samples)
+  }
+
+  // Remember the source timestamp of a received sample, if samples before it
+  // are still missing. This must be called after received_changes_add(seq_num).
+  pub fn record_source_timestamp(&mut self, seq_num: SequenceNumber, source_timestamp: Timestamp) {
+    let is_newest = self
+      .missing_age_bounds
+      .back()
+      .map_or(true, |(sn, _)| *sn < seq_num);
+    if seq_num > self.ack_base && is_newest {
+      self
+        .missing_age_bounds
+        .push_back((seq_num, source_timestamp));
+    }
+  }
+
+  // When the oldest missing sample, whose age is known to be bounded, is older
+  // than max_age at the latest.
+  pub fn missing_stale_at(&self, max_age: Duration) -> Option<Timestamp> {
+    self
+      .missing_age_bounds
+      .iter()
+      .find(|(sn, _)| *sn > self.ack_base)
+      .map(|(_, source_timestamp)| *source_timestamp + max_age)
+  }
+
+  // Give up the missing samples that are known to have been written before
+  // stale_before, as if the Writer had sent a GAP. Returns the number of
+  // samples given up.
+  pub fn skip_stale_missing(&mut self, stale_before: Timestamp) -> i64 {
+    let mut skip_before = None;
+    while let Some(&(sn, source_timestamp)) = self.missing_age_bounds.front() {
+      if sn > self.ack_base && source_timestamp > stale_before {
+        break;
+      }
+      self.missing_age_bounds.pop_front();
+      if sn > self.ack_base {
+        skip_before = Some(sn);
+      }
+    }
+    let Some(skip_before) = skip_before else {
+      return 0;
+    };
+    let skipped = self
+      .missing_seqnums(self.ack_base, skip_before - SequenceNumber::new(1))
+      .len();
+    self.irrelevant_changes_up_to(skip_before);
+    skipped as i64
+  }
+
   // This is used to mark DATA as received.
   pub fn received_changes_add(&mut self, seq_num: SequenceNumber, receive_timestamp: Timestamp) {
     self.changes.insert(seq_num, Some(receive_timestamp));
@@ -325,6 +504,12 @@ impl RtpsWriterProxy {
       ack_base: SequenceNumber::default(),
       last_received_sequence_number: SequenceNumber::new(0),
       last_received_timestamp: Timestamp::INVALID,
+      unreported_lost_samples: 0,
+      missing_age_bounds: VecDeque::new(),
+      liveliness_lease: None,
+      last_activity: Timestamp::now(),
+      alive: true,
+      compresses_payloads: discovered_writer_data.payload_compression.is_some(),
     }
   } // fn
 
@@ -1,9 +1,13 @@
 use std::{
-  cmp::max,
+  cell::RefCell,
+  cmp::{max, min},
   collections::{BTreeMap, BTreeSet},
   ops::Bound::Included,
-  rc::Rc,
-  sync::{atomic, Arc, Mutex},
+  sync::{
+    atomic::{self, AtomicBool, AtomicUsize},
+    Arc, Mutex,
+  },
+  time::Instant,
 };
 use core::task::Waker;
 
@@ -19,35 +23,44 @@ use mio_06::Token;
 use crate::{
   dds::{
     ddsdata::DDSData,
+    key::{InstanceHandle, KeyHash},
     qos::{
       policy,
       policy::{History, Reliability},
-      HasQoSPolicy, QosPolicies,
+      HasQoSPolicy, QosPolicies, QosPolicyId,
     },
     statusevents::{
-      CountWithChange, DataWriterStatus, DomainParticipantStatusEvent, StatusChannelSender,
+      CountWithChange, DataWriterStatus, DomainParticipantStatusEvent, EndpointDescription,
+      HistoryCacheStatus, OfferedIncompatibleQosStatus, PublicationMatchedState, RemoteLocators,
+      StatusChannelSender,
     },
-    with_key::datawriter::WriteOptions,
+    with_key::datawriter::{AckQuorum, AckStatus, WriteOptions},
   },
   messages::submessages::submessages::AckSubmessage,
   network::udp_sender::UDPSender,
   rtps::{
-    constant::{NACK_RESPONSE_DELAY, NACK_SUPPRESSION_DURATION},
+    constant::{
+      DATA_FRAG_MESSAGE_OVERHEAD, DEFAULT_DATA_MAX_SIZE_SERIALIZED, MIN_FRAGMENT_SIZE,
+      NACK_RESPONSE_DELAY, NACK_SUPPRESSION_DURATION,
+    },
     rtps_reader_proxy::RtpsReaderProxy,
     Message, MessageBuilder,
   },
+  serialization::compression::{self, CompressionFallback, PayloadCompression, PayloadCompressor},
   structure::{
     cache_change::CacheChange,
     duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
     locator::Locator,
+    memory_budget::{cache_change_size, MemoryBudget, MemoryKind},
     sequence_number::{FragmentNumber, FragmentNumberRange, SequenceNumber},
     time::Timestamp,
   },
 };
 #[cfg(feature = "security")]
 use crate::{
+  messages::submessages::submessage_kind::SubmessageKind,
   rtps::Submessage,
   security::{security_plugins::SecurityPluginsHandle, SecurityResult},
 };
@@ -66,6 +79,8 @@ pub(crate) enum TimedEvent {
   CacheCleaning,
   SendRepairData { to_reader: GUID },
   SendRepairFrags { to_reader: GUID },
+  DeadlineMissedCheck,
+  AckReceiptTimeout,
 }
 
 // This is used to construct an actual Writer.
@@ -78,8 +93,29 @@ pub(crate) struct WriterIngredients {
   pub(crate) like_stateless: bool, // Usually false (see like_stateless attribute of Writer)
   pub qos_policies: QosPolicies,
   pub status_sender: StatusChannelSender<DataWriterStatus>,
+  pub(crate) memory_budget: Arc<MemoryBudget>,
+  pub(crate) publication_matched: Arc<Mutex<PublicationMatchedState>>,
+  pub(crate) offered_incompatible_qos: Arc<Mutex<OfferedIncompatibleQosStatus>>,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
+
+  pub(crate) payload_compression: Option<PayloadCompression>,
+  // Shared with the DataWriter
+  pub(crate) compress_payloads: Arc<AtomicBool>,
+  // Shared with the DataWriter
+  pub(crate) send_key_hash: Arc<AtomicBool>,
+  // Shared with the DataWriter
+  pub(crate) send_queue: Arc<SendQueue>,
+  // Shared with the DataWriter
+  pub(crate) history_space: Arc<HistorySpace>,
+
+  // Reliability timing, if other than the defaults. The event loop sets these
+  // for built-in Writers from DiscoveryConfig.
+  pub(crate) heartbeat_period: Option<Duration>,
+  pub(crate) nack_response_delay: Option<std::time::Duration>,
+
+  // See DomainParticipantBuilder::max_message_size
+  pub(crate) max_message_size: Option<usize>,
 }
 
 impl WriterIngredients {
@@ -90,6 +126,55 @@ impl WriterIngredients {
   }
 }
 
+// The other ends of the channels in WriterIngredients::for_test
+#[cfg(test)]
+pub(crate) struct WriterTestEnds {
+  pub command_sender: mio_channel::SyncSender<WriterCommand>,
+  pub status_receiver: crate::dds::statusevents::StatusChannelReceiver<DataWriterStatus>,
+}
+
+#[cfg(test)]
+impl WriterIngredients {
+  // Ingredients with new channels and defaults for everything else. Tests set
+  // the fields that they need on the result.
+  pub(crate) fn for_test(
+    guid: GUID,
+    topic_name: &str,
+    qos_policies: QosPolicies,
+  ) -> (Self, WriterTestEnds) {
+    let (command_sender, writer_command_receiver) = mio_channel::sync_channel(4);
+    let (status_sender, status_receiver) =
+      crate::dds::statusevents::sync_status_channel(32).unwrap();
+
+    let ingredients = Self {
+      guid,
+      writer_command_receiver,
+      writer_command_receiver_waker: Arc::new(Mutex::new(None)),
+      topic_name: topic_name.to_string(),
+      like_stateless: false,
+      qos_policies,
+      status_sender,
+      memory_budget: Arc::default(),
+      publication_matched: Arc::default(),
+      offered_incompatible_qos: Arc::default(),
+      security_plugins: None,
+      payload_compression: None,
+      compress_payloads: Arc::new(AtomicBool::new(false)),
+      send_key_hash: Arc::new(AtomicBool::new(false)),
+      send_queue: Arc::default(),
+      history_space: Arc::default(),
+      heartbeat_period: None,
+      nack_response_delay: None,
+      max_message_size: None,
+    };
+    let ends = WriterTestEnds {
+      command_sender,
+      status_receiver,
+    };
+    (ingredients, ends)
+  }
+}
+
 struct AckWaiter {
   wait_until: SequenceNumber,
   complete_channel: StatusChannelSender<()>,
@@ -118,6 +203,37 @@ impl AckWaiter {
   }
 }
 
+// Set by DataWriter::ack_listener
+pub(crate) struct AckListener {
+  pub quorum: AckQuorum,
+  pub timeout: std::time::Duration,
+  pub callback: Box<dyn Fn(SequenceNumber, AckStatus) + Send>,
+}
+
+// Acknowledgements of a single sample, for the AckListener
+struct PendingReceipt {
+  deadline: Instant,
+  required: usize,
+  acked: usize,
+  readers_pending: BTreeSet<GUID>,
+}
+
+impl PendingReceipt {
+  // Returns the outcome, if known already
+  fn reader_acked_or_lost(&mut self, guid: GUID, acked: bool) -> Option<AckStatus> {
+    if self.readers_pending.remove(&guid) && acked {
+      self.acked += 1;
+    }
+    if self.acked >= self.required {
+      Some(AckStatus::Achieved)
+    } else if self.acked + self.readers_pending.len() < self.required {
+      Some(AckStatus::ReaderSetChanged)
+    } else {
+      None
+    }
+  }
+}
+
 // helper struct for Writer
 struct HistoryBuffer {
   first_seq: SequenceNumber, // oldest not removed. Default is 1.
@@ -134,16 +250,21 @@ struct HistoryBuffer {
 
   // topic name is just for debugging
   topic_name: String,
+
+  // Changes are admitted to the budget by the DataWriter before they are
+  // written, so here they are charged unconditionally.
+  memory_budget: Arc<MemoryBudget>,
 }
 
 impl HistoryBuffer {
-  fn new(topic_name: String) -> Self {
+  fn new(topic_name: String, memory_budget: Arc<MemoryBudget>) -> Self {
     HistoryBuffer {
       first_seq: SequenceNumber::new(1),
       last_seq: SequenceNumber::new(0), // Indicates that we have nothing yet
       sequence_number_to_instant: BTreeMap::new(),
       history_buffer: BTreeMap::new(),
       topic_name,
+      memory_budget,
     }
   }
 
@@ -162,6 +283,11 @@ impl HistoryBuffer {
     self.first_seq
   }
 
+  // Number of changes currently kept
+  fn depth(&self) -> usize {
+    self.history_buffer.len()
+  }
+
   fn get_change(&self, ts: Timestamp) -> Option<&CacheChange> {
     self.history_buffer.get(&ts)
   }
@@ -177,12 +303,19 @@ impl HistoryBuffer {
     let new_seq = new_cache_change.sequence_number;
 
     // actual insert
+    self.memory_budget.force_reserve(
+      MemoryKind::WriterHistory,
+      cache_change_size(&new_cache_change),
+    );
     let had_already_same = self.history_buffer.insert(timestamp, new_cache_change);
-    if had_already_same.is_some() {
+    if let Some(old_cc) = had_already_same {
       // This should really not happen.
       error!(
         "HistoryBuffer: Tried to insert CacheChange with duplicate key. Discarding old sample."
       );
+      self
+        .memory_budget
+        .release(MemoryKind::WriterHistory, cache_change_size(&old_cc));
     }
     // also update SeqNo map
     self.sequence_number_to_instant.insert(new_seq, timestamp);
@@ -194,37 +327,76 @@ impl HistoryBuffer {
     }
   }
 
-  fn remove_changes_before(&mut self, remove_before_seq: SequenceNumber) {
-    if let Some(remove_before) = self.sequence_number_to_instant.get(&remove_before_seq) {
-      let count_before = self.history_buffer.len();
-      self.history_buffer = self.history_buffer.split_off(remove_before);
-      self.sequence_number_to_instant = self
-        .sequence_number_to_instant
-        .split_off(&remove_before_seq);
-      // debug printout
-      let count_after = self.history_buffer.len();
-      debug!(
-        "HistoryBuffer: remove_changes_before. count before={} after={}, topic={}",
-        count_before, count_after, self.topic_name
-      );
-      if remove_before_seq >= self.first_seq {
-        self.first_seq = remove_before_seq; // update
-      } else {
-        error!(
-          "HistoryBuffer: Trying to remove before the first SequenceNumber. But how did we find \
-           it in the sequence_number_to_instant map? Looks like a bug."
-        );
+  // Replace compressed samples with uncompressed ones
+  fn decompress_changes(&mut self, decompressor: &Arc<dyn PayloadCompressor>) {
+    for cc in self.history_buffer.values_mut() {
+      let DDSData::Data { serialized_payload } = &cc.data_value else {
+        continue;
+      };
+      if !compression::is_compressed(serialized_payload) {
+        continue;
       }
-    } else {
-      // Number 1 is a special case. If the buffer is empty, then it is not found.
-      if remove_before_seq != SequenceNumber::new(1) {
-        warn!(
-          "HistoryBuffer: remove_changes_before. Cannot find {:?} first={:?} last={:?} topic={}",
-          remove_before_seq, self.first_seq, self.last_seq, self.topic_name
-        );
+      match compression::decompress(std::slice::from_ref(decompressor), serialized_payload) {
+        Ok(payload) => {
+          let compressed_size = cache_change_size(cc);
+          cc.data_value = DDSData::new(payload);
+          self
+            .memory_budget
+            .force_reserve(MemoryKind::WriterHistory, cache_change_size(cc));
+          self
+            .memory_budget
+            .release(MemoryKind::WriterHistory, compressed_size);
+        }
+        Err(e) => error!(
+          "HistoryBuffer: Cannot decompress {:?}: {e}, topic={}",
+          cc.sequence_number, self.topic_name
+        ),
       }
     }
   }
+
+  // Number of changes kept from SequenceNumber `from` onwards
+  fn count_from(&self, from: SequenceNumber) -> usize {
+    self.sequence_number_to_instant.range(from..).count()
+  }
+
+  // Removes the changes with a SequenceNumber below `below`. The caller decides
+  // which changes are no longer needed, e.g. because all Readers have
+  // acknowledged them. SequenceNumbers need not be contiguous, so `below`
+  // does not have to be in the buffer.
+  fn prune_acknowledged(&mut self, below: SequenceNumber) {
+    // Never advance past the next SequenceNumber to be written
+    let below = min(below, self.last_seq.plus_1());
+    if below <= self.first_seq {
+      return;
+    }
+    let count_before = self.history_buffer.len();
+    let retained = self.sequence_number_to_instant.split_off(&below);
+    let removed = std::mem::replace(&mut self.sequence_number_to_instant, retained);
+    let released = removed
+      .values()
+      .filter_map(|ts| self.history_buffer.remove(ts))
+      .map(|cc| cache_change_size(&cc))
+      .sum();
+    self
+      .memory_budget
+      .release(MemoryKind::WriterHistory, released);
+    self.first_seq = below;
+    debug!(
+      "HistoryBuffer: prune_acknowledged below {:?}. count before={} after={}, topic={}",
+      below,
+      count_before,
+      self.history_buffer.len(),
+      self.topic_name
+    );
+  }
+}
+
+impl Drop for HistoryBuffer {
+  fn drop(&mut self) {
+    let size = self.history_buffer.values().map(cache_change_size).sum();
+    self.memory_budget.release(MemoryKind::WriterHistory, size);
+  }
 }
 
 pub(crate) struct Writer {
@@ -275,6 +447,10 @@ pub(crate) struct Writer {
   // "The fragment size must be fixed for a given Writer and is identical for all remote Readers"
   pub data_max_size_serialized: usize,
 
+  // Upper limit for the size of sent messages, including RTPS message
+  // protection. See DomainParticipantBuilder::max_message_size
+  max_message_size: Option<usize>,
+
   my_guid: GUID,
   pub(crate) writer_command_receiver: mio_channel::Receiver<WriterCommand>,
   writer_command_receiver_waker: Arc<Mutex<Option<Waker>>>,
@@ -282,11 +458,18 @@ pub(crate) struct Writer {
   /// The RTPS ReaderProxy class represents the information an RTPS
   /// StatefulWriter maintains on each matched RTPS Reader
   readers: BTreeMap<GUID, RtpsReaderProxy>,
+  // Where a message to each matched Reader was last successfully sent to.
+  // This is updated while sending, which only has a shared reference to self.
+  last_used_locators: RefCell<BTreeMap<GUID, Locator>>,
   matched_readers_count_total: i32, // all matches ever, never decremented
   requested_incompatible_qos_count: i32, // how many times some Reader requested incompatible QoS
+  // Readers whose QoS is incompatible, and the policy last reported for each.
+  // Used to report each incompatibility only once, not on every
+  // re-announcement.
+  incompatible_readers: BTreeMap<GUID, QosPolicyId>,
 
   // Sending mechanism
-  udp_sender: Rc<UDPSender>,
+  udp_sender: Arc<UDPSender>,
 
   // By default, this writer is a StatefulWriter (see RTPS spec section 8.4.9)
   // If like_stateless is true, then the writer mimics the behavior of a Best-Effort
@@ -315,11 +498,173 @@ pub(crate) struct Writer {
 
   // Used for sending status info about messages sent
   status_sender: StatusChannelSender<DataWriterStatus>,
-  // offered_deadline_status: OfferedDeadlineMissedStatus,
+  // Matched Reader counts, shared with the DataWriter
+  publication_matched: Arc<Mutex<PublicationMatchedState>>,
+  // QoS incompatibility counts, shared with the DataWriter
+  offered_incompatible_qos: Arc<Mutex<OfferedIncompatibleQosStatus>>,
+  offered_deadline_missed_count: i32,
+  // Latest write of each alive instance, for the Deadline check. The key is
+  // None on NO_KEY topics.
+  instance_last_written: BTreeMap<Option<KeyHash>, Timestamp>,
   ack_waiter: Option<AckWaiter>,
+  ack_listener: Option<AckListener>,
+  // Samples written since the AckListener was set, and not yet reported to it
+  pending_receipts: BTreeMap<SequenceNumber, PendingReceipt>,
+  ack_receipt_timer_set: bool,
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 
   security_plugins: Option<SecurityPluginsHandle>,
+
+  payload_compression: Option<PayloadCompression>,
+  // Tells the DataWriter whether all matched Readers can decompress
+  compress_payloads: Arc<AtomicBool>,
+  // Tells the DataWriter whether some matched Reader gets the key hash in
+  // inline QoS
+  send_key_hash: Arc<AtomicBool>,
+  // Tells the DataWriter when all the samples it has queued have been processed
+  send_queue: Arc<SendQueue>,
+  // Tells the DataWriter when there is room in the history for new samples
+  history_space: Arc<HistorySpace>,
+  // Samples from the DataWriter that still hold their room in history_space
+  history_slots: usize,
+}
+
+// Counts the samples that the DataWriter has queued to the Writer, but the
+// Writer has not yet processed, i.e. stored to its history and, in push mode,
+// sent to the socket. DataWriter::drain waits for this to reach zero, or for
+// the queue to be closed, i.e. the Writer to be gone.
+#[derive(Default)]
+pub(crate) struct SendQueue {
+  length: AtomicUsize,
+  closed: AtomicBool,
+  drain_wakers: Mutex<Vec<Waker>>,
+}
+
+impl SendQueue {
+  // Called by the DataWriter before queuing a sample, and again with
+  // `dequeued` if queuing fails.
+  pub fn enqueued(&self) {
+    self.length.fetch_add(1, atomic::Ordering::AcqRel);
+  }
+
+  pub fn dequeued(&self) {
+    // Saturate, so that samples sent to the Writer by other means do not
+    // underflow the count.
+    let previous =
+      self
+        .length
+        .fetch_update(atomic::Ordering::AcqRel, atomic::Ordering::Acquire, |n| {
+          n.checked_sub(1)
+        });
+    if previous == Ok(1) {
+      self.wake_drainers();
+    }
+  }
+
+  // Nothing queued will be processed anymore, so there is no point in waiting
+  // for the queue to empty.
+  pub fn close(&self) {
+    self.closed.store(true, atomic::Ordering::Release);
+    self.wake_drainers();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.length.load(atomic::Ordering::Acquire) == 0
+  }
+
+  // Returns true if the queue is empty or closed. Otherwise, `waker` is woken
+  // when it becomes so.
+  pub fn wake_when_empty(&self, waker: &Waker) -> bool {
+    // Check under the lock, so that `dequeued` or `close` cannot happen between
+    // the check and storing the waker.
+    let mut wakers = self.drain_wakers.lock().unwrap();
+    if self.is_empty() || self.closed.load(atomic::Ordering::Acquire) {
+      return true;
+    }
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+      wakers.push(waker.clone());
+    }
+    false
+  }
+
+  fn wake_drainers(&self) {
+    for waker in self.drain_wakers.lock().unwrap().drain(..) {
+      waker.wake();
+    }
+  }
+}
+
+// Room for samples in a KeepAll history with a max_samples limit, shared
+// between the DataWriter and the Writer. The DataWriter takes room for each
+// sample before queuing it, and the Writer gives it back when the sample no
+// longer needs to be kept, i.e. all Reliable Readers have acknowledged it.
+// Without a limit, room is always available and nothing is counted.
+#[derive(Default)]
+pub(crate) struct HistorySpace {
+  limit: Option<usize>,
+  state: Mutex<HistorySpaceState>,
+}
+
+#[derive(Default)]
+struct HistorySpaceState {
+  taken: usize,
+  wakers: Vec<Waker>,
+}
+
+impl HistorySpace {
+  pub fn for_qos(qos: &QosPolicies) -> Self {
+    // KeepLast replaces old samples instead of blocking, and BestEffort
+    // Readers do not acknowledge, so only Reliable KeepAll needs a limit.
+    let limit = match (qos.history, qos.resource_limits) {
+      (Some(History::KeepAll), Some(policy::ResourceLimits { max_samples, .. }))
+        if qos.is_reliable() && max_samples > 0 =>
+      {
+        Some(max_samples as usize)
+      }
+      _ => None,
+    };
+    Self {
+      limit,
+      state: Mutex::default(),
+    }
+  }
+
+  pub fn is_limited(&self) -> bool {
+    self.limit.is_some()
+  }
+
+  // Takes room for one sample. If the history is full, returns false, and
+  // `waker` is woken when room is given back.
+  pub fn try_take(&self, waker: &Waker) -> bool {
+    let Some(limit) = self.limit else {
+      return true;
+    };
+    let mut state = self.state.lock().unwrap();
+    if state.taken < limit {
+      state.taken += 1;
+      return true;
+    }
+    if !state.wakers.iter().any(|w| w.will_wake(waker)) {
+      state.wakers.push(waker.clone());
+    }
+    false
+  }
+
+  pub fn give_back(&self, count: usize) {
+    if self.limit.is_none() || count == 0 {
+      return;
+    }
+    let wakers = {
+      let mut state = self.state.lock().unwrap();
+      state.taken = state.taken.saturating_sub(count);
+      std::mem::take(&mut state.wakers)
+    };
+    // Several writes may be waiting. The ones that do not get room register
+    // again.
+    for waker in wakers {
+      waker.wake();
+    }
+  }
 }
 
 pub enum WriterCommand {
@@ -332,13 +677,25 @@ pub enum WriterCommand {
   WaitForAcknowledgments {
     all_acked: StatusChannelSender<()>,
   },
+  GetRemoteLocators {
+    reply: std::sync::mpsc::SyncSender<Vec<RemoteLocators>>,
+  },
+  GetDiagnostic {
+    reply: std::sync::mpsc::SyncSender<String>,
+  },
+  GetHistoryCacheStatus {
+    reply: std::sync::mpsc::SyncSender<HistoryCacheStatus>,
+  },
+  SetAckListener {
+    listener: AckListener,
+  },
   // ResetOfferedDeadlineMissedStatus { writer_guid: GUID },
 }
 
 impl Writer {
   pub fn new(
     i: WriterIngredients,
-    udp_sender: Rc<UDPSender>,
+    udp_sender: Arc<UDPSender>,
     mut timed_event_timer: Timer<TimedEvent>,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
   ) -> Self {
@@ -353,7 +710,7 @@ impl Writer {
       .reliability
       .and_then(|reliability| {
         if matches!(reliability, Reliability::Reliable { .. }) {
-          Some(Duration::from_secs(1))
+          Some(i.heartbeat_period.unwrap_or(Duration::from_secs(1)))
         } else {
           None
         }
@@ -382,6 +739,33 @@ impl Writer {
       std::time::Duration::from(cache_cleaning_period),
       TimedEvent::CacheCleaning,
     );
+    if let Some(policy::Deadline(period)) = i.qos_policies.deadline {
+      timed_event_timer.set_timeout(period.to_std(), TimedEvent::DeadlineMissedCheck);
+    }
+    i.send_key_hash.store(
+      i.qos_policies.inline_qos() == Some(policy::InlineQos::Always),
+      atomic::Ordering::Release,
+    );
+
+    let data_max_size_serialized = match i.max_message_size {
+      None => DEFAULT_DATA_MAX_SIZE_SERIALIZED,
+      Some(max_message_size) => {
+        // The fragment size is fixed, so we do not know the number of
+        // receiving participants. Assume one, and send fragments to each
+        // participant separately, see fragments_per_participant.
+        #[cfg(feature = "security")]
+        let protection_overhead = i.security_plugins.as_ref().map_or(0, |handle| {
+          handle
+            .get_plugins()
+            .rtps_protection_overhead(&i.guid.prefix, 1)
+        });
+        #[cfg(not(feature = "security"))]
+        let protection_overhead = 0;
+        max_message_size
+          .saturating_sub(DATA_FRAG_MESSAGE_OVERHEAD + protection_overhead)
+          .clamp(MIN_FRAGMENT_SIZE, DEFAULT_DATA_MAX_SIZE_SERIALIZED)
+      }
+    };
 
     Self {
       endianness: Endianness::LittleEndian,
@@ -389,30 +773,46 @@ impl Writer {
       push_mode: true,
       heartbeat_period,
       cache_cleaning_period,
-      nack_response_delay: NACK_RESPONSE_DELAY, // default value from dp_event_loop
-      nackfrag_response_delay: NACK_RESPONSE_DELAY, // default value from dp_event_loop
+      nack_response_delay: i.nack_response_delay.unwrap_or(NACK_RESPONSE_DELAY),
+      nackfrag_response_delay: i.nack_response_delay.unwrap_or(NACK_RESPONSE_DELAY),
       repairfrags_continue_delay: std::time::Duration::from_millis(1),
       nack_suppression_duration: NACK_SUPPRESSION_DURATION,
-      data_max_size_serialized: 1024,
+      data_max_size_serialized,
       // ^^ TODO: Maybe a smarter selection would be in order.
       // We should get the minimum over all outgoing interfaces.
+      max_message_size: i.max_message_size,
       my_guid: i.guid,
       writer_command_receiver: i.writer_command_receiver,
       writer_command_receiver_waker: i.writer_command_receiver_waker,
       readers: BTreeMap::new(),
       matched_readers_count_total: 0,
       requested_incompatible_qos_count: 0,
+      incompatible_readers: BTreeMap::new(),
       udp_sender,
       my_topic_name: i.topic_name.clone(),
-      history_buffer: HistoryBuffer::new(i.topic_name),
+      history_buffer: HistoryBuffer::new(i.topic_name, i.memory_budget),
       timed_event_timer,
       like_stateless: i.like_stateless,
       qos_policies: i.qos_policies,
       status_sender: i.status_sender,
+      publication_matched: i.publication_matched,
+      offered_incompatible_qos: i.offered_incompatible_qos,
+      offered_deadline_missed_count: 0,
+      instance_last_written: BTreeMap::new(),
       participant_status_sender,
       ack_waiter: None,
+      ack_listener: None,
+      pending_receipts: BTreeMap::new(),
+      ack_receipt_timer_set: false,
 
       security_plugins: i.security_plugins,
+      last_used_locators: RefCell::new(BTreeMap::new()),
+      payload_compression: i.payload_compression,
+      compress_payloads: i.compress_payloads,
+      send_key_hash: i.send_key_hash,
+      send_queue: i.send_queue,
+      history_space: i.history_space,
+      history_slots: 0,
     }
   }
 
@@ -456,6 +856,7 @@ impl Writer {
           self.handle_heartbeat_tick(false);
           // ^^ false = This is automatic heartbeat by timer, not manual by application
           // call.
+          self.check_stalled_readers();
           if let Some(period) = self.heartbeat_period {
             self
               .timed_event_timer
@@ -469,6 +870,18 @@ impl Writer {
             TimedEvent::CacheCleaning,
           );
         }
+        TimedEvent::DeadlineMissedCheck => {
+          self.check_offered_deadlines();
+          if let Some(policy::Deadline(period)) = self.qos_policies.deadline {
+            self
+              .timed_event_timer
+              .set_timeout(period.to_std(), TimedEvent::DeadlineMissedCheck);
+          }
+        }
+        TimedEvent::AckReceiptTimeout => {
+          self.ack_receipt_timer_set = false;
+          self.check_ack_receipt_timeouts();
+        }
         TimedEvent::SendRepairData {
           to_reader: reader_guid,
         } => {
@@ -511,11 +924,13 @@ impl Writer {
 
   /// This is called by dp_wrapper every time cacheCleaning message is received.
   fn handle_cache_cleaning(&mut self) {
-    let resource_limit = 32;
     // TODO: This limit should be obtained
     // from Topic and Writer QoS. There should be some reasonable default limit
     // in case some supplied QoS setting does not specify a larger value.
     // In any case, there has to be some limit to avoid memory leak.
+    // A KeepAll history with a max_samples limit blocks the DataWriter instead
+    // of growing past it, so there the unacknowledged samples must all be kept.
+    let resource_limit = max(32, self.history_space.limit.unwrap_or(0));
 
     match self.qos_policies.history {
       None => {
@@ -529,6 +944,31 @@ impl Writer {
         self.remove_all_acked_changes_but_keep_depth(Some(d as usize), resource_limit);
       }
     }
+    self.release_history_space();
+  }
+
+  // Gives room in the history back to the DataWriter for the samples that are
+  // no longer kept, or are acknowledged by all Reliable Readers. Without
+  // Reliable Readers, nothing needs to be kept for them.
+  fn release_history_space(&mut self) {
+    if self.history_slots == 0 {
+      return;
+    }
+    let exclude_stalled = self.exclude_stalled_readers();
+    let unacked = self
+      .readers
+      .values()
+      .filter(|rp| rp.qos().is_reliable() && !(exclude_stalled && rp.stalled))
+      .map(RtpsReaderProxy::acked_up_to_before)
+      .min()
+      .map_or(0, |acked_before| {
+        self.history_buffer.count_from(acked_before)
+      });
+    let released = self.history_slots.saturating_sub(unacked);
+    if released > 0 {
+      self.history_slots -= released;
+      self.history_space.give_back(released);
+    }
   }
 
   // --------------------------------------------------------------
@@ -563,6 +1003,12 @@ impl Writer {
               .map(|w| w.wake_by_ref());
           }
 
+          if self.history_space.is_limited() {
+            self.history_slots += 1;
+          }
+          let dds_data = self.decompress_if_not_allowed(dds_data);
+          self.record_instance_write(&dds_data, &write_options);
+
           // Insert data to local HistoryBuffer
           let timestamp =
             self.insert_to_history_buffer(dds_data, write_options.clone(), sequence_number);
@@ -570,6 +1016,8 @@ impl Writer {
           // If not acting stateless-like, notify reader proxies that there is a new
           // sample
           if !self.like_stateless {
+            self.track_ack_receipt(sequence_number, write_options.to_single_reader());
+
             for reader in &mut self.readers.values_mut() {
               reader.notify_new_cache_change(sequence_number);
 
@@ -619,6 +1067,7 @@ impl Writer {
               &mut self.readers.values(),
             );
           }
+          self.send_queue.dequeued();
         }
 
         // WriterCommand::ResetOfferedDeadlineMissedStatus { writer_guid: _, } => {
@@ -636,11 +1085,15 @@ impl Writer {
           }
 
           let wait_until = self.history_buffer.last_change_sequence_number();
+          let exclude_stalled = self.exclude_stalled_readers();
           let readers_pending: BTreeSet<_> = self
             .readers
             .iter()
             .filter_map(|(guid, rp)| {
-              if rp.qos().is_reliable() && rp.all_acked_before <= wait_until {
+              if rp.qos().is_reliable()
+                && rp.all_acked_before <= wait_until
+                && !(exclude_stalled && rp.stalled)
+              {
                 Some(*guid)
               } else {
                 None
@@ -662,8 +1115,91 @@ impl Writer {
             })
           };
         }
+
+        WriterCommand::GetRemoteLocators { reply } => {
+          // The DataWriter may have timed out already, so ignore errors.
+          let _ = reply.try_send(self.remote_reader_locators());
+        }
+
+        WriterCommand::GetDiagnostic { reply } => {
+          let mut text = String::new();
+          self
+            .write_diagnostic(&mut text)
+            .unwrap_or_else(|e| error!("Cannot format Writer diagnostic: {e}"));
+          let _ = reply.try_send(text);
+        }
+
+        WriterCommand::GetHistoryCacheStatus { reply } => {
+          let _ = reply.try_send(HistoryCacheStatus {
+            depth: self.history_buffer.depth(),
+            oldest_sequence_number: self.history_buffer.first_change_sequence_number(),
+          });
+        }
+
+        WriterCommand::SetAckListener { listener } => {
+          self.ack_listener = Some(listener);
+        }
       }
     }
+    // Samples nobody needs to acknowledge do not hold room in the history.
+    self.release_history_space();
+  }
+
+  // Human-readable summary of the RTPS state, for DataWriter::print_diagnostic
+  fn write_diagnostic(&self, out: &mut impl std::fmt::Write) -> std::fmt::Result {
+    writeln!(
+      out,
+      "RTPS Writer {:?} topic={:?} {}",
+      self.my_guid,
+      self.my_topic_name,
+      if self.like_stateless {
+        "stateless"
+      } else if self.is_reliable() {
+        "reliable"
+      } else {
+        "best effort"
+      }
+    )?;
+    writeln!(
+      out,
+      "Sequence numbers: first available {}, last written {}",
+      i64::from(self.history_buffer.first_change_sequence_number()),
+      i64::from(self.history_buffer.last_change_sequence_number()),
+    )?;
+    writeln!(
+      out,
+      "History buffer: {} changes",
+      self.history_buffer.depth()
+    )?;
+    match &self.ack_waiter {
+      Some(w) => writeln!(
+        out,
+        "Waiting for acknowledgement of {} from {:?}",
+        i64::from(w.wait_until),
+        w.readers_pending
+      )?,
+      None => writeln!(out, "Nobody waiting for acknowledgements")?,
+    }
+    writeln!(out, "Matched readers: {}", self.readers.len())?;
+    for reader in self.readers.values() {
+      write!(out, "  ")?;
+      reader.write_diagnostic(out)?;
+    }
+    Ok(())
+  }
+
+  fn remote_reader_locators(&self) -> Vec<RemoteLocators> {
+    let last_used = self.last_used_locators.borrow();
+    self
+      .readers
+      .values()
+      .map(|rp| RemoteLocators {
+        guid: rp.remote_reader_guid,
+        unicast_locators: rp.unicast_locator_list.clone(),
+        multicast_locators: rp.multicast_locator_list.clone(),
+        last_used: last_used.get(&rp.remote_reader_guid).copied(),
+      })
+      .collect()
   }
 
   // Returns a boolean telling if the data had to be fragmented
@@ -699,30 +1235,104 @@ impl Writer {
       }
     }
 
-    let messages_to_send = FragmentationIter::new(self, cc, target_reader_opt, send_also_heartbeat);
-    let fragmentation_needed = messages_to_send.fragmentation_needed();
-
     // Send the messages, either to all readers or just one
-    for msg in messages_to_send {
-      match target_reader_opt {
-        None => {
-          // To all
-          self.send_message_to_readers(DeliveryMode::Multicast, msg, &mut self.readers.values());
+    match target_reader_opt {
+      None => {
+        // To all. Readers that get inline QoS and those that do not need
+        // different messages.
+        let inline_qos_readers = self
+          .readers
+          .values()
+          .filter(|rp| self.inline_qos_for(rp))
+          .count();
+        if inline_qos_readers == 0 || inline_qos_readers == self.readers.len() {
+          self.send_cache_change_to_readers(
+            cc,
+            send_also_heartbeat,
+            inline_qos_readers > 0,
+            &self.readers.values(),
+          )
+        } else {
+          self.send_cache_change_to_readers(
+            cc,
+            send_also_heartbeat,
+            true,
+            &self.readers.values().filter(|rp| self.inline_qos_for(rp)),
+          );
+          self.send_cache_change_to_readers(
+            cc,
+            send_also_heartbeat,
+            false,
+            &self.readers.values().filter(|rp| !self.inline_qos_for(rp)),
+          )
         }
-        Some(reader_proxy) => {
-          // To one
+      }
+      Some(reader_proxy) => {
+        // To one
+        let messages_to_send = FragmentationIter::new(
+          self,
+          cc,
+          target_reader_opt,
+          send_also_heartbeat,
+          self.inline_qos_for(reader_proxy),
+        );
+        let fragmentation_needed = messages_to_send.fragmentation_needed();
+        for msg in messages_to_send {
           self.send_message_to_readers(
             DeliveryMode::Unicast,
             msg,
             &mut std::iter::once(reader_proxy),
           );
         }
+        // The return value tells if the data had to be fragmented
+        fragmentation_needed
       }
     }
-    // The return value tells if the data had to be fragmented
+  }
+
+  // Sends the cache change to a group of readers. Returns a boolean telling if
+  // the data had to be fragmented.
+  fn send_cache_change_to_readers<'a>(
+    &self,
+    cc: &CacheChange,
+    send_also_heartbeat: bool,
+    inline_qos: bool,
+    readers: &(impl Iterator<Item = &'a RtpsReaderProxy> + Clone),
+  ) -> bool {
+    let messages_to_send = FragmentationIter::new(self, cc, None, send_also_heartbeat, inline_qos);
+    let fragmentation_needed = messages_to_send.fragmentation_needed();
+    for msg in messages_to_send {
+      self.send_message_to_readers(DeliveryMode::Multicast, msg, &mut readers.clone());
+    }
     fragmentation_needed
   }
 
+  // The DataWriter may have compressed the sample before a Reader that cannot
+  // decompress was matched.
+  fn decompress_if_not_allowed(&self, data: DDSData) -> DDSData {
+    let Some(pc) = &self.payload_compression else {
+      return data;
+    };
+    match data {
+      DDSData::Data { serialized_payload }
+        if compression::is_compressed(&serialized_payload)
+          && !self.compress_payloads.load(atomic::Ordering::Acquire) =>
+      {
+        match compression::decompress(std::slice::from_ref(pc.compressor()), &serialized_payload) {
+          Ok(payload) => DDSData::new(payload),
+          Err(e) => {
+            error!(
+              "Cannot decompress sample: {e}, topic={:?}",
+              self.my_topic_name
+            );
+            DDSData::new(serialized_payload)
+          }
+        }
+      }
+      other => other,
+    }
+  }
+
   fn insert_to_history_buffer(
     &mut self,
     data: DDSData,
@@ -772,10 +1382,12 @@ impl Writer {
     let first_change = self.history_buffer.first_change_sequence_number();
     let last_change = self.history_buffer.last_change_sequence_number();
 
-    if self
-      .readers
-      .values()
-      .all(|rp| last_change < rp.all_acked_before)
+    // A manual liveliness assertion must be sent even if there is no new data.
+    if !is_manual_assertion
+      && self
+        .readers
+        .values()
+        .all(|rp| last_change < rp.all_acked_before)
     {
       trace!("heartbeat tick: all readers have all available data.");
     } else {
@@ -878,12 +1490,18 @@ impl Writer {
 
         let my_topic = self.my_topic_name.clone(); // for debugging
         self.update_ack_waiters(reader_guid, Some(an.reader_sn_state.base()));
+        let mut recovered_from_stall = false;
 
         if let Some(reader_proxy) = self.lookup_reader_proxy_mut(reader_guid) {
           // Mark requested SNs as "unsent changes"
 
           //TODO: We should drop SNs in "pending gap" from unsent changes
+          let unacked = reader_proxy.unacked_count(last_seq);
           reader_proxy.handle_ack_nack(ack_submessage, last_seq);
+          if reader_proxy.stalled && reader_proxy.unacked_count(last_seq) < unacked {
+            reader_proxy.stalled = false;
+            recovered_from_stall = true;
+          }
 
           let reader_guid = reader_proxy.remote_reader_guid; // copy to avoid double mut borrow
 
@@ -937,6 +1555,18 @@ impl Writer {
           }
         } // if have reader_proxy
 
+        self.release_history_space();
+
+        if recovered_from_stall {
+          info!(
+            "Reader {reader_guid:?} recovered from stall. topic={:?}",
+            self.my_topic_name
+          );
+          self.send_status(DataWriterStatus::ReaderRecovered {
+            reader: reader_guid,
+          });
+        }
+
         // See if we need to respond by GAP message
         if let Some(reader_proxy) = self.readers.get(&reader_guid) {
           if !reader_proxy.get_pending_gap().is_empty() {
@@ -973,6 +1603,109 @@ impl Writer {
     }
   }
 
+  // Keeps track of instance writes for the Deadline check. Disposed or
+  // unregistered instances are no longer expected to be written.
+  fn record_instance_write(&mut self, dds_data: &DDSData, write_options: &WriteOptions) {
+    if self.qos_policies.deadline.is_none() {
+      return;
+    }
+    match dds_data {
+      DDSData::Data { .. } => {
+        self
+          .instance_last_written
+          .insert(write_options.key_hash(), Timestamp::now());
+      }
+      DDSData::DisposeByKey { .. } => {
+        self.instance_last_written.remove(&write_options.key_hash());
+      }
+      DDSData::DisposeByKeyHash { key_hash, .. } => {
+        self.instance_last_written.remove(&Some(*key_hash));
+      }
+    }
+  }
+
+  // Offered Deadline: each written instance must be written again within the
+  // deadline period. Called every deadline period.
+  fn check_offered_deadlines(&mut self) {
+    let Some(policy::Deadline(deadline)) = self.qos_policies.deadline else {
+      return;
+    };
+    let now = Timestamp::now();
+    let missed: Vec<Option<KeyHash>> = self
+      .instance_last_written
+      .iter()
+      .filter(|(_, last_written)| now.duration_since(**last_written) > deadline)
+      .map(|(key_hash, _)| *key_hash)
+      .collect();
+    for key_hash in missed {
+      debug!(
+        "Offered deadline missed: topic={:?} instance={:?}",
+        self.my_topic_name, key_hash
+      );
+      self.offered_deadline_missed_count += 1;
+      self.send_status(DataWriterStatus::OfferedDeadlineMissed {
+        count: CountWithChange::start_from(self.offered_deadline_missed_count, 1),
+        last_instance: key_hash.map(InstanceHandle),
+      });
+    }
+  }
+
+  // Stalled reader detection, see policy::StalledReaderDetection. Called on
+  // each heartbeat tick.
+  fn check_stalled_readers(&mut self) {
+    let Some(detection) = self.qos_policies.stalled_reader_detection() else {
+      return;
+    };
+    let stall_timeout = std::time::Duration::from(detection.stall_timeout);
+    let last_seq = self.history_buffer.last_change_sequence_number();
+    let now = Instant::now();
+    let mut newly_stalled = Vec::new();
+    for rp in self.readers.values_mut() {
+      if !rp.qos().is_reliable() {
+        continue;
+      }
+      let stalled_for = rp.ack_stalled_for(last_seq, now);
+      if !rp.stalled && stalled_for >= stall_timeout {
+        rp.stalled = true;
+        newly_stalled.push((
+          rp.remote_reader_guid,
+          rp.unacked_count(last_seq),
+          stalled_for,
+        ));
+      }
+    }
+    if newly_stalled.is_empty() {
+      return;
+    }
+
+    for (reader, unacked, since) in newly_stalled {
+      warn!(
+        "Reader {reader:?} stalled: {unacked} changes not acked for {since:?}. topic={:?}",
+        self.my_topic_name
+      );
+      self.send_status(DataWriterStatus::ReaderStalled {
+        reader,
+        unacked,
+        since: since.into(),
+      });
+      if detection.exclude_stalled_readers {
+        self.update_ack_waiters(reader, None);
+      }
+    }
+    if detection.exclude_stalled_readers {
+      // Release the history held back by the stalled Readers now, so that
+      // blocked writes can continue.
+      self.handle_cache_cleaning();
+    }
+  }
+
+  fn exclude_stalled_readers(&self) -> bool {
+    self
+      .qos_policies
+      .stalled_reader_detection()
+      .is_some_and(|d| d.exclude_stalled_readers)
+  }
+
   // Application may be waiting that remote Readers ACK what we are sending.
   // Notify application that the event they have been waiting for is here.
   fn update_ack_waiters(&mut self, guid: GUID, acked_before: Option<SequenceNumber>) {
@@ -987,6 +1720,100 @@ impl Writer {
         .map(AckWaiter::notify_wait_complete);
       self.ack_waiter = None;
     }
+    self.update_ack_receipts(guid, acked_before);
+  }
+
+  // Start waiting for acknowledgements of a new sample, if the application has
+  // set an AckListener.
+  fn track_ack_receipt(&mut self, sequence_number: SequenceNumber, single_reader: Option<GUID>) {
+    let Some(listener) = &self.ack_listener else {
+      return;
+    };
+    let exclude_stalled = self.exclude_stalled_readers();
+    let readers_pending: BTreeSet<GUID> = self
+      .readers
+      .iter()
+      .filter(|(guid, rp)| {
+        rp.qos().is_reliable()
+          && !(exclude_stalled && rp.stalled)
+          && single_reader.map_or(true, |single| single == **guid)
+      })
+      .map(|(guid, _)| *guid)
+      .collect();
+    let required = match listener.quorum {
+      AckQuorum::All => readers_pending.len(),
+      AckQuorum::AtLeast(count) => count,
+    };
+
+    if required == 0 {
+      (listener.callback)(sequence_number, AckStatus::Achieved);
+    } else if readers_pending.len() < required {
+      (listener.callback)(sequence_number, AckStatus::ReaderSetChanged);
+    } else {
+      self.pending_receipts.insert(
+        sequence_number,
+        PendingReceipt {
+          deadline: Instant::now() + listener.timeout,
+          required,
+          acked: 0,
+          readers_pending,
+        },
+      );
+      if !self.ack_receipt_timer_set {
+        self
+          .timed_event_timer
+          .set_timeout(listener.timeout, TimedEvent::AckReceiptTimeout);
+        self.ack_receipt_timer_set = true;
+      }
+    }
+  }
+
+  fn update_ack_receipts(&mut self, guid: GUID, acked_before: Option<SequenceNumber>) {
+    let mut completed = Vec::new();
+    match acked_before {
+      Some(acked_before) => {
+        for (sn, receipt) in self.pending_receipts.range_mut(..acked_before) {
+          if let Some(status) = receipt.reader_acked_or_lost(guid, true) {
+            completed.push((*sn, status));
+          }
+        }
+      }
+      None => {
+        for (sn, receipt) in &mut self.pending_receipts {
+          if let Some(status) = receipt.reader_acked_or_lost(guid, false) {
+            completed.push((*sn, status));
+          }
+        }
+      }
+    }
+    self.report_ack_receipts(completed);
+  }
+
+  fn check_ack_receipt_timeouts(&mut self) {
+    let now = Instant::now();
+    let timed_out: Vec<_> = self
+      .pending_receipts
+      .iter()
+      .filter(|(_, receipt)| receipt.deadline <= now)
+      .map(|(sn, _)| (*sn, AckStatus::TimedOut))
+      .collect();
+    self.report_ack_receipts(timed_out);
+
+    if let Some(next_deadline) = self.pending_receipts.values().map(|r| r.deadline).min() {
+      self
+        .timed_event_timer
+        .set_timeout(next_deadline - now, TimedEvent::AckReceiptTimeout);
+      self.ack_receipt_timer_set = true;
+    }
+  }
+
+  fn report_ack_receipts(&mut self, completed: Vec<(SequenceNumber, AckStatus)>) {
+    for (sn, status) in completed {
+      self.pending_receipts.remove(&sn);
+      if let Some(listener) = &self.ack_listener {
+        (listener.callback)(sn, status);
+      }
+    }
   }
 
   // Send out missing data
@@ -1207,6 +2034,7 @@ impl Writer {
           data_size,
           self.endianness,
           self.security_plugins.as_ref(),
+          self.inline_qos_for(reader_proxy),
         );
 
         // TODO: some sort of queuing is needed
@@ -1240,9 +2068,11 @@ impl Writer {
     let first_keeper = if !self.like_stateless {
       // Regular stateful writer behavior
       // All readers have acked up to this point (SequenceNumber)
+      let exclude_stalled = self.exclude_stalled_readers();
       let acked_by_all_readers = self
         .readers
         .values()
+        .filter(|rp| !(exclude_stalled && rp.stalled))
         .map(RtpsReaderProxy::acked_up_to_before)
         .min()
         .unwrap_or_else(SequenceNumber::zero);
@@ -1278,7 +2108,7 @@ impl Writer {
       self.topic_name()
     );
     // actual cleaning
-    self.history_buffer.remove_changes_before(first_keeper);
+    self.history_buffer.prune_acknowledged(first_keeper);
   }
 
   pub(crate) fn next_heartbeat_count(&self) -> i32 {
@@ -1292,7 +2122,7 @@ impl Writer {
     &self,
     message: Message,
     readers: &[&RtpsReaderProxy],
-  ) -> SecurityResult<Message> {
+  ) -> SecurityResult<Vec<Message>> {
     // If we have security plugins, use them, otherwise pass through
     if let Some(security_plugins_handle) = &self.security_plugins {
       // Get the source and destination GUIDs
@@ -1320,23 +2150,94 @@ impl Writer {
         header,
         submessages: encoded_submessages.concat(),
       })
-      // Encode message
+      // Split to fit, and encode messages
       .and_then(|message| {
         // Convert GUIDs to GuidPrefixes
         let source_guid_prefix = source_guid.prefix;
-        let destination_guid_prefix_list: Vec<GuidPrefix> = destination_guid_list
+        let mut destination_guid_prefix_list: Vec<GuidPrefix> = destination_guid_list
           .iter()
           .map(|guid| guid.prefix)
           .collect();
-        // Encode message
-        security_plugins_handle.get_plugins().encode_message(
-          message,
-          &source_guid_prefix,
-          &destination_guid_prefix_list,
+        destination_guid_prefix_list.sort();
+        destination_guid_prefix_list.dedup();
+
+        // Split before encoding, so that the protected messages fit.
+        let protection_overhead = security_plugins_handle
+          .get_plugins()
+          .rtps_protection_overhead(&source_guid_prefix, destination_guid_prefix_list.len());
+        SecurityResult::from_iter(
+          self
+            .split_to_max_message_size(message, protection_overhead)
+            .into_iter()
+            .map(|message| {
+              security_plugins_handle.get_plugins().encode_message(
+                message,
+                &source_guid_prefix,
+                &destination_guid_prefix_list,
+              )
+            }),
         )
       })
     } else {
-      Ok(message)
+      Ok(self.split_to_max_message_size(message, 0))
+    }
+  }
+
+  // Fragments are sized to fit with RTPS protection for one receiving
+  // participant. A DATA_FRAG message to several participants would be
+  // protected with a receiver-specific MAC for each of them, and could exceed
+  // max_message_size. Such a message is sent to each participant separately,
+  // with an INFO_DST of its own.
+  #[cfg(feature = "security")]
+  fn fragments_per_participant<'r>(
+    &self,
+    message: &Message,
+    readers: &[&'r RtpsReaderProxy],
+  ) -> Option<Vec<(Message, Vec<&'r RtpsReaderProxy>)>> {
+    let security_plugins_handle = self.security_plugins.as_ref()?;
+    self.max_message_size?;
+    let has_kind = |kind| message.submessages.iter().any(|s| s.header.kind == kind);
+    if !has_kind(SubmessageKind::DATA_FRAG) || has_kind(SubmessageKind::INFO_DST) {
+      return None;
+    }
+
+    let mut by_participant: BTreeMap<GuidPrefix, Vec<&RtpsReaderProxy>> = BTreeMap::new();
+    for reader in readers {
+      by_participant
+        .entry(reader.remote_reader_guid.prefix)
+        .or_default()
+        .push(reader);
+    }
+    let source_guid_prefix = self.my_guid.prefix;
+    let plugins = security_plugins_handle.get_plugins();
+    if plugins.rtps_protection_overhead(&source_guid_prefix, by_participant.len())
+      <= plugins.rtps_protection_overhead(&source_guid_prefix, 1)
+    {
+      return None;
+    }
+
+    Some(
+      by_participant
+        .into_iter()
+        .map(|(guid_prefix, readers)| {
+          let mut participant_message = MessageBuilder::new()
+            .dst_submessage(self.endianness, guid_prefix)
+            .add_header_and_build(source_guid_prefix);
+          participant_message
+            .submessages
+            .extend(message.submessages.iter().cloned());
+          (participant_message, readers)
+        })
+        .collect(),
+    )
+  }
+
+  // Split the message, if it would exceed max_message_size after adding
+  // `overhead` bytes to it.
+  fn split_to_max_message_size(&self, message: Message, overhead: usize) -> Vec<Message> {
+    match self.max_message_size {
+      Some(max_message_size) => message.split_to_fit(max_message_size.saturating_sub(overhead)),
+      None => vec![message],
     }
   }
 
@@ -1356,57 +2257,91 @@ impl Writer {
 
     let readers = readers.collect::<Vec<_>>(); // clone iterator
 
+    #[cfg(feature = "security")]
+    if let Some(per_participant) = self.fragments_per_participant(&message, &readers) {
+      for (message, readers) in per_participant {
+        // Unicast, as other participants would discard a multicast copy.
+        self.send_message_to_readers(DeliveryMode::Unicast, message, &mut readers.into_iter());
+      }
+      return;
+    }
+
     #[cfg(feature = "security")]
     let encoded = self.security_encode(message, &readers);
     #[cfg(not(feature = "security"))]
-    let encoded: Result<Message, ()> = Ok(message);
+    let encoded: Result<Vec<Message>, ()> = Ok(self.split_to_max_message_size(message, 0));
 
     match encoded {
-      Ok(message) => {
-        let buffer = message.write_to_vec_with_ctx(self.endianness).unwrap();
-        let mut already_sent_to = BTreeSet::new();
-
-        macro_rules! send_unless_sent_and_mark {
-          ($locs:expr) => {
-            for loc in $locs.iter() {
-              if already_sent_to.contains(loc) {
-                trace!("Already sent to {:?}", loc);
-              } else {
-                self.udp_sender.send_to_locator(&buffer, loc);
-                already_sent_to.insert(loc.clone());
+      Ok(messages) => {
+        let mut last_used_locators = self.last_used_locators.borrow_mut();
+        for message in messages {
+          let buffer = message.write_to_vec_with_ctx(self.endianness).unwrap();
+          if let Some(max_message_size) = self.max_message_size.filter(|m| buffer.len() > *m) {
+            warn!(
+              "Sending a message of {} bytes, over the maximum of {max_message_size}. topic={:?}",
+              buffer.len(),
+              self.my_topic_name
+            );
+          }
+          // Locators sent to, and whether sending succeeded
+          let mut already_sent_to = BTreeMap::new();
+
+          macro_rules! send_unless_sent_and_mark {
+            ($locs:expr, $reader_guid:expr) => {
+              for loc in $locs.iter() {
+                let sent = match already_sent_to.get(loc) {
+                  Some(sent) => {
+                    trace!("Already sent to {:?}", loc);
+                    *sent
+                  }
+                  None => {
+                    let sent = self.udp_sender.send_to_locator(&buffer, loc);
+                    already_sent_to.insert(*loc, sent);
+                    sent
+                  }
+                };
+                if sent {
+                  last_used_locators.insert($reader_guid, *loc);
+                }
               }
-            }
-          };
-        }
+            };
+          }
 
-        for reader in readers {
-          match (
-            preferred_mode,
-            reader
-              .unicast_locator_list
-              .iter()
-              .find(|l| Locator::is_udp(l)),
-            reader
-              .multicast_locator_list
-              .iter()
-              .find(|l| Locator::is_udp(l)),
-          ) {
-            (DeliveryMode::Multicast, _, Some(_mc_locator)) => {
-              send_unless_sent_and_mark!(reader.multicast_locator_list);
-            }
-            (DeliveryMode::Unicast, Some(_uc_locator), _) => {
-              send_unless_sent_and_mark!(reader.unicast_locator_list)
-            }
-            (_delivery_mode, _, Some(_mc_locator)) => {
-              send_unless_sent_and_mark!(reader.multicast_locator_list);
-            }
-            (_delivery_mode, Some(_uc_locator), _) => {
-              send_unless_sent_and_mark!(reader.unicast_locator_list)
-            }
-            (_delivery_mode, None, None) => {
-              warn!("send_message_to_readers: No locators for {reader:?}");
-            }
-          } // match
+          for reader in &readers {
+            match (
+              preferred_mode,
+              reader
+                .unicast_locator_list
+                .iter()
+                .find(|l| Locator::is_udp(l)),
+              reader
+                .multicast_locator_list
+                .iter()
+                .find(|l| Locator::is_udp(l)),
+            ) {
+              (DeliveryMode::Multicast, _, Some(_mc_locator)) => {
+                send_unless_sent_and_mark!(
+                  reader.multicast_locator_list,
+                  reader.remote_reader_guid
+                );
+              }
+              (DeliveryMode::Unicast, Some(_uc_locator), _) => {
+                send_unless_sent_and_mark!(reader.unicast_locator_list, reader.remote_reader_guid)
+              }
+              (_delivery_mode, _, Some(_mc_locator)) => {
+                send_unless_sent_and_mark!(
+                  reader.multicast_locator_list,
+                  reader.remote_reader_guid
+                );
+              }
+              (_delivery_mode, Some(_uc_locator), _) => {
+                send_unless_sent_and_mark!(reader.unicast_locator_list, reader.remote_reader_guid)
+              }
+              (_delivery_mode, None, None) => {
+                warn!("send_message_to_readers: No locators for {reader:?}");
+              }
+            } // match
+          }
         }
       }
       Err(e) => error!("Failed to send message to readers. Encoding failed: {e:?}"),
@@ -1433,16 +2368,50 @@ impl Writer {
     &mut self,
     reader_proxy: &RtpsReaderProxy,
     requested_qos: &QosPolicies,
+  ) {
+    self.update_discovered_reader_proxy(reader_proxy, requested_qos, None);
+  }
+
+  // Like update_reader_proxy, but with the Discovery data of the Reader, which
+  // is passed on to the DataWriter when matched.
+  pub fn update_discovered_reader_proxy(
+    &mut self,
+    reader_proxy: &RtpsReaderProxy,
+    requested_qos: &QosPolicies,
+    reader_description: Option<Box<EndpointDescription>>,
   ) {
     debug!(
       "update_reader_proxy topic={:?} reader_proxy={reader_proxy:?}",
       self.my_topic_name
     );
+    if let Some(pc) = &self.payload_compression {
+      let algorithm = pc.compressor().name();
+      if pc.get_fallback() == CompressionFallback::DoNotMatch
+        && !reader_proxy.can_decompress(algorithm)
+      {
+        info!(
+          "Not matching reader {:?}, because it cannot decompress {algorithm:?}. topic={:?}",
+          reader_proxy.remote_reader_guid, self.my_topic_name
+        );
+        self.reader_lost(reader_proxy.remote_reader_guid); // in case it was matched before
+        return;
+      }
+    }
     match self.qos_policies.compliance_failure_wrt(requested_qos) {
       // matched QoS
       None => {
+        self
+          .incompatible_readers
+          .remove(&reader_proxy.remote_reader_guid);
         let new_reader = self.matched_reader_update(reader_proxy);
+        self.update_payload_compression();
+        self.update_send_key_hash();
         if new_reader {
+          self
+            .publication_matched
+            .lock()
+            .unwrap()
+            .reader_matched(reader_proxy.remote_reader_guid);
           self.matched_readers_count_total += 1;
           self.send_status(DataWriterStatus::PublicationMatched {
             // total: How many matches have been detected ever?
@@ -1450,6 +2419,7 @@ impl Writer {
             // current: How many readers we are matched with?
             current: CountWithChange::new(self.readers.len() as i32, 1),
             reader: reader_proxy.remote_reader_guid,
+            reader_description,
           });
           self.send_participant_status(DomainParticipantStatusEvent::RemoteReaderMatched {
             local_writer: self.my_guid,
@@ -1467,6 +2437,14 @@ impl Writer {
       }
       Some(bad_policy_id) => {
         // QoS not compliant :(
+        if self
+          .incompatible_readers
+          .insert(reader_proxy.remote_reader_guid, bad_policy_id)
+          == Some(bad_policy_id)
+        {
+          debug!("update_reader_proxy - QoS mismatch {bad_policy_id:?} already reported");
+          return;
+        }
         warn!(
           "update_reader_proxy - QoS mismatch {:?} topic={:?}",
           bad_policy_id,
@@ -1477,6 +2455,11 @@ impl Writer {
           requested_qos, self.qos_policies
         );
 
+        self
+          .offered_incompatible_qos
+          .lock()
+          .unwrap()
+          .reader_incompatible(bad_policy_id);
         self.requested_incompatible_qos_count += 1;
         self.send_status(DataWriterStatus::OfferedIncompatibleQos {
           count: CountWithChange::new(self.requested_incompatible_qos_count, 1),
@@ -1518,8 +2501,43 @@ impl Writer {
     is_new
   }
 
+  // Compress only while all matched Readers can decompress. Samples compressed
+  // before that are decompressed for the Readers that cannot.
+  fn update_payload_compression(&mut self) {
+    if let Some(pc) = &self.payload_compression {
+      let algorithm = pc.compressor().name();
+      let compress = self.readers.values().all(|rp| rp.can_decompress(algorithm));
+      let was_compressing = self
+        .compress_payloads
+        .swap(compress, atomic::Ordering::AcqRel);
+      if was_compressing && !compress {
+        self.history_buffer.decompress_changes(pc.compressor());
+      }
+    }
+  }
+
+  // Does the Reader get optional inline QoS, see policy::InlineQos
+  fn inline_qos_for(&self, reader_proxy: &RtpsReaderProxy) -> bool {
+    reader_proxy.expects_inline_qos()
+      || self.qos_policies.inline_qos() == Some(policy::InlineQos::Always)
+  }
+
+  fn update_send_key_hash(&self) {
+    let send = self.qos_policies.inline_qos() == Some(policy::InlineQos::Always)
+      || self
+        .readers
+        .values()
+        .any(RtpsReaderProxy::expects_inline_qos);
+    self.send_key_hash.store(send, atomic::Ordering::Release);
+  }
+
   fn matched_reader_remove(&mut self, guid: GUID) -> Option<RtpsReaderProxy> {
     let removed = self.readers.remove(&guid);
+    self.last_used_locators.get_mut().remove(&guid);
+    if removed.is_some() {
+      self.update_payload_compression();
+      self.update_send_key_hash();
+    }
     if let Some(ref removed_reader) = removed {
       info!(
         "Removed reader proxy. topic={:?} reader={:?}",
@@ -1539,22 +2557,31 @@ impl Writer {
   }
 
   pub fn reader_lost(&mut self, guid: GUID) {
-    if self.readers.contains_key(&guid) {
+    self.incompatible_readers.remove(&guid);
+    if let Some(rp) = self.readers.get(&guid) {
       info!(
-        "reader_lost topic={:?} reader={:?}",
+        "reader_lost topic={:?} reader={:?} stalled={}",
         self.topic_name(),
-        &guid
+        &guid,
+        rp.stalled,
       );
       self.matched_reader_remove(guid);
       // self.matched_readers_count_total -= 1; // this never decreases
+      self
+        .publication_matched
+        .lock()
+        .unwrap()
+        .reader_unmatched(guid);
       self.send_status(DataWriterStatus::PublicationMatched {
         total: CountWithChange::new(self.matched_readers_count_total, 0),
         current: CountWithChange::new(self.readers.len() as i32, -1),
         reader: guid,
+        reader_description: None,
       });
     }
     // also remember to remove reader from ack_waiter
     self.update_ack_waiters(guid, None);
+    self.release_history_space();
   }
 
   // Entire remote participant was lost.
@@ -1564,6 +2591,12 @@ impl Writer {
       .readers
       .range(guid_prefix.range())
       .map(|(g, _)| *g)
+      .chain(
+        self
+          .incompatible_readers
+          .range(guid_prefix.range())
+          .map(|(g, _)| *g),
+      )
       .collect();
     for reader in lost_readers {
       self.reader_lost(reader);
@@ -1578,6 +2611,10 @@ impl Writer {
     &self.my_topic_name
   }
 
+  pub fn matched_reader_count(&self) -> usize {
+    self.readers.len()
+  }
+
   fn send_participant_status(&self, event: DomainParticipantStatusEvent) {
     self
       .participant_status_sender
@@ -1593,6 +2630,13 @@ impl Writer {
   // }
 }
 
+impl Drop for Writer {
+  fn drop(&mut self) {
+    // Samples still in the command channel are never processed
+    self.send_queue.close();
+  }
+}
+
 impl RTPSEntity for Writer {
   fn guid(&self) -> GUID {
     self.my_guid
@@ -1611,6 +2655,7 @@ struct FragmentationIter<'a> {
   target_reader_opt: Option<&'a RtpsReaderProxy>,
   reader_entity_id: EntityId,
   send_heartbeat: bool,
+  inline_qos: bool, // see policy::InlineQos
   finished: bool,
   state: FragmentationIterState,
 }
@@ -1621,6 +2666,7 @@ impl<'a> FragmentationIter<'a> {
     cache_change: &'a CacheChange,
     target_reader_opt: Option<&'a RtpsReaderProxy>,
     send_heartbeat: bool,
+    inline_qos: bool,
   ) -> Self {
     // The EntityId of the destination
     let reader_entity_id =
@@ -1643,6 +2689,7 @@ impl<'a> FragmentationIter<'a> {
       reader_entity_id,
       finished: false,
       send_heartbeat,
+      inline_qos,
     }
   }
 
@@ -1674,6 +2721,7 @@ impl<'a> Iterator for FragmentationIter<'a> {
     let target_reader_opt = self.target_reader_opt;
     let reader_entity_id = self.reader_entity_id;
     let send_heartbeat = self.send_heartbeat;
+    let inline_qos = self.inline_qos;
 
     match &mut self.state {
       FragmentationIterState::Fragmented(state, data_size) => {
@@ -1730,6 +2778,7 @@ impl<'a> Iterator for FragmentationIter<'a> {
                 (*data_size).try_into().unwrap(),
                 writer.endianness,
                 writer.security_plugins.as_ref(),
+                inline_qos,
               );
 
               let datafrag_msg = message_builder.add_header_and_build(writer.my_guid.prefix);
@@ -1797,6 +2846,7 @@ impl<'a> Iterator for FragmentationIter<'a> {
           writer.my_guid,
           writer.endianness,
           writer.security_plugins.as_ref(),
+          inline_qos,
         );
 
         // Add HEARTBEAT if needed
@@ -1835,12 +2885,15 @@ mod tests {
   use byteorder::LittleEndian;
   use log::info;
 
+  use super::*;
   use crate::{
     dds::{
-      participant::DomainParticipant, qos::QosPolicies, topic::TopicKind,
-      with_key::datawriter::DataWriter,
+      participant::DomainParticipant, qos::QosPolicies, statusevents::sync_status_channel,
+      topic::TopicKind, with_key::datawriter::DataWriter,
     },
+    network::udp_sender::UDPSender,
     serialization::CDRSerializerAdapter,
+    structure::guid::EntityKind,
     test::random_data::*,
   };
 
@@ -1894,4 +2947,718 @@ mod tests {
     thread::sleep(std::time::Duration::from_millis(100));
     info!("writerResult:  {write_result:?}");
   }
+
+  #[test]
+  fn writer_updates_publication_matched_status() {
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let publication_matched = Arc::new(Mutex::new(PublicationMatchedState::default()));
+
+    let (mut writer_ing, _ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "matched",
+      QosPolicies::qos_none(),
+    );
+    writer_ing.publication_matched = publication_matched.clone();
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let reader_proxy = RtpsReaderProxy::new(reader_guid, QosPolicies::qos_none(), false);
+    writer.update_reader_proxy(&reader_proxy, &QosPolicies::qos_none());
+    // Update of a known Reader is not a new match
+    writer.update_reader_proxy(&reader_proxy, &QosPolicies::qos_none());
+
+    let status = publication_matched.lock().unwrap().take();
+    assert_eq!(status.total_count, CountWithChange::new(1, 1));
+    assert_eq!(status.current_count, CountWithChange::new(1, 1));
+    assert_eq!(status.last_subscription_key, Some(reader_guid));
+
+    // Reading resets the changes
+    let status = publication_matched.lock().unwrap().take();
+    assert_eq!(status.current_count, CountWithChange::new(1, 0));
+
+    writer.reader_lost(reader_guid);
+    let status = publication_matched.lock().unwrap().take();
+    assert_eq!(status.total_count, CountWithChange::new(1, 0));
+    assert_eq!(status.current_count, CountWithChange::new(0, -1));
+  }
+
+  #[test]
+  fn writer_sends_key_hash_to_readers_expecting_inline_qos() {
+    use crate::{
+      dds::{key::KeyHash, qos::QosPolicyBuilder, with_key::datawriter::WriteOptionsBuilder},
+      messages::submessages::{
+        elements::{inline_qos::InlineQos, serialized_payload::SerializedPayload},
+        submessages::WriterSubmessage,
+      },
+      rtps::SubmessageBody,
+    };
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let send_key_hash = Arc::new(AtomicBool::new(false));
+
+    let (mut writer_ing, _ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "inline qos",
+      QosPolicies::qos_none(),
+    );
+    writer_ing.send_key_hash = send_key_hash.clone();
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let key_hash = KeyHash::zero();
+    let cc = CacheChange::new(
+      writer.guid(),
+      SequenceNumber::new(1),
+      WriteOptionsBuilder::new().key_hash(key_hash).build(),
+      DDSData::new(SerializedPayload::default()),
+    );
+    let sent_key_hash = |writer: &Writer, reader_proxy: &RtpsReaderProxy| {
+      FragmentationIter::new(
+        writer,
+        &cc,
+        Some(reader_proxy),
+        false,
+        writer.inline_qos_for(reader_proxy),
+      )
+      .flat_map(|msg| msg.submessages)
+      .find_map(|submessage| match submessage.body {
+        SubmessageBody::Writer(WriterSubmessage::Data(data, _)) => Some(data),
+        _ => None,
+      })
+      .and_then(|data| data.inline_qos)
+      .and_then(|inline_qos| InlineQos::key_hash(&inline_qos).unwrap())
+    };
+
+    let plain_reader = RtpsReaderProxy::new(
+      GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED),
+      QosPolicies::qos_none(),
+      false,
+    );
+    writer.update_reader_proxy(&plain_reader, &QosPolicies::qos_none());
+    assert!(!send_key_hash.load(atomic::Ordering::Acquire));
+    assert_eq!(sent_key_hash(&writer, &plain_reader), None);
+
+    let inline_qos_reader = RtpsReaderProxy::new(
+      GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      QosPolicies::qos_none(),
+      true,
+    );
+    writer.update_reader_proxy(&inline_qos_reader, &QosPolicies::qos_none());
+    assert!(send_key_hash.load(atomic::Ordering::Acquire));
+    assert_eq!(sent_key_hash(&writer, &inline_qos_reader), Some(key_hash));
+    assert_eq!(sent_key_hash(&writer, &plain_reader), None);
+
+    writer.reader_lost(inline_qos_reader.remote_reader_guid);
+    assert!(!send_key_hash.load(atomic::Ordering::Acquire));
+
+    // The policy overrides what the Reader expects
+    writer.qos_policies = QosPolicyBuilder::new()
+      .inline_qos(policy::InlineQos::Always)
+      .build();
+    assert_eq!(sent_key_hash(&writer, &plain_reader), Some(key_hash));
+  }
+
+  #[test]
+  fn writer_updates_offered_incompatible_qos_status() {
+    use crate::dds::qos::{QosPolicyBuilder, QosPolicyId};
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let offered_incompatible_qos = Arc::new(Mutex::new(OfferedIncompatibleQosStatus::default()));
+    let writer_qos = QosPolicyBuilder::new().best_effort().build();
+
+    let (mut writer_ing, _ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "incompatible",
+      writer_qos,
+    );
+    writer_ing.offered_incompatible_qos = offered_incompatible_qos.clone();
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+
+    let reliable_qos = QosPolicyBuilder::new().reliable(Duration::ZERO).build();
+    for entity_key in 1..=2 {
+      let reader_guid = GUID::new_with_prefix_and_id(
+        GuidPrefix::new(&[entity_key; 12]),
+        EntityId::new([0, 0, 0], EntityKind::READER_WITH_KEY_USER_DEFINED),
+      );
+      let reader_proxy = RtpsReaderProxy::new(reader_guid, reliable_qos.clone(), false);
+      writer.update_reader_proxy(&reader_proxy, &reliable_qos);
+    }
+    assert!(writer.readers.is_empty());
+
+    let status = offered_incompatible_qos.lock().unwrap().take();
+    assert_eq!(status.count, CountWithChange::new(2, 2));
+    assert_eq!(status.last_policy_id, Some(QosPolicyId::Reliability));
+    assert_eq!(status.policies, vec![(QosPolicyId::Reliability, 2)]);
+
+    // Reading resets the change
+    let status = offered_incompatible_qos.lock().unwrap().take();
+    assert_eq!(status.count, CountWithChange::new(2, 0));
+
+    // Re-announcements of an incompatible Reader are not counted again
+    let reader_guid = GUID::new_with_prefix_and_id(
+      GuidPrefix::new(&[1; 12]),
+      EntityId::new([0, 0, 0], EntityKind::READER_WITH_KEY_USER_DEFINED),
+    );
+    let reader_proxy = RtpsReaderProxy::new(reader_guid, reliable_qos.clone(), false);
+    for _ in 0..3 {
+      writer.update_reader_proxy(&reader_proxy, &reliable_qos);
+    }
+    let status = offered_incompatible_qos.lock().unwrap().take();
+    assert_eq!(status.count, CountWithChange::new(2, 0));
+
+    // Unless the Reader is lost in between
+    writer.reader_lost(reader_guid);
+    writer.update_reader_proxy(&reader_proxy, &reliable_qos);
+    let status = offered_incompatible_qos.lock().unwrap().take();
+    assert_eq!(status.count, CountWithChange::new(3, 1));
+
+    // Or its participant
+    writer.participant_lost(reader_guid.prefix);
+    writer.update_reader_proxy(&reader_proxy, &reliable_qos);
+    let status = offered_incompatible_qos.lock().unwrap().take();
+    assert_eq!(status.count, CountWithChange::new(4, 1));
+  }
+
+  #[test]
+  fn writer_detects_stalled_reader() {
+    use crate::{
+      dds::{key::KeyHash, qos::QosPolicyBuilder, statusevents::StatusEvented},
+      messages::submessages::submessages::AckNack,
+      structure::{cache_change::ChangeKind, sequence_number::SequenceNumberSet},
+    };
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let stall_timeout = std::time::Duration::from_millis(50);
+    let qos = QosPolicyBuilder::new()
+      .reliable(Duration::ZERO)
+      .stalled_reader_detection(policy::StalledReaderDetection {
+        stall_timeout: stall_timeout.into(),
+        exclude_stalled_readers: true,
+      })
+      .build();
+
+    let (writer_ing, ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "stalled",
+      qos.clone(),
+    );
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    writer.update_reader_proxy(&RtpsReaderProxy::new(reader_guid, qos.clone(), false), &qos);
+    let _matched = ends.status_receiver.try_recv_status();
+
+    let writer_id = writer.entity_id();
+    let ack_nack = |acked_before: i64, count| {
+      AckSubmessage::AckNack(AckNack {
+        reader_id: reader_guid.entity_id,
+        writer_id,
+        reader_sn_state: SequenceNumberSet::new_empty(SequenceNumber::from(acked_before)),
+        count,
+      })
+    };
+    let write = |sn: i64| {
+      ends
+        .command_sender
+        .try_send(WriterCommand::DDSData {
+          ddsdata: DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, KeyHash::zero()),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::from(sn),
+        })
+        .unwrap();
+    };
+
+    // Nothing to ack is not a stall
+    std::thread::sleep(stall_timeout);
+    writer.check_stalled_readers();
+    assert!(ends.status_receiver.try_recv_status().is_none());
+
+    write(1);
+    write(2);
+    writer.process_writer_command();
+    writer.check_stalled_readers();
+    assert!(ends.status_receiver.try_recv_status().is_none());
+
+    std::thread::sleep(stall_timeout);
+    writer.check_stalled_readers();
+    match ends.status_receiver.try_recv_status() {
+      Some(DataWriterStatus::ReaderStalled {
+        reader,
+        unacked,
+        since,
+      }) => {
+        assert_eq!(reader, reader_guid);
+        assert_eq!(unacked, 2);
+        assert!(since >= stall_timeout.into());
+      }
+      other => panic!("Expected ReaderStalled, got {other:?}"),
+    }
+    // Reported only once
+    writer.check_stalled_readers();
+    assert!(ends.status_receiver.try_recv_status().is_none());
+
+    // The stalled Reader is not waited for
+    let (all_acked_sender, all_acked_receiver) = sync_status_channel(1).unwrap();
+    ends
+      .command_sender
+      .try_send(WriterCommand::WaitForAcknowledgments {
+        all_acked: all_acked_sender,
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert!(all_acked_receiver.try_recv_status().is_some());
+
+    // Acking without progress does not recover
+    writer.handle_ack_nack(reader_guid.prefix, &ack_nack(1, 1));
+    assert!(ends.status_receiver.try_recv_status().is_none());
+    writer.handle_ack_nack(reader_guid.prefix, &ack_nack(2, 2));
+    assert!(matches!(
+      ends.status_receiver.try_recv_status(),
+      Some(DataWriterStatus::ReaderRecovered { reader }) if reader == reader_guid
+    ));
+  }
+
+  #[test]
+  fn writer_stalled_reader_lost_with_participant() {
+    use crate::{
+      dds::{key::KeyHash, qos::QosPolicyBuilder, statusevents::StatusEvented},
+      structure::cache_change::ChangeKind,
+    };
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let stall_timeout = std::time::Duration::from_millis(50);
+    let qos = QosPolicyBuilder::new()
+      .reliable(Duration::ZERO)
+      .stalled_reader_detection(policy::StalledReaderDetection {
+        stall_timeout: stall_timeout.into(),
+        exclude_stalled_readers: false,
+      })
+      .build();
+
+    let (writer_ing, ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "stalled lost",
+      qos.clone(),
+    );
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    writer.update_reader_proxy(&RtpsReaderProxy::new(reader_guid, qos.clone(), false), &qos);
+    let _matched = ends.status_receiver.try_recv_status();
+
+    ends
+      .command_sender
+      .try_send(WriterCommand::DDSData {
+        ddsdata: DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, KeyHash::zero()),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::from(1),
+      })
+      .unwrap();
+    writer.process_writer_command();
+    std::thread::sleep(stall_timeout);
+    writer.check_stalled_readers();
+    assert!(matches!(
+      ends.status_receiver.try_recv_status(),
+      Some(DataWriterStatus::ReaderStalled { .. })
+    ));
+
+    // Participant lease expires: the Reader is unmatched, not recovered.
+    writer.participant_lost(reader_guid.prefix);
+    assert!(matches!(
+      ends.status_receiver.try_recv_status(),
+      Some(DataWriterStatus::PublicationMatched { current, .. }) if current.count_change() == -1
+    ));
+    assert!(ends.status_receiver.try_recv_status().is_none());
+
+    // Discovered again, the stall timeout starts from the new match, although
+    // the sample is still not acked.
+    writer.update_reader_proxy(&RtpsReaderProxy::new(reader_guid, qos.clone(), false), &qos);
+    let _matched = ends.status_receiver.try_recv_status();
+    writer.check_stalled_readers();
+    assert!(ends.status_receiver.try_recv_status().is_none());
+    std::thread::sleep(stall_timeout);
+    writer.check_stalled_readers();
+    assert!(matches!(
+      ends.status_receiver.try_recv_status(),
+      Some(DataWriterStatus::ReaderStalled { reader, .. }) if reader == reader_guid
+    ));
+  }
+
+  #[test]
+  fn writer_reports_ack_receipts() {
+    use crate::{
+      dds::{key::KeyHash, qos::QosPolicyBuilder},
+      messages::submessages::submessages::AckNack,
+      structure::{cache_change::ChangeKind, sequence_number::SequenceNumberSet},
+    };
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let qos = QosPolicyBuilder::new().reliable(Duration::ZERO).build();
+
+    let (writer_ing, ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "receipts",
+      qos.clone(),
+    );
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    let reader_a = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let reader_b = GUID::new(
+      reader_a.prefix,
+      EntityId::new([4, 5, 6], EntityKind::READER_WITH_KEY_USER_DEFINED),
+    );
+    writer.update_reader_proxy(&RtpsReaderProxy::new(reader_a, qos.clone(), false), &qos);
+    writer.update_reader_proxy(&RtpsReaderProxy::new(reader_b, qos.clone(), false), &qos);
+
+    let writer_id = writer.entity_id();
+    let ack_nack = |reader: GUID, acked_before: i64| {
+      AckSubmessage::AckNack(AckNack {
+        reader_id: reader.entity_id,
+        writer_id,
+        reader_sn_state: SequenceNumberSet::new_empty(SequenceNumber::from(acked_before)),
+        count: acked_before as i32,
+      })
+    };
+    let write = |writer: &mut Writer, sn: i64| {
+      ends
+        .command_sender
+        .try_send(WriterCommand::DDSData {
+          ddsdata: DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, KeyHash::zero()),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::from(sn),
+        })
+        .unwrap();
+      writer.process_writer_command();
+    };
+    let receipts = Arc::new(Mutex::new(Vec::new()));
+    let set_listener = |writer: &mut Writer, quorum, timeout| {
+      let receipts = receipts.clone();
+      ends
+        .command_sender
+        .try_send(WriterCommand::SetAckListener {
+          listener: AckListener {
+            quorum,
+            timeout,
+            callback: Box::new(move |sn, status| receipts.lock().unwrap().push((sn, status))),
+          },
+        })
+        .unwrap();
+      writer.process_writer_command();
+    };
+    let take_receipts = || std::mem::take(&mut *receipts.lock().unwrap());
+
+    // Without a listener, nothing is tracked
+    write(&mut writer, 1);
+    assert!(writer.pending_receipts.is_empty());
+
+    let timeout = std::time::Duration::from_millis(50);
+    set_listener(&mut writer, AckQuorum::All, timeout);
+    write(&mut writer, 2);
+    writer.handle_ack_nack(reader_a.prefix, &ack_nack(reader_a, 3));
+    assert!(take_receipts().is_empty());
+    writer.handle_ack_nack(reader_b.prefix, &ack_nack(reader_b, 3));
+    assert_eq!(
+      take_receipts(),
+      vec![(SequenceNumber::from(2), AckStatus::Achieved)]
+    );
+
+    // Losing a Reader that has not acked
+    write(&mut writer, 3);
+    writer.handle_ack_nack(reader_a.prefix, &ack_nack(reader_a, 4));
+    writer.reader_lost(reader_b);
+    assert_eq!(
+      take_receipts(),
+      vec![(SequenceNumber::from(3), AckStatus::ReaderSetChanged)]
+    );
+
+    // Only reader_a is left
+    set_listener(&mut writer, AckQuorum::AtLeast(1), timeout);
+    write(&mut writer, 4);
+    std::thread::sleep(timeout);
+    writer.check_ack_receipt_timeouts();
+    assert_eq!(
+      take_receipts(),
+      vec![(SequenceNumber::from(4), AckStatus::TimedOut)]
+    );
+
+    set_listener(&mut writer, AckQuorum::AtLeast(2), timeout);
+    write(&mut writer, 5);
+    assert_eq!(
+      take_receipts(),
+      vec![(SequenceNumber::from(5), AckStatus::ReaderSetChanged)]
+    );
+    assert!(writer.pending_receipts.is_empty());
+  }
+
+  #[test]
+  fn writer_messages_fit_max_message_size() {
+    use crate::{
+      dds::with_key::datawriter::WriteOptionsBuilder,
+      messages::submessages::elements::serialized_payload::SerializedPayload,
+      RepresentationIdentifier,
+    };
+
+    let max_message_size = 600;
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+
+    let (mut writer_ing, _ends) = WriterIngredients::for_test(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      "mtu",
+      QosPolicies::qos_none(),
+    );
+    writer_ing.max_message_size = Some(max_message_size);
+    let mut writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    assert_eq!(
+      writer.data_max_size_serialized,
+      max_message_size - DATA_FRAG_MESSAGE_OVERHEAD
+    );
+
+    let reader_proxy = RtpsReaderProxy::new(
+      GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED),
+      QosPolicies::qos_none(),
+      true,
+    );
+    writer.update_reader_proxy(&reader_proxy, &QosPolicies::qos_none());
+
+    // Both a sample that needs fragmentation and one that does not
+    for (sn, payload_size) in [(1, 5000), (2, 200)] {
+      let cc = CacheChange::new(
+        writer.guid(),
+        SequenceNumber::new(sn),
+        WriteOptionsBuilder::new()
+          .source_timestamp(Timestamp::now())
+          .key_hash(KeyHash::zero())
+          .build(),
+        DDSData::new(SerializedPayload::new(
+          RepresentationIdentifier::CDR_LE,
+          vec![0; payload_size],
+        )),
+      );
+      let messages: Vec<Message> =
+        FragmentationIter::new(&writer, &cc, Some(&reader_proxy), true, true)
+          .flat_map(|message| writer.split_to_max_message_size(message, 0))
+          .collect();
+      assert!(!messages.is_empty());
+      for message in messages {
+        let len = message
+          .write_to_vec_with_ctx(writer.endianness)
+          .unwrap()
+          .len();
+        assert!(len <= max_message_size, "{len} > {max_message_size}");
+      }
+    }
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn writer_fragments_fit_max_message_size_with_rtps_protection() {
+    use crate::{
+      dds::with_key::datawriter::WriteOptionsBuilder,
+      messages::submessages::{
+        elements::serialized_payload::SerializedPayload, submessages::InterpreterSubmessage,
+      },
+      rtps::SubmessageBody,
+      security::{
+        security_plugins::SecurityPlugins, AccessControlBuiltin, AuthenticationBuiltin,
+        CryptographicBuiltin,
+      },
+      RepresentationIdentifier,
+    };
+
+    let max_message_size = 1000;
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let security_plugins = SecurityPluginsHandle::new(SecurityPlugins::new(
+      Box::new(AuthenticationBuiltin::new()),
+      Box::new(AccessControlBuiltin::new()),
+      Box::new(CryptographicBuiltin::new()),
+    ));
+    let protection_overhead = |count| {
+      security_plugins
+        .get_plugins()
+        .rtps_protection_overhead(&writer_guid.prefix, count)
+    };
+    assert!(protection_overhead(1) > 0);
+    assert!(protection_overhead(3) > protection_overhead(1));
+
+    let (participant_status_sender, _participant_status_receiver) =
+      sync_status_channel(16).unwrap();
+    let (mut writer_ing, _ends) =
+      WriterIngredients::for_test(writer_guid, "mtu", QosPolicies::qos_none());
+    writer_ing.max_message_size = Some(max_message_size);
+    writer_ing.security_plugins = Some(security_plugins.clone());
+    let writer = Writer::new(
+      writer_ing,
+      Arc::new(UDPSender::new_with_random_port().unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    assert_eq!(
+      writer.data_max_size_serialized,
+      max_message_size - DATA_FRAG_MESSAGE_OVERHEAD - protection_overhead(1)
+    );
+
+    // Readers in three participants
+    let reader_entity_id =
+      GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED).entity_id;
+    let reader_proxies: Vec<RtpsReaderProxy> = (1..=3)
+      .map(|p| {
+        RtpsReaderProxy::new(
+          GUID::new(GuidPrefix::new(&[p; 12]), reader_entity_id),
+          QosPolicies::qos_none(),
+          false,
+        )
+      })
+      .collect();
+    let readers: Vec<&RtpsReaderProxy> = reader_proxies.iter().collect();
+
+    let payload_size = 5000;
+    let cc = CacheChange::new(
+      writer.guid(),
+      SequenceNumber::new(1),
+      WriteOptionsBuilder::new()
+        .source_timestamp(Timestamp::now())
+        .key_hash(KeyHash::zero())
+        .build(),
+      DDSData::new(SerializedPayload::new(
+        RepresentationIdentifier::CDR_LE,
+        vec![0; payload_size],
+      )),
+    );
+    let (_num_frags, fragment_size) = writer.num_frags_and_frag_size(payload_size);
+    let data_frag = |builder: MessageBuilder| {
+      builder
+        .ts_msg(writer.endianness, cc.write_options.source_timestamp())
+        .data_frag_msg(
+          &cc,
+          EntityId::UNKNOWN,
+          writer.my_guid,
+          FragmentNumber::new(1),
+          fragment_size,
+          payload_size as u32,
+          writer.endianness,
+          None,
+          true,
+        )
+        .add_header_and_build(writer.my_guid.prefix)
+    };
+
+    // Sent to each participant separately, and fits with its protection
+    let per_participant = writer
+      .fragments_per_participant(&data_frag(MessageBuilder::new()), &readers)
+      .expect("not split per participant");
+    assert_eq!(per_participant.len(), 3);
+    for (message, message_readers) in per_participant {
+      assert_eq!(message_readers.len(), 1);
+      let destination = message_readers[0].remote_reader_guid.prefix;
+      assert!(matches!(
+        &message.submessages[0].body,
+        SubmessageBody::Interpreter(InterpreterSubmessage::InfoDestination(info_dst, _))
+          if info_dst.guid_prefix == destination
+      ));
+      let len = message
+        .write_to_vec_with_ctx(writer.endianness)
+        .unwrap()
+        .len();
+      assert!(
+        len + protection_overhead(1) <= max_message_size,
+        "{len} + {} > {max_message_size}",
+        protection_overhead(1)
+      );
+    }
+
+    // Already addressed to a participant
+    let addressed = data_frag(
+      MessageBuilder::new().dst_submessage(writer.endianness, readers[0].remote_reader_guid.prefix),
+    );
+    assert!(writer
+      .fragments_per_participant(&addressed, &readers[..1])
+      .is_none());
+  }
+
+  #[test]
+  fn history_buffer_prune_acknowledged() {
+    use crate::{
+      messages::submessages::elements::serialized_payload::SerializedPayload,
+      RepresentationIdentifier,
+    };
+
+    let budget = Arc::new(MemoryBudget::new(Some(1 << 20)));
+    let mut history = HistoryBuffer::new("prune".to_string(), Arc::clone(&budget));
+    // SequenceNumber 4 is not in the buffer
+    for (tick, sn) in [1, 2, 3, 5].into_iter().enumerate() {
+      let data = DDSData::new(SerializedPayload::new(
+        RepresentationIdentifier::CDR_LE,
+        vec![0; 100],
+      ));
+      let cc = CacheChange::new(
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::new(sn),
+        WriteOptions::default(),
+        data,
+      );
+      history.add_change(Timestamp::from_ticks(tick as u64 + 1), cc);
+    }
+    let one_change = budget.used() / 4;
+    assert_eq!(history.depth(), 4);
+
+    history.prune_acknowledged(SequenceNumber::new(4));
+    assert_eq!(history.depth(), 1);
+    assert_eq!(
+      history.first_change_sequence_number(),
+      SequenceNumber::new(4)
+    );
+    assert!(history.get_by_sn(SequenceNumber::new(5)).is_some());
+    assert_eq!(budget.used(), one_change);
+
+    // Nothing below the first SequenceNumber
+    history.prune_acknowledged(SequenceNumber::new(2));
+    assert_eq!(history.depth(), 1);
+
+    // Not beyond the next SequenceNumber to be written
+    history.prune_acknowledged(SequenceNumber::new(100));
+    assert_eq!(history.depth(), 0);
+    assert_eq!(
+      history.first_change_sequence_number(),
+      SequenceNumber::new(6)
+    );
+    assert_eq!(budget.used(), 0);
+  }
 }
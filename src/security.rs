@@ -6,6 +6,7 @@ pub mod cryptographic;
 pub mod logging;
 mod private_key;
 pub mod security_plugins;
+pub mod self_test;
 pub mod types;
 
 pub use types::*;
@@ -1,6 +1,8 @@
 use std::{
   collections::HashMap,
   fmt::{self, Formatter},
+  io,
+  sync::Mutex,
 };
 
 use bytes::Bytes;
@@ -10,8 +12,8 @@ use ring::agreement;
 use crate::{
   create_security_error_and_log,
   security::{
-    access_control::PermissionsToken, certificate, private_key, security_error, SecurityError,
-    SecurityResult,
+    access_control::PermissionsToken, certificate, config::KeyAgreementAlgorithm, private_key,
+    security_error, SecurityError, SecurityResult,
   },
   GUID,
 };
@@ -92,10 +94,13 @@ struct LocalParticipantInfo {
   guid: GUID,
   id_cert_private_key: private_key::PrivateKey, // PrivateKey is actually (private,public) key pair
   identity_certificate: certificate::Certificate, // Certificate contains the public key also
-  identity_ca: certificate::Certificate,        /* Certification Authority who has signed
-                                                 * identity_certificate */
+  // Certification Authorities, any of which may have signed remote identities.
+  // One of them has signed our identity_certificate.
+  trusted_identity_cas: Vec<certificate::Certificate>,
   signed_permissions_document_xml: Bytes, // We do not care about UTF-8:ness anymore
   local_permissions_token: Option<PermissionsToken>,
+  // Offered in the handshake requests we send
+  preferred_key_agreement: KeyAgreementAlgorithm,
 }
 
 // All things about remote participant that we're interested in
@@ -194,21 +199,38 @@ pub struct AuthenticationBuiltin {
   next_identity_handle: IdentityHandle,
   next_handshake_handle: HandshakeHandle,
 
+  // Source of challenges and other nonces. This is the system RNG, unless
+  // something else is configured.
+  random_source: Mutex<Box<dyn SecureRandomSource>>,
+
   // Our own cryptographic pseudo-random number generator
   // From ring documentation (https://docs.rs/ring/latest/ring/rand/index.html):
   // "An application should create a single SystemRandom and then use it for all randomness
   // generation"
+  //
+  // This is still used for the ephemeral ECDH keys, because ring does not
+  // accept any other random number generator for those.
   secure_random_generator: ring::rand::SystemRandom,
 }
 
 impl AuthenticationBuiltin {
   pub fn new() -> Self {
+    Self::with_random_source(Box::new(SystemSecureRandom::new()))
+  }
+
+  /// Builtin Authentication, which takes the random bytes for handshake
+  /// challenges and shared secret nonces from `random_source`.
+  ///
+  /// Ephemeral elliptic curve Diffie-Hellman keys are always generated using
+  /// the system random number generator.
+  pub fn with_random_source(random_source: Box<dyn SecureRandomSource>) -> Self {
     Self {
       local_participant_info: None, // No info yet
       remote_participant_infos: HashMap::new(),
       handshake_to_identity_handle_map: HashMap::new(),
       next_identity_handle: 0,
       next_handshake_handle: 0,
+      random_source: Mutex::new(random_source),
       secure_random_generator: ring::rand::SystemRandom::new(),
     }
   }
@@ -276,8 +298,57 @@ impl AuthenticationBuiltin {
   }
 
   fn generate_random_32_bytes(&self) -> SecurityResult<[u8; 32]> {
-    ring::rand::generate::<[u8; 32]>(&self.secure_random_generator)
-      .map(|random| random.expose())
-      .map_err(|e| security_error(&format!("Failed to generate random bytes: {e}")))
+    let mut random = [0; 32];
+    self
+      .random_source
+      .lock()
+      .map_err(|e| security_error(&format!("Random source is poisoned: {e}")))?
+      .fill_bytes(&mut random)
+      .map_err(|e| security_error(&format!("Failed to generate random bytes: {e}")))?;
+    Ok(random)
+  }
+}
+
+/// Cryptographically secure random number generator for the builtin
+/// Authentication plugin.
+///
+/// This can be used to substitute e.g. a hardware random number generator, or
+/// a deterministic one for testing. Note that a predictable generator defeats
+/// the purpose of the authentication handshake, so do not use such in
+/// production.
+pub trait SecureRandomSource: Send {
+  /// Fill `dest` entirely with random bytes.
+  fn fill_bytes(&mut self, dest: &mut [u8]) -> io::Result<()>;
+}
+
+/// The default [`SecureRandomSource`]: the random number generator of the
+/// operating system, as provided by the `ring` crate.
+#[derive(Debug)]
+pub struct SystemSecureRandom {
+  rng: ring::rand::SystemRandom,
+}
+
+impl SystemSecureRandom {
+  pub fn new() -> Self {
+    Self {
+      rng: ring::rand::SystemRandom::new(),
+    }
+  }
+}
+
+impl Default for SystemSecureRandom {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl SecureRandomSource for SystemSecureRandom {
+  fn fill_bytes(&mut self, dest: &mut [u8]) -> io::Result<()> {
+    ring::rand::SecureRandom::fill(&self.rng, dest).map_err(|_| {
+      io::Error::new(
+        io::ErrorKind::Other,
+        "System random number generator failed",
+      )
+    })
   }
 }
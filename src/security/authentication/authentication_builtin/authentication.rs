@@ -79,6 +79,22 @@ fn validate_remote_guid(
   }
 }
 
+// Finds the CA in the trust store that has signed the certificate
+fn find_signing_ca<'a>(
+  certificate: &Certificate,
+  trusted_cas: &'a [Certificate],
+) -> SecurityResult<&'a Certificate> {
+  trusted_cas
+    .iter()
+    .find(|ca| certificate.verify_signed_by_certificate(ca).is_ok())
+    .ok_or_else(|| {
+      create_security_error_and_log!(
+        "Certificate of {:?} does not verify against any trusted identity CA",
+        certificate.subject_name()
+      )
+    })
+}
+
 impl Authentication for AuthenticationBuiltin {
   fn validate_local_identity(
     &mut self,
@@ -116,23 +132,27 @@ impl Authentication for AuthenticationBuiltin {
     // * performing verify and sign operations with this participant's private key
     // * accessing the participant GUID (candidate or adjusted??)
 
+    // There may be several identity CA properties, each of which can give
+    // several certificates. Together they form our trust store.
+    let mut trusted_identity_cas = Vec::new();
+    for certificate_uri in participant_qos.get_all_properties(QOS_IDENTITY_CA_PROPERTY_NAME) {
+      let certificates = read_uri_to_certificates(&certificate_uri).map_err(|conf_err| {
+        create_security_error_and_log!(
+          "Failed to read the identity CA certificates from {}: {:?}",
+          certificate_uri,
+          conf_err
+        )
+      })?;
+      trusted_identity_cas.extend(certificates);
+    }
+    if trusted_identity_cas.is_empty() {
+      return Err(create_security_error_and_log!(
+        "No identity CA certificates configured."
+      ));
+    }
+
     //TODO: These loading code snippets are too cut-and-paste. Copied from access
     // control.
-    let identity_ca = participant_qos
-      .get_property(QOS_IDENTITY_CA_PROPERTY_NAME)
-      .and_then(|certificate_uri| {
-        read_uri(&certificate_uri).map_err(|conf_err| {
-          create_security_error_and_log!(
-            "Failed to read the identity CA certificate from {}: {:?}",
-            certificate_uri,
-            conf_err
-          )
-        })
-      })
-      .and_then(|certificate_contents_pem| {
-        Certificate::from_pem(certificate_contents_pem)
-          .map_err(|e| create_security_error_and_log!("{e:?}"))
-      })?;
 
     let identity_certificate = participant_qos
       .get_property(QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME)
@@ -169,14 +189,14 @@ impl Authentication for AuthenticationBuiltin {
         })
       })?;
 
-    // Verify that CA has signed our identity
-    identity_certificate
-      .verify_signed_by_certificate(&identity_ca)
+    // Verify that a trusted CA has signed our identity
+    let identity_ca = find_signing_ca(&identity_certificate, &trusted_identity_cas)
       .map_err(|_e| {
         create_security_error_and_log!(
-          "My own identity certificate does not verify against identity CA."
+          "My own identity certificate does not verify against any identity CA."
         )
-      })?;
+      })?
+      .clone();
 
     // TODO: Check (somehow) that my identity has not been revoked.
 
@@ -224,11 +244,16 @@ impl Authentication for AuthenticationBuiltin {
       guid: adjusted_guid,
       identity_certificate,
       id_cert_private_key,
-      identity_ca,
+      trusted_identity_cas,
       signed_permissions_document_xml: Bytes::new(), /* This is to filled in later by
                                                       * initialization calling
                                                       * .set_permissions_credential_and_token() */
       local_permissions_token: None, // We do no have it yet.
+      preferred_key_agreement: KeyAgreementAlgorithm::from_property_value(
+        participant_qos
+          .get_optional_property(QOS_KEY_AGREEMENT_PROPERTY_NAME)
+          .as_deref(),
+      ),
     };
 
     self.local_participant_info = Some(local_participant_info);
@@ -425,8 +450,10 @@ impl Authentication for AuthenticationBuiltin {
     }
 
     // We send the request so we get to decide the key agreement algorithm.
-    // We choose to use the elliptic curve Diffie-Hellman
-    let dh_keys = DHKeys::new_ec_keys(&self.secure_random_generator)?;
+    let dh_keys = match local_info.preferred_key_agreement {
+      KeyAgreementAlgorithm::Ecdh => DHKeys::new_ec_keys(&self.secure_random_generator)?,
+      KeyAgreementAlgorithm::DhModp => DHKeys::new_modp_keys()?,
+    };
 
     let pdata_bytes = Bytes::from(serialized_local_participant_data);
 
@@ -532,8 +559,8 @@ impl Authentication for AuthenticationBuiltin {
     // So Cert1 is now `request.c_id`
     let cert1 = Certificate::from_pem(request.c_id.as_ref())?;
 
-    // Verify that 1's identity cert checks out against CA.
-    cert1.verify_signed_by_certificate(&local_info.identity_ca)?;
+    // Verify that 1's identity cert checks out against a trusted CA.
+    find_signing_ca(&cert1, &local_info.trusted_identity_cas)?;
 
     // Verify that the remote GUID is as specified by the spec
     let remote_pdata =
@@ -722,8 +749,8 @@ impl Authentication for AuthenticationBuiltin {
         // So Cert2 is now `request.c_id`
         let cert2 = Certificate::from_pem(reply.c_id.as_ref())?;
 
-        // Verify that 2's identity cert checks out against CA.
-        cert2.verify_signed_by_certificate(&local_info.identity_ca)?;
+        // Verify that 2's identity cert checks out against a trusted CA.
+        find_signing_ca(&cert2, &local_info.trusted_identity_cas)?;
 
         // Verify that the remote GUID is as specified by the spec.
         // Note that spec does say that this check needs to be done here. But it seems
@@ -1104,6 +1131,7 @@ impl Authentication for AuthenticationBuiltin {
 #[cfg(test)]
 mod tests {
   use crate::structure::guid::EntityKind;
+  use super::super::SecureRandomSource;
   use super::*;
 
   #[test]
@@ -1129,4 +1157,67 @@ iHhbVPRB9Uxts9CwglxYgZoUdGUAxreYIIaLO4yLqw==
       "Validating an invalid GUID passed!"
     );
   }
+
+  struct CountingRandom(u8);
+
+  impl SecureRandomSource for CountingRandom {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> std::io::Result<()> {
+      for b in dest.iter_mut() {
+        *b = self.0;
+        self.0 = self.0.wrapping_add(1);
+      }
+      Ok(())
+    }
+  }
+
+  struct BrokenRandom;
+
+  impl SecureRandomSource for BrokenRandom {
+    fn fill_bytes(&mut self, _dest: &mut [u8]) -> std::io::Result<()> {
+      Err(std::io::Error::new(std::io::ErrorKind::Other, "no entropy"))
+    }
+  }
+
+  #[test]
+  pub fn random_bytes_come_from_configured_source() {
+    let auth = AuthenticationBuiltin::with_random_source(Box::new(CountingRandom(0)));
+    let first = auth.generate_random_32_bytes().unwrap();
+    let second = auth.generate_random_32_bytes().unwrap();
+    assert_eq!(first, core::array::from_fn(|i| i as u8));
+    assert_eq!(second, core::array::from_fn(|i| 32 + i as u8));
+
+    let broken = AuthenticationBuiltin::with_random_source(Box::new(BrokenRandom));
+    assert!(broken.generate_random_32_bytes().is_err());
+
+    let system = AuthenticationBuiltin::new();
+    assert_ne!(
+      system.generate_random_32_bytes().unwrap(),
+      system.generate_random_32_bytes().unwrap()
+    );
+  }
+
+  #[test]
+  pub fn remote_identity_verifies_against_any_trusted_ca() {
+    let config_dir = concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/examples/security_configuration_files"
+    );
+    let read = |file: &str| read_uri_to_certificates(&format!("file:{config_dir}/{file}")).unwrap();
+    let participant_cert = read("cert.pem").remove(0);
+    let identity_ca = read("identity_ca.cert.pem").remove(0);
+    let other_ca = read("permissions_ca.cert.pem").remove(0);
+
+    // Not signed by the only trusted CA
+    assert!(find_signing_ca(&participant_cert, std::slice::from_ref(&other_ca)).is_err());
+
+    // Signed by one of the trusted CAs
+    let trust_store = vec![other_ca, identity_ca];
+    let signer = find_signing_ca(&participant_cert, &trust_store).unwrap();
+    assert_eq!(signer.to_pem(), trust_store[1].to_pem());
+
+    // A directory reads all certificates in its .pem files
+    let from_dir = read_uri_to_certificates(&format!("file:{config_dir}")).unwrap();
+    assert_eq!(from_dir.len(), 3);
+    assert!(find_signing_ca(&participant_cert, &from_dir).is_ok());
+  }
 }
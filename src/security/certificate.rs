@@ -47,7 +47,20 @@ impl Certificate {
   pub fn from_pem(pem_data: impl AsRef<[u8]>) -> Result<Self, ConfigError> {
     let cert = CapturedX509Certificate::from_pem(pem_data)
       .map_err(to_config_error_parse("Cannot read X.509 Certificate"))?;
+    Self::from_captured(cert)
+  }
+
+  // Reads all certificates from a PEM document, e.g. a CA bundle. Other kinds
+  // of PEM items are ignored.
+  pub fn from_pem_multiple(pem_data: impl AsRef<[u8]>) -> Result<Vec<Self>, ConfigError> {
+    CapturedX509Certificate::from_pem_multiple(pem_data)
+      .map_err(to_config_error_parse("Cannot read X.509 Certificates"))?
+      .into_iter()
+      .map(Self::from_captured)
+      .collect()
+  }
 
+  fn from_captured(cert: CapturedX509Certificate) -> Result<Self, ConfigError> {
     let other_cert = x509_cert::certificate::Certificate::from_der(cert.constructed_data())
       .map_err(to_config_error_parse("Cannot read X.509 Certificate(2)"))?;
 
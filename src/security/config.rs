@@ -1,12 +1,18 @@
 use std::{
   borrow::Borrow,
   path::{Path, PathBuf},
+  time::Duration,
 };
 
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
 use crate::{
   qos, security,
   security::{
-    authentication::authentication_builtin::types::CertificateAlgorithm, private_key::PrivateKey,
+    authentication::authentication_builtin::{types::CertificateAlgorithm, SecureRandomSource},
+    certificate::Certificate,
+    private_key::PrivateKey,
   },
 };
 use super::{
@@ -15,8 +21,9 @@ use super::{
     QOS_PERMISSIONS_DOCUMENT_PROPERTY_NAME,
   },
   authentication::authentication_builtin::types::{
-    QOS_IDENTITY_CA_PROPERTY_NAME, QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME,
-    QOS_PASSWORD_PROPERTY_NAME, QOS_PRIVATE_KEY_PROPERTY_NAME,
+    DH_MODP_KAGREE_ALGO_NAME, ECDH_KAGREE_ALGO_NAME, QOS_IDENTITY_CA_PROPERTY_NAME,
+    QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME, QOS_PASSWORD_PROPERTY_NAME,
+    QOS_PRIVATE_KEY_PROPERTY_NAME,
   },
 };
 
@@ -58,10 +65,132 @@ pub enum PrivateSigningKey {
   },
 }
 
+// RustDDS-specific properties for configuring handshake retransmission. These
+// are not defined in the Security specification.
+pub(crate) const QOS_HANDSHAKE_RESEND_PERIOD_PROPERTY_NAME: &str =
+  "rustdds.sec.auth.handshake_resend_period_ms";
+pub(crate) const QOS_HANDSHAKE_MAX_RESENDS_PROPERTY_NAME: &str =
+  "rustdds.sec.auth.handshake_max_resends";
+
+/// How unanswered authentication handshake messages are retransmitted.
+///
+/// A handshake message that gets no answer is resent every `resend_period`,
+/// at most `max_resends` times. If there is still no answer after that, the
+/// remote participant is marked as failed to authenticate, and a
+/// [`DomainParticipantStatusEvent::Authentication`](crate::DomainParticipantStatusEvent::Authentication)
+/// event with status `Failed` is emitted.
+///
+/// When using [`DomainParticipantBuilder::security`](crate::DomainParticipantBuilder::security)
+/// directly, the policy is given as properties
+/// `rustdds.sec.auth.handshake_resend_period_ms` and
+/// `rustdds.sec.auth.handshake_max_resends`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeRetryPolicy {
+  pub resend_period: Duration,
+  pub max_resends: u32,
+}
+
+impl Default for HandshakeRetryPolicy {
+  fn default() -> Self {
+    Self {
+      resend_period: Duration::from_secs(1),
+      max_resends: 10,
+    }
+  }
+}
+
+impl HandshakeRetryPolicy {
+  fn to_properties(self) -> Vec<security::types::Property> {
+    vec![
+      mk_string_prop(
+        QOS_HANDSHAKE_RESEND_PERIOD_PROPERTY_NAME,
+        self.resend_period.as_millis().to_string(),
+      ),
+      mk_string_prop(
+        QOS_HANDSHAKE_MAX_RESENDS_PROPERTY_NAME,
+        self.max_resends.to_string(),
+      ),
+    ]
+  }
+
+  // Missing or malformed properties get default values.
+  pub(crate) fn from_property_policy(property: &qos::policy::Property) -> Self {
+    let default = Self::default();
+    let parse = |name: &str| {
+      let value = property.value.iter().find(|p| p.name == name)?;
+      value
+        .value
+        .parse::<u64>()
+        .map_err(|e| warn!("Ignoring property {name}={}: {e}", value.value))
+        .ok()
+    };
+    Self {
+      resend_period: parse(QOS_HANDSHAKE_RESEND_PERIOD_PROPERTY_NAME)
+        .map_or(default.resend_period, Duration::from_millis),
+      max_resends: parse(QOS_HANDSHAKE_MAX_RESENDS_PROPERTY_NAME)
+        .map_or(default.max_resends, |n| {
+          u32::try_from(n).unwrap_or(u32::MAX)
+        }),
+    }
+  }
+}
+
+// RustDDS-specific property for choosing the key agreement algorithm of
+// handshake requests.
+pub(crate) const QOS_KEY_AGREEMENT_PROPERTY_NAME: &str = "rustdds.sec.auth.preferred_kagree_algo";
+
+/// Key agreement algorithm offered in outgoing authentication handshake
+/// requests.
+///
+/// The participant that sends the handshake request chooses the algorithm, and
+/// the replier uses the same. Both algorithms are always accepted in received
+/// requests, regardless of this setting.
+///
+/// When using [`DomainParticipantBuilder::security`](crate::DomainParticipantBuilder::security)
+/// directly, the algorithm is given as property
+/// `rustdds.sec.auth.preferred_kagree_algo` with value `ECDH+prime256v1-CEUM`
+/// or `DH+MODP-2048-256`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyAgreementAlgorithm {
+  /// Elliptic curve Diffie-Hellman with curve P-256 (`ECDH+prime256v1-CEUM`)
+  #[default]
+  Ecdh,
+  /// Diffie-Hellman with a 2048-bit MODP group (`DH+MODP-2048-256`). Use this
+  /// for peers that do not support ECDH.
+  DhModp,
+}
+
+impl KeyAgreementAlgorithm {
+  fn name(self) -> &'static str {
+    match self {
+      Self::Ecdh => ECDH_KAGREE_ALGO_NAME,
+      Self::DhModp => DH_MODP_KAGREE_ALGO_NAME,
+    }
+  }
+
+  // Unknown values get the default.
+  pub(crate) fn from_property_value(value: Option<&str>) -> Self {
+    match value {
+      None => Self::default(),
+      Some(ECDH_KAGREE_ALGO_NAME) => Self::Ecdh,
+      Some(DH_MODP_KAGREE_ALGO_NAME) => Self::DhModp,
+      Some(other) => {
+        warn!("Ignoring property {QOS_KEY_AGREEMENT_PROPERTY_NAME}={other}: unknown algorithm");
+        Self::default()
+      }
+    }
+  }
+}
+
 /// This holds the paths to files that configure DDS Security.
 pub struct DomainParticipantSecurityConfigFiles {
-  /// CA that is used to validate identities of DomainParticipants
-  pub identity_ca_certificate: PathBuf,
+  /// CAs that are trusted to validate identities of DomainParticipants. A
+  /// remote identity is accepted, if it validates against any of these. Our
+  /// own identity certificate must be signed by one of them.
+  ///
+  /// Each path is either a PEM file containing one or more certificates, or a
+  /// directory, from which all `.pem` files are read.
+  pub identity_ca_certificates: Vec<PathBuf>,
   /// Identity docuemnt for this Participant
   pub participant_identity_certificate: PathBuf,
   /// Private (signing) key for this participant
@@ -75,6 +204,14 @@ pub struct DomainParticipantSecurityConfigFiles {
   pub participant_permissions_document: PathBuf,
   /// CRLs are not yet implemented.
   pub certificate_revocation_list: Option<PathBuf>,
+  // Random number generator for the builtin Authentication plugin. If
+  // `None`, the operating system generator is used. Set with
+  // with_secure_random_source.
+  pub(crate) secure_random_source: Option<Box<dyn SecureRandomSource>>,
+  /// Retransmission of unanswered authentication handshake messages.
+  pub handshake_retry_policy: HandshakeRetryPolicy,
+  /// Key agreement algorithm to use in handshakes that we initiate.
+  pub preferred_key_agreement: KeyAgreementAlgorithm,
 }
 
 impl DomainParticipantSecurityConfigFiles {
@@ -88,7 +225,7 @@ impl DomainParticipantSecurityConfigFiles {
     // The default names are taken from
     // https://github.com/ros2/rmw_dds_common/blob/6fae970a99c3d4e0684a6e987edb89505b8ee213/rmw_dds_common/src/security.cpp#L25
     DomainParticipantSecurityConfigFiles {
-      identity_ca_certificate: own_and_append(&d, "identity_ca.cert.pem"),
+      identity_ca_certificates: vec![own_and_append(&d, "identity_ca.cert.pem")],
       participant_identity_certificate: own_and_append(&d, "cert.pem"),
       participant_identity_private_key: PrivateSigningKey::Files {
         file_path: own_and_append(&d, "key.pem"),
@@ -98,6 +235,9 @@ impl DomainParticipantSecurityConfigFiles {
       domain_governance_document: own_and_append(&d, "governance.p7s"),
       participant_permissions_document: own_and_append(&d, "permissions.p7s"),
       certificate_revocation_list: None, // "crl.pem"
+      secure_random_source: None,
+      handshake_retry_policy: HandshakeRetryPolicy::default(),
+      preferred_key_agreement: KeyAgreementAlgorithm::default(),
     }
   }
 
@@ -112,7 +252,7 @@ impl DomainParticipantSecurityConfigFiles {
     // The default names are taken from
     // https://github.com/ros2/rmw_dds_common/blob/6fae970a99c3d4e0684a6e987edb89505b8ee213/rmw_dds_common/src/security.cpp#L25
     DomainParticipantSecurityConfigFiles {
-      identity_ca_certificate: own_and_append(&d, "identity_ca.cert.pem"),
+      identity_ca_certificates: vec![own_and_append(&d, "identity_ca.cert.pem")],
       participant_identity_certificate: own_and_append(&d, "cert.pem"),
       participant_identity_private_key: PrivateSigningKey::Pkcs11 {
         hsm_access_library: own_and_append("", hsm_access_library),
@@ -123,12 +263,60 @@ impl DomainParticipantSecurityConfigFiles {
       domain_governance_document: own_and_append(&d, "governance.p7s"),
       participant_permissions_document: own_and_append(&d, "permissions.p7s"),
       certificate_revocation_list: None, // "crl.pem"
+      secure_random_source: None,
+      handshake_retry_policy: HandshakeRetryPolicy::default(),
+      preferred_key_agreement: KeyAgreementAlgorithm::default(),
     }
   }
 
+  /// Use `random_source` instead of the operating system random number
+  /// generator in the builtin Authentication plugin.
+  pub fn with_secure_random_source(mut self, random_source: Box<dyn SecureRandomSource>) -> Self {
+    self.secure_random_source = Some(random_source);
+    self
+  }
+
+  /// Set how unanswered authentication handshake messages are retransmitted.
+  pub fn with_handshake_retry_policy(mut self, policy: HandshakeRetryPolicy) -> Self {
+    self.handshake_retry_policy = policy;
+    self
+  }
+
+  /// Set the key agreement algorithm to offer in handshake requests. The
+  /// default is ECDH.
+  pub fn with_preferred_key_agreement(mut self, algorithm: KeyAgreementAlgorithm) -> Self {
+    self.preferred_key_agreement = algorithm;
+    self
+  }
+
   pub fn into_property_policy(self) -> qos::policy::Property {
-    let mut value = vec![
-      mk_file_prop(QOS_IDENTITY_CA_PROPERTY_NAME, &self.identity_ca_certificate),
+    self.to_property_policy()
+  }
+
+  /// Check that this configuration is usable, without creating a
+  /// DomainParticipant or sending anything to the network.
+  ///
+  /// Two instances of the builtin security plugins are created from this
+  /// configuration, and they go through local identity and permissions
+  /// validation, a complete authentication handshake with each other, and
+  /// remote permissions validation. This catches e.g. a private key that does
+  /// not match its certificate, certificates signed by an untrusted CA, or
+  /// permissions that do not grant access to `domain_id`.
+  ///
+  /// The configured [`SecureRandomSource`] is not used; the test always uses
+  /// the operating system random number generator.
+  pub fn self_test(&self, domain_id: u16) -> security::self_test::SelfTestReport {
+    security::self_test::run(domain_id, self.to_property_policy())
+  }
+
+  fn to_property_policy(&self) -> qos::policy::Property {
+    // Each trusted CA location is given in a separate property of the same name.
+    let mut value: Vec<_> = self
+      .identity_ca_certificates
+      .iter()
+      .map(|path| mk_file_prop(QOS_IDENTITY_CA_PROPERTY_NAME, path))
+      .collect();
+    value.extend([
       mk_file_prop(
         QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME,
         &self.participant_identity_certificate,
@@ -168,9 +356,20 @@ impl DomainParticipantSecurityConfigFiles {
         QOS_PERMISSIONS_DOCUMENT_PROPERTY_NAME,
         &self.participant_permissions_document,
       ),
-    ];
-    if let PrivateSigningKey::Files { file_password, .. } = self.participant_identity_private_key {
-      value.push(mk_string_prop(QOS_PASSWORD_PROPERTY_NAME, file_password));
+    ]);
+    value.extend(self.handshake_retry_policy.to_properties());
+    value.push(mk_string_prop(
+      QOS_KEY_AGREEMENT_PROPERTY_NAME,
+      self.preferred_key_agreement.name().to_string(),
+    ));
+    if let PrivateSigningKey::Files {
+      ref file_password, ..
+    } = self.participant_identity_private_key
+    {
+      value.push(mk_string_prop(
+        QOS_PASSWORD_PROPERTY_NAME,
+        file_password.clone(),
+      ));
     }
 
     qos::policy::Property {
@@ -222,7 +421,32 @@ pub(crate) fn read_uri(uri: &str) -> Result<Bytes, ConfigError> {
   }
 }
 
-//use crate::security::certificate::Certificate;
+// Reads the certificates from a URI. A "file:" URI may also point to a
+// directory, in which case all ".pem" files in it are read.
+pub(in crate::security) fn read_uri_to_certificates(
+  uri: &str,
+) -> Result<Vec<Certificate>, ConfigError> {
+  match uri.split_once(':') {
+    Some(("file", path)) if Path::new(path).is_dir() => {
+      let mut pem_files: Vec<PathBuf> = std::fs::read_dir(path)
+        .map_err(to_config_error_other(&format!("I/O error reading {path}")))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "pem"))
+        .collect();
+      pem_files.sort(); // for deterministic order
+      let mut certificates = Vec::new();
+      for pem_file in pem_files {
+        let contents = std::fs::read(&pem_file).map_err(to_config_error_other(&format!(
+          "I/O error reading {}",
+          pem_file.display()
+        )))?;
+        certificates.extend(Certificate::from_pem_multiple(contents)?);
+      }
+      Ok(certificates)
+    }
+    _ => read_uri(uri).and_then(Certificate::from_pem_multiple),
+  }
+}
 
 pub(in crate::security) fn read_uri_to_private_key(
   uri: &str,
@@ -303,3 +527,54 @@ pub(crate) fn other_config_error(text: String) -> ConfigError {
 pub(crate) fn pkcs7_config_error(text: String) -> ConfigError {
   ConfigError::Pkcs7(text)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn handshake_retry_policy_roundtrip_through_properties() {
+    let policy = HandshakeRetryPolicy {
+      resend_period: Duration::from_millis(250),
+      max_resends: 3,
+    };
+    let config =
+      DomainParticipantSecurityConfigFiles::with_ros_default_names("/tmp", String::new())
+        .with_handshake_retry_policy(policy);
+    let parsed = HandshakeRetryPolicy::from_property_policy(&config.into_property_policy());
+    assert_eq!(parsed, policy);
+
+    // Missing properties give the default
+    let parsed = HandshakeRetryPolicy::from_property_policy(&qos::policy::Property {
+      value: vec![],
+      binary_value: vec![],
+    });
+    assert_eq!(parsed, HandshakeRetryPolicy::default());
+  }
+
+  #[test]
+  fn preferred_key_agreement_through_properties() {
+    let config =
+      DomainParticipantSecurityConfigFiles::with_ros_default_names("/tmp", String::new())
+        .with_preferred_key_agreement(KeyAgreementAlgorithm::DhModp);
+    let value = config
+      .into_property_policy()
+      .value
+      .into_iter()
+      .find(|p| p.name == QOS_KEY_AGREEMENT_PROPERTY_NAME)
+      .map(|p| p.value);
+    assert_eq!(value.as_deref(), Some(DH_MODP_KAGREE_ALGO_NAME));
+    assert_eq!(
+      KeyAgreementAlgorithm::from_property_value(value.as_deref()),
+      KeyAgreementAlgorithm::DhModp
+    );
+
+    // Missing or unknown values give the default ECDH
+    for value in [None, Some("DH+MODP-1024")] {
+      assert_eq!(
+        KeyAgreementAlgorithm::from_property_value(value),
+        KeyAgreementAlgorithm::Ecdh
+      );
+    }
+  }
+}
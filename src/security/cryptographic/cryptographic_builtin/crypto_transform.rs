@@ -306,6 +306,27 @@ impl CryptoTransform for CryptographicBuiltin {
     })
   }
 
+  fn encoded_size_overhead(&self, receiving_participant_count: usize) -> usize {
+    const SUBMESSAGE_HEADER: usize = 4;
+    // CryptoTransformIdentifier + session id and initialization vector suffix
+    const CRYPTO_HEADER: usize = 8 + INITIALIZATION_VECTOR_LENGTH;
+    // key id + MAC
+    const RECEIVER_SPECIFIC_MAC: usize = 4 + MAC_LENGTH;
+
+    let info_source = SUBMESSAGE_HEADER + 20;
+    let prefix = SUBMESSAGE_HEADER + CRYPTO_HEADER;
+    // In case of encryption, the submessages are wrapped into a SecureBody,
+    // which has a CryptoContent length field. Allow for alignment as well.
+    let secure_body = SUBMESSAGE_HEADER + 4 + 3;
+    // Receiver-specific MACs are present only with origin authentication, but
+    // we do not know that here.
+    let postfix = SUBMESSAGE_HEADER
+      + BuiltinCryptoFooter::minimal_serialized_len()
+      + receiving_participant_count * RECEIVER_SPECIFIC_MAC;
+
+    info_source + prefix + secure_body + postfix
+  }
+
   fn decode_rtps_message(
     &self,
     Message {
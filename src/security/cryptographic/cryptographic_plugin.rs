@@ -236,6 +236,14 @@ pub trait CryptoTransform: Send {
     receiving_participant_crypto_handle_list: Vec<ParticipantCryptoHandle>,
   ) -> SecurityResult<Message>;
 
+  /// Return an upper bound for the number of bytes that
+  /// [Self::encode_rtps_message] adds to a message sent to
+  /// `receiving_participant_count` participants.
+  ///
+  /// Not in the specification. The sender uses this to keep protected messages
+  /// within the maximum message size.
+  fn encoded_size_overhead(&self, receiving_participant_count: usize) -> usize;
+
   /// decode_rtps_message: section 8.5.1.9.5 of the Security specification (v.
   /// 1.1)
   ///
@@ -1242,6 +1242,23 @@ impl SecurityPlugins {
     )
   }
 
+  /// Upper bound for the number of bytes [Self::encode_message] adds to a
+  /// message from `source_guid_prefix` to `receiving_participant_count`
+  /// participants. Zero if RTPS messages are not protected.
+  pub fn rtps_protection_overhead(
+    &self,
+    source_guid_prefix: &GuidPrefix,
+    receiving_participant_count: usize,
+  ) -> usize {
+    if self.rtps_not_protected(source_guid_prefix) {
+      0
+    } else {
+      self
+        .crypto
+        .encoded_size_overhead(receiving_participant_count)
+    }
+  }
+
   // Currently only those RTPS messages whose destination is the local participant
   // can be decoded.
   // TODO: add support for other destinations as well?
@@ -0,0 +1,353 @@
+//! Loopback verification of a security configuration.
+//!
+//! The self-test runs the same Authentication and AccessControl steps that
+//! two participants go through when they discover each other, but between two
+//! local instances of the builtin plugins that use the same configuration.
+//! Nothing is sent to the network.
+
+use std::fmt;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  create_security_error_and_log,
+  dds::qos::QosPolicies,
+  discovery::{
+    builtin_endpoint::BuiltinEndpointSet, spdp_participant_data::SpdpDiscoveredParticipantData,
+  },
+  messages::{protocol_version::ProtocolVersion, vendor_id::VendorId},
+  qos,
+  security::{
+    authentication::ValidationOutcome, security_plugins::SecurityPlugins, AccessControlBuiltin,
+    AuthenticationBuiltin, CryptographicBuiltin, SecurityError, SecurityResult,
+  },
+  serialization::pl_cdr_adapters::PlCdrSerialize,
+  structure::guid::{EntityId, GuidPrefix, GUID},
+  RepresentationIdentifier,
+};
+
+/// One step of a security self-test.
+#[derive(Debug, Clone)]
+pub struct SelfTestStep {
+  /// What was checked
+  pub name: &'static str,
+  /// Error message, if the step failed
+  pub result: Result<(), String>,
+}
+
+/// Result of [`DomainParticipantSecurityConfigFiles::self_test`](crate::DomainParticipantSecurityConfigFiles::self_test).
+///
+/// Steps are listed in the order they were run. The test stops at the first
+/// failing step, so a failed report ends with the step that failed.
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+  pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+  /// True if every step succeeded.
+  pub fn passed(&self) -> bool {
+    self.steps.iter().all(|s| s.result.is_ok())
+  }
+
+  /// The first failed step, if any.
+  pub fn failure(&self) -> Option<&SelfTestStep> {
+    self.steps.iter().find(|s| s.result.is_err())
+  }
+
+  // Record the outcome of a step and convert it to an Option for chaining.
+  fn step<T>(&mut self, name: &'static str, result: SecurityResult<T>) -> Option<T> {
+    match result {
+      Ok(t) => {
+        self.steps.push(SelfTestStep {
+          name,
+          result: Ok(()),
+        });
+        Some(t)
+      }
+      Err(e) => {
+        debug!("Security self-test step \"{name}\" failed: {}", e.msg);
+        self.steps.push(SelfTestStep {
+          name,
+          result: Err(e.msg),
+        });
+        None
+      }
+    }
+  }
+}
+
+impl fmt::Display for SelfTestReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for step in &self.steps {
+      match &step.result {
+        Ok(()) => writeln!(f, "[ OK ] {}", step.name)?,
+        Err(msg) => writeln!(f, "[FAIL] {}: {msg}", step.name)?,
+      }
+    }
+    write!(
+      f,
+      "Security self-test {}",
+      if self.passed() { "passed" } else { "failed" }
+    )
+  }
+}
+
+// One side of the loopback
+struct TestParticipant {
+  plugins: SecurityPlugins,
+  guid_prefix: GuidPrefix,
+}
+
+impl TestParticipant {
+  fn new(domain_id: u16, participant_qos: &QosPolicies) -> SecurityResult<Self> {
+    let mut plugins = SecurityPlugins::new(
+      Box::new(AuthenticationBuiltin::new()),
+      Box::new(AccessControlBuiltin::new()),
+      Box::new(CryptographicBuiltin::new()),
+    );
+    let guid_prefix = plugins
+      .validate_local_identity(domain_id, participant_qos, GUID::new_participant_guid())?
+      .prefix;
+    plugins.validate_local_permissions(domain_id, guid_prefix, participant_qos)?;
+    if !plugins.check_create_participant(domain_id, guid_prefix, participant_qos)? {
+      return Err(create_security_error_and_log!(
+        "Access control does not allow creating a participant in domain {domain_id}"
+      ));
+    }
+    let credential_token = plugins.get_permissions_credential_token(guid_prefix)?;
+    let permissions_token = plugins.get_permissions_token(guid_prefix)?;
+    plugins.set_permissions_credential_and_token(
+      guid_prefix,
+      credential_token,
+      permissions_token,
+    )?;
+    Ok(Self {
+      plugins,
+      guid_prefix,
+    })
+  }
+
+  // Stand-in for what the remote side would receive over SPDP
+  fn serialized_participant_data(&self) -> SecurityResult<Vec<u8>> {
+    let data = SpdpDiscoveredParticipantData {
+      updated_time: chrono::Utc::now(),
+      protocol_version: ProtocolVersion::THIS_IMPLEMENTATION,
+      vendor_id: VendorId::THIS_IMPLEMENTATION,
+      expects_inline_qos: false,
+      participant_guid: GUID::new(self.guid_prefix, EntityId::PARTICIPANT),
+      metatraffic_unicast_locators: vec![],
+      metatraffic_multicast_locators: vec![],
+      default_unicast_locators: vec![],
+      default_multicast_locators: vec![],
+      available_builtin_endpoints: BuiltinEndpointSet::from_u32(0),
+      lease_duration: None,
+      manual_liveliness_count: 0,
+      builtin_endpoint_qos: None,
+      entity_name: None,
+      identity_token: Some(self.plugins.get_identity_token(self.guid_prefix)?),
+      permissions_token: Some(self.plugins.get_permissions_token(self.guid_prefix)?),
+      property: None,
+      security_info: None,
+    };
+    data
+      .to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_BE)
+      .map(|b| b.to_vec())
+      .map_err(|e| create_security_error_and_log!("Serializing participant data failed: {e}"))
+  }
+}
+
+// Validate the identity of `remote` at `local`, and return whether `local`
+// should begin the handshake.
+fn validate_remote_identity(
+  local: &mut TestParticipant,
+  remote: &TestParticipant,
+) -> SecurityResult<bool> {
+  let remote_identity_token = remote.plugins.get_identity_token(remote.guid_prefix)?;
+  let (outcome, _auth_request_token) = local.plugins.validate_remote_identity(
+    local.guid_prefix,
+    remote_identity_token,
+    remote.guid_prefix,
+    None,
+  )?;
+  match outcome {
+    ValidationOutcome::PendingHandshakeRequest => Ok(true),
+    ValidationOutcome::PendingHandshakeMessage => Ok(false),
+    other => Err(create_security_error_and_log!(
+      "Unexpected validation outcome {other:?} from validate_remote_identity"
+    )),
+  }
+}
+
+fn validate_remote_permissions(
+  local: &mut TestParticipant,
+  remote: &TestParticipant,
+) -> SecurityResult<()> {
+  let credential_token = local
+    .plugins
+    .get_authenticated_peer_credential_token(remote.guid_prefix)?;
+  let permissions_token = remote.plugins.get_permissions_token(remote.guid_prefix)?;
+  local.plugins.validate_remote_permissions(
+    local.guid_prefix,
+    remote.guid_prefix,
+    &permissions_token,
+    &credential_token,
+  )
+}
+
+pub(crate) fn run(domain_id: u16, property: qos::policy::Property) -> SelfTestReport {
+  let mut report = SelfTestReport::default();
+  run_steps(domain_id, property, &mut report);
+  report
+}
+
+// Returns None as soon as a step fails. The report records which one.
+fn run_steps(
+  domain_id: u16,
+  property: qos::policy::Property,
+  report: &mut SelfTestReport,
+) -> Option<()> {
+  let participant_qos = QosPolicies {
+    property: Some(property),
+    ..Default::default()
+  };
+
+  // Both sides use the same configuration, but have their own plugin state
+  let (mut a, mut b) = report.step(
+    "Load identity and permissions",
+    TestParticipant::new(domain_id, &participant_qos)
+      .and_then(|a| Ok((a, TestParticipant::new(domain_id, &participant_qos)?))),
+  )?;
+
+  let a_initiates = report.step(
+    "Validate remote identity",
+    validate_remote_identity(&mut a, &b).and_then(|a_initiates| {
+      let b_initiates = validate_remote_identity(&mut b, &a)?;
+      if a_initiates == b_initiates {
+        Err(create_security_error_and_log!(
+          "Both or neither participant wants to begin the handshake"
+        ))
+      } else {
+        Ok(a_initiates)
+      }
+    }),
+  )?;
+  let (initiator, replier) = if a_initiates {
+    (&mut a, &mut b)
+  } else {
+    (&mut b, &mut a)
+  };
+
+  let request = report.step(
+    "Begin handshake request",
+    initiator.serialized_participant_data().and_then(|pdata| {
+      initiator
+        .plugins
+        .begin_handshake_request(initiator.guid_prefix, replier.guid_prefix, pdata)
+        .map(|(_outcome, token)| token)
+    }),
+  )?;
+
+  let reply = report.step(
+    "Begin handshake reply",
+    replier.serialized_participant_data().and_then(|pdata| {
+      replier
+        .plugins
+        .begin_handshake_reply(replier.guid_prefix, initiator.guid_prefix, request, pdata)
+        .map(|(_outcome, token)| token)
+    }),
+  )?;
+
+  let final_message = report.step(
+    "Process handshake reply",
+    initiator
+      .plugins
+      .process_handshake(replier.guid_prefix, reply)
+      .and_then(|(_outcome, token)| {
+        token
+          .ok_or_else(|| create_security_error_and_log!("No handshake final message was produced"))
+      }),
+  )?;
+
+  report.step(
+    "Process handshake final message",
+    replier
+      .plugins
+      .process_handshake(initiator.guid_prefix, final_message)
+      .map(|_| ()),
+  )?;
+
+  report.step(
+    "Compare shared secrets",
+    initiator
+      .plugins
+      .get_shared_secret(replier.guid_prefix)
+      .and_then(|initiator_secret| {
+        let replier_secret = replier.plugins.get_shared_secret(initiator.guid_prefix)?;
+        if initiator_secret.shared_secret == replier_secret.shared_secret
+          && initiator_secret.challenge1 == replier_secret.challenge1
+          && initiator_secret.challenge2 == replier_secret.challenge2
+        {
+          Ok(())
+        } else {
+          Err(create_security_error_and_log!(
+            "The participants derived different shared secrets"
+          ))
+        }
+      }),
+  )?;
+
+  report.step(
+    "Validate remote permissions",
+    validate_remote_permissions(initiator, replier)
+      .and_then(|()| validate_remote_permissions(replier, initiator)),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::security::config::{DomainParticipantSecurityConfigFiles, PrivateSigningKey};
+
+  const CONFIG_DIR: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/security_configuration_files"
+  );
+
+  fn example_config() -> DomainParticipantSecurityConfigFiles {
+    DomainParticipantSecurityConfigFiles::with_ros_default_names(
+      CONFIG_DIR,
+      "password123".to_string(),
+    )
+  }
+
+  #[test]
+  fn self_test_passes_with_example_configuration() {
+    let report = example_config().self_test(0);
+    assert!(report.passed(), "{report}");
+    assert!(report.failure().is_none());
+  }
+
+  #[test]
+  fn self_test_reports_mismatched_private_key() {
+    let mut config = example_config();
+    config.participant_identity_private_key = PrivateSigningKey::Files {
+      file_path: format!("{CONFIG_DIR}/permissions_ca_private_key.pem").into(),
+      file_password: "password123".to_string(),
+    };
+    let report = config.self_test(0);
+    assert!(!report.passed());
+    let failure = report.failure().unwrap();
+    assert_eq!(Some(failure.name), report.steps.last().map(|s| s.name));
+  }
+
+  #[test]
+  fn self_test_reports_domain_without_permissions() {
+    let report = example_config().self_test(1);
+    assert!(!report.passed());
+    assert_eq!(
+      report.failure().map(|s| s.name),
+      Some("Load identity and permissions")
+    );
+  }
+}
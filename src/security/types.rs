@@ -192,6 +192,16 @@ impl QosPolicies {
         get_optional_property(&properties_or_binary_properties.value, property_name)
       })
   }
+  // Values of all properties with the given name, in order
+  pub(super) fn get_all_properties(&self, property_name: &str) -> Vec<String> {
+    self
+      .property
+      .iter()
+      .flat_map(|properties_or_binary_properties| &properties_or_binary_properties.value)
+      .filter(|Property { name, .. }| name.eq(property_name))
+      .map(|Property { value, .. }| value.clone())
+      .collect()
+  }
   pub(super) fn get_property(&self, property_name: &str) -> SecurityResult<String> {
     self
       .property
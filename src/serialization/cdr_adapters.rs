@@ -23,6 +23,12 @@ pub use super::*;
 /// [`CdrSerializer`] cannot directly implement the trait itself, because
 /// [`CdrSerializer`] has the type parameter BO open, and the adapter needs to
 /// be bi-endian.
+///
+/// The byte order `BO` of the output is chosen by the application, and the
+/// payload is marked `CDR_LE` or `CDR_BE` accordingly, whatever the byte order
+/// of the host is. The default is `LittleEndian`. Use
+/// `CDRSerializerAdapter<D, byteorder::NativeEndian>` to avoid byte swapping
+/// on a big-endian host. Readers decode both byte orders.
 pub struct CDRSerializerAdapter<D, BO = LittleEndian>
 where
   BO: ByteOrder,
@@ -39,17 +45,10 @@ where
   type Error = Error;
 
   fn output_encoding() -> RepresentationIdentifier {
-    if TypeId::of::<BO>() == TypeId::of::<LittleEndian>() {
-      RepresentationIdentifier::CDR_LE
-    } else if TypeId::of::<BO>() == TypeId::of::<BigEndian>() {
-      RepresentationIdentifier::CDR_BE
-    } else {
-      // The trait ByteOrder is sealed, so there are no implementations
-      // outside the byteorder package, which defines only LittleEndian
-      // and BigEndian impls.
-      // If you end up here, please explain how did you find a third implementation.
-      unreachable!()
-    }
+    encoding_for_byte_order::<BO>(
+      RepresentationIdentifier::CDR_LE,
+      RepresentationIdentifier::CDR_BE,
+    )
   }
 
   fn to_bytes(value: &D) -> Result<Bytes> {
@@ -74,6 +73,24 @@ where
   }
 }
 
+// Picks the little- or big-endian variant of an encoding to match BO.
+pub(crate) fn encoding_for_byte_order<BO: ByteOrder + 'static>(
+  little_endian: RepresentationIdentifier,
+  big_endian: RepresentationIdentifier,
+) -> RepresentationIdentifier {
+  if TypeId::of::<BO>() == TypeId::of::<LittleEndian>() {
+    little_endian
+  } else if TypeId::of::<BO>() == TypeId::of::<BigEndian>() {
+    big_endian
+  } else {
+    // The trait ByteOrder is sealed, so there are no implementations
+    // outside the byteorder package, which defines only LittleEndian
+    // and BigEndian impls.
+    // If you end up here, please explain how did you find a third implementation.
+    unreachable!()
+  }
+}
+
 /// Serialize
 pub fn to_writer_with_rep_id<T, W>(
   writer: W,
@@ -103,9 +120,10 @@ pub struct CDRDeserializerAdapter<D> {
   phantom: PhantomData<D>,
 }
 
-const REPR_IDS: [RepresentationIdentifier; 3] = [
+const REPR_IDS: [RepresentationIdentifier; 4] = [
   RepresentationIdentifier::CDR_BE,
   RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::PL_CDR_BE,
   RepresentationIdentifier::PL_CDR_LE,
 ];
 
@@ -283,3 +301,97 @@ where
     ))),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use serde::Deserialize;
+
+  use super::*;
+  use crate::dds::key::{Key, KeyHash};
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct Sample {
+    id: u16,
+    value: u32,
+    ratio: f64,
+    name: String,
+  }
+
+  fn sample() -> Sample {
+    Sample {
+      id: 0x0102,
+      value: 0x0304_0506,
+      ratio: 1.0,
+      name: "ab".to_string(),
+    }
+  }
+
+  // Wire bytes are fixed by the encoding, not by the byte order of the host.
+  const SAMPLE_LE: [u8; 23] = [
+    0x02, 0x01, // id
+    0x00, 0x00, // padding
+    0x06, 0x05, 0x04, 0x03, // value
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f, // ratio
+    0x03, 0x00, 0x00, 0x00, b'a', b'b', 0x00, // name, with length and NUL
+  ];
+  const SAMPLE_BE: [u8; 23] = [
+    0x01, 0x02, // id
+    0x00, 0x00, // padding
+    0x03, 0x04, 0x05, 0x06, // value
+    0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // ratio
+    0x00, 0x00, 0x00, 0x03, b'a', b'b', 0x00, // name, with length and NUL
+  ];
+
+  #[test]
+  fn cdr_adapter_byte_order() {
+    type LeAdapter = CDRSerializerAdapter<Sample, LittleEndian>;
+    type BeAdapter = CDRSerializerAdapter<Sample, BigEndian>;
+    use no_key::{Decode, SerializerAdapter};
+
+    assert_eq!(
+      LeAdapter::output_encoding(),
+      RepresentationIdentifier::CDR_LE
+    );
+    assert_eq!(
+      BeAdapter::output_encoding(),
+      RepresentationIdentifier::CDR_BE
+    );
+    let le = LeAdapter::to_bytes(&sample()).unwrap();
+    let be = BeAdapter::to_bytes(&sample()).unwrap();
+    assert_eq!(le[..], SAMPLE_LE);
+    assert_eq!(be[..], SAMPLE_BE);
+
+    let decoder = CdrDeserializeDecoder::<Sample>(PhantomData);
+    for (bytes, encoding) in [
+      (&SAMPLE_LE[..], RepresentationIdentifier::CDR_LE),
+      (&SAMPLE_BE[..], RepresentationIdentifier::CDR_BE),
+      (&SAMPLE_BE[..], RepresentationIdentifier::PL_CDR_BE),
+    ] {
+      assert!(
+        <CDRDeserializerAdapter<Sample> as no_key::DeserializerAdapter<Sample>>::supported_encodings()
+          .contains(&encoding)
+      );
+      assert_eq!(
+        decoder.clone().decode_bytes(bytes, encoding).unwrap(),
+        sample()
+      );
+    }
+  }
+
+  #[test]
+  fn key_hash_is_big_endian() {
+    // RTPS spec v2.5 Section 9.6.4.8: the CDR big-endian key, padded with zeros
+    let mut expected = [0; 16];
+    expected[..4].copy_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+    assert_eq!(
+      0x0102_0304_i32.hash_key(false),
+      KeyHash::from_bytes(expected)
+    );
+
+    // A String has no maximum size, so the MD5 digest of the key is used.
+    assert_eq!(
+      "a".to_string().hash_key(false),
+      KeyHash::from_bytes(*md5::compute([0x00, 0x00, 0x00, 0x02, b'a', 0x00]))
+    );
+  }
+}
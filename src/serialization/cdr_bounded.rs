@@ -0,0 +1,401 @@
+//! CDR encoding of IDL bounded sequences, bounded strings and arrays.
+//!
+//! * `sequence<T, N>` is [`BoundedSeq<T, N>`]
+//! * `string<N>` is [`BoundedString<N>`]
+//! * `T name[N]` is `[T; N]`
+//!
+//! Bounded types are encoded like their unbounded counterparts, i.e. with a
+//! length prefix. Their values cannot be constructed over the bound, and
+//! deserialization fails if a received value exceeds it. This way a Writer
+//! does not send data that a peer using the IDL type would reject, and a
+//! Reader does not accept it either.
+//!
+//! Arrays are encoded without a length prefix. Serde handles arrays of up to
+//! 32 elements this way on its own. Longer arrays need the
+//! [`fixed_array`] module as `#[serde(with = ...)]`.
+//!
+//! # Examples
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use rustdds::serialization::{
+//!   cdr_bounded::{fixed_array, BoundedSeq, BoundedString},
+//!   from_bytes, to_vec,
+//! };
+//! use byteorder::LittleEndian;
+//!
+//! // struct Sensor {
+//! //   string<8> name;
+//! //   sequence<short, 4> readings;
+//! //   octet calibration[64];
+//! // };
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Sensor {
+//!   name: BoundedString<8>,
+//!   readings: BoundedSeq<i16, 4>,
+//!   #[serde(with = "fixed_array")]
+//!   calibration: [u8; 64],
+//! }
+//!
+//! let sensor = Sensor {
+//!   name: "left".try_into().unwrap(),
+//!   readings: vec![1, 2, 3].try_into().unwrap(),
+//!   calibration: [7; 64],
+//! };
+//! let bytes = to_vec::<_, LittleEndian>(&sensor).unwrap();
+//! // name: 4 + 5, padding 3, readings: 4 + 3 * 2, calibration: 64
+//! assert_eq!(bytes.len(), 86);
+//! assert_eq!(from_bytes::<Sensor, LittleEndian>(&bytes).unwrap().0, sensor);
+//!
+//! // Bounds are checked on construction
+//! assert!(BoundedString::<8>::try_from("much too long").is_err());
+//! ```
+
+use std::{fmt, marker::PhantomData, ops::Deref};
+
+use serde::{
+  de::{self, SeqAccess, Visitor},
+  ser::{SerializeSeq, SerializeTuple},
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+use cdr_encoding_size::{CdrEncodingMaxSize, CdrEncodingSize};
+
+/// A value was longer than the bound of its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundExceeded {
+  pub bound: usize,
+  pub length: usize,
+}
+
+impl fmt::Display for BoundExceeded {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Length {} exceeds bound {}", self.length, self.bound)
+  }
+}
+
+impl std::error::Error for BoundExceeded {}
+
+fn check_bound(bound: usize, length: usize) -> Result<(), BoundExceeded> {
+  if length > bound {
+    Err(BoundExceeded { bound, length })
+  } else {
+    Ok(())
+  }
+}
+
+/// IDL `sequence<T, N>`: a sequence of at most `N` elements.
+///
+/// The contents can be read through `Deref`, but not modified in place, so
+/// that the bound always holds.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedSeq<T, const N: usize>(Vec<T>);
+
+impl<T, const N: usize> BoundedSeq<T, N> {
+  pub fn new() -> Self {
+    Self(Vec::new())
+  }
+
+  /// Appends an element, unless the sequence is already full.
+  pub fn push(&mut self, value: T) -> Result<(), BoundExceeded> {
+    check_bound(N, self.0.len() + 1)?;
+    self.0.push(value);
+    Ok(())
+  }
+
+  pub fn into_inner(self) -> Vec<T> {
+    self.0
+  }
+}
+
+impl<T, const N: usize> Default for BoundedSeq<T, N> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, const N: usize> Deref for BoundedSeq<T, N> {
+  type Target = Vec<T>;
+
+  fn deref(&self) -> &Vec<T> {
+    &self.0
+  }
+}
+
+impl<T, const N: usize> TryFrom<Vec<T>> for BoundedSeq<T, N> {
+  type Error = BoundExceeded;
+
+  fn try_from(value: Vec<T>) -> Result<Self, BoundExceeded> {
+    check_bound(N, value.len())?;
+    Ok(Self(value))
+  }
+}
+
+impl<T, const N: usize> From<BoundedSeq<T, N>> for Vec<T> {
+  fn from(seq: BoundedSeq<T, N>) -> Self {
+    seq.0
+  }
+}
+
+impl<T: Serialize, const N: usize> Serialize for BoundedSeq<T, N> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+    for element in &self.0 {
+      seq.serialize_element(element)?;
+    }
+    seq.end()
+  }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for BoundedSeq<T, N> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct SeqVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for SeqVisitor<T, N> {
+      type Value = BoundedSeq<T, N>;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence of at most {N} elements")
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // Fail before allocating, if the length is known
+        let hint = seq.size_hint().unwrap_or(0);
+        if hint > N {
+          return Err(de::Error::invalid_length(hint, &self));
+        }
+        let mut elements = Vec::with_capacity(hint);
+        while let Some(element) = seq.next_element()? {
+          if elements.len() == N {
+            return Err(de::Error::invalid_length(N + 1, &self));
+          }
+          elements.push(element);
+        }
+        Ok(BoundedSeq(elements))
+      }
+    }
+
+    deserializer.deserialize_seq(SeqVisitor::<T, N>(PhantomData))
+  }
+}
+
+impl<T: CdrEncodingSize, const N: usize> CdrEncodingSize for BoundedSeq<T, N> {
+  fn cdr_encoding_max_size() -> CdrEncodingMaxSize {
+    // length prefix and elements
+    CdrEncodingMaxSize::Bytes(4) + T::cdr_encoding_max_size() * N
+  }
+}
+
+/// IDL `string<N>`: a string of at most `N` bytes, not counting the
+/// terminating NUL.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedString<const N: usize>(String);
+
+impl<const N: usize> BoundedString<N> {
+  pub fn new() -> Self {
+    Self(String::new())
+  }
+
+  pub fn into_inner(self) -> String {
+    self.0
+  }
+}
+
+impl<const N: usize> Deref for BoundedString<N> {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl<const N: usize> fmt::Display for BoundedString<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl<const N: usize> TryFrom<String> for BoundedString<N> {
+  type Error = BoundExceeded;
+
+  fn try_from(value: String) -> Result<Self, BoundExceeded> {
+    check_bound(N, value.len())?;
+    Ok(Self(value))
+  }
+}
+
+impl<const N: usize> TryFrom<&str> for BoundedString<N> {
+  type Error = BoundExceeded;
+
+  fn try_from(value: &str) -> Result<Self, BoundExceeded> {
+    Self::try_from(value.to_string())
+  }
+}
+
+impl<const N: usize> From<BoundedString<N>> for String {
+  fn from(s: BoundedString<N>) -> Self {
+    s.0
+  }
+}
+
+impl<const N: usize> Serialize for BoundedString<N> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.0)
+  }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for BoundedString<N> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Self::try_from(s).map_err(de::Error::custom)
+  }
+}
+
+impl<const N: usize> CdrEncodingSize for BoundedString<N> {
+  fn cdr_encoding_max_size() -> CdrEncodingMaxSize {
+    // length prefix, characters and NUL
+    CdrEncodingMaxSize::Bytes(4 + N + 1)
+  }
+}
+
+/// (De)serializes `[T; N]` of any length as a CDR array, i.e. without a
+/// length prefix. Use as `#[serde(with = "fixed_array")]`.
+///
+/// Serde does this on its own for arrays of up to 32 elements.
+pub mod fixed_array {
+  use super::*;
+
+  pub fn serialize<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+    T: Serialize,
+  {
+    let mut tuple = serializer.serialize_tuple(N)?;
+    for element in array {
+      tuple.serialize_element(element)?;
+    }
+    tuple.end()
+  }
+
+  pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+  where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+  {
+    struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+      type Value = [T; N];
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of {N} elements")
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+        let mut elements = Vec::with_capacity(N);
+        for i in 0..N {
+          elements.push(
+            seq
+              .next_element()?
+              .ok_or_else(|| de::Error::invalid_length(i, &self))?,
+          );
+        }
+        elements
+          .try_into()
+          .map_err(|_| de::Error::custom("array length mismatch"))
+      }
+    }
+
+    deserializer.deserialize_tuple(N, ArrayVisitor::<T, N>(PhantomData))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use byteorder::{BigEndian, LittleEndian};
+
+  use super::*;
+  use crate::serialization::{from_bytes, to_vec};
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Arrays {
+    small: [u16; 3],
+    #[serde(with = "fixed_array")]
+    large: [u32; 40],
+  }
+
+  #[test]
+  fn arrays_have_no_length_prefix() {
+    let mut large = [0; 40];
+    large[0] = 0x0102_0304;
+    let value = Arrays {
+      small: [1, 2, 3],
+      large,
+    };
+    let bytes = to_vec::<_, BigEndian>(&value).unwrap();
+    // 3 shorts, padding 2, 40 longs
+    assert_eq!(bytes.len(), 6 + 2 + 160);
+    assert_eq!(&bytes[..10], &[0, 1, 0, 2, 0, 3, 0, 0, 1, 2]);
+    assert_eq!(from_bytes::<Arrays, BigEndian>(&bytes).unwrap().0, value);
+  }
+
+  #[test]
+  fn bounded_seq_encoding() {
+    let seq: BoundedSeq<u16, 3> = vec![1, 2].try_into().unwrap();
+    let bytes = to_vec::<_, LittleEndian>(&seq).unwrap();
+    assert_eq!(bytes, [2, 0, 0, 0, 1, 0, 2, 0]);
+    let (decoded, _) = from_bytes::<BoundedSeq<u16, 3>, LittleEndian>(&bytes).unwrap();
+    assert_eq!(decoded, seq);
+  }
+
+  #[test]
+  fn bounded_seq_rejects_too_long() {
+    assert_eq!(
+      BoundedSeq::<u8, 2>::try_from(vec![1, 2, 3]),
+      Err(BoundExceeded {
+        bound: 2,
+        length: 3
+      })
+    );
+    let mut seq = BoundedSeq::<u8, 1>::new();
+    seq.push(1).unwrap();
+    assert!(seq.push(2).is_err());
+    assert_eq!(*seq, vec![1]);
+
+    // Sent by a peer that does not respect the bound
+    let bytes = to_vec::<_, LittleEndian>(&vec![1u8, 2, 3]).unwrap();
+    assert!(from_bytes::<BoundedSeq<u8, 2>, LittleEndian>(&bytes).is_err());
+    assert!(from_bytes::<BoundedSeq<u8, 3>, LittleEndian>(&bytes).is_ok());
+  }
+
+  #[test]
+  fn bounded_string() {
+    let s: BoundedString<5> = "hello".try_into().unwrap();
+    let bytes = to_vec::<_, LittleEndian>(&s).unwrap();
+    assert_eq!(bytes, to_vec::<_, LittleEndian>(&"hello").unwrap());
+    assert_eq!(
+      from_bytes::<BoundedString<5>, LittleEndian>(&bytes)
+        .unwrap()
+        .0,
+      s
+    );
+    assert!(from_bytes::<BoundedString<4>, LittleEndian>(&bytes).is_err());
+    assert!(BoundedString::<4>::try_from("hello").is_err());
+    assert_eq!(&*s, "hello");
+  }
+
+  #[test]
+  fn bounded_encoding_size() {
+    assert_eq!(
+      BoundedString::<8>::cdr_encoding_max_size(),
+      CdrEncodingMaxSize::Bytes(13)
+    );
+    assert_eq!(
+      BoundedSeq::<u16, 4>::cdr_encoding_max_size(),
+      CdrEncodingMaxSize::Bytes(12)
+    );
+    assert_eq!(
+      BoundedSeq::<String, 4>::cdr_encoding_max_size(),
+      CdrEncodingMaxSize::Unbounded
+    );
+  }
+}
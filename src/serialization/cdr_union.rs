@@ -0,0 +1,291 @@
+//! CDR encoding of IDL discriminated unions.
+//!
+//! An IDL union
+//!
+//! ```idl
+//! union Shape switch (short) {
+//!   case 1: double radius;
+//!   case 2: Rectangle rect;
+//! };
+//! ```
+//!
+//! is encoded in CDR as the discriminator, followed by the selected member.
+//! Both are aligned as if they were consecutive members of a struct.
+//!
+//! Serde's default encoding of a Rust enum writes the variant index as a
+//! `u32`, which matches an IDL union only if the discriminator is a `long`
+//! or an enum, and the case labels happen to be 0, 1, 2, ... in declaration
+//! order. The [`cdr_union!`](crate::cdr_union) macro defines an enum that
+//! writes the declared discriminator type and case labels instead.
+
+// The macro expands in the user's crate, which may not depend on serde
+// directly.
+#[doc(hidden)]
+pub mod __private {
+  pub use serde;
+}
+
+/// Defines a Rust enum that (de)serializes like an IDL discriminated union.
+///
+/// Each variant holds one union member and is labelled with the value of the
+/// discriminator that selects it. The discriminator type comes after the enum
+/// name. It must implement `Serialize`, `Deserialize`, `PartialEq` and
+/// `Debug`, and the labels are expressions of that type. IDL enum
+/// discriminators can be represented by a fieldless Rust enum with a serde
+/// derive, because CDR encodes both as a `u32` index.
+///
+/// Union members can themselves be unions, structs or sequences. Unknown
+/// discriminator values are a deserialization error: `default:` cases are not
+/// supported.
+///
+/// # Examples
+///
+/// ```
+/// use rustdds::{cdr_union, serialization::{from_bytes, to_vec}};
+/// use byteorder::LittleEndian;
+///
+/// // union Shape switch (short) {
+/// //   case 1: double radius;
+/// //   case 2: string name;
+/// // };
+/// cdr_union! {
+///   #[derive(Debug, Clone, PartialEq)]
+///   pub enum Shape: i16 {
+///     1 => Radius(f64),
+///     2 => Name(String),
+///   }
+/// }
+///
+/// let bytes = to_vec::<_, LittleEndian>(&Shape::Radius(1.5)).unwrap();
+/// // short discriminator, padding, and the 8-aligned double
+/// assert_eq!(&bytes[..4], &[1, 0, 0, 0]);
+/// assert_eq!(bytes.len(), 16);
+///
+/// let (shape, _) = from_bytes::<Shape, LittleEndian>(&bytes).unwrap();
+/// assert_eq!(shape, Shape::Radius(1.5));
+/// ```
+#[macro_export]
+macro_rules! cdr_union {
+  (
+    $(#[$meta:meta])*
+    $vis:vis enum $name:ident : $discriminator:ty {
+      $(
+        $(#[$variant_meta:meta])*
+        $label:expr => $variant:ident($member:ty)
+      ),+ $(,)?
+    }
+  ) => {
+    $(#[$meta])*
+    $vis enum $name {
+      $(
+        $(#[$variant_meta])*
+        $variant($member),
+      )+
+    }
+
+    impl $crate::serialization::cdr_union::__private::serde::Serialize for $name {
+      fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+      where
+        S: $crate::serialization::cdr_union::__private::serde::Serializer,
+      {
+        use $crate::serialization::cdr_union::__private::serde::ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(2)?;
+        match self {
+          $(
+            $name::$variant(member) => {
+              let discriminator: $discriminator = $label;
+              tuple.serialize_element(&discriminator)?;
+              tuple.serialize_element(member)?;
+            }
+          )+
+        }
+        tuple.end()
+      }
+    }
+
+    impl<'de> $crate::serialization::cdr_union::__private::serde::Deserialize<'de> for $name {
+      fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+      where
+        D: $crate::serialization::cdr_union::__private::serde::Deserializer<'de>,
+      {
+        use $crate::serialization::cdr_union::__private::serde::de;
+
+        struct UnionVisitor;
+
+        impl<'de> de::Visitor<'de> for UnionVisitor {
+          type Value = $name;
+
+          fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            f.write_str(concat!("union ", stringify!($name)))
+          }
+
+          fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<$name, A::Error>
+          where
+            A: de::SeqAccess<'de>,
+          {
+            let discriminator: $discriminator = seq
+              .next_element()?
+              .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            $(
+              if discriminator == $label {
+                return seq
+                  .next_element()?
+                  .map($name::$variant)
+                  .ok_or_else(|| de::Error::invalid_length(1, &self));
+              }
+            )+
+            Err(de::Error::custom(format!(
+              "Unknown discriminator {:?} for union {}",
+              discriminator,
+              stringify!($name)
+            )))
+          }
+        }
+
+        deserializer.deserialize_tuple(2, UnionVisitor)
+      }
+    }
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use byteorder::{BigEndian, LittleEndian};
+  use serde::{Deserialize, Serialize};
+
+  use crate::serialization::{from_bytes, to_vec};
+
+  // enum Kind { PLAIN, NESTED };
+  #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+  enum Kind {
+    Plain,
+    Nested,
+  }
+
+  // union Inner switch (short) {
+  //   case 1: long a;
+  //   case 2: double b;
+  // };
+  cdr_union! {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Inner: i16 {
+      1 => A(i32),
+      2 => B(f64),
+    }
+  }
+
+  // union Outer switch (octet) {
+  //   case 0: Inner inner;
+  //   case 7: string s;
+  // };
+  cdr_union! {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Outer: u8 {
+      0 => Inner(Inner),
+      7 => S(String),
+    }
+  }
+
+  // union Tagged switch (Kind) {
+  //   case PLAIN: unsigned short plain;
+  //   case NESTED: Outer nested;
+  // };
+  cdr_union! {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Tagged: Kind {
+      Kind::Plain => Plain(u16),
+      Kind::Nested => Nested(Outer),
+    }
+  }
+
+  // struct Holder {
+  //   octet tag;
+  //   Outer value;
+  //   octet trailer;
+  // };
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Holder {
+    tag: u8,
+    value: Outer,
+    trailer: u8,
+  }
+
+  fn round_trip_le<T>(value: &T, expected: &[u8])
+  where
+    T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+  {
+    let bytes = to_vec::<T, LittleEndian>(value).unwrap();
+    assert_eq!(bytes, expected);
+    let (decoded, consumed) = from_bytes::<T, LittleEndian>(&bytes).unwrap();
+    assert_eq!(&decoded, value);
+    assert_eq!(consumed, expected.len());
+  }
+
+  #[test]
+  fn nested_union_layout() {
+    // octet discriminator 0, padding to 2, short discriminator 2, padding to 8,
+    // double 1.5
+    round_trip_le(
+      &Outer::Inner(Inner::B(1.5)),
+      &[
+        0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x3f,
+      ],
+    );
+    // octet discriminator 0, padding, short discriminator 1, long 5
+    round_trip_le(
+      &Outer::Inner(Inner::A(5)),
+      &[0x00, 0x00, 0x01, 0x00, 0x05, 0x00, 0x00, 0x00],
+    );
+    // octet discriminator 7, padding to 4, string length 3, "hi\0"
+    round_trip_le(
+      &Outer::S("hi".to_string()),
+      &[
+        0x07, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, b'h', b'i', 0x00,
+      ],
+    );
+  }
+
+  #[test]
+  fn union_in_struct_layout() {
+    round_trip_le(
+      &Holder {
+        tag: 0xaa,
+        value: Outer::Inner(Inner::A(-1)),
+        trailer: 0xbb,
+      },
+      // tag, octet discriminator, short discriminator, long, trailer
+      &[0xaa, 0x00, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff, 0xbb],
+    );
+  }
+
+  #[test]
+  fn enum_discriminator() {
+    // enum discriminator is encoded as an unsigned long
+    round_trip_le(
+      &Tagged::Plain(0x1234),
+      &[0x00, 0x00, 0x00, 0x00, 0x34, 0x12],
+    );
+    round_trip_le(
+      &Tagged::Nested(Outer::S(String::new())),
+      &[
+        0x01, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, //
+        0x01, 0x00, 0x00, 0x00, 0x00,
+      ],
+    );
+  }
+
+  #[test]
+  fn big_endian() {
+    let bytes = to_vec::<Inner, BigEndian>(&Inner::A(1)).unwrap();
+    assert_eq!(bytes, [0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    let (decoded, _) = from_bytes::<Inner, BigEndian>(&bytes).unwrap();
+    assert_eq!(decoded, Inner::A(1));
+  }
+
+  #[test]
+  fn unknown_discriminator() {
+    let bytes = [0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert!(from_bytes::<Inner, LittleEndian>(&bytes).is_err());
+  }
+}
@@ -0,0 +1,369 @@
+//! Optional compression of serialized payloads between RustDDS endpoints.
+//!
+//! A DataWriter configured with [`PayloadCompression`] (see
+//! [`Publisher::set_payload_compression`](crate::Publisher::set_payload_compression))
+//! advertises the algorithm in Discovery. DataReaders advertise the
+//! algorithms they can decompress, so the DataWriter compresses samples only
+//! when every matched DataReader can decompress them. DataReaders of other DDS
+//! implementations never advertise any, so they either receive uncompressed
+//! samples or are not matched at all, according to [`CompressionFallback`].
+//!
+//! Compression is applied to the serialized sample, before it is
+//! fragmented, so fragmentation is decided by the compressed size.
+//!
+//! A compressed payload is marked by a flag in the representation options of
+//! the SerializedPayload, and starts with a header:
+//!
+//! | Bytes | Contents                                       |
+//! |-------|------------------------------------------------|
+//! | 0     | Algorithm id, see [`PayloadCompressor::id`]    |
+//! | 1     | Number of padding bytes at the end             |
+//! | 2..4  | Reserved, zero                                 |
+//! | 4..8  | Uncompressed size, unsigned 32-bit big-endian  |
+//!
+//! The compressed data is padded to a multiple of 4 bytes, because RTPS pads
+//! the serialized payload to that alignment anyway.
+//!
+//! The uncompressed size is limited to [`MAX_UNCOMPRESSED_SIZE`], because
+//! the DataReader allocates that much before decompressing. Larger samples
+//! are sent uncompressed.
+//!
+//! The algorithms "lz4" and "zstd" are available with the cargo features of
+//! the same name.
+
+use std::{fmt, io, sync::Arc};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+  messages::submessages::elements::serialized_payload::SerializedPayload,
+  serialization::padding_needed_for_alignment_4,
+};
+
+/// A compression algorithm for serialized payloads.
+pub trait PayloadCompressor: Send + Sync {
+  /// Name of the algorithm, as advertised in Discovery, e.g. "lz4". Both ends
+  /// must use the same name.
+  fn name(&self) -> &str;
+
+  /// Identifies the algorithm in compressed payloads. Values below 128 are
+  /// reserved for algorithms provided by RustDDS.
+  fn id(&self) -> u8;
+
+  fn compress(&self, data: &Bytes) -> io::Result<Bytes>;
+
+  /// `uncompressed_size` is the size of the original data.
+  fn decompress(&self, data: &Bytes, uncompressed_size: usize) -> io::Result<Bytes>;
+}
+
+/// LZ4 block compression. Requires the "lz4" feature.
+#[cfg(feature = "lz4")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lz4Compressor;
+
+#[cfg(feature = "lz4")]
+impl PayloadCompressor for Lz4Compressor {
+  fn name(&self) -> &str {
+    "lz4"
+  }
+
+  fn id(&self) -> u8 {
+    1
+  }
+
+  fn compress(&self, data: &Bytes) -> io::Result<Bytes> {
+    Ok(Bytes::from(lz4_flex::block::compress(data)))
+  }
+
+  fn decompress(&self, data: &Bytes, uncompressed_size: usize) -> io::Result<Bytes> {
+    lz4_flex::block::decompress(data, uncompressed_size)
+      .map(Bytes::from)
+      .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+}
+
+/// Zstandard compression. Requires the "zstd" feature.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy)]
+pub struct ZstdCompressor {
+  level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdCompressor {
+  /// Compression level, see [`zstd::bulk::compress`]. Does not need to match
+  /// between DataWriter and DataReader.
+  pub fn with_level(level: i32) -> Self {
+    Self { level }
+  }
+}
+
+#[cfg(feature = "zstd")]
+impl Default for ZstdCompressor {
+  fn default() -> Self {
+    Self::with_level(zstd::DEFAULT_COMPRESSION_LEVEL)
+  }
+}
+
+#[cfg(feature = "zstd")]
+impl PayloadCompressor for ZstdCompressor {
+  fn name(&self) -> &str {
+    "zstd"
+  }
+
+  fn id(&self) -> u8 {
+    2
+  }
+
+  fn compress(&self, data: &Bytes) -> io::Result<Bytes> {
+    zstd::bulk::compress(data, self.level).map(Bytes::from)
+  }
+
+  fn decompress(&self, data: &Bytes, uncompressed_size: usize) -> io::Result<Bytes> {
+    zstd::bulk::decompress(data, uncompressed_size).map(Bytes::from)
+  }
+}
+
+// Decompressors that every DataReader has.
+pub(crate) fn builtin_decompressors() -> Vec<Arc<dyn PayloadCompressor>> {
+  vec![
+    #[cfg(feature = "lz4")]
+    Arc::new(Lz4Compressor),
+    #[cfg(feature = "zstd")]
+    Arc::new(ZstdCompressor::default()),
+  ]
+}
+
+/// What a compressing DataWriter does with DataReaders that cannot decompress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionFallback {
+  /// Match them, and send uncompressed samples to everyone while they are
+  /// matched. Samples already in the DataWriter history are decompressed.
+  #[default]
+  SendUncompressed,
+  /// Do not match them. Compressing DataReaders also refuse to match with
+  /// the DataWriter.
+  DoNotMatch,
+}
+
+/// Payload compression configuration of a DataWriter.
+#[derive(Clone)]
+pub struct PayloadCompression {
+  compressor: Arc<dyn PayloadCompressor>,
+  fallback: CompressionFallback,
+}
+
+impl PayloadCompression {
+  pub fn new(compressor: impl PayloadCompressor + 'static) -> Self {
+    Self {
+      compressor: Arc::new(compressor),
+      fallback: CompressionFallback::default(),
+    }
+  }
+
+  pub fn fallback(self, fallback: CompressionFallback) -> Self {
+    Self { fallback, ..self }
+  }
+
+  pub fn compressor(&self) -> &Arc<dyn PayloadCompressor> {
+    &self.compressor
+  }
+
+  pub fn get_fallback(&self) -> CompressionFallback {
+    self.fallback
+  }
+}
+
+impl fmt::Debug for PayloadCompression {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PayloadCompression")
+      .field("compressor", &self.compressor.name())
+      .field("fallback", &self.fallback)
+      .finish()
+  }
+}
+
+/// Largest sample that is compressed, in bytes. Compressed payloads claiming
+/// a larger uncompressed size are rejected without decompressing.
+pub const MAX_UNCOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+// Flag in SerializedPayload::representation_options[0]
+const COMPRESSED_FLAG: u8 = 0x80;
+const HEADER_LEN: usize = 8;
+
+pub(crate) fn is_compressed(payload: &SerializedPayload) -> bool {
+  payload.representation_options[0] & COMPRESSED_FLAG != 0
+}
+
+pub(crate) fn clear_compressed_flag(payload: &mut SerializedPayload) {
+  payload.representation_options[0] &= !COMPRESSED_FLAG;
+}
+
+// Returns the payload unchanged, if compression would not make it smaller,
+// or if it is larger than MAX_UNCOMPRESSED_SIZE.
+pub(crate) fn compress(
+  compressor: &dyn PayloadCompressor,
+  payload: SerializedPayload,
+) -> io::Result<SerializedPayload> {
+  if payload.value.len() > MAX_UNCOMPRESSED_SIZE {
+    return Ok(payload);
+  }
+  let uncompressed_size = payload.value.len() as u32;
+  let compressed = compressor.compress(&payload.value)?;
+  let padding = padding_needed_for_alignment_4(compressed.len());
+  if HEADER_LEN + compressed.len() + padding >= payload.value.len() {
+    return Ok(payload);
+  }
+  let mut value = BytesMut::with_capacity(HEADER_LEN + compressed.len() + padding);
+  value.put_u8(compressor.id());
+  value.put_u8(padding as u8);
+  value.put_bytes(0, 2);
+  value.put_u32(uncompressed_size);
+  value.put_slice(&compressed);
+  value.put_bytes(0, padding);
+
+  let mut representation_options = payload.representation_options;
+  representation_options[0] |= COMPRESSED_FLAG;
+  Ok(SerializedPayload {
+    representation_identifier: payload.representation_identifier,
+    representation_options,
+    value: value.freeze(),
+  })
+}
+
+pub(crate) fn decompress(
+  decompressors: &[Arc<dyn PayloadCompressor>],
+  payload: &SerializedPayload,
+) -> io::Result<SerializedPayload> {
+  let padding = payload.value.get(1).copied().unwrap_or(0) as usize;
+  if payload.value.len() < HEADER_LEN + padding {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      "Compressed payload is shorter than its header",
+    ));
+  }
+  let id = payload.value[0];
+  let uncompressed_size = u32::from_be_bytes([
+    payload.value[4],
+    payload.value[5],
+    payload.value[6],
+    payload.value[7],
+  ]) as usize;
+  if uncompressed_size > MAX_UNCOMPRESSED_SIZE {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("Uncompressed size {uncompressed_size} exceeds the limit {MAX_UNCOMPRESSED_SIZE}"),
+    ));
+  }
+  let decompressor = decompressors.iter().find(|d| d.id() == id).ok_or_else(|| {
+    io::Error::new(
+      io::ErrorKind::Unsupported,
+      format!("Unknown payload compression algorithm id {id}"),
+    )
+  })?;
+  let compressed = payload
+    .value
+    .slice(HEADER_LEN..payload.value.len() - padding);
+  let value = decompressor.decompress(&compressed, uncompressed_size)?;
+  if value.len() != uncompressed_size {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!(
+        "Decompressed {} bytes, expected {uncompressed_size}",
+        value.len()
+      ),
+    ));
+  }
+
+  let mut representation_options = payload.representation_options;
+  representation_options[0] &= !COMPRESSED_FLAG;
+  Ok(SerializedPayload {
+    representation_identifier: payload.representation_identifier,
+    representation_options,
+    value,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{test::zero_rle::ZeroRle, RepresentationIdentifier};
+
+  fn payload(value: Vec<u8>) -> SerializedPayload {
+    SerializedPayload::new(RepresentationIdentifier::CDR_LE, value)
+  }
+
+  #[test]
+  fn compress_and_decompress() {
+    let original = payload([vec![1, 2, 3], vec![0; 100], vec![4]].concat());
+    let compressed = compress(&ZeroRle, original.clone()).unwrap();
+    assert!(is_compressed(&compressed));
+    assert_eq!(
+      compressed.representation_identifier,
+      original.representation_identifier
+    );
+    // header: id, padding 2, reserved, size 104
+    assert_eq!(&compressed.value[..8], &[200, 2, 0, 0, 0, 0, 0, 104]);
+    assert_eq!(&compressed.value[8..], &[1, 2, 3, 0, 100, 4, 0, 0]);
+
+    let decompressors: Vec<Arc<dyn PayloadCompressor>> = vec![Arc::new(ZeroRle)];
+    let decompressed = decompress(&decompressors, &compressed).unwrap();
+    assert!(!is_compressed(&decompressed));
+    assert_eq!(decompressed, original);
+  }
+
+  #[test]
+  fn incompressible_payload_is_unchanged() {
+    let original = payload(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    let result = compress(&ZeroRle, original.clone()).unwrap();
+    assert!(!is_compressed(&result));
+    assert_eq!(result, original);
+  }
+
+  #[test]
+  fn oversized_payload_is_rejected() {
+    let original = payload(vec![0; MAX_UNCOMPRESSED_SIZE + 1]);
+    let result = compress(&ZeroRle, original.clone()).unwrap();
+    assert!(!is_compressed(&result));
+
+    // A header claiming a huge size is rejected before decompressing.
+    let mut compressed = compress(&ZeroRle, payload(vec![0; 100])).unwrap();
+    let mut value = compressed.value.to_vec();
+    value[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+    compressed.value = Bytes::from(value);
+    let decompressors: Vec<Arc<dyn PayloadCompressor>> = vec![Arc::new(ZeroRle)];
+    let err = decompress(&decompressors, &compressed).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn unknown_algorithm() {
+    let compressed = compress(&ZeroRle, payload(vec![0; 100])).unwrap();
+    assert!(decompress(&builtin_decompressors(), &compressed).is_err());
+  }
+
+  #[cfg(feature = "lz4")]
+  #[test]
+  fn lz4_round_trip() {
+    let original = payload(b"abcdefgh".repeat(100));
+    let compressed = compress(&Lz4Compressor, original.clone()).unwrap();
+    assert!(is_compressed(&compressed));
+    assert!(compressed.value.len() < original.value.len() / 10);
+    assert_eq!(
+      decompress(&builtin_decompressors(), &compressed).unwrap(),
+      original
+    );
+  }
+
+  #[cfg(feature = "zstd")]
+  #[test]
+  fn zstd_round_trip() {
+    let original = payload(b"abcdefgh".repeat(100));
+    let compressed = compress(&ZstdCompressor::default(), original.clone()).unwrap();
+    assert!(is_compressed(&compressed));
+    assert_eq!(
+      decompress(&builtin_decompressors(), &compressed).unwrap(),
+      original
+    );
+  }
+}
@@ -3,6 +3,7 @@ use std::marker::PhantomData;
 use bytes::Bytes;
 use byteorder::{ByteOrder, LittleEndian};
 
+use super::cdr_adapters::encoding_for_byte_order;
 use crate::{
   dds::adapters::{no_key, with_key},
   structure::parameter_id::ParameterId,
@@ -39,18 +40,19 @@ where
 impl<D, BO> no_key::SerializerAdapter<D> for PlCdrSerializerAdapter<D, BO>
 where
   D: PlCdrSerialize,
-  BO: ByteOrder,
+  BO: ByteOrder + 'static,
 {
   type Error = PlCdrSerializeError;
 
   fn output_encoding() -> RepresentationIdentifier {
-    // TODO: This works only for BO=LittleEndian
-    RepresentationIdentifier::PL_CDR_LE
+    encoding_for_byte_order::<BO>(
+      RepresentationIdentifier::PL_CDR_LE,
+      RepresentationIdentifier::PL_CDR_BE,
+    )
   }
 
   fn to_bytes(value: &D) -> Result<Bytes, Self::Error> {
-    // TODO: This works only for BO=LittleEndian
-    value.to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+    value.to_pl_cdr_bytes(<Self as no_key::SerializerAdapter<D>>::output_encoding())
   }
 }
 
@@ -58,11 +60,10 @@ impl<D, BO> with_key::SerializerAdapter<D> for PlCdrSerializerAdapter<D, BO>
 where
   D: Keyed + PlCdrSerialize,
   <D as Keyed>::K: PlCdrSerialize,
-  BO: ByteOrder,
+  BO: ByteOrder + 'static,
 {
   fn key_to_bytes(value: &D::K) -> Result<Bytes, Self::Error> {
-    // TODO: This works only for BO=LittleEndian
-    value.to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+    value.to_pl_cdr_bytes(<Self as no_key::SerializerAdapter<D>>::output_encoding())
   }
 }
 
@@ -0,0 +1,78 @@
+use std::convert::Infallible;
+
+use bytes::Bytes;
+
+use crate::{dds::adapters::no_key, RepresentationIdentifier};
+
+/// A sample payload as received, without decoding.
+///
+/// This is what [`RawDeserializerAdapter`] produces, e.g. for recording or
+/// forwarding samples of types that are not known at compile time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawSample {
+  /// Data representation of the payload, as given by the DataWriter.
+  pub encoding: RepresentationIdentifier,
+  /// The serialized data, without the encapsulation header.
+  pub payload: Bytes,
+}
+
+/// This [`no_key::DeserializerAdapter`] does not decode the payload, but
+/// passes it on as a [`RawSample`]. It accepts all data representations.
+pub struct RawDeserializerAdapter;
+
+const ALL_REPR_IDS: [RepresentationIdentifier; 17] = [
+  RepresentationIdentifier::CDR_BE,
+  RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::PL_CDR_BE,
+  RepresentationIdentifier::PL_CDR_LE,
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::XML,
+  RepresentationIdentifier::XCDR2_BE,
+  RepresentationIdentifier::XCDR2_LE,
+  RepresentationIdentifier::D_CDR2_BE,
+  RepresentationIdentifier::D_CDR2_LE,
+  RepresentationIdentifier::PL_XCDR2_BE,
+  RepresentationIdentifier::PL_XCDR2_LE,
+];
+
+impl no_key::DeserializerAdapter<RawSample> for RawDeserializerAdapter {
+  type Error = Infallible;
+  type Decoded = RawSample;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &ALL_REPR_IDS
+  }
+
+  fn transform_decoded(decoded: Self::Decoded) -> RawSample {
+    decoded
+  }
+}
+
+impl no_key::DefaultDecoder<RawSample> for RawDeserializerAdapter {
+  type Decoder = RawDecoder;
+  const DECODER: Self::Decoder = RawDecoder;
+}
+
+/// Decoder of [`RawDeserializerAdapter`]. It copies the bytes as they are.
+#[derive(Clone)]
+pub struct RawDecoder;
+
+impl no_key::Decode<RawSample> for RawDecoder {
+  type Error = Infallible;
+
+  fn decode_bytes(
+    self,
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<RawSample, Infallible> {
+    Ok(RawSample {
+      encoding,
+      payload: Bytes::copy_from_slice(input_bytes),
+    })
+  }
+}
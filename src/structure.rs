@@ -5,6 +5,7 @@ pub mod endpoint;
 pub mod entity;
 pub mod guid;
 pub mod locator;
+pub(crate) mod memory_budget;
 pub mod parameter_id;
 pub mod sequence_number;
 pub mod time;
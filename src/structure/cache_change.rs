@@ -11,6 +11,26 @@ pub enum ChangeKind {
   Alive,
   NotAliveDisposed,
   NotAliveUnregistered,
+  // Both disposed and unregistered in the same change
+  NotAliveDisposedUnregistered,
+}
+
+impl ChangeKind {
+  // These tell the StatusInfo flags of the change, see RTPS spec v2.5 Section
+  // 9.6.4.9 StatusInfo_t (PID_STATUS_INFO).
+  pub fn is_disposed(self) -> bool {
+    matches!(
+      self,
+      ChangeKind::NotAliveDisposed | ChangeKind::NotAliveDisposedUnregistered
+    )
+  }
+
+  pub fn is_unregistered(self) -> bool {
+    matches!(
+      self,
+      ChangeKind::NotAliveUnregistered | ChangeKind::NotAliveDisposedUnregistered
+    )
+  }
 }
 
 #[derive(Debug, Clone)]
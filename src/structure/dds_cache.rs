@@ -1,6 +1,6 @@
 use std::{
   cmp::max,
-  collections::{btree_map, BTreeMap, HashMap},
+  collections::{btree_map, BTreeMap, BTreeSet, HashMap},
   iter,
   ops::Bound::{Excluded, Included},
   sync::{Arc, Mutex},
@@ -13,16 +13,20 @@ use crate::{
   create_error_internal,
   dds::{
     qos::{
-      policy::{History, ResourceLimits},
+      policy::{History, Reliability, ResourceLimits},
       QosPolicies,
     },
+    statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
     typedesc::TypeDesc,
     CreateError, CreateResult,
   },
   structure::{sequence_number::SequenceNumber, time::Timestamp},
   GUID,
 };
-use super::cache_change::CacheChange;
+use super::{
+  cache_change::CacheChange,
+  memory_budget::{cache_change_size, MemoryBudget, MemoryKind},
+};
 
 /// DDSCache contains all cacheChanges that are
 /// received by this participant. It is for serving local Readers. Local
@@ -45,15 +49,37 @@ use super::cache_change::CacheChange;
 /// the actual TopicCaches. For a given topic, the Reader/Writer and
 /// DataReader/DataWriter get a clone of the handle and
 /// interact with the TopicCache through this handle.
+///
+/// All TopicCaches share the participant-wide memory budget.
 #[derive(Debug, Default)]
 pub struct DDSCache {
   topic_caches: HashMap<String, Arc<Mutex<TopicCache>>>,
+  memory_budget: Arc<MemoryBudget>,
 }
 
 impl DDSCache {
+  #[cfg(test)]
   pub fn new() -> Self {
     Self::default()
   }
+
+  pub(crate) fn with_memory_limit(
+    memory_limit: Option<usize>,
+    status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+  ) -> Self {
+    Self {
+      topic_caches: HashMap::new(),
+      memory_budget: Arc::new(MemoryBudget::with_status_sender(
+        memory_limit,
+        status_sender,
+      )),
+    }
+  }
+
+  pub(crate) fn memory_budget(&self) -> Arc<MemoryBudget> {
+    self.memory_budget.clone()
+  }
+
   // Insert new topic if it does not exist.
   // If it exists already, update cache size limits.
   // Return a handle to the cache topic.
@@ -65,15 +91,21 @@ impl DDSCache {
     topic_data_type: TypeDesc,
     qos: &QosPolicies,
   ) -> Arc<Mutex<TopicCache>> {
+    let memory_budget = &self.memory_budget;
     let topic_cache_handle = self
       .topic_caches
       .entry(topic_name.clone())
       .and_modify(|tc| tc.lock().unwrap().update_keep_limits(qos))
-      .or_insert(Arc::new(Mutex::new(TopicCache::new(
-        topic_name,
-        topic_data_type,
-        qos,
-      ))));
+      .or_insert_with(|| {
+        let tc = Arc::new(Mutex::new(TopicCache::new(
+          topic_name,
+          topic_data_type,
+          qos,
+          memory_budget.clone(),
+        )));
+        memory_budget.register_topic_cache(&tc);
+        tc
+      });
 
     topic_cache_handle.clone()
   }
@@ -141,10 +173,25 @@ pub(crate) struct TopicCache {
   // Therefore, data before the marker SN can be handed off to a Reliable DataReader.
   // Initially, we consider the marker for each Writer (GUID) to be SequenceNumber::new(1)
   received_reliably_before: BTreeMap<GUID, SequenceNumber>,
+
+  // Every CacheChange in "changes" is charged to this.
+  memory_budget: Arc<MemoryBudget>,
+
+  // Local Readers that do not allow evicting samples under memory pressure:
+  // Reliable and built-in ones.
+  protected_by_readers: BTreeSet<GUID>,
+  // Number of samples evicted under memory pressure so far. Readers compare
+  // this to what they have seen to report SampleLost.
+  evicted_count: u64,
 }
 
 impl TopicCache {
-  pub fn new(topic_name: String, topic_data_type: TypeDesc, topic_qos: &QosPolicies) -> Self {
+  pub fn new(
+    topic_name: String,
+    topic_data_type: TypeDesc,
+    topic_qos: &QosPolicies,
+    memory_budget: Arc<MemoryBudget>,
+  ) -> Self {
     let mut new_self = Self {
       topic_name,
       topic_data_type,
@@ -156,6 +203,9 @@ impl TopicCache {
       changes_reallocated_up_to: Timestamp::ZERO,
       sequence_numbers: BTreeMap::new(),
       received_reliably_before: BTreeMap::new(),
+      memory_budget,
+      protected_by_readers: BTreeSet::new(),
+      evicted_count: 0,
     };
 
     new_self.update_keep_limits(topic_qos);
@@ -208,22 +258,29 @@ impl TopicCache {
     self.changes.get(instant)
   }
 
-  pub fn add_change(&mut self, instant: &Timestamp, cache_change: CacheChange) {
-    self
-      .add_change_internal(instant, cache_change)
-      .map(|cc_back| {
+  // Returns false, if the change was rejected because the memory limit would
+  // have been exceeded.
+  pub fn add_change(&mut self, instant: &Timestamp, cache_change: CacheChange) -> bool {
+    match self.add_change_internal(instant, cache_change) {
+      Ok(None) => true,
+      Ok(Some(cc_back)) => {
         warn!(
           "DDSCache insert failed topic={:?} cache_change={:?}",
           self.topic_name, cc_back
         );
-      });
+        true
+      }
+      Err(()) => false,
+    }
   }
 
+  // Ok(Some(cc)) returns a change that was replaced. Err means that the change
+  // did not fit into the memory budget and was dropped.
   fn add_change_internal(
     &mut self,
     instant: &Timestamp,
     cache_change: CacheChange,
-  ) -> Option<CacheChange> {
+  ) -> Result<Option<CacheChange>, ()> {
     // First, do garbage collection.
     // But not at every insert, just to save time and effort.
     // Some heuristic to decide if we should collect now.
@@ -250,18 +307,46 @@ impl TopicCache {
       );
       // We are keeping this quiet, because e.g. FastDDS Discoery keeps sending the
       // same SequenceNumber in periodic updates.
-      None
+      Ok(None)
     } else {
       // This is a new (to us) SequenceNumber, this is the default processing path.
+      let size = cache_change_size(&cache_change);
+      if !self
+        .memory_budget
+        .try_reserve(MemoryKind::ReaderCache, size)
+      {
+        // Garbage collect out of schedule and try again. This only removes
+        // samples over the History and ResourceLimits. If that is not
+        // enough, the oldest BestEffort samples are evicted, possibly from
+        // this cache.
+        self.remove_changes_before(Timestamp::ZERO);
+        let budget = self.memory_budget.clone();
+        let reserved = budget.try_reserve(MemoryKind::ReaderCache, size)
+          || (budget.relieve_pressure(size, Some(self))
+            && budget.try_reserve(MemoryKind::ReaderCache, size));
+        if !reserved {
+          warn!(
+            "DDSCache memory limit {:?} reached. Rejecting {:?} from {:?} topic={:?}",
+            self.memory_budget.limit(),
+            cache_change.sequence_number,
+            cache_change.writer_guid,
+            self.topic_name,
+          );
+          return Err(());
+        }
+      }
       self.insert_sn(*instant, &cache_change);
-      self.changes.insert(*instant, cache_change).map(|old_cc| {
+      Ok(self.changes.insert(*instant, cache_change).map(|old_cc| {
         // If this happens, cache changes were created at exactly same instant.
         // This is bad, since we are using instants as keys and assume that they
         // are unique.
         error!("DDSHistoryCache already contained element with key {instant:?} !!!");
         self.remove_sn(&old_cc);
+        self
+          .memory_budget
+          .release(MemoryKind::ReaderCache, cache_change_size(&old_cc));
         old_cc
-      })
+      }))
     }
   }
 
@@ -423,8 +508,13 @@ impl TopicCache {
 
     let to_remove = std::mem::replace(&mut self.changes, to_retain);
 
-    // update also SequenceNumber map
-    to_remove.values().for_each(|r| self.remove_sn(r));
+    // update also SequenceNumber map and memory accounting
+    to_remove.values().for_each(|r| {
+      self.remove_sn(r);
+      self
+        .memory_budget
+        .release(MemoryKind::ReaderCache, cache_change_size(r));
+    });
 
     // Now, reallocate old cache changes
     let reallocate_timeout = crate::Duration::from_secs(5);
@@ -447,6 +537,60 @@ impl TopicCache {
   pub fn topic_name(&self) -> String {
     self.topic_name.clone()
   }
+
+  pub fn memory_budget(&self) -> Arc<MemoryBudget> {
+    self.memory_budget.clone()
+  }
+
+  // Readers register here, so that eviction can be decided by their QoS, not
+  // the Topic QoS. Returns the current evicted_count.
+  pub(crate) fn add_reader(&mut self, reader: GUID, reliability: &Reliability) -> u64 {
+    if reader.is_built_in() || reliability != &Reliability::BestEffort {
+      self.protected_by_readers.insert(reader);
+    }
+    self.evicted_count
+  }
+
+  pub(crate) fn remove_reader(&mut self, reader: GUID) {
+    self.protected_by_readers.remove(&reader);
+  }
+
+  pub(crate) fn evicted_count(&self) -> u64 {
+    self.evicted_count
+  }
+
+  // Samples may be evicted under memory pressure, even if DataReaders have not
+  // yet taken them, if all the local Readers are BestEffort user Readers.
+  // BestEffort allows losing samples anyway.
+  pub(crate) fn is_evictable(&self) -> bool {
+    self.protected_by_readers.is_empty()
+  }
+
+  pub(crate) fn oldest_change_time(&self) -> Option<Timestamp> {
+    self.changes.keys().next().copied()
+  }
+
+  // Returns false, if there was nothing to evict.
+  pub(crate) fn evict_oldest(&mut self) -> bool {
+    match self.changes.pop_first() {
+      Some((_, cc)) => {
+        self.remove_sn(&cc);
+        self
+          .memory_budget
+          .release(MemoryKind::ReaderCache, cache_change_size(&cc));
+        self.evicted_count += 1;
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+impl Drop for TopicCache {
+  fn drop(&mut self) {
+    let size = self.changes.values().map(cache_change_size).sum();
+    self.memory_budget.release(MemoryKind::ReaderCache, size);
+  }
 }
 
 type ChangesInRangeBestEffort<'a, F> = iter::Map<btree_map::Range<'a, Timestamp, CacheChange>, F>;
@@ -503,10 +647,19 @@ mod tests {
   use super::DDSCache;
   use crate::{
     dds::{
-      ddsdata::DDSData, qos::QosPolicies, typedesc::TypeDesc, with_key::datawriter::WriteOptions,
+      ddsdata::DDSData,
+      qos::{policy::Reliability, QosPolicies, QosPolicyBuilder},
+      statusevents::{sync_status_channel, DomainParticipantStatusEvent},
+      typedesc::TypeDesc,
+      with_key::datawriter::WriteOptions,
     },
     messages::submessages::elements::serialized_payload::SerializedPayload,
-    structure::{cache_change::CacheChange, guid::GUID, sequence_number::SequenceNumber},
+    structure::{
+      cache_change::CacheChange,
+      guid::{EntityKind, GuidPrefix, GUID},
+      memory_budget::cache_change_size,
+      sequence_number::SequenceNumber,
+    },
   };
 
   #[test]
@@ -577,4 +730,135 @@ mod tests {
       3
     );
   }
+
+  #[test]
+  fn topic_cache_rejects_changes_over_memory_limit() {
+    let change = |sn| {
+      CacheChange::new(
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::new(sn),
+        WriteOptions::default(),
+        DDSData::new(SerializedPayload::default()),
+      )
+    };
+    let size = cache_change_size(&change(1));
+    let (status_sender, _status_receiver) = sync_status_channel(4).unwrap();
+    let mut dds_cache = DDSCache::with_memory_limit(Some(2 * size), status_sender);
+    let topic_cache_handle = dds_cache.add_new_topic(
+      "MemoryLimitedTopic".to_string(),
+      TypeDesc::new("Whatever".to_string()),
+      &QosPolicies::qos_none(),
+    );
+    let budget = dds_cache.memory_budget();
+
+    {
+      let mut tc = topic_cache_handle.lock().unwrap();
+      // A Reliable reader keeps the samples from being evicted
+      tc.add_reader(
+        GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+        &Reliability::Reliable {
+          max_blocking_time: crate::Duration::ZERO,
+        },
+      );
+      assert!(tc.add_change(&crate::Timestamp::now(), change(1)));
+      assert!(tc.add_change(&crate::Timestamp::now(), change(2)));
+      assert!(!tc.add_change(&crate::Timestamp::now(), change(3)));
+      // duplicates are accepted, but not charged again
+      assert!(tc.add_change(&crate::Timestamp::now(), change(2)));
+    }
+    assert_eq!(budget.used(), 2 * size);
+
+    // Dropping the topic releases its memory
+    dds_cache.remove_topic("MemoryLimitedTopic");
+    drop(topic_cache_handle);
+    assert_eq!(budget.used(), 0);
+  }
+
+  #[test]
+  fn memory_pressure_evicts_oldest_best_effort_samples() {
+    let change = |sn| {
+      CacheChange::new(
+        GUID::GUID_UNKNOWN,
+        SequenceNumber::new(sn),
+        WriteOptions::default(),
+        DDSData::new(SerializedPayload::default()),
+      )
+    };
+    let size = cache_change_size(&change(1));
+    let (status_sender, status_receiver) = sync_status_channel(4).unwrap();
+    let mut dds_cache = DDSCache::with_memory_limit(Some(3 * size), status_sender);
+    // Evictability follows the Reliability of the local Readers, not of the
+    // Topic
+    let reliable = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: crate::Duration::ZERO,
+      })
+      .build();
+    let best_effort_cache = dds_cache.add_new_topic(
+      "BestEffortTopic".to_string(),
+      TypeDesc::new("Whatever".to_string()),
+      &reliable,
+    );
+    let reliable_cache = dds_cache.add_new_topic(
+      "ReliableTopic".to_string(),
+      TypeDesc::new("Whatever".to_string()),
+      &reliable,
+    );
+    let best_effort_reader = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut reliable_reader = best_effort_reader;
+    reliable_reader.prefix = GuidPrefix::new(b"other reader");
+    best_effort_cache
+      .lock()
+      .unwrap()
+      .add_reader(best_effort_reader, &Reliability::BestEffort);
+    reliable_cache.lock().unwrap().add_reader(
+      reliable_reader,
+      &Reliability::Reliable {
+        max_blocking_time: crate::Duration::ZERO,
+      },
+    );
+    let budget = dds_cache.memory_budget();
+
+    let t = crate::Timestamp::from_ticks;
+    assert!(best_effort_cache
+      .lock()
+      .unwrap()
+      .add_change(&t(1), change(1)));
+    assert!(best_effort_cache
+      .lock()
+      .unwrap()
+      .add_change(&t(2), change(2)));
+    assert!(reliable_cache.lock().unwrap().add_change(&t(3), change(3)));
+
+    // The oldest BestEffort sample makes room for a Reliable one
+    assert!(reliable_cache.lock().unwrap().add_change(&t(4), change(4)));
+    assert_eq!(
+      best_effort_cache.lock().unwrap().oldest_change_time(),
+      Some(t(2))
+    );
+    assert!(matches!(
+      status_receiver.try_recv(),
+      Ok(DomainParticipantStatusEvent::MemoryLimitReached { .. })
+    ));
+
+    // ... and for a BestEffort one in the same cache
+    assert!(best_effort_cache
+      .lock()
+      .unwrap()
+      .add_change(&t(5), change(5)));
+    assert_eq!(
+      best_effort_cache.lock().unwrap().oldest_change_time(),
+      Some(t(5))
+    );
+    assert_eq!(budget.usage().reader_caches, 3 * size);
+    assert_eq!(best_effort_cache.lock().unwrap().evicted_count(), 2);
+    assert_eq!(reliable_cache.lock().unwrap().evicted_count(), 0);
+
+    // Reliable samples are not evicted
+    assert!(reliable_cache.lock().unwrap().add_change(&t(6), change(6)));
+    assert_eq!(best_effort_cache.lock().unwrap().oldest_change_time(), None);
+    assert!(!reliable_cache.lock().unwrap().add_change(&t(7), change(7)));
+    // Reported only once
+    assert!(status_receiver.try_recv().is_err());
+  }
 }
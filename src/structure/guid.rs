@@ -48,6 +48,32 @@ impl GuidPrefix {
     Self { bytes }
   }
 
+  /// Derives a GuidPrefix deterministically from `seed`, e.g. a configured
+  /// node id. The same seed always produces the same prefix, so that a
+  /// restarted participant is recognized by its peers.
+  ///
+  /// As with [`random_for_this_participant`](Self::random_for_this_participant),
+  /// the first two bytes are our vendor id. The rest is taken from an MD5 hash
+  /// of the seed.
+  pub fn from_seed(seed: &[u8]) -> Self {
+    let digest = md5::compute(seed);
+    let mut bytes = [0; 12];
+    let my_vendor_id_bytes = crate::messages::vendor_id::VendorId::THIS_IMPLEMENTATION.as_bytes();
+    bytes[0] = my_vendor_id_bytes[0];
+    bytes[1] = my_vendor_id_bytes[1];
+    bytes[2..].copy_from_slice(&digest[..10]);
+    Self { bytes }
+  }
+
+  /// Checks that this prefix can be used for a local DomainParticipant.
+  ///
+  /// RTPS spec v2.5 Section "9.3.1.5 Mapping of the GuidPrefix_t" requires
+  /// that the first two bytes match the vendor id of the implementation.
+  pub fn is_valid_for_this_participant(&self) -> bool {
+    *self != Self::UNKNOWN
+      && self.bytes[..2] == crate::messages::vendor_id::VendorId::THIS_IMPLEMENTATION.as_bytes()
+  }
+
   pub fn range(&self) -> impl RangeBounds<GUID> {
     GUID::new(*self, EntityId::MIN)..=GUID::new(*self, EntityId::MAX)
   }
@@ -571,8 +597,13 @@ impl GUID {
 
   /// Generates new GUID for Participant when `guid_prefix` is random
   pub fn new_participant_guid() -> Self {
+    Self::participant_guid_with_prefix(GuidPrefix::random_for_this_participant())
+  }
+
+  /// GUID for Participant with a given `guid_prefix`
+  pub fn participant_guid_with_prefix(prefix: GuidPrefix) -> Self {
     Self {
-      prefix: GuidPrefix::random_for_this_participant(),
+      prefix,
       entity_id: EntityId::PARTICIPANT,
     }
   }
@@ -605,6 +636,12 @@ impl GUID {
     self.entity_id.as_usize()
   }
 
+  // Built-in endpoints are recognized by their EntityKind. Topic names do not
+  // tell, as an application may use any name, including ones starting "DCPS".
+  pub(crate) fn is_built_in(&self) -> bool {
+    self.entity_id.kind().is_built_in()
+  }
+
   pub fn to_bytes(&self) -> [u8; 16] {
     let mut bytes = [0; 16];
     bytes.as_mut_slice()[0..12].copy_from_slice(self.prefix.as_ref());
@@ -632,6 +669,18 @@ mod tests {
 
   use super::*;
 
+  #[test]
+  fn guid_prefix_from_seed() {
+    let a = GuidPrefix::from_seed(b"node-17");
+    assert_eq!(a, GuidPrefix::from_seed(b"node-17"));
+    assert_ne!(a, GuidPrefix::from_seed(b"node-18"));
+    assert!(a.is_valid_for_this_participant());
+    assert!(GuidPrefix::random_for_this_participant().is_valid_for_this_participant());
+
+    assert!(!GuidPrefix::UNKNOWN.is_valid_for_this_participant());
+    assert!(!GuidPrefix::new(b"FakeTestGUID").is_valid_for_this_participant());
+  }
+
   #[test]
   fn serde_test() {
     use crate::serialization::{from_bytes, to_vec};
@@ -1,4 +1,7 @@
-use std::net::{SocketAddrV4, SocketAddrV6};
+use std::{
+  fmt,
+  net::{SocketAddrV4, SocketAddrV6},
+};
 pub use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use speedy::{Context, Readable, Reader, Writable, Writer};
@@ -40,6 +43,29 @@ impl Locator {
   }
 }
 
+/// Printable form, e.g. `udp://192.168.1.10:7410` or `udp://[fe80::1]:7410`.
+impl fmt::Display for Locator {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Locator::Invalid => write!(f, "invalid"),
+      Locator::Reserved => write!(f, "reserved"),
+      Locator::UdpV4(socket_address) => write!(f, "udp://{socket_address}"),
+      Locator::UdpV6(socket_address) => write!(f, "udp://{socket_address}"),
+      Locator::Other {
+        kind,
+        port,
+        address,
+      } => {
+        write!(f, "kind{kind}://")?;
+        for b in address {
+          write!(f, "{b:02x}")?;
+        }
+        write!(f, ":{port}")
+      }
+    }
+  }
+}
+
 impl From<Locator> for SocketAddr {
   fn from(locator: Locator) -> Self {
     match locator {
@@ -197,6 +223,19 @@ mod tests {
       .into() // repr::Locator -> Locator
   }
 
+  #[test]
+  fn display() {
+    assert_eq!(
+      Locator::from(SocketAddr::new(Ipv4Addr::new(10, 0, 0, 15).into(), 7410)).to_string(),
+      "udp://10.0.0.15:7410"
+    );
+    assert_eq!(
+      Locator::from(SocketAddr::new(Ipv6Addr::LOCALHOST.into(), 7410)).to_string(),
+      "udp://[::1]:7410"
+    );
+    assert_eq!(Locator::Invalid.to_string(), "invalid");
+  }
+
   #[test_case(SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0) => Locator::Invalid ; "unspecified IPv6")]
   fn from_socket_address(socket_addr: impl Into<Locator>) -> Locator {
     socket_addr.into()
@@ -0,0 +1,306 @@
+use std::{
+  fmt,
+  mem::size_of,
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, MutexGuard, Weak,
+  },
+};
+
+use log::{info, warn};
+
+use crate::dds::{
+  ddsdata::DDSData,
+  statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
+};
+use super::{cache_change::CacheChange, dds_cache::TopicCache};
+
+/// Approximate memory use of a DomainParticipant, in bytes, by where the
+/// memory is held. See
+/// [`DomainParticipant::memory_usage`](crate::DomainParticipant::memory_usage).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+  /// Samples kept by DataWriters for (re)sending
+  pub writer_histories: usize,
+  /// Samples received and not yet removed from the reader caches
+  pub reader_caches: usize,
+  /// Discovered participants, endpoints and topics. This is not charged
+  /// against the memory limit.
+  pub discovery_db: usize,
+  /// Partially received fragmented samples
+  pub fragment_assembly: usize,
+}
+
+impl MemoryUsage {
+  pub fn total(&self) -> usize {
+    self.writer_histories + self.reader_caches + self.discovery_db + self.fragment_assembly
+  }
+}
+
+// What a charge to the MemoryBudget is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MemoryKind {
+  WriterHistory = 0,
+  ReaderCache = 1,
+  FragmentAssembly = 2,
+}
+
+// Participant-wide accounting of (approximate) memory used by sample data in
+// reader and writer caches and fragment reassembly buffers.
+//
+// Only payload bytes and the fixed size of the bookkeeping structures are
+// counted. Map overhead, receive buffers, etc. are not, so the actual memory
+// use is somewhat higher than the reported figure.
+//
+// Memory is always counted, so that it can be reported. The limit, if any,
+// is enforced by the callers of try_reserve and has_room_for.
+#[derive(Default)]
+pub(crate) struct MemoryBudget {
+  limit: Option<usize>,
+  used: AtomicUsize,
+  used_by_kind: [AtomicUsize; 3],
+  // TopicCaches that may have samples evicted under memory pressure
+  topic_caches: Mutex<Vec<Weak<Mutex<TopicCache>>>>,
+  // Set when the limit is reached, and cleared when usage has fallen well
+  // below it. This is to report the pressure only once per episode.
+  under_pressure: AtomicBool,
+  status_sender: Option<StatusChannelSender<DomainParticipantStatusEvent>>,
+}
+
+impl fmt::Debug for MemoryBudget {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("MemoryBudget")
+      .field("limit", &self.limit)
+      .field("used", &self.used())
+      .finish()
+  }
+}
+
+impl MemoryBudget {
+  #[cfg(test)]
+  pub fn new(limit: Option<usize>) -> Self {
+    Self {
+      limit,
+      ..Self::default()
+    }
+  }
+
+  // Memory pressure is reported as DomainParticipantStatusEvent::MemoryLimitReached
+  pub fn with_status_sender(
+    limit: Option<usize>,
+    status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+  ) -> Self {
+    Self {
+      limit,
+      status_sender: Some(status_sender),
+      ..Self::default()
+    }
+  }
+
+  pub fn limit(&self) -> Option<usize> {
+    self.limit
+  }
+
+  pub fn used(&self) -> usize {
+    self.used.load(Ordering::Relaxed)
+  }
+
+  // DiscoveryDB is not tracked here, so it is left at zero.
+  pub fn usage(&self) -> MemoryUsage {
+    let by_kind = |kind: MemoryKind| self.used_by_kind[kind as usize].load(Ordering::Relaxed);
+    MemoryUsage {
+      writer_histories: by_kind(MemoryKind::WriterHistory),
+      reader_caches: by_kind(MemoryKind::ReaderCache),
+      discovery_db: 0,
+      fragment_assembly: by_kind(MemoryKind::FragmentAssembly),
+    }
+  }
+
+  // Would reserving bytes succeed right now? This does not reserve anything,
+  // so a concurrent reservation may still take the room.
+  pub fn has_room_for(&self, bytes: usize) -> bool {
+    match self.limit {
+      None => true,
+      Some(limit) => self.used().saturating_add(bytes) <= limit,
+    }
+  }
+
+  // Returns true, if bytes were reserved and must be released later.
+  pub fn try_reserve(&self, kind: MemoryKind, bytes: usize) -> bool {
+    let limit = self.limit.unwrap_or(usize::MAX);
+    let reserved = self
+      .used
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        used.checked_add(bytes).filter(|total| *total <= limit)
+      })
+      .is_ok();
+    if reserved {
+      self.used_by_kind[kind as usize].fetch_add(bytes, Ordering::Relaxed);
+      // The pressure is over only when there is clearly room again. This is
+      // checked here rather than on release, because evictions release memory
+      // while the pressure is still on.
+      if self.used() <= limit / 4 * 3 && self.under_pressure.swap(false, Ordering::Relaxed) {
+        info!("Memory use is again below the limit {limit}");
+      }
+    }
+    reserved
+  }
+
+  // Reserve regardless of the limit. This is for data that we have already
+  // committed to keep, e.g. samples that were admitted by a DataWriter.
+  pub fn force_reserve(&self, kind: MemoryKind, bytes: usize) {
+    self.used.fetch_add(bytes, Ordering::Relaxed);
+    self.used_by_kind[kind as usize].fetch_add(bytes, Ordering::Relaxed);
+  }
+
+  pub fn release(&self, kind: MemoryKind, bytes: usize) {
+    // saturate at zero to be robust against accounting bugs
+    let saturating_release = |counter: &AtomicUsize| {
+      let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        Some(used.saturating_sub(bytes))
+      });
+    };
+    saturating_release(&self.used);
+    saturating_release(&self.used_by_kind[kind as usize]);
+  }
+
+  pub fn register_topic_cache(&self, topic_cache: &Arc<Mutex<TopicCache>>) {
+    if self.limit.is_some() {
+      let mut topic_caches = self.topic_caches.lock().unwrap();
+      topic_caches.retain(|tc| tc.strong_count() > 0);
+      topic_caches.push(Arc::downgrade(topic_cache));
+    }
+  }
+
+  // Called when a reservation of `bytes` did not fit within the limit.
+  //
+  // Evicts the oldest samples of BestEffort reader caches, across all topics,
+  // until there is room for `bytes`, or there is nothing more to evict. The
+  // pressure is reported once, when the limit is first reached.
+  //
+  // `current` is a TopicCache that the caller has already locked, if any.
+  // Other TopicCaches are only try_lock'ed, so this never blocks or
+  // deadlocks, but a cache that is busy may be skipped.
+  //
+  // Returns true, if there is now room for `bytes`.
+  pub fn relieve_pressure(&self, bytes: usize, current: Option<&mut TopicCache>) -> bool {
+    let Some(limit) = self.limit else {
+      return true;
+    };
+
+    let topic_caches: Vec<Arc<Mutex<TopicCache>>> = self
+      .topic_caches
+      .lock()
+      .unwrap()
+      .iter()
+      .filter_map(Weak::upgrade)
+      .collect();
+    let mut guards: Vec<MutexGuard<TopicCache>> = topic_caches
+      .iter()
+      .filter_map(|tc| tc.try_lock().ok())
+      .collect();
+    let mut candidates: Vec<&mut TopicCache> = guards
+      .iter_mut()
+      .map(|guard| &mut **guard)
+      .chain(current)
+      .filter(|tc| tc.is_evictable())
+      .collect();
+
+    let mut evicted = 0;
+    while !self.has_room_for(bytes) {
+      let oldest = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, tc)| tc.oldest_change_time().map(|ts| (ts, i)))
+        .min();
+      let Some((_, i)) = oldest else { break };
+      if !candidates[i].evict_oldest() {
+        break;
+      }
+      evicted += 1;
+    }
+    drop(candidates);
+    drop(guards);
+
+    if evicted > 0 {
+      warn!("Memory limit {limit} reached. Evicted {evicted} BestEffort samples.");
+    }
+    if !self.under_pressure.swap(true, Ordering::Relaxed) {
+      warn!(
+        "Memory limit {limit} reached. Used {} bytes, need {bytes} more.",
+        self.used()
+      );
+      if let Some(status_sender) = &self.status_sender {
+        let _ = status_sender.try_send(DomainParticipantStatusEvent::MemoryLimitReached {
+          limit,
+          usage: self.usage(),
+        });
+      }
+    }
+    self.has_room_for(bytes)
+  }
+}
+
+// How many bytes a CacheChange is charged for.
+pub(crate) fn cache_change_size(cc: &CacheChange) -> usize {
+  dds_data_size(&cc.data_value)
+}
+
+// Same as above, but before the CacheChange is created.
+pub(crate) fn dds_data_size(data: &DDSData) -> usize {
+  size_of::<CacheChange>() + data.payload_size()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn memory_budget_limits_reservations() {
+    let budget = MemoryBudget::new(Some(100));
+    assert!(budget.try_reserve(MemoryKind::ReaderCache, 60));
+    assert!(!budget.has_room_for(50));
+    assert!(!budget.try_reserve(MemoryKind::ReaderCache, 50));
+    assert_eq!(budget.used(), 60);
+
+    assert!(budget.try_reserve(MemoryKind::FragmentAssembly, 40));
+    assert_eq!(budget.used(), 100);
+
+    budget.release(MemoryKind::ReaderCache, 60);
+    assert!(budget.try_reserve(MemoryKind::ReaderCache, 50));
+
+    // forced reservations may go over the limit
+    budget.force_reserve(MemoryKind::WriterHistory, 100);
+    assert_eq!(budget.used(), 190);
+    assert!(!budget.try_reserve(MemoryKind::ReaderCache, 1));
+    assert_eq!(
+      budget.usage(),
+      MemoryUsage {
+        writer_histories: 100,
+        reader_caches: 50,
+        discovery_db: 0,
+        fragment_assembly: 40,
+      }
+    );
+
+    budget.release(MemoryKind::WriterHistory, 1000);
+    budget.release(MemoryKind::ReaderCache, 1000);
+    budget.release(MemoryKind::FragmentAssembly, 1000);
+    assert_eq!(budget.used(), 0);
+    assert_eq!(budget.usage().total(), 0);
+  }
+
+  #[test]
+  fn memory_budget_unlimited_still_counts() {
+    let budget = MemoryBudget::new(None);
+    assert!(budget.try_reserve(MemoryKind::ReaderCache, usize::MAX / 2));
+    assert!(budget.has_room_for(usize::MAX));
+    budget.release(MemoryKind::ReaderCache, usize::MAX / 2);
+    budget.force_reserve(MemoryKind::WriterHistory, 10);
+    assert_eq!(budget.used(), 10);
+    assert_eq!(budget.usage().writer_histories, 10);
+    budget.release(MemoryKind::WriterHistory, 10);
+    assert_eq!(budget.used(), 0);
+    assert!(budget.relieve_pressure(usize::MAX, None));
+  }
+}
@@ -438,6 +438,54 @@ where
   }
 }
 
+// Set operations, e.g. for working out what to NACK. The Reader and Writer do
+// not use these yet.
+#[allow(dead_code)]
+impl<N> NumberSet<N>
+where
+  N: Clone + Copy + Debug + Hash + PartialEq + Eq + NumOps + From<i64> + Ord + PartialOrd,
+  i64: From<N>,
+{
+  pub fn contains(&self, n: N) -> bool {
+    if n < self.bitmap_base || n >= self.bitmap_base + N::from(self.num_bits as i64) {
+      return false;
+    }
+    let bit_pos = i64::from(n - self.bitmap_base) as u32;
+    self.bitmap[(bit_pos / 32) as usize] & (1 << (31 - bit_pos % 32)) != 0
+  }
+
+  /// Numbers that are in `self`, but not in `other`. The result has the same
+  /// base as `self`.
+  pub fn difference(&self, other: &Self) -> Self {
+    let set = self.iter().filter(|n| !other.contains(*n)).collect();
+    Self::from_base_and_set(self.bitmap_base, &set)
+  }
+
+  /// Numbers that are in both `self` and `other`. The result has the same
+  /// base as `self`.
+  pub fn intersection(&self, other: &Self) -> Self {
+    let set = self.iter().filter(|n| other.contains(*n)).collect();
+    Self::from_base_and_set(self.bitmap_base, &set)
+  }
+
+  /// Numbers that are in `self` or `other`, or both. The result has the lower
+  /// of the two bases.
+  ///
+  /// Returns `None` if the result does not fit into a set, i.e. it would
+  /// contain numbers 256 or more above its base.
+  pub fn union(&self, other: &Self) -> Option<Self> {
+    let base = self.bitmap_base.min(other.bitmap_base);
+    let set: BTreeSet<N> = self.iter().chain(other.iter()).collect();
+    if set
+      .last()
+      .is_some_and(|last| i64::from(*last - base) >= 256)
+    {
+      return None;
+    }
+    Some(Self::from_base_and_set(base, &set))
+  }
+}
+
 impl<'a, C: Context, N> Readable<'a, C> for NumberSet<N>
 where
   N:
@@ -566,6 +614,89 @@ mod tests {
     assert_eq!(FragmentNumber::from(1u32), FragmentNumber::default());
   }
 
+  fn sn_set(base: i64, sns: &[i64]) -> SequenceNumberSet {
+    let set = sns.iter().copied().map(SequenceNumber::from).collect();
+    SequenceNumberSet::from_base_and_set(SequenceNumber::from(base), &set)
+  }
+
+  fn sns(set: &SequenceNumberSet) -> Vec<i64> {
+    set.iter().map(i64::from).collect()
+  }
+
+  #[test]
+  fn sequence_number_set_contains() {
+    let set = sn_set(10, &[10, 12, 41, 42]);
+    assert!(set.contains(SequenceNumber::from(10)));
+    assert!(set.contains(SequenceNumber::from(42)));
+    assert!(!set.contains(SequenceNumber::from(11)));
+    assert!(!set.contains(SequenceNumber::from(9)));
+    assert!(!set.contains(SequenceNumber::from(43)));
+    assert!(!sn_set(10, &[]).contains(SequenceNumber::from(10)));
+  }
+
+  #[test]
+  fn sequence_number_set_difference() {
+    // Missing 3..=8, of which 4, 6 and 7 already received
+    let missing = sn_set(3, &[3, 4, 5, 6, 7, 8]);
+    let received = sn_set(4, &[4, 6, 7]);
+    let nack = missing.difference(&received);
+    assert_eq!(nack.base(), SequenceNumber::from(3));
+    assert_eq!(sns(&nack), vec![3, 5, 8]);
+
+    assert_eq!(sns(&received.difference(&missing)), Vec::<i64>::new());
+    assert_eq!(
+      sns(&missing.difference(&sn_set(100, &[100]))),
+      sns(&missing)
+    );
+    assert_eq!(sns(&sn_set(5, &[]).difference(&missing)), Vec::<i64>::new());
+  }
+
+  #[test]
+  fn sequence_number_set_intersection() {
+    let a = sn_set(1, &[1, 2, 5, 40, 200]);
+    let b = sn_set(2, &[2, 3, 40, 255]);
+    let both = a.intersection(&b);
+    assert_eq!(both.base(), SequenceNumber::from(1));
+    assert_eq!(sns(&both), vec![2, 40]);
+    assert_eq!(sns(&b.intersection(&a)), vec![2, 40]);
+    assert_eq!(b.intersection(&a).base(), SequenceNumber::from(2));
+
+    assert_eq!(sns(&a.intersection(&sn_set(1, &[]))), Vec::<i64>::new());
+  }
+
+  #[test]
+  fn sequence_number_set_union() {
+    let a = sn_set(5, &[5, 7]);
+    let b = sn_set(3, &[3, 7, 9]);
+    let either = a.union(&b).unwrap();
+    assert_eq!(either.base(), SequenceNumber::from(3));
+    assert_eq!(sns(&either), vec![3, 5, 7, 9]);
+    assert_eq!(b.union(&a), Some(either));
+
+    // Empty sets still have a base
+    let empty = sn_set(1, &[]);
+    assert_eq!(a.union(&empty).unwrap().base(), SequenceNumber::from(1));
+    assert_eq!(sns(&a.union(&empty).unwrap()), vec![5, 7]);
+
+    // At most 256 numbers from the base
+    let far = sn_set(100, &[256]);
+    assert_eq!(sns(&sn_set(1, &[1]).union(&far).unwrap()), vec![1, 256]);
+    let too_far = sn_set(100, &[257]);
+    assert_eq!(sn_set(1, &[1]).union(&too_far), None);
+  }
+
+  #[test]
+  fn fragment_number_set_operations() {
+    let set = |nums: &[u32]| {
+      let nums = nums.iter().copied().map(FragmentNumber::from).collect();
+      FragmentNumberSet::from_base_and_set(FragmentNumber::from(1u32), &nums)
+    };
+    let received = set(&[1, 2, 4]);
+    let all = set(&[1, 2, 3, 4, 5]);
+    let missing: Vec<u32> = all.difference(&received).iter().map(u32::from).collect();
+    assert_eq!(missing, vec![3, 5]);
+  }
+
   serialization_test!( type = FragmentNumber,
   {
       fragment_number_zero,
@@ -1,4 +1,7 @@
-use std::ops::{Add, Sub};
+use std::{
+  ops::{Add, Sub},
+  sync::atomic::{AtomicU64, Ordering},
+};
 
 use speedy::{Readable, Writable};
 use serde::{Deserialize, Serialize};
@@ -64,6 +67,21 @@ impl Timestamp {
     })
   }
 
+  // Like now(), but strictly increasing within the process, even if the
+  // system clock is coarse or steps backwards. Receive times of samples and
+  // send times of status events are taken from this, so that they can be
+  // ordered with respect to each other.
+  pub(crate) fn now_monotonic() -> Self {
+    static LATEST: AtomicU64 = AtomicU64::new(0);
+    let now = Self::now().to_ticks();
+    let previous = LATEST
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |latest| {
+        Some(now.max(latest.saturating_add(1)))
+      })
+      .unwrap(); // closure never returns None
+    Self::from_ticks(now.max(previous.saturating_add(1)))
+  }
+
   pub fn to_ticks(self) -> u64 {
     (u64::from(self.seconds) << 32) + u64::from(self.fraction)
   }
@@ -183,4 +201,15 @@ mod tests {
       le = [0x78, 0x6E, 0x8C, 0x5A, 0x7E, 0xE0, 0x2A, 0x4F],
       be = [0x5A, 0x8C, 0x6E, 0x78, 0x4F, 0x2A, 0xE0, 0x7E]
   });
+
+  #[test]
+  fn now_monotonic_increases() {
+    let mut previous = Timestamp::now_monotonic();
+    for _ in 0..1000 {
+      let t = Timestamp::now_monotonic();
+      assert!(t > previous);
+      previous = t;
+    }
+    assert!(previous >= Timestamp::now() - Duration::from_secs(1));
+  }
 }
@@ -2,6 +2,7 @@ pub(crate) mod random_data;
 pub(crate) mod shape_type;
 pub(crate) mod test_data;
 pub(crate) mod test_properties;
+pub(crate) mod zero_rle;
 
 use std::{thread, time::Duration};
 
@@ -256,6 +256,12 @@ pub(crate) fn subscription_builtin_topic_data() -> Option<SubscriptionBuiltinTop
     .lifespan(Lifespan {
       duration: Duration::from(StdDuration::from_secs(6 * 60)),
     })
+    .history(History::KeepLast { depth: 20 })
+    .resource_limits(ResourceLimits {
+      max_samples: 200,
+      max_instances: 10,
+      max_samples_per_instance: 20,
+    })
     .build();
 
   let sub_topic_data = SubscriptionBuiltinTopicData::new(
@@ -298,6 +304,12 @@ pub(crate) fn publication_builtin_topic_data() -> Option<PublicationBuiltinTopic
       coherent_access: true,
       ordered_access: false,
     }),
+    history: Some(History::KeepAll),
+    resource_limits: Some(ResourceLimits {
+      max_samples: 100,
+      max_instances: 1,
+      max_samples_per_instance: 100,
+    }),
     related_datareader_key: None,
     service_instance_name: None,
     topic_aliases: None,
@@ -0,0 +1,58 @@
+use std::io;
+
+use bytes::Bytes;
+
+use crate::serialization::compression::PayloadCompressor;
+
+// Run-length encoding of zeros, enough for testing payload compression
+pub(crate) struct ZeroRle;
+
+impl PayloadCompressor for ZeroRle {
+  fn name(&self) -> &str {
+    "zero-rle"
+  }
+
+  fn id(&self) -> u8 {
+    200
+  }
+
+  fn compress(&self, data: &Bytes) -> io::Result<Bytes> {
+    let mut out = Vec::new();
+    let mut zeros = 0u8;
+    for &b in data.iter() {
+      if b == 0 && zeros < u8::MAX {
+        zeros += 1;
+        continue;
+      }
+      if zeros > 0 {
+        out.extend_from_slice(&[0, zeros]);
+        zeros = 0;
+      }
+      if b == 0 {
+        zeros = 1;
+      } else {
+        out.push(b);
+      }
+    }
+    if zeros > 0 {
+      out.extend_from_slice(&[0, zeros]);
+    }
+    Ok(Bytes::from(out))
+  }
+
+  fn decompress(&self, data: &Bytes, uncompressed_size: usize) -> io::Result<Bytes> {
+    let mut out = Vec::with_capacity(uncompressed_size);
+    let mut bytes = data.iter();
+    while let Some(&b) = bytes.next() {
+      if b == 0 {
+        let count = *bytes
+          .next()
+          .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated"))?;
+        out.resize(out.len() + count as usize, 0);
+      } else {
+        out.push(b);
+      }
+    }
+    Ok(Bytes::from(out))
+  }
+}
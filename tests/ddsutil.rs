@@ -0,0 +1,78 @@
+// Runs the `ddsutil` example commands against a publisher in the same
+// process, so that the example keeps working.
+
+use std::{
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  thread,
+  time::Duration,
+};
+
+use rustdds::{policy, DomainParticipant, QosPolicyBuilder, TopicKind};
+
+#[allow(dead_code)]
+#[path = "../examples/ddsutil/commands.rs"]
+mod commands;
+
+#[test]
+fn ddsutil_list_and_hz() {
+  const DOMAIN: u16 = 17;
+
+  let stop = Arc::new(AtomicBool::new(false));
+  let stop_publisher = Arc::clone(&stop);
+  let publisher_thread = thread::spawn(move || {
+    let dp = DomainParticipant::new(DOMAIN).unwrap();
+    let qos = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::BestEffort)
+      .build();
+    let topic = dp
+      .create_topic(
+        "ddsutil_test".to_string(),
+        "Counter".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let writer = dp
+      .create_publisher(&qos)
+      .unwrap()
+      .create_datawriter_no_key_cdr::<u32>(&topic, None)
+      .unwrap();
+    let mut counter = 0;
+    while !stop_publisher.load(Ordering::Relaxed) {
+      writer.write(counter, None).unwrap();
+      counter += 1;
+      thread::sleep(Duration::from_millis(10));
+    }
+  });
+
+  let dp = DomainParticipant::new(DOMAIN).unwrap();
+
+  // list. The topic may be discovered before its DataWriter.
+  let mut topic = commands::find_topic(&dp, "ddsutil_test", Duration::from_secs(10))
+    .expect("Topic was not discovered");
+  for _ in 0..100 {
+    if topic.writers > 0 {
+      break;
+    }
+    thread::sleep(Duration::from_millis(100));
+    topic = commands::find_topic(&dp, "ddsutil_test", Duration::ZERO).unwrap();
+  }
+  assert_eq!(topic.type_name, "Counter");
+  assert_eq!(topic.writers, 1);
+  assert_eq!(topic.readers, 0);
+
+  // hz
+  let mut reader = commands::subscribe(&dp, &topic).unwrap();
+  let meter = commands::measure(&mut reader, Duration::from_secs(2));
+  stop.store(true, Ordering::Relaxed);
+  publisher_thread.join().unwrap();
+
+  let rate = meter.rate().expect("No samples received");
+  // Nominally 100 Hz. Leave room for slow test machines.
+  assert!((10.0..200.0).contains(&rate), "rate = {rate}");
+  // u32 in CDR, no encapsulation header
+  assert_eq!(meter.bytes, 4 * meter.samples);
+}
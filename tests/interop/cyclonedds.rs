@@ -0,0 +1,122 @@
+use std::{
+  io::BufRead,
+  time::{Duration, Instant},
+};
+
+use rustdds::*;
+use serde::{Deserialize, Serialize};
+use testcontainers::{core::WaitFor, runners::SyncRunner, Container, GenericImage, ImageExt};
+
+// Interoperability with CycloneDDS, running in a Docker container. Discovery
+// (SPDP/SEDP) and data go over UDP, as between any two hosts. The container
+// uses the host network, so that it sees our multicast discovery.
+//
+// The image is built from the `cyclonedds` directory, see Cargo.toml.
+
+const IMAGE_NAME: &str = "rustdds-interop-cyclonedds";
+const IMAGE_TAG: &str = "latest";
+const DOMAIN_ID: u16 = 42;
+const SAMPLE_COUNT: i32 = 100;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+// Must match InteropSample in cyclonedds/interop.py
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct InteropSample {
+  id: i32,
+  text: String,
+}
+
+// Must match QOS in cyclonedds/interop.py. With TransientLocal durability,
+// samples written before discovery completes are delivered after it.
+fn qos() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_secs(1),
+    })
+    .durability(policy::Durability::TransientLocal)
+    .history(policy::History::KeepAll)
+    .build()
+}
+
+fn start_cyclonedds(mode: &str, ready_message: &str) -> Container<GenericImage> {
+  GenericImage::new(IMAGE_NAME, IMAGE_TAG)
+    .with_wait_for(WaitFor::message_on_stdout(ready_message))
+    .with_network("host")
+    .with_cmd([
+      mode.to_string(),
+      DOMAIN_ID.to_string(),
+      SAMPLE_COUNT.to_string(),
+    ])
+    .with_startup_timeout(TIMEOUT)
+    .start()
+    .expect("Cannot start CycloneDDS container. Is Docker running, and the image built?")
+}
+
+fn interop_topic(participant: &DomainParticipant) -> Topic {
+  participant
+    .create_topic(
+      "InteropTopic".to_string(),
+      "InteropSample".to_string(),
+      &qos(),
+      TopicKind::NoKey,
+    )
+    .unwrap()
+}
+
+#[test]
+fn cyclonedds_receives_from_rustdds() {
+  let container = start_cyclonedds("sub", "READY");
+
+  let participant = DomainParticipant::new(DOMAIN_ID).unwrap();
+  let topic = interop_topic(&participant);
+  let publisher = participant.create_publisher(&qos()).unwrap();
+  let writer = publisher
+    .create_datawriter_no_key_cdr::<InteropSample>(&topic, None)
+    .unwrap();
+
+  for id in 0..SAMPLE_COUNT {
+    let sample = InteropSample {
+      id,
+      text: format!("rustdds {id}"),
+    };
+    writer.write(sample, None).unwrap();
+  }
+  writer.wait_for_acknowledgments(TIMEOUT).unwrap();
+
+  // The container prints its result and exits, which ends the log stream.
+  let result = container
+    .stdout(true)
+    .lines()
+    .map_while(Result::ok)
+    .find(|line| line.starts_with("RECEIVED"));
+  assert_eq!(result, Some(format!("RECEIVED {SAMPLE_COUNT}")));
+}
+
+#[test]
+fn rustdds_receives_from_cyclonedds() {
+  let _container = start_cyclonedds("pub", "PUBLISHED");
+
+  let participant = DomainParticipant::new(DOMAIN_ID).unwrap();
+  let topic = interop_topic(&participant);
+  let subscriber = participant.create_subscriber(&qos()).unwrap();
+  let mut reader = subscriber
+    .create_datareader_no_key_cdr::<InteropSample>(&topic, None)
+    .unwrap();
+
+  let mut received = Vec::new();
+  let deadline = Instant::now() + TIMEOUT;
+  while received.len() < SAMPLE_COUNT as usize && Instant::now() < deadline {
+    match reader.take_next_sample().unwrap() {
+      Some(sample) => received.push(sample.into_value()),
+      None => std::thread::sleep(Duration::from_millis(10)),
+    }
+  }
+
+  let expected: Vec<InteropSample> = (0..SAMPLE_COUNT)
+    .map(|id| InteropSample {
+      id,
+      text: format!("cyclonedds {id}"),
+    })
+    .collect();
+  assert_eq!(received, expected);
+}
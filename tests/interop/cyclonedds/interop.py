@@ -0,0 +1,75 @@
+# CycloneDDS side of tests/interop/cyclonedds.rs
+#
+#   interop.py sub <domain> <count>   Prints "READY", then "RECEIVED <n>" when
+#                                     <count> samples have arrived, or on timeout.
+#   interop.py pub <domain> <count>   Writes <count> samples, prints "PUBLISHED",
+#                                     and keeps them available to late joiners.
+
+import sys
+import time
+from dataclasses import dataclass
+
+from cyclonedds.core import Policy, Qos
+from cyclonedds.domain import DomainParticipant
+from cyclonedds.idl import IdlStruct
+from cyclonedds.idl.types import int32
+from cyclonedds.pub import DataWriter
+from cyclonedds.sub import DataReader
+from cyclonedds.topic import Topic
+from cyclonedds.util import duration
+
+TOPIC_NAME = "InteropTopic"
+TIMEOUT_SECS = 60
+
+
+@dataclass
+class InteropSample(IdlStruct, typename="InteropSample"):
+    id: int32
+    text: str
+
+
+# Must match the QoS in cyclonedds.rs. RustDDS serializes as plain CDR.
+QOS = Qos(
+    Policy.Reliability.Reliable(duration(seconds=1)),
+    Policy.Durability.TransientLocal,
+    Policy.History.KeepAll,
+    Policy.DataRepresentation(use_cdrv0_representation=True),
+)
+
+
+def subscribe(participant, count):
+    topic = Topic(participant, TOPIC_NAME, InteropSample, qos=QOS)
+    reader = DataReader(participant, topic, qos=QOS)
+    print("READY")
+    received = set()
+    deadline = time.monotonic() + TIMEOUT_SECS
+    while len(received) < count and time.monotonic() < deadline:
+        for sample in reader.take(N=count):
+            received.add(sample.id)
+        time.sleep(0.01)
+    print(f"RECEIVED {len(received)}")
+
+
+def publish(participant, count):
+    topic = Topic(participant, TOPIC_NAME, InteropSample, qos=QOS)
+    writer = DataWriter(participant, topic, qos=QOS)
+    for i in range(count):
+        writer.write(InteropSample(id=i, text=f"cyclonedds {i}"))
+    print("PUBLISHED")
+    # The test stops the container when it is done
+    time.sleep(TIMEOUT_SECS)
+
+
+def main():
+    mode, domain, count = sys.argv[1], int(sys.argv[2]), int(sys.argv[3])
+    participant = DomainParticipant(domain)
+    if mode == "sub":
+        subscribe(participant, count)
+    elif mode == "pub":
+        publish(participant, count)
+    else:
+        sys.exit(f"Unknown mode {mode}")
+
+
+if __name__ == "__main__":
+    main()